@@ -2,27 +2,32 @@ use std::{
     any::{Any, TypeId},
     cell::RefCell,
     collections::HashMap,
-    io,
     marker::PhantomData,
     rc::Rc,
     sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
 };
 
 use crate::{
     context::{AppContext, Context, Focusable, Global, GpuiBorrow, Reservation, VisualContext},
     element::IntoElement,
     entity::{AnyEntity, AnyView, Entity, EntityId, WindowId},
+    error::{BackendError, RenderError},
     geometry::{Bounds, Pixels, Point, Size},
-    runtime::{event_loop::run_event_loop, lifecycle::enter_terminal},
+    runtime::{
+        event_loop::{DEFAULT_POLL_INTERVAL, run_event_loop},
+        lifecycle::enter_terminal,
+        watchdog::FrameTimings,
+    },
     view::Render,
-    window::{AnyWindowHandle, Window, WindowHandle, WindowOptions},
+    window::{AnyWindowHandle, DEFAULT_CURSOR_BLINK, Window, WindowHandle, WindowOptions},
 };
 
 static NEXT_ENTITY_ID: AtomicU64 = AtomicU64::new(1);
 static NEXT_WINDOW_ID: AtomicU64 = AtomicU64::new(1);
 
 trait WindowRenderer {
-    fn render(&self, app: &mut App, window: &mut Window) -> io::Result<()>;
+    fn render(&self, app: &mut App, window: &mut Window) -> Result<(), RenderError>;
 }
 
 struct ViewRenderer<V: 'static + Render> {
@@ -30,10 +35,15 @@ struct ViewRenderer<V: 'static + Render> {
 }
 
 impl<V: 'static + Render> WindowRenderer for ViewRenderer<V> {
-    fn render(&self, app: &mut App, window: &mut Window) -> io::Result<()> {
+    fn render(&self, app: &mut App, window: &mut Window) -> Result<(), RenderError> {
         let result = app.update_entity(&self.root, |view, cx| {
+            let started = Instant::now();
             let element = view.render(window, cx).into_any_element();
-            window.draw(&element)
+            let timings = FrameTimings {
+                view: started.elapsed(),
+                ..FrameTimings::default()
+            };
+            window.draw(&element, timings)
         });
         result?;
         Ok(())
@@ -43,7 +53,7 @@ impl<V: 'static + Render> WindowRenderer for ViewRenderer<V> {
 struct NoopRenderer;
 
 impl WindowRenderer for NoopRenderer {
-    fn render(&self, _app: &mut App, _window: &mut Window) -> io::Result<()> {
+    fn render(&self, _app: &mut App, _window: &mut Window) -> Result<(), RenderError> {
         Ok(())
     }
 }
@@ -54,7 +64,7 @@ struct WindowState {
     renderer: Box<dyn WindowRenderer>,
 }
 
-pub type Result<T> = io::Result<T>;
+pub type Result<T, E = BackendError> = std::result::Result<T, E>;
 pub type SharedString = String;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -78,22 +88,98 @@ pub enum KeyInput {
     Submit,
     Esc,
     Interrupt,
+    /// Ctrl+G: label every focusable element to jump to it by typing.
+    QuickJump,
+    /// Ctrl+V: paste what the terminal cannot, such as an image. Cmd+V,
+    /// Cmd+C and Cmd+X paste, copy and cut where the terminal reports Cmd.
+    Paste,
+    /// Ctrl+D: another cursor at the next occurrence of the word under the
+    /// cursor.
+    AddCursorAtNext,
+    /// Ctrl+Shift+C or Ctrl+Insert: copy what the terminal cannot, such as a
+    /// block selection.
+    Copy,
+    /// Ctrl+X: cut the block selection, or the line without one.
+    Cut,
+    /// Ctrl+Z: undo the last edit.
+    Undo,
+    /// Ctrl+Shift+Z or Ctrl+Y: redo an undone edit. Terminals without the
+    /// kitty keyboard protocol send Ctrl+Shift+Z as Ctrl+Z.
+    Redo,
+    /// Alt+1 to Alt+9: show the panel at that place, as in an activity bar.
+    Panel(u8),
+    /// Ctrl+K: delete to the end of the line.
+    KillToLineEnd,
+    /// Ctrl+U: delete to the start of the line.
+    KillToLineStart,
+    /// Ctrl+Shift+K: delete the whole line.
+    DeleteLine,
+    /// Alt+Shift+Down: copy the line below itself.
+    DuplicateLine,
+    /// Alt+Up: swap the line with the one above.
+    MoveLineUp,
+    /// Alt+Down: swap the line with the one below.
+    MoveLineDown,
+    /// Alt+J: join the next line onto this one.
+    JoinLines,
     Char(char),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum InputEvent {
     Key(KeyInput),
     ScrollLines(i16),
-    MouseDown { x: u16, y: u16 },
+    /// `alt` is whether Alt (Option) was held.
+    MouseDown {
+        x: u16,
+        y: u16,
+        button: MouseButton,
+        alt: bool,
+    },
+    /// The pointer moved with the left button held.
+    MouseDrag {
+        x: u16,
+        y: u16,
+        alt: bool,
+    },
+    /// The left button was let go.
+    MouseUp {
+        x: u16,
+        y: u16,
+    },
+    /// The pointer moved with no button held, in terminals that report it.
+    MouseMove {
+        x: u16,
+        y: u16,
+    },
+    /// Text pasted into the terminal, in one piece.
+    Paste(String),
     Tick,
 }
 
-#[derive(Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
 pub struct App {
     windows: HashMap<WindowId, WindowState>,
     active_window: Option<WindowId>,
     globals: HashMap<TypeId, Box<dyn Any>>,
+    cursor_blink: Option<Duration>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self {
+            windows: HashMap::new(),
+            active_window: None,
+            globals: HashMap::new(),
+            cursor_blink: Some(DEFAULT_CURSOR_BLINK),
+        }
+    }
 }
 
 impl App {
@@ -104,6 +190,7 @@ impl App {
     ) -> Result<WindowHandle<V>> {
         let id = WindowId(NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed));
         let mut window = Window::new(id, options);
+        window.set_cursor_blink(self.cursor_blink);
         let root = build_root_view(&mut window, self);
 
         self.windows.insert(
@@ -145,6 +232,13 @@ impl App {
         Ok(())
     }
 
+    /// What the active window drew under cell `(x, y)`: the id of the
+    /// innermost div given one with [`Div::id`](crate::Div::id).
+    pub fn region_at(&self, x: u16, y: u16) -> Option<u64> {
+        let active = self.active_window?;
+        self.windows.get(&active)?.window.region_at(x, y)
+    }
+
     pub(crate) fn note_input_activity(&mut self) {
         if let Some(active) = self.active_window
             && let Some(state) = self.windows.get_mut(&active)
@@ -168,7 +262,7 @@ impl App {
         let mut state = self
             .windows
             .remove(&window_id)
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "window not found"))?;
+            .ok_or(BackendError::WindowNotFound(window_id.0))?;
         state.renderer.render(self, &mut state.window)?;
         self.windows.insert(window_id, state);
         Ok(())
@@ -258,14 +352,14 @@ impl AppContext for App {
         read(&handle.inner.borrow(), self)
     }
 
-    fn update_window<T, F>(&mut self, window: AnyWindowHandle, f: F) -> io::Result<T>
+    fn update_window<T, F>(&mut self, window: AnyWindowHandle, f: F) -> Result<T>
     where
         F: FnOnce(AnyView, &mut Window, &mut App) -> T,
     {
         let mut state = self
             .windows
             .remove(&window.id)
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "window not found"))?;
+            .ok_or(BackendError::WindowNotFound(window.id.0))?;
 
         let view = AnyView {
             entity: state.root.clone(),
@@ -279,15 +373,15 @@ impl AppContext for App {
         &self,
         window: &WindowHandle<T>,
         read: impl FnOnce(Entity<T>, &App) -> R,
-    ) -> io::Result<R> {
+    ) -> Result<R> {
         let state = self
             .windows
             .get(&window.id)
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "window not found"))?;
+            .ok_or(BackendError::WindowNotFound(window.id.0))?;
         let entity = state
             .root
             .downcast::<T>()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "root type mismatch"))?;
+            .ok_or(BackendError::RootTypeMismatch(window.id.0))?;
         Ok(read(entity, self))
     }
 
@@ -381,6 +475,8 @@ impl VisualContext for App {
 
 pub struct Application {
     headless: bool,
+    poll_interval: Duration,
+    cursor_blink: Option<Duration>,
 }
 
 impl Default for Application {
@@ -391,14 +487,36 @@ impl Default for Application {
 
 impl Application {
     pub fn new() -> Self {
-        Self { headless: false }
+        Self {
+            headless: false,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            cursor_blink: Some(DEFAULT_CURSOR_BLINK),
+        }
     }
 
     pub fn headless() -> Self {
-        Self { headless: true }
+        Self {
+            headless: true,
+            ..Self::new()
+        }
+    }
+
+    /// How long the event loop waits for input before sending a
+    /// [`InputEvent::Tick`], and so how often an idle app wakes up. 250ms
+    /// unless set.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// How often the text cursor blinks, at most; it changes on the next
+    /// frame after the interval. `None` keeps it steady. 570ms unless set.
+    pub fn cursor_blink(mut self, interval: Option<Duration>) -> Self {
+        self.cursor_blink = interval;
+        self
     }
 
-    pub fn run<F>(self, on_finish_launching: F)
+    pub fn run<F>(self, on_finish_launching: F) -> Result<()>
     where
         F: 'static + FnOnce(&mut App),
     {
@@ -407,10 +525,12 @@ impl Application {
                 event,
                 InputEvent::Key(KeyInput::Char('q')) | InputEvent::Key(KeyInput::Esc)
             )
-        });
+        })
     }
 
-    pub fn run_with_input_handler<F, H>(self, on_finish_launching: F, mut on_input: H)
+    /// Runs until `on_input` asks to quit. The terminal is restored before
+    /// this returns, so errors can be reported on the normal screen.
+    pub fn run_with_input_handler<F, H>(self, on_finish_launching: F, mut on_input: H) -> Result<()>
     where
         F: 'static + FnOnce(&mut App),
         H: 'static + FnMut(&mut App, InputEvent) -> bool,
@@ -418,28 +538,20 @@ impl Application {
         if self.headless {
             let mut app = App::default();
             on_finish_launching(&mut app);
-            return;
+            return Ok(());
         }
 
-        let terminal_guard = match enter_terminal() {
-            Ok(guard) => guard,
-            Err(err) => {
-                eprintln!("cpui terminal init error: {err}");
-                return;
-            }
+        let terminal_guard = enter_terminal().map_err(BackendError::TerminalSetup)?;
+        let mut app = App {
+            cursor_blink: self.cursor_blink,
+            ..App::default()
         };
-        let mut app = App::default();
         on_finish_launching(&mut app);
 
-        if let Err(err) = app.render_all_windows() {
-            eprintln!("cpui render error: {err}");
-            return;
-        }
-
-        if let Err(err) = run_event_loop(&mut app, &mut on_input) {
-            eprintln!("cpui runtime loop error: {err}");
-        }
-
+        let result = app
+            .render_all_windows()
+            .and_then(|()| run_event_loop(&mut app, self.poll_interval, &mut on_input));
         drop(terminal_guard);
+        result
     }
 }
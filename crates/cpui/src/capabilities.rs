@@ -0,0 +1,177 @@
+use std::env;
+use std::sync::OnceLock;
+
+use crate::color::Rgba;
+
+/// How many colors the terminal can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+/// Protocol for drawing pixels into the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageProtocol {
+    Kitty,
+    Iterm2,
+}
+
+/// What the terminal can do. Rendering code asks here instead of reading the
+/// environment itself, so a wrong guess is fixed in one place and users can
+/// override it for their `TERM`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `TERM`, the key overrides are looked up by.
+    pub term: String,
+    pub color: ColorDepth,
+    /// Frames are wrapped in DEC mode 2026 so the terminal shows them whole.
+    /// Without it the cursor is hidden while a frame is written.
+    pub synchronized_updates: bool,
+    /// The kitty keyboard protocol is requested on startup.
+    pub kitty_keyboard: bool,
+    /// Ctrl+Enter reaches the app, as Ctrl+J on legacy terminals. VS Code
+    /// keeps it for itself.
+    pub ctrl_enter: bool,
+    pub images: Option<ImageProtocol>,
+    /// OSC 8 hyperlinks are clickable.
+    pub hyperlinks: bool,
+    /// Running in VS Code's integrated terminal, which opens `vscode://file`
+    /// links in the editor.
+    pub vscode: bool,
+}
+
+impl Capabilities {
+    /// Guesses from the environment. Unknown terminals get the full feature
+    /// set: the escape sequences involved are ignored where unsupported, while
+    /// a downgrade would be visible everywhere.
+    pub fn detect() -> Self {
+        Self::from_env(|name| env::var(name).ok())
+    }
+
+    /// [`detect`](Self::detect), corrected by what the terminal answers when
+    /// asked: whether it knows synchronized updates. It reads stdin, so call
+    /// it before the UI starts.
+    pub fn detect_and_query() -> Self {
+        let mut detected = Self::detect();
+        if let Some(supported) = crate::runtime::probe::query_synchronized_updates() {
+            detected.synchronized_updates = supported;
+        }
+        detected
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        let vscode = program.eq_ignore_ascii_case("vscode");
+        let apple = program == "Apple_Terminal";
+        let console = term == "linux";
+        let multiplexed = var("TMUX").is_some() || term.starts_with("screen");
+        let kitty_graphics = term.contains("kitty")
+            || var("KITTY_WINDOW_ID").is_some()
+            || term.contains("ghostty")
+            || program == "WezTerm";
+
+        let color = if console {
+            ColorDepth::Ansi16
+        } else if apple {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::TrueColor
+        };
+        let images = if multiplexed {
+            None
+        } else if kitty_graphics {
+            Some(ImageProtocol::Kitty)
+        } else if program == "iTerm.app" {
+            Some(ImageProtocol::Iterm2)
+        } else {
+            None
+        };
+        Self {
+            color,
+            synchronized_updates: !console,
+            // VS Code's terminal only speaks the legacy keyboard encoding.
+            kitty_keyboard: !console && !vscode,
+            ctrl_enter: !vscode,
+            images,
+            hyperlinks: !console && !apple,
+            vscode,
+            term,
+        }
+    }
+}
+
+static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+
+/// Fixes the capabilities for this process, usually [`Capabilities::detect`]
+/// with user overrides applied. Has no effect once they have been read;
+/// returns whether it took.
+pub fn init_capabilities(capabilities: Capabilities) -> bool {
+    CAPABILITIES.set(capabilities).is_ok()
+}
+
+/// The capabilities of the attached terminal, detected on first use unless
+/// [`init_capabilities`] ran before.
+pub fn capabilities() -> &'static Capabilities {
+    CAPABILITIES.get_or_init(Capabilities::detect)
+}
+
+/// The closest color `depth` can show, as an xterm palette index, or `None`
+/// when the color can be sent as is.
+pub(crate) fn palette_index(color: Rgba, depth: ColorDepth) -> Option<u8> {
+    match depth {
+        ColorDepth::TrueColor => None,
+        ColorDepth::Ansi256 => Some(ansi256(color)),
+        ColorDepth::Ansi16 => Some(ansi16(color)),
+    }
+}
+
+fn ansi256(Rgba { r, g, b }: Rgba) -> u8 {
+    // Near-grays fit the 24-step gray ramp better than the 6x6x6 cube.
+    if r.abs_diff(g) < 10 && g.abs_diff(b) < 10 && r.abs_diff(b) < 10 {
+        let gray = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+        return match gray {
+            0..8 => 16,
+            248.. => 231,
+            _ => 232 + ((gray - 8) / 10).min(23) as u8,
+        };
+    }
+    let level = |c: u8| (u16::from(c) * 5 + 127) / 255;
+    (16 + 36 * level(r) + 6 * level(g) + level(b)) as u8
+}
+
+fn ansi16(Rgba { r, g, b }: Rgba) -> u8 {
+    let bright = u16::from(r.max(g).max(b)) > 170;
+    let bit = |c: u8| u8::from(c > 85);
+    let index = bit(r) | (bit(g) << 1) | (bit(b) << 2);
+    if bright { index + 8 } else { index }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::rgb;
+
+    #[test]
+    fn detection_and_palette_fallbacks() {
+        let vscode = Capabilities::from_env(|name| match name {
+            "TERM" => Some("xterm-256color".to_string()),
+            "TERM_PROGRAM" => Some("vscode".to_string()),
+            _ => None,
+        });
+        assert!(vscode.vscode && !vscode.ctrl_enter && !vscode.kitty_keyboard);
+        assert_eq!(vscode.color, ColorDepth::TrueColor);
+
+        let console = Capabilities::from_env(|name| (name == "TERM").then(|| "linux".to_string()));
+        assert_eq!(console.color, ColorDepth::Ansi16);
+        assert!(!console.synchronized_updates);
+
+        assert_eq!(palette_index(rgb(0xff0000), ColorDepth::TrueColor), None);
+        assert_eq!(palette_index(rgb(0xff0000), ColorDepth::Ansi256), Some(196));
+        assert_eq!(palette_index(rgb(0x808080), ColorDepth::Ansi256), Some(244));
+        assert_eq!(palette_index(rgb(0xff0000), ColorDepth::Ansi16), Some(9));
+        assert_eq!(palette_index(rgb(0x000000), ColorDepth::Ansi16), Some(0));
+    }
+}
@@ -5,6 +5,17 @@ pub struct Rgba {
     pub b: u8,
 }
 
+impl Rgba {
+    /// Halfway to black, as what an overlay covers is drawn.
+    pub fn dimmed(self) -> Rgba {
+        Rgba {
+            r: self.r / 2,
+            g: self.g / 2,
+            b: self.b / 2,
+        }
+    }
+}
+
 pub fn rgb(hex: u32) -> Rgba {
     Rgba {
         r: ((hex >> 16) & 0xff) as u8,
@@ -123,7 +123,7 @@ pub trait AppContext {
         &mut self,
         window: crate::window::AnyWindowHandle,
         f: F,
-    ) -> std::io::Result<T>
+    ) -> crate::Result<T>
     where
         F: FnOnce(crate::entity::AnyView, &mut Window, &mut App) -> T;
 
@@ -131,7 +131,7 @@ pub trait AppContext {
         &self,
         window: &crate::window::WindowHandle<T>,
         read: impl FnOnce(Entity<T>, &App) -> R,
-    ) -> std::io::Result<R>;
+    ) -> crate::Result<R>;
 
     fn read_global<G: Global, R>(&self, callback: impl FnOnce(&G, &App) -> R) -> Self::Result<R>;
 }
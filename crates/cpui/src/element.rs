@@ -1,13 +1,16 @@
 use std::collections::HashMap;
-use std::io;
+use std::sync::Arc;
+use std::time::Instant;
 
 use taffy::prelude::*;
 use taffy::{Overflow, Point};
 
 use crate::{
     color::Rgba,
-    frame::CellBuffer,
+    error::LayoutError,
+    frame::{Cell, CellBuffer, CellStyle},
     geometry::Pixels,
+    runtime::watchdog::FrameTimings,
     text::{StyledText, styled_text},
 };
 
@@ -18,12 +21,32 @@ enum LayoutDisplay {
     Grid,
 }
 
+/// The line a bordered div is framed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BorderLine {
+    Solid,
+    Dashed,
+}
+
+impl BorderLine {
+    /// Horizontal, vertical, then the corners clockwise from the top left.
+    fn glyphs(self) -> [char; 6] {
+        match self {
+            Self::Solid => ['─', '│', '┌', '┐', '┘', '└'],
+            Self::Dashed => ['╌', '╎', '┌', '┐', '┘', '└'],
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum AnyElement {
     Div(Div),
     ScrollView(ScrollView),
     Text(String),
     InlineText(StyledText),
+    /// A subtree kept from an earlier frame, shared instead of copied.
+    Shared(Arc<AnyElement>),
+    Overlay(Overlay),
     Empty,
 }
 
@@ -49,6 +72,12 @@ impl IntoElement for ScrollView {
     }
 }
 
+impl IntoElement for Overlay {
+    fn into_any_element(self) -> AnyElement {
+        AnyElement::Overlay(self)
+    }
+}
+
 impl IntoElement for String {
     fn into_any_element(self) -> AnyElement {
         AnyElement::Text(self)
@@ -67,6 +96,12 @@ impl IntoElement for StyledText {
     }
 }
 
+impl IntoElement for Arc<AnyElement> {
+    fn into_any_element(self) -> AnyElement {
+        AnyElement::Shared(self)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Style {
     pub text_color: Option<Rgba>,
@@ -79,6 +114,33 @@ pub struct Style {
     width: Option<Pixels>,
     height: Option<Pixels>,
     grid_columns: Option<u16>,
+    /// A one-cell frame drawn inside the div's bounds; children are laid
+    /// out within it.
+    border: Option<BorderLine>,
+    border_color: Option<Rgba>,
+    /// Cells kept clear inside the border, and outside it.
+    padding: taffy::Rect<f32>,
+    margin: taffy::Rect<f32>,
+}
+
+const NO_EDGES: taffy::Rect<f32> = taffy::Rect {
+    left: 0.0,
+    right: 0.0,
+    top: 0.0,
+    bottom: 0.0,
+};
+
+/// Sets the left and right of `edges` to `x` and the top and bottom to `y`,
+/// where given.
+fn set_edges(edges: &mut taffy::Rect<f32>, x: Option<u16>, y: Option<u16>) {
+    if let Some(x) = x {
+        edges.left = f32::from(x);
+        edges.right = f32::from(x);
+    }
+    if let Some(y) = y {
+        edges.top = f32::from(y);
+        edges.bottom = f32::from(y);
+    }
 }
 
 impl Default for Style {
@@ -94,6 +156,10 @@ impl Default for Style {
             width: None,
             height: None,
             grid_columns: None,
+            border: None,
+            border_color: None,
+            padding: NO_EDGES,
+            margin: NO_EDGES,
         }
     }
 }
@@ -102,6 +168,9 @@ impl Default for Style {
 pub struct Div {
     style: Style,
     children: Vec<AnyElement>,
+    /// Where the div lands is recorded under this, for
+    /// [`CellBuffer::region_at`].
+    id: Option<u64>,
 }
 
 #[derive(Clone, Debug)]
@@ -111,10 +180,24 @@ pub struct ScrollView {
     child: Box<AnyElement>,
 }
 
+/// A child drawn centred over the whole frame once the rest is painted,
+/// dimming what it covers. It takes no room where it sits in the tree; an
+/// overlay inside it goes over it in turn.
+#[derive(Clone, Debug)]
+pub struct Overlay {
+    child: Arc<AnyElement>,
+}
+
 pub fn div() -> Div {
     Div::default()
 }
 
+pub fn overlay(child: impl IntoElement) -> Overlay {
+    Overlay {
+        child: Arc::new(child.into_any_element()),
+    }
+}
+
 pub fn scroll_view(child: impl IntoElement) -> ScrollView {
     ScrollView {
         viewport_lines: None,
@@ -164,15 +247,82 @@ impl Div {
         self
     }
 
+    /// `cells` of padding on every side. Padding and margins count in
+    /// cells, so `p_1` is one.
+    pub fn p(mut self, cells: u16) -> Self {
+        set_edges(&mut self.style.padding, Some(cells), Some(cells));
+        self
+    }
+
+    pub fn p_1(self) -> Self {
+        self.p(1)
+    }
+
+    pub fn p_2(self) -> Self {
+        self.p(2)
+    }
+
+    pub fn px(mut self, cells: u16) -> Self {
+        set_edges(&mut self.style.padding, Some(cells), None);
+        self
+    }
+
+    pub fn px_1(self) -> Self {
+        self.px(1)
+    }
+
+    pub fn px_2(self) -> Self {
+        self.px(2)
+    }
+
+    pub fn py(mut self, cells: u16) -> Self {
+        set_edges(&mut self.style.padding, None, Some(cells));
+        self
+    }
+
+    pub fn py_1(self) -> Self {
+        self.py(1)
+    }
+
+    /// `cells` of margin on every side.
+    pub fn m(mut self, cells: u16) -> Self {
+        set_edges(&mut self.style.margin, Some(cells), Some(cells));
+        self
+    }
+
+    pub fn m_1(self) -> Self {
+        self.m(1)
+    }
+
+    pub fn mx(mut self, cells: u16) -> Self {
+        set_edges(&mut self.style.margin, Some(cells), None);
+        self
+    }
+
+    pub fn mx_1(self) -> Self {
+        self.mx(1)
+    }
+
+    pub fn my(mut self, cells: u16) -> Self {
+        set_edges(&mut self.style.margin, None, Some(cells));
+        self
+    }
+
+    pub fn my_1(self) -> Self {
+        self.my(1)
+    }
+
     pub fn shadow_lg(self) -> Self {
         self
     }
 
-    pub fn border_1(self) -> Self {
+    pub fn border_1(mut self) -> Self {
+        self.style.border.get_or_insert(BorderLine::Solid);
         self
     }
 
-    pub fn border_dashed(self) -> Self {
+    pub fn border_dashed(mut self) -> Self {
+        self.style.border = Some(BorderLine::Dashed);
         self
     }
 
@@ -196,6 +346,11 @@ impl Div {
         self
     }
 
+    pub fn w(mut self, width: Pixels) -> Self {
+        self.style.width = Some(width);
+        self
+    }
+
     pub fn h(mut self, height: Pixels) -> Self {
         self.style.height = Some(height);
         self
@@ -211,7 +366,9 @@ impl Div {
         self
     }
 
-    pub fn border_color(self, _color: Rgba) -> Self {
+    /// The color of the frame; the text color when not set.
+    pub fn border_color(mut self, color: Rgba) -> Self {
+        self.style.border_color = Some(color);
         self
     }
 
@@ -219,6 +376,13 @@ impl Div {
         self.children.push(child.into_any_element());
         self
     }
+
+    /// Records the cells the div covers under `id`, so a click can be told
+    /// to have landed on it.
+    pub fn id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
 }
 
 impl ScrollView {
@@ -244,11 +408,22 @@ struct BgLeaf {
     bg: Rgba,
 }
 
+#[derive(Clone, Copy)]
+struct BorderLeaf {
+    line: BorderLine,
+    color: Option<Rgba>,
+}
+
 struct BuildState {
     leaves: Vec<TextLeaf>,
     backgrounds: Vec<BgLeaf>,
+    borders: HashMap<NodeId, BorderLeaf>,
+    /// Divs with an id, innermost first.
+    regions: Vec<(NodeId, u64)>,
     parents: HashMap<NodeId, NodeId>,
     scroll_nodes: HashMap<NodeId, ScrollNode>,
+    /// Overlays met in the tree, painted after it in this order.
+    overlays: Vec<Arc<AnyElement>>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -256,7 +431,7 @@ struct ScrollNode {
     offset_lines: f32,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) struct Rect {
     pub(crate) left: i32,
     pub(crate) top: i32,
@@ -318,6 +493,12 @@ fn taffy_style_from(div: &Div) -> taffy::style::Style {
         style.grid_template_columns = (0..columns).map(|_| fr(1.0)).collect();
     }
 
+    if div.style.border.is_some() {
+        style.border = taffy::Rect::length(1.0);
+    }
+    style.padding = div.style.padding.map(LengthPercentage::length);
+    style.margin = div.style.margin.map(LengthPercentageAuto::length);
+
     style
 }
 
@@ -327,11 +508,11 @@ fn build_layout_tree(
     wrap_width: usize,
     inherited_color: Option<Rgba>,
     state: &mut BuildState,
-) -> io::Result<NodeId> {
+) -> Result<NodeId, LayoutError> {
     match element {
         AnyElement::Empty => taffy
             .new_leaf(taffy::style::Style::default())
-            .map_err(io::Error::other),
+            .map_err(LayoutError::Build),
         AnyElement::Text(text) => {
             let inline = styled_text(text.clone());
             let style = taffy::style::Style {
@@ -343,7 +524,7 @@ fn build_layout_tree(
                 },
                 ..Default::default()
             };
-            let node = taffy.new_leaf(style).map_err(io::Error::other)?;
+            let node = taffy.new_leaf(style).map_err(LayoutError::Build)?;
             state.leaves.push(TextLeaf {
                 node,
                 inline,
@@ -361,7 +542,7 @@ fn build_layout_tree(
                 },
                 ..Default::default()
             };
-            let node = taffy.new_leaf(style).map_err(io::Error::other)?;
+            let node = taffy.new_leaf(style).map_err(LayoutError::Build)?;
             state.leaves.push(TextLeaf {
                 node,
                 inline: inline.clone(),
@@ -369,24 +550,51 @@ fn build_layout_tree(
             });
             Ok(node)
         }
+        AnyElement::Shared(shared) => {
+            build_layout_tree(taffy, shared, wrap_width, inherited_color, state)
+        }
+        AnyElement::Overlay(overlay) => {
+            state.overlays.push(overlay.child.clone());
+            let style = taffy::style::Style {
+                display: Display::None,
+                ..Default::default()
+            };
+            taffy.new_leaf(style).map_err(LayoutError::Build)
+        }
         AnyElement::Div(div) => {
             let child_color = div.style.text_color.or(inherited_color);
+            // Text in a div of fixed width wraps to it, not to the terminal,
+            // less what its padding and border take.
+            let mut child_wrap = div
+                .style
+                .width
+                .map_or(wrap_width, |width| (width.0 as usize).min(wrap_width));
+            let padding = div.style.padding.left + div.style.padding.right;
+            let border = if div.style.border.is_some() { 2 } else { 0 };
+            child_wrap = child_wrap.saturating_sub(padding as usize + border).max(1);
             let mut child_nodes = Vec::with_capacity(div.children.len());
             for child in &div.children {
                 child_nodes.push(build_layout_tree(
                     taffy,
                     child,
-                    wrap_width,
+                    child_wrap,
                     child_color,
                     state,
                 )?);
             }
             let node = taffy
                 .new_with_children(taffy_style_from(div), &child_nodes)
-                .map_err(io::Error::other)?;
+                .map_err(LayoutError::Build)?;
             if let Some(bg) = div.style.bg {
                 state.backgrounds.push(BgLeaf { node, bg });
             }
+            if let Some(line) = div.style.border {
+                let color = div.style.border_color.or(child_color);
+                state.borders.insert(node, BorderLeaf { line, color });
+            }
+            if let Some(id) = div.id {
+                state.regions.push((node, id));
+            }
             for child in child_nodes {
                 state.parents.insert(child, node);
             }
@@ -420,7 +628,7 @@ fn build_layout_tree(
 
             let node = taffy
                 .new_with_children(style, &[child])
-                .map_err(io::Error::other)?;
+                .map_err(LayoutError::Build)?;
             state.parents.insert(child, node);
             state.scroll_nodes.insert(
                 node,
@@ -437,13 +645,51 @@ pub(crate) fn render_element(
     element: &AnyElement,
     terminal_width: u16,
     terminal_height: u16,
-) -> io::Result<CellBuffer> {
+    timings: &mut FrameTimings,
+) -> Result<CellBuffer, LayoutError> {
+    // A terminal with no cells, as some report while being resized, has
+    // nothing to lay out.
+    if terminal_width == 0 || terminal_height == 0 {
+        return Ok(CellBuffer::new(terminal_width, terminal_height));
+    }
+    let mut buffer = CellBuffer::new(terminal_width, terminal_height);
+    let mut overlays = paint_layer(element, &mut buffer, false, timings)?;
+    // Each overlay goes over everything painted before it, overlays found
+    // inside it after it.
+    let mut next = 0;
+    while let Some(overlay) = overlays.get(next).cloned() {
+        buffer.dim();
+        let layer = div()
+            .justify_center()
+            .items_center()
+            .child(overlay)
+            .into_any_element();
+        overlays.extend(paint_layer(&layer, &mut buffer, true, timings)?);
+        next += 1;
+    }
+    Ok(buffer)
+}
+
+/// Lays out `element` over the whole of `buffer` and paints it there,
+/// returning the overlays it holds. `clear` blanks the cells of the root's
+/// first child before painting, so an overlay hides what it covers.
+fn paint_layer(
+    element: &AnyElement,
+    buffer: &mut CellBuffer,
+    clear: bool,
+    timings: &mut FrameTimings,
+) -> Result<Vec<Arc<AnyElement>>, LayoutError> {
+    let (terminal_width, terminal_height) = (buffer.width(), buffer.height());
+    let started = Instant::now();
     let mut taffy = TaffyTree::new();
     let mut state = BuildState {
         leaves: Vec::new(),
         backgrounds: Vec::new(),
+        borders: HashMap::new(),
+        regions: Vec::new(),
         parents: HashMap::new(),
         scroll_nodes: HashMap::new(),
+        overlays: Vec::new(),
     };
 
     let root = build_layout_tree(
@@ -453,14 +699,16 @@ pub(crate) fn render_element(
         None,
         &mut state,
     )?;
-    let mut root_style = taffy.style(root).map_err(io::Error::other)?.clone();
+    timings.wrap += started.elapsed();
+    let started = Instant::now();
+    let mut root_style = taffy.style(root).map_err(LayoutError::Build)?.clone();
     root_style.size = Size {
         width: Dimension::length(terminal_width as f32),
         height: Dimension::length(terminal_height as f32),
     };
     taffy
         .set_style(root, root_style)
-        .map_err(io::Error::other)?;
+        .map_err(LayoutError::Build)?;
 
     taffy
         .compute_layout(
@@ -470,7 +718,9 @@ pub(crate) fn render_element(
                 height: AvailableSpace::Definite(terminal_height as f32),
             },
         )
-        .map_err(io::Error::other)?;
+        .map_err(LayoutError::Compute)?;
+    timings.layout += started.elapsed();
+    let started = Instant::now();
 
     let mut absolute_cache: HashMap<NodeId, (f32, f32)> = HashMap::new();
     let screen = Rect {
@@ -480,76 +730,46 @@ pub(crate) fn render_element(
         bottom: terminal_height as i32,
     };
 
-    let mut buffer = CellBuffer::new(terminal_width, terminal_height);
-
-    for bg in state.backgrounds {
-        let (abs_x, abs_y) =
-            absolute_location(bg.node, &taffy, &state.parents, &mut absolute_cache)?;
-        let mut y = abs_y;
-        let mut clip = Some(screen);
-        let mut current = bg.node;
-
-        while let Some(parent) = state.parents.get(&current).copied() {
-            if let Some(scroll) = state.scroll_nodes.get(&parent).copied() {
-                y -= scroll.offset_lines;
-
-                let (sx, sy) =
-                    absolute_location(parent, &taffy, &state.parents, &mut absolute_cache)?;
-                let layout = taffy.layout(parent).map_err(io::Error::other)?;
-                let bounds = Rect {
-                    left: sx.floor() as i32,
-                    top: sy.floor() as i32,
-                    right: (sx + layout.size.width).ceil() as i32,
-                    bottom: (sy + layout.size.height).ceil() as i32,
-                };
-                clip = clip.and_then(|existing| existing.intersect(bounds));
-            }
-            current = parent;
+    if clear && let Some(&child) = taffy.children(root).map_err(LayoutError::Lookup)?.first() {
+        let (x, y) = absolute_location(child, &taffy, &state.parents, &mut absolute_cache)?;
+        let bounds = node_bounds(child, &taffy, x, y)?;
+        if let Some(bounds) = bounds.intersect(screen) {
+            fill_rect_blank(buffer, bounds);
         }
+    }
 
+    for bg in &state.backgrounds {
+        let (x, y, clip) = placement(bg.node, &taffy, &state, &mut absolute_cache, screen)?;
         if let Some(clip) = clip {
-            let layout = taffy.layout(bg.node).map_err(io::Error::other)?;
-            let bounds = Rect {
-                left: abs_x.floor() as i32,
-                top: y.floor() as i32,
-                right: (abs_x + layout.size.width).ceil() as i32,
-                bottom: (y + layout.size.height).ceil() as i32,
-            };
+            let bounds = node_bounds(bg.node, &taffy, x, y)?;
             if let Some(bounds) = bounds.intersect(clip) {
-                fill_rect_bg(&mut buffer, bounds, bg.bg);
+                fill_rect_bg(buffer, bounds, bg.bg);
             }
         }
     }
 
-    for leaf in state.leaves {
-        let (abs_x, abs_y) =
-            absolute_location(leaf.node, &taffy, &state.parents, &mut absolute_cache)?;
-        let mut y = abs_y;
-        let mut clip = Some(screen);
-        let mut current = leaf.node;
-
-        while let Some(parent) = state.parents.get(&current).copied() {
-            if let Some(scroll) = state.scroll_nodes.get(&parent).copied() {
-                y -= scroll.offset_lines;
-
-                let (sx, sy) =
-                    absolute_location(parent, &taffy, &state.parents, &mut absolute_cache)?;
-                let layout = taffy.layout(parent).map_err(io::Error::other)?;
-                let bounds = Rect {
-                    left: sx.floor() as i32,
-                    top: sy.floor() as i32,
-                    right: (sx + layout.size.width).ceil() as i32,
-                    bottom: (sy + layout.size.height).ceil() as i32,
-                };
-                clip = clip.and_then(|existing| existing.intersect(bounds));
-            }
-            current = parent;
+    for &(node, id) in &state.regions {
+        let (x, y, clip) = placement(node, &taffy, &state, &mut absolute_cache, screen)?;
+        let bounds = node_bounds(node, &taffy, x, y)?;
+        if let Some(bounds) = clip.and_then(|clip| bounds.intersect(clip)) {
+            buffer.add_region(id, bounds);
+        }
+    }
+
+    for (&node, border) in &state.borders {
+        let (x, y, clip) = placement(node, &taffy, &state, &mut absolute_cache, screen)?;
+        if let Some(clip) = clip {
+            let bounds = node_bounds(node, &taffy, x, y)?;
+            draw_border(buffer, bounds, clip, *border);
         }
+    }
 
+    for leaf in &state.leaves {
+        let (x, y, clip) = placement(leaf.node, &taffy, &state, &mut absolute_cache, screen)?;
         if let Some(clip) = clip {
             leaf.inline.render_at_clipped(
-                &mut buffer,
-                abs_x.floor() as i32,
+                buffer,
+                x.floor() as i32,
                 y.floor() as i32,
                 leaf.color,
                 clip,
@@ -557,7 +777,91 @@ pub(crate) fn render_element(
         }
     }
 
-    Ok(buffer)
+    timings.paint += started.elapsed();
+    Ok(state.overlays)
+}
+
+/// Where `node` is drawn once the scroll views around it are scrolled, and
+/// the part of the screen it may draw in: inside those views and inside
+/// the frames of bordered divs around it. `None` when none of it shows.
+fn placement(
+    node: NodeId,
+    taffy: &TaffyTree<()>,
+    state: &BuildState,
+    cache: &mut HashMap<NodeId, (f32, f32)>,
+    screen: Rect,
+) -> Result<(f32, f32, Option<Rect>), LayoutError> {
+    let mut ancestors = Vec::new();
+    let mut current = node;
+    while let Some(parent) = state.parents.get(&current).copied() {
+        ancestors.push(parent);
+        current = parent;
+    }
+    // Outermost first, so each is clipped where the views above put it.
+    let mut scrolled = 0.0;
+    let mut clip = Some(screen);
+    for &ancestor in ancestors.iter().rev() {
+        let scroll = state.scroll_nodes.get(&ancestor).copied();
+        let inset = i32::from(state.borders.contains_key(&ancestor));
+        if scroll.is_some() || inset > 0 {
+            let (x, y) = absolute_location(ancestor, taffy, &state.parents, cache)?;
+            let bounds = node_bounds(ancestor, taffy, x, y - scrolled)?;
+            let inner = Rect {
+                left: bounds.left + inset,
+                top: bounds.top + inset,
+                right: bounds.right - inset,
+                bottom: bounds.bottom - inset,
+            };
+            clip = clip.and_then(|existing| existing.intersect(inner));
+        }
+        if let Some(scroll) = scroll {
+            scrolled += scroll.offset_lines;
+        }
+    }
+    let (x, y) = absolute_location(node, taffy, &state.parents, cache)?;
+    Ok((x, y - scrolled, clip))
+}
+
+/// The cells `node` covers when drawn at `(x, y)`.
+fn node_bounds(node: NodeId, taffy: &TaffyTree<()>, x: f32, y: f32) -> Result<Rect, LayoutError> {
+    let layout = taffy.layout(node).map_err(LayoutError::Lookup)?;
+    Ok(Rect {
+        left: x.floor() as i32,
+        top: y.floor() as i32,
+        right: (x + layout.size.width).ceil() as i32,
+        bottom: (y + layout.size.height).ceil() as i32,
+    })
+}
+
+/// Frames `bounds` with box-drawing characters, leaving out what falls
+/// outside `clip`. Too small a box to frame is left alone.
+fn draw_border(buffer: &mut CellBuffer, bounds: Rect, clip: Rect, border: BorderLeaf) {
+    if bounds.right - bounds.left < 2 || bounds.bottom - bounds.top < 2 {
+        return;
+    }
+    let [horizontal, vertical, corners @ ..] = border.line.glyphs();
+    let style = CellStyle {
+        fg: border.color,
+        ..CellStyle::default()
+    };
+    let (right, bottom) = (bounds.right - 1, bounds.bottom - 1);
+    let mut put = |x: i32, y: i32, ch: char| {
+        if x >= clip.left && x < clip.right && y >= clip.top && y < clip.bottom {
+            buffer.put_char(x, y, ch, style);
+        }
+    };
+    for x in bounds.left + 1..right {
+        put(x, bounds.top, horizontal);
+        put(x, bottom, horizontal);
+    }
+    for y in bounds.top + 1..bottom {
+        put(bounds.left, y, vertical);
+        put(right, y, vertical);
+    }
+    put(bounds.left, bounds.top, corners[0]);
+    put(right, bounds.top, corners[1]);
+    put(right, bottom, corners[2]);
+    put(bounds.left, bottom, corners[3]);
 }
 
 fn fill_rect_bg(
@@ -584,17 +888,26 @@ fn fill_rect_bg(
     }
 }
 
+/// Blanks the cells of `bounds`, which must lie on the buffer.
+fn fill_rect_blank(buffer: &mut CellBuffer, bounds: Rect) {
+    for y in bounds.top..bounds.bottom {
+        for x in bounds.left..bounds.right {
+            buffer.set(x as u16, y as u16, Cell::blank());
+        }
+    }
+}
+
 fn absolute_location(
     node: NodeId,
     taffy: &TaffyTree<()>,
     parents: &HashMap<NodeId, NodeId>,
     cache: &mut HashMap<NodeId, (f32, f32)>,
-) -> io::Result<(f32, f32)> {
+) -> Result<(f32, f32), LayoutError> {
     if let Some(loc) = cache.get(&node).copied() {
         return Ok(loc);
     }
 
-    let layout = taffy.layout(node).map_err(io::Error::other)?;
+    let layout = taffy.layout(node).map_err(LayoutError::Lookup)?;
     let own = (layout.location.x, layout.location.y);
 
     let abs = if let Some(parent) = parents.get(&node).copied() {
@@ -611,18 +924,22 @@ fn absolute_location(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::frame::DIMMED_TEXT;
 
     fn text_leaf_positions(
         element: &AnyElement,
         width: f32,
         height: f32,
-    ) -> io::Result<HashMap<String, (u16, u16)>> {
+    ) -> Result<HashMap<String, (u16, u16)>, LayoutError> {
         let mut taffy = TaffyTree::new();
         let mut state = BuildState {
             leaves: Vec::new(),
             backgrounds: Vec::new(),
+            borders: HashMap::new(),
+            regions: Vec::new(),
             parents: HashMap::new(),
             scroll_nodes: HashMap::new(),
+            overlays: Vec::new(),
         };
 
         let root = build_layout_tree(
@@ -640,7 +957,7 @@ mod tests {
                     height: AvailableSpace::Definite(height),
                 },
             )
-            .map_err(io::Error::other)?;
+            .map_err(LayoutError::Compute)?;
 
         let mut cache = HashMap::new();
         let mut out = HashMap::new();
@@ -658,7 +975,7 @@ mod tests {
     }
 
     #[test]
-    fn nested_children_use_absolute_positions() -> io::Result<()> {
+    fn nested_children_use_absolute_positions() -> Result<(), LayoutError> {
         let tree = div().flex_col().child("header").child(
             div()
                 .flex_col()
@@ -679,7 +996,116 @@ mod tests {
     }
 
     #[test]
-    fn multiline_text_reserves_height_for_following_rows() -> io::Result<()> {
+    fn shared_subtrees_lay_out_like_their_element() -> Result<(), LayoutError> {
+        let header = Arc::new(div().flex_col().child("header").into_any_element());
+        let tree = div().flex_col().child(header).child("body");
+
+        let pos = text_leaf_positions(&tree.into_any_element(), 80.0, 24.0)?;
+        assert!(pos["body"].1 > pos["header"].1);
+        Ok(())
+    }
+
+    #[test]
+    fn extreme_sizes_render_at_their_size() -> Result<(), LayoutError> {
+        let tree = div()
+            .flex_col()
+            .child("header")
+            .child(
+                scroll_view(div().child("wide 字 ".repeat(200)))
+                    .offset_lines(3)
+                    .viewport_lines(2),
+            )
+            .into_any_element();
+        for (width, height) in [(0, 0), (0, 5), (5, 0), (1, 1), (1, 40), (10_000, 3)] {
+            let buffer = render_element(&tree, width, height, &mut FrameTimings::default())?;
+            assert_eq!((buffer.width(), buffer.height()), (width, height));
+        }
+        assert_eq!(
+            crate::frame::clamp_size(u16::MAX, u16::MAX),
+            (crate::frame::MAX_COLUMNS, crate::frame::MAX_ROWS)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn borders_frame_their_children() -> Result<(), LayoutError> {
+        let framed = |line: Div| {
+            div()
+                .child(line.w(Pixels(6.0)).h(Pixels(3.0)).child("abcdefgh"))
+                .into_any_element()
+        };
+        let rows = |tree: &AnyElement| -> Result<Vec<String>, LayoutError> {
+            let buffer = render_element(tree, 8, 3, &mut FrameTimings::default())?;
+            Ok((0..3)
+                .map(|y| {
+                    let row: String = buffer.row_runs(y).into_iter().map(|run| run.text).collect();
+                    row.trim_end().to_string()
+                })
+                .collect())
+        };
+
+        assert_eq!(
+            rows(&framed(div().border_1()))?,
+            ["┌────┐", "│abcd│", "└────┘"]
+        );
+        assert_eq!(rows(&framed(div().border_dashed()))?[0], "┌╌╌╌╌┐");
+        Ok(())
+    }
+
+    #[test]
+    fn divs_with_ids_are_found_where_they_landed() -> Result<(), LayoutError> {
+        let tree = div()
+            .flex_col()
+            .child("title")
+            .child(div().id(1).child(div().id(2).child("ok")).child(" cancel"))
+            .into_any_element();
+        let buffer = render_element(&tree, 20, 3, &mut FrameTimings::default())?;
+
+        assert_eq!(buffer.region_at(0, 0), None);
+        assert_eq!(buffer.region_at(1, 1), Some(2));
+        assert_eq!(buffer.region_at(4, 1), Some(1));
+        assert_eq!(buffer.region_at(1, 2), None);
+        Ok(())
+    }
+
+    #[test]
+    fn overlays_cover_the_frame_centred_and_dim_it() -> Result<(), LayoutError> {
+        let tree = div()
+            .flex_col()
+            .child(div().id(1).child("xxxxxxxxx"))
+            .child(overlay(div().id(2).child("hi")))
+            .child("yyyyyyyyy")
+            .into_any_element();
+        let buffer = render_element(&tree, 9, 3, &mut FrameTimings::default())?;
+
+        let rows: Vec<String> = (0..3)
+            .map(|y| buffer.row_runs(y).into_iter().map(|run| run.text).collect())
+            .collect();
+        assert_eq!(rows, ["xxxxxxxxx", "yyyyhiyyy", "         "]);
+        assert_eq!(buffer.get(0, 0).style.fg, Some(DIMMED_TEXT));
+        assert_eq!(buffer.get(4, 1).style.fg, None);
+        assert_eq!(buffer.region_at(0, 0), None);
+        assert_eq!(buffer.region_at(4, 1), Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn padding_and_margins_move_children() -> Result<(), LayoutError> {
+        let tree = div()
+            .flex_col()
+            .child(div().p_1().child("padded"))
+            .child(div().my_1().mx(2).child("spaced"))
+            .child(div().w(Pixels(6.0)).px_1().child("wrap me"));
+
+        let pos = text_leaf_positions(&tree.into_any_element(), 80.0, 24.0)?;
+        assert_eq!(pos["padded"], (1, 1));
+        assert_eq!(pos["spaced"], (2, 4));
+        assert_eq!(pos["wrap me"], (1, 6));
+        Ok(())
+    }
+
+    #[test]
+    fn multiline_text_reserves_height_for_following_rows() -> Result<(), LayoutError> {
         let tree = div()
             .flex_col()
             .child("row-a\nrow-a-detail")
@@ -692,4 +1118,32 @@ mod tests {
         assert!(second_y >= first_y + 2);
         Ok(())
     }
+
+    #[test]
+    fn word_wrapped_text_breaks_between_words() -> Result<(), LayoutError> {
+        let rows = |text: StyledText| -> Result<Vec<String>, LayoutError> {
+            let tree = div().w(Pixels(8.0)).child(text).into_any_element();
+            let buffer = render_element(&tree, 8, 4, &mut FrameTimings::default())?;
+            Ok((0..4)
+                .map(|y| {
+                    let row: String = buffer.row_runs(y).into_iter().map(|run| run.text).collect();
+                    row.trim_end().to_string()
+                })
+                .collect())
+        };
+
+        assert_eq!(
+            rows(StyledText::new("one two three"))?,
+            ["one two", "three", "", ""]
+        );
+        assert_eq!(
+            rows(StyledText::new("ab cdefgh ijklmnopqr").word_wrap())?,
+            ["ab", "cdefgh", "ijklmnop", "qr"]
+        );
+        assert_eq!(
+            rows(StyledText::new("ab cdefgh ijklmnopqr"))?,
+            ["ab cdefg", "h ijklmn", "opqr", ""]
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,50 @@
+use std::io;
+
+use thiserror::Error;
+
+/// The element tree could not be laid out. This points at a bug in the tree
+/// or in cpui rather than at the environment, so retrying does not help.
+#[derive(Debug, Error)]
+pub enum LayoutError {
+    #[error("cannot build layout node")]
+    Build(#[source] taffy::TaffyError),
+    #[error("cannot compute layout")]
+    Compute(#[source] taffy::TaffyError),
+    #[error("layout node has no computed layout")]
+    Lookup(#[source] taffy::TaffyError),
+}
+
+/// A frame could not be produced or written.
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error("cannot lay out frame")]
+    Layout(#[from] LayoutError),
+    #[error("cannot write frame to the terminal")]
+    Terminal(#[source] io::Error),
+}
+
+/// Errors surfaced by the application runtime.
+#[derive(Debug, Error)]
+pub enum BackendError {
+    #[error("cannot set up the terminal")]
+    TerminalSetup(#[source] io::Error),
+    #[error("cannot read terminal input")]
+    Input(#[source] io::Error),
+    #[error("cannot render window")]
+    Render(#[from] RenderError),
+    #[error("window {0} not found")]
+    WindowNotFound(u64),
+    #[error("window {0} root view has a different type")]
+    RootTypeMismatch(u64),
+}
+
+impl BackendError {
+    /// True when the terminal itself failed (closed, not a tty, write error),
+    /// as opposed to a bug in the UI code.
+    pub fn is_terminal_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::TerminalSetup(_) | Self::Input(_) | Self::Render(RenderError::Terminal(_))
+        )
+    }
+}
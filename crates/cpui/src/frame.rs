@@ -1,6 +1,6 @@
 use unicode_width::UnicodeWidthChar;
 
-use crate::{color::Rgba, text::TextStyle};
+use crate::{color::Rgba, element::Rect, link::LinkId, text::TextStyle};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum Glyph {
@@ -14,16 +14,18 @@ impl Default for Glyph {
     }
 }
 
+/// How the text of a cell is drawn.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
-pub(crate) struct CellStyle {
-    pub(crate) bold: bool,
-    pub(crate) italic: bool,
-    pub(crate) underline: bool,
-    pub(crate) strikethrough: bool,
-    pub(crate) fg: Option<Rgba>,
+pub struct CellStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub fg: Option<Rgba>,
     pub(crate) cursor_anchor: bool,
     pub(crate) cursor_after: bool,
-    pub(crate) bg: Option<Rgba>,
+    pub bg: Option<Rgba>,
+    pub link: Option<LinkId>,
 }
 
 impl From<TextStyle> for CellStyle {
@@ -37,6 +39,7 @@ impl From<TextStyle> for CellStyle {
             cursor_anchor: value.cursor_anchor,
             cursor_after: value.cursor_after,
             bg: value.bg,
+            link: value.link,
         }
     }
 }
@@ -52,6 +55,7 @@ impl From<&TextStyle> for CellStyle {
             cursor_anchor: value.cursor_anchor,
             cursor_after: value.cursor_after,
             bg: value.bg,
+            link: value.link,
         }
     }
 }
@@ -75,25 +79,49 @@ impl Cell {
                 cursor_anchor: false,
                 cursor_after: false,
                 bg: None,
+                link: None,
             },
         }
     }
 }
 
+/// What text without a color of its own becomes under an overlay.
+pub(crate) const DIMMED_TEXT: Rgba = Rgba {
+    r: 0x6c,
+    g: 0x6c,
+    b: 0x6c,
+};
+
+/// Most columns and rows a frame covers. A larger terminal shows the frame in
+/// its top left corner instead of a buffer of billions of cells.
+pub(crate) const MAX_COLUMNS: u16 = 4096;
+pub(crate) const MAX_ROWS: u16 = 1024;
+
+/// The size of frame drawn for a terminal of `width` by `height` cells.
+pub(crate) fn clamp_size(width: u16, height: u16) -> (u16, u16) {
+    (width.min(MAX_COLUMNS), height.min(MAX_ROWS))
+}
+
+/// A frame: the glyph and style of every cell, and where the cursor is.
+/// [`Renderer`](crate::Renderer)s read it; [`render_frame`](crate::render_frame)
+/// makes one.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub(crate) struct CellBuffer {
+pub struct CellBuffer {
     width: u16,
     height: u16,
     cells: Vec<Cell>,
     cursor: Option<(u16, u16)>,
+    /// Cells covered by divs with an id, innermost first.
+    regions: Vec<(u64, Rect)>,
 }
 
+/// Cells side by side in a row with the same style, starting at `x`.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub(crate) struct CellRun {
-    pub(crate) x: u16,
-    pub(crate) y: u16,
-    pub(crate) text: String,
-    pub(crate) style: CellStyle,
+pub struct CellRun {
+    pub x: u16,
+    pub y: u16,
+    pub text: String,
+    pub style: CellStyle,
 }
 
 impl CellBuffer {
@@ -104,14 +132,15 @@ impl CellBuffer {
             height,
             cells: vec![Cell::blank(); len],
             cursor: None,
+            regions: Vec::new(),
         }
     }
 
-    pub(crate) fn width(&self) -> u16 {
+    pub fn width(&self) -> u16 {
         self.width
     }
 
-    pub(crate) fn height(&self) -> u16 {
+    pub fn height(&self) -> u16 {
         self.height
     }
 
@@ -189,11 +218,63 @@ impl CellBuffer {
         }
     }
 
-    pub(crate) fn cursor(&self) -> Option<(u16, u16)> {
+    /// Dims every cell, for an overlay drawn over them. Text in the
+    /// terminal's own color turns grey. The cursor and regions go too: what
+    /// is under an overlay cannot be typed into or clicked.
+    pub(crate) fn dim(&mut self) {
+        for cell in &mut self.cells {
+            cell.style.fg = Some(cell.style.fg.map_or(DIMMED_TEXT, Rgba::dimmed));
+            cell.style.bg = cell.style.bg.map(Rgba::dimmed);
+        }
+        self.cursor = None;
+        self.regions.clear();
+    }
+
+    pub(crate) fn add_region(&mut self, id: u64, bounds: Rect) {
+        self.regions.push((id, bounds));
+    }
+
+    /// The id of the innermost div with one that covers cell `(x, y)`.
+    pub fn region_at(&self, x: u16, y: u16) -> Option<u64> {
+        let (x, y) = (i32::from(x), i32::from(y));
+        self.regions
+            .iter()
+            .find(|(_, bounds)| {
+                x >= bounds.left && x < bounds.right && y >= bounds.top && y < bounds.bottom
+            })
+            .map(|(id, _)| *id)
+    }
+
+    /// Where the app put the text cursor, if anywhere.
+    pub fn cursor(&self) -> Option<(u16, u16)> {
         self.cursor
     }
 
-    pub(crate) fn diff_runs(&self, prev: &Self) -> Vec<CellRun> {
+    /// Row `y` from its first cell to its last, split where the style
+    /// changes. The second half of a wide glyph adds no text.
+    pub fn row_runs(&self, y: u16) -> Vec<CellRun> {
+        let mut runs: Vec<CellRun> = Vec::new();
+        for x in 0..self.width {
+            let cell = self.get(x, y);
+            let Glyph::Char(ch) = cell.glyph else {
+                continue;
+            };
+            match runs.last_mut() {
+                Some(run) if run.style == cell.style => run.text.push(ch),
+                _ => runs.push(CellRun {
+                    x,
+                    y,
+                    text: ch.to_string(),
+                    style: cell.style,
+                }),
+            }
+        }
+        runs
+    }
+
+    /// The runs that differ from `prev`, which is all of them when it is
+    /// another size.
+    pub fn diff_runs(&self, prev: &Self) -> Vec<CellRun> {
         if self.width != prev.width || self.height != prev.height {
             return self.full_runs();
         }
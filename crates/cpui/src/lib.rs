@@ -1,23 +1,41 @@
 mod app;
+mod capabilities;
 mod color;
 mod context;
 mod element;
 mod entity;
+mod error;
 mod frame;
 mod geometry;
+mod link;
+mod renderer;
 mod runtime;
 mod text;
 mod view;
 mod window;
 
-pub use app::{App, Application, InputEvent, KeyInput, Result, SharedString};
+pub use app::{App, Application, InputEvent, KeyInput, MouseButton, Result, SharedString};
+pub use capabilities::{
+    Capabilities, ColorDepth, ImageProtocol, capabilities, init_capabilities,
+};
 pub use color::{Rgba, black, blue, green, red, rgb, white, yellow};
 pub use context::{
     AppContext, Context, EventEmitter, Focusable, Global, GpuiBorrow, Reservation, VisualContext,
 };
-pub use element::{AnyElement, Div, IntoElement, ScrollView, div, scroll_view};
+pub use element::{AnyElement, Div, IntoElement, Overlay, ScrollView, div, overlay, scroll_view};
 pub use entity::{AnyEntity, AnyView, Entity, EntityId, WeakEntity, WindowId};
+pub use error::{BackendError, LayoutError, RenderError};
+pub use frame::{CellBuffer, CellRun, CellStyle};
 pub use geometry::{Bounds, Pixels, Point, Size, px, size};
+pub use link::{LinkId, link};
+pub use renderer::{AnsiRenderer, HtmlRenderer, PlainTextRenderer, Renderer, render_frame};
+pub use runtime::appearance::{Appearance, terminal_appearance};
+pub use runtime::lifecycle::{copy_to_clipboard, ring_bell};
+pub use runtime::probe::{TerminalProbe, probe_terminal};
+pub use runtime::watchdog::{
+    FramePhase, FrameReport, FrameTimings, SlowFrame, frame_report, set_frame_budget,
+    set_slow_frame_log,
+};
 pub use text::{StyledText, TextRun, TextStyle, styled_text};
 pub use view::Render;
 pub use window::{
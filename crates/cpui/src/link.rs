@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Handle to a hyperlink target. Cells carry the handle instead of the URL so
+/// they stay `Copy`; targets are interned for the life of the process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LinkId(u32);
+
+#[derive(Default)]
+struct Links {
+    urls: Vec<String>,
+    ids: HashMap<String, LinkId>,
+}
+
+fn links() -> &'static Mutex<Links> {
+    static LINKS: OnceLock<Mutex<Links>> = OnceLock::new();
+    LINKS.get_or_init(Mutex::default)
+}
+
+/// Interns `url`, returning the same id for the same target.
+pub fn link(url: &str) -> LinkId {
+    let mut links = links().lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(id) = links.ids.get(url) {
+        return *id;
+    }
+    let id = LinkId(links.urls.len() as u32);
+    links.urls.push(url.to_string());
+    links.ids.insert(url.to_string(), id);
+    id
+}
+
+pub(crate) fn url(id: LinkId) -> Option<String> {
+    let links = links().lock().unwrap_or_else(|err| err.into_inner());
+    links.urls.get(id.0 as usize).cloned()
+}
+
+impl LinkId {
+    /// Stable across frames, so a terminal can treat the wrapped cells of one
+    /// link as a single target.
+    pub(crate) fn index(self) -> u32 {
+        self.0
+    }
+}
@@ -0,0 +1,335 @@
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor,
+    style::{
+        Attribute, Color as TermColor, Print, ResetColor, SetAttribute, SetBackgroundColor,
+        SetForegroundColor,
+    },
+    terminal::{BeginSynchronizedUpdate, Clear, ClearType, EndSynchronizedUpdate},
+};
+
+use crate::{
+    capabilities::{Capabilities, ColorDepth, capabilities, palette_index},
+    color::Rgba,
+    element::AnyElement,
+    error::LayoutError,
+    frame::{CellBuffer, CellStyle},
+    link::LinkId,
+    runtime::watchdog::FrameTimings,
+};
+
+/// Turns frames into bytes for some sink: a terminal, a test fixture, a
+/// page. A window hands its renderer every frame it draws; set one with
+/// [`Window::set_renderer`](crate::Window::set_renderer).
+pub trait Renderer {
+    /// Encodes `frame` into `out`. `prev` is the frame rendered before it
+    /// when that was the same size, so a renderer that updates a screen in
+    /// place need only write what changed; `None` on the first frame and
+    /// after a resize. `cursor` is where to show the text cursor, `None` to
+    /// hide it.
+    fn render(
+        &mut self,
+        out: &mut Vec<u8>,
+        prev: Option<&CellBuffer>,
+        frame: &CellBuffer,
+        cursor: Option<(u16, u16)>,
+    ) -> io::Result<()>;
+}
+
+/// Lays out `element` and paints it into a frame of `width` by `height`
+/// cells, as a window would, for a [`Renderer`] to write somewhere else.
+pub fn render_frame(
+    element: &AnyElement,
+    width: u16,
+    height: u16,
+) -> Result<CellBuffer, LayoutError> {
+    crate::element::render_element(element, width, height, &mut FrameTimings::default())
+}
+
+/// Draws frames on a terminal with ANSI escapes, writing only the cells that
+/// changed, in colors and features the terminal was found to support. What
+/// windows use unless told otherwise.
+#[derive(Debug, Default)]
+pub struct AnsiRenderer;
+
+impl Renderer for AnsiRenderer {
+    fn render(
+        &mut self,
+        out: &mut Vec<u8>,
+        prev: Option<&CellBuffer>,
+        frame: &CellBuffer,
+        cursor: Option<(u16, u16)>,
+    ) -> io::Result<()> {
+        let capabilities = capabilities();
+        if capabilities.synchronized_updates {
+            crossterm::queue!(out, BeginSynchronizedUpdate)?;
+        } else {
+            // The frame goes out in one write either way; without mode 2026
+            // this at least keeps the cursor from racing across the screen.
+            crossterm::queue!(out, cursor::Hide)?;
+        }
+        let blank;
+        let prev = match prev {
+            Some(prev) => prev,
+            None => {
+                crossterm::queue!(out, Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+                blank = CellBuffer::new(frame.width(), frame.height());
+                &blank
+            }
+        };
+        let mut style_emitter = StyleEmitter::new(capabilities);
+        for run in frame.diff_runs(prev) {
+            style_emitter.apply(out, run.style)?;
+            crossterm::queue!(out, cursor::MoveTo(run.x, run.y), Print(run.text))?;
+        }
+        style_emitter.reset(out)?;
+        match cursor {
+            Some((x, y)) => crossterm::queue!(out, cursor::MoveTo(x, y), cursor::Show)?,
+            None => crossterm::queue!(out, cursor::Hide)?,
+        }
+        if capabilities.synchronized_updates {
+            crossterm::queue!(out, EndSynchronizedUpdate)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes each frame whole as plain text, a line per row without trailing
+/// spaces, for fixtures and logs. Styles and the cursor are dropped.
+#[derive(Debug, Default)]
+pub struct PlainTextRenderer;
+
+impl Renderer for PlainTextRenderer {
+    fn render(
+        &mut self,
+        out: &mut Vec<u8>,
+        _prev: Option<&CellBuffer>,
+        frame: &CellBuffer,
+        _cursor: Option<(u16, u16)>,
+    ) -> io::Result<()> {
+        for y in 0..frame.height() {
+            let row: String = frame.row_runs(y).into_iter().map(|run| run.text).collect();
+            writeln!(out, "{}", row.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes each frame whole as a `<pre>` of styled `<span>`s, links as
+/// `<a>`, for exporting what the screen showed. Colors are written as they
+/// are, whatever the terminal supports.
+#[derive(Debug, Default)]
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    fn render(
+        &mut self,
+        out: &mut Vec<u8>,
+        _prev: Option<&CellBuffer>,
+        frame: &CellBuffer,
+        _cursor: Option<(u16, u16)>,
+    ) -> io::Result<()> {
+        writeln!(out, "<pre class=\"cpui\">")?;
+        for y in 0..frame.height() {
+            for run in frame.row_runs(y) {
+                let text = escape_html(&run.text);
+                let url = run.style.link.and_then(crate::link::url);
+                if let Some(url) = &url {
+                    write!(out, "<a href=\"{}\">", escape_html(url))?;
+                }
+                match css(run.style) {
+                    Some(css) => write!(out, "<span style=\"{css}\">{text}</span>")?,
+                    None => write!(out, "{text}")?,
+                }
+                if url.is_some() {
+                    write!(out, "</a>")?;
+                }
+            }
+            writeln!(out)?;
+        }
+        writeln!(out, "</pre>")
+    }
+}
+
+/// The inline CSS for `style`; `None` for the default look.
+fn css(style: CellStyle) -> Option<String> {
+    let hex = |color: Rgba| format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b);
+    let mut rules = Vec::new();
+    if let Some(fg) = style.fg {
+        rules.push(format!("color:{}", hex(fg)));
+    }
+    if let Some(bg) = style.bg {
+        rules.push(format!("background-color:{}", hex(bg)));
+    }
+    if style.bold {
+        rules.push("font-weight:bold".to_string());
+    }
+    if style.italic {
+        rules.push("font-style:italic".to_string());
+    }
+    let lines: Vec<&str> = [
+        (style.underline, "underline"),
+        (style.strikethrough, "line-through"),
+    ]
+    .into_iter()
+    .filter_map(|(on, line)| on.then_some(line))
+    .collect();
+    if !lines.is_empty() {
+        rules.push(format!("text-decoration:{}", lines.join(" ")));
+    }
+    (!rules.is_empty()).then(|| rules.join(";"))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+struct StyleEmitter {
+    current: CellStyle,
+    depth: ColorDepth,
+    hyperlinks: bool,
+}
+
+impl StyleEmitter {
+    fn new(capabilities: &Capabilities) -> Self {
+        Self {
+            current: CellStyle::default(),
+            depth: capabilities.color,
+            hyperlinks: capabilities.hyperlinks,
+        }
+    }
+
+    /// Opens `link`, or closes the open one when `None`. OSC 8 is independent
+    /// of SGR, so attribute resets leave it alone.
+    fn write_link(&self, out: &mut impl io::Write, link: Option<LinkId>) -> io::Result<()> {
+        if !self.hyperlinks {
+            return Ok(());
+        }
+        match link.and_then(|id| crate::link::url(id).map(|url| (id, url))) {
+            Some((id, url)) => write!(out, "\x1b]8;id=cpui{};{url}\x1b\\", id.index()),
+            None => write!(out, "\x1b]8;;\x1b\\"),
+        }
+    }
+
+    fn color(&self, color: Rgba) -> TermColor {
+        match palette_index(color, self.depth) {
+            Some(index) => TermColor::AnsiValue(index),
+            None => TermColor::Rgb {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+            },
+        }
+    }
+
+    fn apply(&mut self, out: &mut impl io::Write, target: CellStyle) -> io::Result<()> {
+        if self.current == target {
+            return Ok(());
+        }
+
+        let attrs_changed = self.current.bold != target.bold
+            || self.current.italic != target.italic
+            || self.current.underline != target.underline
+            || self.current.strikethrough != target.strikethrough;
+
+        if attrs_changed {
+            crossterm::queue!(out, SetAttribute(Attribute::Reset))?;
+        }
+
+        if attrs_changed || self.current.fg != target.fg {
+            if let Some(color) = target.fg {
+                crossterm::queue!(out, SetForegroundColor(self.color(color)))?;
+            } else {
+                crossterm::queue!(out, SetForegroundColor(TermColor::Reset))?;
+            }
+        }
+
+        if attrs_changed || self.current.bg != target.bg {
+            if let Some(bg) = target.bg {
+                crossterm::queue!(out, SetBackgroundColor(self.color(bg)))?;
+            } else {
+                crossterm::queue!(out, SetBackgroundColor(TermColor::Reset))?;
+            }
+        }
+
+        if attrs_changed {
+            if target.bold {
+                crossterm::queue!(out, SetAttribute(Attribute::Bold))?;
+            }
+            if target.italic {
+                crossterm::queue!(out, SetAttribute(Attribute::Italic))?;
+            }
+            if target.underline {
+                crossterm::queue!(out, SetAttribute(Attribute::Underlined))?;
+            }
+            if target.strikethrough {
+                crossterm::queue!(out, SetAttribute(Attribute::CrossedOut))?;
+            }
+        }
+
+        if self.current.link != target.link {
+            self.write_link(out, target.link)?;
+        }
+
+        self.current = target;
+        Ok(())
+    }
+
+    fn reset(&mut self, out: &mut impl io::Write) -> io::Result<()> {
+        if self.current.link.is_some() {
+            self.write_link(out, None)?;
+        }
+        if self.current != CellStyle::default() {
+            self.current = CellStyle::default();
+            crossterm::queue!(out, SetAttribute(Attribute::Reset), ResetColor)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::{IntoElement, div};
+
+    fn render(
+        renderer: &mut impl Renderer,
+        prev: Option<&CellBuffer>,
+        frame: &CellBuffer,
+    ) -> String {
+        let mut out = Vec::new();
+        renderer.render(&mut out, prev, frame, None).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn one_frame_renders_to_every_sink() -> Result<(), LayoutError> {
+        let tree = div()
+            .flex_col()
+            .child("a < b")
+            .child("second")
+            .into_any_element();
+        let frame = render_frame(&tree, 8, 3)?;
+
+        assert_eq!(
+            render(&mut PlainTextRenderer, None, &frame),
+            "a < b\nsecond\n\n"
+        );
+        let html = render(&mut HtmlRenderer, None, &frame);
+        assert!(html.starts_with("<pre class=\"cpui\">\na &lt; b"), "{html}");
+        assert!(render(&mut AnsiRenderer, None, &frame).contains("second"));
+        assert!(!render(&mut AnsiRenderer, Some(&frame), &frame).contains("second"));
+        Ok(())
+    }
+}
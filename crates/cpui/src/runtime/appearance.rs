@@ -0,0 +1,152 @@
+use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+
+/// Whether the terminal background is light or dark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+const UNKNOWN: u8 = 0;
+const LIGHT: u8 = 1;
+const DARK: u8 = 2;
+
+static APPEARANCE: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+/// What the terminal background looked like when last asked: on startup and
+/// whenever the terminal regains focus, since that is when a user who just
+/// switched their OS theme comes back. `None` when the terminal did not say.
+pub fn terminal_appearance() -> Option<Appearance> {
+    match APPEARANCE.load(Ordering::Relaxed) {
+        LIGHT => Some(Appearance::Light),
+        DARK => Some(Appearance::Dark),
+        _ => None,
+    }
+}
+
+/// Asks the terminal for its background. Needs raw mode, and nothing else
+/// reading input meanwhile.
+pub(crate) fn refresh_terminal_appearance() {
+    let appearance = query_background()
+        .map(|(r, g, b)| {
+            let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            if luminance > 0.5 {
+                Appearance::Light
+            } else {
+                Appearance::Dark
+            }
+        })
+        .or_else(|| from_colorfgbg(&env::var("COLORFGBG").ok()?));
+    let value = match appearance {
+        Some(Appearance::Light) => LIGHT,
+        Some(Appearance::Dark) => DARK,
+        None => UNKNOWN,
+    };
+    APPEARANCE.store(value, Ordering::Relaxed);
+}
+
+/// How long to wait for the answer; terminals reply in a few milliseconds.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// The background color as red, green and blue in `0.0..=1.0`, from an
+/// OSC 11 query.
+#[cfg(unix)]
+fn query_background() -> Option<(f32, f32, f32)> {
+    use std::fs::OpenOptions;
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    let mut reader = tty.try_clone().ok()?;
+    // Device attributes go out after the query. Every terminal answers them,
+    // so the reader always gets to stop, and an OSC 11 answer comes first.
+    tty.write_all(b"\x1b]11;?\x1b\\\x1b[c").ok()?;
+    tty.flush().ok()?;
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut reply = Vec::new();
+        let mut byte = [0u8; 1];
+        while let Ok(1) = reader.read(&mut byte) {
+            reply.push(byte[0]);
+            if ends_with_device_attributes(&reply) {
+                break;
+            }
+        }
+        let _ = tx.send(reply);
+    });
+    let reply = rx.recv_timeout(QUERY_TIMEOUT).ok()?;
+    parse_osc11(&reply)
+}
+
+#[cfg(not(unix))]
+fn query_background() -> Option<(f32, f32, f32)> {
+    None
+}
+
+/// Whether `reply` ends in a primary device attributes answer,
+/// `ESC [ ? <params> c`.
+#[cfg(unix)]
+fn ends_with_device_attributes(reply: &[u8]) -> bool {
+    if reply.last() != Some(&b'c') {
+        return false;
+    }
+    let Some(start) = reply.windows(3).rposition(|w| w == b"\x1b[?") else {
+        return false;
+    };
+    reply[start + 3..reply.len() - 1]
+        .iter()
+        .all(|byte| byte.is_ascii_digit() || *byte == b';')
+}
+
+/// The color in `ESC ] 11 ; rgb:RRRR/GGGG/BBBB`, terminated by BEL or ST.
+/// Components have one to four hex digits.
+fn parse_osc11(reply: &[u8]) -> Option<(f32, f32, f32)> {
+    let text = String::from_utf8_lossy(reply);
+    let start = text.find("]11;rgb:")? + "]11;rgb:".len();
+    let body = &text[start..];
+    let end = body.find(['\x07', '\x1b']).unwrap_or(body.len());
+    let mut parts = body[..end].split('/').map(|part| {
+        let part = part.get(..part.len().min(4))?;
+        let value = u32::from_str_radix(part, 16).ok()?;
+        let max = (1u32 << (4 * part.len() as u32)) - 1;
+        Some(value as f32 / max as f32)
+    });
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+/// `COLORFGBG` is `fg;bg` (sometimes with a middle field) in xterm palette
+/// indices; 7 and the bright colors but 8 are light backgrounds.
+fn from_colorfgbg(value: &str) -> Option<Appearance> {
+    let bg = value.rsplit(';').next()?.parse::<u8>().ok()?;
+    Some(match bg {
+        7 | 9..=15 => Appearance::Light,
+        _ => Appearance::Dark,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_background_replies() {
+        let (r, g, b) = parse_osc11(b"\x1b]11;rgb:ffff/8080/0000\x1b\\").unwrap();
+        assert_eq!((r, b), (1.0, 0.0));
+        assert!((g - 0.502).abs() < 0.01);
+        assert_eq!(parse_osc11(b"\x1b]11;rgb:f/0/0\x07"), Some((1.0, 0.0, 0.0)));
+        assert_eq!(parse_osc11(b"\x1b[?62;22c"), None);
+    }
+
+    #[test]
+    fn reads_colorfgbg() {
+        assert_eq!(from_colorfgbg("15;0"), Some(Appearance::Dark));
+        assert_eq!(from_colorfgbg("0;default;15"), Some(Appearance::Light));
+        assert_eq!(from_colorfgbg("default"), None);
+    }
+}
@@ -1,15 +1,21 @@
-use std::{
-    io,
-    time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 
 use crossterm::event::{self, Event};
 
 use crate::app::{App, InputEvent};
+use crate::error::BackendError;
 
+use super::appearance::refresh_terminal_appearance;
 use super::input_map::map_input_event;
 
-pub(crate) fn run_event_loop<H>(app: &mut App, on_input: &mut H) -> io::Result<()>
+/// How long to wait for input before ticking, unless the app set otherwise.
+pub(crate) const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub(crate) fn run_event_loop<H>(
+    app: &mut App,
+    poll_interval: Duration,
+    on_input: &mut H,
+) -> Result<(), BackendError>
 where
     H: FnMut(&mut App, InputEvent) -> bool,
 {
@@ -21,13 +27,15 @@ where
             continue;
         }
 
-        match event::poll(Duration::from_millis(250)) {
+        match event::poll(poll_interval) {
             Ok(true) => {
                 let Ok(raw) = event::read() else {
                     continue;
                 };
                 if matches!(raw, Event::FocusGained) {
                     app.set_terminal_focus(true);
+                    // Coming back is when an OS theme switch would show.
+                    refresh_terminal_appearance();
                     if pending_resize_at.is_none() {
                         app.render_all_windows()?;
                     }
@@ -64,7 +72,7 @@ where
                     app.render_all_windows()?;
                 }
             }
-            Err(_) => continue,
+            Err(err) => return Err(BackendError::Input(err)),
         }
     }
 
@@ -75,7 +83,7 @@ fn flush_debounced_resize(
     app: &mut App,
     pending_resize_at: &mut Option<Instant>,
     debounce: Duration,
-) -> io::Result<bool> {
+) -> Result<bool, BackendError> {
     if let Some(at) = *pending_resize_at
         && at.elapsed() >= debounce
     {
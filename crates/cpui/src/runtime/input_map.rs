@@ -1,6 +1,7 @@
 use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers, MouseEventKind};
 
-use crate::app::{InputEvent, KeyInput};
+use crate::app::{InputEvent, KeyInput, MouseButton};
+use crate::capabilities::capabilities;
 
 pub(crate) fn map_input_event(event: Event) -> Option<InputEvent> {
     match event {
@@ -9,15 +10,21 @@ pub(crate) fn map_input_event(event: Event) -> Option<InputEvent> {
                 .modifiers
                 .intersects(KeyModifiers::CONTROL | KeyModifiers::SUPER);
             let submit_modifier = key.modifiers.contains(KeyModifiers::ALT);
+            let line_modifier = key.modifiers.contains(KeyModifiers::ALT);
             let ctrl_j_submit = key.modifiers.contains(KeyModifiers::CONTROL)
                 && matches!(key.code, KeyCode::Char('j' | 'J'))
-                && !is_vscode_terminal();
+                && capabilities().ctrl_enter;
             match key.code {
                 KeyCode::BackTab => Some(InputEvent::Key(KeyInput::ShiftTab)),
                 KeyCode::Left if word_modifier => Some(InputEvent::Key(KeyInput::WordLeft)),
                 KeyCode::Right if word_modifier => Some(InputEvent::Key(KeyInput::WordRight)),
                 KeyCode::Left => Some(InputEvent::Key(KeyInput::Left)),
                 KeyCode::Right => Some(InputEvent::Key(KeyInput::Right)),
+                KeyCode::Down if line_modifier && key.modifiers.contains(KeyModifiers::SHIFT) => {
+                    Some(InputEvent::Key(KeyInput::DuplicateLine))
+                }
+                KeyCode::Up if line_modifier => Some(InputEvent::Key(KeyInput::MoveLineUp)),
+                KeyCode::Down if line_modifier => Some(InputEvent::Key(KeyInput::MoveLineDown)),
                 KeyCode::Up => Some(InputEvent::Key(KeyInput::Up)),
                 KeyCode::Down => Some(InputEvent::Key(KeyInput::Down)),
                 KeyCode::Home => Some(InputEvent::Key(KeyInput::Home)),
@@ -33,9 +40,74 @@ pub(crate) fn map_input_event(event: Event) -> Option<InputEvent> {
                 _ if ctrl_j_submit => Some(InputEvent::Key(KeyInput::Submit)),
                 KeyCode::Enter if submit_modifier => Some(InputEvent::Key(KeyInput::Submit)),
                 KeyCode::Enter => Some(InputEvent::Key(KeyInput::Enter)),
+                KeyCode::Char('c' | 'C')
+                    if key
+                        .modifiers
+                        .contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
+                {
+                    Some(InputEvent::Key(KeyInput::Copy))
+                }
+                KeyCode::Insert if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(InputEvent::Key(KeyInput::Copy))
+                }
+                // Cmd, which only terminals speaking the kitty keyboard
+                // protocol report.
+                KeyCode::Char('c' | 'C') if key.modifiers.contains(KeyModifiers::SUPER) => {
+                    Some(InputEvent::Key(KeyInput::Copy))
+                }
+                KeyCode::Char('x' | 'X') if key.modifiers.contains(KeyModifiers::SUPER) => {
+                    Some(InputEvent::Key(KeyInput::Cut))
+                }
+                KeyCode::Char('v' | 'V') if key.modifiers.contains(KeyModifiers::SUPER) => {
+                    Some(InputEvent::Key(KeyInput::Paste))
+                }
                 KeyCode::Char('c' | 'C') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     Some(InputEvent::Key(KeyInput::Interrupt))
                 }
+                KeyCode::Char('g' | 'G') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(InputEvent::Key(KeyInput::QuickJump))
+                }
+                KeyCode::Char('v' | 'V') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(InputEvent::Key(KeyInput::Paste))
+                }
+                KeyCode::Char('x' | 'X') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(InputEvent::Key(KeyInput::Cut))
+                }
+                KeyCode::Char('z' | 'Z')
+                    if key
+                        .modifiers
+                        .contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
+                {
+                    Some(InputEvent::Key(KeyInput::Redo))
+                }
+                KeyCode::Char('z' | 'Z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(InputEvent::Key(KeyInput::Undo))
+                }
+                KeyCode::Char('y' | 'Y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(InputEvent::Key(KeyInput::Redo))
+                }
+                KeyCode::Char('d' | 'D') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(InputEvent::Key(KeyInput::AddCursorAtNext))
+                }
+                KeyCode::Char('k' | 'K')
+                    if key
+                        .modifiers
+                        .contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
+                {
+                    Some(InputEvent::Key(KeyInput::DeleteLine))
+                }
+                KeyCode::Char('k' | 'K') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(InputEvent::Key(KeyInput::KillToLineEnd))
+                }
+                KeyCode::Char('u' | 'U') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(InputEvent::Key(KeyInput::KillToLineStart))
+                }
+                KeyCode::Char('j' | 'J') if line_modifier => {
+                    Some(InputEvent::Key(KeyInput::JoinLines))
+                }
+                KeyCode::Char(digit @ '1'..='9') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    Some(InputEvent::Key(KeyInput::Panel(digit as u8 - b'0')))
+                }
                 KeyCode::Esc => Some(InputEvent::Key(KeyInput::Esc)),
                 KeyCode::Char(ch) => Some(InputEvent::Key(KeyInput::Char(ch))),
                 _ => None,
@@ -44,20 +116,34 @@ pub(crate) fn map_input_event(event: Event) -> Option<InputEvent> {
         Event::Mouse(mouse) => match mouse.kind {
             MouseEventKind::ScrollUp => Some(InputEvent::ScrollLines(-1)),
             MouseEventKind::ScrollDown => Some(InputEvent::ScrollLines(1)),
-            MouseEventKind::Down(crossterm::event::MouseButton::Left) => Some(
-                InputEvent::MouseDown {
+            MouseEventKind::Down(button) => Some(InputEvent::MouseDown {
+                x: mouse.column,
+                y: mouse.row,
+                button: match button {
+                    crossterm::event::MouseButton::Left => MouseButton::Left,
+                    crossterm::event::MouseButton::Right => MouseButton::Right,
+                    crossterm::event::MouseButton::Middle => MouseButton::Middle,
+                },
+                alt: mouse.modifiers.contains(KeyModifiers::ALT),
+            }),
+            MouseEventKind::Drag(crossterm::event::MouseButton::Left) => {
+                Some(InputEvent::MouseDrag {
                     x: mouse.column,
                     y: mouse.row,
-                },
-            ),
+                    alt: mouse.modifiers.contains(KeyModifiers::ALT),
+                })
+            }
+            MouseEventKind::Up(crossterm::event::MouseButton::Left) => Some(InputEvent::MouseUp {
+                x: mouse.column,
+                y: mouse.row,
+            }),
+            MouseEventKind::Moved => Some(InputEvent::MouseMove {
+                x: mouse.column,
+                y: mouse.row,
+            }),
             _ => None,
         },
+        Event::Paste(text) => Some(InputEvent::Paste(text)),
         _ => None,
     }
 }
-
-fn is_vscode_terminal() -> bool {
-    std::env::var("TERM_PROGRAM")
-        .map(|v| v.eq_ignore_ascii_case("vscode"))
-        .unwrap_or(false)
-}
@@ -3,15 +3,21 @@ use std::{
     sync::atomic::{AtomicBool, Ordering},
 };
 
+use crossterm::clipboard::CopyToClipboard;
 use crossterm::event::{
-    DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
-    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+    EnableFocusChange, EnableMouseCapture, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+    PushKeyboardEnhancementFlags,
 };
 use crossterm::execute;
 use crossterm::style::ResetColor;
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::{cursor, terminal::Clear, terminal::ClearType};
 
+use crate::capabilities::capabilities;
+
+use super::appearance::refresh_terminal_appearance;
+
 static ALT_SCREEN_ACTIVE: AtomicBool = AtomicBool::new(false);
 // NOTE: crossterm currently does not expose cursor-shape APIs (DECSCUSR),
 // so we emit raw CSI sequences for blinking block cursor and reset.
@@ -29,6 +35,21 @@ pub(crate) fn is_alt_screen_active() -> bool {
     ALT_SCREEN_ACTIVE.load(Ordering::Relaxed)
 }
 
+/// Rings the terminal bell, which the terminal turns into a sound, a flash or
+/// an urgency hint as its user configured.
+pub fn ring_bell() {
+    let mut out = io::stdout();
+    let _ = out.write_all(b"\x07");
+    let _ = out.flush();
+}
+
+/// Asks the terminal to put `text` on the clipboard with OSC 52, which
+/// reaches the desktop's clipboard even over SSH. Terminals that do not
+/// support it, or have it turned off, ignore it.
+pub fn copy_to_clipboard(text: &str) {
+    let _ = execute!(io::stdout(), CopyToClipboard::to_clipboard_from(text));
+}
+
 pub(crate) fn enter_terminal() -> io::Result<TerminalGuard> {
     // Runtime contract:
     // 1) enable raw mode + enter alternate screen
@@ -41,16 +62,20 @@ pub(crate) fn enter_terminal() -> io::Result<TerminalGuard> {
         Clear(ClearType::All),
         cursor::MoveTo(0, 0),
         EnableMouseCapture,
-        EnableFocusChange
+        EnableFocusChange,
+        EnableBracketedPaste
     ) {
         let _ = terminal::disable_raw_mode();
         return Err(err);
     }
     ALT_SCREEN_ACTIVE.store(true, Ordering::Relaxed);
-    let _ = execute!(io::stdout(), PushKeyboardEnhancementFlags(KEYBOARD_FLAGS));
+    if capabilities().kitty_keyboard {
+        let _ = execute!(io::stdout(), PushKeyboardEnhancementFlags(KEYBOARD_FLAGS));
+    }
     let _ = io::stdout().write_all(CURSOR_COLOR_OSC.as_bytes());
     let _ = io::stdout().write_all(BLOCK_CURSOR_CSI.as_bytes());
     let _ = io::stdout().flush();
+    refresh_terminal_appearance();
 
     Ok(TerminalGuard)
 }
@@ -61,11 +86,14 @@ impl Drop for TerminalGuard {
     fn drop(&mut self) {
         let mut out = io::stdout();
         let _ = terminal::disable_raw_mode();
+        if capabilities().kitty_keyboard {
+            let _ = execute!(out, PopKeyboardEnhancementFlags);
+        }
         let _ = execute!(
             out,
             DisableMouseCapture,
             DisableFocusChange,
-            PopKeyboardEnhancementFlags,
+            DisableBracketedPaste,
             ResetColor,
             cursor::Show
         );
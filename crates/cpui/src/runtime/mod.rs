@@ -1,3 +1,6 @@
+pub(crate) mod appearance;
 pub(crate) mod event_loop;
 pub(crate) mod input_map;
 pub(crate) mod lifecycle;
+pub(crate) mod probe;
+pub(crate) mod watchdog;
@@ -0,0 +1,146 @@
+use std::io::{self, IsTerminal};
+use std::time::Duration;
+
+use crossterm::terminal;
+
+/// How long a query waits for the terminal to answer. Terminals answer in
+/// well under this locally; over SSH the round trip counts too.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What the attached terminal reports about itself, for diagnostics. Run it
+/// before the UI starts: the keyboard query briefly takes over stdin.
+#[derive(Clone, Copy, Debug)]
+pub struct TerminalProbe {
+    /// Both stdin and stdout are a terminal.
+    pub interactive: bool,
+    /// Columns and rows, when the terminal reports them.
+    pub size: Option<(u16, u16)>,
+    /// Whether the kitty keyboard protocol answered; `None` when the terminal
+    /// could not be asked.
+    pub keyboard_enhancement: Option<bool>,
+    /// Whether the terminal knows DEC mode 2026; `None` when it could not be
+    /// asked.
+    pub synchronized_updates: Option<bool>,
+}
+
+pub fn probe_terminal() -> TerminalProbe {
+    let interactive = io::stdin().is_terminal() && io::stdout().is_terminal();
+    let synchronized_updates = query_synchronized_updates();
+    TerminalProbe {
+        interactive,
+        size: terminal::size().ok(),
+        keyboard_enhancement: interactive
+            .then(|| terminal::supports_keyboard_enhancement().ok())
+            .flatten(),
+        synchronized_updates,
+    }
+}
+
+/// Asks the terminal with DECRQM whether it knows mode 2026, synchronized
+/// updates. Device attributes are asked right after, which every terminal
+/// answers, so one that ignores DECRQM is told apart from a slow one.
+/// `None` without a terminal or when neither answer came. Run it before the
+/// UI starts: it reads stdin.
+pub(crate) fn query_synchronized_updates() -> Option<bool> {
+    if !(io::stdin().is_terminal() && io::stdout().is_terminal()) {
+        return None;
+    }
+    let reply = query_tty(b"\x1b[?2026$p\x1b[c")?;
+    // 1 and 2 are set and reset, 3 permanently set; 0 is an unknown mode and
+    // 4 one that is permanently off.
+    Some(matches!(mode_setting(&reply, 2026), Some(1..=3)))
+}
+
+/// Writes `query` to the terminal and reads until the device attributes
+/// reply that ends it.
+#[cfg(unix)]
+fn query_tty(query: &[u8]) -> Option<Vec<u8>> {
+    use std::fs::OpenOptions;
+    use std::io::{Read, Write};
+    use std::time::Instant;
+
+    use rustix::event::{PollFd, PollFlags, Timespec, poll};
+
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    let was_raw = terminal::is_raw_mode_enabled().ok()?;
+    if !was_raw {
+        terminal::enable_raw_mode().ok()?;
+    }
+    let mut exchange = || {
+        tty.write_all(query).ok()?;
+        tty.flush().ok()?;
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        let mut reply = Vec::new();
+        let mut chunk = [0; 64];
+        while !answered_attributes(&reply) {
+            let left =
+                Timespec::try_from(deadline.saturating_duration_since(Instant::now())).ok()?;
+            let mut fds = [PollFd::new(&tty, PollFlags::IN)];
+            if poll(&mut fds, Some(&left)).ok()? == 0 {
+                return None;
+            }
+            let read = tty.read(&mut chunk).ok().filter(|read| *read > 0)?;
+            reply.extend_from_slice(&chunk[..read]);
+        }
+        Some(reply)
+    };
+    let reply = exchange();
+    if !was_raw {
+        let _ = terminal::disable_raw_mode();
+    }
+    reply
+}
+
+#[cfg(not(unix))]
+fn query_tty(_query: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+/// Whether `reply` ends with a primary device attributes report,
+/// `CSI ? … c`.
+fn answered_attributes(reply: &[u8]) -> bool {
+    let Some(start) = reply.windows(3).rposition(|window| window == b"\x1b[?") else {
+        return false;
+    };
+    let body = &reply[start + 3..];
+    body.last() == Some(&b'c')
+        && body[..body.len() - 1]
+            .iter()
+            .all(|byte| byte.is_ascii_digit() || *byte == b';')
+}
+
+/// The setting a DECRQM report in `reply`, `CSI ? mode ; setting $ y`, gives
+/// for `mode`.
+fn mode_setting(reply: &[u8], mode: u16) -> Option<u8> {
+    let prefix = format!("\x1b[?{mode};");
+    let start = reply
+        .windows(prefix.len())
+        .position(|window| window == prefix.as_bytes())?
+        + prefix.len();
+    let rest = &reply[start..];
+    let end = rest.windows(2).position(|window| window == b"$y")?;
+    std::str::from_utf8(&rest[..end]).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_reports_are_read_up_to_device_attributes() {
+        let supported = b"\x1b[?2026;2$y\x1b[?62;22c";
+        assert!(answered_attributes(supported));
+        assert_eq!(mode_setting(supported, 2026), Some(2));
+
+        let ignored = b"\x1b[?1;2c";
+        assert!(answered_attributes(ignored));
+        assert_eq!(mode_setting(ignored, 2026), None);
+
+        assert!(!answered_attributes(b"\x1b[?2026;0$y"));
+        assert!(!answered_attributes(b"\x1b[?62;2"));
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// About 30 frames a second; slower than that, scrolling visibly stutters.
+const DEFAULT_BUDGET: Duration = Duration::from_millis(33);
+/// Slow frames kept for [`frame_report`].
+const KEPT_FRAMES: usize = 32;
+
+/// A step of drawing one frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FramePhase {
+    /// The view building its element tree.
+    View,
+    /// Measuring text, wrapped to the width it gets, while building the
+    /// layout tree.
+    Wrap,
+    /// Sizing and placing the layout tree.
+    Layout,
+    /// Writing text and backgrounds into cells.
+    Paint,
+    /// Comparing cells with the last frame and encoding the changes.
+    Diff,
+    /// Writing the changes to the terminal.
+    Io,
+}
+
+impl FramePhase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::View => "view",
+            Self::Wrap => "wrap",
+            Self::Layout => "layout",
+            Self::Paint => "paint",
+            Self::Diff => "diff",
+            Self::Io => "io",
+        }
+    }
+}
+
+/// How long each phase of a frame took.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameTimings {
+    pub view: Duration,
+    pub wrap: Duration,
+    pub layout: Duration,
+    pub paint: Duration,
+    pub diff: Duration,
+    pub io: Duration,
+}
+
+impl FrameTimings {
+    pub fn phases(&self) -> [(FramePhase, Duration); 6] {
+        [
+            (FramePhase::View, self.view),
+            (FramePhase::Wrap, self.wrap),
+            (FramePhase::Layout, self.layout),
+            (FramePhase::Paint, self.paint),
+            (FramePhase::Diff, self.diff),
+            (FramePhase::Io, self.io),
+        ]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.phases().iter().map(|(_, time)| *time).sum()
+    }
+
+    /// The phase that took longest, which is where to look first.
+    pub fn dominant(&self) -> (FramePhase, Duration) {
+        self.phases()
+            .into_iter()
+            .max_by_key(|(_, time)| *time)
+            .unwrap_or((FramePhase::View, Duration::ZERO))
+    }
+
+    /// Like `48.1ms, mostly wrap 30.2ms (view 2.0 wrap 30.2 layout 9.8 …)`.
+    pub fn describe(&self) -> String {
+        let ms = |time: Duration| time.as_secs_f64() * 1000.0;
+        let (phase, time) = self.dominant();
+        let phases = self
+            .phases()
+            .iter()
+            .map(|(phase, time)| format!("{} {:.1}", phase.label(), ms(*time)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!(
+            "{:.1}ms, mostly {} {:.1}ms ({phases})",
+            ms(self.total()),
+            phase.label(),
+            ms(time)
+        )
+    }
+}
+
+/// A frame that went over the budget.
+#[derive(Clone, Debug)]
+pub struct SlowFrame {
+    pub at: SystemTime,
+    pub timings: FrameTimings,
+}
+
+/// What the watchdog has seen since startup.
+#[derive(Clone, Debug)]
+pub struct FrameReport {
+    pub budget: Duration,
+    pub frames: u64,
+    pub slow_frames: u64,
+    /// The latest slow frames, oldest first.
+    pub recent: Vec<SlowFrame>,
+    pub log: Option<PathBuf>,
+    /// Why the log stopped being written, if it did.
+    pub log_error: Option<String>,
+}
+
+struct Watchdog {
+    budget: Duration,
+    frames: u64,
+    slow_frames: u64,
+    recent: VecDeque<SlowFrame>,
+    log: Option<PathBuf>,
+    log_error: Option<String>,
+}
+
+static WATCHDOG: Mutex<Watchdog> = Mutex::new(Watchdog {
+    budget: DEFAULT_BUDGET,
+    frames: 0,
+    slow_frames: 0,
+    recent: VecDeque::new(),
+    log: None,
+    log_error: None,
+});
+
+fn watchdog() -> std::sync::MutexGuard<'static, Watchdog> {
+    WATCHDOG
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Frames that take longer than `budget` from building the view to the
+/// terminal flush are reported as slow. 33ms unless set.
+pub fn set_frame_budget(budget: Duration) {
+    watchdog().budget = budget;
+}
+
+/// Appends a line per slow frame to `path`, so a stutter can be reported
+/// after the fact. `None` stops logging.
+pub fn set_slow_frame_log(path: Option<PathBuf>) {
+    let mut watchdog = watchdog();
+    watchdog.log = path;
+    watchdog.log_error = None;
+}
+
+pub fn frame_report() -> FrameReport {
+    let watchdog = watchdog();
+    FrameReport {
+        budget: watchdog.budget,
+        frames: watchdog.frames,
+        slow_frames: watchdog.slow_frames,
+        recent: watchdog.recent.iter().cloned().collect(),
+        log: watchdog.log.clone(),
+        log_error: watchdog.log_error.clone(),
+    }
+}
+
+pub(crate) fn record(timings: FrameTimings) {
+    let mut watchdog = watchdog();
+    watchdog.frames += 1;
+    if timings.total() <= watchdog.budget {
+        return;
+    }
+    watchdog.slow_frames += 1;
+    let frame = SlowFrame {
+        at: SystemTime::now(),
+        timings,
+    };
+    if watchdog.log_error.is_none()
+        && let Some(path) = watchdog.log.clone()
+        && let Err(err) = append(&path, &frame)
+    {
+        watchdog.log_error = Some(format!("cannot append to {}: {err}", path.display()));
+    }
+    if watchdog.recent.len() == KEPT_FRAMES {
+        watchdog.recent.pop_front();
+    }
+    watchdog.recent.push_back(frame);
+}
+
+fn append(path: &Path, frame: &SlowFrame) -> std::io::Result<()> {
+    let seconds = frame
+        .at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{seconds:.3} slow frame {}", frame.timings.describe())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_frames_name_the_phase_that_dominated() {
+        let timings = FrameTimings {
+            view: Duration::from_millis(2),
+            wrap: Duration::from_millis(30),
+            layout: Duration::from_millis(10),
+            io: Duration::from_millis(6),
+            ..FrameTimings::default()
+        };
+        assert_eq!(timings.total(), Duration::from_millis(48));
+        assert_eq!(
+            timings.dominant(),
+            (FramePhase::Wrap, Duration::from_millis(30))
+        );
+        assert_eq!(
+            timings.describe(),
+            "48.0ms, mostly wrap 30.0ms \
+             (view 2.0 wrap 30.0 layout 10.0 paint 0.0 diff 0.0 io 6.0)"
+        );
+    }
+}
@@ -3,6 +3,7 @@ use unicode_width::UnicodeWidthChar;
 use crate::color::Rgba;
 use crate::element::Rect;
 use crate::frame::{CellBuffer, CellStyle};
+use crate::link::LinkId;
 
 #[derive(Clone, Debug, Default)]
 pub struct TextStyle {
@@ -14,6 +15,7 @@ pub struct TextStyle {
     pub cursor_anchor: bool,
     pub cursor_after: bool,
     pub bg: Option<Rgba>,
+    pub link: Option<LinkId>,
 }
 
 impl TextStyle {
@@ -56,6 +58,11 @@ impl TextStyle {
         self.bg = Some(color);
         self
     }
+
+    pub fn link(mut self, link: LinkId) -> Self {
+        self.link = Some(link);
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -83,12 +90,16 @@ impl TextRun {
 #[derive(Clone, Debug, Default)]
 pub struct StyledText {
     pub runs: Vec<TextRun>,
+    /// Lines break between words, and inside a word only when it is longer
+    /// than a line, rather than at whichever cell fills the line.
+    pub word_wrap: bool,
 }
 
 impl StyledText {
     pub fn new(text: impl Into<String>) -> Self {
         Self {
             runs: vec![TextRun::plain(text)],
+            word_wrap: false,
         }
     }
 
@@ -106,6 +117,11 @@ impl StyledText {
         self
     }
 
+    pub fn word_wrap(mut self) -> Self {
+        self.word_wrap = true;
+        self
+    }
+
     pub(crate) fn width_chars(&self) -> usize {
         let mut max_width = 0usize;
         let mut line_width = 0usize;
@@ -140,29 +156,62 @@ impl StyledText {
         self.width_chars().min(max_width)
     }
 
-    pub(crate) fn wrapped_height_lines(&self, max_width: usize) -> usize {
+    /// Rows the text takes drawn `max_width` columns wide, broken between
+    /// words if it [word-wraps](Self::word_wrap). 0 leaves lines unwrapped.
+    pub fn wrapped_height_lines(&self, max_width: usize) -> usize {
         if max_width == 0 {
             return self.height_lines();
         }
-
-        let mut lines = 1usize;
-        let mut line_width = 0usize;
-        for run in &self.runs {
-            for ch in run.text.chars() {
-                if ch == '\n' {
-                    lines = lines.saturating_add(1);
-                    line_width = 0;
-                    continue;
+        self.layout(max_width).1
+    }
+
+    /// Where each char goes with lines wrapped at `max_width` columns, as a
+    /// row and column, and how many rows that takes. Newlines, and spaces a
+    /// word wrap breaks at, have no place.
+    fn layout(&self, max_width: usize) -> (Vec<Option<(usize, usize)>>, usize) {
+        let chars = self
+            .runs
+            .iter()
+            .flat_map(|run| run.text.chars())
+            .collect::<Vec<_>>();
+        let width = |ch: &char| UnicodeWidthChar::width(*ch).unwrap_or(0);
+        let mut places = Vec::with_capacity(chars.len());
+        let (mut row, mut col) = (0usize, 0usize);
+        for (i, ch) in chars.iter().enumerate() {
+            if *ch == '\n' {
+                places.push(None);
+                row += 1;
+                col = 0;
+                continue;
+            }
+            let ch_width = width(ch);
+            let word_start =
+                self.word_wrap && !ch.is_whitespace() && i > 0 && chars[i - 1].is_whitespace();
+            if word_start && col > 0 {
+                let word = chars[i..]
+                    .iter()
+                    .take_while(|ch| !ch.is_whitespace())
+                    .map(width)
+                    .sum::<usize>();
+                // A word longer than a line starts one and breaks where it
+                // fills it.
+                if col + word > max_width {
+                    row += 1;
+                    col = 0;
                 }
-                let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0);
-                if line_width > 0 && line_width.saturating_add(ch_width) > max_width {
-                    lines = lines.saturating_add(1);
-                    line_width = 0;
+            }
+            if col > 0 && col + ch_width > max_width {
+                row += 1;
+                col = 0;
+                if self.word_wrap && ch.is_whitespace() {
+                    places.push(None);
+                    continue;
                 }
-                line_width = line_width.saturating_add(ch_width);
             }
+            places.push(Some((row, col)));
+            col += ch_width;
         }
-        lines
+        (places, row + 1)
     }
 
     pub(crate) fn render_at_clipped(
@@ -173,29 +222,22 @@ impl StyledText {
         inherited_color: Option<Rgba>,
         clip: Rect,
     ) {
-        let mut cursor_x = 0i32;
-        let mut cursor_y = 0i32;
-        let wrap_width = (clip.right - x).max(0);
+        let wrap_width = match (clip.right - x).max(0) as usize {
+            0 => usize::MAX,
+            width => width,
+        };
+        let mut places = self.layout(wrap_width).0.into_iter();
 
         for run in &self.runs {
             let mut style = CellStyle::from(&run.style);
             style.fg = style.fg.or(inherited_color);
 
             for ch in run.text.chars() {
-                if ch == '\n' {
-                    cursor_y = cursor_y.saturating_add(1);
-                    cursor_x = 0;
+                let Some(Some((row, col))) = places.next() else {
                     continue;
-                }
-
-                let ch_width = UnicodeWidthChar::width(ch).unwrap_or(0) as i32;
-                if wrap_width > 0 && cursor_x > 0 && cursor_x.saturating_add(ch_width) > wrap_width
-                {
-                    cursor_y = cursor_y.saturating_add(1);
-                    cursor_x = 0;
-                }
-                let draw_x = x.saturating_add(cursor_x);
-                let draw_y = y.saturating_add(cursor_y);
+                };
+                let draw_x = x.saturating_add(col as i32);
+                let draw_y = y.saturating_add(row as i32);
                 if draw_x >= clip.left
                     && draw_x < clip.right
                     && draw_y >= clip.top
@@ -203,11 +245,9 @@ impl StyledText {
                 {
                     buffer.put_char(draw_x, draw_y, ch, style);
                 }
-                cursor_x = cursor_x.saturating_add(ch_width);
             }
         }
     }
-
 }
 
 pub fn styled_text(text: impl Into<String>) -> StyledText {
@@ -1,23 +1,19 @@
 use std::{
-    io::{self, BufWriter, Write},
+    io::{self, Write},
     marker::PhantomData,
     time::{Duration, Instant},
 };
 
-use crossterm::{
-    cursor,
-    style::{
-        Attribute, Color as TermColor, Print, ResetColor, SetAttribute, SetBackgroundColor,
-        SetForegroundColor,
-    },
-    terminal::{self, BeginSynchronizedUpdate, Clear, ClearType, EndSynchronizedUpdate},
-};
+use crossterm::terminal;
 
 use crate::{
     element::AnyElement,
     entity::WindowId,
-    frame::{CellBuffer, CellStyle},
+    error::RenderError,
+    frame::{CellBuffer, clamp_size},
     geometry::{Bounds, Pixels, Size},
+    renderer::{AnsiRenderer, Renderer},
+    runtime::watchdog::{self, FrameTimings},
 };
 
 #[derive(Clone, Copy, Debug)]
@@ -108,10 +104,15 @@ impl Default for WindowOptions {
     }
 }
 
+/// How often the text cursor blinks, unless the app set otherwise.
+pub(crate) const DEFAULT_CURSOR_BLINK: Duration = Duration::from_millis(570);
+
 pub struct Window {
     id: WindowId,
     pub options: WindowOptions,
     prev_frame: Option<CellBuffer>,
+    renderer: Box<dyn Renderer>,
+    cursor_blink: Option<Duration>,
     cursor_visible: bool,
     cursor_blink_at: Instant,
     terminal_focused: bool,
@@ -123,6 +124,8 @@ impl Window {
             id,
             options,
             prev_frame: None,
+            renderer: Box::new(AnsiRenderer),
+            cursor_blink: Some(DEFAULT_CURSOR_BLINK),
             cursor_visible: true,
             cursor_blink_at: Instant::now(),
             terminal_focused: true,
@@ -133,55 +136,100 @@ impl Window {
         self.id
     }
 
-    pub fn terminal_size(&self) -> io::Result<(u16, u16)> {
-        terminal::size()
+    /// Renders the window's frames with `renderer` from the next one on. It
+    /// starts from a whole frame, so it need not know what the last one
+    /// drew.
+    pub fn set_renderer(&mut self, renderer: impl Renderer + 'static) {
+        self.renderer = Box::new(renderer);
+        self.prev_frame = None;
+    }
+
+    pub(crate) fn set_cursor_blink(&mut self, interval: Option<Duration>) {
+        self.cursor_blink = interval;
+        self.cursor_visible = true;
+    }
+
+    /// The size frames are drawn at: the terminal's, up to
+    /// [`MAX_COLUMNS`](crate::frame::MAX_COLUMNS) by
+    /// [`MAX_ROWS`](crate::frame::MAX_ROWS).
+    pub fn terminal_size(&self) -> Result<(u16, u16), RenderError> {
+        let (w, h) = terminal::size().map_err(RenderError::Terminal)?;
+        Ok(clamp_size(w, h))
     }
 
-    pub(crate) fn draw(&mut self, element: &AnyElement) -> io::Result<()> {
-        let stdout = io::stdout();
-        let mut out = BufWriter::new(stdout.lock());
-        crossterm::queue!(out, BeginSynchronizedUpdate)?;
-        let (w, h) = terminal::size()?;
-        let current = crate::element::render_element(element, w, h)?;
-        let mut resized = false;
+    /// Lays out and paints `element`, then writes what changed to the
+    /// terminal. `timings` already holds how long the view took; the rest of
+    /// the frame is added and reported to the watchdog.
+    pub(crate) fn draw(
+        &mut self,
+        element: &AnyElement,
+        mut timings: FrameTimings,
+    ) -> Result<(), RenderError> {
+        let (w, h) = self.terminal_size()?;
+        let current = crate::element::render_element(element, w, h, &mut timings)?;
+        self.present(current, w, h, &mut timings)
+            .map_err(RenderError::Terminal)?;
+        watchdog::record(timings);
+        Ok(())
+    }
+
+    fn present(
+        &mut self,
+        current: CellBuffer,
+        w: u16,
+        h: u16,
+        timings: &mut FrameTimings,
+    ) -> io::Result<()> {
+        let started = Instant::now();
+        // Encoded in memory first so the watchdog can tell diffing apart from
+        // a slow terminal.
+        let mut out = Vec::new();
         let prev = self
             .prev_frame
             .take()
-            .inspect(|frame| {
-                resized = frame.width() != w || frame.height() != h;
-            })
-            .filter(|frame| frame.width() == w && frame.height() == h)
-            .unwrap_or_else(|| CellBuffer::new(w, h));
-        if resized {
-            crossterm::queue!(out, Clear(ClearType::All), cursor::MoveTo(0, 0))?;
-        }
-        flush_diff(&mut out, &prev, &current)?;
-        if self.terminal_focused {
-            if let Some((cx, cy)) = current.cursor() {
-                if self.cursor_blink_at.elapsed() >= Duration::from_millis(570) {
-                    self.cursor_visible = !self.cursor_visible;
-                    self.cursor_blink_at = Instant::now();
-                }
-                if self.cursor_visible {
-                    crossterm::queue!(out, cursor::MoveTo(cx, cy), cursor::Show)?;
-                } else {
-                    crossterm::queue!(out, cursor::Hide)?;
-                }
-            } else {
-                self.cursor_visible = true;
-                self.cursor_blink_at = Instant::now();
-                crossterm::queue!(out, cursor::Hide)?;
-            }
-        } else {
-            if self.cursor_blink_at.elapsed() >= Duration::from_millis(570) {
+            .filter(|frame| frame.width() == w && frame.height() == h);
+        let cursor = self.shown_cursor(&current);
+        self.renderer
+            .render(&mut out, prev.as_ref(), &current, cursor)?;
+        self.prev_frame = Some(current);
+        timings.diff = started.elapsed();
+        let started = Instant::now();
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&out)?;
+        stdout.flush()?;
+        timings.io = started.elapsed();
+        Ok(())
+    }
+
+    /// The id of the innermost div with one under cell `(x, y)` in the frame
+    /// last drawn.
+    pub fn region_at(&self, x: u16, y: u16) -> Option<u64> {
+        self.prev_frame.as_ref()?.region_at(x, y)
+    }
+
+    /// Where to show the cursor of `frame` this frame: nowhere while the
+    /// terminal is unfocused or the cursor is blinked off.
+    fn shown_cursor(&mut self, frame: &CellBuffer) -> Option<(u16, u16)> {
+        if !self.terminal_focused {
+            if self.cursor_blink_at.elapsed() >= self.cursor_blink.unwrap_or_default() {
                 self.cursor_visible = true;
                 self.cursor_blink_at = Instant::now();
             }
-            crossterm::queue!(out, cursor::Hide)?;
+            return None;
         }
-        self.prev_frame = Some(current);
-        crossterm::queue!(out, EndSynchronizedUpdate)?;
-        out.flush()
+        let Some(cursor) = frame.cursor() else {
+            self.cursor_visible = true;
+            self.cursor_blink_at = Instant::now();
+            return None;
+        };
+        if self
+            .cursor_blink
+            .is_some_and(|blink| self.cursor_blink_at.elapsed() >= blink)
+        {
+            self.cursor_visible = !self.cursor_visible;
+            self.cursor_blink_at = Instant::now();
+        }
+        self.cursor_visible.then_some(cursor)
     }
 
     pub(crate) fn note_input_activity(&mut self) {
@@ -195,92 +243,3 @@ impl Window {
         self.cursor_blink_at = Instant::now();
     }
 }
-
-fn flush_diff(out: &mut impl io::Write, prev: &CellBuffer, current: &CellBuffer) -> io::Result<()> {
-    let mut style_emitter = StyleEmitter::default();
-    for run in current.diff_runs(prev) {
-        style_emitter.apply(out, run.style)?;
-        crossterm::queue!(out, cursor::MoveTo(run.x, run.y), Print(run.text))?;
-    }
-
-    style_emitter.reset(out)
-}
-
-
-#[derive(Default)]
-struct StyleEmitter {
-    current: CellStyle,
-}
-
-impl StyleEmitter {
-    fn apply(&mut self, out: &mut impl io::Write, target: CellStyle) -> io::Result<()> {
-        if self.current == target {
-            return Ok(());
-        }
-
-        let attrs_changed = self.current.bold != target.bold
-            || self.current.italic != target.italic
-            || self.current.underline != target.underline
-            || self.current.strikethrough != target.strikethrough;
-
-        if attrs_changed {
-            crossterm::queue!(out, SetAttribute(Attribute::Reset))?;
-        }
-
-        if attrs_changed || self.current.fg != target.fg {
-            if let Some(color) = target.fg {
-                crossterm::queue!(
-                    out,
-                    SetForegroundColor(TermColor::Rgb {
-                        r: color.r,
-                        g: color.g,
-                        b: color.b,
-                    })
-                )?;
-            } else {
-                crossterm::queue!(out, SetForegroundColor(TermColor::Reset))?;
-            }
-        }
-
-        if attrs_changed || self.current.bg != target.bg {
-            if let Some(bg) = target.bg {
-                crossterm::queue!(
-                    out,
-                    SetBackgroundColor(TermColor::Rgb {
-                        r: bg.r,
-                        g: bg.g,
-                        b: bg.b,
-                    })
-                )?;
-            } else {
-                crossterm::queue!(out, SetBackgroundColor(TermColor::Reset))?;
-            }
-        }
-
-        if attrs_changed {
-            if target.bold {
-                crossterm::queue!(out, SetAttribute(Attribute::Bold))?;
-            }
-            if target.italic {
-                crossterm::queue!(out, SetAttribute(Attribute::Italic))?;
-            }
-            if target.underline {
-                crossterm::queue!(out, SetAttribute(Attribute::Underlined))?;
-            }
-            if target.strikethrough {
-                crossterm::queue!(out, SetAttribute(Attribute::CrossedOut))?;
-            }
-        }
-
-        self.current = target;
-        Ok(())
-    }
-
-    fn reset(&mut self, out: &mut impl io::Write) -> io::Result<()> {
-        if self.current != CellStyle::default() {
-            self.current = CellStyle::default();
-            crossterm::queue!(out, SetAttribute(Attribute::Reset), ResetColor)?;
-        }
-        Ok(())
-    }
-}
@@ -0,0 +1,154 @@
+use crate::chat::{ChatMessage, Role};
+use crate::copy::{self, CopyFormat};
+use crate::table;
+
+/// Something that can be done to a message in the history, from its context
+/// menu or with its key while the message is focused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MessageAction {
+    Edit,
+    Regenerate,
+    Bookmark,
+    Note,
+    Fork,
+    Expand,
+    /// Put the message's code blocks on the clipboard.
+    Copy(CopyFormat),
+    /// Show its CSV and TSV blocks as tables, or as text again.
+    Table,
+    SortTable,
+    FilterTable,
+    /// Show the next page of a long table, or the previous one.
+    TablePage {
+        forward: bool,
+    },
+    /// Put the message's whole text on the clipboard, or an error card's.
+    CopyText,
+    /// Send the prompt above a failed one again.
+    Retry,
+}
+
+impl MessageAction {
+    const ALL: [Self; 16] = [
+        Self::Retry,
+        Self::Expand,
+        Self::CopyText,
+        Self::Edit,
+        Self::Regenerate,
+        Self::Copy(CopyFormat::Raw),
+        Self::Copy(CopyFormat::Fenced),
+        Self::Copy(CopyFormat::Heredoc),
+        Self::Table,
+        Self::SortTable,
+        Self::FilterTable,
+        Self::TablePage { forward: true },
+        Self::TablePage { forward: false },
+        Self::Bookmark,
+        Self::Note,
+        Self::Fork,
+    ];
+
+    pub(crate) fn key(self) -> char {
+        match self {
+            Self::Edit => 'e',
+            Self::Regenerate => 'r',
+            Self::Bookmark => 'm',
+            Self::Note => 'n',
+            Self::Fork => 'f',
+            Self::Expand => 'x',
+            Self::Copy(CopyFormat::Raw) => 'c',
+            Self::Copy(CopyFormat::Fenced) => 'C',
+            Self::Copy(CopyFormat::Heredoc) => 'h',
+            Self::Table => 't',
+            Self::SortTable => 's',
+            Self::FilterTable => '/',
+            Self::TablePage { forward: true } => ']',
+            Self::TablePage { forward: false } => '[',
+            Self::CopyText => 'y',
+            Self::Retry => 'r',
+        }
+    }
+
+    fn label(self, message: &ChatMessage) -> &'static str {
+        match self {
+            Self::Edit => "Edit and resend",
+            Self::Regenerate => "Regenerate reply",
+            Self::Bookmark if message.bookmarked => "Remove bookmark",
+            Self::Bookmark => "Bookmark",
+            Self::Note if message.note.is_some() => "Edit note",
+            Self::Note => "Add note",
+            Self::Fork => "Fork from here",
+            Self::Expand if message.expanded => "Fold",
+            Self::Expand => "Show all",
+            Self::Copy(format) => format.label(),
+            Self::Table if message.table.is_some() => "Show tables as text",
+            Self::Table => "Show as table",
+            Self::SortTable => "Sort table by next column",
+            Self::FilterTable => "Filter table rows",
+            Self::TablePage { forward: true } => "Next table page",
+            Self::TablePage { forward: false } => "Previous table page",
+            Self::CopyText if message.failure.is_some() => "Copy error",
+            Self::CopyText => "Copy message",
+            Self::Retry => "Retry",
+        }
+    }
+
+    fn applies_to(self, message: &ChatMessage) -> bool {
+        match self {
+            Self::Edit => message.role == Role::User,
+            Self::Regenerate => message.role == Role::Assistant,
+            Self::Expand => {
+                matches!(message.role, Role::Context | Role::ToolResult)
+                    || message.failure.is_some()
+            }
+            Self::Copy(_) => !copy::code_blocks(&message.text).is_empty(),
+            Self::Table => !table::find(&message.text).is_empty(),
+            Self::SortTable | Self::FilterTable => message.table.is_some(),
+            Self::TablePage { forward } => message.table.as_ref().is_some_and(|view| {
+                if forward {
+                    view.page + 1 < table::page_count(&message.text, view)
+                } else {
+                    view.page > 0
+                }
+            }),
+            Self::Retry => message.failure.is_some_and(|failure| failure.retry),
+            Self::CopyText | Self::Bookmark | Self::Note | Self::Fork => true,
+        }
+    }
+
+    /// The action bound to `key` on `message`, if any.
+    pub(crate) fn for_key(key: char, message: &ChatMessage) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.key() == key && action.applies_to(message))
+    }
+}
+
+/// The context menu of message `index`: its actions, in menu order.
+pub(crate) struct MessageMenu {
+    pub(crate) index: usize,
+    actions: Vec<MessageAction>,
+    pub(crate) menu: xpui::ContextMenuState,
+}
+
+impl MessageMenu {
+    pub(crate) fn open(index: usize, message: &ChatMessage, title: String) -> Self {
+        let actions = MessageAction::ALL
+            .into_iter()
+            .filter(|action| action.applies_to(message))
+            .collect::<Vec<_>>();
+        let items = actions
+            .iter()
+            .map(|action| xpui::MenuItem::new(action.label(message)).key(action.key()))
+            .collect();
+        Self {
+            index,
+            actions,
+            menu: xpui::ContextMenuState::new(title, items),
+        }
+    }
+
+    pub(crate) fn action(&self, item: usize) -> Option<MessageAction> {
+        self.actions.get(item).copied()
+    }
+}
@@ -0,0 +1,118 @@
+/// Columns the activity bar takes: an icon, its badge and a gap.
+pub(crate) const BAR_WIDTH: u16 = 3;
+/// Lines each entry of the bar takes, the gap below it included.
+const ENTRY_LINES: u16 = 2;
+
+/// The panels the activity bar on the left switches the main area between.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Activity {
+    Chat,
+    Files,
+    Search,
+    /// What is running: the reply, queued prompts, drafts, watches.
+    Tasks,
+    Settings,
+}
+
+impl Activity {
+    /// In the order the bar shows them, top down.
+    pub(crate) const ALL: [Self; 5] = [
+        Self::Chat,
+        Self::Files,
+        Self::Search,
+        Self::Tasks,
+        Self::Settings,
+    ];
+
+    pub(crate) fn title(self) -> &'static str {
+        match self {
+            Self::Chat => "Chat",
+            Self::Files => "Files",
+            Self::Search => "Search",
+            Self::Tasks => "Tasks",
+            Self::Settings => "Settings",
+        }
+    }
+
+    fn icon(self) -> xpui::IconName {
+        match self {
+            Self::Chat => xpui::IconName::Chat,
+            Self::Files => xpui::IconName::Files,
+            Self::Search => xpui::IconName::Search,
+            Self::Tasks => xpui::IconName::Tasks,
+            Self::Settings => xpui::IconName::Settings,
+        }
+    }
+
+    /// Alt and this digit shows the panel.
+    pub(crate) fn shortcut(self) -> u8 {
+        Self::ALL
+            .iter()
+            .position(|activity| *activity == self)
+            .unwrap_or(0) as u8
+            + 1
+    }
+
+    /// The panel Alt and `place` shows.
+    pub(crate) fn from_shortcut(place: u8) -> Option<Self> {
+        Self::ALL.get(usize::from(place).checked_sub(1)?).copied()
+    }
+
+    /// The panel whose entry is on line `row` of the bar.
+    pub(crate) fn at_row(row: u16) -> Option<Self> {
+        let activity = Self::ALL.get(usize::from(row / ENTRY_LINES))?;
+        row.is_multiple_of(ENTRY_LINES).then_some(*activity)
+    }
+}
+
+/// A count as a one-column badge: nothing for none, `+` past nine.
+pub(crate) fn badge(count: usize) -> Option<char> {
+    match count {
+        0 => None,
+        1..=9 => char::from_digit(count as u32, 10),
+        _ => Some('+'),
+    }
+}
+
+/// The bar: one icon per panel with its badge, `active` highlighted.
+pub(crate) fn bar(active: Activity, badges: impl Fn(Activity) -> Option<char>) -> xpui::Node {
+    let theme = xpui::theme();
+    let mut column = xpui::column().gap(1);
+    for activity in Activity::ALL {
+        let color = if activity == active {
+            theme.accent
+        } else {
+            theme.muted
+        };
+        let badge = badges(activity).unwrap_or(' ');
+        let entry = xpui::row()
+            .child(xpui::icon(activity.icon()).color(color))
+            .child(xpui::text(format!("{badge} ")));
+        column = column
+            .child(xpui::container(entry).style(xpui::BoxStyle::default().text_color(theme.badge)));
+    }
+    xpui::IntoNode::into_node(column.key("activity-bar"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panels_are_reached_by_shortcut_and_row() {
+        assert_eq!(Activity::from_shortcut(1), Some(Activity::Chat));
+        assert_eq!(Activity::from_shortcut(4), Some(Activity::Tasks));
+        assert_eq!(Activity::from_shortcut(0), None);
+        assert_eq!(Activity::from_shortcut(6), None);
+        assert_eq!(Activity::Settings.shortcut(), 5);
+
+        assert_eq!(Activity::at_row(0), Some(Activity::Chat));
+        assert_eq!(Activity::at_row(1), None);
+        assert_eq!(Activity::at_row(4), Some(Activity::Search));
+        assert_eq!(Activity::at_row(10), None);
+
+        assert_eq!(badge(0), None);
+        assert_eq!(badge(3), Some('3'));
+        assert_eq!(badge(12), Some('+'));
+    }
+}
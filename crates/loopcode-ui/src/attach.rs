@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::provider::RequestImage;
+
+/// An image sent with a prompt. The bytes live in a file named by their hash
+/// so the session journal only records where to find them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ImageAttachment {
+    pub(crate) path: PathBuf,
+    pub(crate) mime_type: String,
+    pub(crate) size: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) dimensions: Option<(u32, u32)>,
+}
+
+impl ImageAttachment {
+    /// Saves a pasted image under `loopcode/images` in the data directory.
+    pub(crate) fn store(image: &xpui::ClipboardImage) -> Result<Self, String> {
+        let dir = dirs::data_dir()
+            .ok_or("no data directory to keep pasted images in")?
+            .join("loopcode")
+            .join("images");
+        let hash = Sha256::digest(&image.bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        let extension = image.mime_type.trim_start_matches("image/");
+        let path = dir.join(format!("{hash}.{extension}"));
+        fs::create_dir_all(&dir)
+            .and_then(|()| fs::write(&path, &image.bytes))
+            .map_err(|err| format!("cannot save pasted image to {}: {err}", path.display()))?;
+        Ok(Self {
+            path,
+            mime_type: image.mime_type.to_string(),
+            size: image.bytes.len(),
+            dimensions: dimensions(&image.bytes),
+        })
+    }
+
+    /// The image as sent to the model; `None` when its file is gone.
+    pub(crate) fn load(&self) -> Option<RequestImage> {
+        Some(RequestImage {
+            mime_type: self.mime_type.clone(),
+            data: fs::read(&self.path).ok()?,
+        })
+    }
+
+    /// What the chip above the input and the message in history show, like
+    /// `png 640×480 · 34 KB`.
+    pub(crate) fn label(&self) -> String {
+        let format = self.mime_type.trim_start_matches("image/");
+        let size = if self.size < 1024 * 1024 {
+            format!("{} KB", self.size.div_ceil(1024))
+        } else {
+            format!("{:.1} MB", self.size as f64 / (1024.0 * 1024.0))
+        };
+        match self.dimensions {
+            Some((width, height)) => format!("{format} {width}×{height} · {size}"),
+            None => format!("{format} · {size}"),
+        }
+    }
+}
+
+/// Width and height from a PNG or GIF header; other formats go without.
+fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let le = |at: usize| {
+        Some(u32::from(u16::from_le_bytes(
+            bytes.get(at..at + 2)?.try_into().ok()?,
+        )))
+    };
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some((be(16)?, be(20)?))
+    } else if bytes.starts_with(b"GIF8") {
+        Some((le(6)?, le(8)?))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_read_the_image_header() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(dimensions(&png), Some((640, 480)));
+        assert_eq!(dimensions(b"GIF89a\x10\0\x08\0"), Some((16, 8)));
+        assert_eq!(dimensions(b"\xff\xd8\xff"), None);
+
+        let image = ImageAttachment {
+            path: PathBuf::new(),
+            mime_type: "image/png".to_string(),
+            size: 34 * 1024,
+            dimensions: Some((640, 480)),
+        };
+        assert_eq!(image.label(), "png 640×480 · 34 KB");
+    }
+}
@@ -0,0 +1,91 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::chat::ChatMessage;
+
+pub(crate) enum PickerOutcome {
+    Pending,
+    Close,
+    Jump(usize),
+}
+
+/// Jump list over the bookmarked messages, shown in place of the history.
+pub(crate) struct BookmarkPicker {
+    entries: Vec<usize>,
+    selected: usize,
+}
+
+impl BookmarkPicker {
+    pub(crate) fn open(messages: &[ChatMessage]) -> Self {
+        let entries = messages
+            .iter()
+            .enumerate()
+            .filter(|(_, message)| message.bookmarked)
+            .map(|(index, _)| index)
+            .collect();
+        Self {
+            entries,
+            selected: 0,
+        }
+    }
+
+    pub(crate) fn handle_key(&mut self, key: xpui::UiKeyInput) -> PickerOutcome {
+        match key {
+            xpui::UiKeyInput::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                PickerOutcome::Pending
+            }
+            xpui::UiKeyInput::Down => {
+                self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+                PickerOutcome::Pending
+            }
+            xpui::UiKeyInput::Enter | xpui::UiKeyInput::Submit => self
+                .entries
+                .get(self.selected)
+                .map_or(PickerOutcome::Close, |index| PickerOutcome::Jump(*index)),
+            xpui::UiKeyInput::Esc | xpui::UiKeyInput::Char('\'') => PickerOutcome::Close,
+            _ => PickerOutcome::Pending,
+        }
+    }
+
+    /// One line per bookmark, each cut to `width` columns.
+    pub(crate) fn render(&self, messages: &[ChatMessage], width: usize) -> String {
+        if self.entries.is_empty() {
+            return "No bookmarks yet. Press m on a message to add one.".to_string();
+        }
+
+        let mut out = String::from("Bookmarks · Enter jump · Esc close");
+        for (row, index) in self.entries.iter().enumerate() {
+            let Some(message) = messages.get(*index) else {
+                continue;
+            };
+            let marker = if row == self.selected { "▶" } else { " " };
+            let first_line = message.text.lines().next().unwrap_or_default();
+            let line = format!(
+                "{marker} #{} {}: {first_line}",
+                index + 1,
+                message.role.label()
+            );
+            out.push('\n');
+            out.push_str(&truncate(&line, width));
+        }
+        out
+    }
+}
+
+pub(crate) fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut col = 0usize;
+    for ch in text.chars() {
+        let w = UnicodeWidthChar::width(ch).unwrap_or(0);
+        if col + w > width.saturating_sub(1) {
+            out.push('…');
+            return out;
+        }
+        out.push(ch);
+        col += w;
+    }
+    out
+}
@@ -0,0 +1,63 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+/// On-disk store for derived artifacts that are costly to rebuild but can
+/// always be rebuilt, such as highlighted code blocks. Entries are JSON files
+/// named by a hash of everything they were derived from, so stale entries are
+/// never read, only left behind.
+///
+/// Every failure is treated as a miss: losing the cache costs time, not data.
+pub(crate) struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Opens the cache for one kind of artifact. Bump the version in `kind`
+    /// whenever the format or the code producing it changes.
+    pub(crate) fn open(kind: &str) -> Option<Self> {
+        let dir = dirs::cache_dir()?.join("loopcode").join(kind);
+        fs::create_dir_all(&dir).ok()?;
+        Some(Self { dir })
+    }
+
+    /// Hashes `parts` into a key. Parts are length-prefixed so that
+    /// `["ab", "c"]` and `["a", "bc"]` differ.
+    pub(crate) fn key(parts: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    pub(crate) fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let bytes = fs::read(self.path(key)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    pub(crate) fn put<T: Serialize>(&self, key: &str, value: &T) {
+        let _ = self.write(key, value);
+    }
+
+    fn write<T: Serialize>(&self, key: &str, value: &T) -> io::Result<()> {
+        let json = serde_json::to_vec(value).map_err(io::Error::other)?;
+        // Write then rename so a concurrent reader never sees half an entry.
+        let tmp = self.dir.join(format!("{key}.{}.tmp", std::process::id()));
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, self.path(key))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
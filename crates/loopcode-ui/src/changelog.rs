@@ -0,0 +1,118 @@
+use crate::state::AppState;
+
+/// Release notes, newest first, bundled so they match the binary.
+const CHANGELOG: &str = include_str!("../../../CHANGELOG.md");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+struct Release {
+    version: &'static str,
+    notes: &'static str,
+}
+
+/// The `## <version>` sections of the changelog, in file order.
+fn releases() -> impl Iterator<Item = Release> {
+    CHANGELOG.split("\n## ").skip(1).map(|section| {
+        let (version, notes) = section.split_once('\n').unwrap_or((section, ""));
+        Release {
+            version: version.trim(),
+            notes: notes.trim(),
+        }
+    })
+}
+
+/// `1.10.0` sorts after `1.9.0`; anything unparsable sorts first.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+fn render(releases: &[Release]) -> String {
+    releases
+        .iter()
+        .map(|release| format!("{}\n{}", release.version, release.notes))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// The whole changelog, for `/changelog`.
+pub(crate) fn text() -> String {
+    render(&releases().collect::<Vec<_>>())
+}
+
+/// Notes for the releases since the one last shown, once per upgrade, and
+/// marks this version as shown. A first run records the version without
+/// showing anything: everything is new then.
+pub(crate) fn take_unseen() -> Option<WhatsNew> {
+    let mut state = AppState::load();
+    let seen = state.changelog_seen.replace(VERSION.to_string());
+    if seen.as_deref() != Some(VERSION) {
+        state.save();
+    }
+    let seen = version_key(&seen?);
+    let current = version_key(VERSION);
+    let unseen = releases()
+        .filter(|release| {
+            let key = version_key(release.version);
+            key > seen && key <= current
+        })
+        .collect::<Vec<_>>();
+    if unseen.is_empty() {
+        return None;
+    }
+    Some(WhatsNew {
+        text: render(&unseen),
+        scroll: 0,
+    })
+}
+
+/// One-time "What's new" screen, shown in place of the history.
+pub(crate) struct WhatsNew {
+    text: String,
+    scroll: u16,
+}
+
+impl WhatsNew {
+    /// Returns `true` when the screen should close.
+    pub(crate) fn handle_event(&mut self, event: xpui::UiInputEvent) -> bool {
+        match event {
+            xpui::UiInputEvent::Key(
+                xpui::UiKeyInput::Esc | xpui::UiKeyInput::Enter | xpui::UiKeyInput::Submit,
+            ) => return true,
+            xpui::UiInputEvent::Key(xpui::UiKeyInput::Up) => self.scroll(-1),
+            xpui::UiInputEvent::Key(xpui::UiKeyInput::Down) => self.scroll(1),
+            xpui::UiInputEvent::ScrollLines(lines) => self.scroll(lines),
+            _ => {}
+        }
+        false
+    }
+
+    fn scroll(&mut self, lines: i16) {
+        let max = self.text.lines().count() as u16;
+        self.scroll = self.scroll.saturating_add_signed(lines).min(max);
+    }
+
+    pub(crate) fn render(&self) -> (String, u16) {
+        let body = format!(
+            "What's new in loopcode {VERSION} · Enter/Esc close · /changelog shows it again\n\n{}",
+            self.text
+        );
+        (body, self.scroll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_changelog_covers_this_version() {
+        let versions = releases()
+            .map(|release| release.version)
+            .collect::<Vec<_>>();
+        assert_eq!(versions.first(), Some(&VERSION));
+        assert!(releases().all(|release| !release.notes.is_empty()));
+        assert!(version_key("0.10.0") > version_key("0.9.1"));
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+
+/// Batches streamed text so the history, and everything derived from it, is
+/// updated at most a fixed number of times per second instead of once per
+/// chunk.
+pub(super) struct StreamCoalescer {
+    pending: String,
+    /// `None` passes every chunk straight through.
+    interval: Option<Duration>,
+    /// `None` until the first flush, so the first chunk shows right away.
+    last_flush: Option<Instant>,
+}
+
+impl StreamCoalescer {
+    pub(super) fn new(max_per_second: u32) -> Self {
+        Self {
+            pending: String::new(),
+            interval: (max_per_second > 0).then(|| Duration::from_secs(1) / max_per_second),
+            last_flush: None,
+        }
+    }
+
+    pub(super) fn push(&mut self, delta: &str) {
+        self.pending.push_str(delta);
+    }
+
+    /// Returns the batched text once the interval since the last flush has
+    /// passed.
+    pub(super) fn take_due(&mut self) -> Option<String> {
+        let due = match (self.interval, self.last_flush) {
+            (Some(interval), Some(last)) => last.elapsed() >= interval,
+            _ => true,
+        };
+        if due { self.take() } else { None }
+    }
+
+    /// Returns whatever is batched regardless of the rate limit.
+    pub(super) fn take(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        self.last_flush = Some(Instant::now());
+        Some(std::mem::take(&mut self.pending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_chunks_until_the_interval_passes() {
+        let mut coalescer = StreamCoalescer::new(1);
+        coalescer.push("a");
+        assert_eq!(coalescer.take_due(), Some("a".to_string()));
+        coalescer.push("b");
+        coalescer.push("c");
+        assert_eq!(coalescer.take_due(), None);
+        assert_eq!(coalescer.take(), Some("bc".to_string()));
+        assert_eq!(coalescer.take(), None);
+    }
+}
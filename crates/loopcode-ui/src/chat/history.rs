@@ -0,0 +1,534 @@
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::attach::ImageAttachment;
+use crate::failure::Failure;
+use crate::session::{JournalRecord, SessionJournal};
+use crate::table::{self, TableView};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Role {
+    User,
+    Assistant,
+    System,
+    /// A tool call the model asked for: the tool's name, then its arguments.
+    Tool,
+    /// What the tool call above it gave back; shown folded.
+    ToolResult,
+    /// Material attached for the model, such as an issue; sent as part of
+    /// the conversation and shown folded.
+    Context,
+}
+
+impl Role {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::User => "you",
+            Self::Assistant => "assistant",
+            Self::System => "system",
+            Self::Tool => "tool",
+            Self::ToolResult => "result",
+            Self::Context => "context",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MessageStatus {
+    Done,
+    Streaming,
+    Queued,
+    Interrupted,
+    /// Recovered from a session that ended mid-stream.
+    Truncated,
+    /// A tool call waiting for the user to approve it.
+    Awaiting,
+}
+
+/// Tells a message apart from every other this run, wherever paging and
+/// inserts move it in the history. Not saved; a message read back gets a
+/// fresh one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct MessageId(u64);
+
+impl MessageId {
+    fn fresh() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct ChatMessage {
+    #[serde(skip, default = "MessageId::fresh")]
+    pub(crate) id: MessageId,
+    pub(crate) role: Role,
+    pub(crate) text: String,
+    pub(crate) status: MessageStatus,
+    /// Private note from the user. Never sent to the model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) note: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) bookmarked: bool,
+    /// Header for the agent run this prompt started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) run: Option<RunSummary>,
+    /// Whether a context message shows in full rather than its first line.
+    #[serde(skip)]
+    pub(crate) expanded: bool,
+    /// How CSV and TSV blocks show as tables; `None` shows them as text.
+    #[serde(skip)]
+    pub(crate) table: Option<TableView>,
+    /// Images pasted into the prompt.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) images: Vec<ImageAttachment>,
+    /// Set on an error shown as a card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) failure: Option<Failure>,
+    /// The API's id for the tool call a [`Role::Tool`] message shows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) call_id: Option<String>,
+}
+
+/// Wall-clock time and activity of one agent run, from sending the prompt to
+/// the end of the reply.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct RunSummary {
+    pub(crate) elapsed_ms: u64,
+    pub(crate) tool_calls: usize,
+    pub(crate) files: Vec<String>,
+    pub(crate) finished: bool,
+    /// Seconds since the stream last produced anything, once that is long
+    /// enough to be worth showing. Only meaningful while the run is live.
+    #[serde(skip)]
+    pub(crate) idle_secs: Option<u64>,
+}
+
+impl RunSummary {
+    pub(crate) fn label(&self) -> String {
+        // Live runs tick in whole seconds so the header does not redraw on
+        // every frame.
+        let elapsed = if self.finished {
+            format!("{:.1}s", self.elapsed_ms as f64 / 1000.0)
+        } else {
+            format!("{}s", self.elapsed_ms / 1000)
+        };
+        let mut label = format!(
+            "⏱ {elapsed} · {} · {}",
+            plural(self.tool_calls, "tool call"),
+            plural(self.files.len(), "file")
+        );
+        if let Some(idle) = self.idle_secs {
+            label.push_str(&format!(" · idle {idle}s"));
+        } else if !self.finished {
+            label.push_str(" · running");
+        }
+        label
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+impl ChatMessage {
+    pub(crate) fn new(role: Role, text: impl Into<String>) -> Self {
+        Self {
+            id: MessageId::fresh(),
+            role,
+            text: text.into(),
+            status: MessageStatus::Done,
+            note: None,
+            bookmarked: false,
+            run: None,
+            expanded: false,
+            table: None,
+            images: Vec::new(),
+            failure: None,
+            call_id: None,
+        }
+    }
+
+    pub(crate) fn with_status(mut self, status: MessageStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub(crate) fn display_text(&self) -> String {
+        let mut text = self.body_text();
+        if self.bookmarked {
+            text.insert_str(0, "★ ");
+        }
+        for image in &self.images {
+            text.push_str("\n▣ ");
+            text.push_str(&image.label());
+        }
+        if let Some(run) = &self.run {
+            text.push('\n');
+            text.push_str(&run.label());
+        }
+        if let Some(note) = &self.note {
+            for line in note.lines() {
+                text.push_str("\n✎ ");
+                text.push_str(line);
+            }
+        }
+        text
+    }
+
+    fn body_text(&self) -> String {
+        if let Some(failure) = &self.failure {
+            let header = failure.header(&self.text, self.expanded);
+            return match self.text.split_once('\n') {
+                Some((_, rest)) if self.expanded => format!("{header}\n{rest}"),
+                _ => header,
+            };
+        }
+        let text = self.shown_text();
+        match self.status {
+            MessageStatus::Done => format!("{}: {}", self.role.label(), text),
+            MessageStatus::Streaming => format!("{}: {}▍", self.role.label(), text),
+            MessageStatus::Queued => format!("{} (queued): {}", self.role.label(), text),
+            MessageStatus::Interrupted => {
+                format!("{}: {} [interrupted]", self.role.label(), text)
+            }
+            MessageStatus::Truncated => {
+                format!("{}: {} [truncated]", self.role.label(), text)
+            }
+            MessageStatus::Awaiting => {
+                format!("{}: {} · waiting for approval", self.role.label(), text)
+            }
+        }
+    }
+
+    /// The text, or for a folded context or tool result message its first
+    /// line and how much is hidden. Tables in it are drawn as such, or
+    /// offered to be.
+    fn shown_text(&self) -> Cow<'_, str> {
+        if !matches!(self.role, Role::Context | Role::ToolResult) || self.expanded {
+            let tables = table::find(&self.text);
+            return match &self.table {
+                _ if tables.is_empty() => Cow::Borrowed(&self.text),
+                Some(view) => Cow::Owned(table::render(&self.text, &tables, view)),
+                None if self.status == MessageStatus::Done => Cow::Owned(format!(
+                    "{}\n▦ {} · t shows as a table",
+                    self.text,
+                    plural(tables.len(), "table")
+                )),
+                None => Cow::Borrowed(&self.text),
+            };
+        }
+        let mut lines = self.text.lines();
+        let first = lines.next().unwrap_or_default();
+        match lines.count() {
+            0 => Cow::Borrowed(first),
+            hidden => Cow::Owned(format!("{first} ▸ {hidden} more lines")),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum HistoryEvent {
+    UserAppended,
+    Streamed,
+    Reset,
+    /// This many older messages were paged in at the front.
+    Prepended(usize),
+    /// This many of the oldest messages were dropped from memory; they stay
+    /// on disk.
+    PagedOut(usize),
+}
+
+pub(crate) struct ChatHistory {
+    messages: xpui::signal::VecSignal<ChatMessage>,
+    events: xpui::signal::EventSignal<HistoryEvent>,
+    journal: RefCell<Option<SessionJournal>>,
+    /// Messages of the session that are on disk but not in `messages`, which
+    /// holds the ones after them. Journal records are written with absolute
+    /// indices, so they are offset by this.
+    paged_out: Cell<usize>,
+}
+
+impl ChatHistory {
+    pub(crate) fn new(
+        initial: Vec<ChatMessage>,
+        events: xpui::signal::EventSignal<HistoryEvent>,
+        journal: Option<SessionJournal>,
+        paged_out: usize,
+    ) -> Self {
+        Self {
+            messages: xpui::signal::VecSignal::from(initial),
+            events,
+            journal: RefCell::new(journal),
+            paged_out: Cell::new(paged_out),
+        }
+    }
+
+    pub(crate) fn append_user(
+        &self,
+        text: String,
+        images: Vec<ImageAttachment>,
+        status: MessageStatus,
+    ) -> usize {
+        let message = ChatMessage {
+            images,
+            ..ChatMessage::new(Role::User, text).with_status(status)
+        };
+        let index = self.messages.update(|items| {
+            items.push(message.clone());
+            items.len() - 1
+        });
+        self.record(JournalRecord::Insert { index, message });
+        self.events.emit(HistoryEvent::UserAppended);
+        index
+    }
+
+    pub(crate) fn insert(&self, index: usize, message: ChatMessage) {
+        let index = self.messages.update(|items| {
+            let index = index.min(items.len());
+            items.insert(index, message.clone());
+            index
+        });
+        self.record(JournalRecord::Insert { index, message });
+        self.events.emit(HistoryEvent::Streamed);
+    }
+
+    /// Appends the demo conversation a fresh session opens with.
+    pub(crate) fn insert_demo(&self) {
+        for message in [
+            ChatMessage::new(Role::Assistant, "안녕하세요! 무엇을 도와드릴까요?"),
+            ChatMessage::new(Role::User, "포커스 트리 네비게이션을 개선하고 싶어요."),
+            ChatMessage::new(
+                Role::Assistant,
+                "좋아요. Enter로 하위 진입, Esc로 상위 복귀 모델로 가죠.",
+            ),
+        ] {
+            self.insert(self.len(), message);
+        }
+    }
+
+    /// Every delta is journaled as it is applied, so a reply cut off by a
+    /// crash can be recovered up to the last batch shown.
+    pub(crate) fn append_delta(&self, index: usize, delta: &str) {
+        self.messages.update(|items| {
+            if let Some(message) = items.get_mut(index) {
+                message.text.push_str(delta);
+            }
+        });
+        self.record(JournalRecord::Delta {
+            index,
+            text: delta.to_string(),
+        });
+        self.events.emit(HistoryEvent::Streamed);
+    }
+
+    /// Replaces a message's whole text, as when a pending tool call is
+    /// edited before it runs.
+    pub(crate) fn set_text(&self, index: usize, text: String) {
+        self.messages.update(|items| {
+            if let Some(message) = items.get_mut(index) {
+                message.text = text.clone();
+            }
+        });
+        self.record(JournalRecord::Rewrite { index, text });
+    }
+
+    pub(crate) fn set_status(&self, index: usize, status: MessageStatus) {
+        self.messages.update(|items| {
+            if let Some(message) = items.get_mut(index) {
+                message.status = status;
+            }
+        });
+        self.record(JournalRecord::Status { index, status });
+    }
+
+    /// Attaches a private note to a message; `None` removes it.
+    pub(crate) fn set_note(&self, index: usize, note: Option<String>) {
+        self.messages.update(|items| {
+            if let Some(message) = items.get_mut(index) {
+                message.note = note.clone();
+            }
+        });
+        self.record(JournalRecord::Annotate { index, note });
+    }
+
+    pub(crate) fn set_bookmarked(&self, index: usize, bookmarked: bool) {
+        self.messages.update(|items| {
+            if let Some(message) = items.get_mut(index) {
+                message.bookmarked = bookmarked;
+            }
+        });
+        self.record(JournalRecord::Bookmark { index, bookmarked });
+    }
+
+    /// Unfolds or folds a context message. Not journaled; resumed sessions
+    /// show context folded.
+    pub(crate) fn set_expanded(&self, index: usize, expanded: bool) {
+        self.messages.update(|items| {
+            if let Some(message) = items.get_mut(index) {
+                message.expanded = expanded;
+            }
+        });
+    }
+
+    /// Shows the tables of a message as tables or as text. Not journaled.
+    pub(crate) fn set_table(&self, index: usize, view: Option<TableView>) {
+        self.messages.update(|items| {
+            if let Some(message) = items.get_mut(index) {
+                message.table = view;
+            }
+        });
+    }
+
+    pub(crate) fn set_run(&self, index: usize, run: RunSummary) {
+        self.messages.update(|items| {
+            if let Some(message) = items.get_mut(index) {
+                message.run = Some(run.clone());
+            }
+        });
+        self.record(JournalRecord::Run { index, run });
+    }
+
+    pub(crate) fn first_queued(&self) -> Option<usize> {
+        self.messages
+            .borrow()
+            .iter()
+            .position(|message| message.status == MessageStatus::Queued)
+    }
+
+    pub(crate) fn queued_count(&self) -> usize {
+        self.messages
+            .borrow()
+            .iter()
+            .filter(|message| message.status == MessageStatus::Queued)
+            .count()
+    }
+
+    pub(crate) fn reset_to_index(&self, index: usize) {
+        let truncated = self.messages.update(|items| {
+            if let Some(keep) = index.checked_add(1)
+                && keep < items.len()
+            {
+                items.truncate(keep);
+                return Some(keep);
+            }
+            None
+        });
+        if let Some(len) = truncated {
+            self.record(JournalRecord::Truncate { len });
+        }
+        self.events.emit(HistoryEvent::Reset);
+    }
+
+    /// Swaps in another session's messages and journal.
+    pub(crate) fn replace(
+        &self,
+        messages: Vec<ChatMessage>,
+        journal: SessionJournal,
+        paged_out: usize,
+    ) {
+        self.messages.set(messages);
+        *self.journal.borrow_mut() = Some(journal);
+        self.paged_out.set(paged_out);
+        self.events.emit(HistoryEvent::Reset);
+    }
+
+    /// Puts messages read back from disk in front of the resident ones. This
+    /// shifts every index, so callers holding indices must move them too.
+    pub(crate) fn prepend(&self, older: Vec<ChatMessage>) {
+        let count = older.len().min(self.paged_out.get());
+        if count == 0 {
+            return;
+        }
+        let skip = older.len() - count;
+        self.messages.update(|items| {
+            items.splice(0..0, older.into_iter().skip(skip));
+        });
+        self.paged_out.set(self.paged_out.get() - count);
+        self.events.emit(HistoryEvent::Prepended(count));
+    }
+
+    /// Drops the oldest messages until at most `keep` are resident, leaving
+    /// message `pinned` and those after it. Only a journaled history can read
+    /// them back, so an unsaved one keeps everything. Returns how many went;
+    /// every index moves down by that many.
+    pub(crate) fn page_out(&self, keep: usize, pinned: usize) -> usize {
+        if self.journal.borrow().is_none() {
+            return 0;
+        }
+        let count = self.len().saturating_sub(keep).min(pinned);
+        if count == 0 {
+            return 0;
+        }
+        self.messages.update(|items| {
+            items.drain(..count);
+        });
+        self.paged_out.set(self.paged_out.get() + count);
+        self.events.emit(HistoryEvent::PagedOut(count));
+        count
+    }
+
+    /// Where the message with `id` is now, if it is still resident.
+    pub(crate) fn index_of(&self, id: MessageId) -> Option<usize> {
+        self.messages
+            .borrow()
+            .iter()
+            .position(|message| message.id == id)
+    }
+
+    pub(crate) fn paged_out(&self) -> usize {
+        self.paged_out.get()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub(crate) fn version(&self) -> u64 {
+        self.messages.version()
+    }
+
+    pub(crate) fn borrow(&self) -> std::cell::Ref<'_, Vec<ChatMessage>> {
+        self.messages.borrow()
+    }
+
+    /// Runs `f` on the journal of the session, if it is saved.
+    pub(crate) fn with_journal<T>(&self, f: impl FnOnce(&SessionJournal) -> T) -> Option<T> {
+        self.journal.borrow().as_ref().map(f)
+    }
+
+    pub(crate) fn with_journal_mut<T>(
+        &self,
+        f: impl FnOnce(&mut SessionJournal) -> T,
+    ) -> Option<T> {
+        self.journal.borrow_mut().as_mut().map(f)
+    }
+
+    /// Writes a record to the session journal. On failure the journal is
+    /// dropped and a notice is added, since later records would not replay
+    /// correctly without this one.
+    fn record(&self, record: JournalRecord) {
+        let mut journal = self.journal.borrow_mut();
+        let Some(writer) = journal.as_mut() else {
+            return;
+        };
+        if let Err(err) = writer.write(&record.shifted(self.paged_out.get())) {
+            *journal = None;
+            self.messages.update(|items| {
+                items.push(ChatMessage::new(
+                    Role::System,
+                    format!("session journal write failed: {err}; this session is no longer saved"),
+                ));
+            });
+        }
+    }
+}
@@ -0,0 +1,2113 @@
+mod coalesce;
+mod history;
+mod paging;
+mod sessions;
+mod usage;
+
+use std::collections::{BTreeSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+use self::coalesce::StreamCoalescer;
+use crate::attach::ImageAttachment;
+use crate::changelog;
+use crate::command::{self, SlashCommand};
+use crate::commit;
+use crate::compare::{Comparison, Lane, LaneStatus};
+use crate::copy::{self, CopyFormat};
+use crate::draft::{Draft, DraftEvent, DraftPurpose};
+use crate::dropped;
+use crate::export::{self, ExportFormat};
+use crate::failure::Failure;
+use crate::issue;
+use crate::metrics::Metrics;
+use crate::paste::{self, PasteHint};
+use crate::pr::{self, BranchChanges};
+use crate::prompts::PromptHistory;
+use crate::provider::{
+    ChatProvider, CompletionRequest, CompletionStream, RequestMessage, RequestRole,
+    RequestToolCall, StreamEvent, WireLog,
+};
+use crate::remote::{Latency, RemoteRoot};
+use crate::replace;
+use crate::risk::{self, Risk};
+use crate::screens::ScreenStates;
+use crate::session::{ForkOrigin, Session, SessionError, SessionId};
+use crate::sound::{Cue, SoundCues};
+use crate::store::TurnUsage;
+use crate::table::{self, TableView};
+use crate::target::{DevContainer, ExecTarget, RunEnv};
+use crate::themes::{self, ThemePreference, WatchedTheme};
+use crate::todos::TodoPanel;
+use crate::tools::{self, RunningTool, Tool, ToolContext, Tools};
+use crate::watch::Watch;
+use crate::workspace::Workspace;
+
+pub(crate) use history::{
+    ChatHistory, ChatMessage, HistoryEvent, MessageId, MessageStatus, Role, RunSummary,
+};
+pub use usage::UsageTotals;
+
+/// How long a run can go without stream events before its header says so.
+const IDLE_AFTER: Duration = Duration::from_secs(3);
+/// Lines `/debug log` keeps; older ones are dropped.
+const LOG_LINES: usize = 2000;
+/// Times one run sends tool results back before it is stopped, in case the
+/// model keeps calling tools without getting anywhere.
+const MAX_TOOL_ROUNDS: usize = 20;
+
+/// Which tool calls wait for the user to approve them before they run. The
+/// panel sets it from the agent mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ToolApproval {
+    /// Any that can change files or run commands.
+    Mutating,
+    /// Only the risky ones, which also drop Autonomous mode to Safest.
+    Risky,
+    Never,
+}
+
+/// A tool call from a reply, and the tool message that shows it.
+#[derive(Clone, Debug)]
+struct PendingCall {
+    name: String,
+    arguments: String,
+    message_index: usize,
+}
+
+struct ActiveTurn {
+    stream: CompletionStream,
+    message_index: usize,
+    /// The prompt that started the run; its message carries the run header.
+    user_index: usize,
+    started: Instant,
+    last_event: Instant,
+    tool_calls: usize,
+    files: Vec<String>,
+    /// Characters of the request, for the usage estimate.
+    prompt_chars: usize,
+    reply_chars: usize,
+    pending: StreamCoalescer,
+    /// Tool calls the reply asked for, run in order once it ends.
+    calls: VecDeque<PendingCall>,
+    /// The reply has ended and its tool calls are being worked through, so
+    /// its stream has nothing more to say.
+    replied: bool,
+    /// The call whose tool is running, and where its output comes.
+    running: Option<(PendingCall, RunningTool)>,
+    /// Times tool results have been sent back in this run.
+    rounds: usize,
+}
+
+impl ActiveTurn {
+    fn summary(&self, finished: bool) -> RunSummary {
+        let idle = self.last_event.elapsed();
+        RunSummary {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            tool_calls: self.tool_calls,
+            files: self.files.clone(),
+            finished,
+            idle_secs: (!finished && idle >= IDLE_AFTER).then_some(idle.as_secs()),
+        }
+    }
+
+    fn record_tool_call(&mut self, arguments: &str) {
+        self.tool_calls += 1;
+        for path in touched_paths(arguments) {
+            if !self.files.contains(&path) {
+                self.files.push(path);
+            }
+        }
+    }
+}
+
+/// Paths named by a tool call's `path` or `paths` argument.
+fn touched_paths(arguments: &str) -> Vec<String> {
+    let Ok(serde_json::Value::Object(arguments)) = serde_json::from_str(arguments) else {
+        return Vec::new();
+    };
+    let mut paths = Vec::new();
+    if let Some(path) = arguments.get("path").and_then(|path| path.as_str()) {
+        paths.push(path.to_string());
+    }
+    if let Some(list) = arguments.get("paths").and_then(|paths| paths.as_array()) {
+        paths.extend(
+            list.iter()
+                .filter_map(|path| path.as_str())
+                .map(str::to_string),
+        );
+    }
+    paths
+}
+
+/// Tuning for a chat; the app takes these from the command line.
+#[derive(Clone, Debug)]
+pub struct ChatOptions {
+    /// Most history updates per second while a reply streams; 0 is unlimited.
+    pub stream_fps: u32,
+    /// Messages kept in memory for a saved session, when it is opened and
+    /// before each prompt is sent. Older ones stay on disk until the list is
+    /// scrolled up to them.
+    pub resident_messages: usize,
+    /// Light or dark palette; `/theme` changes it while running.
+    pub theme: ThemePreference,
+    /// Blue and orange instead of green and red for added and removed.
+    pub color_blind: bool,
+    /// Ask `/commit` for Conventional Commits messages.
+    pub conventional_commits: bool,
+    /// Events that ring the bell; `/sound` changes them and `/mute` silences
+    /// them all.
+    pub sound_cues: SoundCues,
+    /// Close brackets and quotes and keep indentation while typing in the
+    /// input; `/assist` toggles it.
+    pub edit_assist: bool,
+    /// Suggest prompts from earlier sessions too, not just this one.
+    pub shared_prompt_history: bool,
+    /// What the model may call; the built-in file and shell tools unless
+    /// the embedder gives its own.
+    pub tools: Tools,
+}
+
+impl Default for ChatOptions {
+    fn default() -> Self {
+        Self {
+            stream_fps: 30,
+            resident_messages: 1000,
+            theme: ThemePreference::Auto,
+            color_blind: false,
+            conventional_commits: false,
+            sound_cues: SoundCues::default(),
+            edit_assist: true,
+            shared_prompt_history: true,
+            tools: Tools::default(),
+        }
+    }
+}
+
+pub(crate) struct ChatState {
+    pub(crate) input: xpui::TextInputState,
+    pub(crate) history: ChatHistory,
+    pub(crate) selected_model: xpui::signal::Signal<String>,
+    pub(crate) history_heights_memo: xpui::signal::Memo<(u64, usize), Vec<u16>>,
+    provider: Box<dyn ChatProvider>,
+    wire_log: Option<WireLog>,
+    turn: Option<ActiveTurn>,
+    options: ChatOptions,
+    /// Older messages being read back from the journal.
+    older_page: Option<paging::PageRequest>,
+    /// `None` when the session could not be saved.
+    pub(crate) session_id: xpui::signal::Signal<Option<SessionId>>,
+    pub(crate) fork_origin: Option<ForkOrigin>,
+    /// Second model for A/B compare mode; `None` sends to one model as usual.
+    compare_model: Option<String>,
+    pub(crate) comparison: Option<Comparison>,
+    /// Message whose note the input is editing instead of composing a
+    /// prompt, and the prompt it set aside until the note is done.
+    note_target: Option<(MessageId, String)>,
+    /// Message whose table filter the input is typing, and the filter it had
+    /// before, which Esc puts back.
+    filter_target: Option<(usize, String)>,
+    /// Totals across every session this process has shown.
+    pub(crate) usage: xpui::signal::Signal<UsageTotals>,
+    /// Presentation mode: the transcript alone, read-only.
+    pub(crate) presenting: bool,
+    theme: ThemePreference,
+    /// The file behind a [`ThemePreference::Named`] theme.
+    theme_file: Option<WatchedTheme>,
+    color_blind: bool,
+    /// The text `/commit` or `/pr` is writing, or the input is editing.
+    draft: Option<Draft>,
+    /// An issue `/issue` is fetching.
+    issue_fetch: Option<Receiver<Result<String, String>>>,
+    /// Images pasted into the input, sent with the next prompt.
+    pub(crate) attachments: Vec<ImageAttachment>,
+    sound_cues: SoundCues,
+    muted: bool,
+    /// The prompt `/watch` sends again when files change.
+    watch: Option<Watch>,
+    /// Prompts sent before, for suggesting how the one being typed ends.
+    prompts: PromptHistory,
+    /// The comments `/todos` lists, shown in place of the history.
+    pub(crate) todos: Option<TodoPanel>,
+    /// What the turns of this run did, for `/debug log`.
+    pub(crate) log: xpui::LogBuffer,
+    /// `/debug log`, shown in place of the history.
+    pub(crate) log_view: Option<xpui::LogViewState>,
+    /// Ctrl+C stopped a reply; queued prompts wait for the next submit.
+    queue_paused: bool,
+    /// Risky tool calls seen since the panel last looked, for it to drop
+    /// out of Autonomous mode.
+    risks: Vec<Risk>,
+    pub(crate) tool_approval: ToolApproval,
+    /// Tools the user said to always allow, which Safest mode stops asking
+    /// about for the rest of the session.
+    allowed_tools: BTreeSet<String>,
+    /// What `/replace` last previewed, for `/replace apply` to make.
+    replacement: Option<(String, String)>,
+    /// Opt-in counts of commands and session length, kept locally.
+    metrics: Metrics,
+    /// The directories the session works in; `/root` changes them.
+    pub(crate) workspace: Workspace,
+    /// The round trip to the host of the active root, while it is remote.
+    pub(crate) remote_latency: Option<Latency>,
+    /// Where the session's commands run; `/target` changes it.
+    pub(crate) target: ExecTarget,
+    /// What the session's commands run with on top; `/env` changes it.
+    pub(crate) run_env: RunEnv,
+    /// Where the user was on each screen of the session.
+    pub(crate) screens: ScreenStates,
+}
+
+impl ChatState {
+    /// Builds the chat on top of `session`. A fresh session starts with the
+    /// demo conversation; when no session could be opened the chat still runs,
+    /// unsaved, and says so. `None` starts empty, for a session still being
+    /// opened: [`Self::restore`] takes it from there, and [`Self::warm`] the
+    /// prompt history and metrics left unread.
+    pub(crate) fn new(
+        events: xpui::signal::EventSignal<HistoryEvent>,
+        provider: Box<dyn ChatProvider>,
+        wire_log: Option<WireLog>,
+        session: Option<Result<Session, SessionError>>,
+        options: ChatOptions,
+    ) -> Self {
+        let warming = session.is_none();
+        let mut roots = Vec::new();
+        let mut target = None;
+        let mut run_env = None;
+        let (history, resumed, session_id, fork_origin, notice) = match session {
+            Some(Ok(mut session)) => (
+                {
+                    roots = std::mem::take(&mut session.roots);
+                    target = session.target.take();
+                    run_env = session.run_env.take();
+                    ChatHistory::new(
+                        session.messages,
+                        events,
+                        Some(session.journal),
+                        session.paged_out,
+                    )
+                },
+                session.resumed,
+                Some(session.id),
+                session.origin,
+                None,
+            ),
+            Some(Err(err)) => (
+                ChatHistory::new(Vec::new(), events, None, 0),
+                false,
+                None,
+                None,
+                Some(format!("{err}; this session will not be saved")),
+            ),
+            None => (
+                ChatHistory::new(Vec::new(), events, None, 0),
+                true,
+                None,
+                None,
+                None,
+            ),
+        };
+        if !resumed {
+            history.insert_demo();
+        }
+        if let Some(notice) = notice {
+            history.insert(history.len(), ChatMessage::new(Role::System, notice));
+        }
+        history.reset_to_index(history.len().saturating_sub(1));
+
+        let theme = options.theme.clone();
+        let color_blind = options.color_blind;
+        let sound_cues = options.sound_cues;
+        let edit_assist = if options.edit_assist {
+            xpui::EditAssist::ON
+        } else {
+            xpui::EditAssist::default()
+        };
+        let prompts = PromptHistory::open(options.shared_prompt_history && !warming);
+        let mut chat = Self {
+            input: xpui::TextInputState::default(),
+            history,
+            selected_model: xpui::signal::Signal::from("OpenRouter GPT-4.1".to_string()),
+            history_heights_memo: xpui::signal::Memo::new(),
+            provider,
+            wire_log,
+            turn: None,
+            options,
+            older_page: None,
+            session_id: xpui::signal::Signal::from(session_id),
+            fork_origin,
+            compare_model: None,
+            comparison: None,
+            note_target: None,
+            filter_target: None,
+            usage: xpui::signal::Signal::from(UsageTotals::default()),
+            presenting: false,
+            theme: ThemePreference::Auto,
+            theme_file: None,
+            color_blind,
+            draft: None,
+            issue_fetch: None,
+            attachments: Vec::new(),
+            sound_cues,
+            muted: false,
+            watch: None,
+            todos: None,
+            prompts,
+            log: xpui::LogBuffer::new(LOG_LINES),
+            log_view: None,
+            queue_paused: false,
+            risks: Vec::new(),
+            tool_approval: ToolApproval::Mutating,
+            allowed_tools: BTreeSet::new(),
+            replacement: None,
+            metrics: if warming {
+                Metrics::off()
+            } else {
+                Metrics::open()
+            },
+            workspace: Workspace::new(std::env::current_dir().unwrap_or_default()),
+            remote_latency: None,
+            target: ExecTarget::Host,
+            run_env: RunEnv::default(),
+            screens: ScreenStates::default(),
+        };
+        chat.use_roots(roots);
+        chat.use_target(target);
+        chat.use_run_env(run_env);
+        chat.input.set_edit_assist(edit_assist);
+        if let Err(err) = chat.use_theme(theme) {
+            chat.post_system(format!("{err}; using the default theme"));
+        }
+        chat
+    }
+
+    /// Stops the reply being streamed, as the first Ctrl+C does, and holds
+    /// any queued prompts until the next submit.
+    pub(crate) fn stop(&mut self) {
+        if self.is_streaming() {
+            self.interrupt_turn();
+            self.queue_paused = true;
+        }
+    }
+
+    /// Works in the `roots` saved with a session, those that are still
+    /// there; with none, keeps the current roots and saves them with it.
+    pub(super) fn use_roots(&mut self, roots: Vec<PathBuf>) {
+        let roots = roots
+            .into_iter()
+            .filter(|root| root.is_dir() || RemoteRoot::of(root).is_some())
+            .collect();
+        if let Some(workspace) = Workspace::from_roots(roots) {
+            self.workspace = workspace;
+        } else {
+            self.save_roots();
+        }
+    }
+
+    fn save_roots(&self) {
+        let saved = self
+            .history
+            .with_journal_mut(|journal| journal.set_roots(self.workspace.roots()));
+        if let Some(Err(err)) = saved {
+            self.log
+                .push(xpui::LogLevel::Warn, format!("could not save roots: {err}"));
+        }
+    }
+
+    /// Runs commands where the session saved, or keeps the current target
+    /// and saves it with a session that has none.
+    pub(super) fn use_target(&mut self, target: Option<ExecTarget>) {
+        match target {
+            Some(target) => self.target = target,
+            None => self.save_target(),
+        }
+    }
+
+    fn save_target(&self) {
+        let saved = self
+            .history
+            .with_journal_mut(|journal| journal.set_target(&self.target));
+        if let Some(Err(err)) = saved {
+            self.log.push(
+                xpui::LogLevel::Warn,
+                format!("could not save target: {err}"),
+            );
+        }
+    }
+
+    /// Runs commands with what the session saved, or keeps the current
+    /// environment and saves it with a session that has none.
+    pub(super) fn use_run_env(&mut self, env: Option<RunEnv>) {
+        match env {
+            Some(env) => self.run_env = env,
+            None => self.save_run_env(),
+        }
+    }
+
+    fn save_run_env(&self) {
+        let saved = self
+            .history
+            .with_journal_mut(|journal| journal.set_run_env(&self.run_env));
+        if let Some(Err(err)) = saved {
+            self.log.push(
+                xpui::LogLevel::Warn,
+                format!("could not save environment: {err}"),
+            );
+        }
+    }
+
+    /// Makes `env` what commands run with, once the directory it names
+    /// exists where they run, and says where that is.
+    fn switch_run_env(&mut self, env: RunEnv) -> String {
+        if let Some(cwd) = &env.cwd {
+            let root = self.workspace.active();
+            match self.target.run_in(root, &env, "pwd", &[], None) {
+                Ok(dir) => {
+                    self.run_env = env;
+                    self.save_run_env();
+                    return format!("commands start in {}", dir.trim());
+                }
+                Err(err) => return format!("cannot start commands in {cwd}: {err}"),
+            }
+        }
+        self.run_env = env;
+        self.save_run_env();
+        self.run_env.describe()
+    }
+
+    /// Makes `target` where commands run once it answers from the active
+    /// root, and says where that is.
+    fn switch_target(&mut self, target: ExecTarget) -> String {
+        let root = self.workspace.active();
+        let reply = match &target {
+            ExecTarget::Host => format!("commands run on the host in {}", root.display()),
+            ExecTarget::Container { name, .. } => {
+                match target.run_in(root, &self.run_env, "pwd", &[], None) {
+                    Ok(dir) => format!("commands run in container {name} at {}", dir.trim()),
+                    Err(err) => return format!("cannot exec into {name}: {err}"),
+                }
+            }
+        };
+        self.target = target;
+        self.save_target();
+        reply
+    }
+
+    /// Times the connection to the active root's host while it is remote,
+    /// logging when it fails.
+    pub(crate) fn poll_remote(&mut self) {
+        let Some(host) = RemoteRoot::of(self.workspace.active()).map(|remote| remote.host) else {
+            self.remote_latency = None;
+            return;
+        };
+        match &mut self.remote_latency {
+            Some(latency) if latency.host() == host => {
+                if let Some(err) = latency.poll() {
+                    self.log
+                        .push(xpui::LogLevel::Warn, format!("ssh {host}: {err}"));
+                }
+            }
+            _ => self.remote_latency = Some(Latency::new(host)),
+        }
+    }
+
+    /// What is running or waiting, one line each, for the Tasks panel.
+    pub(crate) fn tasks(&self) -> Vec<String> {
+        let mut tasks = Vec::new();
+        if let Some(turn) = &self.turn {
+            tasks.push(format!(
+                "Reply streaming · {}s · {} tool calls",
+                turn.started.elapsed().as_secs(),
+                turn.tool_calls
+            ));
+        }
+        let queued = self.history.queued_count();
+        if queued > 0 {
+            let paused = if self.queue_paused {
+                " · paused until the next submit"
+            } else {
+                ""
+            };
+            tasks.push(format!("{queued} prompts queued{paused}"));
+        }
+        if let (Some(comparison), Some(model)) = (&self.comparison, &self.compare_model)
+            && comparison.is_streaming()
+        {
+            tasks.push(format!("Comparing with {model}"));
+        }
+        if let Some(draft) = self.draft.as_ref().filter(|draft| !draft.is_editing()) {
+            tasks.push(format!("Writing the {}", draft.purpose.label()));
+        }
+        if self.issue_fetch.is_some() {
+            tasks.push("Fetching an issue".to_string());
+        }
+        if let Some(watch) = &self.watch {
+            tasks.push(watch.describe());
+        }
+        tasks
+    }
+
+    /// The settings of this run and the command that changes each, for the
+    /// Settings panel.
+    pub(crate) fn settings(&self) -> String {
+        let on = |on: bool| if on { "on" } else { "off" };
+        let theme = match &self.theme {
+            ThemePreference::Auto => "auto",
+            ThemePreference::Light => "light",
+            ThemePreference::Dark => "dark",
+            ThemePreference::Named(name) => name,
+        };
+        let muted = if self.muted { " (muted)" } else { "" };
+        let target = match &self.target {
+            ExecTarget::Host => "host".to_string(),
+            ExecTarget::Container { name, .. } => format!("container {name}"),
+        };
+        let rows = [
+            ("Theme", theme.to_string(), "/theme auto|light|dark|<name>"),
+            (
+                "Sound cues",
+                format!("{}{muted}", self.sound_cues.label()),
+                "/sound, /mute",
+            ),
+            ("Wrap input", on(self.input.wraps()).to_string(), "/wrap"),
+            (
+                "Edit assist",
+                on(self.input.edit_assist() != xpui::EditAssist::default()).to_string(),
+                "/assist",
+            ),
+            (
+                "Words",
+                self.input.word_rules().name().to_string(),
+                "/words whitespace|punctuation|subword",
+            ),
+            (
+                "Metrics",
+                on(self.metrics.enabled()).to_string(),
+                "/metrics on|off",
+            ),
+            (
+                "Roots",
+                Workspace::name(self.workspace.active()),
+                "/roots, /root <name>",
+            ),
+            ("Runs on", target, "/target"),
+            ("Environment", self.run_env.summary(), "/env"),
+        ];
+        let mut out = "Settings · type the command to change one · Esc back".to_string();
+        for (name, value, command) in rows {
+            out.push_str(&format!("\n{name:<12} {value:<24} {command}"));
+        }
+        out
+    }
+
+    /// The risky tool calls since the last call.
+    pub(crate) fn take_risks(&mut self) -> Vec<Risk> {
+        std::mem::take(&mut self.risks)
+    }
+
+    /// Says in the transcript, and the log, that `risk` dropped the mode
+    /// from Autonomous.
+    pub(crate) fn flag_downgrade(&self, risk: &Risk) {
+        self.log.push(
+            xpui::LogLevel::Warn,
+            format!("mode dropped to Safest for {}", risk.kind.label()),
+        );
+        let audited = self
+            .history
+            .with_journal(|journal| journal.audit("mode_downgrade", &risk.command));
+        if let Some(Err(err)) = audited {
+            self.log
+                .push(xpui::LogLevel::Warn, format!("could not audit: {err}"));
+        }
+        self.post_system(risk.notice());
+    }
+
+    pub(crate) fn queue_paused(&self) -> bool {
+        self.queue_paused && self.history.first_queued().is_some()
+    }
+
+    pub(crate) fn is_streaming(&self) -> bool {
+        self.turn.is_some()
+    }
+
+    /// Sends the input, or queues it behind the active response. Submitting an
+    /// empty input while something is queued interrupts the active response and
+    /// dispatches the next queued message right away.
+    pub(crate) fn submit_input(&mut self) -> bool {
+        let text = self.input.value().trim().to_string();
+        if let Some(draft) = self.draft.take_if(|draft| draft.is_editing()) {
+            if text.is_empty() {
+                self.draft = Some(draft);
+                return false;
+            }
+            self.input.set_value("");
+            self.use_draft(draft.purpose, &text);
+            return true;
+        }
+        if self.filter_target.take().is_some() {
+            self.input.set_value("");
+            return true;
+        }
+        if let Some(id) = self.end_note() {
+            if let Some(index) = self.history.index_of(id) {
+                self.history
+                    .set_note(index, (!text.is_empty()).then_some(text));
+            }
+            return true;
+        }
+        if text.is_empty() && self.attachments.is_empty() {
+            if self.is_streaming() && self.history.first_queued().is_some() {
+                self.interrupt_turn();
+                self.dispatch_next_queued();
+                return true;
+            }
+            if self.queue_paused() {
+                self.queue_paused = false;
+                return true;
+            }
+            return false;
+        }
+        self.queue_paused = false;
+        let command = SlashCommand::parse(&text);
+        self.input.set_value("");
+
+        if let Some(command) = command {
+            self.run_parsed(&text, command);
+            return true;
+        }
+
+        // Commands stay out: they are quick to retype, and some carry
+        // secrets, such as the value of an `/env` variable.
+        self.prompts.record(&text);
+        self.metrics.record_prompt();
+        let images = std::mem::take(&mut self.attachments);
+        let text = command::prompt_text(&text).to_string();
+        // An open comparison has to be resolved before the conversation moves
+        // on, so the prompt waits for it.
+        if self.comparison.is_some() {
+            self.history
+                .append_user(text, images, MessageStatus::Queued);
+            self.post_system(
+                "queued until the comparison is resolved: /compare pick a or b, or /compare off"
+                    .into(),
+            );
+            return true;
+        }
+        self.send(text, images);
+        true
+    }
+
+    /// Runs the slash command a menu bar item stands for, as if typed.
+    pub(crate) fn run_menu_command(&mut self, text: &str) {
+        if let Some(command) = SlashCommand::parse(text) {
+            self.run_parsed(text, command);
+        }
+    }
+
+    fn run_parsed(&mut self, text: &str, command: Result<SlashCommand, String>) {
+        if command.is_ok()
+            && let Some(name) = text[1..].split_whitespace().next()
+        {
+            self.metrics.record_command(name);
+        }
+        let reply = match command {
+            Ok(command) => self.run_command(command),
+            Err(err) => err,
+        };
+        // Some commands, like /present, have nothing to report.
+        if !reply.is_empty() {
+            self.post_system(reply);
+        }
+    }
+
+    /// What waits on the user: a written draft to edit and send, a
+    /// finished comparison to pick a side of, and a tool call to approve.
+    pub(crate) fn awaiting_user(&self) -> usize {
+        let draft = self.draft.as_ref().is_some_and(Draft::is_editing);
+        let comparison = self
+            .comparison
+            .as_ref()
+            .is_some_and(|comparison| !comparison.is_streaming());
+        usize::from(draft) + usize::from(comparison) + usize::from(self.awaiting_tool().is_some())
+    }
+
+    /// The tool message of the call waiting for approval, if one is.
+    pub(crate) fn awaiting_tool(&self) -> Option<usize> {
+        let call = self.turn.as_ref()?.calls.front()?;
+        let history = self.history.borrow();
+        let message = history.get(call.message_index)?;
+        (message.status == MessageStatus::Awaiting).then_some(call.message_index)
+    }
+
+    /// The name and JSON arguments of the tool call waiting for approval.
+    pub(crate) fn awaiting_call(&self) -> Option<(&str, &str)> {
+        self.awaiting_tool()?;
+        let call = self.turn.as_ref()?.calls.front()?;
+        Some((&call.name, &call.arguments))
+    }
+
+    /// Starts a `/replace` over the pending file writes in the input, or
+    /// offers to apply the one just previewed.
+    pub(crate) fn begin_replace(&mut self) {
+        let text = if self.replacement.is_some() {
+            "/replace apply"
+        } else {
+            "/replace "
+        };
+        self.input.set_value(text);
+        self.input.set_cursor(text.chars().count());
+    }
+
+    /// Whether a `/replace` preview waits to be applied.
+    pub(crate) fn replacement_previewed(&self) -> bool {
+        self.replacement.is_some()
+    }
+
+    /// Approves the waiting tool call and every later call of its tool.
+    pub(crate) fn always_allow_tool(&mut self) {
+        if let Some((name, _)) = self.awaiting_call() {
+            self.allowed_tools.insert(name.to_string());
+            self.answer_tool(true);
+        }
+    }
+
+    /// Runs the tool call waiting for approval, or tells the model it was
+    /// denied.
+    pub(crate) fn answer_tool(&mut self, approve: bool) {
+        if self.awaiting_tool().is_none() {
+            return;
+        }
+        self.replacement = None;
+        let context = self.tool_context();
+        let Some(call) = self.turn.as_mut().and_then(|turn| turn.calls.pop_front()) else {
+            return;
+        };
+        match self.options.tools.find(&call.name) {
+            Some(tool) if approve => self.start_tool(tool, call, context),
+            _ => self.record_tool_result(&call, Err("the user denied running it".to_string())),
+        }
+        self.run_tools();
+    }
+
+    /// Sends `text` as a new prompt, or queues it behind the active response.
+    pub(crate) fn send(&mut self, text: String, images: Vec<ImageAttachment>) {
+        if self.is_streaming() {
+            self.history
+                .append_user(text, images, MessageStatus::Queued);
+        } else {
+            let index = self.history.append_user(text, images, MessageStatus::Done);
+            self.dispatch(index);
+        }
+    }
+
+    /// How an earlier prompt would finish the input, while the cursor is at
+    /// its end and it is a prompt being typed rather than a draft or note.
+    pub(crate) fn prompt_suggestion(&self) -> Option<&str> {
+        let value = self.input.value();
+        let typing = self.input.cursor() == value.chars().count()
+            && self.input.extra_cursors().is_empty()
+            && self.note_target.is_none()
+            && self.filter_target.is_none()
+            && self.editing_draft().is_none();
+        self.prompts.suggest(value).filter(|_| typing)
+    }
+
+    /// Ctrl+V in the input. An image on the clipboard is attached to the next
+    /// prompt rather than pasted as text; anything else goes in as text.
+    pub(crate) fn paste(&mut self) -> Option<PasteHint> {
+        if let Some(image) = xpui::clipboard_image() {
+            match ImageAttachment::store(&image) {
+                Ok(image) => self.attachments.push(image),
+                Err(err) => self.post_system(err),
+            }
+            None
+        } else {
+            self.paste_text(&xpui::clipboard_text()?)
+        }
+    }
+
+    /// Puts `text` in the input, and offers to fence it when it looks like
+    /// code going in at a single cursor.
+    pub(crate) fn paste_text(&mut self, text: &str) -> Option<PasteHint> {
+        let single =
+            self.input.extra_cursors().is_empty() && self.input.block_selection().is_none();
+        let start = self.input.cursor();
+        self.input.paste_str(text);
+        let language = paste::code_language(text).filter(|_| single)?;
+        Some(PasteHint {
+            start,
+            end: self.input.cursor(),
+            language,
+        })
+    }
+
+    /// Attaches the images among files dropped on the window or pasted by a
+    /// terminal, and mentions the rest by path at the cursor.
+    pub(crate) fn drop_files(&mut self, paths: &[PathBuf]) {
+        let mut mentions = Vec::new();
+        for path in paths {
+            let Some(mime_type) = dropped::image_type(path) else {
+                mentions.push(dropped::mention(&self.workspace, path));
+                continue;
+            };
+            let stored = std::fs::read(path)
+                .map_err(|err| format!("cannot attach {}: {err}", path.display()))
+                .and_then(|bytes| {
+                    ImageAttachment::store(&xpui::ClipboardImage { mime_type, bytes })
+                });
+            match stored {
+                Ok(image) => self.attachments.push(image),
+                Err(err) => self.post_system(err),
+            }
+        }
+        if mentions.is_empty() {
+            return;
+        }
+        let before = self
+            .input
+            .cursor()
+            .checked_sub(1)
+            .and_then(|at| self.input.value().chars().nth(at));
+        let space = if before.is_some_and(|ch| !ch.is_whitespace()) {
+            " "
+        } else {
+            ""
+        };
+        self.input
+            .insert_str(&format!("{space}{} ", mentions.join(" ")));
+    }
+
+    /// Copies the input's block selection, or the cursor's line without
+    /// one, and takes it out of the input for a cut.
+    pub(crate) fn copy_input(&mut self, cut: bool) {
+        let copied = if cut {
+            self.input.cut()
+        } else {
+            self.input.copy()
+        };
+        if !copied {
+            self.post_system("cannot copy from the input: install wl-copy, xclip or xsel".into());
+        }
+    }
+
+    /// Puts the text of message `index` in the input to be changed and sent
+    /// again as a new prompt.
+    pub(crate) fn edit_message(&mut self, index: usize) {
+        let text = self
+            .history
+            .borrow()
+            .get(index)
+            .map(|message| message.text.clone());
+        if let Some(text) = text {
+            let end = text.chars().count();
+            self.input.set_value(text);
+            self.input.set_cursor(end);
+        }
+    }
+
+    /// Sends the prompt that reply `index` answered again, for a fresh reply
+    /// at the end of the conversation.
+    pub(crate) fn regenerate(&mut self, index: usize) {
+        if self.comparison.is_some() {
+            return;
+        }
+        let prompt = self
+            .history
+            .borrow()
+            .iter()
+            .take(index + 1)
+            .rev()
+            .find(|message| message.role == Role::User)
+            .map(|message| (message.text.clone(), message.images.clone()));
+        match prompt {
+            Some((prompt, images)) => self.send(prompt, images),
+            None => self.post_system("no prompt above this reply to send again".to_string()),
+        }
+    }
+
+    pub(crate) fn note_target(&self) -> Option<usize> {
+        let (id, _) = self.note_target.as_ref()?;
+        self.history.index_of(*id)
+    }
+
+    /// Switches the input to editing the note on message `index`, starting
+    /// from the current note if there is one. The prompt being typed comes
+    /// back when the note is done.
+    pub(crate) fn begin_note(&mut self, index: usize) {
+        let note = self
+            .history
+            .borrow()
+            .get(index)
+            .map(|message| (message.id, message.note.clone().unwrap_or_default()));
+        if let Some((id, note)) = note {
+            let draft = match self.note_target.take() {
+                Some((_, draft)) => draft,
+                None => self.input.value().to_string(),
+            };
+            let end = note.chars().count();
+            self.input.load_value(note);
+            self.input.set_cursor(end);
+            self.note_target = Some((id, draft));
+        }
+    }
+
+    /// Leaves the note being edited, putting back the prompt it set aside,
+    /// and says whose note it was.
+    fn end_note(&mut self) -> Option<MessageId> {
+        let (id, draft) = self.note_target.take()?;
+        let end = draft.chars().count();
+        self.input.load_value(draft);
+        self.input.set_cursor(end);
+        Some(id)
+    }
+
+    pub(crate) fn toggle_expanded(&mut self, index: usize) {
+        let expanded = self
+            .history
+            .borrow()
+            .get(index)
+            .map(|message| message.expanded);
+        if let Some(expanded) = expanded {
+            self.history.set_expanded(index, !expanded);
+        }
+    }
+
+    /// Switches the tables of message `index` between table and text.
+    pub(crate) fn toggle_table(&mut self, index: usize) {
+        let view = self
+            .history
+            .borrow()
+            .get(index)
+            .map(|message| message.table.is_some());
+        if let Some(shown) = view {
+            self.history
+                .set_table(index, (!shown).then(TableView::default));
+        }
+    }
+
+    /// Sorts the tables of message `index` by the next column.
+    pub(crate) fn sort_table(&mut self, index: usize) {
+        let sorted = self.history.borrow().get(index).and_then(|message| {
+            let columns = table::find(&message.text)
+                .iter()
+                .map(|table| table.columns())
+                .max()?;
+            Some(message.table.as_ref()?.next_sort(columns))
+        });
+        if let Some(view) = sorted {
+            self.history.set_table(index, Some(view));
+        }
+    }
+
+    /// Sorts the tables of message `index` by `column`, as clicking its
+    /// header does.
+    pub(crate) fn sort_table_by(&mut self, index: usize, column: usize) {
+        let sorted = self
+            .history
+            .borrow()
+            .get(index)
+            .and_then(|message| Some(message.table.as_ref()?.sort_by(column)));
+        if let Some(view) = sorted {
+            self.history.set_table(index, Some(view));
+        }
+    }
+
+    /// Shows the next or previous page of the tables of message `index`.
+    pub(crate) fn turn_table_page(&mut self, index: usize, forward: bool) {
+        let turned = self.history.borrow().get(index).and_then(|message| {
+            let view = message.table.as_ref()?;
+            Some(view.turn_page(forward, table::page_count(&message.text, view)))
+        });
+        if let Some(view) = turned {
+            self.history.set_table(index, Some(view));
+        }
+    }
+
+    pub(crate) fn filter_target(&self) -> Option<usize> {
+        self.filter_target.as_ref().map(|(index, _)| *index)
+    }
+
+    /// Switches the input to typing the row filter of the tables of message
+    /// `index`; the rows narrow down as it is typed.
+    pub(crate) fn begin_table_filter(&mut self, index: usize) {
+        let filter = self
+            .history
+            .borrow()
+            .get(index)
+            .and_then(|message| Some(message.table.as_ref()?.filter.clone()));
+        if let Some(filter) = filter {
+            let end = filter.chars().count();
+            self.input.load_value(filter.clone());
+            self.input.set_cursor(end);
+            self.filter_target = Some((index, filter));
+        }
+    }
+
+    /// Applies what the input holds as the filter being typed.
+    pub(crate) fn update_table_filter(&mut self) {
+        if let Some((index, _)) = &self.filter_target {
+            self.set_table_filter(*index, self.input.value().trim().to_string());
+        }
+    }
+
+    /// Stops typing the filter and puts back the one from before.
+    pub(crate) fn cancel_table_filter(&mut self) {
+        if let Some((index, filter)) = self.filter_target.take() {
+            self.input.set_value("");
+            self.set_table_filter(index, filter);
+        }
+    }
+
+    fn set_table_filter(&self, index: usize, filter: String) {
+        let view = self
+            .history
+            .borrow()
+            .get(index)
+            .and_then(|message| message.table.clone());
+        if let Some(view) = view.filter(|view| view.filter != filter) {
+            // The page the rows were on means nothing under a new filter.
+            let view = TableView {
+                filter,
+                page: 0,
+                ..view
+            };
+            self.history.set_table(index, Some(view));
+        }
+    }
+
+    pub(crate) fn toggle_bookmark(&mut self, index: usize) {
+        let bookmarked = self
+            .history
+            .borrow()
+            .get(index)
+            .map(|message| message.bookmarked);
+        if let Some(bookmarked) = bookmarked {
+            self.history.set_bookmarked(index, !bookmarked);
+        }
+    }
+
+    /// Puts the code blocks of message `index` on the clipboard.
+    pub(crate) fn copy_code(&mut self, index: usize, format: CopyFormat) {
+        let blocks = self
+            .history
+            .borrow()
+            .get(index)
+            .map(|message| copy::code_blocks(&message.text))
+            .unwrap_or_default();
+        if !blocks.is_empty() && !xpui::set_clipboard_text(&copy::format(&blocks, format)) {
+            self.post_system("cannot copy the code: install wl-copy, xclip or xsel".into());
+        }
+    }
+
+    /// Puts the text of message `index` on the clipboard: a reply to paste
+    /// elsewhere, or an error card for a bug report.
+    pub(crate) fn copy_text(&mut self, index: usize) {
+        let copied = self
+            .history
+            .borrow()
+            .get(index)
+            .map(|message| (message.failure.is_some(), message.text.clone()));
+        if let Some((failure, text)) = copied
+            && !xpui::set_clipboard_text(&text)
+        {
+            let what = if failure { "the error" } else { "the message" };
+            self.post_system(format!(
+                "cannot copy {what}: install wl-copy, xclip or xsel"
+            ));
+        }
+    }
+
+    /// What the text in the input is for while it is a draft being edited.
+    pub(crate) fn editing_draft(&self) -> Option<DraftPurpose> {
+        self.draft
+            .as_ref()
+            .filter(|draft| draft.is_editing())
+            .map(|draft| draft.purpose)
+    }
+
+    /// Drops the draft being written or edited.
+    pub(crate) fn cancel_draft(&mut self) {
+        if let Some(draft) = self.draft.take() {
+            if draft.is_editing() {
+                self.input.set_value("");
+            }
+            self.post_system(format!("{} dropped", draft.purpose.label()));
+        }
+    }
+
+    /// Starts writing a draft with `request`, unless one is under way.
+    fn start_draft(
+        &mut self,
+        purpose: DraftPurpose,
+        request: Result<CompletionRequest, String>,
+    ) -> String {
+        if let Some(draft) = &self.draft {
+            return format!("a {} is already in progress", draft.purpose.label());
+        }
+        let request = match request {
+            Ok(request) => request,
+            Err(err) => return err,
+        };
+        match self.provider.stream_completion(request) {
+            Ok(stream) => {
+                self.draft = Some(Draft::start(purpose, stream));
+                format!("writing a {}", purpose.label())
+            }
+            Err(err) => failure_text(purpose.label(), &err),
+        }
+    }
+
+    /// Moves a finished draft into the input for editing.
+    fn poll_draft(&mut self) {
+        let Some(draft) = self.draft.as_mut() else {
+            return;
+        };
+        let purpose = draft.purpose;
+        match draft.poll() {
+            Some(DraftEvent::Written(text)) => {
+                let end = text.chars().count();
+                self.input.load_value(text);
+                self.input.set_cursor(end);
+                self.cue(Cue::Approval);
+            }
+            Some(DraftEvent::Failed(err)) => {
+                self.draft = None;
+                self.history.insert(
+                    self.history.len(),
+                    Failure::card(purpose.label(), &err, false),
+                );
+                self.cue(Cue::Error);
+            }
+            None => {}
+        }
+    }
+
+    /// Adds a fetched issue to the end of the conversation, where the next
+    /// prompt will follow it.
+    fn poll_issue_fetch(&mut self) {
+        let Some(rx) = &self.issue_fetch else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("issue fetch stopped".to_string()),
+        };
+        self.issue_fetch = None;
+        match result {
+            Ok(text) => {
+                self.history
+                    .insert(self.history.len(), ChatMessage::new(Role::Context, text));
+            }
+            Err(err) => {
+                self.post_system(err);
+                self.cue(Cue::Error);
+            }
+        }
+    }
+
+    /// Sends the watched prompt once the files it watches have changed and
+    /// the previous run is over.
+    fn poll_watch(&mut self) {
+        if self.is_streaming() || self.comparison.is_some() {
+            return;
+        }
+        let Some(watch) = self.watch.as_mut() else {
+            return;
+        };
+        let Some(changed) = watch.poll() else {
+            return;
+        };
+        let prompt = watch.prompt().to_string();
+        let changed = changed.join(", ");
+        self.post_system(format!("changed: {changed}; running the watched prompt"));
+        self.send(prompt, Vec::new());
+    }
+
+    /// Does what the edited draft was for: commits, or opens the pull request.
+    fn use_draft(&mut self, purpose: DraftPurpose, text: &str) {
+        let root = self.workspace.active().to_path_buf();
+        let reply = match purpose {
+            DraftPurpose::Commit => match commit::commit(&root, text) {
+                Ok(summary) => summary,
+                Err(err) => format!("commit failed: {err}"),
+            },
+            DraftPurpose::PullRequest => match pr::create(&root, text) {
+                Ok(url) => format!("opened {url}"),
+                Err(err) => format!("gh pr create failed: {err}"),
+            },
+        };
+        self.post_system(reply);
+    }
+
+    pub(crate) fn cancel_note(&mut self) {
+        self.end_note();
+    }
+
+    fn run_command(&mut self, command: SlashCommand) -> String {
+        match command {
+            SlashCommand::DebugRequests => command::debug_requests_text(self.wire_log.as_ref()),
+            SlashCommand::DebugFrames => command::debug_frames_text(&xpui::frame_report()),
+            SlashCommand::DebugLog => {
+                self.log_view = Some(xpui::LogViewState::new(self.log.clone()));
+                String::new()
+            }
+            SlashCommand::CompareWith(model) => {
+                let reply = format!(
+                    "compare mode: prompts go to A ({}) and B ({model}); keep a reply with \
+                     /compare pick a|b",
+                    self.selected_model.borrow()
+                );
+                self.compare_model = Some(model);
+                reply
+            }
+            SlashCommand::CompareOff => {
+                self.compare_model = None;
+                let reply = if self.comparison.take().is_some() {
+                    "comparison discarded; compare mode off"
+                } else {
+                    "compare mode off"
+                };
+                self.dispatch_next_queued();
+                reply.to_string()
+            }
+            SlashCommand::ComparePick(lane) => self.pick_comparison(lane),
+            SlashCommand::New => self.new_session(),
+            SlashCommand::Fork => self.fork_at(self.history.len().saturating_sub(1)),
+            SlashCommand::Sessions => self.sessions_text(),
+            SlashCommand::Open(id) => self.open_session(&id),
+            SlashCommand::Export {
+                path,
+                include_notes,
+                format,
+            } => {
+                let options = export::ExportOptions { include_notes };
+                let messages = match self.messages_through(self.history.len().saturating_sub(1)) {
+                    Ok(messages) => messages,
+                    Err(err) => return format!("export to {path} failed: {err}"),
+                };
+                let written = match format {
+                    ExportFormat::Markdown => {
+                        export::write_markdown(path.as_ref(), &messages, options)
+                    }
+                    ExportFormat::Html => {
+                        let theme = self.current_theme();
+                        export::write_html(path.as_ref(), &messages, options, &theme)
+                    }
+                };
+                match written {
+                    Ok(()) => format!("exported transcript to {path}"),
+                    Err(err) => format!("export to {path} failed: {err}"),
+                }
+            }
+            SlashCommand::Changelog => changelog::text(),
+            SlashCommand::Present => {
+                self.presenting = true;
+                String::new()
+            }
+            SlashCommand::Theme(theme) => {
+                let reply = match &theme {
+                    ThemePreference::Auto => "theme follows the system".to_string(),
+                    ThemePreference::Light => "light theme".to_string(),
+                    ThemePreference::Dark => "dark theme".to_string(),
+                    ThemePreference::Named(name) => {
+                        format!("theme {name}; saving its file applies the changes")
+                    }
+                };
+                match self.use_theme(theme) {
+                    Ok(()) => reply,
+                    Err(err) => err,
+                }
+            }
+            SlashCommand::ListThemes => themes::listing(),
+            SlashCommand::Commit => {
+                let root = self.workspace.active().to_path_buf();
+                let model = self.selected_model.borrow().clone();
+                let conventional = self.options.conventional_commits;
+                let request = commit::staged_diff(&root)
+                    .map(|diff| commit::request(model, &diff, conventional));
+                self.start_draft(DraftPurpose::Commit, request)
+            }
+            SlashCommand::Issue(reference) => {
+                if self.issue_fetch.is_some() {
+                    return "an issue is already being fetched".to_string();
+                }
+                let root = self.workspace.active().to_path_buf();
+                let reply = format!("fetching issue {reference}");
+                self.issue_fetch = Some(issue::fetch(root, reference));
+                reply
+            }
+            SlashCommand::PullRequest => {
+                let root = self.workspace.active().to_path_buf();
+                let model = self.selected_model.borrow().clone();
+                let request = BranchChanges::read(&root).map(|changes| changes.request(model));
+                self.start_draft(DraftPurpose::PullRequest, request)
+            }
+            SlashCommand::ColorBlind => {
+                self.color_blind = !self.color_blind;
+                if self.color_blind {
+                    "additions show in blue and removals in orange".to_string()
+                } else {
+                    "additions show in green and removals in red".to_string()
+                }
+            }
+            SlashCommand::Sound(cues) => {
+                self.sound_cues = cues;
+                format!("sound cues: {}", cues.label())
+            }
+            SlashCommand::ListSounds => {
+                let muted = if self.muted { " (muted)" } else { "" };
+                format!(
+                    "sound cues: {}{muted}; /sound complete,approval,error|all|none changes them",
+                    self.sound_cues.label()
+                )
+            }
+            SlashCommand::Watch { pattern, prompt } => {
+                let root = self.workspace.active().to_path_buf();
+                let watch = Watch::start(root, pattern, prompt);
+                let reply = format!("{}; /watch off stops", watch.describe());
+                self.watch = Some(watch);
+                reply
+            }
+            SlashCommand::Todos => {
+                self.todos = Some(TodoPanel::open(self.workspace.clone()));
+                String::new()
+            }
+            SlashCommand::Roots => self.workspace.listing(),
+            SlashCommand::RootAdd(path) => match self.workspace.add(&path) {
+                Ok(root) => {
+                    let reply = format!("added root {}; it is the active one", root.display());
+                    self.save_roots();
+                    reply
+                }
+                Err(err) => err,
+            },
+            SlashCommand::RootRemove(name) => match self.workspace.remove(&name) {
+                Ok(root) => {
+                    self.save_roots();
+                    format!(
+                        "removed root {}; {} is active",
+                        root.display(),
+                        self.workspace.active().display()
+                    )
+                }
+                Err(err) => err,
+            },
+            SlashCommand::RootSwitch(name) => match self.workspace.switch(&name) {
+                Ok(root) => format!("active root: {}", root.display()),
+                Err(err) => err,
+            },
+            SlashCommand::Target => {
+                let current = match &self.target {
+                    ExecTarget::Host => "the host".to_string(),
+                    ExecTarget::Container { name, .. } => format!("container {name}"),
+                };
+                format!(
+                    "commands run on {current}. {}\n\
+                     /target host | /target devcontainer | /target container <name> [<workdir>]",
+                    DevContainer::detect(self.workspace.active()).describe()
+                )
+            }
+            SlashCommand::TargetHost => self.switch_target(ExecTarget::Host),
+            SlashCommand::TargetDevContainer => {
+                let found = DevContainer::detect(self.workspace.active());
+                match found.target() {
+                    Some(target) => self.switch_target(target),
+                    None => found.describe(),
+                }
+            }
+            SlashCommand::TargetContainer { name, workdir } => {
+                self.switch_target(ExecTarget::Container { name, workdir })
+            }
+            SlashCommand::Env => format!("{}\n{}", self.run_env.describe(), command::ENV_USAGE),
+            SlashCommand::EnvSet { name, value } => {
+                let mut env = self.run_env.clone();
+                env.vars.insert(name, value);
+                self.switch_run_env(env)
+            }
+            SlashCommand::EnvUnset(name) => {
+                let mut env = self.run_env.clone();
+                if env.vars.remove(&name).is_none() {
+                    return format!("{name} is not set for the session");
+                }
+                self.switch_run_env(env)
+            }
+            SlashCommand::EnvCwd(cwd) => {
+                let env = RunEnv {
+                    cwd,
+                    ..self.run_env.clone()
+                };
+                self.switch_run_env(env)
+            }
+            SlashCommand::EnvClear => self.switch_run_env(RunEnv::default()),
+            SlashCommand::Metrics => self.metrics.report(),
+            SlashCommand::MetricsOn => match self.metrics.enable() {
+                Ok(()) => "recording metrics locally; /metrics shows them".to_string(),
+                Err(err) => err,
+            },
+            SlashCommand::MetricsOff => match self.metrics.purge() {
+                Ok(()) => "metrics off; everything recorded is deleted".to_string(),
+                Err(err) => err,
+            },
+            SlashCommand::MetricsExport(path) => {
+                match self.metrics.export(std::path::Path::new(&path)) {
+                    Ok(()) => format!("wrote metrics to {path}"),
+                    Err(err) => err,
+                }
+            }
+            SlashCommand::WatchStatus => match &self.watch {
+                Some(watch) => watch.describe(),
+                None => "not watching; /watch <glob> <prompt> starts".to_string(),
+            },
+            SlashCommand::WatchOff => match self.watch.take() {
+                Some(_) => "stopped watching".to_string(),
+                None => "not watching".to_string(),
+            },
+            SlashCommand::Replace { find, with } => {
+                let writes = self.turn.iter().flat_map(|turn| &turn.calls);
+                let writes = writes
+                    .filter_map(|call| replace::pending_write(&call.name, &call.arguments))
+                    .collect::<Vec<_>>();
+                let preview = replace::preview(&writes, &find, &with);
+                self.replacement = Some((find, with));
+                preview
+            }
+            SlashCommand::ReplaceApply => match self.replacement.take() {
+                Some((find, with)) => self.apply_replacement(&find, &with),
+                None => "nothing to apply; /replace <find> <with> previews first".to_string(),
+            },
+            SlashCommand::Mute => {
+                self.muted = !self.muted;
+                if self.muted {
+                    "sound cues muted".to_string()
+                } else {
+                    format!("sound cues on: {}", self.sound_cues.label())
+                }
+            }
+            SlashCommand::Assist => {
+                if self.input.edit_assist() == xpui::EditAssist::default() {
+                    self.input.set_edit_assist(xpui::EditAssist::ON);
+                    "input closes brackets and quotes and keeps indentation".to_string()
+                } else {
+                    self.input.set_edit_assist(xpui::EditAssist::default());
+                    "input types exactly what is typed".to_string()
+                }
+            }
+            SlashCommand::Words(None) => format!(
+                "word motions stop at {} boundaries; /words whitespace|punctuation|subword",
+                self.input.word_rules().name()
+            ),
+            SlashCommand::Words(Some(rules)) => {
+                self.input.set_word_rules(rules);
+                format!("word motions stop at {} boundaries", rules.name())
+            }
+            SlashCommand::Wrap => {
+                let wrap = !self.input.wraps();
+                self.input.set_wrap(wrap);
+                if wrap {
+                    "input wraps long lines".to_string()
+                } else {
+                    "input scrolls long lines sideways; /wrap to wrap again".to_string()
+                }
+            }
+        }
+    }
+
+    /// Keeps one side of the comparison as the reply and makes its model the
+    /// default for the rest of the session.
+    fn pick_comparison(&mut self, lane: Lane) -> String {
+        let Some(comparison) = self.comparison.take() else {
+            return "nothing to pick; send a prompt in compare mode first".to_string();
+        };
+        let user_index = comparison.user_index;
+        let lane = comparison.into_lane(lane);
+        let status = if lane.status == LaneStatus::Done {
+            MessageStatus::Done
+        } else {
+            MessageStatus::Interrupted
+        };
+        self.history.insert(
+            user_index + 1,
+            ChatMessage::new(Role::Assistant, lane.text).with_status(status),
+        );
+        self.selected_model.set(lane.model.clone());
+        self.compare_model = None;
+        self.dispatch_next_queued();
+        format!("default model is now {}", lane.model)
+    }
+
+    /// Drains pending stream events into the history. Called once per frame.
+    pub(crate) fn poll_stream(&mut self) {
+        if let Some(comparison) = &mut self.comparison {
+            comparison.poll();
+        }
+
+        while let Some(event) = self
+            .turn
+            .as_ref()
+            .filter(|turn| !turn.replied)
+            .and_then(|turn| turn.stream.try_next())
+        {
+            let Some(turn) = self.turn.as_mut() else {
+                break;
+            };
+            turn.last_event = Instant::now();
+            let index = turn.message_index;
+            if let StreamEvent::Delta(delta) = &event {
+                turn.reply_chars += delta.chars().count();
+                turn.pending.push(delta);
+                continue;
+            }
+            // Anything other than text ends the batch so it lands in order.
+            self.flush_pending();
+            match event {
+                StreamEvent::Delta(_) => {}
+                StreamEvent::ToolCall {
+                    id,
+                    name,
+                    arguments,
+                } => {
+                    self.log.push(
+                        xpui::LogLevel::Debug,
+                        format!("tool call {name} {arguments}"),
+                    );
+                    if let Some(turn) = self.turn.as_mut() {
+                        turn.record_tool_call(&arguments);
+                    }
+                    self.risks.extend(risk::classify(&name, &arguments));
+                    let call = ChatMessage {
+                        call_id: Some(id),
+                        ..ChatMessage::new(Role::Tool, format!("{name} {arguments}"))
+                    };
+                    let at = self.record_tool_call(index, call);
+                    if let (Some(message_index), Some(turn)) = (at, self.turn.as_mut()) {
+                        turn.calls.push_back(PendingCall {
+                            name,
+                            arguments,
+                            message_index,
+                        });
+                    }
+                }
+                StreamEvent::Done => {
+                    self.history.set_status(index, MessageStatus::Done);
+                    match self.turn.as_mut() {
+                        Some(turn) if !turn.calls.is_empty() => turn.replied = true,
+                        _ => {
+                            self.finish_turn();
+                            self.cue(Cue::Complete);
+                        }
+                    }
+                }
+                StreamEvent::Failed(err) => {
+                    self.log
+                        .push(xpui::LogLevel::Error, failure_text("response stream", &err));
+                    self.history.set_status(index, MessageStatus::Interrupted);
+                    self.history
+                        .insert(index + 1, Failure::card("response stream", &err, true));
+                    self.finish_turn();
+                    self.cue(Cue::Error);
+                }
+            }
+        }
+        if let Some(turn) = self.turn.as_mut()
+            && let Some(text) = turn.pending.take_due()
+        {
+            self.history.append_delta(turn.message_index, &text);
+        }
+        self.run_tools();
+        self.refresh_run_header();
+        self.poll_older_page();
+        self.poll_draft();
+        self.poll_issue_fetch();
+        self.poll_watch();
+
+        if self.turn.is_none() {
+            self.dispatch_next_queued();
+        }
+    }
+
+    /// Shows a tool call in line with the reply. Text streamed before the call
+    /// stays above it and the rest of the reply continues below it. Returns
+    /// where the call's message went.
+    fn record_tool_call(&mut self, index: usize, call: ChatMessage) -> Option<usize> {
+        let turn = self.turn.as_mut()?;
+        let reply_started = self
+            .history
+            .borrow()
+            .get(index)
+            .is_some_and(|message| !message.text.is_empty());
+        if reply_started {
+            self.history.set_status(index, MessageStatus::Done);
+            self.history.insert(index + 1, call);
+            self.history.insert(
+                index + 2,
+                ChatMessage::new(Role::Assistant, "").with_status(MessageStatus::Streaming),
+            );
+            turn.message_index = index + 2;
+            Some(index + 1)
+        } else {
+            self.history.insert(index, call);
+            turn.message_index = index + 1;
+            Some(index)
+        }
+    }
+
+    /// Works through the tool calls of a reply that has ended: runs each in
+    /// turn, or stops at one waiting for approval, then sends the results
+    /// back for the model to go on.
+    fn run_tools(&mut self) {
+        loop {
+            let Some(turn) = self.turn.as_mut().filter(|turn| turn.replied) else {
+                return;
+            };
+            if let Some((call, running)) = &turn.running {
+                let Some(result) = running.poll() else {
+                    return;
+                };
+                let call = call.clone();
+                turn.running = None;
+                self.record_tool_result(&call, result);
+                continue;
+            }
+            let Some(call) = turn.calls.front().cloned() else {
+                self.continue_turn();
+                return;
+            };
+            let awaiting = self
+                .history
+                .borrow()
+                .get(call.message_index)
+                .is_some_and(|message| message.status == MessageStatus::Awaiting);
+            if awaiting {
+                return;
+            }
+            let Some(tool) = self.options.tools.find(&call.name) else {
+                turn.calls.pop_front();
+                let err = format!("there is no tool named {}", call.name);
+                self.record_tool_result(&call, Err(err));
+                continue;
+            };
+            let ask = match self.tool_approval {
+                ToolApproval::Mutating => {
+                    tool.mutates() && !self.allowed_tools.contains(&call.name)
+                }
+                ToolApproval::Risky => risk::classify(&call.name, &call.arguments).is_some(),
+                ToolApproval::Never => false,
+            };
+            if ask {
+                self.history
+                    .set_status(call.message_index, MessageStatus::Awaiting);
+                self.cue(Cue::Approval);
+                return;
+            }
+            turn.calls.pop_front();
+            let context = self.tool_context();
+            self.start_tool(tool, call, context);
+        }
+    }
+
+    /// Replaces `find` with `with` in the content of every file write the
+    /// reply is waiting to make, and in the calls as the transcript and the
+    /// model see them.
+    fn apply_replacement(&mut self, find: &str, with: &str) -> String {
+        let Some(turn) = self.turn.as_mut() else {
+            return "no file writes are pending".to_string();
+        };
+        let mut changed = Vec::new();
+        for call in &mut turn.calls {
+            let Some((arguments, count)) = replace::apply(&call.name, &call.arguments, find, with)
+            else {
+                continue;
+            };
+            call.arguments = arguments;
+            self.history.set_text(
+                call.message_index,
+                format!("{} {}", call.name, call.arguments),
+            );
+            if let Some((path, _)) = replace::pending_write(&call.name, &call.arguments) {
+                changed.push(format!("{path} ({count})"));
+            }
+        }
+        if changed.is_empty() {
+            format!("no pending file write contains `{find}`")
+        } else {
+            format!("replaced `{find}` with `{with}` in {}", changed.join(", "))
+        }
+    }
+
+    /// What the model may call in this chat.
+    pub(crate) fn tools(&self) -> &Tools {
+        &self.options.tools
+    }
+
+    fn tool_context(&self) -> ToolContext {
+        ToolContext {
+            root: self.workspace.active().to_path_buf(),
+            target: self.target.clone(),
+            env: self.run_env.clone(),
+            process: Default::default(),
+        }
+    }
+
+    fn start_tool(&mut self, tool: Arc<dyn Tool>, call: PendingCall, context: ToolContext) {
+        self.log.push(
+            xpui::LogLevel::Info,
+            format!("running tool {} {}", call.name, call.arguments),
+        );
+        self.history
+            .set_status(call.message_index, MessageStatus::Streaming);
+        let output = tools::start(tool, context, call.arguments.clone());
+        if let Some(turn) = self.turn.as_mut() {
+            turn.running = Some((call, output));
+        }
+    }
+
+    /// Puts what `call` gave back under its message, for the model to see
+    /// with the rest of the conversation.
+    fn record_tool_result(&mut self, call: &PendingCall, result: Result<String, String>) {
+        let text = match result {
+            Ok(output) if output.trim().is_empty() => "(no output)".to_string(),
+            Ok(output) => output,
+            Err(err) => format!("failed: {err}"),
+        };
+        self.log.push(
+            xpui::LogLevel::Debug,
+            format!(
+                "tool {} gave back {} chars",
+                call.name,
+                text.chars().count()
+            ),
+        );
+        self.history
+            .set_status(call.message_index, MessageStatus::Done);
+        let at = call.message_index + 1;
+        self.history
+            .insert(at, ChatMessage::new(Role::ToolResult, text));
+        if let Some(turn) = self.turn.as_mut() {
+            if turn.message_index >= at {
+                turn.message_index += 1;
+            }
+            for later in &mut turn.calls {
+                if later.message_index >= at {
+                    later.message_index += 1;
+                }
+            }
+        }
+    }
+
+    /// Sends the conversation with the tool results back to the model,
+    /// continuing the reply in its empty message or a new one below.
+    fn continue_turn(&mut self) {
+        let Some(turn) = self.turn.as_mut() else {
+            return;
+        };
+        turn.replied = false;
+        turn.rounds += 1;
+        if turn.rounds > MAX_TOOL_ROUNDS {
+            self.post_system(format!(
+                "stopped after {MAX_TOOL_ROUNDS} rounds of tool calls; send a prompt to go on"
+            ));
+            self.finish_turn();
+            self.cue(Cue::Complete);
+            return;
+        }
+        let empty = self
+            .history
+            .borrow()
+            .get(turn.message_index)
+            .is_some_and(|message| message.text.is_empty());
+        if empty {
+            self.history
+                .set_status(turn.message_index, MessageStatus::Streaming);
+        } else {
+            turn.message_index += 1;
+            self.history.insert(
+                turn.message_index,
+                ChatMessage::new(Role::Assistant, "").with_status(MessageStatus::Streaming),
+            );
+        }
+        let index = turn.message_index;
+        let request = self.request_up_to(index);
+        match self.provider.stream_completion(request) {
+            Ok(stream) => {
+                if let Some(turn) = self.turn.as_mut() {
+                    turn.stream = stream;
+                }
+            }
+            Err(err) => {
+                self.log.push(
+                    xpui::LogLevel::Error,
+                    failure_text("send tool results", &err),
+                );
+                self.history.set_status(index, MessageStatus::Interrupted);
+                self.history
+                    .insert(index + 1, Failure::card("send tool results", &err, false));
+                self.finish_turn();
+                self.cue(Cue::Error);
+            }
+        }
+    }
+
+    /// Switches to `theme`. A theme file that does not load leaves the
+    /// current theme in place.
+    fn use_theme(&mut self, theme: ThemePreference) -> Result<(), String> {
+        self.theme_file = match &theme {
+            ThemePreference::Named(name) => Some(WatchedTheme::open(name)?),
+            _ => None,
+        };
+        self.theme = theme;
+        Ok(())
+    }
+
+    /// The palette to draw this frame with, picking up edits to a theme file.
+    pub(crate) fn current_theme(&mut self) -> xpui::Theme {
+        let theme = self.base_theme();
+        if self.color_blind {
+            theme.color_blind()
+        } else {
+            theme
+        }
+    }
+
+    fn base_theme(&mut self) -> xpui::Theme {
+        let appearance = match self.theme {
+            ThemePreference::Auto => xpui::system_appearance().unwrap_or(xpui::Appearance::Dark),
+            ThemePreference::Light => xpui::Appearance::Light,
+            ThemePreference::Dark => xpui::Appearance::Dark,
+            ThemePreference::Named(_) => {
+                let Some(file) = self.theme_file.as_mut() else {
+                    return xpui::Theme::dark();
+                };
+                let error = file.poll();
+                let theme = file.theme().clone();
+                if let Some(err) = error {
+                    self.post_system(err);
+                }
+                return theme;
+            }
+        };
+        xpui::Theme::for_appearance(appearance)
+    }
+
+    fn post_system(&self, text: String) {
+        self.history
+            .insert(self.history.len(), ChatMessage::new(Role::System, text));
+    }
+
+    /// Moves batched reply text into the history right away.
+    fn flush_pending(&mut self) {
+        if let Some(turn) = self.turn.as_mut()
+            && let Some(text) = turn.pending.take()
+        {
+            self.history.append_delta(turn.message_index, &text);
+        }
+    }
+
+    fn interrupt_turn(&mut self) {
+        self.flush_pending();
+        if let Some(turn) = &self.turn {
+            self.log.push(xpui::LogLevel::Warn, "turn interrupted");
+            self.history
+                .set_status(turn.message_index, MessageStatus::Interrupted);
+            if let Some((_, running)) = &turn.running {
+                running.kill();
+            }
+            let calls = turn.running.iter().map(|(call, _)| call);
+            for call in calls.chain(&turn.calls) {
+                self.history
+                    .set_status(call.message_index, MessageStatus::Interrupted);
+            }
+        }
+        self.finish_turn();
+    }
+
+    /// Ends the active run and writes its final header.
+    fn finish_turn(&mut self) {
+        if let Some(turn) = self.turn.take() {
+            self.log.push(
+                xpui::LogLevel::Info,
+                format!(
+                    "turn ended after {:.1}s: {} reply chars, {} tool calls",
+                    turn.started.elapsed().as_secs_f32(),
+                    turn.reply_chars,
+                    turn.tool_calls
+                ),
+            );
+            let summary = turn.summary(true);
+            let recorded = self.history.with_journal(|journal| {
+                journal.record_usage(&TurnUsage {
+                    elapsed_ms: summary.elapsed_ms,
+                    tool_calls: turn.tool_calls,
+                    prompt_chars: turn.prompt_chars,
+                    reply_chars: turn.reply_chars,
+                })
+            });
+            if let Some(Err(err)) = recorded {
+                self.log.push(
+                    xpui::LogLevel::Warn,
+                    format!("could not record usage: {err}"),
+                );
+            }
+            self.history.set_run(turn.user_index, summary);
+            self.usage.update(|usage| usage.record(&turn));
+        }
+    }
+
+    /// Rings the bell for `cue` if that event is turned on and not muted.
+    fn cue(&self, cue: Cue) {
+        if !self.muted && self.sound_cues.rings(cue) {
+            xpui::ring_bell();
+        }
+    }
+
+    /// Updates the live run header when what it shows has changed.
+    fn refresh_run_header(&self) {
+        let Some(turn) = &self.turn else {
+            return;
+        };
+        let summary = turn.summary(false);
+        let shown = self
+            .history
+            .borrow()
+            .get(turn.user_index)
+            .and_then(|message| message.run.as_ref())
+            .map(RunSummary::label);
+        if shown.as_deref() != Some(summary.label().as_str()) {
+            self.history.set_run(turn.user_index, summary);
+        }
+    }
+
+    fn dispatch_next_queued(&mut self) {
+        if self.is_streaming() || self.comparison.is_some() || self.queue_paused {
+            return;
+        }
+        if let Some(index) = self.history.first_queued() {
+            self.history.set_status(index, MessageStatus::Done);
+            self.dispatch(index);
+        }
+    }
+
+    fn dispatch(&mut self, user_index: usize) {
+        let user_index = self.keep_window(user_index);
+        let request = self.request_up_to(user_index);
+        let prompt_chars = request
+            .messages
+            .iter()
+            .map(|message| message.content.chars().count())
+            .sum();
+        self.log.push(
+            xpui::LogLevel::Info,
+            format!(
+                "sending {} messages ({prompt_chars} chars) to {}",
+                request.messages.len(),
+                request.model
+            ),
+        );
+        if let Some(model_b) = &self.compare_model {
+            self.comparison = Some(Comparison::start(
+                self.provider.as_ref(),
+                request,
+                model_b,
+                user_index,
+            ));
+            return;
+        }
+        match self.provider.stream_completion(request) {
+            Ok(stream) => {
+                let message_index = user_index + 1;
+                self.history.insert(
+                    message_index,
+                    ChatMessage::new(Role::Assistant, "").with_status(MessageStatus::Streaming),
+                );
+                let now = Instant::now();
+                self.turn = Some(ActiveTurn {
+                    stream,
+                    message_index,
+                    user_index,
+                    started: now,
+                    last_event: now,
+                    tool_calls: 0,
+                    files: Vec::new(),
+                    prompt_chars,
+                    reply_chars: 0,
+                    pending: StreamCoalescer::new(self.options.stream_fps),
+                    calls: VecDeque::new(),
+                    replied: false,
+                    running: None,
+                    rounds: 0,
+                });
+                self.refresh_run_header();
+            }
+            Err(err) => {
+                self.log
+                    .push(xpui::LogLevel::Error, failure_text("send message", &err));
+                self.history
+                    .insert(user_index + 1, Failure::card("send message", &err, true));
+            }
+        }
+    }
+
+    /// The conversation through message `last_index` as the model sees it.
+    /// A tool call goes with the result under it; one without a result, as
+    /// when it was stopped, is left out.
+    fn request_up_to(&self, last_index: usize) -> CompletionRequest {
+        let history = self.history.borrow();
+        let mut messages = Vec::new();
+        let mut call = None;
+        for message in history.iter().take(last_index + 1).filter(|message| {
+            matches!(
+                message.status,
+                MessageStatus::Done | MessageStatus::Interrupted | MessageStatus::Truncated
+            )
+        }) {
+            let asked = call.take();
+            let (role, tool_call) = match message.role {
+                Role::User | Role::Context => (RequestRole::User, None),
+                Role::Assistant => (RequestRole::Assistant, None),
+                Role::Tool => {
+                    call = Some(message);
+                    continue;
+                }
+                Role::ToolResult => {
+                    let Some(asked) = asked else { continue };
+                    let text = asked.text.as_str();
+                    let (name, arguments) = text.split_once(' ').unwrap_or((text, "{}"));
+                    let call = RequestToolCall {
+                        id: asked.call_id.clone().unwrap_or_default(),
+                        name: name.to_string(),
+                        arguments: arguments.to_string(),
+                    };
+                    (RequestRole::Tool, Some(call))
+                }
+                Role::System => continue,
+            };
+            messages.push(RequestMessage {
+                role,
+                content: message.text.clone(),
+                images: message
+                    .images
+                    .iter()
+                    .filter_map(ImageAttachment::load)
+                    .collect(),
+                tool_call,
+            });
+        }
+
+        CompletionRequest {
+            model: self.selected_model.borrow().clone(),
+            messages,
+            tools: self.options.tools.specs(),
+        }
+    }
+}
+
+fn failure_text(operation: &str, err: &crate::provider::ProviderError) -> String {
+    if err.kind().is_retryable() {
+        format!("{operation} failed: {err} (retry by sending again)")
+    } else {
+        format!("{operation} failed: {err}")
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use super::{ChatMessage, ChatState};
+use crate::session::{self, SessionError};
+
+/// Messages read back from disk per scroll to the top.
+const PAGE_SIZE: usize = 100;
+
+pub(super) type PageRequest = Receiver<Result<Vec<ChatMessage>, SessionError>>;
+
+impl ChatState {
+    /// Starts reading the page of messages just before the resident ones, if
+    /// any are on disk and no read is already running.
+    pub(crate) fn load_older(&mut self) {
+        let paged_out = self.history.paged_out();
+        if paged_out == 0 || self.older_page.is_some() {
+            return;
+        }
+        let Some(id) = self.session_id.borrow().clone() else {
+            return;
+        };
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let range = paged_out.saturating_sub(PAGE_SIZE)..paged_out;
+            let _ = tx.send(session::load_messages(&id, range));
+        });
+        self.older_page = Some(rx);
+    }
+
+    /// Moves a finished page into the history. Prepending shifts every index,
+    /// so it waits while anything holds one: a streaming reply, an open
+    /// comparison or a table filter being typed.
+    pub(super) fn poll_older_page(&mut self) {
+        if self.turn.is_some() || self.comparison.is_some() || self.filter_target.is_some() {
+            return;
+        }
+        let Some(request) = &self.older_page else {
+            return;
+        };
+        match request.try_recv() {
+            Ok(Ok(page)) => {
+                self.older_page = None;
+                self.history.prepend(page);
+            }
+            Ok(Err(err)) => {
+                self.older_page = None;
+                self.post_system(format!("{err}; older messages stay unloaded"));
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => self.older_page = None,
+        }
+    }
+
+    /// Drops messages older than the memory window before message `index` is
+    /// sent, so a session that grows while open stays as capped as a resumed
+    /// one. Returns where message `index` is afterwards. Nothing moves while
+    /// an index is held elsewhere or a page is being read back.
+    pub(super) fn keep_window(&mut self, index: usize) -> usize {
+        if self.turn.is_some()
+            || self.comparison.is_some()
+            || self.filter_target.is_some()
+            || self.older_page.is_some()
+        {
+            return index;
+        }
+        index - self.history.page_out(self.options.resident_messages, index)
+    }
+
+    /// The whole session up to and including resident message `index`,
+    /// reading the paged-out part back from disk.
+    pub(super) fn messages_through(&self, index: usize) -> Result<Vec<ChatMessage>, SessionError> {
+        let resident = self.history.borrow();
+        let resident = resident.iter().take(index.saturating_add(1)).cloned();
+        let paged_out = self.history.paged_out();
+        let id = self.session_id.borrow().clone();
+        let mut messages = match id {
+            Some(id) if paged_out > 0 => session::load_messages(&id, 0..paged_out)?,
+            _ => Vec::new(),
+        };
+        messages.extend(resident);
+        Ok(messages)
+    }
+}
@@ -0,0 +1,110 @@
+use super::ChatState;
+use crate::command;
+use crate::session::{self, Session};
+use crate::warmup::Warmed;
+
+impl ChatState {
+    /// Forks the session after message `index` and switches to the fork.
+    pub(crate) fn fork_from(&mut self, index: usize) {
+        let reply = self.fork_at(index);
+        self.post_system(reply);
+    }
+
+    pub(super) fn fork_at(&mut self, index: usize) -> String {
+        let Some(parent) = self.session_id.borrow().clone() else {
+            return "this session is not saved, so it cannot be forked".to_string();
+        };
+        let messages = match self.messages_through(index) {
+            Ok(messages) => messages,
+            Err(err) => return err.to_string(),
+        };
+        match session::fork(&parent, &messages) {
+            Ok(session) => {
+                let id = session.id.clone();
+                self.attach(session);
+                format!(
+                    "forked {parent} at message #{} into {id}; /open {parent} goes back",
+                    messages.len()
+                )
+            }
+            Err(err) => err.to_string(),
+        }
+    }
+
+    pub(super) fn new_session(&mut self) -> String {
+        let previous = self.session_id.borrow().clone();
+        match session::start_new() {
+            Ok(session) => {
+                let id = session.id.clone();
+                self.attach(session);
+                match previous {
+                    Some(previous) => format!("started session {id}; /open {previous} goes back"),
+                    None => format!("started session {id}"),
+                }
+            }
+            Err(err) => err.to_string(),
+        }
+    }
+
+    pub(super) fn open_session(&mut self, id: &str) -> String {
+        if self
+            .session_id
+            .borrow()
+            .as_ref()
+            .is_some_and(|current| current.as_str() == id)
+        {
+            return format!("already in session {id}");
+        }
+        match session::resume(id, self.options.resident_messages) {
+            Ok(session) => {
+                self.attach(session);
+                format!("opened session {id}")
+            }
+            Err(err) => err.to_string(),
+        }
+    }
+
+    pub(super) fn sessions_text(&self) -> String {
+        match session::list() {
+            Ok(sessions) => command::sessions_text(&sessions, self.session_id.borrow().as_ref()),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    /// Takes in what warmup loaded for a chat started without it. A fresh
+    /// session opens with the demo conversation, as it would have at once;
+    /// failures are left to the caller to report.
+    pub(crate) fn warm(&mut self, warmed: Warmed) {
+        match warmed {
+            Warmed::Config { prompts, metrics } => {
+                self.prompts = prompts;
+                self.metrics = metrics;
+            }
+            Warmed::Session(Ok(session)) => {
+                let resumed = session.resumed;
+                self.attach(session);
+                if !resumed {
+                    self.history.insert_demo();
+                }
+            }
+            Warmed::Session(Err(_)) | Warmed::Index(_) => {}
+        }
+    }
+
+    /// Leaves the current session, cancelling anything in flight, and
+    /// continues in `session`.
+    fn attach(&mut self, mut session: Session) {
+        self.interrupt_turn();
+        self.comparison = None;
+        self.compare_model = None;
+        self.older_page = None;
+        self.history
+            .replace(session.messages, session.journal, session.paged_out);
+        self.session_id.set(Some(session.id));
+        self.fork_origin = session.origin;
+        self.screens.clear();
+        self.use_roots(std::mem::take(&mut session.roots));
+        self.use_target(session.target.take());
+        self.use_run_env(session.run_env.take());
+    }
+}
@@ -0,0 +1,30 @@
+use std::collections::BTreeSet;
+
+use super::ActiveTurn;
+
+/// What this process has sent and received, for the summary printed on exit.
+/// Providers do not report usage yet, so tokens are estimated from text
+/// length the same way `/compare` does.
+#[derive(Clone, Debug, Default)]
+pub struct UsageTotals {
+    pub turns: usize,
+    pub tool_calls: usize,
+    pub files: BTreeSet<String>,
+    prompt_chars: usize,
+    reply_chars: usize,
+}
+
+impl UsageTotals {
+    pub(super) fn record(&mut self, turn: &ActiveTurn) {
+        self.turns += 1;
+        self.tool_calls += turn.tool_calls;
+        self.files.extend(turn.files.iter().cloned());
+        self.prompt_chars += turn.prompt_chars;
+        self.reply_chars += turn.reply_chars;
+    }
+
+    /// Estimated tokens sent and received, at four characters per token.
+    pub fn estimated_tokens(&self) -> (usize, usize) {
+        (self.prompt_chars.div_ceil(4), self.reply_chars.div_ceil(4))
+    }
+}
@@ -0,0 +1,441 @@
+use crate::compare::Lane;
+use crate::export::ExportFormat;
+use crate::provider::{WireExchange, WireLog, WireOutcome};
+use crate::session::{SessionId, SessionSummary};
+use crate::sound::SoundCues;
+use crate::themes::ThemePreference;
+
+const COMPARE_USAGE: &str = "usage: /compare <model> | /compare pick a|b | /compare off";
+const WORDS_USAGE: &str = "usage: /words whitespace|punctuation|subword";
+pub(crate) const ENV_USAGE: &str =
+    "usage: /env | /env <NAME>=<value> | /env unset <NAME> | /env cwd [<dir>] | /env clear";
+const EXPORT_USAGE: &str =
+    "usage: /export [--notes] <path> | /export html [--standalone] [--notes] <path>";
+
+/// The first word of every command. Input that starts with any other word
+/// after the `/`, like a path such as `/etc/hosts`, is a prompt.
+const COMMAND_WORDS: [&str; 27] = [
+    "assist",
+    "changelog",
+    "colorblind",
+    "commit",
+    "compare",
+    "debug",
+    "env",
+    "export",
+    "fork",
+    "issue",
+    "metrics",
+    "mute",
+    "new",
+    "open",
+    "pr",
+    "present",
+    "replace",
+    "root",
+    "roots",
+    "sessions",
+    "sound",
+    "target",
+    "theme",
+    "todos",
+    "watch",
+    "words",
+    "wrap",
+];
+
+/// Commands typed into the chat input with a leading `/`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum SlashCommand {
+    DebugRequests,
+    /// Frames that went over the frame budget, and where their time went.
+    DebugFrames,
+    /// The running log of turns and tool calls, live.
+    DebugLog,
+    /// Send the following prompts to the current model and this one.
+    CompareWith(String),
+    ComparePick(Lane),
+    CompareOff,
+    /// Leave this session for a new, empty one.
+    New,
+    /// Fork the session at its last message.
+    Fork,
+    /// List saved sessions and how they relate.
+    Sessions,
+    Open(String),
+    Export {
+        path: String,
+        include_notes: bool,
+        format: ExportFormat,
+    },
+    /// Show the bundled release notes.
+    Changelog,
+    /// Switch the input between wrapping and scrolling long lines.
+    Wrap,
+    /// Turn closing brackets and keeping indentation in the input on or off.
+    Assist,
+    /// Choose where Ctrl+Left, Ctrl+Right and Ctrl+Backspace stop in the
+    /// input, or show it.
+    Words(Option<xpui::WordRules>),
+    /// Show only the transcript, read-only, for presenting it.
+    Present,
+    Theme(ThemePreference),
+    /// List the theme files that `/theme <name>` can load.
+    ListThemes,
+    /// Toggle blue and orange in place of green and red.
+    ColorBlind,
+    /// Write a commit message for the staged changes, then commit.
+    Commit,
+    /// Describe the branch for a pull request, then open it with `gh`.
+    PullRequest,
+    /// Attach an issue, by number or URL, as context for the next prompt.
+    Issue(String),
+    /// Choose which events ring the bell.
+    Sound(SoundCues),
+    /// Show which events ring the bell.
+    ListSounds,
+    /// Silence every sound cue, or bring them back.
+    Mute,
+    /// Send `prompt` again whenever files matching `pattern` change.
+    Watch {
+        pattern: String,
+        prompt: String,
+    },
+    /// Say what `/watch` is watching.
+    WatchStatus,
+    WatchOff,
+    /// List the TODO, FIXME and HACK comments in the workspace.
+    Todos,
+    /// List the workspace roots.
+    Roots,
+    /// Add a directory as a workspace root and make it active.
+    RootAdd(String),
+    RootRemove(String),
+    /// Make the root with this name active.
+    RootSwitch(String),
+    /// Where commands run, and the devcontainer found for the active root.
+    Target,
+    TargetHost,
+    /// Exec into the running devcontainer of the active root.
+    TargetDevContainer,
+    TargetContainer {
+        name: String,
+        workdir: Option<String>,
+    },
+    /// The variables and directory the session's commands run with.
+    Env,
+    EnvSet {
+        name: String,
+        value: String,
+    },
+    EnvUnset(String),
+    /// Start commands in this directory, relative to where they would; back
+    /// where they would when `None`.
+    EnvCwd(Option<String>),
+    EnvClear,
+    /// Show what the opt-in metrics recorded, or how to turn them on.
+    Metrics,
+    MetricsOn,
+    /// Stop recording metrics and delete what was recorded.
+    MetricsOff,
+    MetricsExport(String),
+    /// Preview replacing `find` with `with` in the files the reply is
+    /// waiting to write.
+    Replace {
+        find: String,
+        with: String,
+    },
+    /// Make the previewed replacement in those pending writes.
+    ReplaceApply,
+}
+
+impl SlashCommand {
+    /// Returns `None` for ordinary messages, including ones that start with
+    /// `/` but not a command word, and an error for a malformed command, so
+    /// typos in its arguments are not sent to the model.
+    pub(crate) fn parse(input: &str) -> Option<Result<Self, String>> {
+        let command = input.strip_prefix('/')?;
+        let words = command.split_whitespace().collect::<Vec<_>>();
+        if !words
+            .first()
+            .is_some_and(|word| COMMAND_WORDS.contains(word))
+        {
+            return None;
+        }
+        let parsed = match words.as_slice() {
+            ["debug", "requests"] => Ok(Self::DebugRequests),
+            ["debug", "frames"] => Ok(Self::DebugFrames),
+            ["debug", "log"] => Ok(Self::DebugLog),
+            ["debug", ..] => Err("usage: /debug requests | /debug frames | /debug log".to_string()),
+            ["compare", "off"] => Ok(Self::CompareOff),
+            ["compare", "pick", "a" | "A"] => Ok(Self::ComparePick(Lane::A)),
+            ["compare", "pick", "b" | "B"] => Ok(Self::ComparePick(Lane::B)),
+            ["compare", "pick", ..] | ["compare"] => Err(COMPARE_USAGE.to_string()),
+            ["compare", model @ ..] => Ok(Self::CompareWith(model.join(" "))),
+            ["new"] => Ok(Self::New),
+            ["fork"] => Ok(Self::Fork),
+            ["sessions"] => Ok(Self::Sessions),
+            ["open", id] => Ok(Self::Open(id.to_string())),
+            ["open", ..] => Err("usage: /open <session-id>".to_string()),
+            ["export", "html", args @ ..] => parse_export(args, ExportFormat::Html),
+            ["export", args @ ..] => parse_export(args, ExportFormat::Markdown),
+            ["changelog"] => Ok(Self::Changelog),
+            ["wrap"] => Ok(Self::Wrap),
+            ["assist"] => Ok(Self::Assist),
+            ["words"] => Ok(Self::Words(None)),
+            ["words", name] => xpui::WordRules::ALL
+                .into_iter()
+                .find(|rules| rules.name() == *name)
+                .map(|rules| Self::Words(Some(rules)))
+                .ok_or_else(|| WORDS_USAGE.to_string()),
+            ["words", ..] => Err(WORDS_USAGE.to_string()),
+            ["present"] => Ok(Self::Present),
+            ["theme"] => Ok(Self::ListThemes),
+            ["theme", name] => name.parse().map(Self::Theme),
+            ["theme", ..] => Err("usage: /theme auto|light|dark|<name>".to_string()),
+            ["colorblind"] => Ok(Self::ColorBlind),
+            ["commit"] => Ok(Self::Commit),
+            ["pr"] => Ok(Self::PullRequest),
+            ["issue", reference] => Ok(Self::Issue(reference.to_string())),
+            ["issue", ..] => Err("usage: /issue <number-or-url>".to_string()),
+            ["sound"] => Ok(Self::ListSounds),
+            ["sound", cues @ ..] => cues.join("").parse().map(Self::Sound),
+            ["mute"] => Ok(Self::Mute),
+            ["watch"] => Ok(Self::WatchStatus),
+            ["watch", "off"] => Ok(Self::WatchOff),
+            ["watch", pattern, prompt @ ..] if !prompt.is_empty() => Ok(Self::Watch {
+                pattern: pattern.to_string(),
+                prompt: prompt.join(" "),
+            }),
+            ["todos"] => Ok(Self::Todos),
+            ["roots"] | ["root"] => Ok(Self::Roots),
+            ["root", "add", path @ ..] if !path.is_empty() => Ok(Self::RootAdd(path.join(" "))),
+            ["root", "remove", name] => Ok(Self::RootRemove(name.to_string())),
+            ["root", name] if !["add", "remove"].contains(name) => {
+                Ok(Self::RootSwitch(name.to_string()))
+            }
+            ["root", ..] => Err(
+                "usage: /roots | /root <name> | /root add <path> | /root remove <name>".to_string(),
+            ),
+            ["target"] => Ok(Self::Target),
+            ["target", "host"] => Ok(Self::TargetHost),
+            ["target", "devcontainer"] => Ok(Self::TargetDevContainer),
+            ["target", "container", name] => Ok(Self::TargetContainer {
+                name: name.to_string(),
+                workdir: None,
+            }),
+            ["target", "container", name, workdir] => Ok(Self::TargetContainer {
+                name: name.to_string(),
+                workdir: Some(workdir.to_string()),
+            }),
+            ["target", ..] => Err(
+                "usage: /target | /target host | /target devcontainer | /target container <name> [<workdir>]"
+                    .to_string(),
+            ),
+            ["env"] => Ok(Self::Env),
+            ["env", "clear"] => Ok(Self::EnvClear),
+            ["env", "unset", name] => Ok(Self::EnvUnset(name.to_string())),
+            ["env", "cwd"] => Ok(Self::EnvCwd(None)),
+            ["env", "cwd", dir @ ..] => Ok(Self::EnvCwd(Some(dir.join(" ")))),
+            ["env", assignment @ ..] => match assignment.join(" ").split_once('=') {
+                Some((name, value)) if is_env_name(name) => Ok(Self::EnvSet {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                }),
+                _ => Err(ENV_USAGE.to_string()),
+            },
+            ["metrics"] => Ok(Self::Metrics),
+            ["metrics", "on"] => Ok(Self::MetricsOn),
+            ["metrics", "off"] => Ok(Self::MetricsOff),
+            ["metrics", "export", path] => Ok(Self::MetricsExport(path.to_string())),
+            ["metrics", ..] => Err(
+                "usage: /metrics | /metrics on | /metrics off | /metrics export <path>".to_string(),
+            ),
+            ["watch", ..] => Err("usage: /watch <glob> <prompt> | /watch off".to_string()),
+            ["replace", "apply"] => Ok(Self::ReplaceApply),
+            ["replace", find, with] => Ok(Self::Replace {
+                find: find.to_string(),
+                with: with.to_string(),
+            }),
+            ["replace", ..] => Err("usage: /replace <find> <with> | /replace apply".to_string()),
+            _ => Err(format!("unknown command `{input}`")),
+        };
+        Some(parsed)
+    }
+}
+
+/// A prompt as it is sent: a leading `//` stands for `/`, for a prompt that
+/// starts like a command.
+pub(crate) fn prompt_text(input: &str) -> &str {
+    if input.starts_with("//") {
+        &input[1..]
+    } else {
+        input
+    }
+}
+
+/// Letters, digits and `_`, not starting with a digit, as shells take
+/// variable names.
+fn is_env_name(name: &str) -> bool {
+    name.starts_with(|ch: char| !ch.is_ascii_digit())
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+/// `--notes` and a path, in any order. HTML is always one file with its
+/// styles inline, so `--standalone` only says so.
+fn parse_export(args: &[&str], format: ExportFormat) -> Result<SlashCommand, String> {
+    let mut include_notes = false;
+    let mut path = None;
+    for arg in args {
+        match *arg {
+            "--notes" => include_notes = true,
+            "--standalone" if format == ExportFormat::Html => {}
+            arg if arg.starts_with("--") || path.is_some() => return Err(EXPORT_USAGE.to_string()),
+            arg => path = Some(arg.to_string()),
+        }
+    }
+    Ok(SlashCommand::Export {
+        path: path.ok_or(EXPORT_USAGE)?,
+        include_notes,
+        format,
+    })
+}
+
+pub(crate) fn debug_requests_text(log: Option<&WireLog>) -> String {
+    let Some(log) = log else {
+        return "wire log is off; start with --wire-log <PATH> to record requests".to_string();
+    };
+
+    let mut out = format!("wire log: {}", log.path().display());
+    if let Some(err) = log.write_error() {
+        out.push_str(&format!(" (writing stopped: {err})"));
+    }
+    let recent = log.recent();
+    if recent.is_empty() {
+        out.push_str("\nno requests yet");
+    }
+    for exchange in recent {
+        out.push('\n');
+        out.push_str(&exchange_line(&exchange));
+    }
+    out
+}
+
+pub(crate) fn debug_frames_text(report: &xpui::FrameReport) -> String {
+    if report.frames == 0 {
+        return "no frames timed yet; only the terminal backend times its frames".to_string();
+    }
+    let mut out = format!(
+        "{} frames drawn, {} over the {}ms budget",
+        report.frames,
+        report.slow_frames,
+        report.budget.as_millis()
+    );
+    match (&report.log, &report.log_error) {
+        (_, Some(err)) => out.push_str(&format!("; frame log stopped: {err}")),
+        (Some(path), None) => out.push_str(&format!("; logged to {}", path.display())),
+        (None, None) => {}
+    }
+    for frame in report.recent.iter().rev() {
+        let ago = frame.at.elapsed().unwrap_or_default().as_secs();
+        out.push_str(&format!("\n{ago}s ago: {}", frame.timings.describe()));
+    }
+    out
+}
+
+fn exchange_line(exchange: &WireExchange) -> String {
+    let request = format!(
+        "#{} {} · {} messages · {}",
+        exchange.id,
+        exchange.model,
+        exchange.message_count,
+        format_bytes(exchange.request_bytes)
+    );
+    let outcome = match &exchange.outcome {
+        WireOutcome::Pending => "streaming".to_string(),
+        WireOutcome::Done => "done".to_string(),
+        WireOutcome::Failed(err) => format!("failed: {err}"),
+        WireOutcome::Cancelled => "cancelled".to_string(),
+    };
+    let mut line = format!(
+        "{request} → {outcome}, {} chunks, {}",
+        exchange.deltas,
+        format_bytes(exchange.response_bytes)
+    );
+    if let Some(elapsed) = exchange.elapsed {
+        line.push_str(&format!(" in {:.1}s", elapsed.as_secs_f32()));
+    }
+    line
+}
+
+fn format_bytes(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{bytes} B")
+    } else {
+        format!("{:.1} KB", bytes as f32 / 1024.0)
+    }
+}
+
+/// Text form of the session picker. Forks point at their parent and parents
+/// list their forks, so related sessions can be reached with `/open`.
+pub(crate) fn sessions_text(sessions: &[SessionSummary], current: Option<&SessionId>) -> String {
+    if sessions.is_empty() {
+        return "no saved sessions".to_string();
+    }
+
+    let mut out = "sessions, newest first (switch with /open <id>):".to_string();
+    for summary in sessions {
+        let marker = if current == Some(&summary.id) {
+            "●"
+        } else {
+            " "
+        };
+        let prompt = summary.last_prompt.chars().take(40).collect::<String>();
+        out.push_str(&format!("\n{marker} {} \"{prompt}\"", summary.id));
+        if summary.turns > 0 {
+            out.push_str(&format!(" · {} turns", summary.turns));
+        }
+        if let Some(origin) = &summary.origin {
+            out.push_str(&format!(
+                " · fork of {} at #{}",
+                origin.parent,
+                origin.at + 1
+            ));
+        }
+        let forks = sessions
+            .iter()
+            .filter(|other| {
+                other
+                    .origin
+                    .as_ref()
+                    .is_some_and(|o| o.parent == summary.id)
+            })
+            .map(|other| other.id.to_string())
+            .collect::<Vec<_>>();
+        if !forks.is_empty() {
+            out.push_str(&format!(" · forks: {}", forks.join(", ")));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_command_words_make_a_command() {
+        assert_eq!(SlashCommand::parse("/todos"), Some(Ok(SlashCommand::Todos)));
+        assert!(matches!(SlashCommand::parse("/open"), Some(Err(_))));
+        assert_eq!(
+            SlashCommand::parse("/etc/hosts is wrong, fix the DNS entry"),
+            None
+        );
+        assert_eq!(SlashCommand::parse("/usr is full"), None);
+        assert_eq!(SlashCommand::parse("//todos are piling up"), None);
+        assert_eq!(prompt_text("//todos are piling up"), "/todos are piling up");
+        assert_eq!(prompt_text("/etc/hosts"), "/etc/hosts");
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::Path;
+
+use crate::git;
+use crate::provider::{CompletionRequest, RequestMessage, RequestRole};
+
+/// The staged changes in `root`, or why there are none to describe.
+pub(crate) fn staged_diff(root: &Path) -> Result<String, String> {
+    let args = ["diff", "--cached", "--no-color", "--no-ext-diff"];
+    let diff = git::run("git", root, &args, None)
+        .map_err(|err| format!("cannot read staged changes: {err}"))?;
+    if diff.trim().is_empty() {
+        return Err("nothing staged; stage changes with `git add` first".to_string());
+    }
+    Ok(diff)
+}
+
+/// Asks for a commit message for `diff`, as a one-off request outside the
+/// conversation.
+pub(crate) fn request(model: String, diff: &str, conventional: bool) -> CompletionRequest {
+    let style = if conventional {
+        "Use the Conventional Commits format, `type(scope): summary`, for the first line."
+    } else {
+        "Start with a summary line in the imperative mood."
+    };
+    let diff = git::clip_diff(diff);
+    let prompt = format!(
+        "Write a git commit message for the staged diff below. {style} Keep the \
+         first line under 72 characters, then a blank line and a short body if \
+         the change needs explaining. Reply with the message only.\n\n```diff\n{diff}\n```"
+    );
+    CompletionRequest {
+        model,
+        messages: vec![RequestMessage {
+            role: RequestRole::User,
+            content: prompt,
+            images: Vec::new(),
+            tool_call: None,
+        }],
+        tools: Vec::new(),
+    }
+}
+
+/// Commits the staged changes with `message` and returns git's summary line.
+pub(crate) fn commit(root: &Path, message: &str) -> Result<String, String> {
+    let out = git::run("git", root, &["commit", "--file", "-"], Some(message))?;
+    Ok(out.lines().next().unwrap_or_default().to_string())
+}
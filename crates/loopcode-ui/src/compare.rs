@@ -0,0 +1,206 @@
+use std::time::{Duration, Instant};
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::provider::{ChatProvider, CompletionRequest, CompletionStream, StreamEvent};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Lane {
+    A,
+    B,
+}
+
+impl Lane {
+    fn label(self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::B => "B",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum LaneStatus {
+    Streaming,
+    Done,
+    Failed(String),
+}
+
+/// One model's side of a comparison, with the numbers shown in its header.
+pub(crate) struct CompareLane {
+    pub(crate) model: String,
+    pub(crate) text: String,
+    pub(crate) status: LaneStatus,
+    stream: Option<CompletionStream>,
+    started: Instant,
+    first_chunk: Option<Duration>,
+    elapsed: Option<Duration>,
+    chunks: usize,
+}
+
+impl CompareLane {
+    fn start(provider: &dyn ChatProvider, request: CompletionRequest) -> Self {
+        let model = request.model.clone();
+        let (stream, status) = match provider.stream_completion(request) {
+            Ok(stream) => (Some(stream), LaneStatus::Streaming),
+            Err(err) => (None, LaneStatus::Failed(err.to_string())),
+        };
+        Self {
+            model,
+            text: String::new(),
+            status,
+            stream,
+            started: Instant::now(),
+            first_chunk: None,
+            elapsed: None,
+            chunks: 0,
+        }
+    }
+
+    fn poll(&mut self) {
+        while let Some(event) = self.stream.as_ref().and_then(CompletionStream::try_next) {
+            match event {
+                StreamEvent::Delta(delta) => {
+                    self.first_chunk
+                        .get_or_insert_with(|| self.started.elapsed());
+                    self.chunks += 1;
+                    self.text.push_str(&delta);
+                }
+                StreamEvent::ToolCall {
+                    name, arguments, ..
+                } => {
+                    self.chunks += 1;
+                    self.text
+                        .push_str(&format!("\n[tool call] {name} {arguments}\n"));
+                }
+                StreamEvent::Done => self.finish(LaneStatus::Done),
+                StreamEvent::Failed(err) => self.finish(LaneStatus::Failed(err.to_string())),
+            }
+        }
+    }
+
+    fn finish(&mut self, status: LaneStatus) {
+        self.status = status;
+        self.stream = None;
+        self.elapsed = Some(self.started.elapsed());
+    }
+
+    fn stats(&self) -> String {
+        let state = match &self.status {
+            LaneStatus::Streaming => "streaming".to_string(),
+            LaneStatus::Done => "done".to_string(),
+            LaneStatus::Failed(err) => format!("failed: {err}"),
+        };
+        // Providers do not report usage yet; four characters per token is the
+        // usual rough estimate.
+        let tokens = self.text.chars().count().div_ceil(4);
+        let mut stats = format!("{state} · {} chunks · ~{tokens} tokens", self.chunks);
+        if let Some(first) = self.first_chunk {
+            stats.push_str(&format!(" · first {}ms", first.as_millis()));
+        }
+        let elapsed = self.elapsed.unwrap_or_else(|| self.started.elapsed());
+        stats.push_str(&format!(" · {:.1}s", elapsed.as_secs_f32()));
+        stats
+    }
+}
+
+/// The same prompt sent to two models, shown side by side. Both columns
+/// share one scroll offset so matching parts of the replies stay aligned.
+pub(crate) struct Comparison {
+    pub(crate) user_index: usize,
+    lanes: [CompareLane; 2],
+    scroll_offset: u16,
+}
+
+impl Comparison {
+    pub(crate) fn start(
+        provider: &dyn ChatProvider,
+        request: CompletionRequest,
+        model_b: &str,
+        user_index: usize,
+    ) -> Self {
+        let mut request_b = request.clone();
+        request_b.model = model_b.to_string();
+        Self {
+            user_index,
+            lanes: [
+                CompareLane::start(provider, request),
+                CompareLane::start(provider, request_b),
+            ],
+            scroll_offset: 0,
+        }
+    }
+
+    pub(crate) fn poll(&mut self) {
+        self.lanes.iter_mut().for_each(CompareLane::poll);
+    }
+
+    pub(crate) fn is_streaming(&self) -> bool {
+        self.lanes
+            .iter()
+            .any(|lane| lane.status == LaneStatus::Streaming)
+    }
+
+    pub(crate) fn into_lane(self, lane: Lane) -> CompareLane {
+        let [a, b] = self.lanes;
+        match lane {
+            Lane::A => a,
+            Lane::B => b,
+        }
+    }
+
+    pub(crate) fn scroll_by(&mut self, lines: i16) {
+        self.scroll_offset = self.scroll_offset.saturating_add_signed(lines);
+    }
+
+    /// Lays both lanes out as one block of text `width` columns wide and
+    /// returns it with the scroll offset clamped to the content.
+    pub(crate) fn render(&mut self, width: usize, viewport_lines: u16) -> (String, u16) {
+        let column_width = width.saturating_sub(3).max(2) / 2;
+        let columns = [Lane::A, Lane::B].map(|lane| {
+            let lane_state = &self.lanes[lane as usize];
+            let mut lines = wrap(
+                &format!("{} {}", lane.label(), lane_state.model),
+                column_width,
+            );
+            lines.extend(wrap(&lane_state.stats(), column_width));
+            lines.push("─".repeat(column_width));
+            lines.extend(wrap(&lane_state.text, column_width));
+            lines
+        });
+
+        let rows = columns[0].len().max(columns[1].len());
+        let max_offset = (rows as u16).saturating_sub(viewport_lines);
+        self.scroll_offset = self.scroll_offset.min(max_offset);
+
+        let out = (0..rows)
+            .map(|row| {
+                let left = columns[0].get(row).map(String::as_str).unwrap_or_default();
+                let right = columns[1].get(row).map(String::as_str).unwrap_or_default();
+                let padding = column_width.saturating_sub(left.width());
+                format!("{left}{} │ {right}", " ".repeat(padding))
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        (out, self.scroll_offset)
+    }
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for source in text.split('\n') {
+        let mut line = String::new();
+        let mut col = 0usize;
+        for ch in source.chars() {
+            let w = UnicodeWidthChar::width(ch).unwrap_or(0);
+            if col > 0 && col + w > width {
+                lines.push(std::mem::take(&mut line));
+                col = 0;
+            }
+            line.push(ch);
+            col += w;
+        }
+        lines.push(line);
+    }
+    lines
+}
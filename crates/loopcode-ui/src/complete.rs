@@ -0,0 +1,173 @@
+use std::path::Path;
+
+use crate::workspace::Workspace;
+
+/// Candidates shown at once; the popup says how many there are in all.
+const MAX_SHOWN: usize = 8;
+
+/// An open path completion in the input: the characters it replaces and the
+/// candidates, as a menu.
+pub(crate) struct Completion {
+    /// Char range of the partial path in the input.
+    start: usize,
+    end: usize,
+    candidates: Vec<String>,
+    pub(crate) menu: xpui::ContextMenuState,
+}
+
+impl Completion {
+    /// The candidate at menu row `item` and the char range it replaces.
+    pub(crate) fn choice(&self, item: usize) -> Option<(usize, usize, &str)> {
+        let candidate = self.candidates.get(item)?;
+        Some((self.start, self.end, candidate))
+    }
+}
+
+/// What Tab does at `cursor` in `value`.
+pub(crate) enum Outcome {
+    /// Replace the char range with the text.
+    Insert(usize, usize, String),
+    /// Several candidates and no longer common prefix: pick one.
+    Choose(Completion),
+}
+
+/// Completes the path that ends at `cursor`, relative to the active root of
+/// `workspace` or the root it starts with the name of. Paths are only
+/// completed inside a backtick span or when they look like one, so Tab after
+/// a plain word does nothing.
+pub(crate) fn complete(value: &str, cursor: usize, workspace: &Workspace) -> Option<Outcome> {
+    let (start, partial) = partial_path(value, cursor)?;
+    let (root, skip) = workspace.base(&partial);
+    let mut candidates = candidates(root, &partial, skip);
+    // The other roots, by name, for a path into one of them.
+    if !partial.contains('/') && workspace.roots().len() > 1 {
+        let names = workspace
+            .roots()
+            .iter()
+            .filter(|other| other.as_path() != workspace.active())
+            .map(|other| format!("{}/", Workspace::name(other)))
+            .filter(|name| name.starts_with(&partial) && !candidates.contains(name))
+            .collect::<Vec<_>>();
+        candidates.extend(names);
+        candidates.sort();
+    }
+    let common = common_prefix(&candidates);
+    if candidates.len() == 1 || common.chars().count() > partial.chars().count() {
+        return Some(Outcome::Insert(start, cursor, common));
+    }
+    if candidates.is_empty() {
+        return None;
+    }
+    let title = if candidates.len() > MAX_SHOWN {
+        format!("{} matches, first {MAX_SHOWN}", candidates.len())
+    } else {
+        format!("{} matches", candidates.len())
+    };
+    let candidates = candidates.into_iter().take(MAX_SHOWN).collect::<Vec<_>>();
+    let items = candidates.iter().map(xpui::MenuItem::new).collect();
+    Some(Outcome::Choose(Completion {
+        start,
+        end: cursor,
+        candidates,
+        menu: xpui::ContextMenuState::new(title, items),
+    }))
+}
+
+/// The path being typed before `cursor`, and the char index it starts at.
+fn partial_path(value: &str, cursor: usize) -> Option<(usize, String)> {
+    let before = value.chars().take(cursor).collect::<Vec<_>>();
+    let start = before
+        .iter()
+        .rposition(|ch| ch.is_whitespace() || *ch == '`')
+        .map_or(0, |at| at + 1);
+    let partial = before[start..].iter().collect::<String>();
+    let in_backticks = before[..start].iter().filter(|ch| **ch == '`').count() % 2 == 1;
+    let looks_like_path = ["./", "../", "~/", "/"]
+        .iter()
+        .any(|prefix| partial.starts_with(prefix))
+        || partial.contains('/');
+    (in_backticks || looks_like_path).then_some((start, partial))
+}
+
+/// Entries of the directory `partial` points into whose names continue it,
+/// sorted, directories with a trailing `/`. Dotfiles only when asked for.
+/// The first `skip` bytes of `partial` name `root` rather than a directory
+/// in it.
+fn candidates(root: &Path, partial: &str, skip: usize) -> Vec<String> {
+    let (dir, name) = match partial.rfind('/') {
+        Some(at) => partial.split_at(at + 1),
+        None => ("", partial),
+    };
+    let dir_path = match dir.strip_prefix("~/") {
+        Some(rest) => match std::env::var_os("HOME") {
+            Some(home) => Path::new(&home).join(rest),
+            None => return Vec::new(),
+        },
+        None => root.join(&dir[skip..]),
+    };
+    let Ok(entries) = std::fs::read_dir(dir_path) else {
+        return Vec::new();
+    };
+    let mut out = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let file_name = entry.file_name().into_string().ok()?;
+            if !file_name.starts_with(name)
+                || (file_name.starts_with('.') && !name.starts_with('.'))
+            {
+                return None;
+            }
+            let slash = if entry.file_type().ok()?.is_dir() {
+                "/"
+            } else {
+                ""
+            };
+            Some(format!("{dir}{file_name}{slash}"))
+        })
+        .collect::<Vec<_>>();
+    out.sort();
+    out
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let Some((first, rest)) = candidates.split_first() else {
+        return String::new();
+    };
+    let mut len = first.len();
+    for candidate in rest {
+        len = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((at, ch), _)| at + ch.len_utf8())
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_only_path_like_words_or_backtick_spans() {
+        assert_eq!(
+            partial_path("see src/ma", 10),
+            Some((4, "src/ma".to_string()))
+        );
+        assert_eq!(
+            partial_path("open `Carg", 10),
+            Some((6, "Carg".to_string()))
+        );
+        assert_eq!(partial_path("open Carg", 9), None);
+        assert_eq!(partial_path("`a` Carg", 8), None);
+    }
+
+    #[test]
+    fn common_prefix_stops_at_first_difference() {
+        let names = ["src/main.rs", "src/mod.rs"].map(String::from);
+        assert_eq!(common_prefix(&names), "src/m");
+        assert_eq!(common_prefix(&[]), "");
+    }
+}
@@ -0,0 +1,109 @@
+use crate::paste;
+
+/// How the code blocks of a message go on the clipboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CopyFormat {
+    /// The code alone, blocks separated by a blank line.
+    Raw,
+    /// Each block with its fences and language, ready to paste into Markdown.
+    Fenced,
+    /// Each block as a quoted heredoc, ready to paste into a shell.
+    Heredoc,
+}
+
+impl CopyFormat {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Raw => "Copy code",
+            Self::Fenced => "Copy code with fences",
+            Self::Heredoc => "Copy code as heredoc",
+        }
+    }
+}
+
+/// A fenced block of a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct CodeBlock {
+    /// What the fence names, or a guess from the code when it names nothing;
+    /// empty when there is no telling.
+    pub(crate) language: String,
+    pub(crate) code: String,
+}
+
+/// The closed code blocks in `text`; one still streaming is left out.
+pub(crate) fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let Some(language) = fence(line) else {
+            continue;
+        };
+        let mut code = Vec::new();
+        let mut closed = false;
+        for line in lines.by_ref() {
+            if fence(line).is_some() {
+                closed = true;
+                break;
+            }
+            code.push(line);
+        }
+        if !closed {
+            break;
+        }
+        let code = code.join("\n");
+        let language = if language.is_empty() {
+            paste::code_language(&code).unwrap_or_default()
+        } else {
+            language
+        };
+        blocks.push(CodeBlock {
+            language: language.to_string(),
+            code,
+        });
+    }
+    blocks
+}
+
+pub(crate) fn format(blocks: &[CodeBlock], format: CopyFormat) -> String {
+    let blocks = blocks.iter().map(|block| match format {
+        CopyFormat::Raw => block.code.clone(),
+        CopyFormat::Fenced => format!("```{}\n{}\n```", block.language, block.code),
+        CopyFormat::Heredoc => {
+            // A delimiter the code does not end early.
+            let mut delimiter = "EOF".to_string();
+            while block.code.lines().any(|line| line == delimiter) {
+                delimiter.push('_');
+            }
+            format!("cat <<'{delimiter}'\n{}\n{delimiter}", block.code)
+        }
+    });
+    blocks.collect::<Vec<_>>().join("\n\n")
+}
+
+/// The language of a line that opens or closes a fenced block, `""` when it
+/// names none.
+fn fence(line: &str) -> Option<&str> {
+    line.trim_start().strip_prefix("```").map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_copy_in_each_format() {
+        let text = "Try this:\n```sh\necho EOF\nEOF\n```\nand\n```\nfn main() {\n    let mut n = 1;\n}\n```\n```rust\nunfinished";
+        let blocks = code_blocks(text);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].language, "rust");
+        assert_eq!(format(&blocks[..1], CopyFormat::Raw), "echo EOF\nEOF");
+        assert_eq!(
+            format(&blocks[..1], CopyFormat::Fenced),
+            "```sh\necho EOF\nEOF\n```"
+        );
+        assert_eq!(
+            format(&blocks[..1], CopyFormat::Heredoc),
+            "cat <<'EOF_'\necho EOF\nEOF\nEOF_"
+        );
+    }
+}
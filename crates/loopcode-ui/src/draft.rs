@@ -0,0 +1,99 @@
+use crate::provider::{CompletionStream, ProviderError, StreamEvent};
+
+/// What a draft becomes once the user submits it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DraftPurpose {
+    Commit,
+    PullRequest,
+}
+
+impl DraftPurpose {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Commit => "commit message",
+            Self::PullRequest => "pull request description",
+        }
+    }
+}
+
+/// Text the model writes for the user to edit in the input before it is
+/// used: streaming in first, then editable.
+pub(crate) struct Draft {
+    pub(crate) purpose: DraftPurpose,
+    state: DraftState,
+}
+
+enum DraftState {
+    Writing {
+        stream: CompletionStream,
+        text: String,
+    },
+    Editing,
+}
+
+/// What polling a draft turned up.
+pub(crate) enum DraftEvent {
+    /// The text is complete and the draft is now being edited.
+    Written(String),
+    Failed(ProviderError),
+}
+
+impl Draft {
+    pub(crate) fn start(purpose: DraftPurpose, stream: CompletionStream) -> Self {
+        Self {
+            purpose,
+            state: DraftState::Writing {
+                stream,
+                text: String::new(),
+            },
+        }
+    }
+
+    pub(crate) fn is_editing(&self) -> bool {
+        matches!(self.state, DraftState::Editing)
+    }
+
+    pub(crate) fn poll(&mut self) -> Option<DraftEvent> {
+        let DraftState::Writing { stream, text } = &mut self.state else {
+            return None;
+        };
+        while let Some(event) = stream.try_next() {
+            match event {
+                StreamEvent::Delta(delta) => text.push_str(&delta),
+                StreamEvent::ToolCall { .. } => {}
+                StreamEvent::Done => {
+                    let text = clean_reply(text);
+                    self.state = DraftState::Editing;
+                    return Some(DraftEvent::Written(text));
+                }
+                StreamEvent::Failed(err) => return Some(DraftEvent::Failed(err)),
+            }
+        }
+        None
+    }
+}
+
+/// The reply without the code fence some models wrap it in.
+fn clean_reply(reply: &str) -> String {
+    let trimmed = reply.trim();
+    let unfenced = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.split_once('\n'))
+        .and_then(|(_, body)| body.trim_end().strip_suffix("```"));
+    unfenced.unwrap_or(trimmed).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fenced_replies_lose_their_fence() {
+        assert_eq!(clean_reply("```\nFix typo\n```\n"), "Fix typo");
+        assert_eq!(
+            clean_reply("```text\nFix typo\n\nBody\n```"),
+            "Fix typo\n\nBody"
+        );
+        assert_eq!(clean_reply("  Fix typo\n"), "Fix typo");
+    }
+}
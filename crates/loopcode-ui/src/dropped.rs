@@ -0,0 +1,165 @@
+use std::path::{Path, PathBuf};
+
+use crate::workspace::Workspace;
+
+/// Dropped files with these extensions become image attachments; the rest
+/// are mentioned by path.
+const IMAGE_TYPES: [(&str, &str); 5] = [
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+];
+
+/// The MIME type of an image file, by its extension.
+pub(crate) fn image_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    IMAGE_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, mime_type)| *mime_type)
+}
+
+/// The files in a paste, when it is what a terminal pastes for files dragged
+/// onto it: absolute paths, quoted or escaped as a shell would, or `file://`
+/// URIs. `None` unless every word is one that exists, so pasted prose or a
+/// relative path stays text.
+pub(crate) fn dropped_paths(text: &str) -> Option<Vec<PathBuf>> {
+    let paths = shell_words(text.trim())?
+        .into_iter()
+        .map(|word| match word.strip_prefix("file://") {
+            Some(uri) => percent_decode(uri),
+            None => word,
+        })
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    let all_exist = paths.iter().all(|path| path.is_absolute() && path.exists());
+    (!paths.is_empty() && all_exist).then_some(paths)
+}
+
+/// `path` as an `@` mention, as the Files panel shows it, in backticks
+/// when it has spaces.
+pub(crate) fn mention(workspace: &Workspace, path: &Path) -> String {
+    let shown = workspace.shorten(path);
+    if shown.contains(char::is_whitespace) {
+        format!("@`{shown}`")
+    } else {
+        format!("@{shown}")
+    }
+}
+
+/// Splits `text` on whitespace outside quotes, with `\` escaping the next
+/// character outside single quotes. `None` for an unclosed quote.
+fn shell_words(text: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        ch => word.push(ch),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => word.push(chars.next()?),
+                        ch => word.push(ch),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                word.push(chars.next()?);
+            }
+            ch if ch.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            ch => {
+                in_word = true;
+                word.push(ch);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Some(words)
+}
+
+/// `%20` and the like in a URI path, as bytes; invalid escapes stay as they
+/// are.
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut at = 0;
+    while at < bytes.len() {
+        let escaped = (bytes[at] == b'%')
+            .then(|| bytes.get(at + 1..at + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                at += 3;
+            }
+            None => {
+                out.push(bytes[at]);
+                at += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terminal_drops_are_read_back_as_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let plain = root.join("notes.md");
+        let spaced = root.join("my shot.png");
+        std::fs::write(&plain, "").unwrap();
+        std::fs::write(&spaced, "").unwrap();
+        let (plain_text, spaced_text) = (plain.display(), spaced.display());
+
+        let escaped = format!(
+            "{plain_text} {}",
+            spaced_text.to_string().replace(' ', "\\ ")
+        );
+        assert_eq!(
+            dropped_paths(&escaped),
+            Some(vec![plain.clone(), spaced.clone()])
+        );
+        assert_eq!(
+            dropped_paths(&format!("'{spaced_text}' ")),
+            Some(vec![spaced.clone()])
+        );
+        let uri = format!("file://{}", spaced_text.to_string().replace(' ', "%20"));
+        assert_eq!(dropped_paths(&uri), Some(vec![spaced.clone()]));
+
+        assert_eq!(dropped_paths("fix the parser"), None);
+        assert_eq!(dropped_paths(&format!("{plain_text} and more")), None);
+        assert_eq!(dropped_paths(&format!("'{plain_text}")), None);
+        assert_eq!(image_type(&spaced), Some("image/png"));
+        assert_eq!(image_type(&plain), None);
+
+        let workspace = Workspace::new(root.to_path_buf());
+        assert_eq!(mention(&workspace, &spaced), "@`my shot.png`");
+    }
+}
@@ -0,0 +1,268 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::chat::{ChatMessage, MessageStatus, Role};
+use crate::highlight;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ExportFormat {
+    Markdown,
+    /// One page with its styles inline, readable without a server.
+    Html,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ExportOptions {
+    /// Notes are private by default; they are only written when asked for.
+    pub(crate) include_notes: bool,
+}
+
+pub(crate) fn write_markdown(
+    path: &Path,
+    messages: &[ChatMessage],
+    options: ExportOptions,
+) -> io::Result<()> {
+    write(path, markdown(messages, options))
+}
+
+/// Writes `messages` as a page in the colors of `theme`, tool calls and
+/// context folded away and code highlighted, that opens anywhere offline.
+pub(crate) fn write_html(
+    path: &Path,
+    messages: &[ChatMessage],
+    options: ExportOptions,
+    theme: &xpui::Theme,
+) -> io::Result<()> {
+    write(path, html(messages, options, theme))
+}
+
+fn write(path: &Path, contents: String) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
+
+fn markdown(messages: &[ChatMessage], options: ExportOptions) -> String {
+    let mut out = String::from("# loopcode transcript\n");
+    for message in messages {
+        if message.status == MessageStatus::Queued {
+            continue;
+        }
+        out.push_str(&format!(
+            "\n## {}\n\n{}\n",
+            message.role.label(),
+            message.text
+        ));
+        match message.status {
+            MessageStatus::Interrupted => out.push_str("\n_(interrupted)_\n"),
+            MessageStatus::Truncated => out.push_str("\n_(truncated)_\n"),
+            _ => {}
+        }
+        if options.include_notes
+            && let Some(note) = &message.note
+        {
+            out.push('\n');
+            for line in note.lines() {
+                out.push_str(&format!("> ✎ {line}\n"));
+            }
+        }
+    }
+    out
+}
+
+fn html(messages: &[ChatMessage], options: ExportOptions, theme: &xpui::Theme) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>loopcode transcript</title>\n<style>\n{}</style>\n</head>\n<body>\n\
+         <h1>loopcode transcript</h1>\n",
+        stylesheet(theme)
+    );
+    for message in messages {
+        if message.status == MessageStatus::Queued {
+            continue;
+        }
+        let role = message.role.label();
+        let body = match message.role {
+            // A tool message is the tool's name, then its arguments.
+            Role::Tool => {
+                let (name, arguments) = message.text.split_once(' ').unwrap_or((&message.text, ""));
+                format!(
+                    "<details>\n<summary>{role} · {}</summary>\n<pre>{}</pre>\n</details>\n",
+                    escape(name),
+                    escape(arguments)
+                )
+            }
+            Role::ToolResult => {
+                let first = message.text.lines().next().unwrap_or_default();
+                format!(
+                    "<details>\n<summary>{role} · {}</summary>\n<pre>{}</pre>\n</details>\n",
+                    escape(first),
+                    escape(&message.text)
+                )
+            }
+            Role::Context => {
+                let first = message.text.lines().next().unwrap_or_default();
+                format!(
+                    "<details>\n<summary>{role} · {}</summary>\n{}</details>\n",
+                    escape(first),
+                    html_body(&message.text)
+                )
+            }
+            _ => format!("<h2>{role}</h2>\n{}", html_body(&message.text)),
+        };
+        out.push_str(&format!("<section class=\"{role}\">\n{body}"));
+        match message.status {
+            MessageStatus::Interrupted => out.push_str("<p class=\"status\">interrupted</p>\n"),
+            MessageStatus::Truncated => out.push_str("<p class=\"status\">truncated</p>\n"),
+            _ => {}
+        }
+        if options.include_notes
+            && let Some(note) = &message.note
+        {
+            out.push_str(&format!("<blockquote>✎ {}</blockquote>\n", escape(note)));
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn stylesheet(theme: &xpui::Theme) -> String {
+    let color = |rgb: xpui::Rgb| format!("#{:06x}", rgb.0);
+    // Themes leave the window background to the terminal or OS.
+    let background = match theme.appearance {
+        xpui::Appearance::Dark => "#0d1117",
+        xpui::Appearance::Light => "#ffffff",
+    };
+    format!(
+        "body {{ margin: 0 auto; max-width: 60rem; padding: 1.5rem; background: {background}; \
+         color: {text}; font: 15px/1.5 system-ui, sans-serif; }}\n\
+         h1 {{ font-size: 1.25rem; }}\n\
+         section {{ margin: 1rem 0; padding: 0.5rem 1rem; border: 1px solid {border}; \
+         border-radius: 6px; }}\n\
+         section.you {{ background: {surface}; }}\n\
+         h2, summary, .status, .lang {{ color: {muted}; font-size: 0.85rem; }}\n\
+         h2 {{ margin: 0 0 0.25rem; }}\n\
+         summary {{ cursor: pointer; }}\n\
+         .text {{ white-space: pre-wrap; }}\n\
+         pre {{ overflow-x: auto; padding: 0.5rem; background: {surface}; \
+         border-radius: 4px; }}\n\
+         .lang {{ display: block; color: {accent}; }}\n\
+         blockquote {{ margin: 0.5rem 0; color: {muted}; white-space: pre-wrap; }}\n\
+         .kw {{ color: {keyword}; }}\n\
+         .str {{ color: {string}; }}\n\
+         .com {{ color: {comment}; font-style: italic; }}\n\
+         .num {{ color: {number}; }}\n\
+         .fn {{ color: {function}; }}\n\
+         .ty {{ color: {type_name}; }}\n\
+         .add {{ color: {added}; }}\n\
+         .del {{ color: {removed}; }}\n",
+        text = color(theme.text),
+        muted = color(theme.muted),
+        border = color(theme.border),
+        surface = color(theme.surface),
+        accent = color(theme.accent),
+        keyword = color(theme.syntax.keyword),
+        string = color(theme.syntax.string),
+        comment = color(theme.syntax.comment),
+        number = color(theme.syntax.number),
+        function = color(theme.syntax.function),
+        type_name = color(theme.syntax.type_name),
+        added = color(theme.added),
+        removed = color(theme.removed),
+    )
+}
+
+/// `text` with its fenced blocks as highlighted code and the rest as it
+/// was typed. A block still open at the end runs to the end.
+fn html_body(text: &str) -> String {
+    let mut out = String::new();
+    let mut prose = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let Some(lang) = line.trim_start().strip_prefix("```").map(str::trim) else {
+            prose.push(line);
+            continue;
+        };
+        push_prose(&mut out, &mut prose);
+        let code = lines
+            .by_ref()
+            .take_while(|line| !line.trim_start().starts_with("```"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        out.push_str("<pre>");
+        if !lang.is_empty() {
+            out.push_str(&format!("<span class=\"lang\">{}</span>", escape(lang)));
+        }
+        out.push_str("<code>");
+        let mut at = 0;
+        for (range, class) in highlight::classes(lang, &code) {
+            out.push_str(&escape(&code[at..range.start]));
+            out.push_str(&format!(
+                "<span class=\"{class}\">{}</span>",
+                escape(&code[range.clone()])
+            ));
+            at = range.end;
+        }
+        out.push_str(&escape(&code[at..]));
+        out.push_str("</code></pre>\n");
+    }
+    push_prose(&mut out, &mut prose);
+    out
+}
+
+fn push_prose(out: &mut String, prose: &mut Vec<&str>) {
+    if prose.iter().any(|line| !line.trim().is_empty()) {
+        out.push_str(&format!(
+            "<div class=\"text\">{}</div>\n",
+            escape(prose.join("\n").trim_matches('\n'))
+        ));
+    }
+    prose.clear();
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_folds_tool_calls_and_highlights_code() {
+        let messages = [
+            ChatMessage::new(Role::User, "is <b> safe?"),
+            ChatMessage::new(Role::Tool, "read_file {\"path\":\"a.rs\"}"),
+            ChatMessage::new(Role::Assistant, "Yes:\n```rust\nlet x = 1;\n```"),
+        ];
+        let page = html(
+            &messages,
+            ExportOptions {
+                include_notes: false,
+            },
+            &xpui::Theme::dark(),
+        );
+        assert!(page.contains("<div class=\"text\">is &lt;b&gt; safe?</div>"));
+        assert!(page.contains(
+            "<summary>tool · read_file</summary>\n<pre>{&quot;path&quot;:&quot;a.rs&quot;}</pre>"
+        ));
+        assert!(page.contains(
+            "<span class=\"lang\">rust</span><code><span class=\"kw\">let</span> x = \
+             <span class=\"num\">1</span>;</code>"
+        ));
+        assert!(!page.contains("<link") && !page.contains("<script"));
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use crate::chat::{ChatMessage, Role};
+use crate::provider::{ProviderError, ProviderErrorKind};
+
+/// Longest first line of an error that still makes the summary; a longer
+/// one, such as a JSON body on one line, is folded with the rest.
+const SUMMARY_CHARS: usize = 100;
+
+/// What went wrong, in a word, told from the error kind and what its detail
+/// says.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum FailureClass {
+    Network,
+    RateLimit,
+    Auth,
+    Server,
+    Timeout,
+    Request,
+    /// A replay cassette had no answer, or the answer could not be recorded.
+    Replay,
+}
+
+impl FailureClass {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::RateLimit => "rate limited",
+            Self::Auth => "auth",
+            Self::Server => "server error",
+            Self::Timeout => "timeout",
+            Self::Request => "bad request",
+            Self::Replay => "replay",
+        }
+    }
+
+    pub(crate) fn of(err: &ProviderError) -> Self {
+        let detail = err.detail().to_lowercase();
+        let says = |words: &[&str]| words.iter().any(|word| detail.contains(word));
+        match err.kind() {
+            ProviderErrorKind::NotRecorded | ProviderErrorKind::Recording => Self::Replay,
+            _ if says(&["429", "rate limit", "too many requests"]) => Self::RateLimit,
+            _ if says(&["401", "403", "unauthorized", "forbidden", "api key"]) => Self::Auth,
+            _ if says(&["timed out", "timeout"]) => Self::Timeout,
+            _ if says(&[
+                "500",
+                "502",
+                "503",
+                "504",
+                "internal server error",
+                "overloaded",
+            ]) =>
+            {
+                Self::Server
+            }
+            ProviderErrorKind::Disconnected => Self::Network,
+            ProviderErrorKind::InvalidRequest => Self::Request,
+        }
+    }
+
+    /// Whether the same request may well work a moment later.
+    fn is_transient(self) -> bool {
+        matches!(
+            self,
+            Self::Network | Self::RateLimit | Self::Server | Self::Timeout
+        )
+    }
+}
+
+/// What makes a message an error card: its first line is the summary and
+/// the rest, such as an HTTP body or a stack trace, stays folded until
+/// asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Failure {
+    pub(crate) class: FailureClass,
+    /// Sending the prompt above it again may work.
+    pub(crate) retry: bool,
+}
+
+impl Failure {
+    /// The card for `operation` failing with `err`. `resend` says whether the
+    /// prompt above the card is what failed, so retrying sends it again.
+    pub(crate) fn card(operation: &str, err: &ProviderError, resend: bool) -> ChatMessage {
+        let class = FailureClass::of(err);
+        let failure = Self {
+            class,
+            retry: resend && (class.is_transient() || err.kind().is_retryable()),
+        };
+        let detail = err.detail().trim();
+        let (first, rest) = detail.split_once('\n').unwrap_or((detail, ""));
+        let mut text = format!("{operation} failed");
+        let folded = if first.chars().count() > SUMMARY_CHARS {
+            detail
+        } else {
+            text.push_str(&format!(": {first}"));
+            rest
+        };
+        if !folded.is_empty() {
+            text.push('\n');
+            text.push_str(folded);
+        }
+        ChatMessage {
+            failure: Some(failure),
+            ..ChatMessage::new(Role::System, text)
+        }
+    }
+
+    /// The summary line and how to get at the rest, as the card shows it.
+    pub(crate) fn header(&self, text: &str, expanded: bool) -> String {
+        let mut lines = text.lines();
+        let summary = lines.next().unwrap_or_default();
+        let mut header = format!("✖ {}: {summary}", self.class.label());
+        match lines.count() {
+            0 => {}
+            _ if expanded => header.push_str(" · x folds"),
+            hidden => header.push_str(&format!(" ▸ {hidden} more lines · x shows all")),
+        }
+        header.push_str(" · y copies");
+        if self.retry {
+            header.push_str(" · r retries");
+        }
+        header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_become_classified_cards_with_the_detail_folded() {
+        let body = "HTTP 429 Too Many Requests\n{\"error\": \"slow down\"}";
+        let err = ProviderError::new(ProviderErrorKind::InvalidRequest, body);
+        let card = Failure::card("response stream", &err, true);
+        let failure = card.failure.unwrap();
+        assert_eq!(failure.class, FailureClass::RateLimit);
+        assert!(failure.retry);
+        assert_eq!(
+            failure.header(&card.text, false),
+            "✖ rate limited: response stream failed: HTTP 429 Too Many Requests ▸ 1 more lines · x shows all · y copies · r retries"
+        );
+
+        let err = ProviderError::new(ProviderErrorKind::InvalidRequest, "model is required");
+        let card = Failure::card("commit message", &err, false);
+        assert_eq!(card.failure.unwrap().class, FailureClass::Request);
+        assert!(!card.failure.unwrap().retry);
+    }
+}
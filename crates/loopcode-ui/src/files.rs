@@ -0,0 +1,139 @@
+use std::path::PathBuf;
+
+use crate::bookmarks::truncate;
+use crate::watch;
+use crate::workspace::Workspace;
+
+pub(crate) enum FileOutcome {
+    Pending,
+    /// Put this path into the input.
+    Insert(String),
+    Close,
+}
+
+/// The files in the workspace roots, as the Files panel lists them.
+pub(crate) struct FilePanel {
+    workspace: Workspace,
+    /// As [`Workspace::shorten`] shows them, sorted.
+    files: Vec<String>,
+    selected: usize,
+}
+
+impl FilePanel {
+    pub(crate) fn open(workspace: Workspace) -> Self {
+        let mut panel = Self {
+            workspace,
+            files: Vec::new(),
+            selected: 0,
+        };
+        panel.refresh();
+        panel
+    }
+
+    /// The panel over `paths`, files of `workspace` listed ahead of time.
+    pub(crate) fn listed(workspace: Workspace, paths: Vec<PathBuf>) -> Self {
+        let mut panel = Self {
+            workspace,
+            files: Vec::new(),
+            selected: 0,
+        };
+        panel.list(paths);
+        panel
+    }
+
+    /// Lists the files again, keeping the selected one when it is still
+    /// there.
+    fn refresh(&mut self) {
+        let paths = self
+            .workspace
+            .roots()
+            .iter()
+            .flat_map(|root| watch::scan(root, "**/*").into_keys())
+            .collect();
+        self.list(paths);
+    }
+
+    fn list(&mut self, paths: Vec<PathBuf>) {
+        let selected = self.files.get(self.selected).cloned();
+        let mut files = paths
+            .iter()
+            .map(|path| self.workspace.shorten(path))
+            .collect::<Vec<_>>();
+        files.sort();
+        self.files = files;
+        self.selected = selected
+            .and_then(|selected| self.files.binary_search(&selected).ok())
+            .unwrap_or(0);
+    }
+
+    /// Lists the files of `workspace` instead when its roots changed.
+    pub(crate) fn follow(&mut self, workspace: &Workspace) {
+        if self.workspace.roots() != workspace.roots() {
+            self.workspace = workspace.clone();
+            self.refresh();
+        }
+    }
+
+    pub(crate) fn handle_key(&mut self, key: xpui::UiKeyInput, page: usize) -> FileOutcome {
+        let last = self.files.len().saturating_sub(1);
+        match key {
+            xpui::UiKeyInput::Up => self.selected = self.selected.saturating_sub(1),
+            xpui::UiKeyInput::Down => self.selected = (self.selected + 1).min(last),
+            xpui::UiKeyInput::PageUp => self.selected = self.selected.saturating_sub(page),
+            xpui::UiKeyInput::PageDown => self.selected = (self.selected + page).min(last),
+            xpui::UiKeyInput::Home => self.selected = 0,
+            xpui::UiKeyInput::End => self.selected = last,
+            xpui::UiKeyInput::Char('r') => self.refresh(),
+            xpui::UiKeyInput::Enter | xpui::UiKeyInput::Submit => {
+                if let Some(file) = self.files.get(self.selected) {
+                    return FileOutcome::Insert(file.clone());
+                }
+            }
+            xpui::UiKeyInput::Esc | xpui::UiKeyInput::Char('q') => return FileOutcome::Close,
+            _ => {}
+        }
+        FileOutcome::Pending
+    }
+
+    /// One line per file, each cut to `width` columns, and the offset that
+    /// keeps the selected one within `viewport` lines.
+    pub(crate) fn render(&self, width: usize, viewport: u16) -> (String, u16) {
+        if self.files.is_empty() {
+            return ("No files in the workspace.".to_string(), 0);
+        }
+        let mut out = format!(
+            "{} files · Enter puts the path in the input · r refresh · Esc back",
+            self.files.len()
+        );
+        for (row, file) in self.files.iter().enumerate() {
+            let marker = if row == self.selected { "▶" } else { " " };
+            out.push('\n');
+            out.push_str(&truncate(&format!("{marker} {file}"), width));
+        }
+        // The header takes the first line.
+        let offset = (self.selected + 2).saturating_sub(usize::from(viewport));
+        (out, offset as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_listed_and_picked_by_relative_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "").unwrap();
+        std::fs::write(root.join("Cargo.toml"), "").unwrap();
+
+        let mut panel = FilePanel::open(Workspace::new(root.to_path_buf()));
+        assert_eq!(panel.files, ["Cargo.toml", "src/main.rs"]);
+        panel.handle_key(xpui::UiKeyInput::Down, 10);
+        assert!(matches!(
+            panel.handle_key(xpui::UiKeyInput::Enter, 10),
+            FileOutcome::Insert(path) if path == "src/main.rs"
+        ));
+    }
+}
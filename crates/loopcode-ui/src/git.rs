@@ -0,0 +1,27 @@
+use std::path::Path;
+
+use crate::target::ExecTarget;
+
+/// Diffs longer than this are cut before they go to the model; a summary
+/// does not need every hunk.
+const MAX_DIFF_CHARS: usize = 24_000;
+
+/// `diff`, cut to [`MAX_DIFF_CHARS`] with a note when it is longer.
+pub(crate) fn clip_diff(diff: &str) -> String {
+    match diff.char_indices().nth(MAX_DIFF_CHARS) {
+        Some((cut, _)) => format!("{}\n[diff truncated]", &diff[..cut]),
+        None => diff.to_string(),
+    }
+}
+
+/// Runs `program` (git or gh) in `root`, on its host for a remote root,
+/// feeding it `stdin`, and returns its output or its error message. It
+/// never runs in a container target: git and gh sign in on the host.
+pub(crate) fn run(
+    program: &str,
+    root: &Path,
+    args: &[&str],
+    stdin: Option<&str>,
+) -> Result<String, String> {
+    ExecTarget::Host.run(root, program, args, stdin)
+}
@@ -0,0 +1,755 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, LazyLock};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxSet};
+use syntect::util::LinesWithEndings;
+use xpui::TextStyle;
+
+use crate::cache::DiskCache;
+use crate::links::Linker;
+use crate::paste;
+
+/// Columns `format_history_row` puts in front of every line of a message.
+const ROW_INDENT: usize = 2;
+
+/// Part of every disk cache key. Cached tokens hold kinds, not colors, so
+/// switching `xpui::theme` does not need a new name.
+const THEME: &str = "github-dark";
+
+/// Disk cache directory for highlighted blocks; bump with the lexer.
+const CACHE_KIND: &str = "highlight-v3";
+/// Highlighted blocks kept in memory. Past this, the half used longest ago
+/// is dropped; they come back from the disk cache if they show again.
+const MEMORY_BLOCKS: usize = 2048;
+
+/// The grammars bundled with syntect, loaded on first use.
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+
+/// The kind of a scope and of the scopes under it, most specific first, as
+/// GitHub's themes color them. Scopes left out, such as operators and
+/// punctuation, keep the text color.
+static SCOPE_KINDS: LazyLock<Vec<(Scope, TokenKind)>> = LazyLock::new(|| {
+    [
+        ("comment", TokenKind::Comment),
+        ("string", TokenKind::String),
+        ("constant.character.escape", TokenKind::String),
+        ("constant", TokenKind::Number),
+        ("entity.name.function", TokenKind::Function),
+        ("support.function", TokenKind::Function),
+        ("variable.function", TokenKind::Function),
+        ("entity.name.type", TokenKind::Type),
+        ("entity.name.class", TokenKind::Type),
+        ("entity.name.struct", TokenKind::Type),
+        ("entity.name.enum", TokenKind::Type),
+        ("entity.name.trait", TokenKind::Type),
+        ("entity.other.inherited-class", TokenKind::Type),
+        ("support.type", TokenKind::Type),
+        ("support.class", TokenKind::Type),
+        ("storage.type", TokenKind::Keyword),
+        ("storage.modifier", TokenKind::Keyword),
+        ("keyword.operator", TokenKind::Plain),
+        ("keyword", TokenKind::Keyword),
+    ]
+    .into_iter()
+    .filter_map(|(scope, kind)| Some((Scope::new(scope).ok()?, kind)))
+    .collect()
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenKind {
+    Keyword,
+    String,
+    Comment,
+    Number,
+    Function,
+    Type,
+    /// A scope that would otherwise take its parent's kind, such as an
+    /// operator; never stored as a token.
+    Plain,
+    /// A `+` line of a diff.
+    Added,
+    /// A `-` line of a diff.
+    Removed,
+}
+
+impl TokenKind {
+    fn style(self) -> TextStyle {
+        let theme = xpui::theme();
+        let color = match self {
+            Self::Keyword => theme.syntax.keyword,
+            Self::String => theme.syntax.string,
+            Self::Comment => theme.syntax.comment,
+            Self::Number => theme.syntax.number,
+            Self::Function => theme.syntax.function,
+            Self::Type => theme.syntax.type_name,
+            Self::Plain => return TextStyle::default(),
+            Self::Added => theme.added,
+            Self::Removed => theme.removed,
+        };
+        let style = TextStyle::new().color(color);
+        if self == Self::Comment {
+            style.italic()
+        } else {
+            style
+        }
+    }
+
+    /// The CSS class of the kind in exported HTML.
+    fn class(self) -> &'static str {
+        match self {
+            Self::Keyword => "kw",
+            Self::String => "str",
+            Self::Comment => "com",
+            Self::Number => "num",
+            Self::Function => "fn",
+            Self::Type => "ty",
+            Self::Plain => "",
+            Self::Added => "add",
+            Self::Removed => "del",
+        }
+    }
+}
+
+/// A styled byte range of a code block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Token {
+    range: Range<usize>,
+    kind: TokenKind,
+}
+
+struct Job {
+    key: u64,
+    lang: String,
+    code: String,
+}
+
+/// Highlights fenced code blocks on a worker thread so large blocks never
+/// stall a frame. Blocks render as plain text until their tokens arrive, and
+/// finished blocks are cached by language and content, in memory for this
+/// run and on disk for the next one.
+pub(crate) struct Highlighter {
+    /// Tokens by block, with the frame they were last drawn in.
+    cache: HashMap<u64, (Arc<[Token]>, u64)>,
+    frame: u64,
+    pending: HashSet<u64>,
+    jobs: Sender<Job>,
+    results: Receiver<(u64, Vec<Token>)>,
+    links: Option<Linker>,
+}
+
+impl Highlighter {
+    pub(crate) fn new() -> Self {
+        let mut highlighter = Self::with_disk_cache(DiskCache::open(CACHE_KIND));
+        highlighter.links = std::env::current_dir().ok().and_then(Linker::new);
+        highlighter
+    }
+
+    fn with_disk_cache(disk: Option<DiskCache>) -> Self {
+        let (jobs, job_rx) = mpsc::channel::<Job>();
+        let (result_tx, results) = mpsc::channel();
+        thread::spawn(move || {
+            for job in job_rx {
+                let tokens = highlight_cached(disk.as_ref(), &job.lang, &job.code);
+                if result_tx.send((job.key, tokens)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            cache: HashMap::new(),
+            frame: 0,
+            pending: HashSet::new(),
+            jobs,
+            results,
+            links: None,
+        }
+    }
+
+    /// Takes in blocks the worker has finished since the last frame.
+    pub(crate) fn poll(&mut self) {
+        self.frame += 1;
+        while let Ok((key, tokens)) = self.results.try_recv() {
+            self.pending.remove(&key);
+            self.remember(key, tokens.into());
+        }
+    }
+
+    fn remember(&mut self, key: u64, tokens: Arc<[Token]>) {
+        if self.cache.len() >= MEMORY_BLOCKS {
+            let mut used = self
+                .cache
+                .values()
+                .map(|(_, used)| *used)
+                .collect::<Vec<_>>();
+            used.sort_unstable();
+            let cutoff = used[used.len() / 2];
+            self.cache.retain(|_, (_, used)| *used > cutoff);
+        }
+        self.cache.insert(key, (tokens, self.frame));
+    }
+
+    /// Builds a history row, styling the code blocks that are ready,
+    /// queueing the rest and linking file mentions outside of them. With
+    /// `markdown`, the text outside code blocks is drawn as markdown.
+    pub(crate) fn row(&mut self, row: &str, markdown: bool) -> xpui::TextWidget {
+        let mut out = xpui::text("");
+        let lines = row.split('\n').collect::<Vec<_>>();
+        let prose = prose_lines(&lines);
+        let mut index = 0;
+        while index < lines.len() {
+            if index > 0 {
+                out = out.run("\n", TextStyle::default());
+            }
+            let line = lines[index];
+            index += 1;
+
+            // Blocks still streaming in stay plain until their closing fence
+            // arrives, so the worker only sees finished code.
+            let block = fence(line).and_then(|lang| {
+                let body_len = lines[index..]
+                    .iter()
+                    .position(|line| fence(line).is_some())?;
+                Some((lang, body_len))
+            });
+            let Some((lang, body_len)) = block else {
+                out = if !prose[index - 1] {
+                    out.run(line, TextStyle::default())
+                } else if markdown {
+                    let (indent, text) = split_indent(line);
+                    let mut out = out.run(indent, TextStyle::default());
+                    for run in xpui::markdown_runs(text) {
+                        out = if run.style == TextStyle::default() {
+                            self.link(out, &run.text)
+                        } else {
+                            out.run(run.text, run.style)
+                        };
+                    }
+                    out
+                } else {
+                    self.link(out, line)
+                };
+                continue;
+            };
+            let body = &lines[index..index + body_len];
+            index += body_len;
+            let code = body
+                .iter()
+                .map(|line| split_indent(line).1)
+                .collect::<Vec<_>>()
+                .join("\n");
+            let guessed = if lang.is_empty() {
+                paste::code_language(&code).unwrap_or_default()
+            } else {
+                ""
+            };
+            out = header(out, line, lang, guessed);
+            let lang = if lang.is_empty() { guessed } else { lang };
+            match self.tokens(lang, code) {
+                Some(tokens) => out = styled_block(out, body, &tokens),
+                None => {
+                    for line in body {
+                        out = out.run("\n", TextStyle::default());
+                        out = out.run(*line, TextStyle::default());
+                    }
+                }
+            }
+            // The closing fence.
+            out = out.run("\n", TextStyle::default());
+            out = out.run(lines[index], TextStyle::default());
+            index += 1;
+        }
+        out
+    }
+
+    /// Appends plain `text`, linking the file mentions in it.
+    fn link(&mut self, out: xpui::TextWidget, text: &str) -> xpui::TextWidget {
+        match &mut self.links {
+            Some(links) => links.append(out, text),
+            None => out.run(text, TextStyle::default()),
+        }
+    }
+
+    fn tokens(&mut self, lang: &str, code: String) -> Option<Arc<[Token]>> {
+        let mut hasher = DefaultHasher::new();
+        lang.hash(&mut hasher);
+        code.hash(&mut hasher);
+        let key = hasher.finish();
+        if let Some((tokens, used)) = self.cache.get_mut(&key) {
+            *used = self.frame;
+            return Some(tokens.clone());
+        }
+        if self.pending.insert(key) {
+            let job = Job {
+                key,
+                lang: lang.to_string(),
+                code,
+            };
+            if self.jobs.send(job).is_err() {
+                // The worker is gone; keep showing plain text.
+                self.remember(key, Arc::from([]));
+            }
+        }
+        None
+    }
+}
+
+/// The text of `row` once [`Highlighter::row`] has drawn it as markdown,
+/// for measuring it: the markers drawn as styles are gone.
+pub(crate) fn markdown_text(row: &str) -> String {
+    let lines = row.split('\n').collect::<Vec<_>>();
+    let prose = prose_lines(&lines);
+    let mut out = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        if !prose[index] {
+            out.push_str(line);
+            continue;
+        }
+        let (indent, text) = split_indent(line);
+        out.push_str(indent);
+        out.extend(xpui::markdown_runs(text).into_iter().map(|run| run.text));
+    }
+    out
+}
+
+/// Which of `lines` are text rather than code: every line from an opening
+/// fence to its closing one, or to the end while it is still streaming, is
+/// code.
+fn prose_lines(lines: &[&str]) -> Vec<bool> {
+    let mut prose = vec![true; lines.len()];
+    let mut index = 0;
+    while index < lines.len() {
+        if fence(lines[index]).is_none() {
+            index += 1;
+            continue;
+        }
+        let end = lines[index + 1..]
+            .iter()
+            .position(|line| fence(line).is_some())
+            .map_or(lines.len(), |close| index + 1 + close + 1);
+        prose[index..end].fill(false);
+        index = end;
+    }
+    prose
+}
+
+/// The styled byte ranges of `code`, each with the CSS class of its kind:
+/// `kw`, `str`, `com`, `num`, `add` or `del`.
+pub(crate) fn classes(lang: &str, code: &str) -> Vec<(Range<usize>, &'static str)> {
+    tokenize(lang, code)
+        .into_iter()
+        .map(|token| (token.range, token.kind.class()))
+        .collect()
+}
+
+fn highlight_cached(disk: Option<&DiskCache>, lang: &str, code: &str) -> Vec<Token> {
+    let Some(disk) = disk else {
+        return tokenize(lang, code);
+    };
+    let key = DiskCache::key(&[THEME, lang, code]);
+    if let Some(tokens) = disk.get(&key) {
+        return tokens;
+    }
+    let tokens = tokenize(lang, code);
+    disk.put(&key, &tokens);
+    tokens
+}
+
+/// Appends the lines of a block, each with its row indent, split into runs
+/// at token boundaries. `tokens` index into the lines joined by `\n`.
+fn styled_block(mut out: xpui::TextWidget, lines: &[&str], tokens: &[Token]) -> xpui::TextWidget {
+    let mut line_start = 0;
+    let mut next_token = 0;
+    for line in lines {
+        let (indent, code) = split_indent(line);
+        out = out.run("\n", TextStyle::default());
+        out = out.run(indent, TextStyle::default());
+
+        let line_end = line_start + code.len();
+        let mut at = line_start;
+        while at < line_end {
+            while tokens
+                .get(next_token)
+                .is_some_and(|token| token.range.end <= at)
+            {
+                next_token += 1;
+            }
+            let (end, style) = match tokens.get(next_token) {
+                Some(token) if token.range.start <= at => {
+                    (token.range.end.min(line_end), token.kind.style())
+                }
+                Some(token) => (token.range.start.min(line_end), TextStyle::default()),
+                None => (line_end, TextStyle::default()),
+            };
+            out = out.run(&code[at - line_start..end - line_start], style);
+            at = end;
+        }
+        line_start = line_end + 1;
+    }
+    out
+}
+
+/// The opening fence of a block, its language shown as a badge: the one it
+/// names, or `guessed` after it when it names none.
+fn header(out: xpui::TextWidget, line: &str, lang: &str, guessed: &str) -> xpui::TextWidget {
+    let theme = xpui::theme();
+    let badge = TextStyle::new().bg(theme.surface).color(theme.accent);
+    if !lang.is_empty()
+        && let Some(at) = line.rfind(lang)
+    {
+        return out
+            .run(&line[..at], TextStyle::default())
+            .run(lang, badge)
+            .run(&line[at + lang.len()..], TextStyle::default());
+    }
+    let out = out.run(line, TextStyle::default());
+    if guessed.is_empty() {
+        out
+    } else {
+        out.run(" ", TextStyle::default()).run(guessed, badge)
+    }
+}
+
+/// The language of a line that opens or closes a fenced block, `""` when it
+/// names none.
+fn fence(line: &str) -> Option<&str> {
+    split_indent(line)
+        .1
+        .trim_start()
+        .strip_prefix("```")
+        .map(str::trim)
+}
+
+fn split_indent(line: &str) -> (&str, &str) {
+    let split = line
+        .char_indices()
+        .nth(ROW_INDENT)
+        .map_or(line.len(), |(index, _)| index);
+    line.split_at(split)
+}
+
+fn keywords(lang: &str) -> &'static [&'static str] {
+    match lang {
+        "rust" | "rs" => &[
+            "as", "async", "await", "break", "const", "continue", "crate", "else", "enum", "false",
+            "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+            "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+            "unsafe", "use", "where", "while",
+        ],
+        "python" | "py" => &[
+            "and", "as", "async", "await", "break", "class", "continue", "def", "elif", "else",
+            "except", "False", "finally", "for", "from", "if", "import", "in", "is", "lambda",
+            "None", "not", "or", "pass", "raise", "return", "True", "try", "while", "with",
+            "yield",
+        ],
+        "js" | "javascript" | "ts" | "typescript" => &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "else",
+            "export",
+            "extends",
+            "false",
+            "for",
+            "function",
+            "if",
+            "import",
+            "in",
+            "interface",
+            "let",
+            "new",
+            "null",
+            "of",
+            "return",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "type",
+            "undefined",
+            "var",
+            "while",
+        ],
+        "sh" | "bash" | "shell" | "zsh" => &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "while",
+        ],
+        _ => &[],
+    }
+}
+
+fn line_comment(lang: &str) -> &'static str {
+    match lang {
+        "python" | "py" | "sh" | "bash" | "shell" | "zsh" | "toml" | "yaml" | "yml" => "#",
+        _ => "//",
+    }
+}
+
+/// Tokens of `code` in `lang`: from syntect's grammar for it when it has
+/// one, else from a small lexer that knows a few languages' keywords.
+fn tokenize(lang: &str, code: &str) -> Vec<Token> {
+    if matches!(lang, "diff" | "patch") {
+        return tokenize_diff(code);
+    }
+    match SYNTAXES
+        .find_syntax_by_token(lang)
+        .filter(|_| !lang.is_empty())
+    {
+        Some(syntax) => tokenize_scopes(&mut ParseState::new(syntax), code),
+        None => tokenize_plain(lang, code),
+    }
+}
+
+/// Parses `code` line by line, giving each stretch between scope changes
+/// the kind of its innermost scope that has one. A line the grammar cannot
+/// parse ends the tokens there.
+fn tokenize_scopes(state: &mut ParseState, code: &str) -> Vec<Token> {
+    let mut tokens = Vec::<Token>::new();
+    let mut stack = ScopeStack::new();
+    let mut line_start = 0;
+    for line in LinesWithEndings::from(code) {
+        let Ok(ops) = state.parse_line(line, &SYNTAXES) else {
+            break;
+        };
+        let mut at = 0;
+        let ends = ops.iter().map(|(end, _)| *end).chain([line.len()]);
+        let mut ops = ops.iter();
+        for end in ends {
+            if let Some(kind) = scope_kind(&stack).filter(|_| end > at) {
+                let range = line_start + at..line_start + end;
+                match tokens.last_mut() {
+                    Some(last) if last.kind == kind && last.range.end == range.start => {
+                        last.range.end = range.end;
+                    }
+                    _ => tokens.push(Token { range, kind }),
+                }
+            }
+            at = end;
+            if let Some((_, op)) = ops.next()
+                && stack.apply(op).is_err()
+            {
+                return tokens;
+            }
+        }
+        line_start += line.len();
+    }
+    // A token never takes in the newline ending its line.
+    for token in &mut tokens {
+        while code[token.range.clone()].ends_with('\n') {
+            token.range.end -= 1;
+        }
+    }
+    tokens.retain(|token| !token.range.is_empty());
+    tokens
+}
+
+fn scope_kind(stack: &ScopeStack) -> Option<TokenKind> {
+    stack
+        .as_slice()
+        .iter()
+        .rev()
+        .find_map(|scope| {
+            SCOPE_KINDS
+                .iter()
+                .find(|(prefix, _)| prefix.is_prefix_of(*scope))
+                .map(|(_, kind)| *kind)
+        })
+        .filter(|kind| *kind != TokenKind::Plain)
+}
+
+/// A small lexer that knows keywords, strings, line comments and numbers,
+/// for the languages syntect has no grammar for.
+fn tokenize_plain(lang: &str, code: &str) -> Vec<Token> {
+    let keywords = keywords(lang);
+    let comment = line_comment(lang);
+    // In Rust a single quote usually starts a lifetime, not a string.
+    let quotes: &[char] = if matches!(lang, "rust" | "rs") {
+        &['"']
+    } else {
+        &['"', '\'', '`']
+    };
+
+    let mut tokens = Vec::new();
+    let mut chars = code.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        if code[start..].starts_with(comment) {
+            let end = code[start..].find('\n').map_or(code.len(), |at| start + at);
+            tokens.push(Token {
+                range: start..end,
+                kind: TokenKind::Comment,
+            });
+            while chars.next_if(|(at, _)| *at < end).is_some() {}
+        } else if quotes.contains(&ch) {
+            let mut end = code.len();
+            let mut escaped = false;
+            for (at, next) in chars.by_ref() {
+                if escaped {
+                    escaped = false;
+                } else if next == '\\' {
+                    escaped = true;
+                } else if next == ch {
+                    end = at + next.len_utf8();
+                    break;
+                }
+            }
+            tokens.push(Token {
+                range: start..end,
+                kind: TokenKind::String,
+            });
+        } else if ch.is_ascii_digit() {
+            let mut end = start + 1;
+            while let Some((at, next)) = chars
+                .next_if(|(_, next)| next.is_ascii_alphanumeric() || *next == '.' || *next == '_')
+            {
+                end = at + next.len_utf8();
+            }
+            tokens.push(Token {
+                range: start..end,
+                kind: TokenKind::Number,
+            });
+        } else if ch.is_alphabetic() || ch == '_' {
+            let mut end = start + ch.len_utf8();
+            while let Some((at, next)) =
+                chars.next_if(|(_, next)| next.is_alphanumeric() || *next == '_')
+            {
+                end = at + next.len_utf8();
+            }
+            if keywords.contains(&&code[start..end]) {
+                tokens.push(Token {
+                    range: start..end,
+                    kind: TokenKind::Keyword,
+                });
+            }
+        }
+    }
+    tokens
+}
+
+/// Whole lines of a unified diff: additions, removals, and file and hunk
+/// headers as comments.
+fn tokenize_diff(code: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    for line in code.split('\n') {
+        let kind = match line.as_bytes() {
+            [b'+', b'+', b'+', ..] | [b'-', b'-', b'-', ..] | [b'@', b'@', ..] => {
+                Some(TokenKind::Comment)
+            }
+            [b'+', ..] => Some(TokenKind::Added),
+            [b'-', ..] => Some(TokenKind::Removed),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            tokens.push(Token {
+                range: start..start + line.len(),
+                kind,
+            });
+        }
+        start += line.len() + 1;
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styled_rows_keep_the_original_text() {
+        let row = "  assistant: here\n  ```rust\n  let n = 42; // \"x\"\n  ```";
+        let kinds = tokenize("rust", "let n = 42; // \"x\"")
+            .into_iter()
+            .map(|token| token.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [TokenKind::Keyword, TokenKind::Number, TokenKind::Comment]
+        );
+
+        let kinds = |lang, code| {
+            tokenize(lang, code)
+                .into_iter()
+                .map(|token| token.kind)
+                .collect::<Vec<_>>()
+        };
+        assert!(kinds("go", "func main() {}").contains(&TokenKind::Function));
+        assert!(kinds("rust", "struct Point;").contains(&TokenKind::Type));
+        // No bundled grammar for TypeScript; the small lexer still knows it.
+        assert_eq!(
+            kinds("ts", "const x = 1"),
+            [TokenKind::Keyword, TokenKind::Number]
+        );
+
+        let mut highlighter = Highlighter::with_disk_cache(None);
+        let _ = highlighter.row(row, true);
+        while !highlighter.pending.is_empty() {
+            highlighter.poll();
+        }
+        let node = xpui::IntoNode::into_node(highlighter.row(row, true));
+        let xpui::Node::RichText(text) = node else {
+            panic!("expected rich text");
+        };
+        let joined = text
+            .runs
+            .iter()
+            .map(|run| run.text.as_str())
+            .collect::<String>();
+        assert_eq!(joined, row);
+        assert!(text.runs.iter().any(|run| run.style.color.is_some()));
+    }
+
+    #[test]
+    fn markdown_rows_drop_their_markers_outside_code() {
+        let row = "  assistant: **Done**, see [docs](https://x.dev) and `snake_case`\n  \
+                   ## Next\n  - one *two* three\n  ```\n  let **x** = 1;\n  ```";
+        assert_eq!(
+            markdown_text(row),
+            "  assistant: Done, see docs and snake_case\n  Next\n  • one two three\n  \
+             ```\n  let **x** = 1;\n  ```"
+        );
+        let runs = xpui::markdown_runs("a **b** [c](https://c.dev)");
+        assert!(runs.iter().any(|run| run.text == "b" && run.style.bold));
+        assert!(
+            runs.iter()
+                .any(|run| run.style.link.as_deref() == Some("https://c.dev"))
+        );
+        assert_eq!(
+            xpui::markdown_runs("my_var_name * 2 **open").len(),
+            1,
+            "unclosed and intraword marks stay plain"
+        );
+    }
+
+    #[test]
+    fn diffs_mark_whole_lines() {
+        let kinds = tokenize("diff", "--- a\n+++ b\n@@ -1 +1 @@\n-old\n+new\n same")
+            .into_iter()
+            .map(|token| token.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                TokenKind::Comment,
+                TokenKind::Comment,
+                TokenKind::Comment,
+                TokenKind::Removed,
+                TokenKind::Added
+            ]
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use serde::Deserialize;
+
+use crate::git;
+
+/// Comments past this many are left out; the newest are usually the ones
+/// that matter, so the oldest go.
+const MAX_COMMENTS: usize = 20;
+
+#[derive(Deserialize)]
+struct Issue {
+    number: u64,
+    title: String,
+    #[serde(default)]
+    body: String,
+    url: String,
+    #[serde(default)]
+    comments: Vec<Comment>,
+}
+
+#[derive(Deserialize)]
+struct Comment {
+    author: Option<Author>,
+    #[serde(default)]
+    body: String,
+}
+
+#[derive(Deserialize)]
+struct Author {
+    login: String,
+}
+
+/// Fetches issue `reference`, a number in the current repository or a URL,
+/// with `gh` on a worker thread. The result is the issue as context text,
+/// title line first.
+pub(crate) fn fetch(root: PathBuf, reference: String) -> Receiver<Result<String, String>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(fetch_blocking(&root, &reference));
+    });
+    rx
+}
+
+fn fetch_blocking(root: &Path, reference: &str) -> Result<String, String> {
+    let args = [
+        "issue",
+        "view",
+        reference,
+        "--json",
+        "number,title,body,url,comments",
+    ];
+    let json = git::run("gh", root, &args, None)
+        .map_err(|err| format!("cannot fetch issue {reference}: {err}"))?;
+    let issue = serde_json::from_str::<Issue>(&json)
+        .map_err(|err| format!("cannot read issue {reference} from gh: {err}"))?;
+    Ok(context_text(&issue))
+}
+
+fn context_text(issue: &Issue) -> String {
+    let mut text = format!("issue #{} {}\n{}", issue.number, issue.title, issue.url);
+    if !issue.body.trim().is_empty() {
+        text.push_str("\n\n");
+        text.push_str(issue.body.trim());
+    }
+    let skipped = issue.comments.len().saturating_sub(MAX_COMMENTS);
+    if skipped > 0 {
+        text.push_str(&format!("\n\n({skipped} older comments left out)"));
+    }
+    for comment in &issue.comments[skipped..] {
+        let author = comment
+            .author
+            .as_ref()
+            .map_or("someone", |author| author.login.as_str());
+        text.push_str(&format!("\n\n{author} commented:\n{}", comment.body.trim()));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issues_read_as_title_body_then_comments() {
+        let json = r#"{"number":7,"title":"Crash on start","body":"It crashes.\n",
+            "url":"https://github.com/o/r/issues/7",
+            "comments":[{"author":{"login":"ana"},"body":"Same here."}]}"#;
+        let issue = serde_json::from_str::<Issue>(json).unwrap();
+        assert_eq!(
+            context_text(&issue),
+            "issue #7 Crash on start\nhttps://github.com/o/r/issues/7\n\nIt crashes.\n\n\
+             ana commented:\nSame here."
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! The loopcode chat UI as a component: a history list, an input area and a
+//! status bar over a chat that streams from any
+//! [`ChatProvider`](provider::ChatProvider). Run [`ChatPanel`] as an
+//! [`xpui::UiApp`] with your own provider, and a [`session`] to journal to.
+
+mod actions;
+mod activity;
+mod attach;
+mod bookmarks;
+mod cache;
+mod changelog;
+mod chat;
+mod command;
+mod commit;
+mod compare;
+mod complete;
+mod copy;
+mod draft;
+mod dropped;
+mod export;
+mod failure;
+mod files;
+mod git;
+mod highlight;
+mod issue;
+mod links;
+mod measure;
+mod menu_bar;
+mod metrics;
+mod models;
+mod panel;
+mod paste;
+mod paths;
+mod pr;
+mod prompts;
+pub mod provider;
+mod remote;
+mod replace;
+mod risk;
+mod screens;
+mod search;
+pub mod session;
+mod sound;
+mod state;
+mod store;
+mod table;
+mod target;
+mod themes;
+mod todos;
+mod tools;
+mod warmup;
+mod watch;
+mod workspace;
+
+pub use chat::{ChatOptions, UsageTotals};
+pub use panel::ChatPanel;
+pub use sound::SoundCues;
+pub use themes::ThemePreference;
+pub use tools::{Tool, ToolContext, Tools};
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use xpui::TextStyle;
+
+/// Turns mentions of workspace files, such as `src/main.rs:42`, into
+/// hyperlinks. In VS Code they open the file at that line in the editor;
+/// elsewhere they are plain `file://` links.
+pub(crate) struct Linker {
+    root: PathBuf,
+    vscode: bool,
+    /// Whether a mentioned path is a file. Rows are rebuilt every frame, so
+    /// this keeps the file system out of rendering.
+    files: HashMap<String, Option<PathBuf>>,
+}
+
+impl Linker {
+    /// `None` when the terminal cannot show links.
+    pub(crate) fn new(root: PathBuf) -> Option<Self> {
+        let capabilities = xpui::capabilities();
+        capabilities.hyperlinks.then(|| Self {
+            root,
+            vscode: capabilities.vscode,
+            files: HashMap::new(),
+        })
+    }
+
+    /// Appends `text` to `out`, linking the file mentions in it.
+    pub(crate) fn append(&mut self, mut out: xpui::TextWidget, text: &str) -> xpui::TextWidget {
+        let mut at = 0;
+        for (start, word) in words(text) {
+            let Some((range, url)) = self.link(word) else {
+                continue;
+            };
+            let (start, end) = (start + range.start, start + range.end);
+            out = out.run(&text[at..start], TextStyle::default());
+            out = out.run(&text[start..end], TextStyle::new().underline().link(url));
+            at = end;
+        }
+        out.run(&text[at..], TextStyle::default())
+    }
+
+    /// The part of `word` that names a file, and where it should open.
+    fn link(&mut self, word: &str) -> Option<(std::ops::Range<usize>, String)> {
+        let trimmed = word.trim_start_matches(['(', '[', '<', '"', '\'', '`']);
+        let start = word.len() - trimmed.len();
+        let trimmed = trimmed.trim_end_matches([')', ']', '>', '"', '\'', '`', ',', ';', '.']);
+        let (path, position) = match trimmed.split_once(':') {
+            Some((path, position)) => (path, Some(position)),
+            None => (trimmed, None),
+        };
+        if !path.contains(['.', '/']) {
+            return None;
+        }
+        let file = self.resolve(path)?;
+        let mut numbers = position
+            .into_iter()
+            .flat_map(|position| position.split(':'))
+            .map_while(|part| part.parse::<u32>().ok());
+        let line = numbers.next();
+        let column = numbers.next();
+        let end = start
+            + match (line, column) {
+                (Some(line), Some(column)) => format!("{path}:{line}:{column}").len(),
+                (Some(line), None) => format!("{path}:{line}").len(),
+                _ => path.len(),
+            };
+        Some((start..end, self.url(&file, line, column)))
+    }
+
+    fn resolve(&mut self, path: &str) -> Option<PathBuf> {
+        let root = &self.root;
+        self.files
+            .entry(path.to_string())
+            .or_insert_with(|| {
+                let escapes = Path::new(path)
+                    .components()
+                    .any(|component| component == Component::ParentDir);
+                let file = root.join(path);
+                (!escapes && file.starts_with(root) && file.is_file()).then_some(file)
+            })
+            .clone()
+    }
+
+    fn url(&self, file: &Path, line: Option<u32>, column: Option<u32>) -> String {
+        let path = encode(&file.to_string_lossy());
+        if !self.vscode {
+            return format!("file://{path}");
+        }
+        match (line, column) {
+            (Some(line), Some(column)) => format!("vscode://file{path}:{line}:{column}"),
+            (Some(line), None) => format!("vscode://file{path}:{line}"),
+            _ => format!("vscode://file{path}"),
+        }
+    }
+}
+
+/// Whitespace-separated words with their byte offsets.
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(char::is_whitespace)
+        .scan(0, |at, word| {
+            let start = *at;
+            // `split` drops exactly one whitespace character per boundary.
+            *at += word.len()
+                + text[start + word.len()..]
+                    .chars()
+                    .next()
+                    .map_or(0, char::len_utf8);
+            Some((start, word))
+        })
+        .filter(|(_, word)| !word.is_empty())
+}
+
+/// Escapes the characters that would end or confuse a URL path.
+fn encode(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for ch in path.chars() {
+        match ch {
+            ' ' => out.push_str("%20"),
+            '#' => out.push_str("%23"),
+            '%' => out.push_str("%25"),
+            '?' => out.push_str("%3F"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_workspace_files_with_positions() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let mut linker = Linker {
+            root: root.clone(),
+            vscode: true,
+            files: HashMap::new(),
+        };
+        let text = "see (src/lib.rs:12:3), Cargo.toml. and nope.rs or ../x.rs";
+        let links = words(text)
+            .filter_map(|(start, word)| {
+                let (range, url) = linker.link(word)?;
+                Some((&text[start + range.start..start + range.end], url))
+            })
+            .collect::<Vec<_>>();
+        let root = root.display();
+        assert_eq!(
+            links,
+            [
+                (
+                    "src/lib.rs:12:3",
+                    format!("vscode://file{root}/src/lib.rs:12:3")
+                ),
+                ("Cargo.toml", format!("vscode://file{root}/Cargo.toml")),
+            ]
+        );
+    }
+}
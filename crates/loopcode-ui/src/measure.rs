@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+
+use rayon::prelude::*;
+
+use crate::cache::DiskCache;
+
+/// Below this many rows, measuring them in turn is quicker than handing them
+/// to the thread pool.
+const PARALLEL_ROWS: usize = 512;
+
+/// Disk cache directory for measured heights; bump with the measuring.
+const CACHE_KIND: &str = "heights-v1";
+
+/// The height `measure` gives each of `rows`, in order. A long history, as
+/// on a resize, is measured across rayon's pool and merged back here, so the
+/// frame waits on the slowest chunk rather than on every row in turn.
+pub(crate) fn heights<T: Sync>(rows: &[T], measure: impl Fn(&T) -> u16 + Send + Sync) -> Vec<u16> {
+    if rows.len() < PARALLEL_ROWS {
+        rows.iter().map(measure).collect()
+    } else {
+        rows.par_iter().map(measure).collect()
+    }
+}
+
+/// The heights of rows by a hash of what they show and the width they wrap
+/// at, so a row that did not change is not parsed and measured again. Only
+/// the rows of the last pass are kept, so it holds about one history's
+/// worth; they are also kept on disk, for a session reopened at the same
+/// width to skip measuring.
+pub(crate) struct HeightCache {
+    disk: Option<DiskCache>,
+    width: Option<usize>,
+    heights: HashMap<u64, u16>,
+}
+
+impl HeightCache {
+    pub(crate) fn new() -> Self {
+        Self::with_disk_cache(DiskCache::open(CACHE_KIND))
+    }
+
+    fn with_disk_cache(disk: Option<DiskCache>) -> Self {
+        Self {
+            disk,
+            width: None,
+            heights: HashMap::new(),
+        }
+    }
+
+    /// The height of each of `rows` at `width`, measuring with `measure`
+    /// only the ones whose `key` was not seen at that width. With `save`,
+    /// newly measured heights go to disk as well; while rows still change,
+    /// as when a reply streams, that would only write them again and again.
+    pub(crate) fn heights<T: Sync>(
+        &mut self,
+        rows: &[T],
+        width: usize,
+        key: impl Fn(&T) -> u64,
+        measure: impl Fn(&T) -> u16 + Send + Sync,
+        save: bool,
+    ) -> Vec<u16> {
+        if self.width != Some(width) {
+            self.width = Some(width);
+            self.heights = self
+                .disk
+                .as_ref()
+                .and_then(|disk| disk.get::<Vec<(u64, u16)>>(&disk_key(width)))
+                .map(|saved| saved.into_iter().collect())
+                .unwrap_or_default();
+        }
+        let mut last = std::mem::take(&mut self.heights);
+        let keys = rows.iter().map(key).collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        let (missing, missing_keys): (Vec<_>, Vec<_>) = rows
+            .iter()
+            .zip(&keys)
+            .filter(|(_, key)| !last.contains_key(key) && seen.insert(**key))
+            .unzip();
+        let fresh = !missing.is_empty();
+        last.extend(
+            missing_keys
+                .into_iter()
+                .copied()
+                .zip(heights(&missing, |row| measure(row))),
+        );
+        let out = keys
+            .iter()
+            .map(|key| {
+                let height = last[key];
+                self.heights.insert(*key, height);
+                height
+            })
+            .collect();
+        if fresh
+            && save
+            && let Some(disk) = &self.disk
+        {
+            let saved = self.heights.iter().map(|(key, height)| (*key, *height));
+            disk.put(&disk_key(width), &saved.collect::<Vec<_>>());
+        }
+        out
+    }
+}
+
+fn disk_key(width: usize) -> String {
+    DiskCache::key(&[&width.to_string()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_heights_keep_the_order_of_rows() {
+        let rows = (0..2000).map(|n| "x".repeat(n % 97)).collect::<Vec<_>>();
+        let measure = |row: &String| (row.len() / 10) as u16 + 1;
+        let serial = rows.iter().map(measure).collect::<Vec<_>>();
+        assert_eq!(heights(&rows, measure), serial);
+        assert_eq!(heights(&rows[..3], measure), serial[..3]);
+    }
+
+    #[test]
+    fn cached_heights_are_measured_once_and_kept_for_the_last_pass() {
+        let mut cache = HeightCache::with_disk_cache(None);
+        let measured = std::sync::atomic::AtomicUsize::new(0);
+        let mut pass = |rows: &[&str], width: usize| {
+            let key = |row: &&str| row.len() as u64;
+            let measure = |row: &&str| {
+                measured.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                (row.len() / width) as u16 + 1
+            };
+            cache.heights(rows, width, key, measure, true)
+        };
+        assert_eq!(pass(&["ab", "abcdef", "ab"], 4), [1, 2, 1]);
+        assert_eq!(pass(&["abcdef", "abcdefghi"], 4), [2, 3]);
+        assert_eq!(pass(&["abcdef", "abcdefghi"], 2), [4, 5]);
+        assert_eq!(measured.into_inner(), 5);
+        assert_eq!(cache.heights.len(), 2);
+    }
+}
@@ -0,0 +1,87 @@
+use xpui::{AppMenu, AppMenuItem, UiKeyInput};
+
+use crate::activity::Activity;
+
+/// The window's menu bar in graphics mode. Items either run the slash
+/// command typing would, or press the key of their shortcut, so the menu
+/// does nothing the keyboard does not.
+pub(crate) fn menu_bar() -> Vec<AppMenu> {
+    let panels = Activity::ALL
+        .iter()
+        .map(|activity| AppMenuItem::key(activity.title(), UiKeyInput::Panel(activity.shortcut())));
+    let view = [
+        AppMenuItem::command("Auto Theme", "/theme auto"),
+        AppMenuItem::command("Light Theme", "/theme light"),
+        AppMenuItem::command("Dark Theme", "/theme dark"),
+        AppMenuItem::command("Color-Blind Colors", "/colorblind"),
+        AppMenuItem::Separator,
+        AppMenuItem::command("Wrap Input", "/wrap"),
+        AppMenuItem::command("Present", "/present"),
+        AppMenuItem::Separator,
+    ]
+    .into_iter()
+    .chain(panels)
+    .collect();
+    vec![
+        AppMenu::new(
+            "File",
+            vec![
+                AppMenuItem::command("New Session", "/new"),
+                AppMenuItem::command("Fork Session", "/fork"),
+                AppMenuItem::command("Sessions", "/sessions"),
+                AppMenuItem::Separator,
+                AppMenuItem::command("Commit Staged Changes", "/commit"),
+                AppMenuItem::command("Open Pull Request", "/pr"),
+            ],
+        ),
+        AppMenu::new(
+            "Edit",
+            vec![
+                AppMenuItem::key("Undo", UiKeyInput::Undo),
+                AppMenuItem::key("Redo", UiKeyInput::Redo),
+                AppMenuItem::Separator,
+                AppMenuItem::key("Cut", UiKeyInput::Cut),
+                AppMenuItem::key("Copy", UiKeyInput::Copy),
+                AppMenuItem::key("Paste", UiKeyInput::Paste),
+                AppMenuItem::key("Add Cursor at Next Match", UiKeyInput::AddCursorAtNext),
+                AppMenuItem::Separator,
+                AppMenuItem::key("Delete Line", UiKeyInput::DeleteLine),
+                AppMenuItem::key("Duplicate Line", UiKeyInput::DuplicateLine),
+                AppMenuItem::key("Move Line Up", UiKeyInput::MoveLineUp),
+                AppMenuItem::key("Move Line Down", UiKeyInput::MoveLineDown),
+                AppMenuItem::key("Join Lines", UiKeyInput::JoinLines),
+                AppMenuItem::Separator,
+                AppMenuItem::command("Edit Assist", "/assist"),
+            ],
+        ),
+        AppMenu::new("View", view),
+        AppMenu::new(
+            "Help",
+            vec![
+                AppMenuItem::command("What's New", "/changelog"),
+                AppMenuItem::command("Log", "/debug log"),
+                AppMenuItem::command("Slow Frames", "/debug frames"),
+            ],
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::SlashCommand;
+
+    #[test]
+    fn every_menu_command_parses() {
+        for menu in menu_bar() {
+            for item in menu.items {
+                if let AppMenuItem::Command { command, .. } = item {
+                    assert!(
+                        matches!(SlashCommand::parse(&command), Some(Ok(_))),
+                        "{command}"
+                    );
+                }
+            }
+        }
+    }
+}
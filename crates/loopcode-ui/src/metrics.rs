@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Counts {
+    /// Seconds since the epoch when recording was turned on.
+    since: u64,
+    sessions: u32,
+    session_seconds: u64,
+    prompts: u64,
+    /// Uses of each slash command, by its first word.
+    commands: BTreeMap<String, u64>,
+}
+
+/// Which commands get used and how long sessions last, recorded only once
+/// turned on with `/metrics on` and only to `metrics.json` in the data
+/// directory. Nothing leaves the machine unless `/metrics export` writes it
+/// somewhere to share.
+pub(crate) struct Metrics {
+    /// `None` while recording is off.
+    counts: Option<Counts>,
+    path: Option<PathBuf>,
+    /// When this run's time was last added to the counts.
+    counted_at: Instant,
+}
+
+impl Metrics {
+    /// Recording is on when an earlier run turned it on and left the file.
+    pub(crate) fn open() -> Self {
+        Self::open_at(dirs::data_dir().map(|dir| dir.join("loopcode").join("metrics.json")))
+    }
+
+    /// Not recording and not saved, for a chat whose metrics are still
+    /// being read.
+    pub(crate) fn off() -> Self {
+        Self::open_at(None)
+    }
+
+    fn open_at(path: Option<PathBuf>) -> Self {
+        let counts = path
+            .as_ref()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|json| serde_json::from_slice::<Counts>(&json).ok());
+        let mut metrics = Self {
+            counts,
+            path,
+            counted_at: Instant::now(),
+        };
+        if let Some(counts) = metrics.counts.as_mut() {
+            counts.sessions += 1;
+            metrics.save();
+        }
+        metrics
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.counts.is_some()
+    }
+
+    /// Starts recording, this run counting as the first session.
+    pub(crate) fn enable(&mut self) -> Result<(), String> {
+        if self.counts.is_none() {
+            self.counted_at = Instant::now();
+            self.counts = Some(Counts {
+                since: now(),
+                sessions: 1,
+                ..Counts::default()
+            });
+        }
+        self.try_save()
+    }
+
+    /// Stops recording and deletes everything recorded.
+    pub(crate) fn purge(&mut self) -> Result<(), String> {
+        self.counts = None;
+        match &self.path {
+            Some(path) if path.exists() => fs::remove_file(path)
+                .map_err(|err| format!("could not delete {}: {err}", path.display())),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn record_prompt(&mut self) {
+        if let Some(counts) = self.counts.as_mut() {
+            counts.prompts += 1;
+            self.save();
+        }
+    }
+
+    pub(crate) fn record_command(&mut self, name: &str) {
+        if let Some(counts) = self.counts.as_mut() {
+            *counts.commands.entry(name.to_string()).or_default() += 1;
+            self.save();
+        }
+    }
+
+    /// Writes the counts as JSON to `path`, for sharing them.
+    pub(crate) fn export(&mut self, path: &Path) -> Result<(), String> {
+        self.add_elapsed();
+        let counts = self.counts.as_ref().ok_or("metrics are off; /metrics on")?;
+        let json = serde_json::to_vec_pretty(counts)
+            .map_err(|err| format!("could not encode metrics: {err}"))?;
+        fs::write(path, json).map_err(|err| format!("could not write {}: {err}", path.display()))
+    }
+
+    /// What `/metrics` shows: how to turn recording on, or what it recorded
+    /// with the commands as a table.
+    pub(crate) fn report(&mut self) -> String {
+        self.add_elapsed();
+        let store = self.path.as_ref().map_or_else(
+            || "memory only".to_string(),
+            |path| path.display().to_string(),
+        );
+        let Some(counts) = &self.counts else {
+            return format!(
+                "metrics are off. /metrics on records which commands you use and how long \
+                 sessions last, only to {store}; /metrics off deletes it again"
+            );
+        };
+        let average = counts.session_seconds / u64::from(counts.sessions.max(1));
+        let mut report = format!(
+            "metrics since {} days ago, stored in {store}\n\
+             {} sessions, {} on average · {} prompts\n\
+             /metrics export <path> to share them · /metrics off stops and deletes them",
+            now().saturating_sub(counts.since) / 86_400,
+            counts.sessions,
+            duration(average),
+            counts.prompts,
+        );
+        if !counts.commands.is_empty() {
+            report.push_str("\n\n```csv\ncommand,uses");
+            for (name, uses) in &counts.commands {
+                report.push_str(&format!("\n/{name},{uses}"));
+            }
+            report.push_str("\n```");
+        }
+        report
+    }
+
+    /// Adds the time since it was last added to the session total.
+    fn add_elapsed(&mut self) {
+        let elapsed = self.counted_at.elapsed().as_secs();
+        if let Some(counts) = self.counts.as_mut() {
+            counts.session_seconds += elapsed;
+        }
+        // Keep the part of a second not counted yet.
+        self.counted_at += std::time::Duration::from_secs(elapsed);
+    }
+
+    fn save(&mut self) {
+        let _ = self.try_save();
+    }
+
+    fn try_save(&mut self) -> Result<(), String> {
+        self.add_elapsed();
+        let (Some(path), Some(counts)) = (&self.path, &self.counts) else {
+            return Ok(());
+        };
+        let json =
+            serde_json::to_vec(counts).map_err(|err| format!("could not encode metrics: {err}"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|err| format!("could not create {}: {err}", dir.display()))?;
+        }
+        fs::write(path, json).map_err(|err| format!("could not write {}: {err}", path.display()))
+    }
+}
+
+impl Drop for Metrics {
+    /// Counts the rest of the session.
+    fn drop(&mut self) {
+        if self.enabled() {
+            self.save();
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn duration(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m", seconds / 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_is_recorded_until_turned_on() {
+        let mut metrics = Metrics::open_at(None);
+        metrics.record_prompt();
+        metrics.record_command("theme");
+        assert!(!metrics.enabled());
+        assert!(metrics.report().starts_with("metrics are off"));
+
+        metrics.enable().unwrap();
+        metrics.record_prompt();
+        metrics.record_command("theme");
+        metrics.record_command("theme");
+        metrics.record_command("commit");
+        let report = metrics.report();
+        assert!(report.contains("1 sessions, 0s on average · 1 prompts"));
+        assert!(report.ends_with("command,uses\n/commit,1\n/theme,2\n```"));
+
+        metrics.purge().unwrap();
+        assert!(!metrics.enabled());
+    }
+}
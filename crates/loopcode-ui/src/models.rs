@@ -0,0 +1,56 @@
+/// The models the picker in the bottom bar offers, by provider and name,
+/// each with the name OpenRouter knows it by.
+const MODELS: [(&str, &str); 5] = [
+    ("OpenRouter GPT-4.1", "openai/gpt-4.1"),
+    ("OpenRouter GPT-4.1-mini", "openai/gpt-4.1-mini"),
+    ("OpenRouter o4-mini", "openai/o4-mini"),
+    ("OpenAI GPT-4o", "openai/gpt-4o"),
+    ("Anthropic Sonnet", "anthropic/claude-sonnet-4"),
+];
+
+/// The name to send for the model labelled `label`. A label the picker
+/// does not know is taken to be a name already.
+pub(crate) fn wire_name(label: &str) -> &str {
+    MODELS
+        .iter()
+        .find(|(known, _)| *known == label)
+        .map_or(label, |(_, name)| name)
+}
+
+/// A select over the known models with `current` chosen. A model picked some
+/// other way, such as a `/compare` lane, is listed first so it stays
+/// selectable.
+pub(crate) fn picker(current: &str) -> xpui::SelectState {
+    let mut items: Vec<String> = MODELS.iter().map(|(model, _)| model.to_string()).collect();
+    let selected = match items.iter().position(|model| model == current) {
+        Some(index) => index,
+        None => {
+            items.insert(0, current.to_string());
+            0
+        }
+    };
+    xpui::SelectState::new(items, selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typing_narrows_the_models_and_enter_picks_one() {
+        let mut picker = picker("OpenRouter gpt-5");
+        assert_eq!(picker.selected_item(), Some("OpenRouter gpt-5"));
+        picker.open();
+        for ch in "mini".chars() {
+            picker.handle_key(xpui::UiKeyInput::Char(ch));
+        }
+        assert_eq!(picker.matches().len(), 2);
+        picker.handle_key(xpui::UiKeyInput::Down);
+        assert_eq!(
+            picker.handle_key(xpui::UiKeyInput::Enter),
+            xpui::SelectOutcome::Chose(3)
+        );
+        assert_eq!(picker.selected_item(), Some("OpenRouter o4-mini"));
+        assert!(!picker.is_open());
+    }
+}
@@ -0,0 +1,2201 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use unicode_width::UnicodeWidthChar;
+use unicode_width::UnicodeWidthStr;
+use xpui::IntoNode;
+
+use crate::actions::{MessageAction, MessageMenu};
+use crate::activity::{self, Activity};
+use crate::chat::{
+    ChatMessage, ChatOptions, ChatState, HistoryEvent, MessageStatus, Role, ToolApproval,
+    UsageTotals,
+};
+use crate::draft::DraftPurpose;
+use crate::paste::PasteHint;
+use crate::remote::RemoteRoot;
+use crate::screens::{FocusKey, Screen, ScreenState};
+use crate::session::SessionId;
+use crate::state::AppState;
+use crate::warmup::{Warmed, Warmup};
+use crate::{
+    bookmarks, changelog, compare, complete, dropped, files, highlight, measure, menu_bar, models,
+    paths, provider, replace, risk, search, session, table, todos,
+};
+
+#[derive(Clone, Copy)]
+enum AgentMode {
+    Safe,
+    Autonomous,
+    Jailbreaking,
+}
+
+impl AgentMode {
+    fn cycle(self) -> Self {
+        match self {
+            Self::Safe => Self::Autonomous,
+            Self::Autonomous => Self::Jailbreaking,
+            Self::Jailbreaking => Self::Safe,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Safe => "Safest",
+            Self::Autonomous => "Autonomous",
+            Self::Jailbreaking => "Jailbreak",
+        }
+    }
+
+    fn tool_approval(self) -> ToolApproval {
+        match self {
+            Self::Safe => ToolApproval::Mutating,
+            Self::Autonomous => ToolApproval::Risky,
+            Self::Jailbreaking => ToolApproval::Never,
+        }
+    }
+}
+
+/// What dragging with the left button held does to the history.
+#[derive(Clone, Copy)]
+enum HistoryDrag {
+    /// Moves the scrollbar thumb, held this many rows below its top.
+    Thumb { grab: u16 },
+    /// Pans the list, which was at `offset` when the press was on row `y`.
+    Pan { y: u16, offset: u16 },
+}
+
+impl HistoryDrag {
+    /// Takes hold of the scrollbar thumb of `list` for a press on screen row
+    /// `y`, with the history starting `top` rows down; the thumb jumps there
+    /// first when the press was on the track.
+    fn thumb(list: &xpui::FocusListState, top: u16, y: u16) -> Option<Self> {
+        let (start, len) = list.scrollbar_thumb()?;
+        let row = y.checked_sub(top)?;
+        let grab = if (start..start + len).contains(&row) {
+            row - start
+        } else {
+            len / 2
+        };
+        Some(Self::Thumb { grab })
+    }
+
+    /// Where the drag scrolls `list` to with the pointer on screen row `y`.
+    fn offset(self, list: &xpui::FocusListState, top: u16, y: u16) -> u16 {
+        match self {
+            Self::Thumb { grab } => {
+                list.offset_for_thumb_row(y.saturating_sub(top).saturating_sub(grab))
+            }
+            // The content follows the pointer, so dragging down scrolls up.
+            Self::Pan { y: start, offset } => {
+                (i32::from(offset) + i32::from(start) - i32::from(y)).max(0) as u16
+            }
+        }
+    }
+}
+
+struct FocusUiState {
+    history: xpui::SelectListState,
+    focus: xpui::FocusState,
+    /// Focus entries of the last frame, for the breadcrumb.
+    entries: Vec<xpui::FocusEntry>,
+}
+
+impl FocusUiState {
+    fn new(initial_heights: Vec<u16>, viewport: u16, gap: u16) -> Self {
+        let mut history = xpui::SelectListState::new(
+            "history",
+            xpui::FocusId(ChatPanel::SCROLL_ID),
+            ChatPanel::FIRST_ITEM_ID,
+            gap,
+        )
+        .empty_message("No messages yet.")
+        .empty_hint("Type a prompt below to start.");
+        let mut focus = xpui::FocusState::default();
+        history.update(&focus, initial_heights, viewport);
+        focus.set_focused(xpui::FocusId(ChatPanel::INPUT_ID));
+        Self {
+            history,
+            focus,
+            entries: Vec::new(),
+        }
+    }
+}
+
+/// The whole chat screen: history, input and status bar.
+pub struct ChatPanel {
+    window_size: xpui::WindowSize,
+    chat: ChatState,
+    history_events: xpui::signal::EventSignal<HistoryEvent>,
+    nav: FocusUiState,
+    mode: AgentMode,
+    /// What dropped `mode` from Autonomous, flagged in the status bar until
+    /// the mode is changed by hand.
+    downgraded: Option<risk::RiskKind>,
+    input_scroll_offset: u16,
+    bookmarks: Option<bookmarks::BookmarkPicker>,
+    whats_new: Option<changelog::WhatsNew>,
+    /// The actions menu of a history message, shown under the history.
+    menu: Option<MessageMenu>,
+    /// Path candidates for Tab in the input, shown in the same place.
+    completion: Option<complete::Completion>,
+    highlighter: highlight::Highlighter,
+    /// History row heights by content and width, across frames and runs.
+    row_heights: measure::HeightCache,
+    /// Where the left button went down in the input, as a visual row and
+    /// column, for an Alt+drag to select a block from.
+    input_press: Option<(usize, usize)>,
+    /// What the left button held down in the history drags.
+    history_drag: Option<HistoryDrag>,
+    /// The offer to fence what was just pasted, shown above the input until
+    /// the next key.
+    paste_hint: Option<PasteHint>,
+    /// The screen the history area showed last frame.
+    shown_screen: Screen,
+    /// The panel the activity bar has the main area show.
+    activity: Activity,
+    /// The Files panel, kept while hidden so it stays where it was.
+    files: Option<files::FilePanel>,
+    search: search::SearchPanel,
+    /// Startup work still running; the splash shows instead of the chat
+    /// until it is done.
+    warmup: Option<Warmup>,
+    /// What went wrong during warmup, one line each over the chat until Esc
+    /// dismisses it.
+    banners: Vec<String>,
+    /// The model picker while its list is open.
+    model_picker: Option<xpui::SelectState>,
+    /// The tool dialog is set aside while `/replace` is typed into the input
+    /// under it, until the replacement is applied or Esc brings it back.
+    replacing: bool,
+}
+
+impl ChatPanel {
+    const INPUT_CONTAINER_ID: u64 = 10;
+    const INPUT_ID: u64 = 1;
+    const SCROLL_ID: u64 = 2;
+    const MENU_ID: u64 = 11;
+    const COMPLETION_ID: u64 = 12;
+    const BANNER_BUTTON_ID: u64 = 13;
+    const MODEL_BUTTON_ID: u64 = 14;
+    const MODEL_MENU_ID: u64 = 15;
+    const APPROVE_ID: u64 = 16;
+    const DENY_ID: u64 = 17;
+    const ALWAYS_ALLOW_ID: u64 = 18;
+    const MODE_BUTTON_ID: u64 = 19;
+    const REPLACE_ID: u64 = 20;
+    const ITEM_GAP_LINES: u16 = 1;
+    /// Roomier spacing between messages in presentation mode.
+    const PRESENT_GAP_LINES: u16 = 2;
+    const FIRST_ITEM_ID: u64 = 1000;
+    /// Lines of a tool call the approval dialog shows before cutting it,
+    /// enough for the start of a file being written.
+    const TOOL_DIALOG_LINES: usize = 16;
+
+    /// Opens a chat on `session`, answered by `chat_provider`. `wire_log`
+    /// backs `/debug requests`; when `session` is an error the chat runs
+    /// unsaved and says why.
+    pub fn new(
+        chat_provider: Box<dyn provider::ChatProvider>,
+        wire_log: Option<provider::WireLog>,
+        session: Result<session::Session, session::SessionError>,
+        options: ChatOptions,
+    ) -> Self {
+        Self::build(chat_provider, wire_log, Some(session), options)
+    }
+
+    /// Like [`Self::new`], but opens the session with `open_session` on
+    /// another thread, loading settings and listing the workspace files
+    /// alongside, while a splash shows how far each has got. Whatever fails
+    /// is shown over the chat once it starts, rather than stopping it.
+    pub fn starting<F>(
+        chat_provider: Box<dyn provider::ChatProvider>,
+        wire_log: Option<provider::WireLog>,
+        open_session: F,
+        options: ChatOptions,
+    ) -> Self
+    where
+        F: FnOnce() -> Result<session::Session, session::SessionError> + Send + 'static,
+    {
+        let shared_prompts = options.shared_prompt_history;
+        let mut panel = Self::build(chat_provider, wire_log, None, options);
+        panel.warmup = Some(Warmup::start(
+            shared_prompts,
+            Box::new(open_session),
+            panel.chat.workspace.roots().to_vec(),
+        ));
+        panel
+    }
+
+    fn build(
+        chat_provider: Box<dyn provider::ChatProvider>,
+        wire_log: Option<provider::WireLog>,
+        session: Option<Result<session::Session, session::SessionError>>,
+        options: ChatOptions,
+    ) -> Self {
+        let history_events = xpui::signal::EventSignal::new();
+        let chat = ChatState::new(
+            history_events.clone(),
+            chat_provider,
+            wire_log,
+            session,
+            options,
+        );
+        let heights = chat
+            .history
+            .borrow()
+            .iter()
+            .map(|message| Self::wrapped_line_count(&Self::shown_history_row(message), 78))
+            .collect::<Vec<_>>();
+        let mut nav = FocusUiState::new(heights, 8, Self::ITEM_GAP_LINES);
+        let whats_new = changelog::take_unseen();
+        if whats_new.is_some() {
+            nav.focus.save_focus();
+        }
+
+        Self {
+            window_size: xpui::WindowSize::default(),
+            chat,
+            history_events,
+            nav,
+            mode: AgentMode::Safe,
+            downgraded: None,
+            input_scroll_offset: 0,
+            bookmarks: None,
+            whats_new,
+            menu: None,
+            completion: None,
+            highlighter: highlight::Highlighter::new(),
+            row_heights: measure::HeightCache::new(),
+            input_press: None,
+            history_drag: None,
+            paste_hint: None,
+            shown_screen: Screen::Chat,
+            activity: Activity::Chat,
+            files: None,
+            search: search::SearchPanel::default(),
+            warmup: None,
+            banners: Vec::new(),
+            model_picker: None,
+            replacing: false,
+        }
+    }
+
+    /// Takes in what warmup finished, and says whether it is still running.
+    fn poll_warmup(&mut self) -> bool {
+        let Some(warmup) = self.warmup.as_mut() else {
+            return false;
+        };
+        for warmed in warmup.poll() {
+            match warmed {
+                Warmed::Index(paths) => {
+                    if self.files.is_none() {
+                        let workspace = self.chat.workspace.clone();
+                        self.files = Some(files::FilePanel::listed(workspace, paths));
+                    }
+                }
+                warmed => self.chat.warm(warmed),
+            }
+        }
+        if !warmup.is_done() {
+            return true;
+        }
+        self.banners.extend(warmup.failures());
+        self.warmup = None;
+        false
+    }
+
+    /// The current session; forking or `/open` can change it while running.
+    pub fn session_id(&self) -> xpui::signal::Signal<Option<SessionId>> {
+        self.chat.session_id.clone()
+    }
+
+    /// Running totals for this panel, updated as turns finish.
+    pub fn usage(&self) -> xpui::signal::Signal<UsageTotals> {
+        self.chat.usage.clone()
+    }
+
+    fn is_input_focused(&self) -> bool {
+        self.nav.focus.is_focused(xpui::FocusId(Self::INPUT_ID))
+    }
+
+    fn is_input_container_focused(&self) -> bool {
+        self.nav
+            .focus
+            .is_focused(xpui::FocusId(Self::INPUT_CONTAINER_ID))
+    }
+
+    fn is_scroll_focused(&self) -> bool {
+        self.nav.focus.is_focused(xpui::FocusId(Self::SCROLL_ID))
+    }
+
+    fn input_visual_metrics(&self, total_width: usize) -> (u16, u16) {
+        let lines: Vec<&str> = self.chat.input.value().split('\n').collect();
+        let line_count = lines.len().max(1);
+        let gutter_digits = line_count.to_string().len();
+        let content_width = if self.chat.input.wraps() {
+            total_width.saturating_sub(gutter_digits + 3).max(1)
+        } else {
+            usize::MAX
+        };
+
+        let mut total_visual = 0u16;
+        let mut cursor_visual = 0u16;
+        let mut cursor_left = self.chat.input.cursor();
+        let mut cursor_found = false;
+
+        for line in lines {
+            let mut wraps = 1u16;
+            let mut col = 0usize;
+            let mut line_chars = 0usize;
+            for ch in line.chars() {
+                let w = UnicodeWidthChar::width(ch).unwrap_or(0);
+                if col > 0 && col.saturating_add(w) > content_width {
+                    wraps = wraps.saturating_add(1);
+                    col = 0;
+                }
+                col = col.saturating_add(w);
+                line_chars += 1;
+            }
+
+            if !cursor_found && cursor_left <= line_chars {
+                let mut ccol = 0usize;
+                let mut cwrap = 0u16;
+                for ch in line.chars().take(cursor_left) {
+                    let w = UnicodeWidthChar::width(ch).unwrap_or(0);
+                    if ccol > 0 && ccol.saturating_add(w) > content_width {
+                        cwrap = cwrap.saturating_add(1);
+                        ccol = 0;
+                    }
+                    ccol = ccol.saturating_add(w);
+                }
+                cursor_visual = total_visual.saturating_add(cwrap);
+                cursor_found = true;
+            }
+
+            cursor_left = cursor_left.saturating_sub(line_chars.saturating_add(1));
+            total_visual = total_visual.saturating_add(wraps);
+        }
+
+        (total_visual.max(1), cursor_visual)
+    }
+
+    fn format_history_row(message: &str, focused: bool) -> String {
+        let mut lines = message.lines();
+        let first = lines.next().unwrap_or_default();
+        let mut out = format!("{} {}", if focused { "▶" } else { " " }, first);
+        for line in lines {
+            out.push('\n');
+            out.push_str("  ");
+            out.push_str(line);
+        }
+        out
+    }
+
+    /// The row `message` shows in the history, as measured: an assistant's
+    /// markdown without the markers it is drawn with.
+    fn shown_history_row(message: &ChatMessage) -> String {
+        let row = Self::format_history_row(&message.display_text(), false);
+        if message.role == Role::Assistant {
+            highlight::markdown_text(&row)
+        } else {
+            row
+        }
+    }
+
+    /// A hash of what [`Self::shown_history_row`] reads, for caching the
+    /// height of the row.
+    fn history_row_key(message: &ChatMessage) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (message.role == Role::Assistant, message.display_text()).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Rows `text` takes when wrapped between words at `wrap_width`, as
+    /// history rows are drawn.
+    fn wrapped_line_count(text: &str, wrap_width: usize) -> u16 {
+        let text = xpui::RichText {
+            word_wrap: true,
+            ..xpui::RichText::plain(text)
+        };
+        let lines = xpui::wrapped_height(&text, wrap_width.max(1));
+        u16::try_from(lines).unwrap_or(u16::MAX)
+    }
+
+    fn usage_top_parts(
+        &self,
+        input_focused: bool,
+        input_container_focused: bool,
+        scroll_focused: bool,
+    ) -> Vec<(&'static str, &'static str)> {
+        if self.replacing {
+            vec![
+                ("/replace <find> <with>", "preview"),
+                ("/replace apply", "apply"),
+                ("Esc", "back to approval"),
+            ]
+        } else if let Some((name, arguments)) = self.chat.awaiting_call() {
+            let mut parts = vec![
+                ("y", "approve"),
+                ("n/Esc", "deny"),
+                ("a", "always allow this tool"),
+            ];
+            if replace::pending_write(name, arguments).is_some() {
+                parts.push(("r", "find and replace"));
+            }
+            parts.push(("Tab", "move"));
+            parts
+        } else if self.menu.is_some() {
+            vec![("Up/Down", "choose"), ("Enter", "run"), ("Esc", "close")]
+        } else if self.model_picker.is_some() {
+            vec![("Type", "filter"), ("Enter", "pick"), ("Esc", "close")]
+        } else if input_focused && self.completion.is_some() {
+            vec![("Tab", "next"), ("Enter", "insert"), ("Esc", "cancel")]
+        } else if input_focused && self.chat.comparison.is_some() {
+            vec![
+                ("/compare pick a|b", "keep reply"),
+                ("/compare off", "discard"),
+                ("PgUp/PgDn", "scroll both"),
+            ]
+        } else if let Some(purpose) = self.chat.editing_draft().filter(|_| input_focused) {
+            let use_key = if xpui::capabilities().ctrl_enter {
+                "Ctrl+Enter"
+            } else {
+                "Alt+Enter"
+            };
+            match purpose {
+                DraftPurpose::Commit => vec![(use_key, "commit"), ("Esc", "cancel commit")],
+                DraftPurpose::PullRequest => {
+                    vec![(use_key, "create PR with gh"), ("Esc", "cancel PR")]
+                }
+            }
+        } else if input_focused && self.chat.note_target().is_some() {
+            let save_key = if xpui::capabilities().ctrl_enter {
+                "Ctrl+Enter"
+            } else {
+                "Alt+Enter"
+            };
+            vec![
+                (save_key, "save note (empty removes it)"),
+                ("Esc", "cancel note"),
+            ]
+        } else if input_focused && self.chat.filter_target().is_some() {
+            let keep_key = if xpui::capabilities().ctrl_enter {
+                "Ctrl+Enter"
+            } else {
+                "Alt+Enter"
+            };
+            vec![(keep_key, "keep filter"), ("Esc", "put back filter")]
+        } else if input_focused {
+            let send_key = if xpui::capabilities().ctrl_enter {
+                "Ctrl+Enter"
+            } else {
+                "Alt+Enter"
+            };
+            let send_action = if !self.chat.is_streaming() {
+                "send"
+            } else if self.chat.input.value().trim().is_empty()
+                && self.chat.history.queued_count() > 0
+            {
+                "send queued now"
+            } else {
+                "queue"
+            };
+            vec![
+                (send_key, send_action),
+                ("Enter", "newline"),
+                ("Tab", "complete path"),
+                ("Esc", "exit input"),
+            ]
+        } else if input_container_focused {
+            vec![
+                ("Enter", "focus input"),
+                ("Up", "see history"),
+                ("Ctrl+G", "jump"),
+            ]
+        } else if scroll_focused {
+            vec![
+                ("Enter", "focus and scroll"),
+                ("Down", "return to input"),
+                ("Ctrl+G", "jump"),
+            ]
+        } else {
+            vec![
+                ("Up/Down", "navigate"),
+                ("Enter", "actions"),
+                ("f", "fork here"),
+                ("n", "note"),
+                ("m", "bookmark"),
+                ("'", "bookmarks"),
+                ("Esc", "return to chat list"),
+            ]
+        }
+    }
+
+    fn bottom_bar_node(
+        &self,
+        width: usize,
+        input_focused: bool,
+        input_container_focused: bool,
+        scroll_focused: bool,
+    ) -> xpui::Node {
+        let parts = self.usage_top_parts(input_focused, input_container_focused, scroll_focused);
+        let picker = match &self.model_picker {
+            Some(picker) => picker.clone(),
+            None => models::picker(&self.chat.selected_model.borrow()),
+        };
+        let usage_top_plain = parts
+            .iter()
+            .map(|(k, a)| format!("{k} {a}"))
+            .collect::<Vec<_>>()
+            .join(" • ");
+        let queued = self.chat.history.queued_count();
+        let usage_mid_left = if self.nav.focus.quit_armed() {
+            "Press Ctrl+C again to quit".to_string()
+        } else if self
+            .chat
+            .comparison
+            .as_ref()
+            .is_some_and(compare::Comparison::is_streaming)
+        {
+            "Comparing…".to_string()
+        } else if self.nav.focus.interrupted_recently() {
+            "Stopped · Ctrl+C twice more quits".to_string()
+        } else if self.chat.is_streaming() && queued > 0 {
+            format!("Responding… {queued} queued · Ctrl+C stops")
+        } else if self.chat.is_streaming() {
+            "Responding… · Ctrl+C stops".to_string()
+        } else if self.chat.queue_paused() {
+            format!("Stopped · {queued} queued, send an empty message to go on")
+        } else {
+            String::new()
+        };
+        let usage_mid_right = "45% used · $0.21";
+        let left_w = usage_top_plain.width();
+        // The picker button pads its label and adds an arrow.
+        let right_w = picker.selected_item().unwrap_or_default().width() + 4;
+        let spaces = if left_w + right_w + 1 > width {
+            1
+        } else {
+            width - left_w - right_w
+        };
+
+        let theme = xpui::theme();
+        let key_style = xpui::TextStyle::new().color(theme.muted);
+        let action_style = xpui::TextStyle::new().color(theme.faint);
+        let dot_style = xpui::TextStyle::new().color(theme.border);
+        let usage_left_style = xpui::TextStyle::new().color(theme.faint);
+        let usage_right_style = xpui::TextStyle::new().color(theme.muted);
+
+        let mut line1 = xpui::text("");
+        for (i, (key, action)) in parts.iter().enumerate() {
+            if i > 0 {
+                line1 = line1.run(" · ", dot_style.clone());
+            }
+            line1 = line1
+                .run(*key, key_style.clone())
+                .run(" ", action_style.clone())
+                .run(*action, action_style.clone());
+        }
+        line1 = line1.run(" ".repeat(spaces), xpui::TextStyle::new());
+        let model_button = xpui::FocusId(Self::MODEL_BUTTON_ID);
+        let line1 = xpui::row().child(line1).child(
+            xpui::select(&picker, model_button).focused(self.nav.focus.is_focused(model_button)),
+        );
+
+        let mid_left_w = usage_mid_left.width();
+        let mid_right_w = usage_mid_right.width();
+        let mid_spaces = if mid_left_w + mid_right_w + 1 > width {
+            1
+        } else {
+            width - mid_left_w - mid_right_w
+        };
+
+        xpui::column()
+            .child(line1)
+            .child(
+                xpui::text("")
+                    .run(usage_mid_left, usage_left_style)
+                    .run(" ".repeat(mid_spaces), xpui::TextStyle::new())
+                    .run(usage_mid_right, usage_right_style),
+            )
+            .into_node()
+    }
+
+    fn mode_surface_colors(&self) -> (xpui::Rgb, xpui::Rgb) {
+        match self.mode {
+            AgentMode::Safe => (xpui::rgb(0x1f4d2b), xpui::rgb(0xf2fbf4)),
+            AgentMode::Autonomous => (xpui::rgb(0x1f3f66), xpui::rgb(0xf2f7fc)),
+            AgentMode::Jailbreaking => (xpui::rgb(0x6b2f2f), xpui::rgb(0xfcf3f3)),
+        }
+    }
+
+    fn mode_tag_colors(&self) -> (xpui::Rgb, xpui::Rgb) {
+        match self.mode {
+            AgentMode::Safe => (xpui::rgb(0x132a13), xpui::rgb(0xb7f7c0)),
+            AgentMode::Autonomous => (xpui::rgb(0x10243d), xpui::rgb(0xb3e3ff)),
+            AgentMode::Jailbreaking => (xpui::rgb(0x3a1212), xpui::rgb(0xffc9c9)),
+        }
+    }
+
+    /// The left of the status bar: the active root, which a click cycles
+    /// when there are several, and what follows it.
+    fn status_left(&self, width: usize) -> (String, String) {
+        let mut left = String::new();
+        if let Some(origin) = &self.chat.fork_origin {
+            left.push_str(&format!(" · ⑂ fork of {}", origin.parent));
+        }
+        let paged_out = self.chat.history.paged_out();
+        if paged_out > 0 {
+            left.push_str(&format!(" · ↑ {paged_out} older on disk"));
+        }
+        if let Some(target) = self.chat.target.indicator() {
+            left.push_str(&format!(" · {target}"));
+        }
+        if let Some(latency) = &self.chat.remote_latency {
+            left.push_str(&format!(" · ⇅ {}", latency.label()));
+        }
+        let breadcrumb = self.nav.focus.breadcrumb(&self.nav.entries);
+        if !breadcrumb.is_empty() {
+            left.push_str(&format!(" · {}", breadcrumb.join(" › ")));
+        }
+        let label = match self.chat.workspace.position() {
+            (_, 1) => "Dir: ".to_string(),
+            (at, count) => format!("⇄ Root {at}/{count}: "),
+        };
+        let right_plain = format!(" {} ", "MODE") + &format!(" {} ", self.mode_label());
+        // The directory gets what the rest leaves, cut in the middle.
+        let dir_width =
+            width.saturating_sub(label.width() + left.width() + right_plain.width() + 1);
+        let active = self.chat.workspace.active();
+        let dir = match RemoteRoot::of(active) {
+            Some(remote) => paths::display(std::path::Path::new(&remote.label()), None, dir_width),
+            None => paths::display(active, None, dir_width),
+        };
+        (format!("{label}{dir}"), left)
+    }
+
+    fn status_bar_node(&self, width: usize) -> xpui::Node {
+        let mode_tag = format!(" {} ", "MODE");
+        let mode_value = format!(" {} ", self.mode_label());
+        let right_plain = format!("{mode_tag}{mode_value}");
+        let (root, rest) = self.status_left(width);
+        let left = format!("{root}{rest}");
+        let left_w = left.width();
+        let right_w = right_plain.width();
+        let spaces = if left_w + right_w + 1 > width {
+            1
+        } else {
+            width - left_w - right_w
+        };
+        let (tag_fg, tag_bg) = self.mode_tag_colors();
+        let (value_fg, value_bg) = self.mode_surface_colors();
+        let mode_tag_style = xpui::TextStyle::new().bg(tag_bg).color(tag_fg).bold();
+        // The value takes the tag's colors too while pointed at or focused,
+        // saying the tag is a button.
+        let value_style = xpui::BoxStyle::default().bg(value_bg).text_color(value_fg);
+        let lit_style = xpui::BoxStyle::default().bg(tag_bg).text_color(tag_fg);
+        let mode_button = xpui::FocusId(Self::MODE_BUTTON_ID);
+        let style = if self.nav.focus.is_focused(mode_button) {
+            lit_style.clone()
+        } else {
+            value_style
+        };
+        let mode = xpui::text("")
+            .run(mode_tag, mode_tag_style)
+            .run(mode_value, xpui::TextStyle::new().bold());
+
+        xpui::row()
+            .child(xpui::text(left).run(" ".repeat(spaces), xpui::TextStyle::new()))
+            .child(
+                xpui::container(mode)
+                    .style(style)
+                    .hover_style(lit_style)
+                    .focus(mode_button)
+                    .focus_kind(xpui::FocusKind::Button)
+                    .label("Mode"),
+            )
+            .into_node()
+    }
+
+    /// Columns the activity bar takes; none while presenting.
+    fn bar_width(&self) -> usize {
+        if self.chat.presenting {
+            0
+        } else {
+            usize::from(activity::BAR_WIDTH)
+        }
+    }
+
+    /// Columns left for the main area beside the activity bar.
+    fn main_width(&self) -> usize {
+        (self.window_size.width as usize).saturating_sub(self.bar_width())
+    }
+
+    /// Whether the main area shows the messages, with a scrollbar beside
+    /// them, rather than another view.
+    fn shows_message_list(&self) -> bool {
+        !self.chat.presenting
+            && self.activity == Activity::Chat
+            && self.whats_new.is_none()
+            && self.chat.log_view.is_none()
+            && self.chat.todos.is_none()
+            && self.bookmarks.is_none()
+            && self.chat.comparison.is_none()
+    }
+
+    /// Columns the history takes, less the scrollbar while there is one.
+    fn history_width(&self) -> usize {
+        let scrollbar = usize::from(self.shows_message_list());
+        self.main_width().saturating_sub(scrollbar)
+    }
+
+    fn history_viewport_lines(&self) -> u16 {
+        let terminal_lines = (self.window_size.height as u16).max(1);
+        if self.chat.presenting {
+            return terminal_lines.max(3);
+        }
+        let dynamic_input_max = ((self.window_size.height * 0.20).floor() as u16).max(5);
+        let input_wrap_width = (self.window_size.width as usize).max(8);
+        let (input_visual_lines, _) = self.input_visual_metrics(input_wrap_width);
+        let input_viewport_lines = input_visual_lines.clamp(1, dynamic_input_max);
+        // input(1 block) + help(2) + status(1) + vertical gaps(3) + menu
+        let reserved_without_history = 6u16
+            .saturating_add(input_viewport_lines)
+            .saturating_add(self.attachment_lines())
+            .saturating_add(self.menu_lines())
+            .saturating_add(self.history_top());
+        terminal_lines.saturating_sub(reserved_without_history).max(3)
+    }
+
+    /// The row above the input for pasted images and the paste hint, when
+    /// there are any.
+    fn attachment_lines(&self) -> u16 {
+        u16::from(!self.chat.attachments.is_empty() || self.paste_hint.is_some())
+    }
+
+    /// Rows the open actions menu or completion popup takes, with its gap.
+    fn menu_lines(&self) -> u16 {
+        let menu = self.menu.as_ref().map(|menu| &menu.menu);
+        let completion = self.completion.as_ref().map(|completion| &completion.menu);
+        menu.or(completion).map_or(0, |menu| menu.lines() + 1)
+    }
+
+    fn input_viewport_lines(&self) -> u16 {
+        let dynamic_input_max = ((self.window_size.height * 0.20).floor() as u16).max(5);
+        let input_wrap_width = (self.window_size.width as usize).max(8);
+        let (input_visual_lines, _) = self.input_visual_metrics(input_wrap_width);
+        input_visual_lines.clamp(1, dynamic_input_max)
+    }
+
+    fn input_layout_for_click(&self) -> (usize, u16, u16, usize) {
+        let line_count = self.chat.input.value().split('\n').count().max(1);
+        let gutter_digits = line_count.to_string().len();
+        let input_total_width = (self.window_size.width as usize).max(8);
+        let content_width = input_total_width.saturating_sub(gutter_digits + 3).max(1);
+        let dynamic_input_max = ((self.window_size.height * 0.20).floor() as u16).max(5);
+        let (input_visual_lines, _) = self.input_visual_metrics(input_total_width);
+        let input_viewport_lines = input_visual_lines.clamp(1, dynamic_input_max);
+        let input_offset_lines = self
+            .input_scroll_offset
+            .min(input_visual_lines.saturating_sub(input_viewport_lines));
+        (content_width, input_viewport_lines, input_offset_lines, gutter_digits)
+    }
+
+    /// The visual row and column of the input under screen cell `(x, y)`,
+    /// and the wrap width they are in; `None` outside the input.
+    fn input_position_at(&self, x: u16, y: u16) -> Option<(usize, usize, usize)> {
+        let input_top = self
+            .history_viewport_lines()
+            .saturating_add(1)
+            .saturating_add(self.attachment_lines());
+        let (content_width, input_viewport_lines, input_offset_lines, gutter_digits) =
+            self.input_layout_for_click();
+        let input_bottom = input_top.saturating_add(input_viewport_lines);
+        if y < input_top || y >= input_bottom {
+            return None;
+        }
+        let local_row = y.saturating_sub(input_top) as usize;
+        let visual_row = usize::from(input_offset_lines).saturating_add(local_row);
+        let content_x = x.saturating_sub((gutter_digits + 3) as u16) as usize;
+        Some((visual_row, content_x, content_width))
+    }
+
+    fn input_max_scroll_offset(&self) -> u16 {
+        let input_total_width = (self.window_size.width as usize).max(8);
+        let (input_visual_lines, _) = self.input_visual_metrics(input_total_width);
+        let input_viewport_lines = self.input_viewport_lines();
+        input_visual_lines.saturating_sub(input_viewport_lines)
+    }
+
+    fn clamp_input_scroll_offset(&mut self) {
+        self.input_scroll_offset = self.input_scroll_offset.min(self.input_max_scroll_offset());
+    }
+
+    fn history_item_at_row(&self, row: u16) -> Option<u16> {
+        self.nav.history.item_at_line(row)
+    }
+
+    /// The message and table column whose header is drawn at `x`, `row` of
+    /// the history.
+    fn table_header_at(&self, x: u16, row: u16) -> Option<(usize, usize)> {
+        let (index, mut line_row) = self.nav.history.item_line_at(row)?;
+        let index = usize::from(index);
+        let wrap_width = self.history_width().saturating_sub(2).max(1);
+        let history = self.chat.history.borrow();
+        let message = history.get(index)?;
+        let view = message.table.as_ref()?;
+        let body = Self::shown_history_row(message);
+        for line in body.split('\n') {
+            let height = Self::wrapped_line_count(line, wrap_width);
+            if line_row >= height {
+                line_row -= height;
+                continue;
+            }
+            // Headers fit on one line and are never the first, which holds
+            // the role label.
+            let header = line.strip_prefix("  ").filter(|_| height == 1)?;
+            let x = usize::from(x).checked_sub(2 + self.bar_width())?;
+            let column = table::header_column(&message.text, view, header, x)?;
+            return Some((index, column));
+        }
+        None
+    }
+
+    /// The mode as the status bar shows it, with what dropped it from
+    /// Autonomous.
+    fn mode_label(&self) -> String {
+        match self.downgraded {
+            Some(kind) => format!("{} ↓ {}", self.mode.title(), kind.label()),
+            None => self.mode.title().to_string(),
+        }
+    }
+
+    /// The main area for a panel of the activity bar other than Chat.
+    fn activity_view(&mut self, viewport_lines: u16) -> xpui::ScrollViewWidget {
+        let width = self.main_width();
+        let (body, offset) = match self.activity {
+            Activity::Files => {
+                let panel = self
+                    .files
+                    .get_or_insert_with(|| files::FilePanel::open(self.chat.workspace.clone()));
+                panel.follow(&self.chat.workspace);
+                panel.render(width, viewport_lines)
+            }
+            Activity::Search => self.search.render(
+                &self.chat.history.borrow(),
+                self.chat.history.paged_out(),
+                width,
+                viewport_lines,
+            ),
+            Activity::Tasks => {
+                let tasks = self.chat.tasks();
+                let mut out = match tasks.len() {
+                    0 => "Nothing running · Esc back".to_string(),
+                    count => format!("{count} running · Esc back"),
+                };
+                for task in tasks {
+                    out.push_str("\n  ");
+                    out.push_str(&bookmarks::truncate(&task, width.saturating_sub(2)));
+                }
+                (out, 0)
+            }
+            Activity::Settings => (self.chat.settings(), 0),
+            Activity::Chat => (String::new(), 0),
+        };
+        xpui::scroll_view(xpui::text(body))
+            .focus(xpui::FocusId(Self::SCROLL_ID))
+            .label(format!(
+                "{} · Alt+{}",
+                self.activity.title(),
+                self.activity.shortcut()
+            ))
+            .viewport_lines(viewport_lines)
+            .offset_lines(offset)
+    }
+
+    /// Input for the panel shown in place of the chat; false for what it
+    /// leaves to the rest of the screen, such as the status bar.
+    fn on_panel_input(&mut self, event: xpui::UiInputEvent) -> bool {
+        let page = usize::from(self.history_viewport_lines());
+        let key = match event {
+            xpui::UiInputEvent::Key(key) => key,
+            xpui::UiInputEvent::ScrollLines(lines) if lines < 0 => xpui::UiKeyInput::Up,
+            xpui::UiInputEvent::ScrollLines(_) => xpui::UiKeyInput::Down,
+            xpui::UiInputEvent::MouseDown { y, .. } => return usize::from(y) < page,
+            _ => return false,
+        };
+        match self.activity {
+            Activity::Files => match self.files.as_mut().map(|panel| panel.handle_key(key, page)) {
+                Some(files::FileOutcome::Insert(path)) => {
+                    self.chat.input.insert_str(&path);
+                    self.show_activity(Activity::Chat);
+                    self.nav.focus.set_focused(xpui::FocusId(Self::INPUT_ID));
+                }
+                Some(files::FileOutcome::Close) => self.show_activity(Activity::Chat),
+                _ => {}
+            },
+            Activity::Search => {
+                let outcome = self.search.handle_key(key, &self.chat.history.borrow());
+                match outcome {
+                    search::SearchOutcome::Pending => {}
+                    search::SearchOutcome::Jump(index) => {
+                        // Coming back to the chat focuses the message.
+                        let offset = self.nav.history.list().scroll_offset();
+                        let focus = FocusKey::Message(self.chat.history.paged_out() + index);
+                        self.chat.screens.save(
+                            Screen::Chat,
+                            ScreenState {
+                                focus: Some(focus),
+                                offset,
+                            },
+                        );
+                        self.show_activity(Activity::Chat);
+                    }
+                    search::SearchOutcome::Close => self.show_activity(Activity::Chat),
+                }
+            }
+            _ => {
+                if key == xpui::UiKeyInput::Esc {
+                    self.show_activity(Activity::Chat);
+                }
+            }
+        }
+        true
+    }
+
+    /// Shows `activity` in the main area.
+    fn show_activity(&mut self, activity: Activity) {
+        self.activity = activity;
+        self.completion = None;
+    }
+
+    /// The screen in the history area.
+    fn screen(&self) -> Screen {
+        if self.activity != Activity::Chat {
+            Screen::Panel(self.activity)
+        } else if self.chat.log_view.is_some() {
+            Screen::Log
+        } else if self.chat.todos.is_some() {
+            Screen::Todos
+        } else {
+            Screen::Chat
+        }
+    }
+
+    /// Notes where the user is on the screen shown, and puts them back
+    /// where they were when they return to one.
+    fn track_screen(&mut self) {
+        let screen = self.screen();
+        if screen != self.shown_screen {
+            if let Some(state) = self.chat.screens.get(screen).cloned() {
+                self.restore_screen(screen, state);
+            }
+            self.shown_screen = screen;
+        }
+        let state = match screen {
+            Screen::Chat => {
+                let focus = if self.is_input_focused() {
+                    Some(FocusKey::Input)
+                } else {
+                    self.nav
+                        .history
+                        .focused_index(&self.nav.focus)
+                        .map(|index| {
+                            FocusKey::Message(self.chat.history.paged_out() + usize::from(index))
+                        })
+                };
+                ScreenState {
+                    focus,
+                    offset: self.nav.history.list().scroll_offset(),
+                }
+            }
+            Screen::Log => ScreenState {
+                focus: self
+                    .chat
+                    .log_view
+                    .as_ref()
+                    .and_then(xpui::LogViewState::anchor)
+                    .map(FocusKey::LogLine),
+                offset: 0,
+            },
+            Screen::Todos => ScreenState {
+                focus: self
+                    .chat
+                    .todos
+                    .as_ref()
+                    .and_then(todos::TodoPanel::focus_key),
+                offset: 0,
+            },
+            Screen::Panel(_) => ScreenState::default(),
+        };
+        self.chat.screens.save(screen, state);
+    }
+
+    fn restore_screen(&mut self, screen: Screen, state: ScreenState) {
+        match (screen, state.focus) {
+            (Screen::Chat, Some(FocusKey::Input)) if !self.chat.presenting => {
+                self.nav.focus.set_focused(xpui::FocusId(Self::INPUT_ID));
+            }
+            (Screen::Chat, Some(FocusKey::Message(number))) => {
+                // A message paged out since stays unfocused.
+                if let Some(index) = number.checked_sub(self.chat.history.paged_out()) {
+                    self.nav.history.select(&mut self.nav.focus, index as u16);
+                }
+            }
+            (Screen::Log, focus) => {
+                if let Some(log_view) = self.chat.log_view.as_mut() {
+                    log_view.set_anchor(match focus {
+                        Some(FocusKey::LogLine(seq)) => Some(seq),
+                        _ => None,
+                    });
+                }
+            }
+            (Screen::Todos, Some(key)) => {
+                if let Some(todos) = self.chat.todos.as_mut() {
+                    todos.restore_focus(&key);
+                }
+            }
+            _ => {}
+        }
+        if screen == Screen::Chat {
+            self.nav.history.list_mut().set_scroll_offset(state.offset);
+        }
+    }
+
+    /// Drops Autonomous mode to Safest for each risky tool call the turn
+    /// made, flagging it in the transcript.
+    fn downgrade_for_risks(&mut self) {
+        for risk in self.chat.take_risks() {
+            if matches!(self.mode, AgentMode::Autonomous) {
+                self.mode = AgentMode::Safe;
+                self.chat.tool_approval = self.mode.tool_approval();
+                self.downgraded = Some(risk.kind);
+                self.chat.flag_downgrade(&risk);
+            }
+        }
+    }
+
+    fn cycle_mode(&mut self) {
+        self.mode = self.mode.cycle();
+        self.chat.tool_approval = self.mode.tool_approval();
+        self.downgraded = None;
+    }
+
+    /// Rows above the history: the warmup banner and the gap under it,
+    /// while there is one.
+    fn history_top(&self) -> u16 {
+        if self.banners.is_empty() { 0 } else { 2 }
+    }
+
+    /// The history row drawn on screen row `y`, if the history is there.
+    fn history_row(&self, y: u16) -> Option<u16> {
+        y.checked_sub(self.history_top())
+            .filter(|row| *row < self.history_viewport_lines())
+    }
+
+    fn is_scrollbar_click(&self, x: u16, y: u16) -> bool {
+        self.shows_message_list()
+            && usize::from(x) == self.bar_width() + self.history_width()
+            && self.history_row(y).is_some()
+    }
+
+    /// Takes hold of the scrollbar thumb at screen row `y`.
+    fn press_scrollbar(&mut self, y: u16) {
+        let top = self.history_top();
+        if let Some(drag) = HistoryDrag::thumb(self.nav.history.list(), top, y) {
+            self.history_drag = Some(drag);
+            self.drag_history(drag, y);
+        }
+    }
+
+    /// Scrolls the history as `drag` has it with the pointer on screen row
+    /// `y`.
+    fn drag_history(&mut self, drag: HistoryDrag, y: u16) {
+        let offset = drag.offset(self.nav.history.list(), self.history_top(), y);
+        self.nav.history.scroll_to(&mut self.nav.focus, offset);
+    }
+
+    fn is_root_click(&self, x: u16, y: u16) -> bool {
+        let width = self.window_size.width as usize;
+        let height = self.window_size.height as u16;
+        self.chat.workspace.roots().len() > 1
+            && y == height.saturating_sub(1)
+            && usize::from(x) < self.status_left(width).0.width()
+    }
+
+    fn open_menu(&mut self, index: usize) {
+        let title = format!("Message {}", self.chat.history.paged_out() + index + 1);
+        self.menu = self
+            .chat
+            .history
+            .borrow()
+            .get(index)
+            .map(|message| MessageMenu::open(index, message, title));
+    }
+
+    /// Replaces the input's chars `start..end` with `text`, cursor after it.
+    fn replace_input(&mut self, start: usize, end: usize, text: &str) {
+        let value = self.chat.input.value();
+        let mut out = value.chars().take(start).collect::<String>();
+        out.push_str(text);
+        out.extend(value.chars().skip(end));
+        self.chat.input.set_value(out);
+        self.chat.input.set_cursor(start + text.chars().count());
+        self.clamp_input_scroll_offset();
+    }
+
+    /// The question a tool call waiting for approval puts: what it would do,
+    /// and buttons to approve it, deny it or stop asking about its tool.
+    fn tool_dialog(&self, name: &str, arguments: &str) -> xpui::Node {
+        let theme = xpui::theme();
+        let width = (self.window_size.width as usize).saturating_sub(4).min(72);
+        let mut body = xpui::text("").run(
+            format!(" Allow {name}?"),
+            xpui::TextStyle::new().color(theme.accent).bold(),
+        );
+        let summary = self.chat.tools().describe(name, arguments);
+        let lines = summary.lines().count();
+        for line in summary.lines().take(Self::TOOL_DIALOG_LINES) {
+            body = body.run(
+                format!("\n {}", bookmarks::truncate(line, width.saturating_sub(2))),
+                xpui::TextStyle::default(),
+            );
+        }
+        if lines > Self::TOOL_DIALOG_LINES {
+            body = body.run(
+                format!("\n … {} more lines", lines - Self::TOOL_DIALOG_LINES),
+                xpui::TextStyle::new().color(theme.muted),
+            );
+        }
+        let button = |label: &str, id| {
+            let id = xpui::FocusId(id);
+            xpui::button(label, id).focused(self.nav.focus.is_focused(id))
+        };
+        let mut buttons = xpui::row()
+            .gap(1)
+            .child(xpui::text(""))
+            .child(button("Approve", Self::APPROVE_ID))
+            .child(button("Deny", Self::DENY_ID))
+            .child(button("Always allow", Self::ALWAYS_ALLOW_ID));
+        if replace::pending_write(name, arguments).is_some() {
+            buttons = buttons.child(button("Find and replace", Self::REPLACE_ID));
+        }
+        xpui::container(xpui::column().gap(1).child(body).child(buttons))
+            .style(
+                xpui::BoxStyle::default()
+                    .bg(theme.surface)
+                    .width(width as u16),
+            )
+            .label("Tool approval")
+            .into_node()
+    }
+
+    fn close_menu(&mut self) {
+        self.menu = None;
+        // Esc already left the trap on its way here.
+        if self.nav.focus.trap().is_some() {
+            self.nav.focus.pop_trap();
+        }
+    }
+
+    fn run_action(&mut self, index: usize, action: MessageAction) {
+        match action {
+            MessageAction::Edit => {
+                self.chat.edit_message(index);
+                self.nav.focus.set_focused(xpui::FocusId(Self::INPUT_ID));
+            }
+            MessageAction::Regenerate => self.chat.regenerate(index),
+            MessageAction::Bookmark => self.chat.toggle_bookmark(index),
+            MessageAction::Note => {
+                self.chat.begin_note(index);
+                self.nav.focus.set_focused(xpui::FocusId(Self::INPUT_ID));
+            }
+            MessageAction::Fork => self.chat.fork_from(index),
+            MessageAction::Expand => self.chat.toggle_expanded(index),
+            MessageAction::Copy(format) => self.chat.copy_code(index, format),
+            MessageAction::CopyText => self.chat.copy_text(index),
+            MessageAction::Retry => self.chat.regenerate(index),
+            MessageAction::Table => self.chat.toggle_table(index),
+            MessageAction::SortTable => self.chat.sort_table(index),
+            MessageAction::TablePage { forward } => self.chat.turn_table_page(index, forward),
+            MessageAction::FilterTable => {
+                self.chat.begin_table_filter(index);
+                self.nav.focus.set_focused(xpui::FocusId(Self::INPUT_ID));
+            }
+        }
+    }
+}
+
+impl xpui::UiApp for ChatPanel {
+    fn set_window_size(&mut self, size: xpui::WindowSize) {
+        self.window_size = size;
+    }
+
+    fn menu_bar(&self) -> Vec<xpui::AppMenu> {
+        menu_bar::menu_bar()
+    }
+
+    fn window_title(&self) -> Option<String> {
+        Some(match self.chat.awaiting_user() {
+            0 => "loopcode".to_string(),
+            waiting => format!("loopcode · {waiting} waiting on you"),
+        })
+    }
+
+    fn saved_window_state(&self) -> Option<xpui::WindowState> {
+        AppState::load().window.map(Into::into)
+    }
+
+    fn save_window_state(&mut self, state: xpui::WindowState) {
+        let mut saved = AppState::load();
+        saved.window = Some(state.into());
+        saved.save();
+    }
+
+    fn render(&mut self) -> xpui::Node {
+        let theme = self.chat.current_theme();
+        if xpui::theme() != theme {
+            xpui::set_theme(theme.clone());
+        }
+        if self.poll_warmup()
+            && let Some(warmup) = &self.warmup
+        {
+            return xpui::container(xpui::text(warmup.render()))
+                .label("Starting")
+                .style(xpui::BoxStyle::default().text_color(theme.text))
+                .into_node();
+        }
+        self.nav.focus.expire_quit_arm();
+        self.chat.poll_stream();
+        self.downgrade_for_risks();
+        self.chat.poll_remote();
+        self.nav.focus.set_interruptible(self.chat.is_streaming());
+        if self.chat.presenting {
+            self.activity = Activity::Chat;
+        }
+        let wrap_width = self.history_width().saturating_sub(2).max(1);
+        let streaming = self.chat.is_streaming();
+        let row_heights = &mut self.row_heights;
+        let heights = self.chat.history_heights_memo.get_or_update(
+            (self.chat.history.version(), wrap_width),
+            || {
+                row_heights.heights(
+                    &self.chat.history.borrow(),
+                    wrap_width,
+                    Self::history_row_key,
+                    |message| {
+                        Self::wrapped_line_count(&Self::shown_history_row(message), wrap_width)
+                    },
+                    !streaming,
+                )
+            },
+        );
+        let history_viewport_lines = self.history_viewport_lines();
+        let gap_lines = if self.chat.presenting {
+            Self::PRESENT_GAP_LINES
+        } else {
+            Self::ITEM_GAP_LINES
+        };
+        self.nav.history.list_mut().set_gap_lines(gap_lines);
+        // Read before the new row count can rule out a row paged out of it.
+        let focused = self.nav.history.focused_index(&self.nav.focus);
+        self.nav
+            .history
+            .update(&self.nav.focus, heights, history_viewport_lines);
+
+        let mut should_scroll_to_bottom = false;
+        let mut should_follow_stream = false;
+        let mut shifted = 0isize;
+        self.history_events.drain(|event| match event {
+            HistoryEvent::UserAppended => should_scroll_to_bottom = true,
+            HistoryEvent::Streamed => should_follow_stream = true,
+            HistoryEvent::Reset => {}
+            HistoryEvent::Prepended(count) => shifted += count as isize,
+            HistoryEvent::PagedOut(count) => shifted -= count as isize,
+        });
+        if shifted != 0 {
+            // Stay on the same message now that the ones above it changed.
+            if let Some(index) = focused {
+                let index = (index as isize + shifted).clamp(0, u16::MAX as isize) as u16;
+                self.nav.history.select(&mut self.nav.focus, index);
+            }
+            if self.bookmarks.is_some() {
+                self.bookmarks = Some(bookmarks::BookmarkPicker::open(&self.chat.history.borrow()));
+            }
+        } else if self.nav.history.focused_index(&self.nav.focus) == Some(0) {
+            self.chat.load_older();
+        }
+        // Keep the streaming reply in view unless the user is browsing history.
+        if should_follow_stream && (self.is_input_focused() || self.is_input_container_focused()) {
+            should_scroll_to_bottom = true;
+        }
+        if should_scroll_to_bottom {
+            let list = self.nav.history.list_mut();
+            let count = list.item_count();
+            if count > 0 {
+                list.set_focused_index(count - 1);
+            }
+        }
+
+        self.track_screen();
+
+        let input_focused = self.is_input_focused();
+        let input_container_focused = self.is_input_container_focused();
+        let scroll_focused = self.is_scroll_focused();
+        // Esc leaves the input before the popup sees it.
+        if !input_focused {
+            self.completion = None;
+            self.paste_hint = None;
+        }
+        // The input is gone while presenting; start at the latest message.
+        if self.chat.presenting && (input_focused || input_container_focused) {
+            let count = self.nav.history.list().item_count();
+            if count > 0 {
+                self.nav.history.select(&mut self.nav.focus, count - 1);
+            } else {
+                self.nav.focus.set_focused(xpui::FocusId(Self::SCROLL_ID));
+            }
+        }
+        let (input_content_width, ..) = self.input_layout_for_click();
+        self.chat.input.follow_cursor(input_content_width);
+        let dynamic_input_max = ((self.window_size.height * 0.20).floor() as u16).max(5);
+        let input_wrap_width = (self.window_size.width as usize).max(8);
+        let (input_visual_lines, _) = self.input_visual_metrics(input_wrap_width);
+        let input_viewport_lines = input_visual_lines.clamp(1, dynamic_input_max);
+        let max_input_offset = input_visual_lines.saturating_sub(input_viewport_lines);
+        let input_offset_lines = self.input_scroll_offset.min(max_input_offset);
+        let history_viewport_lines = self.history_viewport_lines();
+        if should_scroll_to_bottom {
+            self.nav.history.list_mut().scroll_to_bottom();
+        }
+
+        let main_width = self.main_width();
+        let history_view = if let Some(whats_new) = &self.whats_new {
+            let (body, offset) = whats_new.render();
+            xpui::scroll_view(xpui::markdown(&body))
+                .focus(xpui::FocusId(Self::SCROLL_ID))
+                .label("What's new")
+                .viewport_lines(history_viewport_lines)
+                .offset_lines(offset)
+        } else if self.activity != Activity::Chat {
+            self.activity_view(history_viewport_lines)
+        } else if let Some(log_view) = self.chat.log_view.as_mut() {
+            // One line goes to the status line above the log.
+            log_view.set_viewport_lines(history_viewport_lines.saturating_sub(1));
+            xpui::log_view(log_view).focus(xpui::FocusId(Self::SCROLL_ID))
+        } else if let Some(todos) = self.chat.todos.as_mut() {
+            todos.poll();
+            let (body, offset) = todos.render(main_width, history_viewport_lines);
+            xpui::scroll_view(xpui::text(body))
+                .focus(xpui::FocusId(Self::SCROLL_ID))
+                .label("TODOs")
+                .viewport_lines(history_viewport_lines)
+                .offset_lines(offset)
+        } else if let Some(picker) = &self.bookmarks {
+            let body = picker.render(&self.chat.history.borrow(), main_width);
+            xpui::scroll_view(xpui::text(body))
+                .focus(xpui::FocusId(Self::SCROLL_ID))
+                .label("Bookmarks")
+                .viewport_lines(history_viewport_lines)
+        } else if let Some(comparison) = self.chat.comparison.as_mut() {
+            let (body, offset) = comparison.render(main_width, history_viewport_lines);
+            xpui::scroll_view(xpui::text(body))
+                .focus(xpui::FocusId(Self::SCROLL_ID))
+                .label("Comparison")
+                .viewport_lines(history_viewport_lines)
+                .offset_lines(offset)
+        } else {
+            self.highlighter.poll();
+            let paged_out = self.chat.history.paged_out();
+            let highlighter = &mut self.highlighter;
+            xpui::select_list(
+                &self.nav.history,
+                &self.nav.focus,
+                self.chat.history.borrow().iter(),
+                |i, message, is_focused| {
+                    let label = format!("Message {}", paged_out + usize::from(i) + 1);
+                    // A reply waiting for its first token; one line, like the
+                    // row it turns into.
+                    if message.status == MessageStatus::Streaming && message.text.is_empty() {
+                        let marker = if is_focused { "▶" } else { " " };
+                        let header = format!("{marker} {}: ", message.role.label());
+                        return xpui::container(
+                            xpui::row()
+                                .child(xpui::text(header))
+                                .child(xpui::skeleton(1).width(24)),
+                        )
+                        .label(label);
+                    }
+                    let body = Self::format_history_row(&message.display_text(), is_focused);
+                    let markdown = message.role == Role::Assistant;
+                    let text = highlighter.row(&body, markdown).word_wrap();
+                    let mut row = xpui::container(text).label(label);
+                    if message.failure.is_some() {
+                        row = row.style(
+                            xpui::BoxStyle::default()
+                                .bg(theme.surface)
+                                .text_color(theme.removed),
+                        );
+                    }
+                    // The run header only counts the files; list them.
+                    match &message.run {
+                        Some(run) if !run.files.is_empty() => {
+                            let files = run
+                                .files
+                                .iter()
+                                .map(|file| self.chat.workspace.shorten(std::path::Path::new(file)))
+                                .collect::<Vec<_>>();
+                            row.tooltip(files.join("\n"))
+                        }
+                        _ => row,
+                    }
+                },
+            )
+            .label("History")
+        };
+
+        let history = xpui::container(history_view).key("history");
+        let main = if self.chat.presenting {
+            history.into_node()
+        } else {
+            let streaming = self.chat.is_streaming() && self.activity != Activity::Chat;
+            let tasks = self.chat.tasks().len();
+            let badges = |activity| match activity {
+                Activity::Chat if streaming => Some('•'),
+                Activity::Tasks => activity::badge(tasks),
+                _ => None,
+            };
+            let width = self.history_width() as u16;
+            let mut main = xpui::row()
+                .child(activity::bar(self.activity, badges))
+                .child(history.style(xpui::BoxStyle::default().width(width)));
+            if self.shows_message_list() {
+                main = main.child(xpui::scrollbar(self.nav.history.list()));
+            }
+            main.into_node()
+        };
+        let mut layout = xpui::column().gap(1);
+        if let Some(banner) = self.banners.first() {
+            let more = match self.banners.len() - 1 {
+                0 => String::new(),
+                more => format!(" (+{more} more)"),
+            };
+            let line = format!("⚠ {banner}{more} · Esc dismisses");
+            let dismiss = xpui::FocusId(Self::BANNER_BUTTON_ID);
+            let width = (self.window_size.width as usize).saturating_sub(" Dismiss ".len() + 1);
+            layout = layout.child(
+                xpui::container(
+                    xpui::row()
+                        .gap(1)
+                        .child(xpui::text(bookmarks::truncate(&line, width)))
+                        .child(
+                            xpui::button("Dismiss", dismiss)
+                                .focused(self.nav.focus.is_focused(dismiss)),
+                        ),
+                )
+                .label("Warning")
+                .style(
+                    xpui::BoxStyle::default()
+                        .bg(theme.surface)
+                        .text_color(theme.removed),
+                ),
+            );
+        }
+        layout = layout.child(main);
+        if let Some(picker) = &self.model_picker {
+            let list = xpui::select_menu(picker, xpui::FocusId(Self::MODEL_MENU_ID));
+            layout = layout.child(xpui::overlay(list).key("model-picker"));
+        }
+        if let Some((name, arguments)) = self.chat.awaiting_call().filter(|_| !self.replacing) {
+            let dialog = self.tool_dialog(name, arguments);
+            layout = layout.child(xpui::overlay(dialog).key("tool-dialog"));
+        }
+        if let Some(menu) = &self.menu {
+            layout = layout
+                .child(xpui::context_menu(&menu.menu, xpui::FocusId(Self::MENU_ID)).key("menu"));
+        } else if let Some(completion) = &self.completion {
+            layout = layout.child(
+                xpui::context_menu(&completion.menu, xpui::FocusId(Self::COMPLETION_ID))
+                    .key("completion"),
+            );
+        }
+        if self.chat.presenting {
+            return xpui::container(layout)
+                .style(xpui::BoxStyle::default().text_color(theme.text))
+                .into_node();
+        }
+        let input = xpui::scroll_view(
+            xpui::text_input_from_state(&self.chat.input)
+                .placeholder("Find and fix issues.")
+                .ghost(self.chat.prompt_suggestion())
+                .focus(xpui::FocusId(Self::INPUT_ID))
+                .focused(input_focused)
+                .gutter_highlighted(input_focused || input_container_focused)
+                .visible_offset_lines(input_offset_lines)
+                .visible_lines(input_viewport_lines),
+        )
+        .viewport_lines(input_viewport_lines)
+        .offset_lines(input_offset_lines);
+        let input = if self.attachment_lines() == 0 {
+            xpui::container(input)
+        } else {
+            let chips = if let Some(hint) = &self.paste_hint {
+                hint.label()
+            } else {
+                let chips = self
+                    .chat
+                    .attachments
+                    .iter()
+                    .map(|image| format!("[▣ {}]", image.label()))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                format!("{chips}  Backspace removes")
+            };
+            xpui::container(
+                xpui::column()
+                    .child(
+                        xpui::container(xpui::text(chips))
+                            .style(xpui::BoxStyle::default().text_color(theme.accent)),
+                    )
+                    .child(input),
+            )
+        };
+        xpui::container(
+            layout
+                .child(
+                    input
+                        .key("input")
+                        .focus(xpui::FocusId(Self::INPUT_CONTAINER_ID))
+                        .label("Input"),
+                )
+                .child(
+                    xpui::container(
+                        xpui::scroll_view(self.bottom_bar_node(
+                            self.window_size.width as usize,
+                            input_focused,
+                            input_container_focused,
+                            scroll_focused,
+                        ))
+                        .viewport_lines(2),
+                    )
+                    .style(xpui::BoxStyle::default().text_color(theme.text)),
+                )
+                .child(
+                    xpui::container(self.status_bar_node(self.window_size.width as usize))
+                        .style(
+                            xpui::BoxStyle::default()
+                                .bg(theme.surface)
+                                .text_color(theme.muted),
+                        ),
+                ),
+        )
+        .style(xpui::BoxStyle::default().text_color(theme.text))
+        .into_node()
+    }
+
+    fn on_input(&mut self, event: xpui::UiInputEvent) {
+        // The first Ctrl+C while a reply streams stops it; focus navigation
+        // handles the ones that quit.
+        if event == xpui::UiInputEvent::Key(xpui::UiKeyInput::Interrupt) {
+            self.chat.stop();
+            return;
+        }
+
+        // Nothing but quitting until the splash is done.
+        if self.warmup.is_some() && event != xpui::UiInputEvent::Tick {
+            return;
+        }
+
+        if let xpui::UiInputEvent::MenuCommand(command) = &event {
+            self.chat.run_menu_command(command);
+            return;
+        }
+
+        // Ctrl+C still quits from the what's-new screen.
+        if let Some(whats_new) = self.whats_new.as_mut()
+            && !matches!(
+                event,
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::Interrupt) | xpui::UiInputEvent::Tick
+            )
+        {
+            if whats_new.handle_event(event) {
+                self.whats_new = None;
+                self.nav.focus.restore_focus();
+            }
+            return;
+        }
+
+        // A stopped run takes the question `/replace` was editing with it.
+        if self.chat.awaiting_call().is_none() {
+            self.replacing = false;
+        }
+        if self.replacing && event == xpui::UiInputEvent::Key(xpui::UiKeyInput::Esc) {
+            self.replacing = false;
+            self.chat.input.set_value("");
+            return;
+        }
+
+        // Nothing behind the tool dialog takes input until it is answered, but
+        // Ctrl+C still stops the run, and with it the question.
+        if self.chat.awaiting_call().is_some()
+            && !self.replacing
+            && !matches!(
+                event,
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::Interrupt) | xpui::UiInputEvent::Tick
+            )
+        {
+            let approve = xpui::FocusId(Self::APPROVE_ID);
+            let deny = xpui::FocusId(Self::DENY_ID);
+            let always_allow = xpui::FocusId(Self::ALWAYS_ALLOW_ID);
+            match event {
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::Char('y')) => self.chat.answer_tool(true),
+                xpui::UiInputEvent::Activate(id) if id == approve => self.chat.answer_tool(true),
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::Char('n') | xpui::UiKeyInput::Esc) => {
+                    self.chat.answer_tool(false)
+                }
+                xpui::UiInputEvent::Activate(id) if id == deny => self.chat.answer_tool(false),
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::Char('a')) => {
+                    self.chat.always_allow_tool()
+                }
+                xpui::UiInputEvent::Activate(id) if id == always_allow => {
+                    self.chat.always_allow_tool()
+                }
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::Char('r'))
+                | xpui::UiInputEvent::Activate(xpui::FocusId(Self::REPLACE_ID))
+                    if self
+                        .chat
+                        .awaiting_call()
+                        .and_then(|(name, arguments)| replace::pending_write(name, arguments))
+                        .is_some() =>
+                {
+                    self.replacing = true;
+                    self.chat.begin_replace();
+                    self.nav.focus.set_focused(xpui::FocusId(Self::INPUT_ID));
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let dismiss = xpui::UiInputEvent::Activate(xpui::FocusId(Self::BANNER_BUTTON_ID));
+        if !self.banners.is_empty()
+            && self.menu.is_none()
+            && self.model_picker.is_none()
+            && self.completion.is_none()
+            && (event == xpui::UiInputEvent::Key(xpui::UiKeyInput::Esc) || event == dismiss)
+        {
+            self.banners.remove(0);
+            return;
+        }
+
+        if let Some(picker) = self.model_picker.as_mut() {
+            match event {
+                xpui::UiInputEvent::Key(key) => {
+                    match picker.handle_key(key) {
+                        xpui::SelectOutcome::Pending => {}
+                        xpui::SelectOutcome::Close => self.model_picker = None,
+                        xpui::SelectOutcome::Chose(_) => {
+                            if let Some(model) = picker.selected_item() {
+                                self.chat.selected_model.set(model.to_string());
+                            }
+                            self.model_picker = None;
+                        }
+                    }
+                    return;
+                }
+                // A click anywhere dismisses the list.
+                xpui::UiInputEvent::MouseDown { .. } => {
+                    self.model_picker = None;
+                    return;
+                }
+                _ => {}
+            }
+        }
+        if event == xpui::UiInputEvent::Activate(xpui::FocusId(Self::MODE_BUTTON_ID)) {
+            self.cycle_mode();
+            return;
+        }
+        if event == xpui::UiInputEvent::Activate(xpui::FocusId(Self::MODEL_BUTTON_ID)) {
+            let mut picker = models::picker(&self.chat.selected_model.borrow());
+            picker.open();
+            self.model_picker = Some(picker);
+            return;
+        }
+
+        if let Some(menu) = self.menu.as_mut() {
+            match event {
+                xpui::UiInputEvent::Key(key) => {
+                    match menu.menu.handle_key(key) {
+                        xpui::MenuOutcome::Pending => {}
+                        xpui::MenuOutcome::Close => self.close_menu(),
+                        xpui::MenuOutcome::Chose(item) => {
+                            let index = menu.index;
+                            let action = menu.action(item);
+                            self.close_menu();
+                            if let Some(action) = action {
+                                self.run_action(index, action);
+                            }
+                        }
+                    }
+                    return;
+                }
+                // A click anywhere dismisses the menu.
+                xpui::UiInputEvent::MouseDown { .. } => {
+                    self.close_menu();
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        if self.chat.presenting {
+            match event {
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::Esc | xpui::UiKeyInput::Char('q')) => {
+                    self.chat.presenting = false;
+                    self.nav.focus.set_focused(xpui::FocusId(Self::INPUT_ID));
+                }
+                xpui::UiInputEvent::MouseDown { y, .. } => {
+                    if let Some(index) = self.history_item_at_row(y) {
+                        self.nav.history.select(&mut self.nav.focus, index);
+                    }
+                }
+                // Read-only: Enter opens no menu and row keys do nothing.
+                xpui::UiInputEvent::Key(
+                    xpui::UiKeyInput::Enter | xpui::UiKeyInput::Submit | xpui::UiKeyInput::Char(_),
+                ) => {}
+                _ => {
+                    let _ = self.nav.history.handle_input(&mut self.nav.focus, event);
+                }
+            }
+            return;
+        }
+
+        match event {
+            xpui::UiInputEvent::Key(xpui::UiKeyInput::Panel(place)) => {
+                if let Some(activity) = Activity::from_shortcut(place) {
+                    self.show_activity(activity);
+                }
+                return;
+            }
+            xpui::UiInputEvent::MouseDown {
+                x,
+                y,
+                button: xpui::UiMouseButton::Left,
+                ..
+            } if usize::from(x) < self.bar_width() && y < self.history_viewport_lines() => {
+                if let Some(activity) = Activity::at_row(y) {
+                    self.show_activity(activity);
+                }
+                return;
+            }
+            _ => {}
+        }
+        if self.activity != Activity::Chat && self.on_panel_input(event.clone()) {
+            return;
+        }
+
+        if let Some(completion) = self.completion.as_mut() {
+            let key = match event {
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::Tab | xpui::UiKeyInput::Down) => {
+                    Some(xpui::UiKeyInput::Down)
+                }
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::ShiftTab | xpui::UiKeyInput::Up) => {
+                    Some(xpui::UiKeyInput::Up)
+                }
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::Enter) => Some(xpui::UiKeyInput::Enter),
+                xpui::UiInputEvent::Tick => return,
+                _ => None,
+            };
+            match key.map(|key| completion.menu.handle_key(key)) {
+                Some(xpui::MenuOutcome::Pending) => return,
+                Some(xpui::MenuOutcome::Chose(item)) => {
+                    let choice = completion
+                        .choice(item)
+                        .map(|(start, end, text)| (start, end, text.to_string()));
+                    self.completion = None;
+                    if let Some((start, end, text)) = choice {
+                        self.replace_input(start, end, &text);
+                    }
+                    return;
+                }
+                Some(xpui::MenuOutcome::Close) => {
+                    self.completion = None;
+                    return;
+                }
+                // Anything else dismisses the popup and goes on as usual.
+                None => self.completion = None,
+            }
+        }
+
+        if let xpui::UiInputEvent::MouseDown { x, y, button, alt } = event {
+            self.history_drag = None;
+            if button == xpui::UiMouseButton::Left && self.is_scrollbar_click(x, y) {
+                self.press_scrollbar(y);
+                return;
+            }
+            if button == xpui::UiMouseButton::Left && self.is_root_click(x, y) {
+                self.chat.workspace.cycle();
+                return;
+            }
+
+            if let Some(row) = self.history_row(y)
+                && button != xpui::UiMouseButton::Middle
+                && let Some(index) = self.history_item_at_row(row)
+            {
+                self.nav.history.select(&mut self.nav.focus, index);
+                if button == xpui::UiMouseButton::Left {
+                    let offset = self.nav.history.list().scroll_offset();
+                    self.history_drag = Some(HistoryDrag::Pan { y, offset });
+                }
+                if button == xpui::UiMouseButton::Left
+                    && let Some((index, column)) = self.table_header_at(x, row)
+                {
+                    self.chat.sort_table_by(index, column);
+                }
+                if button == xpui::UiMouseButton::Right
+                    && self.bookmarks.is_none()
+                    && self.chat.comparison.is_none()
+                {
+                    self.open_menu(usize::from(index));
+                }
+                return;
+            }
+
+            self.input_press = None;
+            if let Some((visual_row, content_x, content_width)) = self.input_position_at(x, y) {
+                self.nav.focus.set_focused(xpui::FocusId(Self::INPUT_ID));
+                if button == xpui::UiMouseButton::Left {
+                    self.input_press = Some((visual_row, content_x));
+                }
+                if alt && button == xpui::UiMouseButton::Left {
+                    self.chat.input.toggle_cursor_at_visual_position(
+                        visual_row,
+                        content_x,
+                        content_width,
+                    );
+                    return;
+                }
+                self.chat
+                    .input
+                    .set_cursor_from_visual_position(visual_row, content_x, content_width);
+                if button == xpui::UiMouseButton::Middle
+                    && let Some(selection) = xpui::primary_selection()
+                {
+                    self.chat.input.insert_str(&selection);
+                    self.clamp_input_scroll_offset();
+                }
+                return;
+            }
+
+            return;
+        }
+
+        if let xpui::UiInputEvent::MouseDrag { x, y, alt } = event {
+            if !alt && let Some(drag) = self.history_drag {
+                self.drag_history(drag, y);
+            } else if alt
+                && let Some(anchor) = self.input_press
+                && let Some((visual_row, content_x, content_width)) = self.input_position_at(x, y)
+            {
+                self.chat.input.select_block_at_visual_positions(
+                    anchor,
+                    (visual_row, content_x),
+                    content_width,
+                );
+            }
+            return;
+        }
+        if let xpui::UiInputEvent::MouseUp { .. } = event {
+            self.history_drag = None;
+            return;
+        }
+
+        if let Some(picker) = self.bookmarks.as_mut()
+            && let xpui::UiInputEvent::Key(key) = event
+        {
+            match picker.handle_key(key) {
+                bookmarks::PickerOutcome::Pending => {}
+                bookmarks::PickerOutcome::Close => {
+                    self.bookmarks = None;
+                    self.nav.focus.restore_focus();
+                }
+                bookmarks::PickerOutcome::Jump(index) => {
+                    self.bookmarks = None;
+                    self.nav.focus.forget_saved_focus();
+                    self.nav.history.select(&mut self.nav.focus, index as u16);
+                }
+            }
+            return;
+        }
+
+        if let Some(log_view) = self.chat.log_view.as_mut() {
+            // The wheel scrolls the log a line per line it turns.
+            let keys = match event {
+                xpui::UiInputEvent::Key(key) => Some((key, 1)),
+                xpui::UiInputEvent::ScrollLines(lines) if lines < 0 => {
+                    Some((xpui::UiKeyInput::Up, lines.unsigned_abs()))
+                }
+                xpui::UiInputEvent::ScrollLines(lines) => {
+                    Some((xpui::UiKeyInput::Down, lines as u16))
+                }
+                _ => None,
+            };
+            if let Some((key, times)) = keys {
+                for _ in 0..times {
+                    if log_view.handle_key(key) == xpui::LogViewOutcome::Close {
+                        self.chat.log_view = None;
+                        break;
+                    }
+                }
+                return;
+            }
+        }
+
+        if let Some(todos) = self.chat.todos.as_mut()
+            && let xpui::UiInputEvent::Key(key) = event
+        {
+            match todos.handle_key(key) {
+                todos::TodoOutcome::Pending => {}
+                todos::TodoOutcome::Close => self.chat.todos = None,
+                todos::TodoOutcome::Send(prompt) => {
+                    self.chat.todos = None;
+                    self.chat.send(prompt, Vec::new());
+                }
+            }
+            return;
+        }
+
+        let page = self.history_viewport_lines() as i16;
+        if let Some(comparison) = self.chat.comparison.as_mut() {
+            let lines = match event {
+                xpui::UiInputEvent::ScrollLines(lines) => Some(lines),
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::PageUp) => Some(-page),
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::PageDown) => Some(page),
+                _ => None,
+            };
+            if let Some(lines) = lines {
+                comparison.scroll_by(lines);
+                return;
+            }
+        }
+
+        if matches!(event, xpui::UiInputEvent::Key(xpui::UiKeyInput::ShiftTab)) {
+            self.cycle_mode();
+            return;
+        }
+
+        let line_count = self.chat.input.value().split('\n').count().max(1);
+        let gutter_digits = line_count.to_string().len();
+        let input_total_width = (self.window_size.width as usize).max(8);
+        let input_content_width = input_total_width.saturating_sub(gutter_digits + 3).max(1);
+        let wrap_width = self.chat.input.wraps().then_some(input_content_width);
+        self.chat.input.set_soft_wrap_width(wrap_width);
+
+        if let xpui::UiInputEvent::Paste(text) = &event {
+            self.nav.focus.set_focused(xpui::FocusId(Self::INPUT_ID));
+            // Files dragged onto a terminal arrive as their paths.
+            match dropped::dropped_paths(text) {
+                Some(paths) => self.chat.drop_files(&paths),
+                None => self.paste_hint = self.chat.paste_text(text),
+            }
+            self.clamp_input_scroll_offset();
+            return;
+        }
+
+        if let xpui::UiInputEvent::DropFiles(paths) = &event {
+            self.nav.focus.set_focused(xpui::FocusId(Self::INPUT_ID));
+            self.chat.drop_files(paths);
+            self.clamp_input_scroll_offset();
+            return;
+        }
+
+        if self.is_input_focused() {
+            // Tab takes up the offer to fence a paste; any other key drops it.
+            let hint = self
+                .paste_hint
+                .take_if(|_| event != xpui::UiInputEvent::Tick);
+            if let Some(hint) = hint
+                && event == xpui::UiInputEvent::Key(xpui::UiKeyInput::Tab)
+            {
+                let pasted = self
+                    .chat
+                    .input
+                    .value()
+                    .chars()
+                    .skip(hint.start)
+                    .take(hint.end - hint.start)
+                    .collect::<String>();
+                self.replace_input(hint.start, hint.end, &hint.fence(&pasted));
+                return;
+            }
+
+            if let xpui::UiInputEvent::ScrollLines(lines) = event {
+                let max_offset = self.input_max_scroll_offset();
+                if lines < 0 {
+                    self.input_scroll_offset = self.input_scroll_offset.saturating_sub(1);
+                } else if lines > 0 {
+                    self.input_scroll_offset = self.input_scroll_offset.saturating_add(1);
+                }
+                self.input_scroll_offset = self.input_scroll_offset.min(max_offset);
+                return;
+            }
+
+            if matches!(event, xpui::UiInputEvent::Key(xpui::UiKeyInput::Submit)) {
+                let _ = self.chat.submit_input();
+                // A preview offers to apply it; anything else ends the replace.
+                if self.replacing {
+                    if self.chat.replacement_previewed() {
+                        self.chat.begin_replace();
+                    } else {
+                        self.replacing = false;
+                    }
+                }
+                return;
+            }
+
+            if let xpui::UiInputEvent::Key(key @ (xpui::UiKeyInput::Copy | xpui::UiKeyInput::Cut)) =
+                event
+            {
+                self.chat.copy_input(key == xpui::UiKeyInput::Cut);
+                self.clamp_input_scroll_offset();
+                return;
+            }
+
+            if event == xpui::UiInputEvent::Key(xpui::UiKeyInput::Paste) {
+                self.paste_hint = self.chat.paste();
+                self.clamp_input_scroll_offset();
+                return;
+            }
+
+            // Backspace in an empty input takes back the last pasted image.
+            if event == xpui::UiInputEvent::Key(xpui::UiKeyInput::Backspace)
+                && self.chat.input.value().is_empty()
+                && self.chat.attachments.pop().is_some()
+            {
+                return;
+            }
+
+            if event == xpui::UiInputEvent::Key(xpui::UiKeyInput::Tab) {
+                let (value, cursor) = (self.chat.input.value(), self.chat.input.cursor());
+                match complete::complete(value, cursor, &self.chat.workspace) {
+                    Some(complete::Outcome::Insert(start, end, text)) => {
+                        self.replace_input(start, end, &text);
+                    }
+                    Some(complete::Outcome::Choose(completion)) => {
+                        self.completion = Some(completion);
+                    }
+                    None => {}
+                }
+                return;
+            }
+
+            // Right or End at the end of the input takes the suggested ending.
+            if matches!(
+                event,
+                xpui::UiInputEvent::Key(xpui::UiKeyInput::Right | xpui::UiKeyInput::End)
+            ) && let Some(rest) = self.chat.prompt_suggestion()
+            {
+                let rest = rest.to_string();
+                self.chat.input.insert_str(&rest);
+                self.clamp_input_scroll_offset();
+                return;
+            }
+
+            let key = match event {
+                xpui::UiInputEvent::Key(key) => Some(key),
+                _ => None,
+            };
+            if self.chat.input.handle_input(event.clone()) {
+                self.chat.update_table_filter();
+                if matches!(
+                    key,
+                    Some(
+                        xpui::UiKeyInput::Up
+                            | xpui::UiKeyInput::Down
+                            | xpui::UiKeyInput::Enter
+                            | xpui::UiKeyInput::Home
+                            | xpui::UiKeyInput::End
+                            | xpui::UiKeyInput::Undo
+                            | xpui::UiKeyInput::Redo
+                    )
+                ) {
+                    let (_, cursor_line) =
+                        self.input_visual_metrics((self.window_size.width as usize).max(8));
+                    let viewport = self.input_viewport_lines();
+                    let min_offset = cursor_line.saturating_add(1).saturating_sub(viewport);
+                    let max_offset = cursor_line;
+                    self.input_scroll_offset =
+                        self.input_scroll_offset.clamp(min_offset, max_offset);
+                }
+                self.clamp_input_scroll_offset();
+                return;
+            }
+        }
+        if event == xpui::UiInputEvent::Key(xpui::UiKeyInput::Char('\'')) {
+            self.nav.focus.save_focus();
+            self.bookmarks = Some(bookmarks::BookmarkPicker::open(&self.chat.history.borrow()));
+            return;
+        }
+        if let xpui::UiInputEvent::Key(xpui::UiKeyInput::Char(ch)) = event
+            && let Some(index) = self.nav.history.focused_index(&self.nav.focus)
+        {
+            let index = usize::from(index);
+            let action = self
+                .chat
+                .history
+                .borrow()
+                .get(index)
+                .and_then(|message| MessageAction::for_key(ch, message));
+            if let Some(action) = action {
+                self.run_action(index, action);
+                return;
+            }
+        }
+        if event == xpui::UiInputEvent::Key(xpui::UiKeyInput::Esc) {
+            self.chat.cancel_note();
+            self.chat.cancel_table_filter();
+            self.chat.cancel_draft();
+        }
+        if let xpui::SelectEvent::Activated(index) =
+            self.nav.history.handle_input(&mut self.nav.focus, event)
+        {
+            self.open_menu(usize::from(index));
+        }
+    }
+
+    fn focus_state(&mut self) -> Option<&mut xpui::FocusState> {
+        Some(&mut self.nav.focus)
+    }
+
+    fn on_focus_entries(&mut self, entries: &[xpui::FocusEntry]) {
+        self.nav.entries = entries.to_vec();
+        self.nav.history.on_focus_entries(&mut self.nav.focus, entries);
+        // Trap focus in a menu opened since the last frame.
+        if self.menu.is_some()
+            && self.nav.focus.trap().is_none()
+            && let Some(entry) = entries
+                .iter()
+                .find(|entry| entry.id == xpui::FocusId(Self::MENU_ID))
+        {
+            self.nav.focus.push_trap(entry.path.clone());
+            self.nav.focus.set_focused_entry(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_scrollbar_thumb_is_held_where_it_shows_under_a_banner() {
+        // 100 lines in a 20-line viewport: a 4-row thumb with 16 rows to go.
+        let list = xpui::FocusListState::new(vec![10; 10], 20, 0);
+        let banner = 2;
+        let drag = HistoryDrag::thumb(&list, banner, banner).unwrap();
+        assert!(matches!(drag, HistoryDrag::Thumb { grab: 0 }));
+        assert_eq!(drag.offset(&list, banner, banner), 0);
+        assert_eq!(drag.offset(&list, banner, banner + 16), 80);
+        assert!(HistoryDrag::thumb(&list, banner, 1).is_none());
+    }
+}
@@ -0,0 +1,132 @@
+/// Pasted text that looks like code, which Tab wraps in a fenced block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PasteHint {
+    /// Where the paste sits in the input, in chars.
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    /// Empty when the language is anyone's guess.
+    pub(crate) language: &'static str,
+}
+
+impl PasteHint {
+    pub(crate) fn label(&self) -> String {
+        let what = if self.language.is_empty() {
+            "code".to_string()
+        } else {
+            format!("{} code", self.language)
+        };
+        format!("Pasted {what}? Tab wraps it in a code block, any other key keeps it as is")
+    }
+
+    pub(crate) fn fence(&self, text: &str) -> String {
+        format!("```{}\n{}\n```", self.language, text.trim_end_matches('\n'))
+    }
+}
+
+/// The language of `text` when it reads like code rather than prose: a
+/// shebang, or most lines indented or ending in braces, brackets or `;`.
+/// `Some("")` is code in no language it can tell.
+pub(crate) fn code_language(text: &str) -> Option<&'static str> {
+    if text.contains("```") {
+        return None;
+    }
+    if let Some(shebang) = text.lines().next().and_then(|line| line.strip_prefix("#!")) {
+        return Some(shebang_language(shebang));
+    }
+    let lines = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    if lines.len() < 2 {
+        return None;
+    }
+    let code_lines = lines
+        .iter()
+        .filter(|line| {
+            line.starts_with([' ', '\t'])
+                || line
+                    .trim_end()
+                    .ends_with(['{', '}', '(', ')', '[', ']', ';', ','])
+        })
+        .count();
+    (code_lines * 2 >= lines.len()).then(|| keyword_language(text))
+}
+
+fn shebang_language(shebang: &str) -> &'static str {
+    let mut words = shebang
+        .split_whitespace()
+        .map(|word| word.rsplit('/').next().unwrap_or(word));
+    let interpreter = match words.next() {
+        Some("env") => words.next(),
+        interpreter => interpreter,
+    };
+    match interpreter.unwrap_or_default() {
+        "sh" | "bash" | "zsh" => "sh",
+        name if name.starts_with("python") => "python",
+        "node" => "javascript",
+        "ruby" => "ruby",
+        "perl" => "perl",
+        _ => "",
+    }
+}
+
+/// A language named by words only it uses, checked most telling first.
+fn keyword_language(text: &str) -> &'static str {
+    const SIGNS: [(&str, &[&str]); 9] = [
+        (
+            "rust",
+            &["fn ", "let mut ", "impl ", "pub fn ", "use std::"],
+        ),
+        ("go", &["func ", "package ", ":= "]),
+        ("python", &["def ", "elif ", "self.", "import "]),
+        ("typescript", &["interface ", ": string", ": number"]),
+        ("javascript", &["function ", "const ", "=> ", "console."]),
+        ("java", &["public class ", "private ", "System.out"]),
+        ("c", &["#include ", "int main("]),
+        ("sql", &["SELECT ", "INSERT INTO ", "CREATE TABLE "]),
+        ("json", &["\": "]),
+    ];
+    SIGNS
+        .iter()
+        .map(|(language, words)| {
+            let hits = words.iter().filter(|word| text.contains(*word)).count();
+            (hits, *language)
+        })
+        .filter(|(hits, _)| *hits > 0)
+        // The first of equally likely languages wins.
+        .fold(
+            (0, ""),
+            |best, next| if next.0 > best.0 { next } else { best },
+        )
+        .1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_is_told_from_prose() {
+        assert_eq!(
+            code_language("fn main() {\n    let mut x = 1;\n}\n"),
+            Some("rust")
+        );
+        assert_eq!(
+            code_language("#!/usr/bin/env python3\nprint(1)"),
+            Some("python")
+        );
+        assert_eq!(code_language("{\n  \"a\": 1\n}"), Some("json"));
+        assert_eq!(
+            code_language("Thanks for the fix.\nIt works on my machine now."),
+            None
+        );
+        assert_eq!(code_language("let x = 1;"), None);
+
+        let hint = PasteHint {
+            start: 0,
+            end: 0,
+            language: "rust",
+        };
+        assert_eq!(hint.fence("x;\n"), "```rust\nx;\n```");
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::{MAIN_SEPARATOR, Path, PathBuf};
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// `path` as the UI shows it: relative to `root` when inside it, otherwise
+/// with the home directory as `~`.
+pub(crate) fn shorten(path: &Path, root: Option<&Path>) -> String {
+    shorten_with(path, root, dirs::home_dir().as_deref())
+}
+
+/// [`shorten`]ed `path` cut to `width` columns by dropping middle
+/// directories for `…`, keeping the first and the file name as long as
+/// they fit.
+pub(crate) fn display(path: &Path, root: Option<&Path>, width: usize) -> String {
+    fit(&shorten(path, root), width)
+}
+
+/// `path` as typed, with a leading `~/` as the home directory.
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn shorten_with(path: &Path, root: Option<&Path>, home: Option<&Path>) -> String {
+    if let Some(relative) = root.and_then(|root| path.strip_prefix(root).ok()) {
+        return if relative.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            relative.display().to_string()
+        };
+    }
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(relative) if relative.as_os_str().is_empty() => "~".to_string(),
+        Some(relative) => format!("~{MAIN_SEPARATOR}{}", relative.display()),
+        None => path.display().to_string(),
+    }
+}
+
+fn fit(path: &str, width: usize) -> String {
+    if path.width() <= width {
+        return path.to_string();
+    }
+    let parts = path.split(MAIN_SEPARATOR).collect::<Vec<_>>();
+    let ellipsis = "…";
+    // The first part, `…`, then as many of the last parts as fit.
+    if parts.len() > 2 {
+        let mut tail = parts[parts.len() - 1].to_string();
+        let joined = |tail: &str| {
+            format!(
+                "{}{MAIN_SEPARATOR}{ellipsis}{MAIN_SEPARATOR}{tail}",
+                parts[0]
+            )
+        };
+        if joined(&tail).width() <= width {
+            for part in parts[1..parts.len() - 1].iter().rev() {
+                let longer = format!("{part}{MAIN_SEPARATOR}{tail}");
+                if joined(&longer).width() > width {
+                    break;
+                }
+                tail = longer;
+            }
+            return joined(&tail);
+        }
+    }
+    // Too narrow for whole parts: the end of the path after `…`.
+    let mut tail = Vec::new();
+    let mut used = ellipsis.width();
+    for ch in path.chars().rev() {
+        let w = UnicodeWidthChar::width(ch).unwrap_or(0);
+        if used + w > width {
+            break;
+        }
+        used += w;
+        tail.push(ch);
+    }
+    if used > width {
+        return String::new();
+    }
+    tail.push('…');
+    tail.into_iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_shorten_to_the_workspace_or_home() {
+        let home = Path::new("/home/me");
+        let root = Path::new("/home/me/src/app");
+        let shorten = |path: &str| shorten_with(Path::new(path), Some(root), Some(home));
+        assert_eq!(
+            shorten("/home/me/src/app/crates/ui/lib.rs"),
+            "crates/ui/lib.rs"
+        );
+        assert_eq!(shorten("/home/me/src/app"), ".");
+        assert_eq!(shorten("/home/me/notes.md"), "~/notes.md");
+        assert_eq!(shorten("/etc/hosts"), "/etc/hosts");
+
+        let long = "~/src/work/loopcode/crates/loopcode-ui/src/panel.rs";
+        assert_eq!(fit(long, 80), long);
+        assert_eq!(fit(long, 30), "~/…/loopcode-ui/src/panel.rs");
+        assert_eq!(fit(long, 12), "~/…/panel.rs");
+        assert_eq!(fit(long, 9), "…panel.rs");
+        assert_eq!(fit(long, 0), "");
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use crate::git;
+use crate::provider::{CompletionRequest, RequestMessage, RequestRole};
+
+/// What the current branch adds on top of the default branch.
+pub(crate) struct BranchChanges {
+    base: String,
+    log: String,
+    diff: String,
+}
+
+impl BranchChanges {
+    /// Compares HEAD with where it forked from the remote default branch, or
+    /// from a local `main` or `master` when there is no remote.
+    pub(crate) fn read(root: &Path) -> Result<Self, String> {
+        let base = default_branch(root).ok_or("cannot find the default branch to compare with")?;
+        let fork_point = git::run("git", root, &["merge-base", "HEAD", &base], None)
+            .map_err(|err| format!("cannot find where this branch forked from {base}: {err}"))?;
+        let range = format!("{}..HEAD", fork_point.trim());
+        let log = git::run("git", root, &["log", "--format=%s%n%n%b", &range], None)
+            .map_err(|err| format!("cannot list the commits on this branch: {err}"))?;
+        if log.trim().is_empty() {
+            return Err(format!("no commits on this branch since {base}"));
+        }
+        let args = ["diff", "--no-color", "--no-ext-diff", &range];
+        let diff = git::run("git", root, &args, None)
+            .map_err(|err| format!("cannot read the branch diff: {err}"))?;
+        Ok(Self { base, log, diff })
+    }
+
+    /// Asks for a title and body, as a one-off request outside the
+    /// conversation.
+    pub(crate) fn request(&self, model: String) -> CompletionRequest {
+        let prompt = format!(
+            "Write a pull request description for the changes below, which go \
+             into {}. The first line is the title, under 72 characters; after a \
+             blank line comes a Markdown body saying what changed, why, and how \
+             it was tested. Reply with the title and body only.\n\n\
+             Commits:\n{}\n\nDiff:\n```diff\n{}\n```",
+            self.base,
+            self.log.trim(),
+            git::clip_diff(&self.diff)
+        );
+        CompletionRequest {
+            model,
+            messages: vec![RequestMessage {
+                role: RequestRole::User,
+                content: prompt,
+                images: Vec::new(),
+                tool_call: None,
+            }],
+            tools: Vec::new(),
+        }
+    }
+}
+
+/// Opens the pull request with `gh`, taking the first line of `text` as the
+/// title and the rest as the body. Returns the pull request URL.
+pub(crate) fn create(root: &Path, text: &str) -> Result<String, String> {
+    let (title, body) = text.split_once('\n').unwrap_or((text, ""));
+    let args = ["pr", "create", "--title", title.trim(), "--body-file", "-"];
+    let out = git::run("gh", root, &args, Some(body.trim()))?;
+    Ok(out.lines().last().unwrap_or_default().to_string())
+}
+
+fn default_branch(root: &Path) -> Option<String> {
+    let remote_head = ["rev-parse", "--abbrev-ref", "origin/HEAD"];
+    if let Ok(name) = git::run("git", root, &remote_head, None) {
+        return Some(name.trim().to_string());
+    }
+    ["main", "master"]
+        .into_iter()
+        .map(str::to_string)
+        .find(|name| {
+            let args = ["rev-parse", "--verify", "--quiet", name.as_str()];
+            git::run("git", root, &args, None).is_ok()
+        })
+}
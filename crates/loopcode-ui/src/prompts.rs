@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Prompts kept; the least used of the oldest go first.
+const CAPACITY: usize = 500;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    text: String,
+    uses: u32,
+    /// Seconds since the epoch.
+    last_used: u64,
+}
+
+/// Prompts sent before, each once however often it was sent, for suggesting
+/// the rest of one while its start is typed. Shared between sessions through
+/// `prompts.json` in the data directory unless it is kept to this run.
+pub(crate) struct PromptHistory {
+    entries: Vec<Entry>,
+    path: Option<PathBuf>,
+}
+
+impl PromptHistory {
+    /// The prompts of earlier sessions when `shared`, or none, kept in memory.
+    pub(crate) fn open(shared: bool) -> Self {
+        let path = shared
+            .then(|| Some(dirs::data_dir()?.join("loopcode").join("prompts.json")))
+            .flatten();
+        let entries = path
+            .as_ref()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default();
+        Self { entries, path }
+    }
+
+    pub(crate) fn record(&mut self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        match self.entries.iter_mut().find(|entry| entry.text == text) {
+            Some(entry) => {
+                entry.uses += 1;
+                entry.last_used = now;
+            }
+            None => self.entries.push(Entry {
+                text: text.to_string(),
+                uses: 1,
+                last_used: now,
+            }),
+        }
+        if self.entries.len() > CAPACITY {
+            self.entries
+                .sort_by_key(|entry| (entry.uses, entry.last_used));
+            self.entries.drain(..self.entries.len() - CAPACITY);
+        }
+        self.save();
+    }
+
+    /// The rest of the most used prompt starting with `prefix`, the latest
+    /// of equally used ones. Only the line being typed is completed.
+    pub(crate) fn suggest(&self, prefix: &str) -> Option<&str> {
+        if prefix.trim().is_empty() {
+            return None;
+        }
+        let best = self
+            .entries
+            .iter()
+            .filter(|entry| entry.text.len() > prefix.len() && entry.text.starts_with(prefix))
+            .max_by_key(|entry| (entry.uses, entry.last_used))?;
+        let rest = &best.text[prefix.len()..];
+        let line = rest.split('\n').next().unwrap_or(rest);
+        (!line.is_empty()).then_some(line)
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let Ok(json) = serde_json::to_vec(&self.entries) else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, json);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_most_used_prompt_is_suggested_once() {
+        let mut history = PromptHistory::open(false);
+        history.record("fix the failing test");
+        history.record("fix the build\nthen run it");
+        history.record("fix the build\nthen run it");
+        history.record("  fix the failing test ");
+        assert_eq!(history.entries.len(), 2);
+        history.record("fix the build\nthen run it");
+        assert_eq!(history.suggest("fix the "), Some("build"));
+        assert_eq!(history.suggest("fix the f"), Some("ailing test"));
+        assert_eq!(history.suggest("fix the failing test"), None);
+        assert_eq!(history.suggest(""), None);
+    }
+}
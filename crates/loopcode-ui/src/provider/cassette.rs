@@ -0,0 +1,287 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{
+    ChatProvider, CompletionRequest, CompletionStream, ProviderError, ProviderErrorKind,
+    StreamEvent,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RecordedEvent {
+    Delta {
+        text: String,
+    },
+    ToolCall {
+        /// Missing from cassettes recorded before ids were kept.
+        #[serde(default)]
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    Done,
+    Failed {
+        kind: ProviderErrorKind,
+        detail: String,
+    },
+}
+
+impl RecordedEvent {
+    fn from_stream(event: &StreamEvent) -> Self {
+        match event {
+            StreamEvent::Delta(text) => Self::Delta { text: text.clone() },
+            StreamEvent::ToolCall {
+                id,
+                name,
+                arguments,
+            } => Self::ToolCall {
+                id: id.clone(),
+                name: name.clone(),
+                arguments: arguments.clone(),
+            },
+            StreamEvent::Done => Self::Done,
+            StreamEvent::Failed(err) => Self::Failed {
+                kind: err.kind(),
+                detail: err.detail().to_string(),
+            },
+        }
+    }
+
+    fn into_stream(self) -> StreamEvent {
+        match self {
+            Self::Delta { text } => StreamEvent::Delta(text),
+            Self::ToolCall {
+                id,
+                name,
+                arguments,
+            } => StreamEvent::ToolCall {
+                id,
+                name,
+                arguments,
+            },
+            Self::Done => StreamEvent::Done,
+            Self::Failed { kind, detail } => StreamEvent::Failed(ProviderError::new(kind, detail)),
+        }
+    }
+}
+
+/// One recorded exchange. `model` is informational; lookups use `request`.
+#[derive(Debug, Serialize, Deserialize)]
+struct CassetteEntry {
+    request: String,
+    model: String,
+    events: Vec<RecordedEvent>,
+}
+
+/// Stable key for a request: the SHA-256 of its JSON form, so the same
+/// conversation replays the same response across runs and machines.
+fn request_key(request: &CompletionRequest) -> String {
+    let json = serde_json::to_vec(request).unwrap_or_default();
+    Sha256::digest(&json)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Decorator that appends every finished exchange to a cassette file.
+/// Cancelled streams are not recorded since they have no defined ending.
+pub struct RecordingProvider {
+    inner: Box<dyn ChatProvider>,
+    writer: Arc<Mutex<LineWriter<File>>>,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Box<dyn ChatProvider>, path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            inner,
+            writer: Arc::new(Mutex::new(LineWriter::new(file))),
+        })
+    }
+}
+
+impl ChatProvider for RecordingProvider {
+    fn stream_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        let key = request_key(&request);
+        let model = request.model.clone();
+        let inner = self.inner.stream_completion(request)?;
+
+        let (tx, rx) = mpsc::channel();
+        let writer = Arc::clone(&self.writer);
+        thread::spawn(move || {
+            let mut events = Vec::new();
+            loop {
+                let event = inner.recv();
+                events.push(RecordedEvent::from_stream(&event));
+                if matches!(event, StreamEvent::Delta(_) | StreamEvent::ToolCall { .. }) {
+                    if tx.send(event).is_err() {
+                        return;
+                    }
+                    continue;
+                }
+
+                let entry = CassetteEntry {
+                    request: key,
+                    model,
+                    events,
+                };
+                let event = match write_entry(&writer, &entry) {
+                    Ok(()) => event,
+                    Err(err) => StreamEvent::Failed(ProviderError::new(
+                        ProviderErrorKind::Recording,
+                        err.to_string(),
+                    )),
+                };
+                let _ = tx.send(event);
+                return;
+            }
+        });
+
+        Ok(CompletionStream::new(rx))
+    }
+}
+
+fn write_entry(writer: &Mutex<LineWriter<File>>, entry: &CassetteEntry) -> io::Result<()> {
+    let mut line = serde_json::to_vec(entry).map_err(io::Error::other)?;
+    line.push(b'\n');
+    writer
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .write_all(&line)
+}
+
+/// Serves responses from a cassette instead of a live provider. Requests
+/// with the same key are answered in recorded order; the last answer is
+/// reused once they run out.
+pub struct ReplayProvider {
+    entries: RefCell<HashMap<String, VecDeque<Vec<RecordedEvent>>>>,
+    chunk_delay: Duration,
+}
+
+impl ReplayProvider {
+    pub fn load(path: &Path, chunk_delay: Duration) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut entries: HashMap<String, VecDeque<Vec<RecordedEvent>>> = HashMap::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: CassetteEntry = serde_json::from_str(&line).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {err}", number + 1),
+                )
+            })?;
+            entries
+                .entry(entry.request)
+                .or_default()
+                .push_back(entry.events);
+        }
+        Ok(Self {
+            entries: RefCell::new(entries),
+            chunk_delay,
+        })
+    }
+}
+
+impl ChatProvider for ReplayProvider {
+    fn stream_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        let key = request_key(&request);
+        let events = {
+            let mut entries = self.entries.borrow_mut();
+            let queue = entries.get_mut(&key).ok_or_else(|| {
+                ProviderError::new(
+                    ProviderErrorKind::NotRecorded,
+                    format!("request {} is not in the cassette", &key[..12]),
+                )
+            })?;
+            if queue.len() > 1 {
+                queue.pop_front().unwrap_or_default()
+            } else {
+                queue.front().cloned().unwrap_or_default()
+            }
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let delay = self.chunk_delay;
+        thread::spawn(move || {
+            for event in events {
+                thread::sleep(delay);
+                if tx.send(event.into_stream()).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(CompletionStream::new(rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{MockOptions, MockProvider, RequestMessage, RequestRole};
+
+    fn drain(stream: CompletionStream) -> Vec<RecordedEvent> {
+        let mut events = Vec::new();
+        loop {
+            let event = RecordedEvent::from_stream(&stream.recv());
+            let last = matches!(event, RecordedEvent::Done | RecordedEvent::Failed { .. });
+            events.push(event);
+            if last {
+                return events;
+            }
+        }
+    }
+
+    #[test]
+    fn replay_returns_recorded_events_for_the_same_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.jsonl");
+        let request = CompletionRequest {
+            model: "mock".to_string(),
+            messages: vec![RequestMessage {
+                role: RequestRole::User,
+                content: "hello".to_string(),
+                images: Vec::new(),
+                tool_call: None,
+            }],
+            tools: Vec::new(),
+        };
+
+        let mock = MockProvider::new(MockOptions {
+            chunk_delay: Duration::ZERO,
+            fail_every: None,
+        });
+        let recorder = RecordingProvider::new(Box::new(mock), &path).unwrap();
+        let recorded = drain(recorder.stream_completion(request.clone()).unwrap());
+
+        let replay = ReplayProvider::load(&path, Duration::ZERO).unwrap();
+        let replayed = drain(replay.stream_completion(request).unwrap());
+
+        assert_eq!(
+            serde_json::to_string(&recorded).unwrap(),
+            serde_json::to_string(&replayed).unwrap()
+        );
+    }
+}
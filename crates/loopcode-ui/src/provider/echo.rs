@@ -0,0 +1,62 @@
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use super::{
+    ChatProvider, CompletionRequest, CompletionStream, ProviderError, ProviderErrorKind,
+    RequestRole, StreamEvent,
+};
+
+/// Offline stand-in that streams the last user message back word by word.
+pub struct EchoProvider {
+    chunk_delay: Duration,
+}
+
+impl EchoProvider {
+    pub fn new() -> Self {
+        Self {
+            chunk_delay: Duration::from_millis(80),
+        }
+    }
+}
+
+impl Default for EchoProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChatProvider for EchoProvider {
+    fn stream_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        let prompt = request
+            .messages
+            .iter()
+            .rev()
+            .find(|message| message.role == RequestRole::User)
+            .map(|message| message.content.clone())
+            .ok_or_else(|| {
+                ProviderError::new(
+                    ProviderErrorKind::InvalidRequest,
+                    "request has no user message",
+                )
+            })?;
+
+        let (tx, rx) = mpsc::channel();
+        let delay = self.chunk_delay;
+        let reply = format!("({}) {prompt}", request.model);
+        thread::spawn(move || {
+            for word in reply.split_inclusive(' ') {
+                thread::sleep(delay);
+                if tx.send(StreamEvent::Delta(word.to_string())).is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(StreamEvent::Done);
+        });
+
+        Ok(CompletionStream::new(rx))
+    }
+}
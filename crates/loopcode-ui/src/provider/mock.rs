@@ -0,0 +1,147 @@
+use std::cell::Cell;
+use std::num::NonZeroUsize;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use super::{
+    ChatProvider, CompletionRequest, CompletionStream, ProviderError, ProviderErrorKind,
+    RequestRole, StreamEvent,
+};
+
+enum MockStep {
+    Text(&'static str),
+    ToolCall {
+        id: &'static str,
+        name: &'static str,
+        arguments: &'static str,
+    },
+}
+
+/// The answer to a request that ends in a tool result.
+const AFTER_TOOL: &[MockStep] = &[MockStep::Text(
+    "The workspace has a root binary and the xpui and cpui crates.",
+)];
+
+/// Canned turns, played in order and then repeated.
+const SCRIPT: &[&[MockStep]] = &[
+    &[MockStep::Text(
+        "This is the mock provider. Responses are canned, so the UI can be \
+         exercised without network access or API keys.",
+    )],
+    &[
+        MockStep::Text("Let me look at the project layout first."),
+        MockStep::ToolCall {
+            id: "call_ls",
+            name: "shell",
+            arguments: r#"{"command":"ls"}"#,
+        },
+    ],
+    &[
+        MockStep::Text("Here is a small change:\n"),
+        MockStep::Text("```rust\nfn main() {\n    println!(\"hello\");\n}\n```\n"),
+        MockStep::Text("Run it with `cargo run`."),
+    ],
+];
+
+#[derive(Clone, Copy, Debug)]
+pub struct MockOptions {
+    pub chunk_delay: Duration,
+    /// Every Nth request drops halfway through its stream.
+    pub fail_every: Option<NonZeroUsize>,
+}
+
+/// Offline provider that replays a fixed script, including tool calls, with
+/// configurable latency and injected failures.
+pub struct MockProvider {
+    options: MockOptions,
+    requests: Cell<usize>,
+}
+
+impl MockProvider {
+    pub fn new(options: MockOptions) -> Self {
+        Self {
+            options,
+            requests: Cell::new(0),
+        }
+    }
+}
+
+impl ChatProvider for MockProvider {
+    fn stream_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        if !request
+            .messages
+            .iter()
+            .any(|message| message.role == RequestRole::User)
+        {
+            return Err(ProviderError::new(
+                ProviderErrorKind::InvalidRequest,
+                "request has no user message",
+            ));
+        }
+
+        // Answering a tool result goes on with the same turn rather than
+        // starting the next one.
+        let after_tool = request
+            .messages
+            .last()
+            .is_some_and(|message| message.role == RequestRole::Tool);
+        let count = self.requests.get() + usize::from(!after_tool);
+        self.requests.set(count);
+        let events = if after_tool {
+            script_events(AFTER_TOOL)
+        } else {
+            script_events(SCRIPT[(count - 1) % SCRIPT.len()])
+        };
+        let fail_at = self
+            .options
+            .fail_every
+            .filter(|every| count.is_multiple_of(every.get()))
+            .map(|_| events.len() / 2);
+
+        let (tx, rx) = mpsc::channel();
+        let delay = self.options.chunk_delay;
+        thread::spawn(move || {
+            for (position, event) in events.into_iter().enumerate() {
+                thread::sleep(delay);
+                if fail_at == Some(position) {
+                    let _ = tx.send(StreamEvent::Failed(ProviderError::new(
+                        ProviderErrorKind::Disconnected,
+                        "mock provider dropped the stream",
+                    )));
+                    return;
+                }
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(StreamEvent::Done);
+        });
+
+        Ok(CompletionStream::new(rx))
+    }
+}
+
+fn script_events(steps: &[MockStep]) -> Vec<StreamEvent> {
+    steps
+        .iter()
+        .flat_map(|step| match step {
+            MockStep::Text(text) => text
+                .split_inclusive(' ')
+                .map(|word| StreamEvent::Delta(word.to_string()))
+                .collect::<Vec<_>>(),
+            MockStep::ToolCall {
+                id,
+                name,
+                arguments,
+            } => vec![StreamEvent::ToolCall {
+                id: id.to_string(),
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            }],
+        })
+        .collect()
+}
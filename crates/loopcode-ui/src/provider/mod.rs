@@ -0,0 +1,195 @@
+mod cassette;
+mod echo;
+mod mock;
+mod openai;
+mod wire_log;
+
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+pub use cassette::{RecordingProvider, ReplayProvider};
+pub use echo::EchoProvider;
+pub use mock::{MockOptions, MockProvider};
+pub use openai::OpenAiProvider;
+pub(crate) use wire_log::{WireExchange, WireOutcome};
+pub use wire_log::{WireLog, WireLoggingProvider};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestRole {
+    User,
+    Assistant,
+    /// What a tool call the model asked for gave back.
+    Tool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RequestMessage {
+    pub role: RequestRole,
+    pub content: String,
+    /// Images sent along with a user message, for models that take them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<RequestImage>,
+    /// The call a tool message answers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call: Option<RequestToolCall>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RequestToolCall {
+    /// The id the API gave the call, which its result answers to. Empty for
+    /// calls recorded before ids were kept.
+    #[serde(skip)]
+    pub id: String,
+    pub name: String,
+    /// The raw JSON arguments, as the model sent them.
+    pub arguments: String,
+}
+
+/// A tool the model may call, described for it.
+#[derive(Clone, Debug, Serialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema of the arguments.
+    pub parameters: serde_json::Value,
+}
+
+#[derive(Clone, Debug)]
+pub struct RequestImage {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Serialized by size and hash rather than content, which keeps wire logs
+/// readable and cassette keys stable.
+impl Serialize for RequestImage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let sha256 = Sha256::digest(&self.data)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        let mut image = serializer.serialize_struct("RequestImage", 3)?;
+        image.serialize_field("mime_type", &self.mime_type)?;
+        image.serialize_field("bytes", &self.data.len())?;
+        image.serialize_field("sha256", &sha256)?;
+        image.end()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub messages: Vec<RequestMessage>,
+    /// Tools the model may ask to have run; none for a plain completion.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolSpec>,
+}
+
+#[derive(Debug)]
+pub enum StreamEvent {
+    Delta(String),
+    /// The model asks for a tool to run. `arguments` is the raw JSON payload,
+    /// and `id` what the API calls the call.
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    Done,
+    Failed(ProviderError),
+}
+
+/// Handle to an in-flight completion. Dropping it cancels the request: the
+/// producer observes the closed channel on its next send and stops.
+pub struct CompletionStream {
+    rx: Receiver<StreamEvent>,
+}
+
+impl CompletionStream {
+    pub fn new(rx: Receiver<StreamEvent>) -> Self {
+        Self { rx }
+    }
+
+    pub fn try_next(&self) -> Option<StreamEvent> {
+        match self.rx.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Self::closed()),
+        }
+    }
+
+    /// Blocks until the next event. Only for adapters that run on their own
+    /// thread; the UI polls with [`Self::try_next`].
+    pub fn recv(&self) -> StreamEvent {
+        self.rx.recv().unwrap_or_else(|_| Self::closed())
+    }
+
+    fn closed() -> StreamEvent {
+        StreamEvent::Failed(ProviderError::new(
+            ProviderErrorKind::Disconnected,
+            "stream closed before completion",
+        ))
+    }
+}
+
+pub trait ChatProvider {
+    fn stream_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderErrorKind {
+    InvalidRequest,
+    Disconnected,
+    /// Replay mode got a request the cassette has no answer for.
+    NotRecorded,
+    /// The response arrived but could not be written to the cassette.
+    Recording,
+}
+
+impl ProviderErrorKind {
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::Disconnected)
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::InvalidRequest => "invalid request",
+            Self::Disconnected => "provider disconnected",
+            Self::NotRecorded => "no recorded response",
+            Self::Recording => "recording failed",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("{}: {}", .kind.label(), .detail)]
+pub struct ProviderError {
+    kind: ProviderErrorKind,
+    detail: String,
+}
+
+impl ProviderError {
+    pub fn new(kind: ProviderErrorKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn kind(&self) -> ProviderErrorKind {
+        self.kind
+    }
+
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+}
@@ -0,0 +1,540 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ChatProvider, CompletionRequest, CompletionStream, ProviderError, ProviderErrorKind,
+    RequestMessage, RequestRole, RequestToolCall, StreamEvent, ToolSpec,
+};
+use crate::models;
+
+/// Streams completions from an OpenAI-style chat completions endpoint,
+/// OpenAI's own or OpenRouter's, through `curl`, which brings TLS and the
+/// proxy settings along.
+pub struct OpenAiProvider {
+    endpoint: &'static str,
+    api_key: String,
+    /// OpenAI serves its own models and takes their names without the
+    /// `openai/` OpenRouter puts before them.
+    openai: bool,
+}
+
+impl OpenAiProvider {
+    pub fn openai(api_key: impl Into<String>) -> Self {
+        Self {
+            endpoint: "https://api.openai.com/v1/chat/completions",
+            api_key: api_key.into(),
+            openai: true,
+        }
+    }
+
+    pub fn openrouter(api_key: impl Into<String>) -> Self {
+        Self {
+            endpoint: "https://openrouter.ai/api/v1/chat/completions",
+            api_key: api_key.into(),
+            openai: false,
+        }
+    }
+
+    /// The name the endpoint knows the picked model by.
+    fn model(&self, label: &str) -> Result<String, ProviderError> {
+        let name = models::wire_name(label);
+        if !self.openai {
+            return Ok(name.to_string());
+        }
+        match name.split_once('/') {
+            None => Ok(name.to_string()),
+            Some(("openai", name)) => Ok(name.to_string()),
+            Some(_) => Err(ProviderError::new(
+                ProviderErrorKind::InvalidRequest,
+                format!("OpenAI does not serve {name}; use --provider openrouter"),
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Body {
+    model: String,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Function<ToolSpec>>,
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct Message {
+    role: &'static str,
+    /// `None` for the assistant message that only calls a tool.
+    content: Option<Content>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<WireToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+/// A tool, or a call to one, as the API nests them.
+#[derive(Serialize)]
+struct Function<T> {
+    r#type: &'static str,
+    function: T,
+}
+
+#[derive(Serialize)]
+struct WireToolCall {
+    id: String,
+    #[serde(flatten)]
+    call: Function<RequestToolCall>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Content {
+    Text(String),
+    Parts(Vec<Part>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Part {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Serialize)]
+struct ImageUrl {
+    url: String,
+}
+
+/// The API's messages for `messages`. Each tool result becomes the
+/// assistant's call, then the result answering it by the call's id.
+fn messages(messages: Vec<RequestMessage>) -> Vec<Message> {
+    let mut out = Vec::new();
+    for (index, message) in messages.into_iter().enumerate() {
+        let Some(call) = message.tool_call else {
+            out.push(Message::from(message));
+            continue;
+        };
+        let id = if call.id.is_empty() {
+            format!("call_{index}")
+        } else {
+            call.id.clone()
+        };
+        out.push(Message {
+            role: "assistant",
+            content: None,
+            tool_calls: vec![WireToolCall {
+                id: id.clone(),
+                call: Function {
+                    r#type: "function",
+                    function: call,
+                },
+            }],
+            tool_call_id: None,
+        });
+        out.push(Message {
+            role: "tool",
+            content: Some(Content::Text(message.content)),
+            tool_calls: Vec::new(),
+            tool_call_id: Some(id),
+        });
+    }
+    out
+}
+
+impl From<RequestMessage> for Message {
+    fn from(message: RequestMessage) -> Self {
+        let role = match message.role {
+            RequestRole::User => "user",
+            RequestRole::Assistant => "assistant",
+            RequestRole::Tool => "tool",
+        };
+        let content = if message.images.is_empty() {
+            Content::Text(message.content)
+        } else {
+            let images = message.images.into_iter().map(|image| Part::ImageUrl {
+                image_url: ImageUrl {
+                    url: format!(
+                        "data:{};base64,{}",
+                        image.mime_type,
+                        base64::engine::general_purpose::STANDARD.encode(&image.data)
+                    ),
+                },
+            });
+            let text = Part::Text {
+                text: message.content,
+            };
+            Content::Parts(std::iter::once(text).chain(images).collect())
+        };
+        Self {
+            role,
+            content: Some(content),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+}
+
+/// One `data:` line of the stream.
+#[derive(Deserialize)]
+struct Chunk {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    error: Option<ApiError>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    #[serde(default)]
+    delta: Delta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct Delta {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+/// A piece of a tool call. The id and name come first and the arguments in
+/// pieces after them, all under the call's index.
+#[derive(Deserialize)]
+struct ToolCallDelta {
+    #[serde(default)]
+    index: usize,
+    id: Option<String>,
+    function: Option<FunctionDelta>,
+}
+
+#[derive(Deserialize)]
+struct FunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+    /// A name like `invalid_api_key` from OpenAI, an HTTP status from
+    /// OpenRouter.
+    #[serde(default)]
+    code: Option<serde_json::Value>,
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+}
+
+impl ApiError {
+    /// Whether sending again could help: not for a bad key, an unknown model
+    /// or a request the API refuses, but for rate limits and server trouble.
+    fn kind(&self) -> ProviderErrorKind {
+        const REFUSED: [&str; 7] = [
+            "invalid_request_error",
+            "authentication_error",
+            "permission_error",
+            "not_found_error",
+            "invalid_api_key",
+            "model_not_found",
+            "insufficient_quota",
+        ];
+        let status = self.code.as_ref().and_then(serde_json::Value::as_u64);
+        let names = [
+            self.kind.as_deref(),
+            self.code.as_ref().and_then(serde_json::Value::as_str),
+        ];
+        let refused = match status {
+            Some(status) => (400..500).contains(&status) && status != 408 && status != 429,
+            None => names
+                .into_iter()
+                .flatten()
+                .any(|name| REFUSED.contains(&name)),
+        };
+        if refused {
+            ProviderErrorKind::InvalidRequest
+        } else {
+            ProviderErrorKind::Disconnected
+        }
+    }
+}
+
+/// Turns the stream's `data:` lines into events, putting tool calls back
+/// together from their pieces.
+#[derive(Default)]
+struct StreamParser {
+    /// The calls so far, by index.
+    calls: Vec<PartialCall>,
+}
+
+/// A tool call as far as the stream has sent it.
+#[derive(Clone, Default)]
+struct PartialCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+impl StreamParser {
+    /// What a `data:` line adds: text, whole tool calls once the response
+    /// says it has finished, or an error.
+    fn line(&mut self, data: &str) -> Vec<StreamEvent> {
+        if data == "[DONE]" {
+            let mut events = self.take_calls();
+            events.push(StreamEvent::Done);
+            return events;
+        }
+        let chunk = match serde_json::from_str::<Chunk>(data) {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                return vec![StreamEvent::Failed(ProviderError::new(
+                    ProviderErrorKind::Disconnected,
+                    format!("cannot read the response stream: {err}\n{data}"),
+                ))];
+            }
+        };
+        if let Some(error) = chunk.error {
+            return vec![StreamEvent::Failed(ProviderError::new(
+                error.kind(),
+                error.message,
+            ))];
+        }
+        let mut events = Vec::new();
+        let mut text = String::new();
+        let mut finished = false;
+        for choice in chunk.choices {
+            text.extend(choice.delta.content);
+            for call in choice.delta.tool_calls {
+                if self.calls.len() <= call.index {
+                    self.calls.resize(call.index + 1, Default::default());
+                }
+                let partial = &mut self.calls[call.index];
+                partial.id.extend(call.id);
+                if let Some(function) = call.function {
+                    partial.name.extend(function.name);
+                    partial.arguments.extend(function.arguments);
+                }
+            }
+            finished |= choice.finish_reason.is_some();
+        }
+        if !text.is_empty() {
+            events.push(StreamEvent::Delta(text));
+        }
+        if finished {
+            events.extend(self.take_calls());
+        }
+        events
+    }
+
+    fn take_calls(&mut self) -> Vec<StreamEvent> {
+        self.calls
+            .drain(..)
+            .filter(|call| !call.name.is_empty())
+            .map(|call| StreamEvent::ToolCall {
+                id: call.id,
+                name: call.name,
+                arguments: call.arguments,
+            })
+            .collect()
+    }
+}
+
+impl ChatProvider for OpenAiProvider {
+    fn stream_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        let body = Body {
+            model: self.model(&request.model)?,
+            messages: messages(request.messages),
+            tools: request
+                .tools
+                .into_iter()
+                .map(|tool| Function {
+                    r#type: "function",
+                    function: tool,
+                })
+                .collect(),
+            stream: true,
+        };
+        let body = serde_json::to_string(&body).map_err(|err| {
+            ProviderError::new(ProviderErrorKind::InvalidRequest, err.to_string())
+        })?;
+        // The key and body go in on stdin, where other users' `ps` cannot
+        // see them.
+        let config = format!(
+            "url = \"{}\"\nheader = \"Authorization: Bearer {}\"\n\
+             header = \"Content-Type: application/json\"\ndata-binary = \"{}\"\n",
+            self.endpoint,
+            quote(&self.api_key),
+            quote(&body)
+        );
+        let mut child = Command::new("curl")
+            .args([
+                "--silent",
+                "--show-error",
+                "--no-buffer",
+                "--fail-with-body",
+            ])
+            .args(["--config", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| {
+                ProviderError::new(
+                    ProviderErrorKind::Disconnected,
+                    format!("cannot run curl: {err}"),
+                )
+            })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(config.as_bytes());
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let stdout = child.stdout.take();
+        thread::spawn(move || {
+            let mut parser = StreamParser::default();
+            // Lines that are not events, such as an error's JSON body.
+            let mut other = String::new();
+            for line in stdout
+                .into_iter()
+                .flat_map(|out| BufReader::new(out).lines())
+            {
+                let Ok(line) = line else { break };
+                let events = match line.strip_prefix("data:") {
+                    Some(data) => parser.line(data.trim()),
+                    // Blank lines end events; `:` lines keep the connection
+                    // alive.
+                    None if line.is_empty() || line.starts_with(':') => Vec::new(),
+                    None => {
+                        other.push_str(&line);
+                        other.push('\n');
+                        Vec::new()
+                    }
+                };
+                for event in events {
+                    let last = matches!(event, StreamEvent::Done | StreamEvent::Failed(_));
+                    if tx.send(event).is_err() || last {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        return;
+                    }
+                }
+            }
+            let mut stderr = String::new();
+            if let Some(mut err) = child.stderr.take() {
+                let _ = err.read_to_string(&mut stderr);
+            }
+            let status = child.wait();
+            // curl exits 22 for an HTTP error status, whose body is in `other`.
+            let kind = match status.as_ref().ok().and_then(|status| status.code()) {
+                Some(22) => ProviderErrorKind::InvalidRequest,
+                _ => ProviderErrorKind::Disconnected,
+            };
+            let mut detail = stderr.trim().trim_start_matches("curl: ").to_string();
+            if detail.is_empty() {
+                detail = "stream ended before the response was done".to_string();
+            }
+            if !other.trim().is_empty() {
+                detail.push('\n');
+                detail.push_str(other.trim());
+            }
+            let _ = tx.send(StreamEvent::Failed(ProviderError::new(kind, detail)));
+        });
+
+        Ok(CompletionStream::new(rx))
+    }
+}
+
+/// `text` escaped for a double-quoted curl config value.
+fn quote(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_results_answer_their_call_by_the_api_id() {
+        let result = |id: &str| RequestMessage {
+            role: RequestRole::Tool,
+            content: "found".to_string(),
+            images: Vec::new(),
+            tool_call: Some(RequestToolCall {
+                id: id.to_string(),
+                name: "grep".to_string(),
+                arguments: "{}".to_string(),
+            }),
+        };
+        let wire = serde_json::to_value(messages(vec![result("call_x7"), result("")])).unwrap();
+        assert_eq!(wire[0]["tool_calls"][0]["id"], "call_x7");
+        assert_eq!(wire[1]["tool_call_id"], "call_x7");
+        // A call recorded without one gets an id of its own.
+        assert_eq!(wire[3]["tool_call_id"], "call_1");
+    }
+
+    #[test]
+    fn stream_lines_become_deltas_tool_calls_and_errors() {
+        let mut parser = StreamParser::default();
+        let delta = parser.line(r#"{"choices":[{"delta":{"content":"Hi"}}]}"#);
+        assert!(matches!(delta.as_slice(), [StreamEvent::Delta(text)] if text == "Hi"));
+        let name = r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_x7","function":{"name":"grep","arguments":"{\"pat"}}]}}]}"#;
+        assert!(parser.line(name).is_empty());
+        let rest = r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"tern\":\"fn\"}"}}]}}]}"#;
+        assert!(parser.line(rest).is_empty());
+        let finish = parser.line(r#"{"choices":[{"delta":{},"finish_reason":"tool_calls"}]}"#);
+        assert!(matches!(
+            finish.as_slice(),
+            [StreamEvent::ToolCall { id, name, arguments }]
+                if id == "call_x7" && name == "grep" && arguments == r#"{"pattern":"fn"}"#
+        ));
+        assert!(matches!(
+            parser.line("[DONE]").as_slice(),
+            [StreamEvent::Done]
+        ));
+        let error = parser.line(r#"{"error":{"message":"Rate limit exceeded"}}"#);
+        assert!(
+            matches!(error.as_slice(), [StreamEvent::Failed(err)] if err.detail() == "Rate limit exceeded")
+        );
+        let kind = |data: &str| match StreamParser::default().line(data).as_slice() {
+            [StreamEvent::Failed(err)] => Some(err.kind()),
+            _ => None,
+        };
+        assert_eq!(
+            kind(
+                r#"{"error":{"message":"Incorrect API key","type":"invalid_request_error","code":"invalid_api_key"}}"#
+            ),
+            Some(ProviderErrorKind::InvalidRequest)
+        );
+        assert_eq!(
+            kind(r#"{"error":{"message":"No such model","code":404}}"#),
+            Some(ProviderErrorKind::InvalidRequest)
+        );
+        assert_eq!(
+            kind(r#"{"error":{"message":"Slow down","code":429}}"#),
+            Some(ProviderErrorKind::Disconnected)
+        );
+        assert_eq!(
+            kind(r#"{"error":{"message":"Overloaded","type":"server_error"}}"#),
+            Some(ProviderErrorKind::Disconnected)
+        );
+
+        let provider = OpenAiProvider::openai("key");
+        assert_eq!(provider.model("OpenRouter GPT-4.1").unwrap(), "gpt-4.1");
+        assert!(provider.model("Anthropic Sonnet").is_err());
+        assert_eq!(
+            OpenAiProvider::openrouter("key")
+                .model("Anthropic Sonnet")
+                .unwrap(),
+            "anthropic/claude-sonnet-4"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+
+use super::{ChatProvider, CompletionRequest, CompletionStream, ProviderError, StreamEvent};
+
+const MAX_BODY_BYTES: usize = 8 * 1024;
+const RECENT_LIMIT: usize = 50;
+const SECRET_KEYS: &[&str] = &[
+    "authorization",
+    "api_key",
+    "apikey",
+    "x-api-key",
+    "token",
+    "access_token",
+    "secret",
+    "password",
+];
+const SECRET_PREFIXES: &[&str] = &["sk-", "sk_", "ghp_", "gho_", "xoxb-", "xoxp-"];
+
+#[derive(Clone, Debug)]
+pub(crate) enum WireOutcome {
+    Pending,
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+/// Summary of one logged request, kept in memory for `/debug requests`.
+#[derive(Clone, Debug)]
+pub(crate) struct WireExchange {
+    pub(crate) id: u64,
+    pub(crate) model: String,
+    pub(crate) message_count: usize,
+    pub(crate) request_bytes: usize,
+    pub(crate) deltas: usize,
+    pub(crate) response_bytes: usize,
+    pub(crate) elapsed: Option<Duration>,
+    pub(crate) outcome: WireOutcome,
+}
+
+struct WireLogState {
+    writer: Option<LineWriter<File>>,
+    write_error: Option<String>,
+    next_id: u64,
+    recent: VecDeque<WireExchange>,
+}
+
+/// Opt-in JSONL log of provider traffic. Secrets are redacted and bodies are
+/// capped before anything reaches the disk.
+#[derive(Clone)]
+pub struct WireLog {
+    path: PathBuf,
+    state: Arc<Mutex<WireLogState>>,
+}
+
+impl WireLog {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            state: Arc::new(Mutex::new(WireLogState {
+                writer: Some(LineWriter::new(file)),
+                write_error: None,
+                next_id: 1,
+                recent: VecDeque::new(),
+            })),
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Most recent exchanges, oldest first.
+    pub(crate) fn recent(&self) -> Vec<WireExchange> {
+        self.lock().recent.iter().cloned().collect()
+    }
+
+    /// Set once a write fails; the log stops writing from then on.
+    pub(crate) fn write_error(&self) -> Option<String> {
+        self.lock().write_error.clone()
+    }
+
+    fn begin(&self, request: &CompletionRequest) -> u64 {
+        let mut body = serde_json::to_value(request).unwrap_or(Value::Null);
+        sanitize(&mut body);
+        let request_bytes = body.to_string().len();
+
+        let mut state = self.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.write(json!({
+            "id": id,
+            "ts_ms": unix_millis(),
+            "kind": "request",
+            "body": body,
+        }));
+        if state.recent.len() == RECENT_LIMIT {
+            state.recent.pop_front();
+        }
+        state.recent.push_back(WireExchange {
+            id,
+            model: request.model.clone(),
+            message_count: request.messages.len(),
+            request_bytes,
+            deltas: 0,
+            response_bytes: 0,
+            elapsed: None,
+            outcome: WireOutcome::Pending,
+        });
+        id
+    }
+
+    fn finish(&self, id: u64, outcome: WireOutcome, text: &str, deltas: usize, elapsed: Duration) {
+        let (label, error) = match &outcome {
+            WireOutcome::Pending => ("pending", None),
+            WireOutcome::Done => ("done", None),
+            WireOutcome::Failed(err) => ("failed", Some(err.clone())),
+            WireOutcome::Cancelled => ("cancelled", None),
+        };
+        let mut body = Value::String(text.to_string());
+        sanitize(&mut body);
+
+        let mut state = self.lock();
+        state.write(json!({
+            "id": id,
+            "ts_ms": unix_millis(),
+            "kind": "response",
+            "outcome": label,
+            "error": error,
+            "elapsed_ms": elapsed.as_millis() as u64,
+            "deltas": deltas,
+            "body": body,
+        }));
+        if let Some(exchange) = state.recent.iter_mut().find(|exchange| exchange.id == id) {
+            exchange.deltas = deltas;
+            exchange.response_bytes = text.len();
+            exchange.elapsed = Some(elapsed);
+            exchange.outcome = outcome;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, WireLogState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl WireLogState {
+    fn write(&mut self, record: Value) {
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        let mut line = record.to_string();
+        line.push('\n');
+        if let Err(err) = writer.write_all(line.as_bytes()) {
+            self.writer = None;
+            self.write_error = Some(err.to_string());
+        }
+    }
+}
+
+/// Decorator that records every request and the response it produced.
+pub struct WireLoggingProvider {
+    inner: Box<dyn ChatProvider>,
+    log: WireLog,
+}
+
+impl WireLoggingProvider {
+    pub fn new(inner: Box<dyn ChatProvider>, log: WireLog) -> Self {
+        Self { inner, log }
+    }
+}
+
+impl ChatProvider for WireLoggingProvider {
+    fn stream_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        let id = self.log.begin(&request);
+        let started = Instant::now();
+        let inner = match self.inner.stream_completion(request) {
+            Ok(stream) => stream,
+            Err(err) => {
+                let outcome = WireOutcome::Failed(err.to_string());
+                self.log.finish(id, outcome, "", 0, started.elapsed());
+                return Err(err);
+            }
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let log = self.log.clone();
+        thread::spawn(move || {
+            let mut text = String::new();
+            let mut deltas = 0;
+            let outcome = loop {
+                let event = inner.recv();
+                let finished = match &event {
+                    StreamEvent::Delta(delta) => {
+                        text.push_str(delta);
+                        deltas += 1;
+                        None
+                    }
+                    StreamEvent::ToolCall {
+                        name, arguments, ..
+                    } => {
+                        text.push_str(&format!("[tool_call {name} {arguments}]"));
+                        deltas += 1;
+                        None
+                    }
+                    StreamEvent::Done => Some(WireOutcome::Done),
+                    StreamEvent::Failed(err) => Some(WireOutcome::Failed(err.to_string())),
+                };
+                if tx.send(event).is_err() {
+                    break WireOutcome::Cancelled;
+                }
+                if let Some(outcome) = finished {
+                    break outcome;
+                }
+            };
+            log.finish(id, outcome, &text, deltas, started.elapsed());
+        });
+
+        Ok(CompletionStream::new(rx))
+    }
+}
+
+fn sanitize(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_key(key) {
+                    *value = Value::String("[redacted]".to_string());
+                } else {
+                    sanitize(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(sanitize),
+        Value::String(text) => {
+            *text = redact_tokens(text);
+            cap(text);
+        }
+        _ => {}
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    SECRET_KEYS
+        .iter()
+        .any(|secret| key.eq_ignore_ascii_case(secret))
+}
+
+fn redact_tokens(text: &str) -> String {
+    text.split_inclusive(char::is_whitespace)
+        .map(|word| {
+            let token = word.trim_end();
+            let secret = token.len() >= 20
+                && SECRET_PREFIXES
+                    .iter()
+                    .any(|prefix| token.starts_with(prefix));
+            if secret {
+                format!("[redacted]{}", &word[token.len()..])
+            } else {
+                word.to_string()
+            }
+        })
+        .collect()
+}
+
+fn cap(text: &mut String) {
+    if text.len() <= MAX_BODY_BYTES {
+        return;
+    }
+    let mut end = MAX_BODY_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let omitted = text.len() - end;
+    text.truncate(end);
+    text.push_str(&format!("…[{omitted} bytes omitted]"));
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_redacts_secrets_and_caps_bodies() {
+        let mut value = json!({
+            "headers": { "Authorization": "Bearer abc" },
+            "content": format!("key sk-{} here", "x".repeat(30)),
+            "long": "a".repeat(MAX_BODY_BYTES + 10),
+        });
+        sanitize(&mut value);
+
+        assert_eq!(value["headers"]["Authorization"], "[redacted]");
+        assert_eq!(value["content"], "key [redacted] here");
+        assert!(
+            value["long"]
+                .as_str()
+                .unwrap()
+                .ends_with("…[10 bytes omitted]")
+        );
+    }
+}
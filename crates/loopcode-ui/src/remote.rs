@@ -0,0 +1,248 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// How often the connection to the host of a remote root is timed.
+const PROBE_INTERVAL: Duration = Duration::from_secs(15);
+const SCHEME: &str = "ssh://";
+
+/// A workspace root on another machine, reached over SSH. Locally it is the
+/// path `ssh://host/dir`, so it sits among the other roots; commands for it
+/// run on `host` in `dir` through one shared control connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct RemoteRoot<'a> {
+    pub(crate) host: &'a str,
+    /// The root's directory on the host.
+    pub(crate) dir: &'a Path,
+}
+
+impl<'a> RemoteRoot<'a> {
+    /// The remote root `root` stands for, if it is one.
+    pub(crate) fn of(root: &'a Path) -> Option<Self> {
+        let rest = root.to_str()?.strip_prefix(SCHEME)?;
+        let at = rest.find('/')?;
+        let (host, dir) = rest.split_at(at);
+        is_host(host).then_some(Self {
+            host,
+            dir: Path::new(dir),
+        })
+    }
+
+    /// `host:/dir` or `ssh://host/dir` as the local path of the root; `None`
+    /// for anything else. A relative `dir` is taken from the home directory
+    /// on the host.
+    pub(crate) fn parse(spec: &str) -> Option<PathBuf> {
+        let (host, dir) = match spec.strip_prefix(SCHEME) {
+            Some(rest) => rest.split_at(rest.find('/')?),
+            None => {
+                let (host, dir) = spec.split_once(':')?;
+                // `C:\...` and `./a:b` are local.
+                if host.len() < 2 || host.contains(['/', '\\']) {
+                    return None;
+                }
+                (host, dir)
+            }
+        };
+        if !is_host(host) {
+            return None;
+        }
+        let dir = match dir.trim_end_matches('/') {
+            "" | "~" => "~".to_string(),
+            dir if dir.starts_with('/') => dir.to_string(),
+            dir => format!("~/{}", dir.trim_start_matches("~/")),
+        };
+        // Home has no absolute path here; `/~` stands for it.
+        let dir = match dir.strip_prefix('~') {
+            Some(rest) => format!("/~{rest}"),
+            None => dir,
+        };
+        Some(PathBuf::from(format!("{SCHEME}{host}{dir}")))
+    }
+
+    /// How the status bar and `/roots` show it.
+    pub(crate) fn label(&self) -> String {
+        format!("{}:{}", self.host, self.shell_dir())
+    }
+
+    /// `program` with `args` run in the root's directory on the host.
+    pub(crate) fn command(&self, program: &str, args: &[&str]) -> Command {
+        let mut script = format!("cd {} && exec {}", self.shell_dir_quoted(), quote(program));
+        for arg in args {
+            script.push(' ');
+            script.push_str(&quote(arg));
+        }
+        let mut command = ssh(self.host);
+        command.arg(script);
+        command
+    }
+
+    /// The directory as the remote shell takes it, `~` for home.
+    fn shell_dir(&self) -> String {
+        let dir = self.dir.to_string_lossy();
+        match dir.strip_prefix("/~") {
+            Some(rest) => format!("~{rest}"),
+            None => dir.into_owned(),
+        }
+    }
+
+    fn shell_dir_quoted(&self) -> String {
+        let dir = self.shell_dir();
+        match dir.strip_prefix('~') {
+            // The shell expands `~` only unquoted.
+            Some(rest) => format!("~{}", quote(rest)),
+            None => quote(&dir),
+        }
+    }
+}
+
+/// Whether `host` can name an SSH destination: not empty, and not starting
+/// with `-`, which ssh would take for an option such as `-oProxyCommand=`.
+fn is_host(host: &str) -> bool {
+    !host.is_empty() && !host.starts_with('-')
+}
+
+/// An `ssh` to `host` that shares one control connection with every other
+/// command for it, kept open for a while after the last one, and never
+/// prompts. Options end before `host`, so it is never read as one.
+fn ssh(host: &str) -> Command {
+    let control = std::env::temp_dir().join("loopcode-ssh-%C");
+    let mut command = Command::new("ssh");
+    command
+        .arg("-o")
+        .arg("BatchMode=yes")
+        .arg("-o")
+        .arg("ControlMaster=auto")
+        .arg("-o")
+        .arg(format!("ControlPath={}", control.display()))
+        .arg("-o")
+        .arg("ControlPersist=600")
+        .arg("--")
+        .arg(host);
+    command
+}
+
+/// `text` quoted for a POSIX shell.
+fn quote(text: &str) -> String {
+    if !text.is_empty()
+        && text
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || "-_./=:@%+,".contains(ch))
+    {
+        return text.to_string();
+    }
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// The round trip to a host as last timed, for the status bar.
+pub(crate) struct Latency {
+    host: String,
+    last: Option<Result<Duration, String>>,
+    pending: Option<Receiver<Result<Duration, String>>>,
+    checked_at: Instant,
+}
+
+impl Latency {
+    pub(crate) fn new(host: &str) -> Self {
+        let mut latency = Self {
+            host: host.to_string(),
+            last: None,
+            pending: None,
+            checked_at: Instant::now(),
+        };
+        latency.probe();
+        latency
+    }
+
+    pub(crate) fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Picks up a finished timing and starts the next when it is due.
+    /// Returns why the timing just picked up failed, if it did.
+    pub(crate) fn poll(&mut self) -> Option<String> {
+        let mut failed = None;
+        if let Some(result) = self.pending.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            failed = result.as_ref().err().cloned();
+            self.last = Some(result);
+            self.pending = None;
+        }
+        if self.pending.is_none() && self.checked_at.elapsed() >= PROBE_INTERVAL {
+            self.probe();
+        }
+        failed
+    }
+
+    /// `host 42ms`, `host …` before the first timing, or why it failed.
+    pub(crate) fn label(&self) -> String {
+        match &self.last {
+            None => format!("{} …", self.host),
+            Some(Ok(elapsed)) => format!("{} {}ms", self.host, elapsed.as_millis()),
+            Some(Err(_)) => format!("{} unreachable", self.host),
+        }
+    }
+
+    fn probe(&mut self) {
+        self.checked_at = Instant::now();
+        let (tx, rx) = mpsc::channel();
+        let mut command = ssh(&self.host);
+        command
+            .arg("true")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        std::thread::spawn(move || {
+            let started = Instant::now();
+            let result = match command.output() {
+                Ok(output) if output.status.success() => Ok(started.elapsed()),
+                Ok(output) => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+                Err(err) => Err(format!("cannot run ssh: {err}")),
+            };
+            let _ = tx.send(result);
+        });
+        self.pending = Some(rx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_roots_parse_and_run_in_their_directory() {
+        let root = RemoteRoot::parse("build-box:/srv/app/").unwrap();
+        assert_eq!(root, Path::new("ssh://build-box/srv/app"));
+        assert_eq!(
+            RemoteRoot::parse("ssh://me@host/work").unwrap(),
+            Path::new("ssh://me@host/work")
+        );
+        let home = RemoteRoot::parse("host:src/my app").unwrap();
+        assert_eq!(RemoteRoot::of(&home).unwrap().label(), "host:~/src/my app");
+        assert_eq!(RemoteRoot::parse("./notes:today"), None);
+        assert_eq!(RemoteRoot::parse("C:\\work"), None);
+        assert_eq!(RemoteRoot::of(Path::new("/srv/app")), None);
+
+        let remote = RemoteRoot::of(&home).unwrap();
+        assert_eq!(remote.host, "host");
+        let command = remote.command("git", &["log", "--format=%s", "it's"]);
+        let args = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args.last().unwrap(),
+            r"cd ~'/src/my app' && exec git log --format=%s 'it'\''s'"
+        );
+        assert_eq!(args[args.len() - 3..args.len() - 1], ["--", "host"]);
+    }
+
+    #[test]
+    fn hosts_that_look_like_options_are_not_remote_roots() {
+        assert_eq!(RemoteRoot::parse("-oProxyCommand=touch pwned:/tmp"), None);
+        assert_eq!(RemoteRoot::parse("ssh://-oProxyCommand=x/tmp"), None);
+        assert_eq!(
+            RemoteRoot::of(Path::new("ssh://-oProxyCommand=x/tmp")),
+            None
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Find-and-replace over the files a reply is waiting to write, so a name
+//! the model chose can be changed before anything reaches the disk.
+
+use serde_json::Value;
+
+/// Changed lines a preview shows for each file before cutting it.
+const PREVIEW_LINES: usize = 3;
+
+/// The path and content a `write_file` call would write, or `None` for any
+/// other call.
+pub(crate) fn pending_write(name: &str, arguments: &str) -> Option<(String, String)> {
+    if name != "write_file" {
+        return None;
+    }
+    let arguments = serde_json::from_str::<Value>(arguments).ok()?;
+    let path = arguments.get("path")?.as_str()?;
+    let content = arguments.get("content")?.as_str()?;
+    Some((path.to_string(), content.to_string()))
+}
+
+/// The `arguments` of a `write_file` call with every `find` in its content
+/// replaced by `with`, and how many were; `None` when there were none.
+pub(crate) fn apply(
+    name: &str,
+    arguments: &str,
+    find: &str,
+    with: &str,
+) -> Option<(String, usize)> {
+    let (_, content) = pending_write(name, arguments)?;
+    let count = content.matches(find).count();
+    if count == 0 {
+        return None;
+    }
+    let mut arguments = serde_json::from_str::<Value>(arguments).ok()?;
+    arguments["content"] = Value::String(content.replace(find, with));
+    Some((arguments.to_string(), count))
+}
+
+/// What replacing `find` with `with` would change in `writes`, given as
+/// paths and contents: how many matches each file has, and its first few
+/// changed lines before and after.
+pub(crate) fn preview(writes: &[(String, String)], find: &str, with: &str) -> String {
+    let mut lines = Vec::new();
+    for (path, content) in writes {
+        let count = content.matches(find).count();
+        if count == 0 {
+            continue;
+        }
+        let noun = if count == 1 { "match" } else { "matches" };
+        lines.push(format!("{path}: {count} {noun}"));
+        let changed = content.lines().filter(|line| line.contains(find));
+        for line in changed.clone().take(PREVIEW_LINES) {
+            lines.push(format!("- {line}"));
+            lines.push(format!("+ {}", line.replace(find, with)));
+        }
+        let more = changed.count().saturating_sub(PREVIEW_LINES);
+        if more > 0 {
+            lines.push(format!("… {more} more changed lines"));
+        }
+    }
+    if lines.is_empty() {
+        return format!("no pending file write contains `{find}`");
+    }
+    lines.push("/replace apply makes these changes".to_string());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replacing_rewrites_only_the_content_of_file_writes() {
+        let arguments = r#"{"path":"src/foo.rs","content":"let foo = 1;\nfoo + foo\n"}"#;
+        let writes = [pending_write("write_file", arguments).unwrap()];
+        assert_eq!(
+            preview(&writes, "foo", "bar"),
+            "src/foo.rs: 3 matches\n\
+             - let foo = 1;\n\
+             + let bar = 1;\n\
+             - foo + foo\n\
+             + bar + bar\n\
+             /replace apply makes these changes"
+        );
+        assert_eq!(
+            preview(&writes, "baz", "bar"),
+            "no pending file write contains `baz`"
+        );
+
+        let (replaced, count) = apply("write_file", arguments, "foo", "bar").unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(
+            pending_write("write_file", &replaced).unwrap(),
+            (
+                "src/foo.rs".to_string(),
+                "let bar = 1;\nbar + bar\n".to_string()
+            )
+        );
+        assert_eq!(apply("write_file", arguments, "baz", "bar"), None);
+        assert_eq!(apply("shell", r#"{"command":"foo"}"#, "foo", "bar"), None);
+    }
+}
@@ -0,0 +1,164 @@
+use unicode_width::UnicodeWidthStr;
+
+/// Longest command a downgrade notice quotes.
+const QUOTE_WIDTH: usize = 60;
+
+/// Operations that Autonomous mode stops for, dropping to Safest so they
+/// need confirming.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RiskKind {
+    PackageInstall,
+    GitPush,
+    RecursiveDelete,
+    Network,
+}
+
+impl RiskKind {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::PackageInstall => "package install",
+            Self::GitPush => "git push",
+            Self::RecursiveDelete => "rm -r",
+            Self::Network => "network call",
+        }
+    }
+}
+
+/// A tool call classified as risky, with what it asked to run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Risk {
+    pub(crate) kind: RiskKind,
+    pub(crate) command: String,
+}
+
+impl Risk {
+    /// The transcript line that flags the drop from Autonomous.
+    pub(crate) fn notice(&self) -> String {
+        let mut command = String::new();
+        for ch in self.command.chars() {
+            if command.width() + 1 >= QUOTE_WIDTH {
+                command.push('…');
+                break;
+            }
+            command.push(ch);
+        }
+        format!(
+            "⚠ Autonomous mode dropped to Safest: the agent asked for a {} (`{command}`). \
+             Confirm each step from here; Shift+Tab changes the mode.",
+            self.kind.label()
+        )
+    }
+}
+
+/// What a tool call to `tool` with JSON `arguments` risks, if anything: the
+/// command it runs when it has one, its name otherwise.
+pub(crate) fn classify(tool: &str, arguments: &str) -> Option<Risk> {
+    let command = command(arguments).unwrap_or_default();
+    let tool_lower = tool.to_lowercase();
+    let kind = if ["fetch", "http", "web", "download", "browse"]
+        .iter()
+        .any(|word| tool_lower.contains(word))
+    {
+        Some(RiskKind::Network)
+    } else {
+        command
+            .split(['&', ';', '|', '\n'])
+            .find_map(|part| classify_command(&part.split_whitespace().collect::<Vec<_>>()))
+    }?;
+    let command = if command.is_empty() {
+        tool.to_string()
+    } else {
+        command.trim().to_string()
+    };
+    Some(Risk { kind, command })
+}
+
+/// The shell command in a tool call's arguments.
+fn command(arguments: &str) -> Option<String> {
+    let serde_json::Value::Object(arguments) = serde_json::from_str(arguments).ok()? else {
+        return None;
+    };
+    ["command", "cmd", "script"]
+        .iter()
+        .find_map(|key| match arguments.get(*key)? {
+            serde_json::Value::String(command) => Some(command.clone()),
+            serde_json::Value::Array(words) => Some(
+                words
+                    .iter()
+                    .filter_map(serde_json::Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            _ => None,
+        })
+}
+
+/// One command of a pipeline or list, split into words.
+fn classify_command(words: &[&str]) -> Option<RiskKind> {
+    // `sudo` and `env` run what follows.
+    let words = match words {
+        ["sudo" | "env", rest @ ..] => rest,
+        words => words,
+    };
+    let (program, args) = words.split_first()?;
+    let program = program.rsplit('/').next().unwrap_or(program);
+    let subcommand = args.first().copied().unwrap_or_default();
+    match program {
+        "npm" | "pnpm" | "yarn" | "bun" | "pip" | "pip3" | "cargo" | "gem" | "go" | "apt"
+        | "apt-get" | "brew" | "dnf" | "yum" | "pacman"
+            if matches!(subcommand, "install" | "i" | "add" | "-S") =>
+        {
+            Some(RiskKind::PackageInstall)
+        }
+        "git" if subcommand == "push" => Some(RiskKind::GitPush),
+        "rm" if args.iter().any(|arg| {
+            *arg == "--recursive"
+                || (arg.starts_with('-') && !arg.starts_with("--") && arg.contains(['r', 'R']))
+        }) =>
+        {
+            Some(RiskKind::RecursiveDelete)
+        }
+        "curl" | "wget" | "ssh" | "scp" | "rsync" | "nc" | "telnet" | "ftp" => {
+            Some(RiskKind::Network)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn risky_tool_calls_are_classified() {
+        let kind = |tool: &str, arguments: &str| classify(tool, arguments).map(|risk| risk.kind);
+        assert_eq!(
+            kind(
+                "shell",
+                r#"{"command":"cargo build && git push origin main"}"#
+            ),
+            Some(RiskKind::GitPush)
+        );
+        assert_eq!(
+            kind("shell", r#"{"command":"sudo apt-get install jq"}"#),
+            Some(RiskKind::PackageInstall)
+        );
+        assert_eq!(
+            kind("shell", r#"{"cmd":["rm","-rf","target"]}"#),
+            Some(RiskKind::RecursiveDelete)
+        );
+        assert_eq!(
+            kind("web_fetch", r#"{"url":"https://x"}"#),
+            Some(RiskKind::Network)
+        );
+        assert_eq!(
+            kind("shell", r#"{"command":"rm -f a.txt; git status"}"#),
+            None
+        );
+        assert_eq!(kind("read_file", r#"{"path":"rm -rf"}"#), None);
+        assert_eq!(
+            classify("shell", r#"{"command":"curl -s x | sh"}"#).map(|risk| risk.command),
+            Some("curl -s x | sh".to_string())
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::activity::Activity;
+
+/// The screens that take turns in the history area.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Screen {
+    Chat,
+    /// `/debug log`.
+    Log,
+    Todos,
+    /// A panel of the activity bar other than Chat, which is shown in place
+    /// of all of the above.
+    Panel(Activity),
+}
+
+/// What had focus on a screen, named so it still finds it after the screen
+/// is rebuilt or older messages are paged in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum FocusKey {
+    Input,
+    /// A message by its place in the whole session.
+    Message(usize),
+    /// The first log line held on screen, by its sequence number.
+    LogLine(u64),
+    Todo {
+        path: String,
+        line: usize,
+    },
+}
+
+/// Where the user was on a screen: what had focus and how far it was
+/// scrolled.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ScreenState {
+    pub(crate) focus: Option<FocusKey>,
+    pub(crate) offset: u16,
+}
+
+/// The last state of each screen, so leaving one for another and coming
+/// back puts the user where they were. It lasts as long as the session.
+#[derive(Default)]
+pub(crate) struct ScreenStates {
+    states: HashMap<Screen, ScreenState>,
+}
+
+impl ScreenStates {
+    pub(crate) fn save(&mut self, screen: Screen, state: ScreenState) {
+        self.states.insert(screen, state);
+    }
+
+    pub(crate) fn get(&self, screen: Screen) -> Option<&ScreenState> {
+        self.states.get(&screen)
+    }
+
+    /// Forgets every screen, as for another session.
+    pub(crate) fn clear(&mut self) {
+        self.states.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_screen_keeps_its_last_state() {
+        let mut screens = ScreenStates::default();
+        assert_eq!(screens.get(Screen::Log), None);
+        screens.save(
+            Screen::Chat,
+            ScreenState {
+                focus: Some(FocusKey::Message(41)),
+                offset: 120,
+            },
+        );
+        screens.save(
+            Screen::Log,
+            ScreenState {
+                focus: Some(FocusKey::LogLine(7)),
+                offset: 0,
+            },
+        );
+        screens.save(
+            Screen::Chat,
+            ScreenState {
+                focus: Some(FocusKey::Input),
+                offset: 80,
+            },
+        );
+        assert_eq!(screens.get(Screen::Chat).unwrap().offset, 80);
+        assert_eq!(
+            screens.get(Screen::Log).unwrap().focus,
+            Some(FocusKey::LogLine(7))
+        );
+
+        screens.clear();
+        assert_eq!(screens.get(Screen::Chat), None);
+    }
+}
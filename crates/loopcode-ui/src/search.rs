@@ -0,0 +1,139 @@
+use crate::bookmarks::truncate;
+use crate::chat::ChatMessage;
+
+pub(crate) enum SearchOutcome {
+    Pending,
+    /// Show this message in the chat.
+    Jump(usize),
+    Close,
+}
+
+/// The Search panel: what is typed finds the messages of the session that
+/// contain it, ignoring case.
+#[derive(Default)]
+pub(crate) struct SearchPanel {
+    query: String,
+    selected: usize,
+}
+
+impl SearchPanel {
+    /// The indices of the messages that match, in order.
+    pub(crate) fn matches(&self, messages: &[ChatMessage]) -> Vec<usize> {
+        if self.query.is_empty() {
+            return Vec::new();
+        }
+        let query = self.query.to_lowercase();
+        messages
+            .iter()
+            .enumerate()
+            .filter(|(_, message)| message.text.to_lowercase().contains(&query))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    pub(crate) fn handle_key(
+        &mut self,
+        key: xpui::UiKeyInput,
+        messages: &[ChatMessage],
+    ) -> SearchOutcome {
+        match key {
+            xpui::UiKeyInput::Char(ch) => {
+                self.query.push(ch);
+                self.selected = 0;
+            }
+            xpui::UiKeyInput::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            xpui::UiKeyInput::BackspaceWord => {
+                self.query.clear();
+                self.selected = 0;
+            }
+            xpui::UiKeyInput::Up => self.selected = self.selected.saturating_sub(1),
+            xpui::UiKeyInput::Down => {
+                let last = self.matches(messages).len().saturating_sub(1);
+                self.selected = (self.selected + 1).min(last);
+            }
+            xpui::UiKeyInput::Enter | xpui::UiKeyInput::Submit => {
+                if let Some(index) = self.matches(messages).get(self.selected) {
+                    return SearchOutcome::Jump(*index);
+                }
+            }
+            xpui::UiKeyInput::Esc => return SearchOutcome::Close,
+            _ => {}
+        }
+        SearchOutcome::Pending
+    }
+
+    /// The query, then one line per match cut to `width` columns, and the
+    /// offset that keeps the selected one within `viewport` lines.
+    pub(crate) fn render(
+        &self,
+        messages: &[ChatMessage],
+        paged_out: usize,
+        width: usize,
+        viewport: u16,
+    ) -> (String, u16) {
+        let matches = self.matches(messages);
+        let mut out = format!("Search: {}▏", self.query);
+        if self.query.is_empty() {
+            out.push_str("\nType to find messages · Enter shows one · Esc back");
+            return (out, 0);
+        }
+        out.push_str(&format!(
+            "\n{} messages match · Enter shows one · Esc back",
+            matches.len()
+        ));
+        let query = self.query.to_lowercase();
+        for (row, index) in matches.iter().enumerate() {
+            let message = &messages[*index];
+            let marker = if row == self.selected { "▶" } else { " " };
+            // The line that matched, not just the first.
+            let line = message
+                .text
+                .lines()
+                .find(|line| line.to_lowercase().contains(&query))
+                .unwrap_or_default()
+                .trim();
+            out.push('\n');
+            out.push_str(&truncate(
+                &format!(
+                    "{marker} #{} {}: {line}",
+                    paged_out + index + 1,
+                    message.role.label()
+                ),
+                width,
+            ));
+        }
+        // The query and the count take the first two lines.
+        let offset = (self.selected + 3).saturating_sub(usize::from(viewport));
+        (out, offset as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::Role;
+
+    #[test]
+    fn typing_finds_messages_and_enter_jumps_to_one() {
+        let messages = [
+            ChatMessage::new(Role::User, "Why does the Parser panic?".to_string()),
+            ChatMessage::new(Role::Assistant, "It unwraps.".to_string()),
+            ChatMessage::new(Role::User, "fix the parser".to_string()),
+        ];
+        let mut search = SearchPanel::default();
+        for ch in "parser".chars() {
+            search.handle_key(xpui::UiKeyInput::Char(ch), &messages);
+        }
+        assert_eq!(search.matches(&messages), [0, 2]);
+        search.handle_key(xpui::UiKeyInput::Down, &messages);
+        assert!(matches!(
+            search.handle_key(xpui::UiKeyInput::Enter, &messages),
+            SearchOutcome::Jump(2)
+        ));
+        let (body, _) = search.render(&messages, 10, 80, 20);
+        assert!(body.ends_with("▶ #13 you: fix the parser"));
+    }
+}
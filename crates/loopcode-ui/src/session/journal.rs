@@ -0,0 +1,561 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use serde::{Deserialize, Serialize};
+
+use super::{ForkOrigin, SessionId};
+use crate::chat::{ChatMessage, MessageStatus, Role, RunSummary};
+use crate::store::{self, Store, TurnUsage};
+use crate::target::{ExecTarget, RunEnv};
+
+/// One history mutation. The journal is the ordered list of these, so replaying
+/// it rebuilds the history exactly as it was when the last line was written.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub(crate) enum JournalRecord {
+    /// First record of a forked session.
+    Forked {
+        origin: ForkOrigin,
+    },
+    Insert {
+        index: usize,
+        message: ChatMessage,
+    },
+    Delta {
+        index: usize,
+        text: String,
+    },
+    /// Replaces a message's whole text.
+    Rewrite {
+        index: usize,
+        text: String,
+    },
+    Status {
+        index: usize,
+        status: MessageStatus,
+    },
+    Truncate {
+        len: usize,
+    },
+    Annotate {
+        index: usize,
+        note: Option<String>,
+    },
+    Bookmark {
+        index: usize,
+        bookmarked: bool,
+    },
+    Run {
+        index: usize,
+        run: RunSummary,
+    },
+}
+
+impl JournalRecord {
+    /// Moves the record's message indices up by `base`, for a history that
+    /// holds only the messages from `base` on.
+    pub(crate) fn shifted(self, base: usize) -> Self {
+        match self {
+            Self::Forked { origin } => Self::Forked { origin },
+            Self::Insert { index, message } => Self::Insert {
+                index: index + base,
+                message,
+            },
+            Self::Delta { index, text } => Self::Delta {
+                index: index + base,
+                text,
+            },
+            Self::Rewrite { index, text } => Self::Rewrite {
+                index: index + base,
+                text,
+            },
+            Self::Status { index, status } => Self::Status {
+                index: index + base,
+                status,
+            },
+            Self::Truncate { len } => Self::Truncate { len: len + base },
+            Self::Annotate { index, note } => Self::Annotate {
+                index: index + base,
+                note,
+            },
+            Self::Bookmark { index, bookmarked } => Self::Bookmark {
+                index: index + base,
+                bookmarked,
+            },
+            Self::Run { index, run } => Self::Run {
+                index: index + base,
+                run,
+            },
+        }
+    }
+}
+
+/// The history of one session in the [`Store`], kept up to date record by
+/// record. Each record is its own transaction, so a killed process loses at
+/// most the one in flight.
+pub(crate) struct SessionJournal {
+    store: Store,
+    session: SessionId,
+}
+
+impl SessionJournal {
+    /// Adds `session`, with no messages yet, to `store`.
+    pub(crate) fn create(mut store: Store, session: &SessionId) -> rusqlite::Result<Self> {
+        let now = store::now_millis();
+        store.transaction().and_then(|tx| {
+            tx.execute(
+                "INSERT INTO sessions (id, created, modified) VALUES (?1, ?2, ?2)",
+                params![session.as_str(), now],
+            )?;
+            tx.commit()
+        })?;
+        Ok(Self {
+            store,
+            session: session.clone(),
+        })
+    }
+
+    /// Picks up where `session` left off; `None` if `store` has no such
+    /// session.
+    pub(crate) fn open(store: Store, session: &SessionId) -> rusqlite::Result<Option<Self>> {
+        let exists = store
+            .conn()
+            .query_row(
+                "SELECT 1 FROM sessions WHERE id = ?1",
+                [session.as_str()],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        Ok(exists.then(|| Self {
+            store,
+            session: session.clone(),
+        }))
+    }
+
+    pub(crate) fn write(&mut self, record: &JournalRecord) -> rusqlite::Result<()> {
+        self.write_all([record])
+    }
+
+    /// Writes `records` in one transaction, all or none of them.
+    pub(crate) fn write_all<'a>(
+        &mut self,
+        records: impl IntoIterator<Item = &'a JournalRecord>,
+    ) -> rusqlite::Result<()> {
+        let session = self.session.as_str();
+        let tx = self.store.transaction()?;
+        for record in records {
+            apply(&tx, session, record)?;
+        }
+        tx.execute(
+            "UPDATE sessions SET modified = ?2 WHERE id = ?1",
+            params![session, store::now_millis()],
+        )
+        .and_then(|_| tx.commit())
+    }
+
+    /// Replaces the workspace roots saved with the session.
+    pub(crate) fn set_roots(&mut self, roots: &[PathBuf]) -> rusqlite::Result<()> {
+        let session = self.session.as_str();
+        let write = |tx: Transaction| -> rusqlite::Result<()> {
+            tx.execute("DELETE FROM session_roots WHERE session = ?1", [session])?;
+            for (index, root) in roots.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO session_roots (session, idx, path) VALUES (?1, ?2, ?3)",
+                    params![session, index, root.to_string_lossy()],
+                )?;
+            }
+            tx.commit()
+        };
+        self.store.transaction().and_then(write)
+    }
+
+    /// Saves where the session's commands run.
+    pub(crate) fn set_target(&mut self, target: &ExecTarget) -> rusqlite::Result<()> {
+        let json = to_json(target)?;
+        self.store
+            .conn()
+            .execute(
+                "UPDATE sessions SET target = ?2 WHERE id = ?1",
+                params![self.session.as_str(), json],
+            )
+            .map(drop)
+    }
+
+    /// Saves the variables and directory the session's commands run with.
+    pub(crate) fn set_run_env(&mut self, env: &RunEnv) -> rusqlite::Result<()> {
+        let json = to_json(env)?;
+        self.store
+            .conn()
+            .execute(
+                "UPDATE sessions SET run_env = ?2 WHERE id = ?1",
+                params![self.session.as_str(), json],
+            )
+            .map(drop)
+    }
+
+    /// Records a finished turn of the session.
+    pub(crate) fn record_usage(&self, usage: &TurnUsage) -> rusqlite::Result<()> {
+        self.store.record_usage(self.session.as_str(), usage)
+    }
+
+    /// Adds an entry to the audit trail of the session.
+    pub(crate) fn audit(&self, kind: &str, detail: &str) -> rusqlite::Result<()> {
+        self.store.audit(self.session.as_str(), kind, detail)
+    }
+}
+
+/// Makes the change `record` describes to the messages of `session`.
+fn apply(tx: &Transaction, session: &str, record: &JournalRecord) -> rusqlite::Result<()> {
+    match record {
+        JournalRecord::Forked { origin } => {
+            tx.execute(
+                "UPDATE sessions SET parent = ?2, fork_at = ?3 WHERE id = ?1",
+                params![session, origin.parent.as_str(), origin.at],
+            )?;
+        }
+        JournalRecord::Insert { index, message } => {
+            let len = count(tx, session)?;
+            let index = (*index).min(len);
+            tx.execute(
+                "UPDATE messages SET idx = idx + 1 WHERE session = ?1 AND idx >= ?2",
+                params![session, index],
+            )?;
+            // The text has its own column, for deltas to append to.
+            let body = ChatMessage {
+                text: String::new(),
+                ..message.clone()
+            };
+            tx.execute(
+                "INSERT INTO messages (session, idx, role, text, message)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    session,
+                    index,
+                    role_name(message.role),
+                    message.text,
+                    to_json(&body)?
+                ],
+            )?;
+        }
+        JournalRecord::Delta { index, text } => {
+            tx.execute(
+                "UPDATE messages SET text = text || ?3 WHERE session = ?1 AND idx = ?2",
+                params![session, index, text],
+            )?;
+        }
+        JournalRecord::Rewrite { index, text } => {
+            tx.execute(
+                "UPDATE messages SET text = ?3 WHERE session = ?1 AND idx = ?2",
+                params![session, index, text],
+            )?;
+        }
+        JournalRecord::Status { index, status } => {
+            update(tx, session, *index, |message| message.status = *status)?;
+        }
+        JournalRecord::Truncate { len } => {
+            tx.execute(
+                "DELETE FROM messages WHERE session = ?1 AND idx >= ?2",
+                params![session, len],
+            )?;
+        }
+        JournalRecord::Annotate { index, note } => {
+            update(tx, session, *index, |message| message.note = note.clone())?;
+        }
+        JournalRecord::Bookmark { index, bookmarked } => {
+            update(tx, session, *index, |message| {
+                message.bookmarked = *bookmarked;
+            })?;
+        }
+        JournalRecord::Run { index, run } => {
+            update(tx, session, *index, |message| {
+                message.run = Some(run.clone())
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites the stored message at `index`, if there is one, with `change`.
+fn update(
+    tx: &Transaction,
+    session: &str,
+    index: usize,
+    change: impl FnOnce(&mut ChatMessage),
+) -> rusqlite::Result<()> {
+    let body = tx
+        .query_row(
+            "SELECT message FROM messages WHERE session = ?1 AND idx = ?2",
+            params![session, index],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    let Some(body) = body else {
+        return Ok(());
+    };
+    let mut message = from_json(&body)?;
+    change(&mut message);
+    tx.execute(
+        "UPDATE messages SET message = ?3 WHERE session = ?1 AND idx = ?2",
+        params![session, index, to_json(&message)?],
+    )?;
+    Ok(())
+}
+
+/// How many messages `session` has stored.
+pub(super) fn count(conn: &Connection, session: &str) -> rusqlite::Result<usize> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM messages WHERE session = ?1",
+        [session],
+        |row| row.get(0),
+    )
+}
+
+/// The workspace roots saved with `session`, in order.
+pub(super) fn roots(conn: &Connection, session: &str) -> rusqlite::Result<Vec<PathBuf>> {
+    let mut statement =
+        conn.prepare("SELECT path FROM session_roots WHERE session = ?1 ORDER BY idx")?;
+    let rows = statement.query_map([session], |row| row.get::<_, String>(0).map(PathBuf::from))?;
+    rows.collect()
+}
+
+/// Where `session` runs its commands, if that was ever saved.
+pub(super) fn target(conn: &Connection, session: &str) -> rusqlite::Result<Option<ExecTarget>> {
+    let json: Option<String> = conn.query_row(
+        "SELECT target FROM sessions WHERE id = ?1",
+        [session],
+        |row| row.get(0),
+    )?;
+    Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+/// What `session` runs its commands with, if that was ever saved.
+pub(super) fn run_env(conn: &Connection, session: &str) -> rusqlite::Result<Option<RunEnv>> {
+    let json: Option<String> = conn.query_row(
+        "SELECT run_env FROM sessions WHERE id = ?1",
+        [session],
+        |row| row.get(0),
+    )?;
+    Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+/// The stored messages of `session` in `range`, clamped to what it has.
+pub(super) fn read(
+    conn: &Connection,
+    session: &str,
+    range: Range<usize>,
+) -> rusqlite::Result<Vec<ChatMessage>> {
+    let mut statement = conn.prepare(
+        "SELECT text, message FROM messages
+         WHERE session = ?1 AND idx >= ?2 AND idx < ?3 ORDER BY idx",
+    )?;
+    let rows = statement.query_map(params![session, range.start, range.end], |row| {
+        let text = row.get::<_, String>(0)?;
+        let mut message = from_json(&row.get::<_, String>(1)?)?;
+        message.text = text;
+        Ok(message)
+    })?;
+    rows.collect()
+}
+
+/// Stores `messages` as the history of the new `session`, for a journal
+/// imported from the file it used to be kept in.
+pub(super) fn import(
+    tx: &Transaction,
+    session: &SessionId,
+    modified: i64,
+    replayed: &Replayed,
+) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT OR IGNORE INTO sessions (id, created, modified) VALUES (?1, ?2, ?2)",
+        params![session.as_str(), modified],
+    )?;
+    if let Some(origin) = &replayed.origin {
+        apply(
+            tx,
+            session.as_str(),
+            &JournalRecord::Forked {
+                origin: origin.clone(),
+            },
+        )?;
+    }
+    for (index, message) in replayed.messages.iter().enumerate() {
+        apply(
+            tx,
+            session.as_str(),
+            &JournalRecord::Insert {
+                index,
+                message: message.clone(),
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// The name `role` is stored under, as it is written in JSON.
+pub(super) fn role_name(role: Role) -> String {
+    serde_json::to_value(role)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn to_json<T: Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|err| rusqlite::Error::ToSqlConversionFailure(err.into()))
+}
+
+fn from_json(json: &str) -> rusqlite::Result<ChatMessage> {
+    serde_json::from_str(json).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, err.into())
+    })
+}
+
+pub(super) struct Replayed {
+    pub(super) messages: Vec<ChatMessage>,
+    pub(super) origin: Option<ForkOrigin>,
+}
+
+/// Rebuilds the history from a JSONL journal file, as sessions were kept
+/// before the store. Lines that fail to parse, such as a record cut short by
+/// a crash, are skipped.
+pub(super) fn replay(path: &Path) -> io::Result<Replayed> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut messages: Vec<ChatMessage> = Vec::new();
+    let mut origin = None;
+
+    for line in reader.lines() {
+        let line = line?;
+        let Ok(record) = serde_json::from_str::<JournalRecord>(&line) else {
+            continue;
+        };
+        match record {
+            JournalRecord::Forked { origin: forked } => origin = Some(forked),
+            JournalRecord::Insert { index, message } => {
+                messages.insert(index.min(messages.len()), message);
+            }
+            JournalRecord::Delta { index, text } => {
+                if let Some(message) = messages.get_mut(index) {
+                    message.text.push_str(&text);
+                }
+            }
+            JournalRecord::Rewrite { index, text } => {
+                if let Some(message) = messages.get_mut(index) {
+                    message.text = text;
+                }
+            }
+            JournalRecord::Status { index, status } => {
+                if let Some(message) = messages.get_mut(index) {
+                    message.status = status;
+                }
+            }
+            JournalRecord::Truncate { len } => messages.truncate(len),
+            JournalRecord::Annotate { index, note } => {
+                if let Some(message) = messages.get_mut(index) {
+                    message.note = note;
+                }
+            }
+            JournalRecord::Bookmark { index, bookmarked } => {
+                if let Some(message) = messages.get_mut(index) {
+                    message.bookmarked = bookmarked;
+                }
+            }
+            JournalRecord::Run { index, run } => {
+                if let Some(message) = messages.get_mut(index) {
+                    message.run = Some(run);
+                }
+            }
+        }
+    }
+
+    Ok(Replayed { messages, origin })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn replay_keeps_partial_stream_and_skips_cut_off_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+
+        let mut file = fs::File::create(&path).unwrap();
+        let mut reply = ChatMessage::new(Role::Assistant, "");
+        reply.status = MessageStatus::Streaming;
+        for record in [
+            JournalRecord::Insert {
+                index: 0,
+                message: ChatMessage::new(Role::User, "hi"),
+            },
+            JournalRecord::Insert {
+                index: 1,
+                message: reply,
+            },
+            JournalRecord::Delta {
+                index: 1,
+                text: "hel".to_string(),
+            },
+        ] {
+            serde_json::to_writer(&mut file, &record).unwrap();
+            file.write_all(b"\n").unwrap();
+        }
+        file.write_all(br#"{"op":"delta","index":1,"te"#).unwrap();
+        drop(file);
+
+        let messages = replay(&path).unwrap().messages;
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].text, "hel");
+        assert_eq!(messages[1].status, MessageStatus::Streaming);
+    }
+
+    #[test]
+    fn records_apply_to_the_store_in_order() {
+        let store = Store::open_in_memory().unwrap();
+        let id = SessionId("journal-test".to_string());
+        let mut journal = SessionJournal::create(store, &id).unwrap();
+        for record in [
+            JournalRecord::Insert {
+                index: 0,
+                message: ChatMessage::new(Role::User, "second"),
+            },
+            JournalRecord::Insert {
+                index: 0,
+                message: ChatMessage::new(Role::User, "first"),
+            },
+            JournalRecord::Insert {
+                index: 2,
+                message: ChatMessage::new(Role::Assistant, "he"),
+            },
+            JournalRecord::Delta {
+                index: 2,
+                text: "llo".to_string(),
+            },
+            JournalRecord::Bookmark {
+                index: 2,
+                bookmarked: true,
+            },
+            JournalRecord::Status {
+                index: 2,
+                status: MessageStatus::Interrupted,
+            },
+        ] {
+            journal.write(&record).unwrap();
+        }
+
+        let messages = read(journal.store.conn(), id.as_str(), 0..10).unwrap();
+        let texts = messages.iter().map(|m| m.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, ["first", "second", "hello"]);
+        assert!(messages[2].bookmarked);
+        assert_eq!(messages[2].status, MessageStatus::Interrupted);
+
+        journal.write(&JournalRecord::Truncate { len: 1 }).unwrap();
+        assert_eq!(count(journal.store.conn(), id.as_str()).unwrap(), 1);
+    }
+}
@@ -0,0 +1,457 @@
+mod journal;
+
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::chat::{ChatMessage, MessageStatus, Role};
+use crate::store::{self, Store};
+use crate::target::{ExecTarget, RunEnv};
+
+pub(crate) use journal::{JournalRecord, SessionJournal};
+
+static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SessionId(String);
+
+impl SessionId {
+    fn generate() -> Self {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default();
+        // Forking can create several sessions within the same millisecond.
+        let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        Self(format!("{millis:x}-{:x}-{sequence}", std::process::id()))
+    }
+
+    /// `id` as a session id, if it could be one: a plain name of letters,
+    /// digits, `-` and `_`, so nothing like `../x` or `/abs` gets through.
+    fn parse(id: &str) -> Option<Self> {
+        let plain = !id.is_empty()
+            && id
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+        plain.then(|| Self(id.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Where a forked session branched off: the parent session and the index of
+/// the last message the two share.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ForkOrigin {
+    pub(crate) parent: SessionId,
+    pub(crate) at: usize,
+}
+
+/// A session ready to be attached to the chat: its stored messages and the
+/// journal that records every subsequent change.
+pub struct Session {
+    pub(crate) id: SessionId,
+    pub(crate) messages: Vec<ChatMessage>,
+    pub(crate) journal: SessionJournal,
+    pub(crate) origin: Option<ForkOrigin>,
+    pub(crate) resumed: bool,
+    /// Number of earliest messages left on disk; `messages[0]` is the
+    /// message at this index in the session.
+    pub(crate) paged_out: usize,
+    /// The workspace roots saved with it; none for a new session.
+    pub(crate) roots: Vec<PathBuf>,
+    /// Where its commands run, once chosen; none for a new session.
+    pub(crate) target: Option<ExecTarget>,
+    /// What its commands run with, once set; none for a new session.
+    pub(crate) run_env: Option<RunEnv>,
+}
+
+pub fn start_new() -> Result<Session, SessionError> {
+    let id = SessionId::generate();
+    let journal = open_store("create session", Some(&id)).and_then(|store| {
+        SessionJournal::create(store, &id)
+            .map_err(|source| SessionError::storage("create session", &id, source))
+    })?;
+    Ok(Session {
+        id,
+        messages: Vec::new(),
+        journal,
+        origin: None,
+        resumed: false,
+        paged_out: 0,
+        roots: Vec::new(),
+        target: None,
+        run_env: None,
+    })
+}
+
+/// Starts a new session that already holds `exchanges`, prompt and reply
+/// pairs, as if they had been chatted. Benchmarks and bug reports use it to
+/// get a long session without typing one.
+pub fn start_with_transcript(
+    exchanges: impl IntoIterator<Item = (String, String)>,
+) -> Result<Session, SessionError> {
+    let mut session = start_new()?;
+    let messages = exchanges
+        .into_iter()
+        .flat_map(|(prompt, reply)| {
+            [
+                ChatMessage::new(Role::User, prompt),
+                ChatMessage::new(Role::Assistant, reply),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let records = inserts(&messages);
+    session
+        .journal
+        .write_all(&records)
+        .map_err(|source| SessionError::storage("write transcript", &session.id, source))?;
+    session.messages = messages;
+    session.resumed = true;
+    Ok(session)
+}
+
+/// Opens a saved session. A reply that was still streaming when it was last
+/// written is kept with whatever text arrived and marked truncated. Only the
+/// last `resident` messages are kept in memory; older ones are paged in with
+/// [`load_messages`].
+pub fn resume(id: &str, resident: usize) -> Result<Session, SessionError> {
+    let Some(id) = SessionId::parse(id) else {
+        return Err(SessionError {
+            kind: SessionErrorKind::InvalidId,
+            operation: "resume session",
+            session: Some(SessionId(id.to_string())),
+            source: None,
+        });
+    };
+    let store = open_store("resume session", Some(&id))?;
+    let read = |store: &Store| -> rusqlite::Result<_> {
+        let origin = store.conn().query_row(
+            "SELECT parent, fork_at FROM sessions WHERE id = ?1",
+            [id.as_str()],
+            |row| Ok(origin(row.get(0)?, row.get(1)?)),
+        );
+        let origin = match origin {
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            origin => origin?,
+        };
+        let len = journal::count(store.conn(), id.as_str())?;
+        let paged_out = len.saturating_sub(resident);
+        let messages = journal::read(store.conn(), id.as_str(), paged_out..len)?;
+        let roots = journal::roots(store.conn(), id.as_str())?;
+        let target = journal::target(store.conn(), id.as_str())?;
+        let run_env = journal::run_env(store.conn(), id.as_str())?;
+        Ok(Some((origin, paged_out, messages, roots, target, run_env)))
+    };
+    let Some((origin, paged_out, mut messages, roots, target, run_env)) =
+        read(&store).map_err(|source| SessionError::storage("read session", &id, source))?
+    else {
+        return Err(SessionError {
+            kind: SessionErrorKind::NotFound,
+            operation: "resume session",
+            session: Some(id),
+            source: None,
+        });
+    };
+    for message in &mut messages {
+        settle(message);
+    }
+    let journal = SessionJournal::open(store, &id)
+        .map_err(|source| SessionError::storage("open session", &id, source))?
+        .ok_or_else(|| SessionError {
+            kind: SessionErrorKind::NotFound,
+            operation: "resume session",
+            session: Some(id.clone()),
+            source: None,
+        })?;
+
+    Ok(Session {
+        id,
+        messages,
+        journal,
+        origin,
+        resumed: true,
+        paged_out,
+        roots,
+        target,
+        run_env,
+    })
+}
+
+/// Reads the messages in `range` of a saved session back, clamped to what it
+/// holds.
+pub(crate) fn load_messages(
+    id: &SessionId,
+    range: Range<usize>,
+) -> Result<Vec<ChatMessage>, SessionError> {
+    let store = open_store("read session", Some(id))?;
+    let mut page = journal::read(store.conn(), id.as_str(), range)
+        .map_err(|source| SessionError::storage("read session", id, source))?;
+    for message in &mut page {
+        settle(message);
+    }
+    Ok(page)
+}
+
+/// Starts a new session that shares `messages` with `parent` and records
+/// where it branched off.
+pub(crate) fn fork(parent: &SessionId, messages: &[ChatMessage]) -> Result<Session, SessionError> {
+    let origin = ForkOrigin {
+        parent: parent.clone(),
+        at: messages.len().saturating_sub(1),
+    };
+    let messages = messages
+        .iter()
+        .filter(|message| message.status != MessageStatus::Queued)
+        .cloned()
+        .map(|mut message| {
+            settle(&mut message);
+            message
+        })
+        .collect::<Vec<_>>();
+
+    let mut session = start_new()?;
+    let mut records = vec![JournalRecord::Forked {
+        origin: origin.clone(),
+    }];
+    records.extend(inserts(&messages));
+    session
+        .journal
+        .write_all(&records)
+        .map_err(|source| SessionError::storage("write forked session", &session.id, source))?;
+    session.messages = messages;
+    session.origin = Some(origin);
+    session.resumed = true;
+    Ok(session)
+}
+
+/// What the session picker shows for one saved session.
+pub(crate) struct SessionSummary {
+    pub(crate) id: SessionId,
+    pub(crate) origin: Option<ForkOrigin>,
+    pub(crate) last_prompt: String,
+    /// Turns that finished in the session.
+    pub(crate) turns: usize,
+}
+
+/// Lists saved sessions, newest first.
+pub(crate) fn list() -> Result<Vec<SessionSummary>, SessionError> {
+    let store = open_store("list sessions", None)?;
+    let query = || -> rusqlite::Result<Vec<SessionSummary>> {
+        let mut statement = store.conn().prepare(
+            "SELECT id, parent, fork_at,
+                (SELECT text FROM messages
+                 WHERE session = sessions.id AND role = ?1 ORDER BY idx DESC LIMIT 1),
+                (SELECT COUNT(*) FROM usage WHERE session = sessions.id)
+             FROM sessions ORDER BY modified DESC",
+        )?;
+        let rows = statement.query_map([journal::role_name(Role::User)], |row| {
+            let last_prompt = row
+                .get::<_, Option<String>>(4)?
+                .map(|text| text.lines().next().unwrap_or_default().to_string())
+                .unwrap_or_else(|| "(no messages)".to_string());
+            Ok(SessionSummary {
+                id: SessionId(row.get(0)?),
+                origin: origin(row.get(1)?, row.get(2)?),
+                last_prompt,
+                turns: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    };
+    query().map_err(|source| SessionError {
+        kind: SessionErrorKind::Storage,
+        operation: "list sessions",
+        session: None,
+        source: Some(source),
+    })
+}
+
+/// Makes sure sessions can be saved by opening the store for writing.
+/// Returns where it is.
+pub fn check_storage() -> Result<PathBuf, SessionError> {
+    let path = store::path().ok_or_else(no_data_dir)?;
+    Store::open_at(&path)
+        .and_then(|mut store| store.check_writable())
+        .map_err(|source| SessionError {
+            kind: SessionErrorKind::Storage,
+            operation: "write to session store",
+            session: None,
+            source: Some(source),
+        })?;
+    Ok(path)
+}
+
+/// Moves the JSONL journals sessions were kept in before the store into it,
+/// leaving the files where they are. Journals that cannot be read are left
+/// out rather than failing the upgrade.
+pub(crate) fn import_journals(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    let Some(dir) = dirs::data_dir().map(|dir| dir.join("loopcode").join("sessions")) else {
+        return Ok(());
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(());
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let Some(id) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".jsonl"))
+        else {
+            continue;
+        };
+        let Ok(replayed) = journal::replay(&path) else {
+            continue;
+        };
+        let modified = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .map_or_else(|_| store::now_millis(), store::to_millis);
+        journal::import(tx, &SessionId(id.to_string()), modified, &replayed)?;
+    }
+    Ok(())
+}
+
+/// An [`Insert`](JournalRecord::Insert) for each of `messages`, in order.
+fn inserts(messages: &[ChatMessage]) -> Vec<JournalRecord> {
+    messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| JournalRecord::Insert {
+            index,
+            message: message.clone(),
+        })
+        .collect()
+}
+
+fn origin(parent: Option<String>, at: Option<usize>) -> Option<ForkOrigin> {
+    Some(ForkOrigin {
+        parent: SessionId(parent?),
+        at: at?,
+    })
+}
+
+/// Messages loaded from disk cannot still be in flight.
+fn settle(message: &mut ChatMessage) {
+    match message.status {
+        MessageStatus::Streaming => message.status = MessageStatus::Truncated,
+        MessageStatus::Queued => message.status = MessageStatus::Done,
+        MessageStatus::Awaiting => message.status = MessageStatus::Interrupted,
+        _ => {}
+    }
+    if let Some(run) = &mut message.run {
+        run.finished = true;
+    }
+}
+
+fn open_store(operation: &'static str, session: Option<&SessionId>) -> Result<Store, SessionError> {
+    let path = store::path().ok_or_else(no_data_dir)?;
+    Store::open_at(&path).map_err(|source| SessionError {
+        kind: SessionErrorKind::Storage,
+        operation,
+        session: session.cloned(),
+        source: Some(source),
+    })
+}
+
+fn no_data_dir() -> SessionError {
+    SessionError {
+        kind: SessionErrorKind::Storage,
+        operation: "locate session store",
+        session: None,
+        source: None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionErrorKind {
+    NotFound,
+    /// The id asked for is not one a session could have.
+    InvalidId,
+    Storage,
+}
+
+#[derive(Debug)]
+pub struct SessionError {
+    kind: SessionErrorKind,
+    operation: &'static str,
+    session: Option<SessionId>,
+    /// The store's own error, when it gave one.
+    source: Option<rusqlite::Error>,
+}
+
+impl SessionError {
+    fn storage(operation: &'static str, session: &SessionId, source: rusqlite::Error) -> Self {
+        Self {
+            kind: SessionErrorKind::Storage,
+            operation,
+            session: Some(session.clone()),
+            source: Some(source),
+        }
+    }
+
+    pub fn kind(&self) -> SessionErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, &self.session) {
+            (SessionErrorKind::NotFound, Some(session)) => {
+                write!(f, "session {session} not found")
+            }
+            (SessionErrorKind::NotFound, None) => write!(f, "session not found"),
+            (SessionErrorKind::InvalidId, Some(session)) => {
+                write!(f, "`{session}` is not a session id")
+            }
+            (SessionErrorKind::InvalidId, None) => write!(f, "not a session id"),
+            (SessionErrorKind::Storage, session) => {
+                write!(f, "{} failed", self.operation)?;
+                if let Some(session) = session {
+                    write!(f, " for session {session}")?;
+                }
+                if let Some(source) = &self.source {
+                    write!(f, ": {source}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SessionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_plain_names_are_session_ids() {
+        assert!(SessionId::parse(&SessionId::generate().0).is_some());
+        for id in ["", "../x", "/abs", "a/b", "a\\b", ".", "x y"] {
+            assert_eq!(SessionId::parse(id), None, "{id:?}");
+        }
+    }
+}
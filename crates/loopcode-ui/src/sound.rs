@@ -0,0 +1,99 @@
+use std::str::FromStr;
+
+/// Something worth hearing about when looking elsewhere.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Cue {
+    /// A reply finished streaming.
+    Complete,
+    /// A draft from `/commit` or `/pr`, or a tool call, is waiting to be
+    /// approved.
+    Approval,
+    /// A reply, draft or fetch failed.
+    Error,
+}
+
+/// Which events ring the bell: the terminal bell under cpui, the system
+/// alert sound under gpui.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SoundCues {
+    pub complete: bool,
+    pub approval: bool,
+    pub error: bool,
+}
+
+impl SoundCues {
+    pub(crate) fn rings(&self, cue: Cue) -> bool {
+        match cue {
+            Cue::Complete => self.complete,
+            Cue::Approval => self.approval,
+            Cue::Error => self.error,
+        }
+    }
+
+    /// The events that ring, as [`FromStr`] reads them back.
+    pub(crate) fn label(&self) -> String {
+        let names = [
+            (self.complete, "complete"),
+            (self.approval, "approval"),
+            (self.error, "error"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect::<Vec<_>>();
+        if names.is_empty() {
+            "none".to_string()
+        } else {
+            names.join(",")
+        }
+    }
+}
+
+impl FromStr for SoundCues {
+    type Err = String;
+
+    /// `none`, `all`, or a comma-separated list of `complete`, `approval`
+    /// and `error`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut cues = Self::default();
+        for name in value.split(',').map(str::trim) {
+            match name {
+                "none" => {}
+                "all" => {
+                    cues = Self {
+                        complete: true,
+                        approval: true,
+                        error: true,
+                    }
+                }
+                "complete" => cues.complete = true,
+                "approval" => cues.approval = true,
+                "error" => cues.error = true,
+                _ => {
+                    return Err(format!(
+                        "`{name}` is not a sound cue; use complete, approval, error, all or none"
+                    ));
+                }
+            }
+        }
+        Ok(cues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cue_lists_parse_and_print_back() {
+        let cues = "complete, error".parse::<SoundCues>().unwrap();
+        assert!(cues.rings(Cue::Complete) && cues.rings(Cue::Error));
+        assert!(!cues.rings(Cue::Approval));
+        assert_eq!(cues.label(), "complete,error");
+        assert_eq!(
+            "all".parse::<SoundCues>().unwrap().label(),
+            "complete,approval,error"
+        );
+        assert_eq!("none".parse::<SoundCues>().unwrap(), SoundCues::default());
+        assert!("beep".parse::<SoundCues>().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Small bits of state that outlive a run, kept in `state.json` in the config
+/// directory. Losing it only repeats one-time screens, so failures to read or
+/// write it are ignored.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct AppState {
+    /// Newest release whose notes have been shown.
+    pub(crate) changelog_seen: Option<String>,
+    /// How the graphics window was left.
+    pub(crate) window: Option<SavedWindow>,
+}
+
+/// [`xpui::WindowState`] as it is written down.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(crate) struct SavedWindow {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    zoom: f32,
+}
+
+impl From<xpui::WindowState> for SavedWindow {
+    fn from(state: xpui::WindowState) -> Self {
+        let xpui::WindowState {
+            x,
+            y,
+            width,
+            height,
+            zoom,
+        } = state;
+        Self {
+            x,
+            y,
+            width,
+            height,
+            zoom,
+        }
+    }
+}
+
+impl From<SavedWindow> for xpui::WindowState {
+    fn from(saved: SavedWindow) -> Self {
+        let SavedWindow {
+            x,
+            y,
+            width,
+            height,
+            zoom,
+        } = saved;
+        Self {
+            x,
+            y,
+            width,
+            height,
+            zoom,
+        }
+    }
+}
+
+impl AppState {
+    pub(crate) fn load() -> Self {
+        path()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn save(&self) {
+        let Some(path) = path() else {
+            return;
+        };
+        let Ok(json) = serde_json::to_vec_pretty(self) else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, json);
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("loopcode").join("state.json"))
+}
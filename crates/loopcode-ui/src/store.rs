@@ -0,0 +1,211 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{Connection, Transaction, params};
+
+use crate::session;
+
+/// Schema changes in the order they were made. `PRAGMA user_version` holds
+/// how many a database has had, so each runs once.
+const MIGRATIONS: &[Migration] = &[
+    Migration::Sql(
+        "CREATE TABLE sessions (
+            id TEXT PRIMARY KEY,
+            parent TEXT,
+            fork_at INTEGER,
+            created INTEGER NOT NULL,
+            modified INTEGER NOT NULL
+        );
+        CREATE TABLE messages (
+            session TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+            idx INTEGER NOT NULL,
+            role TEXT NOT NULL,
+            text TEXT NOT NULL,
+            message TEXT NOT NULL
+        );
+        CREATE INDEX messages_by_session ON messages (session, idx);
+        CREATE TABLE usage (
+            session TEXT NOT NULL,
+            at INTEGER NOT NULL,
+            elapsed_ms INTEGER NOT NULL,
+            tool_calls INTEGER NOT NULL,
+            prompt_chars INTEGER NOT NULL,
+            reply_chars INTEGER NOT NULL
+        );
+        CREATE INDEX usage_by_session ON usage (session);
+        CREATE TABLE audit (
+            at INTEGER NOT NULL,
+            session TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            detail TEXT NOT NULL
+        );",
+    ),
+    // Sessions used to be one JSONL journal each; they stay on disk as they
+    // were.
+    Migration::Run(session::import_journals),
+    Migration::Sql(
+        "CREATE TABLE session_roots (
+            session TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+            idx INTEGER NOT NULL,
+            path TEXT NOT NULL
+        );",
+    ),
+    Migration::Sql("ALTER TABLE sessions ADD COLUMN target TEXT;"),
+    Migration::Sql("ALTER TABLE sessions ADD COLUMN run_env TEXT;"),
+];
+
+enum Migration {
+    Sql(&'static str),
+    Run(fn(&Transaction) -> rusqlite::Result<()>),
+}
+
+/// One turn, as `usage` records it.
+pub(crate) struct TurnUsage {
+    pub(crate) elapsed_ms: u64,
+    pub(crate) tool_calls: usize,
+    pub(crate) prompt_chars: usize,
+    pub(crate) reply_chars: usize,
+}
+
+/// The SQLite database in the data directory that holds sessions with their
+/// messages, workspace roots, execution target and environment, usage per
+/// turn and the audit trail. It runs in WAL mode, so a killed process loses
+/// at most the write in flight and readers, such as paging in older
+/// messages, never wait on the writer.
+pub(crate) struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub(crate) fn open_at(path: &Path) -> rusqlite::Result<Self> {
+        if let Some(dir) = path.parent() {
+            // Opening reports the failure with the path if this fails.
+            let _ = std::fs::create_dir_all(dir);
+        }
+        Self::setup(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::setup(Connection::open_in_memory()?)
+    }
+
+    fn setup(conn: Connection) -> rusqlite::Result<Self> {
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let mut store = Self { conn };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    fn migrate(&mut self) -> rusqlite::Result<()> {
+        let done: usize = self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+        for (version, migration) in MIGRATIONS.iter().enumerate().skip(done) {
+            let tx = self.conn.transaction()?;
+            match migration {
+                Migration::Sql(sql) => tx.execute_batch(sql)?,
+                Migration::Run(run) => run(&tx)?,
+            }
+            tx.pragma_update(None, "user_version", version + 1)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn conn(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub(crate) fn transaction(&mut self) -> rusqlite::Result<Transaction<'_>> {
+        self.conn.transaction()
+    }
+
+    /// Fails unless the database can be written to.
+    pub(crate) fn check_writable(&mut self) -> rusqlite::Result<()> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        tx.rollback()
+    }
+
+    pub(crate) fn record_usage(&self, session: &str, usage: &TurnUsage) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO usage (session, at, elapsed_ms, tool_calls, prompt_chars, reply_chars)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                session,
+                now_millis(),
+                usage.elapsed_ms,
+                usage.tool_calls,
+                usage.prompt_chars,
+                usage.reply_chars,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Notes something done on the user's behalf that they may want to look
+    /// back on, such as a mode change they did not make.
+    pub(crate) fn audit(&self, session: &str, kind: &str, detail: &str) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO audit (at, session, kind, detail) VALUES (?1, ?2, ?3, ?4)",
+            params![now_millis(), session, kind, detail],
+        )?;
+        Ok(())
+    }
+}
+
+/// Where the database lives.
+pub(crate) fn path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("loopcode").join("loopcode.db"))
+}
+
+/// Milliseconds since the epoch, as the store keeps times.
+pub(crate) fn now_millis() -> i64 {
+    to_millis(SystemTime::now())
+}
+
+pub(crate) fn to_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_run_once_and_usage_is_recorded() {
+        let mut store = Store::open_in_memory().unwrap();
+        store.migrate().unwrap();
+        let version: usize = store
+            .conn()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        let turn = TurnUsage {
+            elapsed_ms: 1200,
+            tool_calls: 2,
+            prompt_chars: 40,
+            reply_chars: 400,
+        };
+        store.record_usage("s1", &turn).unwrap();
+        store.record_usage("s1", &turn).unwrap();
+        store.audit("s1", "mode_downgrade", "git push").unwrap();
+        let (turns, calls): (i64, i64) = store
+            .conn()
+            .query_row(
+                "SELECT COUNT(*), SUM(tool_calls) FROM usage WHERE session = 's1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((turns, calls), (2, 4));
+        store.check_writable().unwrap();
+    }
+}
@@ -0,0 +1,403 @@
+use unicode_width::UnicodeWidthStr;
+
+/// Widest a column gets before its cells are cut.
+const MAX_COLUMN_WIDTH: usize = 40;
+/// Comma-separated prose is common; a comma table needs this many lines.
+const MIN_CSV_LINES: usize = 3;
+/// Rows shown at a time; longer tables turn pages.
+const PAGE_ROWS: usize = 20;
+
+/// How a message shows its CSV and TSV blocks as tables.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct TableView {
+    /// Column index and whether descending, or the order of the text.
+    pub(crate) sort: Option<(usize, bool)>,
+    /// Rows without this in any cell are hidden; case is ignored.
+    pub(crate) filter: String,
+    /// The page of [`PAGE_ROWS`] rows shown, counted after the filter.
+    pub(crate) page: usize,
+}
+
+impl TableView {
+    /// The next sort of `columns` columns: each column ascending then
+    /// descending, then back to the text's order.
+    pub(crate) fn next_sort(&self, columns: usize) -> Self {
+        let sort = match self.sort {
+            None => Some((0, false)),
+            Some((column, false)) => Some((column, true)),
+            Some((column, true)) if column + 1 < columns => Some((column + 1, false)),
+            Some(_) => None,
+        };
+        Self {
+            sort,
+            page: 0,
+            ..self.clone()
+        }
+    }
+
+    /// The sort after clicking the header of `column`: ascending, then
+    /// descending, then the text's order again.
+    pub(crate) fn sort_by(&self, column: usize) -> Self {
+        let sort = match self.sort {
+            Some((sorted, false)) if sorted == column => Some((column, true)),
+            Some((sorted, true)) if sorted == column => None,
+            _ => Some((column, false)),
+        };
+        Self {
+            sort,
+            page: 0,
+            ..self.clone()
+        }
+    }
+
+    /// The page after or before this one, staying within `pages`.
+    pub(crate) fn turn_page(&self, forward: bool, pages: usize) -> Self {
+        let page = if forward {
+            (self.page + 1).min(pages.saturating_sub(1))
+        } else {
+            self.page.saturating_sub(1)
+        };
+        Self {
+            page,
+            ..self.clone()
+        }
+    }
+
+    fn keeps(&self, row: &[String]) -> bool {
+        let filter = self.filter.to_lowercase();
+        row.iter().any(|cell| cell.to_lowercase().contains(&filter))
+    }
+}
+
+/// A block of delimited lines, the first of them the header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Table {
+    /// The lines of the text the table was read from.
+    lines: std::ops::Range<usize>,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub(crate) fn columns(&self) -> usize {
+        self.header.len()
+    }
+
+    /// The lines of the table under `view`. Alignment and widths come from
+    /// every row, so columns stay put while the filter changes.
+    fn render(&self, view: &TableView) -> Vec<String> {
+        let numeric = (0..self.columns())
+            .map(|column| {
+                self.rows
+                    .iter()
+                    .all(|row| row[column].trim().parse::<f64>().is_ok())
+            })
+            .collect::<Vec<_>>();
+        let widths = self.widths(view);
+        let mut rows = self
+            .rows
+            .iter()
+            .filter(|row| view.keeps(row))
+            .collect::<Vec<_>>();
+        if let Some((column, descending)) = view.sort.filter(|(column, _)| *column < self.columns())
+        {
+            rows.sort_by(|a, b| {
+                let order = if numeric[column] {
+                    let number = |cell: &str| cell.trim().parse::<f64>().unwrap_or_default();
+                    number(&a[column]).total_cmp(&number(&b[column]))
+                } else {
+                    a[column].cmp(&b[column])
+                };
+                if descending { order.reverse() } else { order }
+            });
+        }
+        let total = rows.len();
+        let page = view.page.min(pages(total) - 1);
+        let rows = rows
+            .into_iter()
+            .skip(page * PAGE_ROWS)
+            .take(PAGE_ROWS)
+            .collect::<Vec<_>>();
+        let header = self.header(view);
+        let line = |cells: &[String], numbers: bool| {
+            cells
+                .iter()
+                .enumerate()
+                .map(|(column, cell)| {
+                    let cell = fit(cell, widths[column]);
+                    let pad = " ".repeat(widths[column] - cell.width());
+                    if numbers && numeric[column] {
+                        format!("{pad}{cell}")
+                    } else {
+                        format!("{cell}{pad}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" │ ")
+                .trim_end()
+                .to_string()
+        };
+        let rule = widths
+            .iter()
+            .map(|width| "─".repeat(*width))
+            .collect::<Vec<_>>()
+            .join("─┼─");
+        let mut out = Vec::new();
+        if !view.filter.is_empty() {
+            out.push(format!(
+                "⌕ {} · {} of {} rows",
+                view.filter,
+                total,
+                self.rows.len()
+            ));
+        }
+        out.extend([line(&header, false), rule]);
+        let first = page * PAGE_ROWS + 1;
+        let last = page * PAGE_ROWS + rows.len();
+        out.extend(rows.into_iter().map(|row| line(row, true)));
+        if pages(total) > 1 {
+            out.push(format!(
+                "rows {first}–{last} of {total} · page {} of {} · ] next, [ previous",
+                page + 1,
+                pages(total)
+            ));
+        }
+        out
+    }
+
+    /// The pages the rows `view` keeps fill.
+    fn pages(&self, view: &TableView) -> usize {
+        pages(self.rows.iter().filter(|row| view.keeps(row)).count())
+    }
+
+    /// The header names, the sorted one marked with its direction. Every
+    /// name leaves room for a marker so sorting does not shift the columns.
+    fn header(&self, view: &TableView) -> Vec<String> {
+        self.header
+            .iter()
+            .enumerate()
+            .map(|(column, name)| match view.sort {
+                Some((sorted, false)) if sorted == column => format!("{name} ▲"),
+                Some((sorted, true)) if sorted == column => format!("{name} ▼"),
+                _ => format!("{name}  "),
+            })
+            .collect()
+    }
+
+    fn widths(&self, view: &TableView) -> Vec<usize> {
+        let header = self.header(view);
+        (0..self.columns())
+            .map(|column| {
+                self.rows
+                    .iter()
+                    .map(|row| row[column].width())
+                    .chain([header[column].width()])
+                    .max()
+                    .unwrap_or_default()
+                    .min(MAX_COLUMN_WIDTH)
+            })
+            .collect()
+    }
+
+    /// The column under display column `x` of the drawn header line.
+    fn column_at(&self, view: &TableView, x: usize) -> Option<usize> {
+        let mut end = 0;
+        self.widths(view).iter().position(|width| {
+            end += width + " │ ".width();
+            x < end
+        })
+    }
+}
+
+/// The tables in `text`: runs of two or more tab-separated lines, or three
+/// or more comma-separated ones, with as many fields on every line. Fenced
+/// code is left alone unless fenced as `csv` or `tsv`.
+pub(crate) fn find(text: &str) -> Vec<Table> {
+    let lines = text.split('\n').collect::<Vec<_>>();
+    let mut tables = Vec::new();
+    // Inside a fenced block, whether it is one fenced as a table.
+    let mut fenced = None;
+    let mut start = 0;
+    for (index, line) in lines.iter().enumerate().chain([(lines.len(), &"")]) {
+        let fence = line.trim_start().strip_prefix("```").map(str::trim);
+        let boundary = line.trim().is_empty() || fence.is_some();
+        if boundary {
+            if fenced.is_none_or(|table| table)
+                && let Some(table) = parse(&lines, start..index)
+            {
+                tables.push(table);
+            }
+            start = index + 1;
+        }
+        if let Some(lang) = fence {
+            fenced = match fenced {
+                Some(_) => None,
+                None => Some(matches!(lang, "csv" | "tsv")),
+            };
+        }
+    }
+    tables
+}
+
+/// `text` with each of its tables drawn as aligned columns.
+pub(crate) fn render(text: &str, tables: &[Table], view: &TableView) -> String {
+    let lines = text.split('\n').collect::<Vec<_>>();
+    let mut out = Vec::new();
+    let mut at = 0;
+    for table in tables {
+        out.extend(
+            lines[at..table.lines.start]
+                .iter()
+                .map(|line| line.to_string()),
+        );
+        // The role label goes in front of the first line; keep it off the
+        // header.
+        if table.lines.start == 0 {
+            out.push(String::new());
+        }
+        out.extend(table.render(view));
+        at = table.lines.end;
+    }
+    out.extend(lines[at..].iter().map(|line| line.to_string()));
+    out.join("\n")
+}
+
+/// The most pages any table in `text` fills under `view`; they all turn
+/// together.
+pub(crate) fn page_count(text: &str, view: &TableView) -> usize {
+    find(text)
+        .iter()
+        .map(|table| table.pages(view))
+        .max()
+        .unwrap_or(1)
+}
+
+/// The column whose header is under display column `x` of `line`, when
+/// `line` is the header of one of the tables in `text` drawn under `view`.
+pub(crate) fn header_column(text: &str, view: &TableView, line: &str, x: usize) -> Option<usize> {
+    find(text).iter().find_map(|table| {
+        // The filter row, when there is one, comes first.
+        let header = usize::from(!view.filter.is_empty());
+        (table.render(view)[header] == line).then(|| table.column_at(view, x))?
+    })
+}
+
+/// The table at the end of the lines in `range`, after any lead-in such as
+/// `Here it is:` on the lines before it.
+fn parse(lines: &[&str], range: std::ops::Range<usize>) -> Option<Table> {
+    let last = lines[range.clone()].last()?;
+    let (delimiter, min_lines) = if last.contains('\t') {
+        ('\t', 2)
+    } else {
+        (',', MIN_CSV_LINES)
+    };
+    let mut rows = lines[range.clone()]
+        .iter()
+        .map(|line| split(line, delimiter))
+        .collect::<Vec<_>>();
+    let columns = rows.last()?.len();
+    let lead_in = rows.iter().rposition(|row| row.len() != columns);
+    let start = lead_in.map_or(0, |at| at + 1);
+    if columns < 2 || rows.len() - start < min_lines {
+        return None;
+    }
+    let mut rows = rows.split_off(start).into_iter();
+    Some(Table {
+        lines: range.start + start..range.end,
+        header: rows.next()?,
+        rows: rows.collect(),
+    })
+}
+
+/// The fields of a line, with CSV quoting undone: `"a, b"` is one field and
+/// `""` inside quotes is a quote.
+fn split(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                quoted = true;
+                field.clear();
+            }
+            ch if ch == delimiter && !quoted => {
+                fields.push(field.trim().to_string());
+                field.clear();
+            }
+            ch => field.push(ch),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Pages for `rows` rows; an empty table still has its one.
+fn pages(rows: usize) -> usize {
+    rows.div_ceil(PAGE_ROWS).max(1)
+}
+
+/// `cell` cut to `width` columns, ending in `…` when cut.
+fn fit(cell: &str, width: usize) -> String {
+    if cell.width() <= width {
+        return cell.to_string();
+    }
+    let mut out = String::new();
+    for ch in cell.chars() {
+        if out.width() + ch.to_string().width() + 1 > width {
+            break;
+        }
+        out.push(ch);
+    }
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_blocks_render_as_sortable_filterable_tables() {
+        let text = "Here:\nname,age\n\"Smith, J\",31\nbo,4\n\nOne, two, three.";
+        let tables = find(text);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(
+            render(text, &tables, &TableView::default()),
+            "Here:\nname     │ age\n─────────┼──────\nSmith, J │    31\nbo       │     4\n\nOne, two, three."
+        );
+        let sorted = TableView::default().next_sort(2).next_sort(2);
+        assert_eq!(sorted.sort, Some((0, true)));
+        let tsv = "a\tn\nx\t10\ny\t9\nxy\t1";
+        assert_eq!(
+            render(tsv, &find(tsv), &sorted.next_sort(2)),
+            "\na   │ n ▲\n────┼────\nxy  │   1\ny   │   9\nx   │  10"
+        );
+        let view = TableView {
+            filter: "X".to_string(),
+            ..TableView::default().sort_by(1).sort_by(1)
+        };
+        assert_eq!(
+            render(tsv, &find(tsv), &view),
+            "\n⌕ X · 2 of 3 rows\na   │ n ▼\n────┼────\nx   │  10\nxy  │   1"
+        );
+        assert_eq!(header_column(tsv, &view, "a   │ n ▼", 6), Some(1));
+        assert_eq!(header_column(tsv, &view, "x   │  10", 5), None);
+        let long = (0..45)
+            .map(|row| format!("{row}\t."))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let view = TableView::default().turn_page(true, 3).turn_page(true, 3);
+        assert_eq!(page_count(&long, &view), 3);
+        let drawn = render(&long, &find(&long), &view.turn_page(true, 3));
+        assert_eq!(drawn.lines().count(), 1 + 2 + 4 + 1);
+        assert!(drawn.ends_with("rows 41–44 of 44 · page 3 of 3 · ] next, [ previous"));
+        assert!(find("```rust\na,b\nc,d\ne,f\n```").is_empty());
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::remote::RemoteRoot;
+use crate::workspace::Workspace;
+
+/// Where commands for the session run: the host shell, or a container they
+/// are exec'd into, such as the one a devcontainer set up. For a remote root
+/// the host is the root's machine, and so is the container's.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum ExecTarget {
+    #[default]
+    Host,
+    Container {
+        name: String,
+        /// The directory commands start in; the container's own when `None`.
+        workdir: Option<String>,
+    },
+}
+
+impl ExecTarget {
+    /// What the status bar shows; nothing for the host.
+    pub(crate) fn indicator(&self) -> Option<String> {
+        match self {
+            Self::Host => None,
+            Self::Container { name, .. } => Some(format!("⬢ {name}")),
+        }
+    }
+
+    /// `program` with `args` run for `root` on this target.
+    pub(crate) fn command(&self, root: &Path, program: &str, args: &[&str]) -> Command {
+        let Self::Container { name, workdir } = self else {
+            return match RemoteRoot::of(root) {
+                Some(remote) => remote.command(program, args),
+                None => {
+                    let mut command = Command::new(program);
+                    command.args(args).current_dir(root);
+                    command
+                }
+            };
+        };
+        let mut exec = vec!["exec", "-i"];
+        if let Some(workdir) = workdir {
+            exec.extend(["-w", workdir]);
+        }
+        exec.push(name);
+        exec.push(program);
+        exec.extend(args);
+        Self::Host.command(root, "docker", &exec)
+    }
+
+    /// As [`command`](Self::command), with `env` on top. Locally it is set on
+    /// the process; elsewhere the command line is wrapped to set it there.
+    pub(crate) fn command_in(
+        &self,
+        root: &Path,
+        env: &RunEnv,
+        program: &str,
+        args: &[&str],
+    ) -> Command {
+        if *self == Self::Host && RemoteRoot::of(root).is_none() {
+            let mut command = Command::new(program);
+            command
+                .args(args)
+                .envs(&env.vars)
+                .current_dir(match &env.cwd {
+                    Some(cwd) => root.join(cwd),
+                    None => root.to_path_buf(),
+                });
+            return command;
+        }
+        let line = env.wrap(program, args);
+        let line = line.iter().map(String::as_str).collect::<Vec<_>>();
+        self.command(root, line[0], &line[1..])
+    }
+
+    /// Runs `program` for `root` on this target, feeding it `stdin`, and
+    /// returns its output or its error message.
+    pub(crate) fn run(
+        &self,
+        root: &Path,
+        program: &str,
+        args: &[&str],
+        stdin: Option<&str>,
+    ) -> Result<String, String> {
+        self.run_in(root, &RunEnv::default(), program, args, stdin)
+    }
+
+    /// As [`run`](Self::run), with `env` on top.
+    pub(crate) fn run_in(
+        &self,
+        root: &Path,
+        env: &RunEnv,
+        program: &str,
+        args: &[&str],
+        stdin: Option<&str>,
+    ) -> Result<String, String> {
+        let mut child = self
+            .command_in(root, env, program, args)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("cannot run {program}: {err}"))?;
+        // Written on its own thread so a process that answers before it has
+        // read everything does not block on a full pipe while this one waits.
+        if let (Some(input), Some(mut pipe)) = (stdin.map(str::to_string), child.stdin.take()) {
+            thread::spawn(move || pipe.write_all(input.as_bytes()));
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|err| format!("{program} did not finish: {err}"))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(stderr.trim().to_string())
+        }
+    }
+}
+
+/// Variables and a starting directory the session's commands get on top of
+/// what their target gives them, such as a virtualenv on `PATH`, without
+/// touching the shell loopcode was started from. `/env` changes them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct RunEnv {
+    pub(crate) vars: BTreeMap<String, String>,
+    /// Where commands start, relative to where they would have.
+    pub(crate) cwd: Option<String>,
+}
+
+impl RunEnv {
+    /// What the Settings panel shows.
+    pub(crate) fn summary(&self) -> String {
+        let vars = match self.vars.len() {
+            0 => "inherited".to_string(),
+            1 => "1 variable".to_string(),
+            count => format!("{count} variables"),
+        };
+        match &self.cwd {
+            Some(cwd) => format!("{vars}, in {cwd}"),
+            None => vars,
+        }
+    }
+
+    /// What `/env` lists, a line each.
+    pub(crate) fn describe(&self) -> String {
+        if self.vars.is_empty() && self.cwd.is_none() {
+            return "commands run with the environment loopcode started with, from the root"
+                .to_string();
+        }
+        let mut out = "commands run with".to_string();
+        for (name, value) in &self.vars {
+            out.push_str(&format!("\n  {name}={value}"));
+        }
+        if let Some(cwd) = &self.cwd {
+            out.push_str(&format!("\n  in {cwd}"));
+        }
+        out
+    }
+
+    /// `program` with `args` as a command line that moves to `cwd` and sets
+    /// the variables first, for where the process itself cannot be set up.
+    fn wrap(&self, program: &str, args: &[&str]) -> Vec<String> {
+        let mut line = Vec::new();
+        if let Some(cwd) = &self.cwd {
+            let cd = r#"cd "$1" && shift && exec "$@""#;
+            line.extend(["sh", "-c", cd, "sh", cwd].map(str::to_string));
+        }
+        if !self.vars.is_empty() {
+            line.push("env".to_string());
+            line.extend(
+                self.vars
+                    .iter()
+                    .map(|(name, value)| format!("{name}={value}")),
+            );
+        }
+        line.push(program.to_string());
+        line.extend(args.iter().map(|arg| arg.to_string()));
+        line
+    }
+}
+
+/// A devcontainer set up for a root: what its config says, and the
+/// containers running for it.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct DevContainer {
+    /// Where the config was found, relative to the root.
+    pub(crate) config: Option<&'static str>,
+    pub(crate) name: Option<String>,
+    /// Where the root is mounted inside the container.
+    pub(crate) workdir: String,
+    pub(crate) running: Vec<String>,
+}
+
+impl DevContainer {
+    /// Reads the devcontainer config of `root`, and asks docker which
+    /// containers the devcontainer tools started for it.
+    pub(crate) fn detect(root: &Path) -> Self {
+        let mut found = Self {
+            workdir: format!("/workspaces/{}", Workspace::name(root)),
+            ..Self::default()
+        };
+        for config in [".devcontainer/devcontainer.json", ".devcontainer.json"] {
+            let text = match RemoteRoot::of(root) {
+                Some(_) => ExecTarget::Host.run(root, "cat", &[config], None).ok(),
+                None => fs::read_to_string(root.join(config)).ok(),
+            };
+            let Some(text) = text else {
+                continue;
+            };
+            found.config = Some(config);
+            if let Ok(serde_json::Value::Object(config)) =
+                serde_json::from_str::<serde_json::Value>(&strip_comments(&text))
+            {
+                let field = |key: &str| config.get(key)?.as_str().map(str::to_string);
+                found.name = field("name");
+                found.workdir = field("workspaceFolder").unwrap_or(found.workdir);
+            }
+            break;
+        }
+        // The tools label a container with the folder on the machine it runs
+        // on, which for a remote root is its directory there.
+        let folder = RemoteRoot::of(root).map_or(root, |remote| remote.dir);
+        let label = format!("label=devcontainer.local_folder={}", folder.display());
+        let names = ExecTarget::Host.run(
+            root,
+            "docker",
+            &["ps", "--filter", &label, "--format", "{{.Names}}"],
+            None,
+        );
+        found.running = names
+            .map(|names| names.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        found
+    }
+
+    /// The target to exec into its first running container.
+    pub(crate) fn target(&self) -> Option<ExecTarget> {
+        Some(ExecTarget::Container {
+            name: self.running.first()?.clone(),
+            workdir: Some(self.workdir.clone()),
+        })
+    }
+
+    /// What `/target` says about it.
+    pub(crate) fn describe(&self) -> String {
+        let Some(config) = self.config else {
+            return "no devcontainer config in the active root".to_string();
+        };
+        let name = self.name.as_deref().unwrap_or("devcontainer");
+        match self.running.as_slice() {
+            [] => format!("{name} ({config}) is not running; start it, then /target devcontainer"),
+            running => format!(
+                "{name} ({config}) is running as {}; /target devcontainer execs into it",
+                running.join(", ")
+            ),
+        }
+    }
+}
+
+/// `text`, JSON with comments as devcontainer configs are, without them.
+fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(ch) = chars.next() {
+        if in_string {
+            out.push(ch);
+            match ch {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (ch, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(ch);
+            }
+            ('/', Some('/')) => while chars.next_if(|&next| next != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for next in chars.by_ref() {
+                    if last == '*' && next == '/' {
+                        break;
+                    }
+                    last = next;
+                }
+            }
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn containers_are_exec_d_into_at_their_workdir() {
+        let target = ExecTarget::Container {
+            name: "app-dev".to_string(),
+            workdir: Some("/workspaces/app".to_string()),
+        };
+        let command = target.command(Path::new("/src/app"), "cargo", &["test"]);
+        assert_eq!(command.get_program(), "docker");
+        let args = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args,
+            [
+                "exec",
+                "-i",
+                "-w",
+                "/workspaces/app",
+                "app-dev",
+                "cargo",
+                "test"
+            ]
+        );
+        assert_eq!(target.indicator().as_deref(), Some("⬢ app-dev"));
+        assert_eq!(ExecTarget::Host.indicator(), None);
+
+        let config = r#"{
+            // The image is built from the Dockerfile.
+            "name": "App", /* shown in /target */
+            "workspaceFolder": "/work//app"
+        }"#;
+        let config: serde_json::Value = serde_json::from_str(&strip_comments(config)).unwrap();
+        assert_eq!(config["workspaceFolder"], "/work//app");
+    }
+
+    #[test]
+    fn run_env_is_set_on_the_process_or_wrapped_around_the_command() {
+        let env = RunEnv {
+            vars: BTreeMap::from([("VIRTUAL_ENV".to_string(), ".venv".to_string())]),
+            cwd: Some("api".to_string()),
+        };
+        let local = ExecTarget::Host.command_in(Path::new("/src/app"), &env, "pytest", &[]);
+        assert_eq!(local.get_current_dir(), Some(Path::new("/src/app/api")));
+        assert_eq!(local.get_envs().count(), 1);
+
+        let target = ExecTarget::Container {
+            name: "app-dev".to_string(),
+            workdir: None,
+        };
+        let command = target.command_in(Path::new("/src/app"), &env, "pytest", &["-q"]);
+        let args = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args[3..],
+            [
+                "sh",
+                "-c",
+                r#"cd "$1" && shift && exec "$@""#,
+                "sh",
+                "api",
+                "env",
+                "VIRTUAL_ENV=.venv",
+                "pytest",
+                "-q"
+            ]
+        );
+        assert_eq!(env.summary(), "1 variable, in api");
+    }
+}
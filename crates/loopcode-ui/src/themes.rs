@@ -0,0 +1,254 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Deserialize;
+
+/// How often an open theme file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Which palette the UI draws with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ThemePreference {
+    /// Follow the OS appearance, or the terminal background under cpui;
+    /// dark when neither is known.
+    #[default]
+    Auto,
+    Light,
+    Dark,
+    /// A theme file, `<name>.toml` in the themes directory.
+    Named(String),
+}
+
+impl FromStr for ThemePreference {
+    type Err = String;
+
+    /// `auto`, `light`, `dark`, or the name of a theme file.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "auto" => Self::Auto,
+            "light" => Self::Light,
+            "dark" => Self::Dark,
+            name if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') => {
+                return Err(format!("`{name}` is not a theme name"));
+            }
+            name => Self::Named(name.to_string()),
+        })
+    }
+}
+
+/// Where theme files live: `loopcode/themes` in the config directory.
+pub(crate) fn themes_dir() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("loopcode").join("themes"))
+}
+
+/// Names of the theme files in [`themes_dir`], sorted.
+pub(crate) fn available() -> Vec<String> {
+    let Some(entries) = themes_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut names = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "toml" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// What `/theme` alone says: the theme files there are and where to add one.
+pub(crate) fn listing() -> String {
+    let Some(dir) = themes_dir() else {
+        return "no config directory to load themes from".to_string();
+    };
+    let names = available();
+    if names.is_empty() {
+        return format!(
+            "no theme files in {}; /theme auto|light|dark picks a built-in one",
+            dir.display()
+        );
+    }
+    format!("themes in {}: {}", dir.display(), names.join(", "))
+}
+
+/// A theme file in use, reloaded when it changes so a theme can be tuned
+/// while looking at it.
+pub(crate) struct WatchedTheme {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    checked_at: Instant,
+    theme: xpui::Theme,
+}
+
+impl WatchedTheme {
+    pub(crate) fn open(name: &str) -> Result<Self, String> {
+        let dir = themes_dir().ok_or("no config directory to load themes from")?;
+        let path = dir.join(format!("{name}.toml"));
+        let modified = modified(&path);
+        let theme = load(&path)?;
+        Ok(Self {
+            path,
+            modified,
+            checked_at: Instant::now(),
+            theme,
+        })
+    }
+
+    pub(crate) fn theme(&self) -> &xpui::Theme {
+        &self.theme
+    }
+
+    /// Reloads the file if it changed since the last look. A broken edit
+    /// keeps the last good theme and returns why it did not load, once per
+    /// save.
+    pub(crate) fn poll(&mut self) -> Option<String> {
+        if self.checked_at.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.checked_at = Instant::now();
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        match load(&self.path) {
+            Ok(theme) => {
+                self.theme = theme;
+                None
+            }
+            Err(err) => Some(err),
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn load(path: &Path) -> Result<xpui::Theme, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("cannot read theme {}: {err}", path.display()))?;
+    parse(&text).map_err(|err| format!("theme {}: {err}", path.display()))
+}
+
+/// A theme file: every role of [`xpui::Theme`] as `"#rrggbb"`, all optional,
+/// over the `base` palette.
+///
+/// ```toml
+/// base = "light"
+/// accent = "#8250df"
+///
+/// [syntax]
+/// keyword = "#8250df"
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    #[serde(default)]
+    base: Base,
+    text: Option<Color>,
+    muted: Option<Color>,
+    faint: Option<Color>,
+    surface: Option<Color>,
+    border: Option<Color>,
+    accent: Option<Color>,
+    selection: Option<Color>,
+    selection_text: Option<Color>,
+    badge: Option<Color>,
+    badge_text: Option<Color>,
+    added: Option<Color>,
+    removed: Option<Color>,
+    #[serde(default)]
+    syntax: SyntaxFile,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SyntaxFile {
+    keyword: Option<Color>,
+    string: Option<Color>,
+    comment: Option<Color>,
+    number: Option<Color>,
+    function: Option<Color>,
+    type_name: Option<Color>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Base {
+    #[default]
+    Dark,
+    Light,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+struct Color(xpui::Rgb);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .map(|rgb| Self(xpui::Rgb(rgb)))
+            .ok_or_else(|| format!("`{value}` is not a #rrggbb color"))
+    }
+}
+
+fn parse(text: &str) -> Result<xpui::Theme, String> {
+    let file = toml::from_str::<ThemeFile>(text).map_err(|err| err.message().to_string())?;
+    let mut theme = match file.base {
+        Base::Dark => xpui::Theme::dark(),
+        Base::Light => xpui::Theme::light(),
+    };
+    let roles = [
+        (&mut theme.text, file.text),
+        (&mut theme.muted, file.muted),
+        (&mut theme.faint, file.faint),
+        (&mut theme.surface, file.surface),
+        (&mut theme.border, file.border),
+        (&mut theme.accent, file.accent),
+        (&mut theme.selection, file.selection),
+        (&mut theme.selection_text, file.selection_text),
+        (&mut theme.badge, file.badge),
+        (&mut theme.badge_text, file.badge_text),
+        (&mut theme.added, file.added),
+        (&mut theme.removed, file.removed),
+        (&mut theme.syntax.keyword, file.syntax.keyword),
+        (&mut theme.syntax.string, file.syntax.string),
+        (&mut theme.syntax.comment, file.syntax.comment),
+        (&mut theme.syntax.number, file.syntax.number),
+        (&mut theme.syntax.function, file.syntax.function),
+        (&mut theme.syntax.type_name, file.syntax.type_name),
+    ];
+    for (slot, color) in roles {
+        if let Some(Color(rgb)) = color {
+            *slot = rgb;
+        }
+    }
+    Ok(theme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_files_override_their_base() {
+        let theme = parse("base = \"light\"\naccent = \"#8250df\"\n[syntax]\nnumber = \"#000000\"")
+            .unwrap();
+        assert_eq!(theme.accent, xpui::Rgb(0x8250df));
+        assert_eq!(theme.syntax.number, xpui::Rgb(0));
+        assert_eq!(theme.text, xpui::Theme::light().text);
+        assert!(parse("accent = \"blue\"").unwrap_err().contains("#rrggbb"));
+        assert!(parse("acent = \"#000000\"").is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use crate::bookmarks::truncate;
+use crate::screens::FocusKey;
+use crate::watch::FileTracker;
+use crate::workspace::Workspace;
+
+/// How often the workspace is checked for edited files.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Files bigger than this are not source anyone annotates by hand.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+/// How much of a file is looked at for a NUL byte, which marks it binary.
+const BINARY_SNIFF_BYTES: usize = 8192;
+const TAGS: [&str; 3] = ["TODO", "FIXME", "HACK"];
+
+/// A TODO, FIXME or HACK comment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct TodoItem {
+    /// As [`Workspace::shorten`] shows it.
+    pub(crate) path: String,
+    pub(crate) line: usize,
+    pub(crate) tag: &'static str,
+    pub(crate) text: String,
+}
+
+impl TodoItem {
+    fn label(&self) -> String {
+        format!("{}:{} {}: {}", self.path, self.line, self.tag, self.text)
+    }
+}
+
+pub(crate) enum TodoOutcome {
+    Pending,
+    Close,
+    /// A prompt asking the agent to take on the chosen items.
+    Send(String),
+}
+
+/// What one look over the workspace found: the items of each file added or
+/// edited since the look before, and the files gone.
+struct TodoUpdate {
+    read: Vec<(PathBuf, Vec<TodoItem>)>,
+    removed: Vec<PathBuf>,
+}
+
+/// The TODO comments in the workspace, shown in place of the history. The
+/// files are looked at on another thread, and only the ones edited since
+/// the last look are read again.
+pub(crate) struct TodoPanel {
+    files: BTreeMap<PathBuf, Vec<TodoItem>>,
+    updates: Receiver<TodoUpdate>,
+    /// Whether the first look is done.
+    loaded: bool,
+    selected: usize,
+    /// Items marked with Space, by path and line.
+    marked: HashSet<(String, usize)>,
+}
+
+impl TodoPanel {
+    /// Starts looking through `workspace`; the items show as they are found.
+    /// The looking stops when the panel is dropped.
+    pub(crate) fn open(workspace: Workspace) -> Self {
+        let (tx, updates) = mpsc::channel();
+        let mut tracker = FileTracker::new(workspace.roots().to_vec(), "**/*".to_string());
+        thread::spawn(move || {
+            loop {
+                let (modified, removed) = tracker.changes();
+                let read = modified
+                    .into_iter()
+                    .map(|path| {
+                        let items = read_items(&path, &workspace.shorten(&path));
+                        (path, items)
+                    })
+                    .collect();
+                if tx.send(TodoUpdate { read, removed }).is_err() {
+                    return;
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+        Self {
+            files: BTreeMap::new(),
+            updates,
+            loaded: false,
+            selected: 0,
+            marked: HashSet::new(),
+        }
+    }
+
+    /// Takes in what the last looks found.
+    pub(crate) fn poll(&mut self) {
+        for update in self.updates.try_iter() {
+            self.loaded = true;
+            for path in update.removed {
+                self.files.remove(&path);
+            }
+            self.files.extend(update.read);
+        }
+        self.selected = self.selected.min(self.len().saturating_sub(1));
+    }
+
+    /// The selected item, by path and line.
+    pub(crate) fn focus_key(&self) -> Option<FocusKey> {
+        let item = self.items().nth(self.selected)?;
+        Some(FocusKey::Todo {
+            path: item.path.clone(),
+            line: item.line,
+        })
+    }
+
+    /// Selects the item `key` names, if it is still there.
+    pub(crate) fn restore_focus(&mut self, key: &FocusKey) {
+        let FocusKey::Todo { path, line } = key else {
+            return;
+        };
+        let found = self
+            .items()
+            .position(|item| item.path == *path && item.line == *line);
+        if let Some(at) = found {
+            self.selected = at;
+        }
+    }
+
+    fn items(&self) -> impl Iterator<Item = &TodoItem> {
+        self.files.values().flatten()
+    }
+
+    fn len(&self) -> usize {
+        self.files.values().map(Vec::len).sum()
+    }
+
+    pub(crate) fn handle_key(&mut self, key: xpui::UiKeyInput) -> TodoOutcome {
+        match key {
+            xpui::UiKeyInput::Up => self.selected = self.selected.saturating_sub(1),
+            xpui::UiKeyInput::Down => {
+                self.selected = (self.selected + 1).min(self.len().saturating_sub(1));
+            }
+            xpui::UiKeyInput::Char(' ') => {
+                let selected = self.items().nth(self.selected);
+                if let Some(key) = selected.map(|item| (item.path.clone(), item.line))
+                    && !self.marked.remove(&key)
+                {
+                    self.marked.insert(key);
+                }
+            }
+            xpui::UiKeyInput::Enter | xpui::UiKeyInput::Submit => {
+                return self.prompt().map_or(TodoOutcome::Close, TodoOutcome::Send);
+            }
+            xpui::UiKeyInput::Esc | xpui::UiKeyInput::Char('q') => return TodoOutcome::Close,
+            _ => {}
+        }
+        TodoOutcome::Pending
+    }
+
+    /// The marked items as tasks, or the selected one when none is marked.
+    fn prompt(&self) -> Option<String> {
+        let marked = self
+            .items()
+            .filter(|item| self.marked.contains(&(item.path.clone(), item.line)))
+            .collect::<Vec<_>>();
+        let items = if marked.is_empty() {
+            vec![self.items().nth(self.selected)?]
+        } else {
+            marked
+        };
+        let mut prompt = String::from("Resolve these comments left in the code:");
+        for item in items {
+            prompt.push_str("\n- ");
+            prompt.push_str(&item.label());
+        }
+        Some(prompt)
+    }
+
+    /// One line per item, each cut to `width` columns, and the offset that
+    /// keeps the selected one within `viewport` lines.
+    pub(crate) fn render(&self, width: usize, viewport: u16) -> (String, u16) {
+        let count = self.len();
+        if count == 0 && !self.loaded {
+            return ("Looking for TODO, FIXME and HACK comments…".to_string(), 0);
+        }
+        if count == 0 {
+            return ("No TODO, FIXME or HACK comments here.".to_string(), 0);
+        }
+        let mut out = format!(
+            "{count} TODOs in {} files · Space mark · Enter send · Esc close",
+            self.files
+                .values()
+                .filter(|items| !items.is_empty())
+                .count()
+        );
+        for (row, item) in self.items().enumerate() {
+            let marker = if row == self.selected { "▶" } else { " " };
+            let check = if self.marked.contains(&(item.path.clone(), item.line)) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            out.push('\n');
+            out.push_str(&truncate(
+                &format!("{marker} {check} {}", item.label()),
+                width,
+            ));
+        }
+        // The header takes the first line.
+        let offset = (self.selected + 2).saturating_sub(usize::from(viewport));
+        (out, offset as u16)
+    }
+}
+
+/// The items in the file at `path`; none for a big or binary file.
+fn read_items(path: &std::path::Path, relative: &str) -> Vec<TodoItem> {
+    let too_big = fs::metadata(path).map_or(true, |meta| meta.len() > MAX_FILE_BYTES);
+    let bytes = if too_big { None } else { fs::read(path).ok() };
+    let text = bytes
+        .filter(|bytes| !bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0))
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+    let Some(text) = text else {
+        return Vec::new();
+    };
+    text.lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let (tag, text) = find_tag(line)?;
+            Some(TodoItem {
+                path: relative.to_string(),
+                line: index + 1,
+                tag,
+                text: text.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The tag a comment on `line` starts with and what follows it, as in
+/// `// TODO: text`, `# FIXME(who): text` or ` * HACK text`.
+fn find_tag(line: &str) -> Option<(&'static str, &str)> {
+    let trimmed = line.trim_start();
+    let comment = if trimmed.starts_with('*') {
+        trimmed
+    } else {
+        let start = ["//", "#", "/*", "--"]
+            .iter()
+            .filter_map(|marker| line.find(marker))
+            .min()?;
+        &line[start..]
+    };
+    let body = comment.trim_start_matches(['/', '*', '#', '-', '!', ' ', '\t']);
+    let tag = TAGS.into_iter().find(|tag| body.starts_with(tag))?;
+    let rest = &body[tag.len()..];
+    if rest.starts_with(|ch: char| ch.is_alphanumeric() || ch == '_') {
+        return None;
+    }
+    // Skip an owner like `(name)` and the separator.
+    let rest = match rest.strip_prefix('(') {
+        Some(owned) => owned.split_once(')').map_or(owned, |(_, rest)| rest),
+        None => rest,
+    };
+    let text = rest.trim_start_matches([':', ' ', '\t']).trim_end();
+    let text = text.strip_suffix("*/").unwrap_or(text).trim_end();
+    Some((tag, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_found_in_comments_only() {
+        assert_eq!(
+            find_tag("    // TODO: split this"),
+            Some(("TODO", "split this"))
+        );
+        assert_eq!(
+            find_tag("x = 1  # FIXME(ranol): off by one"),
+            Some(("FIXME", "off by one"))
+        );
+        assert_eq!(
+            find_tag(" * HACK until gpui ships it */"),
+            Some(("HACK", "until gpui ships it"))
+        );
+        assert_eq!(find_tag("/* TODO */"), Some(("TODO", "")));
+        assert_eq!(find_tag("let todo = \"TODO: not a comment\";"), None);
+        assert_eq!(find_tag("// TODOS are elsewhere"), None);
+    }
+}
@@ -0,0 +1,590 @@
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+use crate::provider::ToolSpec;
+use crate::remote::RemoteRoot;
+use crate::target::{ExecTarget, RunEnv};
+
+/// Longest tool output sent back to the model; the rest is cut with a note
+/// saying how much.
+const MAX_OUTPUT: usize = 16_000;
+
+/// How long a tool may run before it is stopped and the model told so.
+pub(crate) const TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How often a worker looks whether its tool's process has ended.
+const WAIT_POLL: Duration = Duration::from_millis(20);
+
+/// Where a tool runs: the active root, on the session's target and with
+/// its environment, as the session's other commands do.
+#[derive(Clone, Debug)]
+pub struct ToolContext {
+    pub(crate) root: PathBuf,
+    pub(crate) target: ExecTarget,
+    pub(crate) env: RunEnv,
+    /// The process the tool is waiting on, where [`RunningTool::kill`]
+    /// finds it.
+    pub(crate) process: Arc<Mutex<Option<Child>>>,
+}
+
+/// What a finished process gave: its status and both streams as text.
+struct Finished {
+    status: ExitStatus,
+    stdout: String,
+    stderr: String,
+}
+
+impl ToolContext {
+    /// The workspace root the call is for. It may be an `ssh://` root, so
+    /// reach files through [`run`](Self::run) rather than the local disk.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Runs `program` in the root on the session's target, feeding it
+    /// `stdin`, and waits for it where it can be killed.
+    fn output(
+        &self,
+        program: &str,
+        args: &[&str],
+        stdin: Option<&str>,
+    ) -> Result<Finished, String> {
+        let mut child = self
+            .target
+            .command_in(&self.root, &self.env, program, args)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("cannot run {program}: {err}"))?;
+        // Streams are drained on their own threads so a chatty process does
+        // not block on a full pipe while this one waits.
+        let drain = |pipe: Option<Box<dyn Read + Send>>| {
+            thread::spawn(move || {
+                let mut out = Vec::new();
+                if let Some(mut pipe) = pipe {
+                    let _ = pipe.read_to_end(&mut out);
+                }
+                String::from_utf8_lossy(&out).into_owned()
+            })
+        };
+        let stdout = drain(child.stdout.take().map(|pipe| Box::new(pipe) as _));
+        let stderr = drain(child.stderr.take().map(|pipe| Box::new(pipe) as _));
+        if let (Some(input), Some(mut pipe)) = (stdin.map(str::to_string), child.stdin.take()) {
+            thread::spawn(move || pipe.write_all(input.as_bytes()));
+        }
+        *self.process.lock().unwrap_or_else(PoisonError::into_inner) = Some(child);
+        let status = loop {
+            let mut process = self.process.lock().unwrap_or_else(PoisonError::into_inner);
+            let Some(child) = process.as_mut() else {
+                return Err(format!("{program} was stopped"));
+            };
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    *process = None;
+                    break status;
+                }
+                Ok(None) => {}
+                Err(err) => return Err(format!("{program} did not finish: {err}")),
+            }
+            drop(process);
+            thread::sleep(WAIT_POLL);
+        };
+        Ok(Finished {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+
+    /// Runs `program` in the root on the session's target, feeding it
+    /// `stdin`, and gives its stdout when it succeeds and its error message
+    /// when not. Interrupting the reply kills it.
+    pub fn run(&self, program: &str, args: &[&str], stdin: Option<&str>) -> Result<String, String> {
+        let finished = self.output(program, args, stdin)?;
+        if finished.status.success() {
+            Ok(finished.stdout)
+        } else {
+            Err(finished.stderr.trim().to_string())
+        }
+    }
+}
+
+/// A tool call under way on its worker thread.
+pub(crate) struct RunningTool {
+    output: Receiver<Result<String, String>>,
+    process: Arc<Mutex<Option<Child>>>,
+    started: Instant,
+}
+
+impl RunningTool {
+    /// What the tool gave back, once it has. A tool that has run past
+    /// [`TIMEOUT`] is killed and gives an error instead.
+    pub(crate) fn poll(&self) -> Option<Result<String, String>> {
+        match self.output.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Disconnected) => {
+                Some(Err("the tool stopped without an answer".to_string()))
+            }
+            Err(TryRecvError::Empty) if self.started.elapsed() >= TIMEOUT => {
+                self.kill();
+                Some(Err(format!(
+                    "stopped after {} seconds without finishing",
+                    TIMEOUT.as_secs()
+                )))
+            }
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+
+    /// Kills the tool's process, if it has one running.
+    pub(crate) fn kill(&self) {
+        let mut process = self.process.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(mut child) = process.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Something the model can ask to have done. `run` blocks, so each call
+/// runs on a worker thread.
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    /// JSON Schema of the arguments.
+    fn parameters(&self) -> Value;
+    /// It can change files or run anything, so Safest asks first.
+    fn mutates(&self) -> bool;
+    /// The output for the model, or why there is none.
+    fn run(&self, context: &ToolContext, arguments: &Value) -> Result<String, String>;
+
+    /// What a call would do, for the user deciding on it: a line, and what
+    /// it would change under it when that is worth seeing.
+    fn describe(&self, arguments: &Value) -> String {
+        format!("{} {arguments}", self.name())
+    }
+}
+
+/// The tools the model is offered. The default is the built-in `shell`,
+/// `read_file`, `write_file` and `grep`; an embedder adds its own or takes
+/// some away.
+#[derive(Clone)]
+pub struct Tools {
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+impl Tools {
+    /// No tools at all, for a chat that only talks.
+    pub fn none() -> Self {
+        Self { tools: Vec::new() }
+    }
+
+    /// These tools and `tool`, which takes the place of one with its name.
+    pub fn with(mut self, tool: impl Tool + 'static) -> Self {
+        self.tools.retain(|other| other.name() != tool.name());
+        self.tools.push(Arc::new(tool));
+        self
+    }
+
+    /// These tools but the one called `name`.
+    pub fn without(mut self, name: &str) -> Self {
+        self.tools.retain(|tool| tool.name() != name);
+        self
+    }
+
+    pub(crate) fn find(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.tools.iter().find(|tool| tool.name() == name).cloned()
+    }
+
+    /// Every tool, described for a request.
+    pub(crate) fn specs(&self) -> Vec<ToolSpec> {
+        self.tools
+            .iter()
+            .map(|tool| ToolSpec {
+                name: tool.name().to_string(),
+                description: tool.description().to_string(),
+                parameters: tool.parameters(),
+            })
+            .collect()
+    }
+
+    /// What the call of `name` with JSON `arguments` would do, as its tool
+    /// describes it.
+    pub(crate) fn describe(&self, name: &str, arguments: &str) -> String {
+        match (self.find(name), serde_json::from_str::<Value>(arguments)) {
+            (Some(tool), Ok(arguments)) => tool.describe(&arguments),
+            _ => format!("{name} {arguments}"),
+        }
+    }
+}
+
+impl Default for Tools {
+    fn default() -> Self {
+        Self::none()
+            .with(Shell)
+            .with(ReadFile)
+            .with(WriteFile)
+            .with(Grep)
+    }
+}
+
+impl fmt::Debug for Tools {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.tools.iter().map(|tool| tool.name()))
+            .finish()
+    }
+}
+
+/// Runs `tool` with JSON `arguments` on a worker thread. The output is cut
+/// to what is worth sending back.
+pub(crate) fn start(tool: Arc<dyn Tool>, context: ToolContext, arguments: String) -> RunningTool {
+    let (tx, rx) = mpsc::channel();
+    let process = context.process.clone();
+    thread::spawn(move || {
+        let result = serde_json::from_str::<Value>(&arguments)
+            .map_err(|err| format!("arguments are not JSON: {err}"))
+            .and_then(|arguments| tool.run(&context, &arguments))
+            .map(|output| truncate(&output))
+            .map_err(|err| truncate(&err));
+        let _ = tx.send(result);
+    });
+    RunningTool {
+        output: rx,
+        process,
+        started: Instant::now(),
+    }
+}
+
+fn truncate(output: &str) -> String {
+    let total = output.chars().count();
+    if total <= MAX_OUTPUT {
+        return output.to_string();
+    }
+    let kept = output.chars().take(MAX_OUTPUT).collect::<String>();
+    format!("{kept}\n… {} more characters cut", total - MAX_OUTPUT)
+}
+
+/// The string argument `name`, which the call must have.
+fn string<'a>(arguments: &'a Value, name: &str) -> Result<&'a str, String> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("missing string argument `{name}`"))
+}
+
+/// The path argument `name`, which must stay under the root: relative,
+/// not climbing out with `..` and, on this machine, not leading out through
+/// a symlink either.
+fn confined<'a>(
+    context: &ToolContext,
+    arguments: &'a Value,
+    name: &str,
+) -> Result<&'a str, String> {
+    let path = string(arguments, name)?;
+    let outside = || format!("{path} is outside the workspace root");
+    let mut depth = 0usize;
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return Err(outside()),
+        }
+    }
+    if context.target == ExecTarget::Host && RemoteRoot::of(&context.root).is_none() {
+        let root = context
+            .root
+            .canonicalize()
+            .map_err(|err| format!("cannot resolve the root: {err}"))?;
+        // What does not exist yet cannot be a link; check where it starts.
+        let mut existing = context.root.join(path);
+        while !existing.exists() && existing.pop() {}
+        if existing
+            .canonicalize()
+            .is_ok_and(|real| !real.starts_with(&root))
+        {
+            return Err(outside());
+        }
+    }
+    Ok(path)
+}
+
+fn schema(properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+struct Shell;
+
+impl Tool for Shell {
+    fn name(&self) -> &'static str {
+        "shell"
+    }
+
+    fn description(&self) -> &'static str {
+        "Run a shell command in the workspace root and get its output and exit status."
+    }
+
+    fn parameters(&self) -> Value {
+        schema(
+            json!({ "command": { "type": "string", "description": "The command line for sh -c" } }),
+            &["command"],
+        )
+    }
+
+    fn mutates(&self) -> bool {
+        true
+    }
+
+    fn run(&self, context: &ToolContext, arguments: &Value) -> Result<String, String> {
+        let command = string(arguments, "command")?;
+        let output = context.output("sh", &["-c", command], None)?;
+        let mut text = output.stdout;
+        text.push_str(&output.stderr);
+        match output.status.code() {
+            Some(0) => {}
+            Some(code) => text.push_str(&format!("\n(exit status {code})")),
+            None => text.push_str("\n(killed by a signal)"),
+        }
+        Ok(text)
+    }
+
+    fn describe(&self, arguments: &Value) -> String {
+        match string(arguments, "command") {
+            Ok(command) => format!("$ {command}"),
+            Err(_) => format!("shell {arguments}"),
+        }
+    }
+}
+
+struct ReadFile;
+
+impl Tool for ReadFile {
+    fn name(&self) -> &'static str {
+        "read_file"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read a text file, by its path relative to the workspace root."
+    }
+
+    fn parameters(&self) -> Value {
+        schema(json!({ "path": { "type": "string" } }), &["path"])
+    }
+
+    fn mutates(&self) -> bool {
+        false
+    }
+
+    fn run(&self, context: &ToolContext, arguments: &Value) -> Result<String, String> {
+        let path = confined(context, arguments, "path")?;
+        context
+            .run("cat", &["--", path], None)
+            .map_err(|err| format!("cannot read {path}: {err}"))
+    }
+}
+
+struct WriteFile;
+
+impl Tool for WriteFile {
+    fn name(&self) -> &'static str {
+        "write_file"
+    }
+
+    fn description(&self) -> &'static str {
+        "Write a whole text file, by its path relative to the workspace root, \
+         creating it and its directories as needed."
+    }
+
+    fn parameters(&self) -> Value {
+        schema(
+            json!({
+                "path": { "type": "string" },
+                "content": { "type": "string" },
+            }),
+            &["path", "content"],
+        )
+    }
+
+    fn mutates(&self) -> bool {
+        true
+    }
+
+    fn run(&self, context: &ToolContext, arguments: &Value) -> Result<String, String> {
+        let path = confined(context, arguments, "path")?;
+        let content = string(arguments, "content")?;
+        let script = r#"mkdir -p -- "$(dirname -- "$1")" && cat > "$1""#;
+        context
+            .run("sh", &["-c", script, "sh", path], Some(content))
+            .map_err(|err| format!("cannot write {path}: {err}"))?;
+        Ok(format!("wrote {} lines to {path}", content.lines().count()))
+    }
+
+    fn describe(&self, arguments: &Value) -> String {
+        match (string(arguments, "path"), string(arguments, "content")) {
+            (Ok(path), Ok(content)) => {
+                let mut summary = format!("write {} lines to {path}", content.lines().count());
+                for line in content.lines() {
+                    summary.push_str("\n  ");
+                    summary.push_str(line);
+                }
+                summary
+            }
+            _ => format!("write_file {arguments}"),
+        }
+    }
+}
+
+struct Grep;
+
+impl Tool for Grep {
+    fn name(&self) -> &'static str {
+        "grep"
+    }
+
+    fn description(&self) -> &'static str {
+        "Search files under a directory of the workspace for a regular expression \
+         and get the matching lines with their paths and line numbers."
+    }
+
+    fn parameters(&self) -> Value {
+        schema(
+            json!({
+                "pattern": { "type": "string", "description": "An extended regular expression" },
+                "path": { "type": "string", "description": "Where to search; the root by default" },
+            }),
+            &["pattern"],
+        )
+    }
+
+    fn mutates(&self) -> bool {
+        false
+    }
+
+    fn run(&self, context: &ToolContext, arguments: &Value) -> Result<String, String> {
+        let pattern = string(arguments, "pattern")?;
+        let path = match arguments.get("path") {
+            Some(_) => confined(context, arguments, "path")?,
+            None => ".",
+        };
+        let args = ["-rnIE", "--exclude-dir=.git", "--", pattern, path];
+        match context.run("grep", &args, None) {
+            Ok(matches) => Ok(matches),
+            // grep says nothing and exits 1 when nothing matched.
+            Err(err) if err.is_empty() => Ok("no matches".to_string()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tools_write_read_and_search_the_root() {
+        let root = tempfile::tempdir().unwrap();
+        let context = ToolContext {
+            root: root.path().to_path_buf(),
+            target: ExecTarget::Host,
+            env: RunEnv::default(),
+            process: Default::default(),
+        };
+        let tools = Tools::default();
+        let call = |name: &str, arguments: Value| {
+            tools
+                .find(name)
+                .unwrap()
+                .run(&context, &arguments)
+                .map(|output| output.trim().to_string())
+        };
+
+        let wrote = call(
+            "write_file",
+            json!({ "path": "src/a.txt", "content": "one\ntwo\n" }),
+        );
+        assert_eq!(wrote.unwrap(), "wrote 2 lines to src/a.txt");
+        assert_eq!(
+            call("read_file", json!({ "path": "src/a.txt" })).unwrap(),
+            "one\ntwo"
+        );
+        assert_eq!(
+            call("grep", json!({ "pattern": "tw" })).unwrap(),
+            "./src/a.txt:2:two"
+        );
+        assert_eq!(
+            call("grep", json!({ "pattern": "three" })).unwrap(),
+            "no matches"
+        );
+        assert_eq!(
+            call("shell", json!({ "command": "exit 3" })).unwrap(),
+            "(exit status 3)"
+        );
+        assert!(call("read_file", json!({})).is_err());
+        for path in ["../outside.txt", "src/../../outside.txt", "/etc/hostname"] {
+            let escaped = call("read_file", json!({ "path": path }));
+            assert_eq!(
+                escaped,
+                Err(format!("{path} is outside the workspace root"))
+            );
+        }
+        assert!(call("write_file", json!({ "path": "/tmp/x", "content": "" })).is_err());
+        assert!(call("grep", json!({ "pattern": "x", "path": ".." })).is_err());
+        assert!(call("read_file", json!({ "path": "src/../src/a.txt" })).is_ok());
+        assert!(tools.find("list_files").is_none());
+        assert!(tools.clone().without("shell").find("shell").is_none());
+        assert_eq!(tools.describe("shell", r#"{"command":"ls -a"}"#), "$ ls -a");
+        assert_eq!(tools.describe("list_files", "{}"), "list_files {}");
+        assert_eq!(
+            tools.describe("write_file", r#"{"path":"a.txt","content":"one\ntwo\n"}"#),
+            "write 2 lines to a.txt\n  one\n  two"
+        );
+    }
+
+    #[test]
+    fn killing_a_running_tool_stops_its_process() {
+        let context = ToolContext {
+            root: std::env::temp_dir(),
+            target: ExecTarget::Host,
+            env: RunEnv::default(),
+            process: Default::default(),
+        };
+        let process = context.process.clone();
+        let running = start(
+            Arc::new(Shell),
+            context,
+            r#"{"command":"sleep 30"}"#.to_string(),
+        );
+        let started = Instant::now();
+        while process.lock().unwrap().is_none() {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::sleep(WAIT_POLL);
+        }
+        running.kill();
+        let result = loop {
+            if let Some(result) = running.poll() {
+                break result;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::sleep(WAIT_POLL);
+        };
+        assert_eq!(result, Err("sh was stopped".to_string()));
+    }
+}
@@ -0,0 +1,211 @@
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::metrics::Metrics;
+use crate::prompts::PromptHistory;
+use crate::session::{Session, SessionError};
+use crate::watch;
+
+/// Opens the session the chat starts in, off the UI thread.
+pub(crate) type OpenSession = Box<dyn FnOnce() -> Result<Session, SessionError> + Send>;
+
+type Task = Box<dyn FnOnce() -> Warmed + Send>;
+
+/// What a warmup task hands the chat when it finishes.
+pub(crate) enum Warmed {
+    Config {
+        prompts: PromptHistory,
+        metrics: Metrics,
+    },
+    Session(Result<Session, SessionError>),
+    /// The files under the workspace roots, for the Files panel.
+    Index(Vec<PathBuf>),
+}
+
+impl Warmed {
+    /// The line the splash shows for it, or why it failed.
+    fn outcome(&self) -> Result<String, String> {
+        match self {
+            Self::Config { .. } => Ok(String::new()),
+            Self::Session(Ok(session)) if session.resumed => Ok(format!(
+                "{} messages",
+                session.messages.len() + session.paged_out
+            )),
+            Self::Session(Ok(session)) => Ok(format!("new session {}", session.id)),
+            Self::Session(Err(err)) => Err(format!("{err}; this session will not be saved")),
+            Self::Index(files) => Ok(format!("{} files", files.len())),
+        }
+    }
+}
+
+enum StepState {
+    Running,
+    Done(String),
+    Failed(String),
+}
+
+struct Step {
+    label: &'static str,
+    state: StepState,
+    took: Duration,
+}
+
+/// The work done before the chat shows, each task on its own thread so
+/// none waits on another. The splash lists them until all are done; a
+/// failed one does not hold up the rest.
+pub(crate) struct Warmup {
+    steps: Vec<Step>,
+    results: mpsc::Receiver<(usize, Warmed)>,
+    started: Instant,
+}
+
+impl Warmup {
+    /// Loads the prompt history and metrics, opens the session and lists
+    /// the files under `roots`.
+    pub(crate) fn start(
+        shared_prompts: bool,
+        open_session: OpenSession,
+        roots: Vec<PathBuf>,
+    ) -> Self {
+        let config: Task = Box::new(move || Warmed::Config {
+            prompts: PromptHistory::open(shared_prompts),
+            metrics: Metrics::open(),
+        });
+        let session: Task = Box::new(move || Warmed::Session(open_session()));
+        let index: Task = Box::new(move || {
+            Warmed::Index(
+                roots
+                    .iter()
+                    .flat_map(|root| watch::scan(root, "**/*").into_keys())
+                    .collect(),
+            )
+        });
+        Self::run(vec![
+            ("Load config", config),
+            ("Restore session", session),
+            ("Index files", index),
+        ])
+    }
+
+    fn run(tasks: Vec<(&'static str, Task)>) -> Self {
+        let (tx, results) = mpsc::channel();
+        let steps = tasks
+            .into_iter()
+            .enumerate()
+            .map(|(index, (label, task))| {
+                let tx = tx.clone();
+                std::thread::spawn(move || {
+                    let _ = tx.send((index, task()));
+                });
+                Step {
+                    label,
+                    state: StepState::Running,
+                    took: Duration::ZERO,
+                }
+            })
+            .collect();
+        Self {
+            steps,
+            results,
+            started: Instant::now(),
+        }
+    }
+
+    /// What finished since the last poll. A task that died without an
+    /// answer is marked failed.
+    pub(crate) fn poll(&mut self) -> Vec<Warmed> {
+        let mut warmed = Vec::new();
+        loop {
+            match self.results.try_recv() {
+                Ok((index, result)) => {
+                    let step = &mut self.steps[index];
+                    step.took = self.started.elapsed();
+                    step.state = match result.outcome() {
+                        Ok(detail) => StepState::Done(detail),
+                        Err(err) => StepState::Failed(err),
+                    };
+                    warmed.push(result);
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    for step in &mut self.steps {
+                        if matches!(step.state, StepState::Running) {
+                            step.state = StepState::Failed(format!(
+                                "{} stopped unexpectedly",
+                                step.label.to_lowercase()
+                            ));
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+        warmed
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        !self
+            .steps
+            .iter()
+            .any(|step| matches!(step.state, StepState::Running))
+    }
+
+    /// Why the tasks that failed did, for banners over the chat.
+    pub(crate) fn failures(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter_map(|step| match &step.state {
+                StepState::Failed(err) => Some(format!("{}: {err}", step.label)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The splash: a line per task, ticked off as they finish.
+    pub(crate) fn render(&self) -> String {
+        let mut text = format!("loopcode {}\n", env!("CARGO_PKG_VERSION"));
+        for step in &self.steps {
+            let line = match &step.state {
+                StepState::Running => format!("  … {}", step.label),
+                StepState::Done(detail) => {
+                    format!(
+                        "  ✓ {:<16}{:>6} ms  {detail}",
+                        step.label,
+                        step.took.as_millis()
+                    )
+                }
+                StepState::Failed(err) => format!("  ✖ {:<16}{err}", step.label),
+            };
+            text.push('\n');
+            text.push_str(line.trim_end());
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_tasks_finish_the_warmup_and_become_banners() {
+        let index: Task = Box::new(|| Warmed::Index(vec![PathBuf::from("a.rs")]));
+        let dead: Task = Box::new(|| panic!("no session"));
+        let mut warmup = Warmup::run(vec![("Index files", index), ("Restore session", dead)]);
+        let mut warmed = Vec::new();
+        while !warmup.is_done() {
+            warmed.extend(warmup.poll());
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(matches!(warmed.as_slice(), [Warmed::Index(files)] if files.len() == 1));
+        assert_eq!(
+            warmup.failures(),
+            ["Restore session: restore session stopped unexpectedly"]
+        );
+        let splash = warmup.render();
+        assert!(splash.contains("✓ Index files"), "{splash}");
+        assert!(splash.contains("1 files"), "{splash}");
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::git;
+use crate::remote::RemoteRoot;
+use crate::target::ExecTarget;
+
+/// How often the watched files are looked at.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long changes have to stop before the prompt runs, so saving several
+/// files or a formatter rewriting them runs it once.
+const SETTLE: Duration = Duration::from_millis(800);
+/// Directories never looked into, whatever the pattern says, outside a git
+/// repository; inside one, what git ignores is left out instead.
+const SKIPPED_DIRS: [&str; 3] = [".git", "target", "node_modules"];
+/// Lists a remote root's files with their modification times in seconds,
+/// the ones git tracks or would when it is a repository.
+const REMOTE_LIST: &str = "{ git ls-files -z --cached --others --exclude-standard 2>/dev/null \
+    || find . -type d \\( -name .git -o -name target -o -name node_modules \\) -prune \
+       -o -type f -print0; } | xargs -0 stat -c '%Y %n' 2>/dev/null; exit 0";
+
+/// One pass over the watched files: how many match, and which of them
+/// changed since the pass before.
+struct Scanned {
+    files: usize,
+    changed: Vec<PathBuf>,
+}
+
+/// A prompt that `/watch` sends again whenever files matching a glob change.
+/// The files are looked at on another thread, so a large tree or a remote
+/// root does not hold up the UI.
+pub(crate) struct Watch {
+    root: PathBuf,
+    pattern: String,
+    prompt: String,
+    scans: Receiver<Scanned>,
+    /// How many files match, once the first pass is done.
+    files: Option<usize>,
+    /// Files changed since the last run, and when the latest change was seen.
+    changed: Vec<PathBuf>,
+    changed_at: Option<Instant>,
+}
+
+impl Watch {
+    /// Starts watching `pattern`, relative to `root`. Only later changes run
+    /// the prompt. The watching stops when this is dropped.
+    pub(crate) fn start(root: PathBuf, pattern: String, prompt: String) -> Self {
+        let (tx, scans) = mpsc::channel();
+        let mut tracker = FileTracker::new(vec![root.clone()], pattern.clone());
+        thread::spawn(move || {
+            // The first look only sets what later changes are measured from.
+            tracker.changes();
+            let mut changed = Vec::new();
+            loop {
+                let scanned = Scanned {
+                    files: tracker.len(),
+                    changed,
+                };
+                if tx.send(scanned).is_err() {
+                    return;
+                }
+                thread::sleep(POLL_INTERVAL);
+                let (modified, removed) = tracker.changes();
+                changed = modified;
+                changed.extend(removed);
+            }
+        });
+        Self {
+            root,
+            pattern,
+            prompt,
+            scans,
+            files: None,
+            changed: Vec::new(),
+            changed_at: None,
+        }
+    }
+
+    pub(crate) fn prompt(&self) -> &str {
+        &self.prompt
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        let files = match self.files {
+            Some(files) => format!("{files} files"),
+            None => "listing files".to_string(),
+        };
+        format!(
+            "watching `{}` ({files}) to send: {}",
+            self.pattern, self.prompt
+        )
+    }
+
+    /// The files that changed, relative to the root, once they have settled
+    /// and the prompt should run again.
+    pub(crate) fn poll(&mut self) -> Option<Vec<String>> {
+        for scanned in self.scans.try_iter() {
+            self.files = Some(scanned.files);
+            if scanned.changed.is_empty() {
+                continue;
+            }
+            for path in scanned.changed {
+                if !self.changed.contains(&path) {
+                    self.changed.push(path);
+                }
+            }
+            self.changed_at = Some(Instant::now());
+        }
+        if self.changed_at?.elapsed() < SETTLE {
+            return None;
+        }
+        self.changed_at = None;
+        let mut changed = std::mem::take(&mut self.changed);
+        changed.sort();
+        let relative = |path: PathBuf| match path.strip_prefix(&self.root) {
+            Ok(relative) => relative.display().to_string(),
+            Err(_) => path.display().to_string(),
+        };
+        Some(changed.into_iter().map(relative).collect())
+    }
+}
+
+/// The files matching a glob under some roots as last seen, to tell what
+/// changed since. Every look reads the whole tree, so it belongs on a worker
+/// thread.
+pub(crate) struct FileTracker {
+    roots: Vec<PathBuf>,
+    pattern: String,
+    files: HashMap<PathBuf, SystemTime>,
+}
+
+impl FileTracker {
+    /// Tracks `pattern` under `roots`; the first look finds every file new.
+    pub(crate) fn new(roots: Vec<PathBuf>, pattern: String) -> Self {
+        Self {
+            roots,
+            pattern,
+            files: HashMap::new(),
+        }
+    }
+
+    /// How many files matched at the last look.
+    pub(crate) fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Looks again: the files added or modified since the last look, and
+    /// the ones gone.
+    pub(crate) fn changes(&mut self) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let next = self
+            .roots
+            .iter()
+            .flat_map(|root| scan(root, &self.pattern))
+            .collect::<HashMap<_, _>>();
+        let modified = next
+            .iter()
+            .filter(|(path, modified)| self.files.get(*path) != Some(modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        let removed = self
+            .files
+            .keys()
+            .filter(|path| !next.contains_key(*path))
+            .cloned()
+            .collect();
+        self.files = next;
+        (modified, removed)
+    }
+}
+
+/// Modification times of the files under `root` that match `pattern`,
+/// leaving out what git ignores. A remote root is listed on its host. This
+/// reads the whole tree, so it belongs off the UI thread.
+pub(crate) fn scan(root: &Path, pattern: &str) -> HashMap<PathBuf, SystemTime> {
+    if RemoteRoot::of(root).is_some() {
+        return scan_remote(root, pattern);
+    }
+    let listed = git::run(
+        "git",
+        root,
+        &[
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ],
+        None,
+    );
+    if let Ok(listed) = listed {
+        return listed
+            .split('\0')
+            .filter(|relative| !relative.is_empty() && glob_matches(pattern, relative))
+            .filter_map(|relative| {
+                let path = root.join(relative);
+                let modified = fs::metadata(&path).ok()?.modified().ok()?;
+                Some((path, modified))
+            })
+            .collect();
+    }
+    // Start in the deepest directory the pattern names without wildcards.
+    let segments = pattern.split('/').collect::<Vec<_>>();
+    let base = segments[..segments.len() - 1]
+        .iter()
+        .take_while(|segment| !segment.contains(['*', '?']))
+        .copied()
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut files = HashMap::new();
+    walk(root, &root.join(&base), pattern, &mut files);
+    files
+}
+
+fn walk(root: &Path, dir: &Path, pattern: &str, files: &mut HashMap<PathBuf, SystemTime>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            let name = entry.file_name();
+            if !SKIPPED_DIRS.iter().any(|skipped| name == *skipped) {
+                walk(root, &path, pattern, files);
+            }
+            continue;
+        }
+        let Some(relative) = path.strip_prefix(root).ok().and_then(Path::to_str) else {
+            continue;
+        };
+        if glob_matches(pattern, &relative.replace('\\', "/"))
+            && let Ok(modified) = meta.modified()
+        {
+            files.insert(path, modified);
+        }
+    }
+}
+
+/// As [`scan`], on the host of the remote `root`, to the second.
+fn scan_remote(root: &Path, pattern: &str) -> HashMap<PathBuf, SystemTime> {
+    let Ok(listed) = ExecTarget::Host.run(root, "sh", &["-c", REMOTE_LIST], None) else {
+        return HashMap::new();
+    };
+    listed
+        .lines()
+        .filter_map(|line| {
+            let (seconds, relative) = line.split_once(' ')?;
+            let relative = relative.strip_prefix("./").unwrap_or(relative);
+            if !glob_matches(pattern, relative) {
+                return None;
+            }
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds.parse().ok()?);
+            Some((root.join(relative), modified))
+        })
+        .collect()
+}
+
+/// Whether the `/`-separated `path` matches `pattern`, where `*` and `?`
+/// stay within one path segment and `**` spans any number of them.
+pub(crate) fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.split('/').collect::<Vec<_>>();
+    let path = path.split('/').collect::<Vec<_>>();
+    segments_match(&pattern, &path)
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, None) => true,
+        (Some((&"**", rest)), _) => {
+            segments_match(rest, path) || (!path.is_empty() && segments_match(pattern, &path[1..]))
+        }
+        (Some((segment, rest)), Some((name, path))) => {
+            let segment = segment.chars().collect::<Vec<_>>();
+            let name = name.chars().collect::<Vec<_>>();
+            segment_matches(&segment, &name) && segments_match(rest, path)
+        }
+        _ => false,
+    }
+}
+
+fn segment_matches(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some(('*', rest)), _) => {
+            segment_matches(rest, name)
+                || (!name.is_empty() && segment_matches(pattern, &name[1..]))
+        }
+        (Some(('?', rest)), Some((_, name))) => segment_matches(rest, name),
+        (Some((expected, rest)), Some((actual, name))) => {
+            expected == actual && segment_matches(rest, name)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_within_and_across_segments() {
+        assert!(glob_matches("src/*.rs", "src/main.rs"));
+        assert!(!glob_matches("src/*.rs", "src/chat/mod.rs"));
+        assert!(glob_matches("src/**/*.rs", "src/main.rs"));
+        assert!(glob_matches("src/**/*.rs", "src/chat/mod.rs"));
+        assert!(glob_matches("**/test?.py", "a/b/test1.py"));
+        assert!(!glob_matches("**/test?.py", "a/b/test10.py"));
+        assert!(glob_matches("Cargo.toml", "Cargo.toml"));
+    }
+}
@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+use crate::remote::RemoteRoot;
+
+/// The directories a session works in, such as a frontend and a backend
+/// repository, one of them active. Commands that act on a repository use the
+/// active root; paths are shown relative to whichever root holds them, after
+/// its name once there is more than one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Workspace {
+    roots: Vec<PathBuf>,
+    active: usize,
+}
+
+impl Workspace {
+    pub(crate) fn new(root: PathBuf) -> Self {
+        Self {
+            roots: vec![root],
+            active: 0,
+        }
+    }
+
+    /// A workspace of `roots`, the first active; `None` when there are none.
+    pub(crate) fn from_roots(roots: Vec<PathBuf>) -> Option<Self> {
+        (!roots.is_empty()).then_some(Self { roots, active: 0 })
+    }
+
+    pub(crate) fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    pub(crate) fn active(&self) -> &Path {
+        &self.roots[self.active]
+    }
+
+    /// The active root's place among them, from 1, and how many there are.
+    pub(crate) fn position(&self) -> (usize, usize) {
+        (self.active + 1, self.roots.len())
+    }
+
+    /// What a root is called in commands and paths: its directory name.
+    pub(crate) fn name(root: &Path) -> String {
+        root.file_name().map_or_else(
+            || root.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// Adds the directory at `path`, relative to the active root unless
+    /// absolute, and makes it active. `host:/dir` that is not a local path
+    /// adds a directory on `host` over SSH.
+    pub(crate) fn add(&mut self, path: &str) -> Result<&Path, String> {
+        let local = paths::expand_home(path);
+        let root = match self.active().join(&local).canonicalize() {
+            Ok(root) if !root.is_dir() => {
+                return Err(format!(
+                    "cannot add root {}: not a directory",
+                    root.display()
+                ));
+            }
+            Ok(root) => root,
+            Err(err) => RemoteRoot::parse(path)
+                .ok_or_else(|| format!("cannot add root {}: {err}", local.display()))?,
+        };
+        if let Some(at) = self.roots.iter().position(|known| *known == root) {
+            self.active = at;
+            return Err(format!("{} is already a root", root.display()));
+        }
+        let name = Self::name(&root);
+        if self.find(&name).is_some() {
+            return Err(format!("there is already a root called {name}"));
+        }
+        self.roots.push(root);
+        self.active = self.roots.len() - 1;
+        Ok(self.active())
+    }
+
+    /// Drops the root called `name`. The last root stays.
+    pub(crate) fn remove(&mut self, name: &str) -> Result<PathBuf, String> {
+        let at = self.find(name).ok_or_else(|| self.unknown(name))?;
+        if self.roots.len() == 1 {
+            return Err("the workspace needs at least one root".to_string());
+        }
+        let root = self.roots.remove(at);
+        if self.active > at || self.active == self.roots.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        Ok(root)
+    }
+
+    /// Makes the root called `name` active.
+    pub(crate) fn switch(&mut self, name: &str) -> Result<&Path, String> {
+        self.active = self.find(name).ok_or_else(|| self.unknown(name))?;
+        Ok(self.active())
+    }
+
+    /// Makes the next root active, wrapping around.
+    pub(crate) fn cycle(&mut self) {
+        self.active = (self.active + 1) % self.roots.len();
+    }
+
+    /// Where a path typed as `partial` starts from: the root it names first,
+    /// as `backend/src`, or else the active root.
+    pub(crate) fn base(&self, partial: &str) -> (&Path, usize) {
+        if self.roots.len() > 1
+            && let Some((first, _)) = partial.split_once('/')
+            && let Some(at) = self.find(first)
+            && at != self.active
+            // A directory of the active root by that name comes first.
+            && !self.active().join(first).is_dir()
+        {
+            return (&self.roots[at], first.len() + 1);
+        }
+        (self.active(), 0)
+    }
+
+    /// How the status bar and `/roots` show `root`: `host:dir` for a remote
+    /// one.
+    pub(crate) fn label(root: &Path) -> String {
+        match RemoteRoot::of(root) {
+            Some(remote) => remote.label(),
+            None => paths::shorten(root, None),
+        }
+    }
+
+    /// `path` as the UI shows it: relative to the root that holds it,
+    /// prefixed with that root's name when there are several. Paths on the
+    /// host of a remote root count as in it.
+    pub(crate) fn shorten(&self, path: &Path) -> String {
+        let root = self
+            .roots
+            .iter()
+            .map(|root| RemoteRoot::of(root).map_or(root.as_path(), |remote| remote.dir))
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count());
+        match root {
+            Some(root) if self.roots.len() > 1 => {
+                let relative = paths::shorten(path, Some(root));
+                let name = Self::name(root);
+                if relative == "." {
+                    name
+                } else {
+                    format!("{name}/{relative}")
+                }
+            }
+            Some(root) => paths::shorten(path, Some(root)),
+            None => paths::shorten(path, None),
+        }
+    }
+
+    /// What `/roots` shows.
+    pub(crate) fn listing(&self) -> String {
+        let mut out = "workspace roots (switch with /root <name>, click the status bar to cycle):"
+            .to_string();
+        for (at, root) in self.roots.iter().enumerate() {
+            let marker = if at == self.active { "●" } else { " " };
+            out.push_str(&format!(
+                "\n{marker} {} {}",
+                Self::name(root),
+                Self::label(root)
+            ));
+        }
+        out
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.roots.iter().position(|root| Self::name(root) == name)
+    }
+
+    fn unknown(&self, name: &str) -> String {
+        let names = self
+            .roots
+            .iter()
+            .map(|root| Self::name(root))
+            .collect::<Vec<_>>();
+        format!("no root called {name}; roots: {}", names.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roots_are_added_switched_and_shown_by_name() {
+        let base = tempfile::tempdir().unwrap();
+        let (front, back) = (base.path().join("front"), base.path().join("back"));
+        std::fs::create_dir_all(front.join("src")).unwrap();
+        std::fs::create_dir_all(&back).unwrap();
+        let front = front.canonicalize().unwrap();
+        let back = back.canonicalize().unwrap();
+
+        let mut workspace = Workspace::new(front.clone());
+        assert_eq!(workspace.shorten(&front.join("src/app.ts")), "src/app.ts");
+        assert_eq!(workspace.add("../back").unwrap(), back);
+        assert!(workspace.add(back.to_str().unwrap()).is_err());
+        assert_eq!(workspace.position(), (2, 2));
+        assert_eq!(
+            workspace.shorten(&front.join("src/app.ts")),
+            "front/src/app.ts"
+        );
+        assert_eq!(workspace.shorten(&back), "back");
+        assert_eq!(workspace.base("front/src/a"), (front.as_path(), 6));
+        assert_eq!(workspace.base("src/a"), (back.as_path(), 0));
+
+        workspace.switch("front").unwrap();
+        assert_eq!(workspace.active(), front);
+        assert!(workspace.switch("nope").is_err());
+        workspace.remove("back").unwrap();
+        assert!(workspace.remove("front").is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::node::Node;
+use crate::runtime::FocusKind;
+
+/// Converted subtrees by the content hash of the nodes they came from. Each
+/// frame keeps what it used and drops the rest, so the cache holds about one
+/// frame's worth and a subtree that did not change is not converted again.
+pub(crate) struct ElementCache<T> {
+    previous: HashMap<u64, T>,
+    current: HashMap<u64, T>,
+}
+
+impl<T> Default for ElementCache<T> {
+    fn default() -> Self {
+        Self {
+            previous: HashMap::new(),
+            current: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone> ElementCache<T> {
+    pub(crate) fn get(&mut self, hash: u64) -> Option<T> {
+        if let Some(hit) = self.current.get(&hash) {
+            return Some(hit.clone());
+        }
+        let hit = self.previous.remove(&hash)?;
+        self.current.insert(hash, hit.clone());
+        Some(hit)
+    }
+
+    pub(crate) fn insert(&mut self, hash: u64, converted: T) {
+        self.current.insert(hash, converted);
+    }
+
+    /// Ends a frame; what it did not use goes with the next one.
+    pub(crate) fn finish_frame(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+}
+
+/// A hash of `value` and `salt`, which stands for what a conversion reads
+/// besides the node: the width it wraps to and the theme.
+pub(crate) fn content_hash(value: &impl Hash, salt: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    salt.hash(&mut hasher);
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The salt for a frame converted at `viewport_columns` with the current
+/// theme.
+pub(crate) fn frame_salt(viewport_columns: usize) -> u64 {
+    content_hash(&crate::theme(), viewport_columns as u64)
+}
+
+/// The content hash of every subtree of a tree, taken in one pass, and read
+/// back in pre-order as the conversion walks the same tree.
+pub(crate) struct SubtreeHashes {
+    /// Each node's hash and how many nodes its subtree spans.
+    entries: Vec<(u64, usize)>,
+    next: usize,
+}
+
+impl SubtreeHashes {
+    pub(crate) fn new(root: &Node, salt: u64) -> Self {
+        let mut entries = Vec::new();
+        fill(root, salt, &mut entries);
+        Self { entries, next: 0 }
+    }
+
+    /// The hash of the next node, moving on to its first child.
+    pub(crate) fn enter(&mut self) -> u64 {
+        let (hash, _) = self.entries[self.next];
+        self.next += 1;
+        hash
+    }
+
+    /// Moves past the children of the node just entered, as for a subtree
+    /// taken from the cache.
+    pub(crate) fn skip_children(&mut self) {
+        let (_, span) = self.entries[self.next - 1];
+        self.next += span - 1;
+    }
+}
+
+/// Hashes what converting `node` reads, its children by their own hashes.
+/// The keys and labels of boxes are left out: they do not change the output.
+/// Their focus ids do when a backend puts them on the element, for buttons
+/// and boxes with a hover style, so two such rows that look the same are
+/// still told apart.
+fn fill(node: &Node, salt: u64, out: &mut Vec<(u64, usize)>) -> u64 {
+    let slot = out.len();
+    out.push((0, 1));
+    let mut hasher = DefaultHasher::new();
+    salt.hash(&mut hasher);
+    std::mem::discriminant(node).hash(&mut hasher);
+    match node {
+        Node::Stack(stack) => {
+            (
+                stack.axis,
+                stack.gap,
+                stack.justify_center,
+                stack.items_center,
+            )
+                .hash(&mut hasher);
+            for child in &stack.children {
+                fill(child, salt, out).hash(&mut hasher);
+            }
+        }
+        Node::Container(container) => {
+            (&container.style, &container.hover_style).hash(&mut hasher);
+            if container.focus_kind == FocusKind::Button || container.hover_style.is_some() {
+                (container.focus_kind, container.focus_id).hash(&mut hasher);
+            }
+            fill(&container.child, salt, out).hash(&mut hasher);
+        }
+        Node::ScrollView(scroll) => {
+            (scroll.viewport_lines, scroll.offset_lines).hash(&mut hasher);
+            fill(&scroll.child, salt, out).hash(&mut hasher);
+        }
+        Node::Overlay(overlay) => fill(&overlay.child, salt, out).hash(&mut hasher),
+        Node::RichText(text) => text.hash(&mut hasher),
+        Node::Icon(icon) => icon.hash(&mut hasher),
+        Node::TextInput(input) => input.hash(&mut hasher),
+        Node::Empty => {}
+    }
+    let hash = hasher.finish();
+    out[slot] = (hash, out.len() - slot);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FocusId, IntoNode, button, column};
+
+    #[test]
+    fn rows_that_differ_only_by_focus_id_hash_apart() {
+        let rows = column()
+            .child(button("Open", FocusId(1)))
+            .child(button("Open", FocusId(2)))
+            .into_node();
+        let mut hashes = SubtreeHashes::new(&rows, 0);
+        hashes.enter();
+        let first = hashes.enter();
+        hashes.skip_children();
+        assert_ne!(first, hashes.enter());
+    }
+}
@@ -1,9 +1,15 @@
+use std::sync::Arc;
+
 use cpui::{AppContext, IntoElement};
 
+use super::cache::{ElementCache, SubtreeHashes, frame_salt};
 use crate::{
     backend::Backend,
-    node::{Axis, Icon, IconName, Node, RichText, TextInput},
-    runtime::{FocusEntry, FocusNavOutcome, UiApp, UiInputEvent, UiKeyInput, WindowSize},
+    node::{Axis, FocusId, Icon, IconName, Node, RichText, TextInput},
+    runtime::{
+        BackendClipboard, ExitReport, FocusEntry, FocusKind, FocusNavOutcome, RunError, UiApp,
+        UiInputEvent, UiKeyInput, UiMouseButton, WindowSize, click_button,
+    },
     style::{Rgb, TextStyle},
 };
 
@@ -13,11 +19,14 @@ impl Backend for CpuiBackend {
     type Output = cpui::AnyElement;
 
     fn render_node(&mut self, node: Node) -> Self::Output {
-        node_to_cpui(node, 80)
+        node_to_cpui(node, 80, &mut ElementCache::default())
     }
 }
 
-pub(crate) fn run_cpui<A: UiApp + 'static>(app: A, size: WindowSize) {
+pub(crate) fn run_cpui<A: UiApp + 'static>(
+    app: A,
+    size: WindowSize,
+) -> Result<ExitReport, RunError> {
     struct HostEntity<A: UiApp + 'static>(cpui::Entity<Host<A>>);
 
     impl<A: UiApp + 'static> Clone for HostEntity<A> {
@@ -30,6 +39,9 @@ pub(crate) fn run_cpui<A: UiApp + 'static>(app: A, size: WindowSize) {
         app: A,
         focus_order: Vec<FocusEntry>,
         window_size: WindowSize,
+        /// The box with a hover style under the pointer.
+        hovered: Option<FocusId>,
+        elements: ElementCache<Arc<cpui::AnyElement>>,
     }
 
     impl<A: UiApp + 'static> cpui::Render for Host<A> {
@@ -45,22 +57,64 @@ pub(crate) fn run_cpui<A: UiApp + 'static>(app: A, size: WindowSize) {
                 };
             }
             self.app.set_window_size(self.window_size);
+            crate::theme::set_system_appearance(cpui::terminal_appearance().map(|appearance| {
+                match appearance {
+                    cpui::Appearance::Light => crate::Appearance::Light,
+                    cpui::Appearance::Dark => crate::Appearance::Dark,
+                }
+            }));
             let node = self.app.render();
+            if crate::runtime::take_bell() {
+                cpui::ring_bell();
+            }
+            if let Some(text) = crate::runtime::take_copy() {
+                cpui::copy_to_clipboard(&text);
+            }
 
             let mut entries = Vec::new();
             node.collect_focus_entries(&mut entries);
             self.focus_order = entries.clone();
 
+            let mut hints = None;
             if let Some(focus) = self.app.focus_state() {
+                focus.sync_overlay(node.overlay_path());
                 focus.ensure_valid(&entries);
+                hints = focus.quick_jump_hints();
             }
             self.app.on_focus_entries(&entries);
+            let tooltip = self
+                .app
+                .focus_state()
+                .and_then(|focus| focus.tooltip(&entries));
+            let mut node = match hints {
+                Some(hints) => node.with_focus_hints(&hints),
+                None => node,
+            };
+            if let Some((path, tooltip)) = tooltip {
+                node = node.with_tooltip(&path, &tooltip);
+            }
+            if let Some(id) = self.hovered {
+                node = node.with_hover(id);
+            }
 
-            node_to_cpui(node, self.window_size.width.max(1.0) as usize)
+            if self.window_size.is_too_small() {
+                return size_guard(self.window_size);
+            }
+            node_to_cpui(
+                node,
+                self.window_size.width.max(1.0) as usize,
+                &mut self.elements,
+            )
         }
     }
 
-    cpui::Application::new().run_with_input_handler(
+    let started = std::time::Instant::now();
+    crate::runtime::set_backend_clipboard(BackendClipboard::Terminal);
+    let pacing = crate::runtime::pacing();
+    let application = cpui::Application::new()
+        .poll_interval(pacing.tick_interval)
+        .cursor_blink(pacing.cursor_blink);
+    application.run_with_input_handler(
         move |cx: &mut cpui::App| {
             let bounds = cpui::Bounds::centered(
                 None,
@@ -78,6 +132,8 @@ pub(crate) fn run_cpui<A: UiApp + 'static>(app: A, size: WindowSize) {
                         app,
                         focus_order: Vec::new(),
                         window_size: size,
+                        hovered: None,
+                        elements: ElementCache::default(),
                     });
                     cx.set_global(HostEntity(entity.clone()));
                     entity
@@ -91,14 +147,34 @@ pub(crate) fn run_cpui<A: UiApp + 'static>(app: A, size: WindowSize) {
                 return false;
             };
 
+            // The pointer only restyles what it is on; the app never hears it.
+            if let cpui::InputEvent::MouseMove { x, y } = event {
+                let hovered = cx.region_at(x, y).map(FocusId);
+                cx.update_entity(&host_entity, |host, _| host.hovered = hovered);
+                return false;
+            }
+            let clicked = match event {
+                cpui::InputEvent::MouseDown {
+                    x,
+                    y,
+                    button: cpui::MouseButton::Left,
+                    ..
+                } => cx.region_at(x, y),
+                _ => None,
+            };
             let mut should_quit = false;
             cx.update_entity(&host_entity, |host, _| {
+                if let Some(id) = clicked
+                    && click_button(&mut host.app, &host.focus_order, FocusId(id))
+                {
+                    return;
+                }
                 let Some(event) = from_cpui_input(event) else {
                     return;
                 };
 
                 let nav_outcome = if let Some(focus) = host.app.focus_state() {
-                    focus.handle_navigation(event, &host.focus_order)
+                    focus.handle_navigation(event.clone(), &host.focus_order)
                 } else {
                     FocusNavOutcome::Ignored
                 };
@@ -107,71 +183,155 @@ pub(crate) fn run_cpui<A: UiApp + 'static>(app: A, size: WindowSize) {
                     FocusNavOutcome::Ignored => host.app.on_input(event),
                     FocusNavOutcome::Handled => {}
                     FocusNavOutcome::RequestQuit => should_quit = true,
+                    FocusNavOutcome::Activate(id) => {
+                        host.app.on_input(UiInputEvent::Activate(id));
+                    }
                 }
             });
 
             should_quit
         },
-    );
+    )?;
+    Ok(ExitReport {
+        elapsed: started.elapsed(),
+    })
 }
 
-fn node_to_cpui(node: Node, viewport_columns: usize) -> cpui::AnyElement {
-    match node {
-        Node::Empty => cpui::AnyElement::Empty,
-        Node::RichText(text) => text_to_cpui(text).into_any_element(),
-        Node::Icon(icon) => icon_to_cpui(icon).into_any_element(),
-        Node::TextInput(input) => text_input_to_cpui(input, viewport_columns),
-        Node::Container(container) => {
-            let mut out = cpui::div();
-            if let Some(bg) = container.style.bg {
-                out = out.bg(to_cpui_color(bg));
-            }
-            if let Some(text_color) = container.style.text_color {
-                out = out.text_color(to_cpui_color(text_color));
-            }
-            out.child(node_to_cpui(*container.child, viewport_columns))
-                .into_any_element()
-        }
-        Node::ScrollView(scroll) => {
-            let mut out = cpui::scroll_view(node_to_cpui(*scroll.child, viewport_columns))
-                .offset_lines(scroll.offset_lines);
-            if let Some(lines) = scroll.viewport_lines {
-                out = out.viewport_lines(lines);
-            }
-            out.into_any_element()
+/// Converts a frame's tree, reusing what the last frame converted for
+/// subtrees that did not change, such as the history while only the input
+/// is typed into.
+fn node_to_cpui(
+    node: Node,
+    viewport_columns: usize,
+    cache: &mut ElementCache<Arc<cpui::AnyElement>>,
+) -> cpui::AnyElement {
+    let hashes = SubtreeHashes::new(&node, frame_salt(viewport_columns));
+    let out = Converter {
+        cache,
+        hashes,
+        viewport_columns,
+    }
+    .convert(node);
+    cache.finish_frame();
+    out
+}
+
+struct Converter<'a> {
+    cache: &'a mut ElementCache<Arc<cpui::AnyElement>>,
+    hashes: SubtreeHashes,
+    viewport_columns: usize,
+}
+
+impl Converter<'_> {
+    fn convert(&mut self, node: Node) -> cpui::AnyElement {
+        let hash = self.hashes.enter();
+        if let Some(hit) = self.cache.get(hash) {
+            self.hashes.skip_children();
+            return hit.into_any_element();
         }
-        Node::Stack(stack) => {
-            let mut out = cpui::div().flex();
+        let converted = Arc::new(self.convert_uncached(node));
+        self.cache.insert(hash, converted.clone());
+        converted.into_any_element()
+    }
 
-            if matches!(stack.axis, Axis::Column) {
-                out = out.flex_col();
+    fn convert_uncached(&mut self, node: Node) -> cpui::AnyElement {
+        match node {
+            Node::Empty => cpui::AnyElement::Empty,
+            Node::RichText(text) => text_to_cpui(text).into_any_element(),
+            Node::Icon(icon) => icon_to_cpui(icon).into_any_element(),
+            Node::TextInput(input) => text_input_to_cpui(input, self.viewport_columns),
+            Node::Container(container) => {
+                let mut out = cpui::div();
+                if let Some(bg) = container.style.bg {
+                    out = out.bg(to_cpui_color(bg));
+                }
+                if let Some(text_color) = container.style.text_color {
+                    out = out.text_color(to_cpui_color(text_color));
+                }
+                if let Some(width) = container.style.width {
+                    out = out.w(cpui::Pixels(f32::from(width)));
+                }
+                // Buttons are found under a click, and boxes with a hover
+                // style under the pointer, by their focus id.
+                if (container.focus_kind == FocusKind::Button || container.hover_style.is_some())
+                    && let Some(id) = container.focus_id
+                {
+                    out = out.id(id.0);
+                }
+                out.child(self.convert(*container.child)).into_any_element()
             }
-            if stack.justify_center {
-                out = out.justify_center();
+            Node::ScrollView(scroll) => {
+                let mut out = cpui::scroll_view(self.convert(*scroll.child))
+                    .offset_lines(scroll.offset_lines);
+                if let Some(lines) = scroll.viewport_lines {
+                    out = out.viewport_lines(lines);
+                }
+                out.into_any_element()
             }
-            if stack.items_center {
-                out = out.items_center();
+            Node::Overlay(overlay) => {
+                cpui::overlay(self.convert(*overlay.child)).into_any_element()
             }
+            Node::Stack(stack) => {
+                let mut out = cpui::div().flex();
 
-            out = match stack.gap {
-                0 => out,
-                1..=2 => out.gap_2(),
-                _ => out.gap_3(),
-            };
+                if matches!(stack.axis, Axis::Column) {
+                    out = out.flex_col();
+                }
+                if stack.justify_center {
+                    out = out.justify_center();
+                }
+                if stack.items_center {
+                    out = out.items_center();
+                }
 
-            for child in stack.children {
-                out = out.child(node_to_cpui(child, viewport_columns));
-            }
+                out = match stack.gap {
+                    0 => out,
+                    1..=2 => out.gap_2(),
+                    _ => out.gap_3(),
+                };
+
+                for child in stack.children {
+                    out = out.child(self.convert(child));
+                }
 
-            out.into_any_element()
+                out.into_any_element()
+            }
         }
     }
 }
 
+/// What a terminal below [`WindowSize::MIN`] shows instead of the app: its
+/// size and the least it needs, each line cut to the room there is.
+fn size_guard(size: WindowSize) -> cpui::AnyElement {
+    let lines = [
+        "Terminal too small".to_string(),
+        format!(
+            "{}×{}, needs {}×{}",
+            size.width,
+            size.height,
+            WindowSize::MIN.width,
+            WindowSize::MIN.height
+        ),
+    ];
+    let text = lines
+        .iter()
+        .take(size.height as usize)
+        .map(|line| line.chars().take(size.width as usize).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n");
+    cpui::div().flex_col().child(text).into_any_element()
+}
+
 fn text_input_to_cpui(input: TextInput, viewport_columns: usize) -> cpui::AnyElement {
     text_to_cpui(input.to_wrapped_rich_text(viewport_columns)).into_any_element()
 }
 
+/// Rows `text` takes drawn `width` columns wide, wrapped as the terminal
+/// draws it.
+pub fn wrapped_height(text: &RichText, width: usize) -> usize {
+    text_to_cpui(text.clone()).wrapped_height_lines(width)
+}
+
 fn text_to_cpui(text: RichText) -> cpui::StyledText {
     if text.runs.is_empty() {
         return cpui::StyledText::new("");
@@ -181,6 +341,9 @@ fn text_to_cpui(text: RichText) -> cpui::StyledText {
     for run in text.runs {
         out = out.push_run(run.text, to_cpui_text_style(run.style));
     }
+    if text.word_wrap {
+        out = out.word_wrap();
+    }
     out
 }
 
@@ -195,6 +358,10 @@ fn icon_to_cpui(icon: Icon) -> cpui::StyledText {
         IconName::Check => "󰄬",
         IconName::ChevronRight => "󰅂",
         IconName::ChevronDown => "󰅀",
+        IconName::Chat => "󰭹",
+        IconName::Files => "󰉋",
+        IconName::Tasks => "󰄲",
+        IconName::Settings => "󰒓",
     };
     let mut style = cpui::TextStyle::new();
     if let Some(color) = icon.color {
@@ -226,6 +393,9 @@ fn to_cpui_text_style(style: TextStyle) -> cpui::TextStyle {
     if style.cursor_anchor {
         out = out.cursor_anchor(style.cursor_after);
     }
+    if let Some(url) = &style.link {
+        out = out.link(cpui::link(url));
+    }
     out
 }
 
@@ -256,12 +426,41 @@ fn from_cpui_input(event: cpui::InputEvent) -> Option<UiInputEvent> {
                 cpui::KeyInput::Submit => UiKeyInput::Submit,
                 cpui::KeyInput::Esc => UiKeyInput::Esc,
                 cpui::KeyInput::Interrupt => UiKeyInput::Interrupt,
+                cpui::KeyInput::QuickJump => UiKeyInput::QuickJump,
+                cpui::KeyInput::Paste => UiKeyInput::Paste,
+                cpui::KeyInput::AddCursorAtNext => UiKeyInput::AddCursorAtNext,
+                cpui::KeyInput::Copy => UiKeyInput::Copy,
+                cpui::KeyInput::Cut => UiKeyInput::Cut,
+                cpui::KeyInput::Undo => UiKeyInput::Undo,
+                cpui::KeyInput::Redo => UiKeyInput::Redo,
+                cpui::KeyInput::Panel(place) => UiKeyInput::Panel(place),
+                cpui::KeyInput::KillToLineEnd => UiKeyInput::KillToLineEnd,
+                cpui::KeyInput::KillToLineStart => UiKeyInput::KillToLineStart,
+                cpui::KeyInput::DeleteLine => UiKeyInput::DeleteLine,
+                cpui::KeyInput::DuplicateLine => UiKeyInput::DuplicateLine,
+                cpui::KeyInput::MoveLineUp => UiKeyInput::MoveLineUp,
+                cpui::KeyInput::MoveLineDown => UiKeyInput::MoveLineDown,
+                cpui::KeyInput::JoinLines => UiKeyInput::JoinLines,
                 cpui::KeyInput::Char(ch) => UiKeyInput::Char(ch),
             };
             Some(UiInputEvent::Key(mapped))
         }
         cpui::InputEvent::ScrollLines(lines) => Some(UiInputEvent::ScrollLines(lines)),
-        cpui::InputEvent::MouseDown { x, y } => Some(UiInputEvent::MouseDown { x, y }),
+        cpui::InputEvent::MouseDown { x, y, button, alt } => Some(UiInputEvent::MouseDown {
+            x,
+            y,
+            button: match button {
+                cpui::MouseButton::Left => UiMouseButton::Left,
+                cpui::MouseButton::Right => UiMouseButton::Right,
+                cpui::MouseButton::Middle => UiMouseButton::Middle,
+            },
+            alt,
+        }),
+        cpui::InputEvent::MouseDrag { x, y, alt } => Some(UiInputEvent::MouseDrag { x, y, alt }),
+        cpui::InputEvent::MouseUp { x, y } => Some(UiInputEvent::MouseUp { x, y }),
+        // Hover is the backend's business; see `run_cpui`.
+        cpui::InputEvent::MouseMove { .. } => None,
+        cpui::InputEvent::Paste(text) => Some(UiInputEvent::Paste(text)),
         cpui::InputEvent::Tick => Some(UiInputEvent::Tick),
     }
 }
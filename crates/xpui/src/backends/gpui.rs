@@ -1,7 +1,12 @@
+#[cfg(feature = "backend-gpui")]
+use super::cache::{ElementCache, SubtreeHashes, content_hash, frame_salt};
 use crate::{
     backend::Backend,
-    node::{Axis, Icon, Node, RichText},
-    runtime::{FocusEntry, FocusNavOutcome, UiApp, UiInputEvent, UiKeyInput, WindowSize},
+    node::{Axis, FocusId, Icon, Node, RichText},
+    runtime::{
+        AppMenu, AppMenuItem, ExitReport, FocusEntry, FocusKind, FocusNavOutcome, RunError, UiApp,
+        UiInputEvent, UiKeyInput, UiMouseButton, WindowSize, WindowState, click_button,
+    },
 };
 
 pub trait GpuiAdapter {
@@ -31,8 +36,27 @@ where
     }
 }
 
+/// Size of a cell at zoom 1.0: rows match the wheel's line height, columns
+/// the font's advance at the default size.
+#[cfg(feature = "backend-gpui")]
+const CELL_WIDTH: f32 = 8.0;
+#[cfg(feature = "backend-gpui")]
+const CELL_HEIGHT: f32 = 18.0;
+/// gpui's rem size, which text is sized by, at zoom 1.0.
+#[cfg(feature = "backend-gpui")]
+const REM_SIZE: f32 = 16.0;
 #[cfg(feature = "backend-gpui")]
-pub(crate) fn run_gpui<A: UiApp + 'static>(app: A, _size: WindowSize) {
+const ZOOM_STEP: f32 = 0.1;
+#[cfg(feature = "backend-gpui")]
+const MIN_ZOOM: f32 = 0.5;
+#[cfg(feature = "backend-gpui")]
+const MAX_ZOOM: f32 = 3.0;
+
+#[cfg(feature = "backend-gpui")]
+pub(crate) fn run_gpui<A: UiApp + 'static>(
+    app: A,
+    _size: WindowSize,
+) -> Result<ExitReport, RunError> {
     use gpui::{App, AppContext, Application, Context, IntoElement, Render, Window, WindowOptions};
 
     struct Host<A> {
@@ -41,6 +65,83 @@ pub(crate) fn run_gpui<A: UiApp + 'static>(app: A, _size: WindowSize) {
         root_focus: gpui::FocusHandle,
         wheel_line_carry: f32,
         window_size: WindowSize,
+        texts: ElementCache<GpuiText>,
+        /// The title last given to the window.
+        title: Option<String>,
+        /// Text scale, which Ctrl+= and Ctrl+- (Cmd on macOS) change.
+        zoom: f32,
+        /// Where the window was at the last frame, kept for quitting.
+        bounds: Option<gpui::Bounds<gpui::Pixels>>,
+        /// The button a mouse press landed on, set by the button before the
+        /// window's own handler sees the press.
+        pressed: PressedButton,
+    }
+
+    /// A menu bar item picked, with what it sends to the app.
+    #[derive(Clone, PartialEq, gpui::Action)]
+    #[action(namespace = xpui, no_json, no_register)]
+    struct MenuPick(UiInputEvent);
+
+    impl<A: UiApp + 'static> Host<A> {
+        /// The cell under `position`, with cells as big as the zoom makes
+        /// them.
+        fn cell_at(&self, position: gpui::Point<gpui::Pixels>) -> (u16, u16) {
+            let x = (position.x / gpui::px(CELL_WIDTH * self.zoom)).max(0.0) as u16;
+            let y = (position.y / gpui::px(CELL_HEIGHT * self.zoom)).max(0.0) as u16;
+            (x, y)
+        }
+
+        /// Zooms for Ctrl+=, Ctrl+- and Ctrl+0, or Cmd with them on macOS.
+        /// `false` for any other key.
+        fn zoom_key(&mut self, event: &gpui::KeyDownEvent) -> bool {
+            if !event.keystroke.modifiers.secondary() {
+                return false;
+            }
+            self.zoom = match event.keystroke.key.as_str() {
+                "=" | "+" => self.zoom + ZOOM_STEP,
+                "-" => self.zoom - ZOOM_STEP,
+                "0" => 1.0,
+                _ => return false,
+            }
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+            true
+        }
+
+        /// Hands `event` to focus navigation first, and to the app when
+        /// navigation leaves it.
+        fn dispatch(&mut self, event: UiInputEvent, cx: &mut Context<Self>) {
+            let nav_outcome = if let Some(focus) = self.app.focus_state() {
+                focus.handle_navigation(event.clone(), &self.focus_order)
+            } else {
+                FocusNavOutcome::Ignored
+            };
+
+            match nav_outcome {
+                FocusNavOutcome::Ignored => self.app.on_input(event),
+                FocusNavOutcome::Handled => {}
+                FocusNavOutcome::RequestQuit => cx.quit(),
+                FocusNavOutcome::Activate(id) => self.app.on_input(UiInputEvent::Activate(id)),
+            }
+            cx.notify();
+        }
+    }
+
+    fn menu_to_gpui(menu: AppMenu) -> gpui::Menu {
+        let items = menu
+            .items
+            .iter()
+            .map(|item| match (item, item.event()) {
+                (
+                    AppMenuItem::Key { label, .. } | AppMenuItem::Command { label, .. },
+                    Some(event),
+                ) => gpui::MenuItem::action(label.clone(), MenuPick(event)),
+                _ => gpui::MenuItem::separator(),
+            })
+            .collect();
+        gpui::Menu {
+            name: menu.title.into(),
+            items,
+        }
     }
 
     impl<A: UiApp + 'static> Render for Host<A> {
@@ -48,22 +149,100 @@ pub(crate) fn run_gpui<A: UiApp + 'static>(app: A, _size: WindowSize) {
             use gpui::{InteractiveElement, ParentElement, Styled, div, px};
 
             window.focus(&self.root_focus);
+            // Text scales with the rem size; cells scale with it so the app
+            // lays out in as many as fit.
+            window.set_rem_size(px(REM_SIZE * self.zoom));
+            let viewport = window.viewport_size();
+            self.window_size = WindowSize {
+                width: viewport.width / px(CELL_WIDTH * self.zoom),
+                height: viewport.height / px(CELL_HEIGHT * self.zoom),
+            };
+            self.bounds = Some(window.bounds());
             self.app.set_window_size(self.window_size);
+            let title = self.app.window_title();
+            if title != self.title {
+                if let Some(title) = &title {
+                    window.set_window_title(title);
+                }
+                self.title = title;
+            }
+            // Read every frame; the 250ms tick picks up an OS theme switch.
+            crate::theme::set_system_appearance(Some(match window.appearance() {
+                gpui::WindowAppearance::Light | gpui::WindowAppearance::VibrantLight => {
+                    crate::Appearance::Light
+                }
+                gpui::WindowAppearance::Dark | gpui::WindowAppearance::VibrantDark => {
+                    crate::Appearance::Dark
+                }
+            }));
 
             let node = self.app.render();
+            if crate::runtime::take_bell() {
+                crate::runtime::play_alert_sound();
+            }
+            if let Some(text) = crate::runtime::take_copy() {
+                cx.write_to_clipboard(gpui::ClipboardItem::new_string(text));
+            }
             let mut focus_order = Vec::new();
             node.collect_focus_entries(&mut focus_order);
             self.focus_order = focus_order.clone();
+            let mut hints = None;
             if let Some(focus) = self.app.focus_state() {
+                focus.sync_overlay(node.overlay_path());
                 focus.ensure_valid(&focus_order);
+                hints = focus.quick_jump_hints();
             }
             self.app.on_focus_entries(&focus_order);
+            let tooltip = self
+                .app
+                .focus_state()
+                .and_then(|focus| focus.tooltip(&focus_order));
+            let mut node = match hints {
+                Some(hints) => node.with_focus_hints(&hints),
+                None => node,
+            };
+            if let Some((path, tooltip)) = tooltip {
+                node = node.with_tooltip(&path, &tooltip);
+            }
+
+            let mouse_down = |button: UiMouseButton| {
+                cx.listener(
+                    move |this: &mut Self, event: &gpui::MouseDownEvent, window, cx| {
+                        if let Some(id) = this.pressed.take()
+                            && button == UiMouseButton::Left
+                            && click_button(&mut this.app, &this.focus_order, id)
+                        {
+                            cx.notify();
+                            window.refresh();
+                            return;
+                        }
+                        let (x, y) = this.cell_at(event.position);
+                        let ui_event = UiInputEvent::MouseDown {
+                            x,
+                            y,
+                            button,
+                            alt: event.modifiers.alt,
+                        };
+                        if let Some(focus) = this.app.focus_state() {
+                            focus.handle_navigation(ui_event.clone(), &this.focus_order);
+                        }
+                        this.app.on_input(ui_event);
+                        cx.notify();
+                        window.refresh();
+                    },
+                )
+            };
 
             let mut root = div()
                 .size_full()
                 .font_family("DejaVu Sans")
                 .track_focus(&self.root_focus)
                 .on_key_down(cx.listener(|this, event: &gpui::KeyDownEvent, window, cx| {
+                    if this.zoom_key(event) {
+                        cx.notify();
+                        window.refresh();
+                        return;
+                    }
                     let mapped = if event.keystroke.key == "tab" {
                         Some(if event.keystroke.modifiers.shift {
                             UiKeyInput::ShiftTab
@@ -77,27 +256,29 @@ pub(crate) fn run_gpui<A: UiApp + 'static>(app: A, _size: WindowSize) {
                     let Some(mapped) = mapped else {
                         return;
                     };
-                    let ui_event = UiInputEvent::Key(mapped);
-
-                    let nav_outcome = if let Some(focus) = this.app.focus_state() {
-                        focus.handle_navigation(ui_event, &this.focus_order)
-                    } else {
-                        FocusNavOutcome::Ignored
-                    };
-
-                    match nav_outcome {
-                        FocusNavOutcome::Ignored => this.app.on_input(ui_event),
-                        FocusNavOutcome::Handled => {}
-                        FocusNavOutcome::RequestQuit => cx.quit(),
+                    // Text on the window's clipboard pastes as if typed; the
+                    // app reads anything else, such as an image, itself.
+                    if mapped == UiKeyInput::Paste
+                        && let Some(text) = cx.read_from_clipboard().and_then(|item| item.text())
+                    {
+                        this.dispatch(UiInputEvent::Paste(text), cx);
+                        window.refresh();
+                        return;
                     }
-                    cx.notify();
+                    this.dispatch(UiInputEvent::Key(mapped), cx);
+                    window.refresh();
+                }))
+                .on_action(cx.listener(|this, pick: &MenuPick, window, cx| {
+                    this.dispatch(pick.0.clone(), cx);
                     window.refresh();
                 }))
-                .on_scroll_wheel(cx.listener(
-                    |this, event: &gpui::ScrollWheelEvent, window, cx| {
+                .on_scroll_wheel(
+                    cx.listener(|this, event: &gpui::ScrollWheelEvent, window, cx| {
                         let delta_lines = match event.delta {
                             gpui::ScrollDelta::Lines(delta) => delta.y,
-                            gpui::ScrollDelta::Pixels(delta) => delta.y / px(18.0),
+                            gpui::ScrollDelta::Pixels(delta) => {
+                                delta.y / px(CELL_HEIGHT * this.zoom)
+                            }
                         };
 
                         this.wheel_line_carry += delta_lines;
@@ -109,51 +290,158 @@ pub(crate) fn run_gpui<A: UiApp + 'static>(app: A, _size: WindowSize) {
                             cx.notify();
                             window.refresh();
                         }
-                    },
-                ));
-
-            match node {
+                    }),
+                )
+                .on_mouse_down(gpui::MouseButton::Left, mouse_down(UiMouseButton::Left))
+                .on_mouse_down(gpui::MouseButton::Right, mouse_down(UiMouseButton::Right))
+                .on_mouse_down(gpui::MouseButton::Middle, mouse_down(UiMouseButton::Middle))
+                .on_drop(
+                    cx.listener(|this, paths: &gpui::ExternalPaths, window, cx| {
+                        let paths = paths.paths().to_vec();
+                        this.app.on_input(UiInputEvent::DropFiles(paths));
+                        cx.notify();
+                        window.refresh();
+                    }),
+                )
+                .on_mouse_move(
+                    cx.listener(|this, event: &gpui::MouseMoveEvent, window, cx| {
+                        if event.pressed_button != Some(gpui::MouseButton::Left) {
+                            return;
+                        }
+                        let (x, y) = this.cell_at(event.position);
+                        this.app.on_input(UiInputEvent::MouseDrag {
+                            x,
+                            y,
+                            alt: event.modifiers.alt,
+                        });
+                        cx.notify();
+                        window.refresh();
+                    }),
+                )
+                .on_mouse_up(
+                    gpui::MouseButton::Left,
+                    cx.listener(|this, event: &gpui::MouseUpEvent, window, cx| {
+                        let (x, y) = this.cell_at(event.position);
+                        this.app.on_input(UiInputEvent::MouseUp { x, y });
+                        cx.notify();
+                        window.refresh();
+                    }),
+                );
+
+            let viewport_columns = self.window_size.width.max(1.0) as usize;
+            let mut converter = Converter {
+                hashes: SubtreeHashes::new(&node, frame_salt(viewport_columns)),
+                texts: &mut self.texts,
+                viewport_columns,
+                viewport,
+                pressed: self.pressed.clone(),
+            };
+            let element = match node {
                 Node::Container(container) => {
+                    // The root box styles the window itself.
+                    converter.hashes.enter();
                     if let Some(bg) = container.style.bg {
                         root = root.bg(gpui::rgb(bg.0));
                     }
                     if let Some(text_color) = container.style.text_color {
                         root = root.text_color(gpui::rgb(text_color.0));
                     }
-                    root.child(node_to_gpui(
-                        *container.child,
-                        self.window_size.width.max(1.0) as usize,
-                    ))
-                    .into_any_element()
+                    root.child(converter.convert(*container.child))
+                        .into_any_element()
                 }
-                other => root
-                    .child(node_to_gpui(
-                        other,
-                        self.window_size.width.max(1.0) as usize,
-                    ))
-                    .into_any_element(),
-            }
+                other => root.child(converter.convert(other)).into_any_element(),
+            };
+            self.texts.finish_frame();
+            element
         }
     }
 
+    // Same as the cpui poll interval so apps observe the same Tick cadence.
+    let tick_interval = crate::runtime::pacing().tick_interval;
+
+    let started = std::time::Instant::now();
+    crate::runtime::set_backend_clipboard(crate::runtime::BackendClipboard::Native);
+    let failure = std::rc::Rc::new(std::cell::RefCell::new(None));
+    let window_failure = failure.clone();
+    let menus = app.menu_bar();
+    let saved = app.saved_window_state();
     Application::new().run(move |cx: &mut App| {
-        let _ = cx.open_window(WindowOptions::default(), |_window, cx| {
-            cx.new(|cx| Host {
-                app,
-                focus_order: Vec::new(),
-                root_focus: cx.focus_handle(),
-                wheel_line_carry: 0.0,
-                window_size: _size,
+        cx.set_menus(menus.into_iter().map(menu_to_gpui).collect());
+        let options = WindowOptions {
+            window_bounds: saved.map(|saved| {
+                gpui::WindowBounds::Windowed(gpui::Bounds::new(
+                    gpui::point(gpui::px(saved.x), gpui::px(saved.y)),
+                    gpui::size(gpui::px(saved.width), gpui::px(saved.height)),
+                ))
+            }),
+            ..WindowOptions::default()
+        };
+        let opened = cx.open_window(options, |_window, cx| {
+            cx.new(|cx| {
+                cx.on_app_quit(|host: &mut Host<A>, _cx| {
+                    if let Some(bounds) = host.bounds {
+                        host.app.save_window_state(WindowState {
+                            x: bounds.origin.x.into(),
+                            y: bounds.origin.y.into(),
+                            width: bounds.size.width.into(),
+                            height: bounds.size.height.into(),
+                            zoom: host.zoom,
+                        });
+                    }
+                    async {}
+                })
+                .detach();
+                cx.spawn(async move |this, cx| {
+                    loop {
+                        cx.background_executor().timer(tick_interval).await;
+                        let ticked = this.update(cx, |host: &mut Host<A>, cx| {
+                            host.app.on_input(UiInputEvent::Tick);
+                            cx.notify();
+                        });
+                        if ticked.is_err() {
+                            break;
+                        }
+                    }
+                })
+                .detach();
+
+                Host {
+                    app,
+                    focus_order: Vec::new(),
+                    root_focus: cx.focus_handle(),
+                    wheel_line_carry: 0.0,
+                    window_size: _size,
+                    texts: ElementCache::default(),
+                    title: None,
+                    zoom: saved.map_or(1.0, |saved| saved.zoom.clamp(MIN_ZOOM, MAX_ZOOM)),
+                    bounds: None,
+                    pressed: PressedButton::default(),
+                }
             })
         });
+        if let Err(err) = opened {
+            *window_failure.borrow_mut() = Some(RunError::Window(err.into()));
+            cx.quit();
+            return;
+        }
         cx.activate(true);
     });
+
+    match failure.take() {
+        Some(err) => Err(err),
+        None => Ok(ExitReport {
+            elapsed: started.elapsed(),
+        }),
+    }
 }
 
 #[cfg(not(feature = "backend-gpui"))]
-pub(crate) fn run_gpui<A: UiApp + 'static>(app: A, _size: WindowSize) {
+pub(crate) fn run_gpui<A: UiApp + 'static>(
+    app: A,
+    _size: WindowSize,
+) -> Result<ExitReport, RunError> {
     let _ = app;
-    panic!("xpui built without backend-gpui feature");
+    Err(RunError::BackendUnavailable("gpui"))
 }
 
 #[cfg(feature = "backend-gpui")]
@@ -171,10 +459,54 @@ fn map_gpui_key_event(event: &gpui::KeyDownEvent) -> Option<UiKeyInput> {
     if secondary && matches!(event.keystroke.key_char.as_deref(), Some("w")) {
         return Some(UiKeyInput::BackspaceWord);
     }
+    if event.keystroke.modifiers.control && event.keystroke.key == "g" {
+        return Some(UiKeyInput::QuickJump);
+    }
+    if secondary && event.keystroke.key == "v" {
+        return Some(UiKeyInput::Paste);
+    }
+    if secondary && event.keystroke.key == "c" {
+        return Some(UiKeyInput::Copy);
+    }
+    if secondary && event.keystroke.key == "x" {
+        return Some(UiKeyInput::Cut);
+    }
+    if secondary && event.keystroke.key == "z" {
+        return Some(if shift {
+            UiKeyInput::Redo
+        } else {
+            UiKeyInput::Undo
+        });
+    }
+    if secondary && event.keystroke.key == "y" {
+        return Some(UiKeyInput::Redo);
+    }
+    if secondary && event.keystroke.key == "d" {
+        return Some(UiKeyInput::AddCursorAtNext);
+    }
+    if alt && let Ok(place @ 1..=9) = event.keystroke.key.parse::<u8>() {
+        return Some(UiKeyInput::Panel(place));
+    }
+    if secondary && event.keystroke.key == "k" {
+        return Some(if shift {
+            UiKeyInput::DeleteLine
+        } else {
+            UiKeyInput::KillToLineEnd
+        });
+    }
+    if secondary && event.keystroke.key == "u" {
+        return Some(UiKeyInput::KillToLineStart);
+    }
+    if alt && event.keystroke.key == "j" {
+        return Some(UiKeyInput::JoinLines);
+    }
     match event.keystroke.key.as_str() {
         "left" if secondary => Some(UiKeyInput::WordLeft),
         "right" if secondary => Some(UiKeyInput::WordRight),
         "backspace" if secondary => Some(UiKeyInput::BackspaceWord),
+        "down" if alt && shift => Some(UiKeyInput::DuplicateLine),
+        "up" if alt => Some(UiKeyInput::MoveLineUp),
+        "down" if alt => Some(UiKeyInput::MoveLineDown),
         "left" => Some(UiKeyInput::Left),
         "right" => Some(UiKeyInput::Right),
         "up" => Some(UiKeyInput::Up),
@@ -200,60 +532,148 @@ fn map_gpui_key_event(event: &gpui::KeyDownEvent) -> Option<UiKeyInput> {
     }
 }
 
+/// The highlighted text of a [`RichText`], as gpui takes it.
 #[cfg(feature = "backend-gpui")]
-fn node_to_gpui(node: Node, viewport_columns: usize) -> gpui::AnyElement {
-    use gpui::{IntoElement, ParentElement, Styled, div};
+type GpuiText = (
+    gpui::SharedString,
+    Vec<(std::ops::Range<usize>, gpui::HighlightStyle)>,
+);
 
-    match node {
-        Node::Empty => div().into_any_element(),
-        Node::RichText(text) => rich_text_to_gpui(text).into_any_element(),
-        Node::Icon(icon) => icon_to_gpui(icon),
-        Node::TextInput(input) => text_input_to_gpui(input, viewport_columns),
-        Node::Container(container) => {
-            let mut out = div();
-            if let Some(bg) = container.style.bg {
-                out = out.bg(gpui::rgb(bg.0));
-            }
-            if let Some(text_color) = container.style.text_color {
-                out = out.text_color(gpui::rgb(text_color.0));
+/// Shared between the buttons of a frame and the window: the one a press
+/// landed on.
+#[cfg(feature = "backend-gpui")]
+type PressedButton = std::rc::Rc<std::cell::Cell<Option<FocusId>>>;
+
+/// Converts a frame's tree. gpui elements are used up by the frame that
+/// draws them, so what is kept between frames is the text they show: its
+/// highlights and, for an input, its wrapping.
+#[cfg(feature = "backend-gpui")]
+struct Converter<'a> {
+    hashes: SubtreeHashes,
+    texts: &'a mut ElementCache<GpuiText>,
+    viewport_columns: usize,
+    /// The window's size, which overlays cover.
+    viewport: gpui::Size<gpui::Pixels>,
+    pressed: PressedButton,
+}
+
+#[cfg(feature = "backend-gpui")]
+impl Converter<'_> {
+    fn text(&mut self, hash: u64, text: impl FnOnce() -> RichText) -> gpui::StyledText {
+        let (full, highlights) = match self.texts.get(hash) {
+            Some(hit) => hit,
+            None => {
+                let converted = rich_text_to_gpui(text());
+                self.texts.insert(hash, converted.clone());
+                converted
             }
-            out.child(node_to_gpui(*container.child, viewport_columns))
-                .into_any_element()
+        };
+        if highlights.is_empty() {
+            gpui::StyledText::new(full)
+        } else {
+            gpui::StyledText::new(full).with_highlights(highlights)
         }
-        Node::ScrollView(scroll) => {
-            const LINE_HEIGHT_PX: f32 = 18.0;
+    }
 
-            let mut out = div().overflow_hidden();
-            out = out.w_full().flex_none();
-            if let Some(lines) = scroll.viewport_lines {
-                out = out.h(gpui::px(lines as f32 * LINE_HEIGHT_PX));
+    fn convert(&mut self, node: Node) -> gpui::AnyElement {
+        use gpui::{InteractiveElement, IntoElement, ParentElement, Styled, div, px};
+
+        let hash = self.hashes.enter();
+        match node {
+            Node::Empty => div().into_any_element(),
+            Node::RichText(text) => self.text(hash, || text).into_any_element(),
+            Node::Icon(icon) => icon_to_gpui(icon),
+            Node::TextInput(input) => {
+                let columns = self.viewport_columns;
+                let gutter = self.text(content_hash(&"gutter", hash), || {
+                    input.to_wrapped_gutter_with_pipe_rich_text(columns)
+                });
+                let content = self.text(hash, || input.to_wrapped_content_rich_text(columns));
+                text_input_to_gpui(input.soft_wrap, gutter, content)
             }
-
-            let mut inner = div()
-                .relative()
-                .w_full()
-                .child(node_to_gpui(*scroll.child, viewport_columns));
-            if scroll.offset_lines > 0 {
-                inner = inner.top(gpui::px(-(scroll.offset_lines as f32 * LINE_HEIGHT_PX)));
+            Node::Container(container) => {
+                let mut out = div();
+                if let Some(bg) = container.style.bg {
+                    out = out.bg(gpui::rgb(bg.0));
+                }
+                if let Some(text_color) = container.style.text_color {
+                    out = out.text_color(gpui::rgb(text_color.0));
+                }
+                if let Some(hover) = container.hover_style {
+                    out = out.hover(move |mut style| {
+                        if let Some(bg) = hover.bg {
+                            style = style.bg(gpui::rgb(bg.0));
+                        }
+                        if let Some(text_color) = hover.text_color {
+                            style = style.text_color(gpui::rgb(text_color.0));
+                        }
+                        style
+                    });
+                }
+                if container.focus_kind == FocusKind::Button
+                    && let Some(id) = container.focus_id
+                {
+                    let pressed = self.pressed.clone();
+                    // The frontmost button hears the press first, so one in
+                    // an overlay wins over what it covers.
+                    out = out.on_mouse_down(gpui::MouseButton::Left, move |_, _, _| {
+                        if pressed.get().is_none() {
+                            pressed.set(Some(id));
+                        }
+                    });
+                }
+                out.child(self.convert(*container.child)).into_any_element()
             }
+            Node::ScrollView(scroll) => {
+                const LINE_HEIGHT_PX: f32 = 18.0;
 
-            out.child(inner).into_any_element()
-        }
-        Node::Stack(stack) => {
-            let mut out = div().flex();
-            if matches!(stack.axis, Axis::Column) {
-                out = out.flex_col();
-            }
-            if stack.justify_center {
-                out = out.justify_center();
+                let mut out = div().overflow_hidden();
+                out = out.w_full().flex_none();
+                if let Some(lines) = scroll.viewport_lines {
+                    out = out.h(gpui::px(lines as f32 * LINE_HEIGHT_PX));
+                }
+
+                let mut inner = div().relative().w_full().child(self.convert(*scroll.child));
+                if scroll.offset_lines > 0 {
+                    inner = inner.top(gpui::px(-(scroll.offset_lines as f32 * LINE_HEIGHT_PX)));
+                }
+
+                out.child(inner).into_any_element()
             }
-            if stack.items_center {
-                out = out.items_center();
+            Node::Overlay(overlay) => {
+                // Painted after the rest of the window, over all of it.
+                let layer = div()
+                    .w(self.viewport.width)
+                    .h(self.viewport.height)
+                    .flex()
+                    .justify_center()
+                    .items_center()
+                    .bg(gpui::rgba(0x0000_0080))
+                    .child(self.convert(*overlay.child));
+                gpui::deferred(
+                    gpui::anchored()
+                        .position(gpui::point(px(0.0), px(0.0)))
+                        .child(layer),
+                )
+                .with_priority(1)
+                .into_any_element()
             }
-            for child in stack.children {
-                out = out.child(node_to_gpui(child, viewport_columns));
+            Node::Stack(stack) => {
+                let mut out = div().flex();
+                if matches!(stack.axis, Axis::Column) {
+                    out = out.flex_col();
+                }
+                if stack.justify_center {
+                    out = out.justify_center();
+                }
+                if stack.items_center {
+                    out = out.items_center();
+                }
+                for child in stack.children {
+                    out = out.child(self.convert(child));
+                }
+                out.into_any_element()
             }
-            out.into_any_element()
         }
     }
 }
@@ -272,10 +692,19 @@ fn icon_to_gpui(icon: Icon) -> gpui::AnyElement {
 }
 
 #[cfg(feature = "backend-gpui")]
-fn text_input_to_gpui(input: crate::TextInput, viewport_columns: usize) -> gpui::AnyElement {
+fn text_input_to_gpui(
+    soft_wrap: bool,
+    gutter: gpui::StyledText,
+    content: gpui::StyledText,
+) -> gpui::AnyElement {
     use gpui::{IntoElement, ParentElement, Styled, div};
 
-    let border = gpui::rgb(0x30363d);
+    let theme = crate::theme();
+    let border = gpui::rgb(theme.border.0);
+    let mut content_box = div().flex_1().px_2();
+    if !soft_wrap {
+        content_box = content_box.whitespace_nowrap().overflow_hidden();
+    }
     div()
         .flex()
         .border_1()
@@ -287,22 +716,16 @@ fn text_input_to_gpui(input: crate::TextInput, viewport_columns: usize) -> gpui:
                 .px_2()
                 .border_r_1()
                 .border_color(border)
-                .text_color(gpui::rgb(0x6e7681))
-                .child(rich_text_to_gpui(
-                    input.to_wrapped_gutter_with_pipe_rich_text(viewport_columns),
-                )),
+                .text_color(gpui::rgb(theme.faint.0))
+                .child(gutter),
         )
-        .child(div().flex_1().px_2().child(rich_text_to_gpui(
-            input.to_wrapped_content_rich_text(viewport_columns),
-        )))
+        .child(content_box.child(content))
         .into_any_element()
 }
 
 #[cfg(feature = "backend-gpui")]
-fn rich_text_to_gpui(text: RichText) -> gpui::StyledText {
-    use gpui::{
-        FontStyle, FontWeight, HighlightStyle, StrikethroughStyle, StyledText, UnderlineStyle, px,
-    };
+fn rich_text_to_gpui(text: RichText) -> GpuiText {
+    use gpui::{FontStyle, FontWeight, HighlightStyle, StrikethroughStyle, UnderlineStyle, px};
 
     let mut full = String::new();
     let mut highlights = Vec::new();
@@ -352,9 +775,5 @@ fn rich_text_to_gpui(text: RichText) -> gpui::StyledText {
         }
     }
 
-    if highlights.is_empty() {
-        StyledText::new(full)
-    } else {
-        StyledText::new(full).with_highlights(highlights)
-    }
+    (full.into(), highlights)
 }
@@ -1,9 +1,11 @@
+#[cfg(any(feature = "backend-cpui", feature = "backend-gpui"))]
+mod cache;
 #[cfg(feature = "backend-cpui")]
 mod cpui;
 mod gpui;
 
 #[cfg(feature = "backend-cpui")]
-pub use cpui::CpuiBackend;
+pub use cpui::{CpuiBackend, wrapped_height};
 #[cfg(feature = "backend-cpui")]
 pub(crate) use cpui::run_cpui;
 pub(crate) use gpui::run_gpui;
@@ -4,24 +4,39 @@ mod node;
 mod runtime;
 pub mod signal;
 mod style;
+mod theme;
 mod widgets;
 
 pub use backend::{Backend, render};
 #[cfg(feature = "backend-cpui")]
-pub use backends::CpuiBackend;
+pub use backends::{CpuiBackend, wrapped_height};
+#[cfg(feature = "backend-cpui")]
+pub use cpui::{
+    Capabilities, ColorDepth, FramePhase, FrameReport, FrameTimings, ImageProtocol, SlowFrame,
+    TerminalProbe, capabilities, frame_report, init_capabilities, probe_terminal,
+    set_frame_budget, set_slow_frame_log,
+};
 pub use backends::{GpuiAdapter, GpuiBackend};
 pub use node::{
-    Axis, FocusId, Icon, IconName, IntoNode, Node, RichText, ScrollView, TextInput, TextRun,
+    Axis, FocusId, Icon, IconName, IntoNode, Node, Overlay, RichText, ScrollView, TextInput,
+    TextRun,
 };
 pub use runtime::{
-    FocusEntry, FocusKind, FocusListBinding, FocusListState, FocusNavOutcome, FocusPath,
-    FocusState, TextInputState, UiApp, UiInputEvent, UiKeyInput, WindowSize, run_gpui,
-    run_gpui_with_size,
+    AppMenu, AppMenuItem, ClipboardImage, ContextMenuState, EditAssist, ExitReport, FocusEntry,
+    FocusKind, FocusListBinding, FocusListState, FocusNavOutcome, FocusPath, FocusState, LogBuffer,
+    LogLevel, LogLine, LogViewOutcome, LogViewState, MenuItem, MenuOutcome, Pacing, PathSegment,
+    RunError, SelectEvent, SelectListState, SelectOutcome, SelectState, TextInputState, UiApp,
+    UiInputEvent, UiKeyInput, UiMouseButton, WindowSize, WindowState, WordRules, clipboard_image,
+    clipboard_text, pacing, primary_selection, ring_bell, run_gpui, run_gpui_with_size,
+    set_clipboard_text, set_pacing,
 };
 #[cfg(feature = "backend-cpui")]
 pub use runtime::{run_cpui, run_cpui_with_size};
 pub use style::{BoxStyle, Rgb, TextStyle, rgb};
+pub use theme::{Appearance, SyntaxColors, Theme, set_theme, system_appearance, theme};
 pub use widgets::{
-    ContainerWidget, IconWidget, ScrollViewWidget, StackWidget, TextInputWidget, TextWidget,
-    column, container, icon, row, scroll_view, text, text_input, text_input_from_state,
+    ButtonWidget, ContainerWidget, EmptyStateWidget, IconWidget, OverlayWidget, ScrollViewWidget,
+    SkeletonWidget, StackWidget, TextInputWidget, TextWidget, button, column, container,
+    context_menu, empty_state, icon, log_view, markdown, markdown_runs, overlay, row, scroll_view,
+    scrollbar, select, select_list, select_menu, skeleton, text, text_input, text_input_from_state,
 };
@@ -1,12 +1,15 @@
+use std::ops::Range;
+
 use crate::{
-    runtime::{FocusEntry, FocusKind, FocusPath},
+    runtime::{FocusEntry, FocusKind, FocusPath, PathSegment},
     style::{BoxStyle, Rgb, TextStyle},
+    theme::theme,
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct FocusId(pub u64);
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Axis {
     Row,
     Column,
@@ -14,6 +17,7 @@ pub enum Axis {
 
 #[derive(Clone, Debug)]
 pub struct Stack {
+    pub key: Option<String>,
     pub axis: Axis,
     pub gap: u8,
     pub justify_center: bool,
@@ -24,6 +28,7 @@ pub struct Stack {
 impl Stack {
     pub fn new(axis: Axis) -> Self {
         Self {
+            key: None,
             axis,
             gap: 0,
             justify_center: false,
@@ -35,22 +40,42 @@ impl Stack {
 
 #[derive(Clone, Debug)]
 pub struct Container {
+    pub key: Option<String>,
     pub style: BoxStyle,
     pub focus_id: Option<FocusId>,
+    pub focus_kind: FocusKind,
+    pub label: Option<String>,
+    /// Shown next to the box after focus rests on it for a moment.
+    pub tooltip: Option<String>,
+    /// Laid over `style` while the pointer is on the box.
+    pub hover_style: Option<BoxStyle>,
     pub child: Box<Node>,
 }
 
 #[derive(Clone, Debug)]
 pub struct ScrollView {
+    pub key: Option<String>,
     pub focus_id: Option<FocusId>,
+    pub label: Option<String>,
     pub viewport_lines: Option<u16>,
     pub offset_lines: u16,
     pub child: Box<Node>,
 }
 
+/// A child drawn centred over the whole window, the rest dimmed under it.
+/// Focus stays inside it while it is shown.
 #[derive(Clone, Debug)]
+pub struct Overlay {
+    pub key: Option<String>,
+    pub child: Box<Node>,
+}
+
+#[derive(Clone, Debug, Hash)]
 pub struct RichText {
     pub runs: Vec<TextRun>,
+    /// Lines break between words rather than at the last cell that fits.
+    /// The window always breaks between words.
+    pub word_wrap: bool,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -64,9 +89,13 @@ pub enum IconName {
     Check,
     ChevronRight,
     ChevronDown,
+    Chat,
+    Files,
+    Tasks,
+    Settings,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Hash)]
 pub struct Icon {
     pub name: IconName,
     pub color: Option<Rgb>,
@@ -80,37 +109,56 @@ impl RichText {
                 text: text.into(),
                 style: TextStyle::default(),
             }],
+            word_wrap: false,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Hash)]
 pub struct TextRun {
     pub text: String,
     pub style: TextStyle,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Hash)]
 pub struct TextInput {
+    pub key: Option<String>,
     pub focus_id: Option<FocusId>,
+    pub label: Option<String>,
     pub value: String,
     pub placeholder: Option<String>,
     pub cursor: usize,
+    /// More cursors, drawn as highlighted cells while focused.
+    pub extra_cursors: Vec<usize>,
+    /// Lines and columns of a block selection, highlighted where the lines
+    /// reach into it.
+    pub block_selection: Option<(Range<usize>, Range<usize>)>,
+    /// Dim text after the end of the value, such as a suggested ending,
+    /// shown while the cursor is there. Cut to the room left on its row.
+    pub ghost: Option<String>,
     pub focused: bool,
     pub gutter_highlighted: bool,
     pub visible_offset_lines: u16,
+    /// Rows the input is shown in. When its rows do not fit, the gutter says
+    /// how many are cut off above and below and draws a scrollbar.
+    pub visible_lines: Option<u16>,
+    /// When off, each line is one row, scrolled by `scroll_columns`.
+    pub soft_wrap: bool,
+    pub scroll_columns: usize,
 }
 
 impl TextInput {
     pub fn to_wrapped_rich_text(&self, total_width: usize) -> RichText {
-        let line_number_style = TextStyle::new().color(Rgb(0x6e7681));
+        let theme = theme();
+        let line_number_style = TextStyle::new().color(theme.faint);
         let pipe_style = if self.gutter_highlighted {
-            TextStyle::new().color(Rgb(0x2f81f7))
+            TextStyle::new().color(theme.accent)
         } else {
-            TextStyle::new().color(Rgb(0x6e7681))
+            TextStyle::new().color(theme.faint)
         };
         let mut runs = Vec::new();
         let (gutter_digits, rows) = self.wrapped_rows(total_width.saturating_sub(3));
+        let total_rows = rows.len();
 
         for row in rows {
             if !runs.is_empty() {
@@ -119,22 +167,13 @@ impl TextInput {
                     style: TextStyle::default(),
                 });
             }
-            let show_idx = row.visible_label_row(self.visible_offset_lines as usize);
-            let line_label = if row.row_in_line == show_idx {
-                format!("{:>width$}", row.line_number, width = gutter_digits)
-            } else {
-                format!("{:>width$}", "", width = gutter_digits)
-            };
+            let (line_label, pipe) = self.gutter(&row, gutter_digits, total_rows);
             runs.push(TextRun {
                 text: line_label,
                 style: line_number_style.clone(),
             });
             runs.push(TextRun {
-                text: if self.gutter_highlighted && row.is_cursor_line && row.row_in_line == show_idx {
-                    " > ".to_string()
-                } else {
-                    " | ".to_string()
-                },
+                text: pipe,
                 style: pipe_style.clone(),
             });
             for (ch, style) in row.content {
@@ -145,11 +184,14 @@ impl TextInput {
             }
         }
 
-        RichText { runs }
+        RichText {
+            runs,
+            word_wrap: false,
+        }
     }
 
     pub fn to_wrapped_gutter_rich_text(&self, total_width: usize) -> RichText {
-        let line_number_style = TextStyle::new().color(Rgb(0x6e7681));
+        let line_number_style = TextStyle::new().color(theme().faint);
         let mut runs = Vec::new();
         let (gutter_digits, rows) = self.wrapped_rows(total_width.saturating_sub(1));
 
@@ -172,18 +214,23 @@ impl TextInput {
             });
         }
 
-        RichText { runs }
+        RichText {
+            runs,
+            word_wrap: false,
+        }
     }
 
     pub fn to_wrapped_gutter_with_pipe_rich_text(&self, total_width: usize) -> RichText {
-        let line_number_style = TextStyle::new().color(Rgb(0x6e7681));
+        let theme = theme();
+        let line_number_style = TextStyle::new().color(theme.faint);
         let pipe_style = if self.gutter_highlighted {
-            TextStyle::new().color(Rgb(0x2f81f7))
+            TextStyle::new().color(theme.accent)
         } else {
-            TextStyle::new().color(Rgb(0x6e7681))
+            TextStyle::new().color(theme.faint)
         };
         let mut runs = Vec::new();
         let (gutter_digits, rows) = self.wrapped_rows(total_width.saturating_sub(3));
+        let total_rows = rows.len();
 
         for row in rows {
             if !runs.is_empty() {
@@ -192,27 +239,21 @@ impl TextInput {
                     style: TextStyle::default(),
                 });
             }
-            let show_idx = row.visible_label_row(self.visible_offset_lines as usize);
-            let number = if row.row_in_line == show_idx {
-                format!("{:>width$}", row.line_number, width = gutter_digits)
-            } else {
-                format!("{:>width$}", "", width = gutter_digits)
-            };
+            let (number, pipe) = self.gutter(&row, gutter_digits, total_rows);
             runs.push(TextRun {
                 text: number,
                 style: line_number_style.clone(),
             });
             runs.push(TextRun {
-                text: if self.gutter_highlighted && row.is_cursor_line && row.row_in_line == show_idx {
-                    " > ".to_string()
-                } else {
-                    " | ".to_string()
-                },
+                text: pipe,
                 style: pipe_style.clone(),
             });
         }
 
-        RichText { runs }
+        RichText {
+            runs,
+            word_wrap: false,
+        }
     }
 
     pub fn to_wrapped_content_rich_text(&self, total_width: usize) -> RichText {
@@ -232,11 +273,85 @@ impl TextInput {
                 });
             }
         }
-        RichText { runs }
+        RichText {
+            runs,
+            word_wrap: false,
+        }
+    }
+
+    /// The line number and the pipe before `row`. While rows are cut off,
+    /// the first and last visible ones count them with an arrow in place of
+    /// the line number, and the pipe thickens where the visible rows sit.
+    fn gutter(
+        &self,
+        row: &WrappedRow,
+        gutter_digits: usize,
+        total_rows: usize,
+    ) -> (String, String) {
+        let show_idx = row.visible_label_row(self.visible_offset_lines as usize);
+        let marker = if self.gutter_highlighted && row.is_cursor_line && row.row_in_line == show_idx
+        {
+            '>'
+        } else {
+            '|'
+        };
+        let number = if row.row_in_line == show_idx {
+            row.line_number.to_string()
+        } else {
+            String::new()
+        };
+        let Some((above, below)) = self.hidden_rows(total_rows) else {
+            return (format!("{number:>gutter_digits$}"), format!(" {marker} "));
+        };
+        let visible = total_rows - above - below;
+        let hidden = if above > 0 && row.global_row == above {
+            Some((above, '↑'))
+        } else if below > 0 && row.global_row + 1 == above + visible {
+            Some((below, '↓'))
+        } else {
+            None
+        };
+        if let Some((count, arrow)) = hidden {
+            // The count takes the number's room and the space after it, and
+            // is left out when even that is too narrow.
+            let count = format!("+{count}");
+            let count = if count.len() > gutter_digits + 1 {
+                String::new()
+            } else {
+                count
+            };
+            return (
+                format!("{count:>width$}", width = gutter_digits + 1),
+                format!("{arrow} "),
+            );
+        }
+        let thumb_len = (visible * visible / total_rows).max(1);
+        let thumb_start = above + (visible - thumb_len) * above / (above + below);
+        let in_thumb = (thumb_start..thumb_start + thumb_len).contains(&row.global_row);
+        let marker = if marker == '|' && in_thumb {
+            '┃'
+        } else {
+            marker
+        };
+        (format!("{number:>gutter_digits$}"), format!(" {marker} "))
+    }
+
+    /// Rows cut off above and below the visible ones, or `None` when they
+    /// all fit.
+    fn hidden_rows(&self, total_rows: usize) -> Option<(usize, usize)> {
+        let visible = usize::from(self.visible_lines?);
+        let above = usize::from(self.visible_offset_lines).min(total_rows);
+        let below = total_rows.saturating_sub(above + visible);
+        (above + below > 0).then_some((above, below))
     }
 
     fn wrapped_rows(&self, total_width: usize) -> (usize, Vec<WrappedRow>) {
-        let placeholder_style = TextStyle::new().italic().color(Rgb(0x6e7681));
+        let theme = theme();
+        let placeholder_style = TextStyle::new().italic().color(theme.faint);
+        let ghost_style = TextStyle::new().color(theme.faint);
+        let extra_cursor_style = TextStyle::new()
+            .bg(theme.selection)
+            .color(theme.selection_text);
         let lines: Vec<&str> = self.value.split('\n').collect();
         let line_count = lines.len().max(1);
         let gutter_digits = line_count.to_string().len();
@@ -246,6 +361,14 @@ impl TextInput {
         } else {
             (0, 0)
         };
+        let extra_cursors = if self.focused {
+            self.extra_cursors
+                .iter()
+                .map(|cursor| cursor_line_col(&self.value, *cursor))
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
 
         let mut out = Vec::new();
         let mut global_row_index = 0usize;
@@ -290,12 +413,66 @@ impl TextInput {
                     }
                 }
             } else {
-                for ch in chars {
+                for ch in chars.iter().copied() {
                     styled_chars.push((ch, TextStyle::default()));
                 }
             }
+            for (_, col) in extra_cursors.iter().filter(|(line, _)| *line == line_idx) {
+                match styled_chars.get_mut(*col) {
+                    Some((_, style)) if *col < chars.len() => {
+                        *style = style
+                            .clone()
+                            .bg(theme.selection)
+                            .color(theme.selection_text);
+                    }
+                    _ => styled_chars.push((' ', extra_cursor_style.clone())),
+                }
+            }
+            if let Some((block_lines, block_columns)) = &self.block_selection
+                && block_lines.contains(&line_idx)
+            {
+                let end = block_columns.end.min(chars.len());
+                for (_, style) in styled_chars.iter_mut().take(end).skip(block_columns.start) {
+                    *style = style
+                        .clone()
+                        .bg(theme.selection)
+                        .color(theme.selection_text);
+                }
+            }
+            if let Some(ghost) = &self.ghost
+                && self.focused
+                && line_idx + 1 == lines.len()
+                && self.cursor == self.value.chars().count()
+            {
+                let char_width = |ch: char| unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0);
+                let used = styled_chars.iter().map(|(ch, _)| char_width(*ch)).sum::<usize>();
+                // Leave the cursor its cell, and never start another row.
+                let room = if !self.soft_wrap {
+                    usize::MAX
+                } else if used > 0 && used.is_multiple_of(content_width) {
+                    0
+                } else {
+                    content_width.saturating_sub(used % content_width + 1)
+                };
+                let mut width = 0;
+                for ch in ghost.chars().take_while(|ch| *ch != '\n') {
+                    width += char_width(ch);
+                    if width > room {
+                        break;
+                    }
+                    styled_chars.push((ch, ghost_style.clone()));
+                }
+            }
 
-            let wrapped = wrap_styled_chars(&styled_chars, content_width);
+            let wrapped = if self.soft_wrap {
+                wrap_styled_chars(&styled_chars, content_width)
+            } else {
+                vec![scroll_styled_chars(
+                    &styled_chars,
+                    self.scroll_columns,
+                    content_width,
+                )]
+            };
             let wrapped_len = wrapped.len().max(1);
 
             for (row_idx, row) in wrapped.into_iter().enumerate() {
@@ -376,11 +553,31 @@ fn wrap_styled_chars(chars: &[(char, TextStyle)], width: usize) -> Vec<Vec<(char
     rows
 }
 
+/// The chars of `chars` that fall within `width` columns after skipping
+/// `skip` columns.
+fn scroll_styled_chars(
+    chars: &[(char, TextStyle)],
+    skip: usize,
+    width: usize,
+) -> Vec<(char, TextStyle)> {
+    let mut col = 0usize;
+    let mut out = Vec::new();
+    for (ch, style) in chars.iter().cloned() {
+        let ch_width = unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0);
+        if col >= skip && col - skip + ch_width <= width {
+            out.push((ch, style));
+        }
+        col = col.saturating_add(ch_width);
+    }
+    out
+}
+
 #[derive(Clone, Debug)]
 pub enum Node {
     Stack(Stack),
     Container(Container),
     ScrollView(ScrollView),
+    Overlay(Overlay),
     RichText(RichText),
     Icon(Icon),
     TextInput(TextInput),
@@ -409,13 +606,24 @@ impl Node {
         self.collect_focus_entries_inner(out, &mut path);
     }
 
-    fn collect_focus_entries_inner(&self, out: &mut Vec<FocusEntry>, path: &mut Vec<usize>) {
+    /// The key set with `.key(..)`, which names this node in focus paths
+    /// instead of its position among its siblings.
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            Node::Stack(stack) => stack.key.as_deref(),
+            Node::Container(container) => container.key.as_deref(),
+            Node::ScrollView(scroll) => scroll.key.as_deref(),
+            Node::TextInput(input) => input.key.as_deref(),
+            Node::Overlay(overlay) => overlay.key.as_deref(),
+            Node::RichText(_) | Node::Icon(_) | Node::Empty => None,
+        }
+    }
+
+    fn collect_focus_entries_inner(&self, out: &mut Vec<FocusEntry>, path: &mut Vec<PathSegment>) {
         match self {
             Node::Stack(stack) => {
                 for (i, child) in stack.children.iter().enumerate() {
-                    path.push(i);
-                    child.collect_focus_entries_inner(out, path);
-                    path.pop();
+                    child.collect_child_focus_entries(i, out, path);
                 }
             }
             Node::Container(container) => {
@@ -423,12 +631,12 @@ impl Node {
                     out.push(FocusEntry {
                         id,
                         path: FocusPath(path.clone()),
-                        kind: FocusKind::Generic,
+                        kind: container.focus_kind,
+                        label: container.label.clone(),
+                        tooltip: container.tooltip.clone(),
                     });
                 }
-                path.push(0);
-                container.child.collect_focus_entries_inner(out, path);
-                path.pop();
+                container.child.collect_child_focus_entries(0, out, path);
             }
             Node::ScrollView(scroll) => {
                 if let Some(id) = scroll.focus_id {
@@ -436,22 +644,210 @@ impl Node {
                         id,
                         path: FocusPath(path.clone()),
                         kind: FocusKind::ScrollRegion,
+                        label: scroll.label.clone(),
+                        tooltip: None,
                     });
                 }
-                path.push(0);
-                scroll.child.collect_focus_entries_inner(out, path);
-                path.pop();
+                scroll.child.collect_child_focus_entries(0, out, path);
             }
+            Node::Overlay(overlay) => overlay.child.collect_child_focus_entries(0, out, path),
             Node::TextInput(input) => {
                 if let Some(id) = input.focus_id {
                     out.push(FocusEntry {
                         id,
                         path: FocusPath(path.clone()),
                         kind: FocusKind::TextInput,
+                        label: input.label.clone(),
+                        tooltip: None,
                     });
                 }
             }
             Node::RichText(_) | Node::Icon(_) | Node::Empty => {}
         }
     }
+
+    fn collect_child_focus_entries(
+        &self,
+        index: usize,
+        out: &mut Vec<FocusEntry>,
+        path: &mut Vec<PathSegment>,
+    ) {
+        path.push(match self.key() {
+            Some(key) => PathSegment::Key(key.to_string()),
+            None => PathSegment::Index(index),
+        });
+        self.collect_focus_entries_inner(out, path);
+        path.pop();
+    }
+
+    /// The path of the overlay drawn on top, the last in the tree, whose
+    /// entries are the only ones focus may move among.
+    pub fn overlay_path(&self) -> Option<FocusPath> {
+        let mut found = None;
+        self.find_overlays(&mut Vec::new(), &mut found);
+        found
+    }
+
+    fn find_overlays(&self, path: &mut Vec<PathSegment>, found: &mut Option<FocusPath>) {
+        if let Node::Overlay(_) = self {
+            *found = Some(FocusPath(path.clone()));
+        }
+        let children: &[Node] = match self {
+            Node::Stack(stack) => &stack.children,
+            Node::Container(container) => std::slice::from_ref(&container.child),
+            Node::ScrollView(scroll) => std::slice::from_ref(&scroll.child),
+            Node::Overlay(overlay) => std::slice::from_ref(&overlay.child),
+            Node::RichText(_) | Node::Icon(_) | Node::TextInput(_) | Node::Empty => &[],
+        };
+        for (index, child) in children.iter().enumerate() {
+            path.push(match child.key() {
+                Some(key) => PathSegment::Key(key.to_string()),
+                None => PathSegment::Index(index),
+            });
+            child.find_overlays(path, found);
+            path.pop();
+        }
+    }
+
+    /// This tree with each hint drawn as a badge at the start of the node at
+    /// its path, for quick-jump. Paths are the ones
+    /// [`collect_focus_entries`](Self::collect_focus_entries) reports.
+    pub fn with_focus_hints(self, hints: &[(FocusPath, String)]) -> Node {
+        if hints.is_empty() {
+            return self;
+        }
+        self.map_focus_paths(&mut Vec::new(), &mut |path, node| {
+            let hint = hints.iter().find(|(hint_path, _)| hint_path.0 == path);
+            match hint {
+                Some((_, hint)) => node.with_badge(hint.clone()),
+                None => node,
+            }
+        })
+    }
+
+    /// This tree with `tooltip` drawn under the node at `path`.
+    pub fn with_tooltip(self, path: &FocusPath, tooltip: &str) -> Node {
+        self.map_focus_paths(&mut Vec::new(), &mut |node_path, node| {
+            if node_path == path.0 {
+                node.with_tooltip_box(tooltip)
+            } else {
+                node
+            }
+        })
+    }
+
+    /// This tree with the hover style of the box with focus id `id` in
+    /// effect, for backends that find what the pointer is on themselves.
+    pub fn with_hover(self, id: FocusId) -> Node {
+        self.map_focus_paths(&mut Vec::new(), &mut |_, node| match node {
+            Node::Container(mut container) if container.focus_id == Some(id) => {
+                if let Some(hover) = &container.hover_style {
+                    let style = &mut container.style;
+                    style.bg = hover.bg.or(style.bg);
+                    style.text_color = hover.text_color.or(style.text_color);
+                    style.width = hover.width.or(style.width);
+                }
+                Node::Container(container)
+            }
+            other => other,
+        })
+    }
+
+    /// Rebuilds the tree bottom-up, passing each node and its focus path
+    /// through `f`.
+    fn map_focus_paths(
+        self,
+        path: &mut Vec<PathSegment>,
+        f: &mut dyn FnMut(&[PathSegment], Node) -> Node,
+    ) -> Node {
+        let node = match self {
+            Node::Stack(mut stack) => {
+                stack.children = stack
+                    .children
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, child)| child.map_child_focus_paths(i, path, f))
+                    .collect();
+                Node::Stack(stack)
+            }
+            Node::Container(mut container) => {
+                container.child = Box::new(container.child.map_child_focus_paths(0, path, f));
+                Node::Container(container)
+            }
+            Node::ScrollView(mut scroll) => {
+                scroll.child = Box::new(scroll.child.map_child_focus_paths(0, path, f));
+                Node::ScrollView(scroll)
+            }
+            Node::Overlay(mut overlay) => {
+                overlay.child = Box::new(overlay.child.map_child_focus_paths(0, path, f));
+                Node::Overlay(overlay)
+            }
+            other => other,
+        };
+        f(path, node)
+    }
+
+    fn map_child_focus_paths(
+        self,
+        index: usize,
+        path: &mut Vec<PathSegment>,
+        f: &mut dyn FnMut(&[PathSegment], Node) -> Node,
+    ) -> Node {
+        path.push(match self.key() {
+            Some(key) => PathSegment::Key(key.to_string()),
+            None => PathSegment::Index(index),
+        });
+        let node = self.map_focus_paths(path, f);
+        path.pop();
+        node
+    }
+
+    fn with_tooltip_box(self, tooltip: &str) -> Node {
+        let theme = theme();
+        let style = TextStyle::new().color(theme.text).bg(theme.border);
+        let runs = tooltip
+            .lines()
+            .enumerate()
+            .map(|(i, line)| TextRun {
+                text: format!("{}{line} ", if i == 0 { "╰ " } else { "\n  " }),
+                style: style.clone(),
+            })
+            .collect();
+        let text = RichText {
+            runs,
+            word_wrap: false,
+        };
+        let mut column = Stack::new(Axis::Column);
+        column.children = vec![self, Node::RichText(text)];
+        Node::Stack(column)
+    }
+
+    fn with_badge(self, hint: String) -> Node {
+        let theme = theme();
+        let badge = TextRun {
+            text: format!(" {hint} "),
+            style: TextStyle::new()
+                .color(theme.badge_text)
+                .bg(theme.badge)
+                .bold(),
+        };
+        match self {
+            // Text boxes take the badge inline so the layout does not shift.
+            Node::Container(mut container) if matches!(*container.child, Node::RichText(_)) => {
+                if let Node::RichText(text) = container.child.as_mut() {
+                    text.runs.insert(0, badge);
+                }
+                Node::Container(container)
+            }
+            other => {
+                let badge = RichText {
+                    runs: vec![badge],
+                    word_wrap: false,
+                };
+                let mut row = Stack::new(Axis::Row);
+                row.children = vec![Node::RichText(badge), other];
+                Node::Stack(row)
+            }
+        }
+    }
 }
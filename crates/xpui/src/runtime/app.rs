@@ -1,6 +1,8 @@
 use crate::Node;
 
-use super::{FocusEntry, FocusState, UiInputEvent, WindowSize};
+use super::{
+    AppMenu, ExitReport, FocusEntry, FocusState, RunError, UiInputEvent, WindowSize, WindowState,
+};
 
 pub trait UiApp {
     fn render(&mut self) -> Node;
@@ -14,22 +16,50 @@ pub trait UiApp {
     }
 
     fn on_focus_entries(&mut self, _entries: &[FocusEntry]) {}
+
+    /// The window's menu bar, read once at start by backends that have one.
+    fn menu_bar(&self) -> Vec<AppMenu> {
+        Vec::new()
+    }
+
+    /// The window's title, read every frame by backends with a window. It
+    /// is the place to count what waits on the user, as there is no dock
+    /// badge to put that on.
+    fn window_title(&self) -> Option<String> {
+        None
+    }
+
+    /// How the last window was left, read at start by backends with a
+    /// window to open it the same way.
+    fn saved_window_state(&self) -> Option<WindowState> {
+        None
+    }
+
+    /// How the window is left, given on quit for the next
+    /// [`saved_window_state`](Self::saved_window_state).
+    fn save_window_state(&mut self, _state: WindowState) {}
 }
 
 #[cfg(feature = "backend-cpui")]
-pub fn run_cpui<A: UiApp + 'static>(app: A) {
+pub fn run_cpui<A: UiApp + 'static>(app: A) -> Result<ExitReport, RunError> {
     crate::backends::run_cpui(app, WindowSize::default())
 }
 
 #[cfg(feature = "backend-cpui")]
-pub fn run_cpui_with_size<A: UiApp + 'static>(app: A, size: WindowSize) {
+pub fn run_cpui_with_size<A: UiApp + 'static>(
+    app: A,
+    size: WindowSize,
+) -> Result<ExitReport, RunError> {
     crate::backends::run_cpui(app, size)
 }
 
-pub fn run_gpui<A: UiApp + 'static>(app: A) {
+pub fn run_gpui<A: UiApp + 'static>(app: A) -> Result<ExitReport, RunError> {
     crate::backends::run_gpui(app, WindowSize::default())
 }
 
-pub fn run_gpui_with_size<A: UiApp + 'static>(app: A, size: WindowSize) {
+pub fn run_gpui_with_size<A: UiApp + 'static>(
+    app: A,
+    size: WindowSize,
+) -> Result<ExitReport, RunError> {
     crate::backends::run_gpui(app, size)
 }
@@ -0,0 +1,94 @@
+use super::UiKeyInput;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MenuItem {
+    pub label: String,
+    /// Picks the item directly while the menu is open.
+    pub key: Option<char>,
+}
+
+impl MenuItem {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            key: None,
+        }
+    }
+
+    pub fn key(mut self, key: char) -> Self {
+        self.key = Some(key);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuOutcome {
+    Pending,
+    Close,
+    Chose(usize),
+}
+
+/// An open list of actions: which item is highlighted and how keys move it.
+/// Render it with [`context_menu`](crate::context_menu).
+#[derive(Clone, Debug)]
+pub struct ContextMenuState {
+    title: String,
+    items: Vec<MenuItem>,
+    selected: usize,
+}
+
+impl ContextMenuState {
+    pub fn new(title: impl Into<String>, items: Vec<MenuItem>) -> Self {
+        Self {
+            title: title.into(),
+            items,
+            selected: 0,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn items(&self) -> &[MenuItem] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Rows the menu takes on screen: the title and one per item.
+    pub fn lines(&self) -> u16 {
+        self.items.len() as u16 + 1
+    }
+
+    pub fn handle_key(&mut self, key: UiKeyInput) -> MenuOutcome {
+        let last = self.items.len().saturating_sub(1);
+        match key {
+            UiKeyInput::Up => self.selected = self.selected.checked_sub(1).unwrap_or(last),
+            UiKeyInput::Down => {
+                self.selected = if self.selected >= last {
+                    0
+                } else {
+                    self.selected + 1
+                }
+            }
+            UiKeyInput::Home => self.selected = 0,
+            UiKeyInput::End => self.selected = last,
+            UiKeyInput::Enter | UiKeyInput::Submit if !self.items.is_empty() => {
+                return MenuOutcome::Chose(self.selected);
+            }
+            UiKeyInput::Esc | UiKeyInput::Enter | UiKeyInput::Submit => {
+                return MenuOutcome::Close;
+            }
+            UiKeyInput::Char(ch) => {
+                if let Some(index) = self.items.iter().position(|item| item.key == Some(ch)) {
+                    return MenuOutcome::Chose(index);
+                }
+            }
+            _ => {}
+        }
+        MenuOutcome::Pending
+    }
+}
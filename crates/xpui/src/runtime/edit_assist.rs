@@ -0,0 +1,121 @@
+use std::ops::Range;
+
+use super::UiKeyInput;
+
+const PAIRS: [(char, char); 6] = [
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('"', '"'),
+    ('\'', '\''),
+    ('`', '`'),
+];
+
+/// Help with typing code into a [`TextInputState`](super::TextInputState).
+/// Both parts are off unless set with
+/// [`set_edit_assist`](super::TextInputState::set_edit_assist).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EditAssist {
+    /// An opening bracket, quote or backtick brings its closing one along.
+    /// Typing the closing one over it steps past it, and Backspace between
+    /// an empty pair removes both.
+    pub auto_close: bool,
+    /// Enter starts the new line with the indentation of the one it breaks,
+    /// one level deeper between a pair of brackets.
+    pub keep_indent: bool,
+}
+
+/// What a key does instead of its plain edit: `text` replaces the chars in
+/// `range` and the cursor ends up at `cursor`.
+pub(super) struct AssistedEdit {
+    pub(super) range: Range<usize>,
+    pub(super) text: String,
+    pub(super) cursor: usize,
+}
+
+impl EditAssist {
+    pub const ON: Self = Self {
+        auto_close: true,
+        keep_indent: true,
+    };
+
+    /// The edit `key` makes at `cursor` in `value`, or `None` to leave it to
+    /// the plain one.
+    pub(super) fn edit(self, value: &str, cursor: usize, key: UiKeyInput) -> Option<AssistedEdit> {
+        let chars = value.chars().collect::<Vec<_>>();
+        let before = cursor.checked_sub(1).and_then(|at| chars.get(at)).copied();
+        let after = chars.get(cursor).copied();
+        match key {
+            UiKeyInput::Char(ch) if self.auto_close => {
+                if after == Some(ch) && PAIRS.iter().any(|(_, close)| *close == ch) {
+                    return Some(AssistedEdit {
+                        range: cursor..cursor,
+                        text: String::new(),
+                        cursor: cursor + 1,
+                    });
+                }
+                let close = closing(ch)?;
+                let quote = ch == close;
+                // `don't` and `a'` are words, not the start of a quote.
+                if quote && before.is_some_and(|before| before.is_alphanumeric() || before == ch) {
+                    return None;
+                }
+                let free = after.is_none_or(|after| {
+                    after.is_whitespace() || PAIRS.iter().any(|(_, close)| *close == after)
+                });
+                free.then(|| AssistedEdit {
+                    range: cursor..cursor,
+                    text: format!("{ch}{close}"),
+                    cursor: cursor + 1,
+                })
+            }
+            UiKeyInput::Backspace if self.auto_close => {
+                let open = before?;
+                (after.is_some() && closing(open) == after).then(|| AssistedEdit {
+                    range: cursor - 1..cursor + 1,
+                    text: String::new(),
+                    cursor: cursor - 1,
+                })
+            }
+            UiKeyInput::Enter if self.keep_indent => {
+                let line_start = chars[..cursor]
+                    .iter()
+                    .rposition(|ch| *ch == '\n')
+                    .map_or(0, |at| at + 1);
+                let indent = chars[line_start..cursor]
+                    .iter()
+                    .take_while(|ch| **ch == ' ' || **ch == '\t')
+                    .collect::<String>();
+                let between_brackets = before
+                    .and_then(closing)
+                    .is_some_and(|close| Some(close) == after && before != after);
+                if !between_brackets {
+                    return (!indent.is_empty()).then(|| AssistedEdit {
+                        range: cursor..cursor,
+                        text: format!("\n{indent}"),
+                        cursor: cursor + 1 + indent.chars().count(),
+                    });
+                }
+                let level = if indent.starts_with('\t') {
+                    "\t"
+                } else {
+                    "    "
+                };
+                let inner = format!("\n{indent}{level}");
+                Some(AssistedEdit {
+                    range: cursor..cursor,
+                    cursor: cursor + inner.chars().count(),
+                    text: format!("{inner}\n{indent}"),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn closing(open: char) -> Option<char> {
+    PAIRS
+        .iter()
+        .find(|(pair_open, _)| *pair_open == open)
+        .map(|(_, close)| *close)
+}
@@ -0,0 +1,43 @@
+use std::error::Error as StdError;
+use std::time::Duration;
+
+use thiserror::Error;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// How a UI run that ended normally went.
+#[derive(Clone, Copy, Debug)]
+pub struct ExitReport {
+    /// Wall-clock time from startup to the last frame.
+    pub elapsed: Duration,
+}
+
+/// Why a UI run stopped early. The variants tell the environment apart from
+/// bugs: a terminal failure is worth retrying elsewhere, a render failure is
+/// not.
+#[derive(Debug, Error)]
+pub enum RunError {
+    /// The terminal could not be set up, read from or written to.
+    #[error("terminal failure")]
+    Terminal(#[source] BoxError),
+    /// The graphics backend could not open its window.
+    #[error("cannot open window")]
+    Window(#[source] BoxError),
+    /// A frame could not be laid out or drawn.
+    #[error("rendering failure")]
+    Render(#[source] BoxError),
+    /// The requested backend is not compiled in.
+    #[error("xpui built without the {0} backend")]
+    BackendUnavailable(&'static str),
+}
+
+#[cfg(feature = "backend-cpui")]
+impl From<cpui::BackendError> for RunError {
+    fn from(err: cpui::BackendError) -> Self {
+        if err.is_terminal_failure() {
+            Self::Terminal(Box::new(err))
+        } else {
+            Self::Render(Box::new(err))
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::FocusId;
 
-use super::{FocusEntry, FocusState, UiInputEvent, UiKeyInput};
+use super::{FocusEntry, FocusPath, FocusState, PathSegment, UiInputEvent, UiKeyInput};
 
 #[derive(Clone, Debug)]
 pub struct FocusListState {
@@ -11,21 +11,49 @@ pub struct FocusListState {
     scroll_offset: u16,
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Ties a [`FocusListState`] to the rows of one list on screen. Rows are
+/// keyed with [`item_key`](Self::item_key) and recognized by that key, so
+/// lists with different names never take each other's focus, wherever they
+/// are nested.
+#[derive(Clone, Debug)]
 pub struct FocusListBinding {
+    name: String,
     first_focus_id: u64,
 }
 
 impl FocusListBinding {
-    pub fn new(first_focus_id: u64) -> Self {
-        Self { first_focus_id }
+    pub fn new(name: impl Into<String>, first_focus_id: u64) -> Self {
+        Self {
+            name: name.into(),
+            first_focus_id,
+        }
     }
 
     pub fn focus_id(&self, index: u16) -> FocusId {
         FocusId(self.first_focus_id + index as u64)
     }
 
+    /// The key for row `index`, to set with `.key(..)` on the row.
+    pub fn item_key(&self, index: u16) -> String {
+        format!("{}#{index}", self.name)
+    }
+
+    fn index_of_key(&self, key: &str) -> Option<u16> {
+        key.strip_prefix(self.name.as_str())?
+            .strip_prefix('#')?
+            .parse()
+            .ok()
+    }
+
     pub fn focused_index(&self, focus: &FocusState, item_count: u16) -> Option<u16> {
+        if let Some(path) = focus.focused_path() {
+            let index = match path.0.last() {
+                Some(PathSegment::Key(key)) => self.index_of_key(key),
+                _ => None,
+            };
+            return index.filter(|index| *index < item_count);
+        }
+        // Focus set by id alone, before the next frame resolves its path.
         let id = focus.focused()?.0;
         let end = self.first_focus_id + item_count as u64;
         if (self.first_focus_id..end).contains(&id) {
@@ -81,7 +109,17 @@ impl FocusListBinding {
         };
 
         if handled {
-            focus.set_focused(self.focus_id(list.focused_index()));
+            let index = list.focused_index();
+            match focus.focused_path() {
+                Some(path) => {
+                    let mut path = FocusPath(path.0.clone());
+                    if let Some(last) = path.0.last_mut() {
+                        *last = PathSegment::Key(self.item_key(index));
+                    }
+                    focus.set_focused_at(self.focus_id(index), path);
+                }
+                None => focus.set_focused(self.focus_id(index)),
+            }
         }
         handled
     }
@@ -124,6 +162,18 @@ impl FocusListState {
         self.viewport_lines
     }
 
+    pub fn gap_lines(&self) -> u16 {
+        self.gap_lines
+    }
+
+    pub fn set_gap_lines(&mut self, gap_lines: u16) {
+        if self.gap_lines == gap_lines {
+            return;
+        }
+        self.gap_lines = gap_lines;
+        self.ensure_focused_visible();
+    }
+
     pub fn set_viewport_lines(&mut self, viewport_lines: u16) {
         let next = viewport_lines.max(1);
         if self.viewport_lines == next {
@@ -151,6 +201,61 @@ impl FocusListState {
         self.ensure_focused_visible();
     }
 
+    /// Scrolls to `offset`, as far as the content goes, keeping the focused
+    /// row in view.
+    pub fn set_scroll_offset(&mut self, offset: u16) {
+        self.scroll_offset = offset.min(self.max_scroll_offset());
+        self.ensure_focused_visible();
+    }
+
+    /// Scrolls to `offset`, as far as the content goes, and moves focus to
+    /// a row in view instead of the view back to the focused row.
+    pub fn scroll_to(&mut self, offset: u16) {
+        self.scroll_offset = offset.min(self.max_scroll_offset());
+        if !self.shows_whole(self.focused_index)
+            && let Some(index) = (0..self.item_count()).find(|&index| self.shows_whole(index))
+        {
+            self.focused_index = index;
+        }
+        self.ensure_focused_visible();
+    }
+
+    /// Whether row `index` is in view as far as it can be: all of it, or
+    /// all of the viewport when it is taller.
+    fn shows_whole(&self, index: u16) -> bool {
+        let top = self.item_top_line(index);
+        let bottom = top.saturating_add(self.item_height(index));
+        let viewport_end = self.scroll_offset.saturating_add(self.viewport_lines);
+        (top >= self.scroll_offset && bottom <= viewport_end)
+            || (top <= self.scroll_offset && bottom >= viewport_end)
+    }
+
+    /// Where a scrollbar thumb goes: its first row within the viewport and
+    /// how many rows it takes. None when everything fits.
+    pub fn scrollbar_thumb(&self) -> Option<(u16, u16)> {
+        let total = self.content_lines() as u32;
+        let visible = self.viewport_lines as u32;
+        if total <= visible {
+            return None;
+        }
+        let len = (visible * visible / total).clamp(1, visible);
+        let travel = visible - len;
+        let max = self.max_scroll_offset().max(1) as u32;
+        let start = self.scroll_offset as u32 * travel / max;
+        Some((start as u16, len as u16))
+    }
+
+    /// The offset that puts the top of the scrollbar thumb on viewport row
+    /// `row`, for dragging it.
+    pub fn offset_for_thumb_row(&self, row: u16) -> u16 {
+        let Some((_, len)) = self.scrollbar_thumb() else {
+            return 0;
+        };
+        let travel = self.viewport_lines.saturating_sub(len).max(1) as u32;
+        let offset = row.min(travel as u16) as u32 * self.max_scroll_offset() as u32 / travel;
+        offset as u16
+    }
+
     pub fn max_scroll_offset(&self) -> u16 {
         self.content_lines().saturating_sub(self.viewport_lines)
     }
@@ -208,16 +313,19 @@ impl FocusListState {
         let height = self.item_height(self.focused_index);
         let bottom = top.saturating_add(height);
 
+        let viewport_end = self.scroll_offset.saturating_add(self.viewport_lines);
+        // A row taller than the viewport can be read a part at a time.
+        let covers_viewport = top <= self.scroll_offset && bottom >= viewport_end;
+        if covers_viewport {
+            return;
+        }
         if top < self.scroll_offset {
             self.scroll_offset = top;
-        } else {
-            let viewport_end = self.scroll_offset.saturating_add(self.viewport_lines);
-            if bottom > viewport_end {
-                if height >= self.viewport_lines {
-                    self.scroll_offset = top;
-                } else {
-                    self.scroll_offset = bottom.saturating_sub(self.viewport_lines);
-                }
+        } else if bottom > viewport_end {
+            if height >= self.viewport_lines {
+                self.scroll_offset = top;
+            } else {
+                self.scroll_offset = bottom.saturating_sub(self.viewport_lines);
             }
         }
 
@@ -1,4 +1,5 @@
-use super::{FocusEntry, FocusKind, FocusNavOutcome, FocusState, UiInputEvent, UiKeyInput};
+use super::{FocusEntry, FocusKind, FocusNavOutcome, FocusState, UiApp, UiInputEvent, UiKeyInput};
+use crate::FocusId;
 
 impl FocusState {
     pub fn handle_navigation(
@@ -11,30 +12,65 @@ impl FocusState {
         let key = match event {
             UiInputEvent::Key(key) => key,
             UiInputEvent::Tick => return FocusNavOutcome::Ignored,
-            UiInputEvent::MouseDown { .. } => {
+            UiInputEvent::MouseDown { .. }
+            | UiInputEvent::MouseDrag { .. }
+            | UiInputEvent::MouseUp { .. } => {
                 self.disarm_quit();
                 return FocusNavOutcome::Ignored;
             }
-            UiInputEvent::ScrollLines(_) => {
+            UiInputEvent::ScrollLines(_)
+            | UiInputEvent::Paste(_)
+            | UiInputEvent::DropFiles(_)
+            | UiInputEvent::MenuCommand(_)
+            | UiInputEvent::Activate(_) => {
                 self.disarm_quit();
                 return FocusNavOutcome::Ignored;
             }
         };
 
-        let focused_kind = self.focused_entry(entries).map(|entry| entry.kind);
+        let trapped = self.trap().is_some();
+        let scoped = self.scope(entries);
+        let entries = scoped.as_ref();
+        if self.quick_jump.is_some() || key == UiKeyInput::QuickJump {
+            self.disarm_quit();
+            return self.handle_quick_jump(key, entries);
+        }
+        let focused = self.focused_entry(entries);
+        let focused_kind = focused.map(|entry| entry.kind);
         let out = match key {
+            UiKeyInput::Enter | UiKeyInput::Char(' ')
+                if focused_kind == Some(FocusKind::Button) =>
+            {
+                focused.map_or(FocusNavOutcome::Ignored, |entry| {
+                    FocusNavOutcome::Activate(entry.id)
+                })
+            }
             UiKeyInput::Esc => {
                 let moved_parent = self.focus_parent(entries);
                 if moved_parent {
                     FocusNavOutcome::Handled
                 } else {
+                    // Leaving the dialog: drop its trap and let the app close it.
+                    if trapped {
+                        self.pop_trap();
+                    }
                     FocusNavOutcome::Ignored
                 }
             }
+            UiKeyInput::Tab | UiKeyInput::ShiftTab if trapped && !entries.is_empty() => {
+                if key == UiKeyInput::Tab {
+                    self.focus_next(entries);
+                } else {
+                    self.focus_prev(entries);
+                }
+                FocusNavOutcome::Handled
+            }
             UiKeyInput::Interrupt => {
                 if self.quit_armed() {
                     self.disarm_quit();
                     FocusNavOutcome::RequestQuit
+                } else if self.interrupt_app() {
+                    FocusNavOutcome::Ignored
                 } else {
                     self.arm_quit();
                     FocusNavOutcome::Handled
@@ -72,3 +108,19 @@ impl FocusState {
         out
     }
 }
+
+/// Focuses the button with focus id `id` and tells `app` it was pressed, for
+/// a click that landed on it. `false` when no button of `entries` has it.
+pub(crate) fn click_button(app: &mut impl UiApp, entries: &[FocusEntry], id: FocusId) -> bool {
+    let Some(button) = entries
+        .iter()
+        .find(|entry| entry.kind == FocusKind::Button && entry.id == id)
+    else {
+        return false;
+    };
+    if let Some(focus) = app.focus_state() {
+        focus.set_focused_entry(button);
+    }
+    app.on_input(UiInputEvent::Activate(id));
+    true
+}
@@ -1,17 +1,35 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
 use crate::FocusId;
 
-use super::{FocusEntry, FocusKind, FocusPath};
+/// How long a Ctrl+C waits for the next one to take the next step.
+const INTERRUPT_WINDOW: Duration = Duration::from_secs(2);
+
+use super::quick_jump::QuickJump;
+use super::tooltip::Dwell;
+use super::{FocusEntry, FocusKind, FocusPath, PathSegment};
 
 #[derive(Clone, Debug, Default)]
 pub struct FocusState {
     focused: Option<FocusId>,
     focused_path: Option<FocusPath>,
     last_child_by_parent: HashMap<FocusPath, FocusPath>,
+    /// Subtrees navigation is confined to, innermost last.
+    traps: Vec<FocusPath>,
+    /// What was focused before each open overlay, innermost last.
+    history: Vec<(Option<FocusId>, Option<FocusPath>)>,
+    /// The top overlay of the last frame, which has a trap of its own.
+    overlay: Option<FocusPath>,
+    pub(crate) quick_jump: Option<QuickJump>,
+    pub(crate) dwell: Option<Dwell>,
     pub(crate) quit_armed: bool,
     pub(crate) quit_armed_at: Option<Instant>,
+    /// The app has work running that Ctrl+C should stop before quitting.
+    interruptible: bool,
+    /// When Ctrl+C last went to the app to stop its work.
+    interrupted_at: Option<Instant>,
 }
 
 impl FocusState {
@@ -27,7 +45,7 @@ impl FocusState {
         self.quit_armed
             && self
                 .quit_armed_at
-                .map(|at| at.elapsed() < Duration::from_secs(2))
+                .map(|at| at.elapsed() < INTERRUPT_WINDOW)
                 .unwrap_or(false)
     }
 
@@ -35,12 +53,40 @@ impl FocusState {
         if self.quit_armed
             && self
                 .quit_armed_at
-                .map(|at| at.elapsed() >= Duration::from_secs(2))
+                .map(|at| at.elapsed() >= INTERRUPT_WINDOW)
                 .unwrap_or(true)
         {
             self.quit_armed = false;
             self.quit_armed_at = None;
         }
+        if !self.interrupted_recently() {
+            self.interrupted_at = None;
+        }
+    }
+
+    /// While `interruptible`, the first Ctrl+C goes to the app as
+    /// [`UiKeyInput::Interrupt`](super::UiKeyInput::Interrupt) to stop its
+    /// work, and only the next ones arm quit and quit. Set it every frame
+    /// from whether anything is running.
+    pub fn set_interruptible(&mut self, interruptible: bool) {
+        self.interruptible = interruptible;
+    }
+
+    /// Whether the last Ctrl+C, moments ago, went to the app to stop its
+    /// work; the next one arms quit.
+    pub fn interrupted_recently(&self) -> bool {
+        self.interrupted_at
+            .is_some_and(|at| at.elapsed() < INTERRUPT_WINDOW)
+    }
+
+    /// Takes the first step of a Ctrl+C sequence that has not reached quit:
+    /// `true` when it should go to the app to stop its work.
+    pub(crate) fn interrupt_app(&mut self) -> bool {
+        if !self.interruptible || self.interrupted_recently() {
+            return false;
+        }
+        self.interrupted_at = Some(Instant::now());
+        true
     }
 
     pub(crate) fn arm_quit(&mut self) {
@@ -51,6 +97,7 @@ impl FocusState {
     pub(crate) fn disarm_quit(&mut self) {
         self.quit_armed = false;
         self.quit_armed_at = None;
+        self.interrupted_at = None;
     }
 
     pub fn is_focused(&self, id: FocusId) -> bool {
@@ -67,6 +114,13 @@ impl FocusState {
         self.focused_path = Some(entry.path.clone());
     }
 
+    /// Focuses the entry at `path`, with `id` standing in until the next
+    /// frame confirms it.
+    pub fn set_focused_at(&mut self, id: FocusId, path: FocusPath) {
+        self.focused = Some(id);
+        self.focused_path = Some(path);
+    }
+
     pub fn clear_focus(&mut self) {
         self.focused = None;
         self.focused_path = None;
@@ -74,7 +128,83 @@ impl FocusState {
         self.disarm_quit();
     }
 
+    /// Remembers the focused entry before an overlay or screen takes over,
+    /// for [`restore_focus`](Self::restore_focus) when it closes.
+    pub fn save_focus(&mut self) {
+        self.history.push((self.focused, self.focused_path.clone()));
+    }
+
+    /// Focuses what was focused at the matching [`save_focus`](Self::save_focus).
+    /// The saved path is matched on the next frame, so a keyed entry is found
+    /// again even if the tree changed meanwhile. Returns false with nothing saved.
+    pub fn restore_focus(&mut self) -> bool {
+        let Some((focused, path)) = self.history.pop() else {
+            return false;
+        };
+        self.focused = focused;
+        self.focused_path = path;
+        true
+    }
+
+    /// Drops the last saved focus, for overlays that close by moving focus
+    /// somewhere new.
+    pub fn forget_saved_focus(&mut self) {
+        self.history.pop();
+    }
+
+    /// Confines navigation to the subtree at `path`, such as an open dialog,
+    /// until the matching [`pop_trap`](Self::pop_trap). Focus moves into it
+    /// on the next frame if it is elsewhere, and returns to where it was when
+    /// the trap is popped.
+    pub fn push_trap(&mut self, path: FocusPath) {
+        self.save_focus();
+        self.traps.push(path);
+    }
+
+    pub fn pop_trap(&mut self) -> Option<FocusPath> {
+        let trap = self.traps.pop()?;
+        self.restore_focus();
+        Some(trap)
+    }
+
+    pub fn trap(&self) -> Option<&FocusPath> {
+        self.traps.last()
+    }
+
+    /// Traps focus in `overlay`, the path of the overlay a frame shows on
+    /// top, and pops the trap once the frame shows it no more. The backends
+    /// call it every frame.
+    pub(crate) fn sync_overlay(&mut self, overlay: Option<FocusPath>) {
+        if self.overlay == overlay {
+            return;
+        }
+        if self.overlay.take().is_some() {
+            self.pop_trap();
+        }
+        if let Some(path) = overlay {
+            self.push_trap(path.clone());
+            self.overlay = Some(path);
+        }
+    }
+
+    /// `entries` narrowed to the active trap, if any.
+    pub(crate) fn scope<'a>(&self, entries: &'a [FocusEntry]) -> Cow<'a, [FocusEntry]> {
+        match self.traps.last() {
+            Some(trap) => Cow::Owned(
+                entries
+                    .iter()
+                    .filter(|entry| entry.path == *trap || entry.path.is_descendant_of(trap))
+                    .cloned()
+                    .collect(),
+            ),
+            None => Cow::Borrowed(entries),
+        }
+    }
+
     pub fn ensure_valid(&mut self, entries: &[FocusEntry]) {
+        let scoped = self.scope(entries);
+        // A trap whose subtree is gone would leave nothing to focus.
+        let entries = if scoped.is_empty() { entries } else { &scoped };
         if entries.is_empty() {
             self.focused = None;
             self.focused_path = None;
@@ -141,8 +271,8 @@ impl FocusState {
         let Some(path) = self.focused_path.clone() else {
             return false;
         };
-        for depth in (1..path.0.len()).rev() {
-            let ancestor = FocusPath(path.0[..depth].to_vec());
+        for depth in (1..path.depth()).rev() {
+            let ancestor = FocusPath(path.prefix(depth).to_vec());
             if let Some(entry) = entries.iter().find(|entry| entry.path == ancestor) {
                 if matches!(entry.kind, FocusKind::ScrollRegion) {
                     self.last_child_by_parent.insert(ancestor, path.clone());
@@ -154,6 +284,23 @@ impl FocusState {
         false
     }
 
+    /// Labels from the outermost labelled ancestor of the focused entry down
+    /// to the entry itself, for a "History › Message 3" style header.
+    pub fn breadcrumb<'a>(&self, entries: &'a [FocusEntry]) -> Vec<&'a str> {
+        let Some(current) = self.focused_entry(entries) else {
+            return Vec::new();
+        };
+        (0..=current.path.depth())
+            .filter_map(|depth| {
+                let prefix = current.path.prefix(depth);
+                entries
+                    .iter()
+                    .find(|entry| entry.path.0 == prefix)
+                    .and_then(|entry| entry.label.as_deref())
+            })
+            .collect()
+    }
+
     pub fn focused_entry<'a>(&self, entries: &'a [FocusEntry]) -> Option<&'a FocusEntry> {
         self.current_index(entries).map(|idx| &entries[idx])
     }
@@ -170,24 +317,16 @@ impl FocusState {
             self.set_focused_entry(entry);
             return true;
         }
-        let mut candidates = entries
+        // Entries come in tree order, so the first shallowest descendant is
+        // the topmost one on screen.
+        let Some(child) = entries
             .iter()
-            .filter(|entry| {
-                entry.path.0.len() > current.path.0.len()
-                    && entry.path.0.get(..current.path.0.len()) == Some(current.path.0.as_slice())
-            })
-            .collect::<Vec<_>>();
-        if candidates.is_empty() {
+            .filter(|entry| entry.path.is_descendant_of(&current.path))
+            .min_by_key(|entry| entry.path.depth())
+        else {
             return false;
-        }
-        candidates.sort_by(|a, b| {
-            a.path
-                .0
-                .len()
-                .cmp(&b.path.0.len())
-                .then_with(|| a.path.0.cmp(&b.path.0))
-        });
-        self.set_focused_entry(candidates[0]);
+        };
+        self.set_focused_entry(child);
         true
     }
 
@@ -203,9 +342,7 @@ impl FocusState {
         let Some(child) = entries.iter().find(|entry| entry.id == child_id) else {
             return false;
         };
-        if child.path.0.len() <= parent.path.0.len()
-            || child.path.0.get(..parent.path.0.len()) != Some(parent.path.0.as_slice())
-        {
+        if !child.path.is_descendant_of(&parent.path) {
             return false;
         }
         self.last_child_by_parent
@@ -228,18 +365,17 @@ impl FocusState {
             return false;
         };
         let current = &entries[current_idx];
-        let mut siblings = entries
+        let parent = current.path.prefix(current.path.depth().saturating_sub(1));
+        let siblings = entries
             .iter()
             .filter(|entry| {
-                entry.path.0.len() == current.path.0.len()
-                    && entry.path.0.get(..entry.path.0.len().saturating_sub(1))
-                        == current.path.0.get(..current.path.0.len().saturating_sub(1))
+                entry.path.depth() == current.path.depth()
+                    && entry.path.prefix(entry.path.depth().saturating_sub(1)) == parent
             })
             .collect::<Vec<_>>();
         if siblings.len() <= 1 {
             return false;
         }
-        siblings.sort_by_key(|entry| &entry.path.0);
         let Some(pos) = siblings.iter().position(|entry| entry.id == current.id) else {
             return false;
         };
@@ -261,56 +397,42 @@ impl FocusState {
 
         for level in (0..path.len()).rev() {
             let parent = &path[..level];
-            let current_slot = path[level];
-
-            let mut sibling_slots = entries
-                .iter()
-                .filter_map(|entry| {
-                    if entry.path.0.len() > level && entry.path.0.get(..level) == Some(parent) {
-                        Some(entry.path.0[level])
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<_>>();
-
-            sibling_slots.sort_unstable();
-            sibling_slots.dedup();
-
+            let current_slot = &path[level];
+
+            // Branches under `parent`, in the order they appear in the tree.
+            let mut slots: Vec<&PathSegment> = Vec::new();
+            for entry in entries {
+                if entry.path.depth() > level
+                    && entry.path.prefix(level) == parent
+                    && !slots.contains(&&entry.path.0[level])
+                {
+                    slots.push(&entry.path.0[level]);
+                }
+            }
+            let Some(pos) = slots.iter().position(|slot| *slot == current_slot) else {
+                continue;
+            };
             let target_slot = if next {
-                sibling_slots.into_iter().find(|slot| *slot > current_slot)
+                slots.get(pos + 1)
             } else {
-                sibling_slots
-                    .into_iter()
-                    .rev()
-                    .find(|slot| *slot < current_slot)
+                pos.checked_sub(1).and_then(|prev| slots.get(prev))
             };
-
             let Some(target_slot) = target_slot else {
                 continue;
             };
 
-            let mut branch_entries = entries
+            if let Some(entry) = entries
                 .iter()
                 .filter(|entry| {
-                    entry.path.0.len() > level
-                        && entry.path.0.get(..level) == Some(parent)
-                        && entry.path.0[level] == target_slot
+                    entry.path.depth() > level
+                        && entry.path.prefix(level) == parent
+                        && &entry.path.0[level] == *target_slot
                 })
-                .collect::<Vec<_>>();
-            if branch_entries.is_empty() {
-                continue;
+                .min_by_key(|entry| entry.path.depth())
+            {
+                self.set_focused_entry(entry);
+                return true;
             }
-
-            branch_entries.sort_by(|a, b| {
-                a.path
-                    .0
-                    .len()
-                    .cmp(&b.path.0.len())
-                    .then_with(|| a.path.0.cmp(&b.path.0))
-            });
-            self.set_focused_entry(branch_entries[0]);
-            return true;
         }
 
         false
@@ -0,0 +1,254 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::UiKeyInput;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+
+    /// The next level up, wrapping from the most severe back to the least.
+    fn next(self) -> Self {
+        match self {
+            Self::Debug => Self::Info,
+            Self::Info => Self::Warn,
+            Self::Warn => Self::Error,
+            Self::Error => Self::Debug,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogLine {
+    /// Counts up from 0 across the buffer's life, so a line keeps its number
+    /// while older ones are dropped.
+    pub seq: u64,
+    pub level: LogLevel,
+    pub text: String,
+}
+
+struct LogLines {
+    lines: VecDeque<LogLine>,
+    capacity: usize,
+    next_seq: u64,
+}
+
+/// A bounded log any thread can append to, oldest lines dropped first.
+/// Clones share the lines. Show it with a [`LogViewState`].
+#[derive(Clone)]
+pub struct LogBuffer {
+    inner: Arc<Mutex<LogLines>>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(LogLines {
+                lines: VecDeque::new(),
+                capacity: capacity.max(1),
+                next_seq: 0,
+            })),
+        }
+    }
+
+    /// Appends `text`, one line per line of it.
+    pub fn push(&self, level: LogLevel, text: impl AsRef<str>) {
+        let mut inner = self.lock();
+        for text in text.as_ref().lines() {
+            if inner.lines.len() == inner.capacity {
+                inner.lines.pop_front();
+            }
+            let seq = inner.next_seq;
+            inner.next_seq += 1;
+            inner.lines.push_back(LogLine {
+                seq,
+                level,
+                text: text.to_string(),
+            });
+        }
+    }
+
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.lock().lines.iter().cloned().collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LogLines> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogViewOutcome {
+    Pending,
+    Close,
+}
+
+/// What of a [`LogBuffer`] is on screen: the lines at or above a level that
+/// contain the search, following the newest or held where they were
+/// scrolled to. Render it with [`log_view`](crate::log_view).
+///
+/// Keys: Up, Down, PageUp, PageDown and Home scroll and hold the view; End
+/// follows again, and `f` switches between the two. `l` raises the level
+/// shown, wrapping back to all of them. `/` types a search until Enter or
+/// Esc. Esc and `q` close the view.
+#[derive(Clone)]
+pub struct LogViewState {
+    source: LogBuffer,
+    /// The first line shown, by [`LogLine::seq`]; `None` follows the tail.
+    anchor: Option<u64>,
+    min_level: LogLevel,
+    search: String,
+    searching: bool,
+    viewport_lines: usize,
+}
+
+impl LogViewState {
+    pub fn new(source: LogBuffer) -> Self {
+        Self {
+            source,
+            anchor: None,
+            min_level: LogLevel::Debug,
+            search: String::new(),
+            searching: false,
+            viewport_lines: 10,
+        }
+    }
+
+    pub fn following(&self) -> bool {
+        self.anchor.is_none()
+    }
+
+    /// The first line held on screen, by [`LogLine::seq`]; `None` while
+    /// following.
+    pub fn anchor(&self) -> Option<u64> {
+        self.anchor
+    }
+
+    /// Holds the view from line `anchor` on, or follows the tail for `None`.
+    pub fn set_anchor(&mut self, anchor: Option<u64>) {
+        self.anchor = anchor;
+    }
+
+    pub fn min_level(&self) -> LogLevel {
+        self.min_level
+    }
+
+    pub fn set_min_level(&mut self, level: LogLevel) {
+        self.min_level = level;
+    }
+
+    pub fn search(&self) -> &str {
+        &self.search
+    }
+
+    /// Whether keys are going into the search.
+    pub fn searching(&self) -> bool {
+        self.searching
+    }
+
+    pub fn set_search(&mut self, search: impl Into<String>) {
+        self.search = search.into();
+    }
+
+    pub fn viewport_lines(&self) -> u16 {
+        self.viewport_lines as u16
+    }
+
+    /// Lines the view has for log lines, below its status line.
+    pub fn set_viewport_lines(&mut self, lines: u16) {
+        self.viewport_lines = usize::from(lines).max(1);
+    }
+
+    /// The lines on screen, and how many lines pass the level and search.
+    pub fn visible(&self) -> (Vec<LogLine>, usize) {
+        let lines = self.matching();
+        let count = lines.len();
+        let top = self.top(&lines);
+        let shown = lines
+            .into_iter()
+            .skip(top)
+            .take(self.viewport_lines)
+            .collect();
+        (shown, count)
+    }
+
+    pub fn handle_key(&mut self, key: UiKeyInput) -> LogViewOutcome {
+        if self.searching {
+            match key {
+                UiKeyInput::Char(ch) => self.search.push(ch),
+                UiKeyInput::Backspace => {
+                    self.search.pop();
+                }
+                UiKeyInput::Enter | UiKeyInput::Submit | UiKeyInput::Esc => {
+                    self.searching = false;
+                }
+                _ => {}
+            }
+            return LogViewOutcome::Pending;
+        }
+        let page = self.viewport_lines as isize;
+        match key {
+            UiKeyInput::Up => self.scroll_by(-1),
+            UiKeyInput::Down => self.scroll_by(1),
+            UiKeyInput::PageUp => self.scroll_by(-page),
+            UiKeyInput::PageDown => self.scroll_by(page),
+            UiKeyInput::Home => self.scroll_by(isize::MIN),
+            UiKeyInput::End => self.anchor = None,
+            UiKeyInput::Char('f') if self.following() => self.scroll_by(0),
+            UiKeyInput::Char('f') => self.anchor = None,
+            UiKeyInput::Char('l') => self.min_level = self.min_level.next(),
+            UiKeyInput::Char('/') => self.searching = true,
+            UiKeyInput::Esc | UiKeyInput::Char('q') => return LogViewOutcome::Close,
+            _ => {}
+        }
+        LogViewOutcome::Pending
+    }
+
+    fn matching(&self) -> Vec<LogLine> {
+        let search = self.search.to_lowercase();
+        self.source
+            .lines()
+            .into_iter()
+            .filter(|line| {
+                line.level >= self.min_level && line.text.to_lowercase().contains(&search)
+            })
+            .collect()
+    }
+
+    /// Where in `lines` the view starts.
+    fn top(&self, lines: &[LogLine]) -> usize {
+        let last_page = lines.len().saturating_sub(self.viewport_lines);
+        match self.anchor {
+            None => last_page,
+            Some(anchor) => lines
+                .iter()
+                .position(|line| line.seq >= anchor)
+                .unwrap_or(lines.len())
+                .min(last_page),
+        }
+    }
+
+    /// Moves the view `delta` lines and holds it there.
+    fn scroll_by(&mut self, delta: isize) {
+        let lines = self.matching();
+        let last_page = lines.len().saturating_sub(self.viewport_lines);
+        let top = self.top(&lines).saturating_add_signed(delta).min(last_page);
+        self.anchor = Some(lines.get(top).map_or(0, |line| line.seq));
+    }
+}
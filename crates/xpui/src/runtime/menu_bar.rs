@@ -0,0 +1,58 @@
+use super::{UiInputEvent, UiKeyInput};
+
+/// A menu of the window's menu bar, for backends that have one: gpui does,
+/// a terminal does not. Give them from [`UiApp::menu_bar`](super::UiApp::menu_bar).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppMenu {
+    pub title: String,
+    pub items: Vec<AppMenuItem>,
+}
+
+impl AppMenu {
+    pub fn new(title: impl Into<String>, items: Vec<AppMenuItem>) -> Self {
+        Self {
+            title: title.into(),
+            items,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AppMenuItem {
+    /// Picking it presses `key`, so it does what the key's shortcut does.
+    Key {
+        label: String,
+        key: UiKeyInput,
+    },
+    /// Picking it sends [`UiInputEvent::MenuCommand`] with `command`.
+    Command {
+        label: String,
+        command: String,
+    },
+    Separator,
+}
+
+impl AppMenuItem {
+    pub fn key(label: impl Into<String>, key: UiKeyInput) -> Self {
+        Self::Key {
+            label: label.into(),
+            key,
+        }
+    }
+
+    pub fn command(label: impl Into<String>, command: impl Into<String>) -> Self {
+        Self::Command {
+            label: label.into(),
+            command: command.into(),
+        }
+    }
+
+    /// What picking it sends to the app; `None` for a separator.
+    pub fn event(&self) -> Option<UiInputEvent> {
+        match self {
+            Self::Key { key, .. } => Some(UiInputEvent::Key(*key)),
+            Self::Command { command, .. } => Some(UiInputEvent::MenuCommand(command.clone())),
+            Self::Separator => None,
+        }
+    }
+}
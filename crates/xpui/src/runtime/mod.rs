@@ -1,16 +1,48 @@
 mod app;
+mod context_menu;
+mod edit_assist;
+mod exit;
 mod focus_list;
 mod focus_nav;
 mod focus_state;
+mod log_view;
+mod menu_bar;
+mod pacing;
+mod quick_jump;
+mod select;
+mod select_list;
+mod selection;
+mod sound;
 mod text_input;
+mod tooltip;
 mod types;
+mod word;
 
 pub use app::{UiApp, run_gpui, run_gpui_with_size};
 #[cfg(feature = "backend-cpui")]
 pub use app::{run_cpui, run_cpui_with_size};
+pub use context_menu::{ContextMenuState, MenuItem, MenuOutcome};
+pub use edit_assist::EditAssist;
+pub use exit::{ExitReport, RunError};
 pub use focus_list::{FocusListBinding, FocusListState};
+pub(crate) use focus_nav::click_button;
 pub use focus_state::FocusState;
+pub use log_view::{LogBuffer, LogLevel, LogLine, LogViewOutcome, LogViewState};
+pub use menu_bar::{AppMenu, AppMenuItem};
+pub use pacing::{Pacing, pacing, set_pacing};
+pub use select::{SelectOutcome, SelectState};
+pub use select_list::{SelectEvent, SelectListState};
+pub use selection::{
+    ClipboardImage, clipboard_image, clipboard_text, primary_selection, set_clipboard_text,
+};
+pub(crate) use selection::{BackendClipboard, set_backend_clipboard, take_copy};
+#[cfg(feature = "backend-gpui")]
+pub(crate) use sound::play_alert_sound;
+pub use sound::ring_bell;
+pub(crate) use sound::take_bell;
 pub use text_input::TextInputState;
 pub use types::{
-    FocusEntry, FocusKind, FocusNavOutcome, FocusPath, UiInputEvent, UiKeyInput, WindowSize,
+    FocusEntry, FocusKind, FocusNavOutcome, FocusPath, PathSegment, UiInputEvent, UiKeyInput,
+    UiMouseButton, WindowSize, WindowState,
 };
+pub use word::WordRules;
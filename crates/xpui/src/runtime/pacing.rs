@@ -0,0 +1,52 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often an idle app wakes up and what moves on screen while it waits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pacing {
+    /// How long the backend waits for input before sending
+    /// [`UiInputEvent::Tick`](crate::UiInputEvent::Tick).
+    pub tick_interval: Duration,
+    /// How often the text cursor blinks; `None` keeps it steady.
+    pub cursor_blink: Option<Duration>,
+    /// Whether loading placeholders sweep.
+    pub animations: bool,
+}
+
+impl Pacing {
+    pub const DEFAULT: Self = Self {
+        tick_interval: Duration::from_millis(250),
+        cursor_blink: Some(Duration::from_millis(570)),
+        animations: true,
+    };
+
+    /// Wakes once a second and keeps everything still, for running on
+    /// battery. Streaming replies show up in one-second steps.
+    pub const LOW_POWER: Self = Self {
+        tick_interval: Duration::from_secs(1),
+        cursor_blink: None,
+        animations: false,
+    };
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static PACING: Mutex<Pacing> = Mutex::new(Pacing::DEFAULT);
+
+/// Sets the pacing for backends started afterwards; widgets follow it from
+/// the next render.
+pub fn set_pacing(pacing: Pacing) {
+    *PACING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = pacing;
+}
+
+pub fn pacing() -> Pacing {
+    *PACING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,97 @@
+use super::{FocusEntry, FocusNavOutcome, FocusPath, FocusState, UiKeyInput};
+
+/// Home row first, so most jumps stay under the fingers.
+const HINT_KEYS: &str = "asdfghjklqwertyuiopzxcvbnm";
+
+/// An open quick-jump: a hint per focusable entry and what has been typed.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct QuickJump {
+    hints: Vec<(FocusPath, String)>,
+    typed: String,
+}
+
+impl QuickJump {
+    fn new(entries: &[FocusEntry]) -> Self {
+        let hints = entries
+            .iter()
+            .zip(hint_labels(entries.len()))
+            .map(|(entry, hint)| (entry.path.clone(), hint))
+            .collect();
+        Self {
+            hints,
+            typed: String::new(),
+        }
+    }
+}
+
+/// `count` distinct hints of equal length, so none is a prefix of another.
+fn hint_labels(count: usize) -> Vec<String> {
+    let keys = HINT_KEYS.chars().collect::<Vec<_>>();
+    let mut len = 1;
+    while keys.len().pow(len) < count {
+        len += 1;
+    }
+    (0..count)
+        .map(|mut n| {
+            let mut hint = vec![' '; len as usize];
+            for slot in hint.iter_mut().rev() {
+                *slot = keys[n % keys.len()];
+                n /= keys.len();
+            }
+            hint.into_iter().collect()
+        })
+        .collect()
+}
+
+impl FocusState {
+    /// Hints still matching what was typed, with the typed part removed,
+    /// while quick-jump is open. Backends draw them over their entries.
+    pub fn quick_jump_hints(&self) -> Option<Vec<(FocusPath, String)>> {
+        let jump = self.quick_jump.as_ref()?;
+        Some(
+            jump.hints
+                .iter()
+                .filter_map(|(path, hint)| {
+                    let rest = hint.strip_prefix(jump.typed.as_str())?;
+                    Some((path.clone(), rest.to_string()))
+                })
+                .collect(),
+        )
+    }
+
+    pub(crate) fn handle_quick_jump(
+        &mut self,
+        key: UiKeyInput,
+        entries: &[FocusEntry],
+    ) -> FocusNavOutcome {
+        let Some(jump) = self.quick_jump.as_mut() else {
+            if key == UiKeyInput::QuickJump && !entries.is_empty() {
+                self.quick_jump = Some(QuickJump::new(entries));
+                return FocusNavOutcome::Handled;
+            }
+            return FocusNavOutcome::Ignored;
+        };
+        // Any key but a hint letter closes the hints and is swallowed.
+        let UiKeyInput::Char(ch) = key else {
+            self.quick_jump = None;
+            return FocusNavOutcome::Handled;
+        };
+        jump.typed.push(ch.to_ascii_lowercase());
+        let typed = jump.typed.as_str();
+        let target = jump
+            .hints
+            .iter()
+            .find(|(_, hint)| hint == typed)
+            .map(|(path, _)| path.clone());
+        let pending = jump.hints.iter().any(|(_, hint)| hint.starts_with(typed));
+        if let Some(path) = target {
+            self.quick_jump = None;
+            if let Some(entry) = entries.iter().find(|entry| entry.path == path) {
+                self.set_focused_entry(entry);
+            }
+        } else if !pending {
+            self.quick_jump = None;
+        }
+        FocusNavOutcome::Handled
+    }
+}
@@ -0,0 +1,166 @@
+use super::UiKeyInput;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectOutcome {
+    Pending,
+    Close,
+    Chose(usize),
+}
+
+/// The list a closed select drops open: what has been typed to filter it
+/// and which of the matching items is highlighted.
+#[derive(Clone, Debug)]
+struct Popup {
+    filter: String,
+    highlighted: usize,
+}
+
+/// One choice out of a fixed list of items, shown as a button until opened.
+/// Open, arrows move through the items and typing narrows them to those
+/// containing what was typed. Render it with [`select`](crate::select) and,
+/// while open, [`select_menu`](crate::select_menu).
+#[derive(Clone, Debug)]
+pub struct SelectState {
+    items: Vec<String>,
+    selected: usize,
+    popup: Option<Popup>,
+}
+
+impl SelectState {
+    pub fn new(items: Vec<String>, selected: usize) -> Self {
+        Self {
+            selected: selected.min(items.len().saturating_sub(1)),
+            items,
+            popup: None,
+        }
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_item(&self) -> Option<&str> {
+        self.items.get(self.selected).map(String::as_str)
+    }
+
+    /// Selects the item labelled `item`, if there is one.
+    pub fn select_item(&mut self, item: &str) -> bool {
+        match self.items.iter().position(|label| label == item) {
+            Some(index) => {
+                self.selected = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    /// Opens the list with the selected item highlighted.
+    pub fn open(&mut self) {
+        self.popup = Some(Popup {
+            filter: String::new(),
+            highlighted: self.selected,
+        });
+    }
+
+    pub fn close(&mut self) {
+        self.popup = None;
+    }
+
+    /// What has been typed to narrow the open list.
+    pub fn filter(&self) -> &str {
+        self.popup
+            .as_ref()
+            .map_or("", |popup| popup.filter.as_str())
+    }
+
+    /// The item highlighted in the open list.
+    pub fn highlighted(&self) -> Option<usize> {
+        let popup = self.popup.as_ref()?;
+        self.matches()
+            .contains(&popup.highlighted)
+            .then_some(popup.highlighted)
+    }
+
+    /// The items the filter leaves, by index, in order.
+    pub fn matches(&self) -> Vec<usize> {
+        let filter = self.filter().to_lowercase();
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.to_lowercase().contains(&filter))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Rows the open list takes on screen: the filter line and one per
+    /// matching item, or a line saying nothing matches.
+    pub fn lines(&self) -> u16 {
+        if !self.is_open() {
+            return 0;
+        }
+        self.matches().len().max(1) as u16 + 1
+    }
+
+    pub fn handle_key(&mut self, key: UiKeyInput) -> SelectOutcome {
+        let matches = self.matches();
+        let Some(popup) = self.popup.as_mut() else {
+            return SelectOutcome::Pending;
+        };
+        let at = matches.iter().position(|&index| index == popup.highlighted);
+        let last = matches.len().saturating_sub(1);
+        let current = popup.highlighted;
+        let step = |to: usize| matches.get(to).copied().unwrap_or(current);
+        match key {
+            UiKeyInput::Up => {
+                popup.highlighted = step(at.and_then(|at| at.checked_sub(1)).unwrap_or(last))
+            }
+            UiKeyInput::Down => {
+                popup.highlighted = step(match at {
+                    Some(at) if at < last => at + 1,
+                    _ => 0,
+                })
+            }
+            UiKeyInput::Home => popup.highlighted = step(0),
+            UiKeyInput::End => popup.highlighted = step(last),
+            UiKeyInput::Enter | UiKeyInput::Submit if at.is_some() => {
+                self.selected = popup.highlighted;
+                self.popup = None;
+                return SelectOutcome::Chose(self.selected);
+            }
+            UiKeyInput::Esc | UiKeyInput::Enter | UiKeyInput::Submit => {
+                self.popup = None;
+                return SelectOutcome::Close;
+            }
+            UiKeyInput::Backspace => {
+                popup.filter.pop();
+                self.refilter();
+            }
+            UiKeyInput::Char(ch) => {
+                popup.filter.push(ch);
+                self.refilter();
+            }
+            _ => {}
+        }
+        SelectOutcome::Pending
+    }
+
+    /// Keeps the highlight on a matching item after the filter changed,
+    /// moving it to the first match when it was filtered out.
+    fn refilter(&mut self) {
+        let matches = self.matches();
+        if let Some(popup) = self.popup.as_mut()
+            && !matches.contains(&popup.highlighted)
+            && let Some(&first) = matches.first()
+        {
+            popup.highlighted = first;
+        }
+    }
+}
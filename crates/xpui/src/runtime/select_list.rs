@@ -0,0 +1,144 @@
+use crate::{EmptyStateWidget, FocusId, IconName, empty_state};
+
+use super::{FocusEntry, FocusListBinding, FocusListState, FocusState, UiInputEvent, UiKeyInput};
+
+/// What an input did to a select list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectEvent {
+    Ignored,
+    /// Focus moved to this row.
+    Moved(u16),
+    /// Enter on this row.
+    Activated(u16),
+}
+
+/// Everything a scrolling list of focusable rows needs between frames: row
+/// heights and scroll offset, which row is focused, and the ids and keys of
+/// the rows. Render it with [`select_list`](crate::select_list).
+#[derive(Clone, Debug)]
+pub struct SelectListState {
+    binding: FocusListBinding,
+    list: FocusListState,
+    scroll_id: FocusId,
+    empty_message: String,
+    empty_hint: Option<String>,
+}
+
+impl SelectListState {
+    /// `name` keys the rows and must differ between lists on screen; rows
+    /// take focus ids from `first_item_id` up, the list itself `scroll_id`.
+    pub fn new(name: &str, scroll_id: FocusId, first_item_id: u64, gap_lines: u16) -> Self {
+        Self {
+            binding: FocusListBinding::new(name, first_item_id),
+            list: FocusListState::new(Vec::new(), 1, gap_lines),
+            scroll_id,
+            empty_message: String::new(),
+            empty_hint: None,
+        }
+    }
+
+    /// Shown in place of the rows when there are none.
+    pub fn empty_message(mut self, message: impl Into<String>) -> Self {
+        self.empty_message = message.into();
+        self
+    }
+
+    /// Shown under the empty message, saying how to add rows.
+    pub fn empty_hint(mut self, hint: impl Into<String>) -> Self {
+        self.empty_hint = Some(hint.into());
+        self
+    }
+
+    pub fn binding(&self) -> &FocusListBinding {
+        &self.binding
+    }
+
+    pub fn list(&self) -> &FocusListState {
+        &self.list
+    }
+
+    pub fn list_mut(&mut self) -> &mut FocusListState {
+        &mut self.list
+    }
+
+    pub fn scroll_id(&self) -> FocusId {
+        self.scroll_id
+    }
+
+    pub(crate) fn empty_state(&self) -> EmptyStateWidget {
+        let state = empty_state(self.empty_message.as_str()).icon(IconName::Info);
+        match &self.empty_hint {
+            Some(hint) => state.hint(hint.as_str()),
+            None => state,
+        }
+    }
+
+    pub fn focused_index(&self, focus: &FocusState) -> Option<u16> {
+        self.binding.focused_index(focus, self.list.item_count())
+    }
+
+    /// Focuses row `index` and scrolls it into view.
+    pub fn select(&mut self, focus: &mut FocusState, index: u16) {
+        self.list.set_focused_index(index);
+        focus.set_focused(self.binding.focus_id(self.list.focused_index()));
+    }
+
+    /// Scrolls to `offset`, as dragging the list or its scrollbar does, and
+    /// focuses a row in view.
+    pub fn scroll_to(&mut self, focus: &mut FocusState, offset: u16) {
+        self.list.scroll_to(offset);
+        focus.set_focused(self.binding.focus_id(self.list.focused_index()));
+    }
+
+    /// Takes this frame's row heights and viewport, and follows focus moved
+    /// by navigation since the last one.
+    pub fn update(&mut self, focus: &FocusState, item_heights: Vec<u16>, viewport_lines: u16) {
+        self.list.set_item_heights(item_heights);
+        self.binding.sync_list_from_focus(focus, &mut self.list);
+        self.list.set_viewport_lines(viewport_lines);
+    }
+
+    /// The row drawn `line` lines below the top of the viewport.
+    pub fn item_at_line(&self, line: u16) -> Option<u16> {
+        self.item_line_at(line).map(|(index, _)| index)
+    }
+
+    /// The row drawn `line` lines below the top of the viewport, and which
+    /// of its own lines that is.
+    pub fn item_line_at(&self, line: u16) -> Option<(u16, u16)> {
+        let line = self.list.scroll_offset().saturating_add(line);
+        (0..self.list.item_count()).find_map(|index| {
+            let top = self.list.item_top_line(index);
+            (line >= top && line < top.saturating_add(self.list.item_height(index)))
+                .then(|| (index, line - top))
+        })
+    }
+
+    pub fn handle_input(&mut self, focus: &mut FocusState, event: UiInputEvent) -> SelectEvent {
+        let Some(index) = self.focused_index(focus) else {
+            return SelectEvent::Ignored;
+        };
+        if matches!(
+            event,
+            UiInputEvent::Key(UiKeyInput::Enter | UiKeyInput::Submit)
+        ) {
+            return SelectEvent::Activated(index);
+        }
+        if self.binding.handle_input(focus, &mut self.list, event) {
+            SelectEvent::Moved(self.list.focused_index())
+        } else {
+            SelectEvent::Ignored
+        }
+    }
+
+    /// Lets Enter on the list return to the row focused last. Call from
+    /// [`UiApp::on_focus_entries`](super::UiApp::on_focus_entries).
+    pub fn on_focus_entries(&self, focus: &mut FocusState, entries: &[FocusEntry]) {
+        let _ = self.binding.sync_preferred_child_for_parent(
+            focus,
+            &self.list,
+            self.scroll_id,
+            entries,
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+/// How the running backend puts copies on the clipboard itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BackendClipboard {
+    /// No backend is running; copies go through the desktop's tools alone.
+    None,
+    /// The terminal takes them with OSC 52, besides the tools.
+    Terminal,
+    /// The window's own clipboard takes them instead of the tools.
+    Native,
+}
+
+struct Clipboard {
+    backend: BackendClipboard,
+    /// Copied since the backend last rendered.
+    pending: Option<String>,
+}
+
+static CLIPBOARD: Mutex<Clipboard> = Mutex::new(Clipboard {
+    backend: BackendClipboard::None,
+    pending: None,
+});
+
+pub(crate) fn set_backend_clipboard(backend: BackendClipboard) {
+    if let Ok(mut clipboard) = CLIPBOARD.lock() {
+        clipboard.backend = backend;
+    }
+}
+
+/// The last text copied since the backend last asked, for it to put on
+/// the clipboard when it next renders.
+pub(crate) fn take_copy() -> Option<String> {
+    CLIPBOARD.lock().ok()?.pending.take()
+}
+
+/// The text selected anywhere on the desktop (the X11/Wayland primary
+/// selection), which middle-click pastes. Read through `wl-paste`, `xclip` or
+/// `xsel`, whichever is installed; `None` elsewhere or when nothing is
+/// selected.
+pub fn primary_selection() -> Option<String> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    const READERS: [(&str, &[&str]); 3] = [
+        ("wl-paste", &["--primary", "--no-newline"]),
+        ("xclip", &["-o", "-selection", "primary"]),
+        ("xsel", &["--output", "--primary"]),
+    ];
+    let bytes = READERS
+        .iter()
+        .find_map(|(program, args)| read(program, args))?;
+    String::from_utf8(bytes).ok()
+}
+
+/// The text on the clipboard, read the same way as [`primary_selection`] on
+/// Linux and through `pbpaste` on macOS.
+pub fn clipboard_text() -> Option<String> {
+    const READERS: [(&str, &[&str]); 4] = [
+        ("pbpaste", &[]),
+        ("wl-paste", &["--no-newline"]),
+        ("xclip", &["-o", "-selection", "clipboard"]),
+        ("xsel", &["--output", "--clipboard"]),
+    ];
+    let readers = if cfg!(target_os = "macos") {
+        &READERS[..1]
+    } else if cfg!(target_os = "linux") {
+        &READERS[1..]
+    } else {
+        return None;
+    };
+    let bytes = readers
+        .iter()
+        .find_map(|(program, args)| read(program, args))?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Puts `text` on the clipboard: through the window's clipboard under gpui,
+/// and otherwise through `pbcopy` on macOS and `wl-copy`, `xclip` or `xsel`
+/// on Linux. Under cpui the terminal is asked too, with OSC 52, which works
+/// over SSH. `false` when nothing could take it.
+pub fn set_clipboard_text(text: &str) -> bool {
+    let (backend, taken) = copy(text);
+    taken || backend == BackendClipboard::Terminal
+}
+
+/// Puts `text` on the clipboard as [`set_clipboard_text`] does, but `true`
+/// only when the window or a tool took it: a terminal never says whether
+/// it took an OSC 52 copy.
+pub(crate) fn set_clipboard_text_checked(text: &str) -> bool {
+    copy(text).1
+}
+
+/// Hands `text` to the backend and the tools; the backend, and whether the
+/// window or a tool took it.
+fn copy(text: &str) -> (BackendClipboard, bool) {
+    let backend = match CLIPBOARD.lock() {
+        Ok(mut clipboard) => {
+            if clipboard.backend != BackendClipboard::None {
+                clipboard.pending = Some(text.to_string());
+            }
+            clipboard.backend
+        }
+        Err(_) => BackendClipboard::None,
+    };
+    let taken = match backend {
+        BackendClipboard::Native => true,
+        BackendClipboard::Terminal | BackendClipboard::None => copy_with_tools(text),
+    };
+    (backend, taken)
+}
+
+fn copy_with_tools(text: &str) -> bool {
+    const WRITERS: [(&str, &[&str]); 4] = [
+        ("pbcopy", &[]),
+        ("wl-copy", &[]),
+        ("xclip", &["-i", "-selection", "clipboard"]),
+        ("xsel", &["--input", "--clipboard"]),
+    ];
+    let writers = if cfg!(target_os = "macos") {
+        &WRITERS[..1]
+    } else if cfg!(target_os = "linux") {
+        &WRITERS[1..]
+    } else {
+        return false;
+    };
+    writers
+        .iter()
+        .any(|(program, args)| write(program, args, text.as_bytes()))
+}
+
+/// An image copied to the clipboard, as encoded by whoever copied it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClipboardImage {
+    /// `image/png`, `image/jpeg`, `image/gif` or `image/webp`.
+    pub mime_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+/// Image formats taken from the clipboard, most preferred first.
+const IMAGE_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// The image on the clipboard, if there is one. Linux asks `wl-paste` or
+/// `xclip` which types the clipboard offers; macOS asks `osascript` for PNG
+/// data. `None` on other platforms, without those tools, or when the
+/// clipboard holds something else.
+pub fn clipboard_image() -> Option<ClipboardImage> {
+    if cfg!(target_os = "macos") {
+        return macos_clipboard_png();
+    }
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    if let Some(types) = read("wl-paste", &["--list-types"]) {
+        let mime_type = pick_image_type(&types)?;
+        let bytes = read("wl-paste", &["--no-newline", "--type", mime_type])?;
+        return Some(ClipboardImage { mime_type, bytes });
+    }
+    let types = read("xclip", &["-o", "-selection", "clipboard", "-t", "TARGETS"])?;
+    let mime_type = pick_image_type(&types)?;
+    let bytes = read("xclip", &["-o", "-selection", "clipboard", "-t", mime_type])?;
+    Some(ClipboardImage { mime_type, bytes })
+}
+
+fn pick_image_type(offered: &[u8]) -> Option<&'static str> {
+    let offered = String::from_utf8_lossy(offered);
+    IMAGE_TYPES
+        .into_iter()
+        .find(|wanted| offered.lines().any(|line| line.trim() == *wanted))
+}
+
+/// AppleScript prints clipboard data as `«data PNGf89504E47…»`.
+fn macos_clipboard_png() -> Option<ClipboardImage> {
+    let out = read("osascript", &["-e", "the clipboard as «class PNGf»"])?;
+    let out = String::from_utf8(out).ok()?;
+    let hex = out.trim().strip_prefix("«data PNGf")?.strip_suffix('»')?;
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    Some(ClipboardImage {
+        mime_type: "image/png",
+        bytes,
+    })
+}
+
+/// Whether `program` ran, read `input` and succeeded. The Linux tools fork to
+/// keep serving the clipboard, so the wait ends once they have the text.
+fn write(program: &str, args: &[&str], input: &[u8]) -> bool {
+    let Ok(mut child) = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    let written = child
+        .stdin
+        .take()
+        .is_some_and(|mut stdin| stdin.write_all(input).is_ok());
+    written && child.wait().is_ok_and(|status| status.success())
+}
+
+/// Standard output of `program`, if it ran, succeeded and printed anything.
+fn read(program: &str, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return None;
+    }
+    Some(output.stdout)
+}
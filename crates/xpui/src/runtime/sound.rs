@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static BELL_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Asks for an audible cue, played when the backend next renders: the
+/// terminal bell under cpui, the system alert sound under gpui. Several
+/// requests in one frame ring once.
+pub fn ring_bell() {
+    BELL_PENDING.store(true, Ordering::Relaxed);
+}
+
+pub(crate) fn take_bell() -> bool {
+    BELL_PENDING.swap(false, Ordering::Relaxed)
+}
+
+/// Plays the desktop's alert sound without waiting for it to finish, through
+/// `afplay` on macOS and `canberra-gtk-play` elsewhere. Silent when neither
+/// is installed.
+#[cfg(feature = "backend-gpui")]
+pub(crate) fn play_alert_sound() {
+    use std::process::{Command, Stdio};
+
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("afplay", &["/System/Library/Sounds/Glass.aiff"])
+    } else {
+        ("canberra-gtk-play", &["--id", "bell"])
+    };
+    let _ = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
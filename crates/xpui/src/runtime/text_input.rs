@@ -1,11 +1,30 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use super::edit_assist::EditAssist;
+use super::selection::{clipboard_text, set_clipboard_text, set_clipboard_text_checked};
+use super::word::WordRules;
 use super::{UiInputEvent, UiKeyInput};
 
 #[derive(Clone, Debug, Default)]
 pub struct TextInputState {
     value: String,
     cursor: usize,
+    /// Cursors besides `cursor`, in ascending order. Edits and moves apply
+    /// at every one of them.
+    extra_cursors: Vec<usize>,
+    /// A rectangle picked with Alt+drag. Its lines each have a cursor at
+    /// the column the drag ended in.
+    block: Option<Block>,
     preferred_column: Option<usize>,
     soft_wrap_width: Option<usize>,
+    /// Long lines scroll sideways instead of wrapping.
+    no_wrap: bool,
+    /// Columns scrolled past on every line while not wrapping.
+    scroll_columns: usize,
+    assist: EditAssist,
+    words: WordRules,
+    history: History,
 }
 
 impl TextInputState {
@@ -15,8 +34,15 @@ impl TextInputState {
         Self {
             value,
             cursor,
+            extra_cursors: Vec::new(),
+            block: None,
             preferred_column: None,
             soft_wrap_width: None,
+            no_wrap: false,
+            scroll_columns: 0,
+            assist: EditAssist::default(),
+            words: WordRules::default(),
+            history: History::default(),
         }
     }
 
@@ -28,37 +54,568 @@ impl TextInputState {
         self.cursor
     }
 
+    pub fn extra_cursors(&self) -> &[usize] {
+        &self.extra_cursors
+    }
+
+    /// Replaces the text, dropping any extra cursors and block selection.
+    /// Undo brings the old text back.
     pub fn set_value(&mut self, value: impl Into<String>) {
+        let before = self.snapshot();
+        self.value = value.into();
+        self.record(before, false);
+        self.cursor = self.cursor.min(self.value.chars().count());
+        self.clear_extra_cursors();
+        self.preferred_column = None;
+    }
+
+    /// Replaces the text as [`set_value`](Self::set_value) does, for text
+    /// loaded from elsewhere rather than edited: undo starts afresh from it
+    /// instead of reaching back to what was there before.
+    pub fn load_value(&mut self, value: impl Into<String>) {
         self.value = value.into();
+        self.history = History::default();
         self.cursor = self.cursor.min(self.value.chars().count());
+        self.clear_extra_cursors();
         self.preferred_column = None;
     }
 
+    /// Moves the cursor, dropping any extra cursors and block selection.
     pub fn set_cursor(&mut self, cursor: usize) {
         self.cursor = cursor.min(self.value.chars().count());
+        self.clear_extra_cursors();
+        self.preferred_column = None;
+    }
+
+    /// Adds a cursor at `cursor`, or removes the one already there as long
+    /// as another is left.
+    pub fn toggle_cursor(&mut self, cursor: usize) {
+        let cursor = cursor.min(self.value.chars().count());
+        self.block = None;
+        if cursor == self.cursor {
+            if !self.extra_cursors.is_empty() {
+                self.cursor = self.extra_cursors.remove(0);
+            }
+        } else if let Some(at) = self.extra_cursors.iter().position(|c| *c == cursor) {
+            self.extra_cursors.remove(at);
+        } else {
+            self.extra_cursors.push(cursor);
+            self.extra_cursors.sort_unstable();
+        }
+        self.preferred_column = None;
+    }
+
+    pub fn clear_extra_cursors(&mut self) {
+        self.extra_cursors.clear();
+        self.block = None;
+    }
+
+    /// The lines and the columns, in chars, of the block selection.
+    pub fn block_selection(&self) -> Option<(Range<usize>, Range<usize>)> {
+        self.block.map(|block| (block.lines(), block.columns()))
+    }
+
+    /// Selects the rectangle between two visual positions, as an Alt+drag
+    /// from `anchor` to `head` does, and puts a cursor on each of its lines
+    /// at the head's column. Columns past the end of a line count on, so
+    /// the block can be wider than the line the drag ended on.
+    pub fn select_block_at_visual_positions(
+        &mut self,
+        anchor: (usize, usize),
+        head: (usize, usize),
+        wrap_width: usize,
+    ) {
+        let block = Block {
+            anchor: self.line_col_at_visual_position(anchor.0, anchor.1, wrap_width),
+            head: self.line_col_at_visual_position(head.0, head.1, wrap_width),
+        };
+        self.place_block_cursors(block.lines(), block.head.1, block.head.0);
+        self.block = Some(block);
+    }
+
+    /// The selected columns of each line of the block, one line per line.
+    pub fn block_text(&self) -> Option<String> {
+        let block = self.block?;
+        let columns = block.columns();
+        let text = self
+            .value
+            .split('\n')
+            .skip(block.lines().start)
+            .take(block.lines().len())
+            .map(|line| {
+                line.chars()
+                    .skip(columns.start)
+                    .take(columns.len())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(text)
+    }
+
+    /// Puts the block selection on the clipboard, or the cursor's line with
+    /// its line break without one. `false` when nothing took it.
+    pub fn copy(&self) -> bool {
+        set_clipboard_text(&self.clip())
+    }
+
+    /// Copies as [`copy`](Self::copy) does, then deletes what was copied.
+    /// The text stays when nothing is known to have taken it, as with only
+    /// a terminal's OSC 52 to go through.
+    pub fn cut(&mut self) -> bool {
+        let before = self.snapshot();
+        let cut = self.cut_clip();
+        self.record(before, false);
+        cut
+    }
+
+    fn cut_clip(&mut self) -> bool {
+        if !set_clipboard_text_checked(&self.clip()) {
+            return false;
+        }
+        if !self.take_block() {
+            self.extra_cursors.clear();
+            self.edit_lines(UiKeyInput::DeleteLine);
+        }
+        true
+    }
+
+    fn clip(&self) -> String {
+        self.block_text().unwrap_or_else(|| {
+            let (line, _) = line_col_for_cursor(&self.value, self.cursor);
+            let text = self.value.split('\n').nth(line).unwrap_or_default();
+            format!("{text}\n")
+        })
+    }
+
+    /// Removes the block's columns from each of its lines, leaving a cursor
+    /// where each cut was.
+    fn delete_block(&mut self, block: Block) {
+        let columns = block.columns();
+        let lines = block.lines();
+        let value = self
+            .value
+            .split('\n')
+            .enumerate()
+            .map(|(index, line)| {
+                if !lines.contains(&index) {
+                    return line.to_string();
+                }
+                line.chars()
+                    .enumerate()
+                    .filter(|(column, _)| !columns.contains(column))
+                    .map(|(_, ch)| ch)
+                    .collect()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.value = value;
+        self.place_block_cursors(lines, columns.start, block.head.0);
+    }
+
+    /// Drops the block selection before an edit, taking its text out first
+    /// when it has any. `true` when text was removed.
+    fn take_block(&mut self) -> bool {
+        match self.block.take() {
+            Some(block) if !block.columns().is_empty() => {
+                self.delete_block(block);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// One cursor per line of `lines` at `column`, or at the end of lines
+    /// shorter than that; the main one on `main_line`.
+    fn place_block_cursors(&mut self, lines: Range<usize>, column: usize, main_line: usize) {
+        self.cursor = cursor_for_line_col(&self.value, main_line, column);
+        let mut cursors = lines
+            .map(|line| cursor_for_line_col(&self.value, line, column))
+            .filter(|cursor| *cursor != self.cursor)
+            .collect::<Vec<_>>();
+        cursors.dedup();
+        self.extra_cursors = cursors;
         self.preferred_column = None;
     }
 
+    /// Adds a cursor at the next occurrence, after the last cursor and
+    /// wrapping around, of the word under the main cursor, at the same place
+    /// within the word. `false` when the cursor is not on a word or every
+    /// occurrence already has a cursor.
+    pub fn add_cursor_at_next_occurrence(&mut self) -> bool {
+        self.block = None;
+        let chars = self.value.chars().collect::<Vec<_>>();
+        let (start, end) = self.words.word_around(&chars, self.cursor);
+        if start == end {
+            return false;
+        }
+        let word = &chars[start..end];
+        let within = self.cursor - start;
+        // Cursors are kept sorted, so this is the furthest one.
+        let furthest = self.extra_cursors.last().copied().unwrap_or(0);
+        let last = self.cursor.max(furthest);
+        let occurrences = (0..=chars.len() - word.len())
+            .filter(|at| {
+                self.words.is_boundary(&chars, *at)
+                    && self.words.is_boundary(&chars, *at + word.len())
+            })
+            .filter(|at| chars[*at..*at + word.len()] == *word)
+            .map(|at| at + within)
+            .collect::<Vec<_>>();
+        let (after, before): (Vec<_>, Vec<_>) = occurrences.iter().partition(|at| **at > last);
+        let next = after
+            .into_iter()
+            .chain(before)
+            .find(|at| *at != self.cursor && !self.extra_cursors.contains(at));
+        let Some(next) = next else {
+            return false;
+        };
+        self.extra_cursors.push(next);
+        self.extra_cursors.sort_unstable();
+        true
+    }
+
+    /// Inserts `text` at every cursor, in place of any block selection, and
+    /// moves each cursor past it.
+    pub fn insert_str(&mut self, text: &str) {
+        let before = self.snapshot();
+        self.insert_text(text);
+        self.record(before, false);
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        self.take_block();
+        self.at_each_cursor(|state| {
+            let idx = char_to_byte_index(&state.value, state.cursor);
+            state.value.insert_str(idx, text);
+            state.cursor += text.chars().count();
+            state.preferred_column = None;
+            true
+        });
+    }
+
+    /// Like [`insert_str`](Self::insert_str), except that text with one line
+    /// per cursor goes in a line at each cursor, so a copied block pastes
+    /// back as a block.
+    pub fn paste_str(&mut self, text: &str) {
+        let before = self.snapshot();
+        self.paste_text(text);
+        self.record(before, false);
+    }
+
+    fn paste_text(&mut self, text: &str) {
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let lines = text.split('\n').collect::<Vec<_>>();
+        if lines.len() == 1 || lines.len() != self.extra_cursors.len() + 1 {
+            self.insert_text(text);
+            return;
+        }
+        self.take_block();
+        let mut lines = lines.into_iter();
+        self.at_each_cursor(|state| {
+            let line = lines.next().unwrap_or_default();
+            let idx = char_to_byte_index(&state.value, state.cursor);
+            state.value.insert_str(idx, line);
+            state.cursor += line.chars().count();
+            true
+        });
+    }
+
+    /// Runs `edit` with `cursor` set to each cursor in turn, front to back,
+    /// shifting the later ones by what it inserted or removed. With one
+    /// cursor this is just `edit`.
+    fn at_each_cursor(&mut self, mut edit: impl FnMut(&mut Self) -> bool) -> bool {
+        if self.extra_cursors.is_empty() {
+            return edit(self);
+        }
+        let mut cursors = self.extra_cursors.clone();
+        cursors.push(self.cursor);
+        cursors.sort_unstable();
+        let main = cursors.iter().position(|c| *c == self.cursor).unwrap_or(0);
+        let mut shift = 0isize;
+        let mut handled = false;
+        for cursor in &mut cursors {
+            let len = self.value.chars().count();
+            self.cursor = cursor.saturating_add_signed(shift).min(len);
+            // Each cursor keeps to its own column.
+            self.preferred_column = None;
+            handled |= edit(self);
+            shift += self.value.chars().count() as isize - len as isize;
+            *cursor = self.cursor;
+        }
+        self.cursor = cursors[main];
+        cursors.sort_unstable();
+        cursors.dedup();
+        cursors.retain(|c| *c != self.cursor);
+        self.extra_cursors = cursors;
+        self.preferred_column = None;
+        handled
+    }
+
     pub fn set_soft_wrap_width(&mut self, width: Option<usize>) {
         self.soft_wrap_width = width.map(|w| w.max(1));
     }
 
+    /// Whether long lines wrap (the default) or scroll sideways.
+    pub fn wraps(&self) -> bool {
+        !self.no_wrap
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.no_wrap = !wrap;
+        self.scroll_columns = 0;
+    }
+
+    pub fn scroll_columns(&self) -> usize {
+        self.scroll_columns
+    }
+
+    /// Scrolls sideways just enough to keep the cursor within `width`
+    /// columns. Does nothing while wrapping.
+    pub fn follow_cursor(&mut self, width: usize) {
+        if !self.no_wrap {
+            return;
+        }
+        let before = self.value.chars().take(self.cursor).collect::<Vec<_>>();
+        let line_start = before
+            .iter()
+            .rposition(|ch| *ch == '\n')
+            .map_or(0, |at| at + 1);
+        let column = before[line_start..]
+            .iter()
+            .map(|ch| unicode_width::UnicodeWidthChar::width(*ch).unwrap_or(0))
+            .sum::<usize>();
+        let width = width.max(1);
+        if column < self.scroll_columns {
+            self.scroll_columns = column;
+        } else if column >= self.scroll_columns + width {
+            self.scroll_columns = column + 1 - width;
+        }
+    }
+
     pub fn set_cursor_from_visual_position(
         &mut self,
         visual_row: usize,
         visual_col: usize,
         wrap_width: usize,
     ) {
-        let width = wrap_width.max(1);
-        self.cursor = cursor_for_visual_row_col(&self.value, width, visual_row, visual_col);
-        self.preferred_column = None;
+        let cursor = self.cursor_at_visual_position(visual_row, visual_col, wrap_width);
+        self.set_cursor(cursor);
+    }
+
+    /// [`toggle_cursor`](Self::toggle_cursor) at a clicked position.
+    pub fn toggle_cursor_at_visual_position(
+        &mut self,
+        visual_row: usize,
+        visual_col: usize,
+        wrap_width: usize,
+    ) {
+        let cursor = self.cursor_at_visual_position(visual_row, visual_col, wrap_width);
+        self.toggle_cursor(cursor);
+    }
+
+    fn cursor_at_visual_position(
+        &self,
+        visual_row: usize,
+        visual_col: usize,
+        wrap_width: usize,
+    ) -> usize {
+        let (width, col) = self.wrap_width_and_column(visual_col, wrap_width);
+        cursor_for_visual_row_col(&self.value, width, visual_row, col)
+    }
+
+    /// The line and column, in chars, at a visual position, counting on
+    /// past the end of the line.
+    fn line_col_at_visual_position(
+        &self,
+        visual_row: usize,
+        visual_col: usize,
+        wrap_width: usize,
+    ) -> (usize, usize) {
+        let (width, col) = self.wrap_width_and_column(visual_col, wrap_width);
+        let cursor = cursor_for_visual_row_col(&self.value, width, visual_row, col);
+        let (line, column) = line_col_for_cursor(&self.value, cursor);
+        let at_line_end = self.value.chars().nth(cursor).is_none_or(|ch| ch == '\n');
+        if !at_line_end {
+            return (line, column);
+        }
+        let (_, shown, _) = visual_row_col_for_cursor(&self.value, cursor, width);
+        (line, column + col.saturating_sub(shown))
+    }
+
+    fn wrap_width_and_column(&self, visual_col: usize, wrap_width: usize) -> (usize, usize) {
+        if self.no_wrap {
+            (usize::MAX, visual_col.saturating_add(self.scroll_columns))
+        } else {
+            (wrap_width.max(1), visual_col)
+        }
     }
 
     pub fn handle_input(&mut self, event: UiInputEvent) -> bool {
-        let UiInputEvent::Key(key) = event else {
+        let typing = match event {
+            UiInputEvent::Key(UiKeyInput::Undo) => return self.undo(),
+            UiInputEvent::Key(UiKeyInput::Redo) => return self.redo(),
+            UiInputEvent::Key(UiKeyInput::Char(ch)) => !ch.is_whitespace(),
+            _ => false,
+        };
+        let before = self.snapshot();
+        let handled = self.apply_input(event);
+        self.record(before, typing);
+        handled
+    }
+
+    /// Takes back the last edit, putting the cursors back where they were
+    /// before it. `false` when there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.history.undo.pop_back() else {
             return false;
         };
+        let current = self.snapshot();
+        self.history.redo.push(current);
+        self.restore(snapshot);
+        true
+    }
+
+    /// Makes the last undone edit again. `false` when there is nothing to
+    /// redo, as after a new edit.
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.history.redo.pop() else {
+            return false;
+        };
+        let current = self.snapshot();
+        self.history.undo.push_back(current);
+        self.restore(snapshot);
+        true
+    }
 
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            value: self.value.clone(),
+            cursor: self.cursor,
+            extra_cursors: self.extra_cursors.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.value = snapshot.value;
+        self.cursor = snapshot.cursor;
+        self.extra_cursors = snapshot.extra_cursors;
+        self.block = None;
+        self.preferred_column = None;
+        self.history.typed_to = None;
+    }
+
+    /// Remembers `before` for undo if the text has changed since. Typed
+    /// characters join the edit before them while the cursor has stayed
+    /// where that edit left it, so a word undoes in one step.
+    fn record(&mut self, before: Snapshot, typing: bool) {
+        if self.value == before.value {
+            return;
+        }
+        if !typing || self.history.typed_to != Some(before.cursor) {
+            self.history.undo.push_back(before);
+            if self.history.undo.len() > UNDO_LIMIT {
+                self.history.undo.pop_front();
+            }
+        }
+        self.history.redo.clear();
+        self.history.typed_to = typing.then_some(self.cursor);
+    }
+
+    fn apply_input(&mut self, event: UiInputEvent) -> bool {
+        let key = match event {
+            UiInputEvent::Key(key) => key,
+            UiInputEvent::Paste(text) => {
+                self.paste_text(&text);
+                return true;
+            }
+            _ => return false,
+        };
+        match key {
+            UiKeyInput::AddCursorAtNext => return self.add_cursor_at_next_occurrence(),
+            UiKeyInput::Copy => return self.copy(),
+            UiKeyInput::Cut => return self.cut_clip(),
+            UiKeyInput::Paste => {
+                let Some(text) = clipboard_text() else {
+                    return false;
+                };
+                self.paste_text(&text);
+                return true;
+            }
+            _ => {}
+        }
+        if matches!(
+            key,
+            UiKeyInput::KillToLineEnd
+                | UiKeyInput::KillToLineStart
+                | UiKeyInput::DeleteLine
+                | UiKeyInput::DuplicateLine
+                | UiKeyInput::MoveLineUp
+                | UiKeyInput::MoveLineDown
+                | UiKeyInput::JoinLines
+        ) {
+            // Line edits act on the main cursor's line alone.
+            self.block = None;
+            self.extra_cursors.clear();
+            return self.edit_lines(key);
+        }
+        let edit = matches!(key, UiKeyInput::Char(_) | UiKeyInput::Enter);
+        let delete = matches!(
+            key,
+            UiKeyInput::Backspace | UiKeyInput::BackspaceWord | UiKeyInput::Delete
+        );
+        if edit || delete {
+            // Deleting a block is all a delete key does to it.
+            if self.take_block() && delete {
+                return true;
+            }
+        } else {
+            self.block = None;
+        }
+        self.at_each_cursor(|state| state.assist_key(key) || state.handle_key(key))
+    }
+
+    pub fn edit_assist(&self) -> EditAssist {
+        self.assist
+    }
+
+    pub fn set_edit_assist(&mut self, assist: EditAssist) {
+        self.assist = assist;
+    }
+
+    pub fn word_rules(&self) -> WordRules {
+        self.words
+    }
+
+    pub fn set_word_rules(&mut self, words: WordRules) {
+        self.words = words;
+    }
+
+    fn prev_word_boundary(&self) -> usize {
+        let chars = self.value.chars().collect::<Vec<_>>();
+        self.words.prev_boundary(&chars, self.cursor)
+    }
+
+    fn next_word_boundary(&self) -> usize {
+        let chars = self.value.chars().collect::<Vec<_>>();
+        self.words.next_boundary(&chars, self.cursor)
+    }
+
+    /// Makes the edit-assist version of `key`'s edit at the cursor, if it
+    /// has one.
+    fn assist_key(&mut self, key: UiKeyInput) -> bool {
+        let Some(edit) = self.assist.edit(&self.value, self.cursor, key) else {
+            return false;
+        };
+        let start = char_to_byte_index(&self.value, edit.range.start);
+        let end = char_to_byte_index(&self.value, edit.range.end);
+        self.value.replace_range(start..end, &edit.text);
+        self.cursor = edit.cursor;
+        self.preferred_column = None;
+        true
+    }
+
+    fn handle_key(&mut self, key: UiKeyInput) -> bool {
         match key {
             UiKeyInput::Left => {
                 self.cursor = self.cursor.saturating_sub(1);
@@ -71,11 +628,11 @@ impl TextInputState {
                 true
             }
             UiKeyInput::WordLeft => {
-                self.cursor = prev_word_boundary(&self.value, self.cursor);
+                self.cursor = self.prev_word_boundary();
                 true
             }
             UiKeyInput::WordRight => {
-                self.cursor = next_word_boundary(&self.value, self.cursor);
+                self.cursor = self.next_word_boundary();
                 true
             }
             UiKeyInput::Home => {
@@ -108,7 +665,7 @@ impl TextInputState {
                 if self.cursor == 0 {
                     return false;
                 }
-                let start_char = prev_word_boundary(&self.value, self.cursor);
+                let start_char = self.prev_word_boundary();
                 let start = char_to_byte_index(&self.value, start_char);
                 let end = char_to_byte_index(&self.value, self.cursor);
                 self.value.replace_range(start..end, "");
@@ -156,6 +713,79 @@ impl TextInputState {
         }
     }
 
+    /// Makes the line edit `key` stands for at the cursor's line, then puts
+    /// the cursor where the edit left that text. `false` when there is
+    /// nothing to do, as for moving the first line up.
+    fn edit_lines(&mut self, key: UiKeyInput) -> bool {
+        let (line, col) = line_col_for_cursor(&self.value, self.cursor);
+        let mut lines = self
+            .value
+            .split('\n')
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let last = lines.len() - 1;
+        let (line, col) = match key {
+            // At the end of a line, the line break goes, as in Emacs.
+            UiKeyInput::KillToLineEnd if col < lines[line].chars().count() => {
+                lines[line] = lines[line].chars().take(col).collect();
+                (line, col)
+            }
+            UiKeyInput::KillToLineEnd | UiKeyInput::JoinLines if line == last => return false,
+            UiKeyInput::KillToLineEnd => {
+                let next = lines.remove(line + 1);
+                lines[line].push_str(&next);
+                (line, col)
+            }
+            UiKeyInput::KillToLineStart if col == 0 => return false,
+            UiKeyInput::KillToLineStart => {
+                lines[line] = lines[line].chars().skip(col).collect();
+                (line, 0)
+            }
+            UiKeyInput::DeleteLine if last == 0 && lines[0].is_empty() => return false,
+            UiKeyInput::DeleteLine if last == 0 => {
+                lines[0].clear();
+                (0, 0)
+            }
+            UiKeyInput::DeleteLine => {
+                lines.remove(line);
+                (line.min(last - 1), col)
+            }
+            UiKeyInput::DuplicateLine => {
+                lines.insert(line + 1, lines[line].clone());
+                (line + 1, col)
+            }
+            UiKeyInput::MoveLineUp if line == 0 => return false,
+            UiKeyInput::MoveLineUp => {
+                lines.swap(line, line - 1);
+                (line - 1, col)
+            }
+            UiKeyInput::MoveLineDown if line == last => return false,
+            UiKeyInput::MoveLineDown => {
+                lines.swap(line, line + 1);
+                (line + 1, col)
+            }
+            // One space in place of the break and the next line's indent,
+            // as Vim's `J` does; the cursor goes where they met.
+            UiKeyInput::JoinLines => {
+                let next = lines.remove(line + 1);
+                let next = next.trim_start();
+                let joined = &mut lines[line];
+                joined.truncate(joined.trim_end().len());
+                let at = joined.chars().count();
+                if !joined.is_empty() && !next.is_empty() {
+                    joined.push(' ');
+                }
+                joined.push_str(next);
+                (line, at)
+            }
+            _ => return false,
+        };
+        self.value = lines.join("\n");
+        self.cursor = cursor_for_line_col(&self.value, line, col);
+        self.preferred_column = None;
+        true
+    }
+
     fn move_vertical(&mut self, delta: i32) {
         let (line, col) = line_col_for_cursor(&self.value, self.cursor);
         let total_lines = line_count(&self.value);
@@ -178,7 +808,8 @@ impl TextInputState {
     }
 
     fn move_visual_vertical(&mut self, delta: i32, wrap_width: usize) {
-        let (row, col, total_rows) = visual_row_col_for_cursor(&self.value, self.cursor, wrap_width);
+        let (row, col, total_rows) =
+            visual_row_col_for_cursor(&self.value, self.cursor, wrap_width);
         if delta < 0 && row == 0 {
             self.cursor = 0;
             self.preferred_column = None;
@@ -198,6 +829,25 @@ impl TextInputState {
     }
 }
 
+/// Edits kept for undo; the oldest go first past this.
+const UNDO_LIMIT: usize = 200;
+
+/// The states a [`TextInputState`] can undo back to and redo forward to.
+#[derive(Clone, Debug, Default)]
+struct History {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// Where the last edit left the cursor, when it was a typed character.
+    typed_to: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Snapshot {
+    value: String,
+    cursor: usize,
+    extra_cursors: Vec<usize>,
+}
+
 fn char_to_byte_index(value: &str, char_index: usize) -> usize {
     if char_index == 0 {
         return 0;
@@ -259,7 +909,11 @@ fn cursor_for_line_col(value: &str, target_line: usize, target_col: usize) -> us
     idx
 }
 
-fn visual_row_col_for_cursor(value: &str, cursor: usize, wrap_width: usize) -> (usize, usize, usize) {
+fn visual_row_col_for_cursor(
+    value: &str,
+    cursor: usize,
+    wrap_width: usize,
+) -> (usize, usize, usize) {
     let width = wrap_width.max(1);
     let chars: Vec<char> = value.chars().collect();
     let mut row = 0usize;
@@ -312,7 +966,12 @@ fn total_visual_rows(value: &str, wrap_width: usize) -> usize {
     rows
 }
 
-fn cursor_for_visual_row_col(value: &str, wrap_width: usize, target_row: usize, target_col: usize) -> usize {
+fn cursor_for_visual_row_col(
+    value: &str,
+    wrap_width: usize,
+    target_row: usize,
+    target_col: usize,
+) -> usize {
     let width = wrap_width.max(1);
     let chars: Vec<char> = value.chars().collect();
     let mut row = 0usize;
@@ -361,30 +1020,251 @@ fn cursor_for_visual_row_col(value: &str, wrap_width: usize, target_row: usize,
     best.or(last).or(first).unwrap_or(end)
 }
 
-fn prev_word_boundary(value: &str, cursor: usize) -> usize {
-    let chars: Vec<char> = value.chars().collect();
-    let mut i = cursor.min(chars.len());
+/// A rectangle of text between two (line, column) corners, in chars.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Block {
+    anchor: (usize, usize),
+    head: (usize, usize),
+}
 
-    while i > 0 && chars[i - 1].is_whitespace() {
-        i -= 1;
-    }
-    while i > 0 && !chars[i - 1].is_whitespace() {
-        i -= 1;
+impl Block {
+    fn lines(&self) -> Range<usize> {
+        self.anchor.0.min(self.head.0)..self.anchor.0.max(self.head.0) + 1
     }
 
-    i
+    fn columns(&self) -> Range<usize> {
+        self.anchor.1.min(self.head.1)..self.anchor.1.max(self.head.1)
+    }
 }
 
-fn next_word_boundary(value: &str, cursor: usize) -> usize {
-    let chars: Vec<char> = value.chars().collect();
-    let mut i = cursor.min(chars.len());
+#[cfg(test)]
+mod tests {
+    use super::super::selection::{BackendClipboard, set_backend_clipboard, take_copy};
+    use super::*;
 
-    while i < chars.len() && chars[i].is_whitespace() {
-        i += 1;
+    fn press(state: &mut TextInputState, key: UiKeyInput) {
+        state.handle_input(UiInputEvent::Key(key));
     }
-    while i < chars.len() && !chars[i].is_whitespace() {
-        i += 1;
+
+    fn type_str(state: &mut TextInputState, text: &str) {
+        for ch in text.chars() {
+            press(state, UiKeyInput::Char(ch));
+        }
     }
 
-    i
+    #[test]
+    fn copy_and_cut_take_the_cursor_line_without_a_selection() {
+        set_backend_clipboard(BackendClipboard::Native);
+        let mut state = TextInputState::new("first\nsecond\nthird");
+        state.set_cursor(8);
+
+        assert!(state.copy());
+        assert_eq!(take_copy().as_deref(), Some("second\n"));
+        assert_eq!(state.value(), "first\nsecond\nthird");
+
+        assert!(state.cut());
+        assert_eq!(take_copy().as_deref(), Some("second\n"));
+        assert_eq!(state.value(), "first\nthird");
+        set_backend_clipboard(BackendClipboard::None);
+    }
+
+    #[test]
+    fn undo_takes_back_a_typed_word_at_once_and_puts_the_cursor_back() {
+        let mut state = TextInputState::new("say ");
+        type_str(&mut state, "hello");
+        press(&mut state, UiKeyInput::Char(' '));
+        type_str(&mut state, "there");
+
+        assert!(state.undo());
+        assert_eq!(state.value(), "say hello ");
+        assert_eq!(state.cursor(), 10);
+        assert!(state.undo());
+        assert!(state.undo());
+        assert_eq!(state.value(), "say ");
+        assert_eq!(state.cursor(), 4);
+        assert!(!state.undo());
+
+        assert!(state.redo());
+        assert_eq!(state.value(), "say hello");
+        assert_eq!(state.cursor(), 9);
+    }
+
+    #[test]
+    fn a_new_edit_drops_what_could_be_redone() {
+        let mut state = TextInputState::new("");
+        type_str(&mut state, "one");
+        assert!(state.undo());
+        type_str(&mut state, "two");
+        assert!(!state.redo());
+        assert_eq!(state.value(), "two");
+    }
+
+    #[test]
+    fn loaded_text_starts_undo_afresh() {
+        let mut state = TextInputState::new("");
+        type_str(&mut state, "draft");
+        state.load_value("note");
+        assert!(!state.undo());
+        assert_eq!(state.value(), "note");
+    }
+
+    #[test]
+    fn word_motions_stop_where_the_word_rules_say() {
+        let mut state = TextInputState::new("foo.bar(baz)");
+        press(&mut state, UiKeyInput::WordLeft);
+        assert_eq!(state.cursor(), 11);
+        press(&mut state, UiKeyInput::WordLeft);
+        assert_eq!(state.cursor(), 8);
+        state.set_cursor(0);
+        press(&mut state, UiKeyInput::WordRight);
+        assert_eq!(state.cursor(), 3);
+
+        state.set_word_rules(WordRules::Whitespace);
+        state.set_value("foo.bar baz");
+        state.set_cursor(11);
+        press(&mut state, UiKeyInput::WordLeft);
+        assert_eq!(state.cursor(), 8);
+        press(&mut state, UiKeyInput::WordLeft);
+        assert_eq!(state.cursor(), 0);
+
+        state.set_word_rules(WordRules::SubWord);
+        state.set_value("parseHttpRequest");
+        state.set_cursor(0);
+        press(&mut state, UiKeyInput::WordRight);
+        assert_eq!(state.cursor(), 5);
+        press(&mut state, UiKeyInput::WordRight);
+        assert_eq!(state.cursor(), 9);
+
+        state.set_value("max_line_len");
+        state.set_cursor(12);
+        press(&mut state, UiKeyInput::BackspaceWord);
+        assert_eq!(state.value(), "max_line_");
+    }
+
+    #[test]
+    fn line_edits_act_on_the_cursor_line() {
+        let mut state = TextInputState::new("one\ntwo\nthree");
+        state.set_cursor(5);
+        press(&mut state, UiKeyInput::DuplicateLine);
+        assert_eq!(state.value(), "one\ntwo\ntwo\nthree");
+        assert_eq!(state.cursor(), 9);
+        assert!(state.undo());
+
+        press(&mut state, UiKeyInput::MoveLineUp);
+        assert_eq!(state.value(), "two\none\nthree");
+        assert_eq!(state.cursor(), 1);
+        assert!(state.undo());
+
+        press(&mut state, UiKeyInput::DeleteLine);
+        assert_eq!(state.value(), "one\nthree");
+        assert_eq!(state.cursor(), 5);
+        assert!(state.undo());
+
+        state.set_cursor(5);
+        press(&mut state, UiKeyInput::KillToLineStart);
+        assert_eq!(state.value(), "one\nwo\nthree");
+        assert_eq!(state.cursor(), 4);
+        assert!(state.undo());
+
+        state.set_cursor(5);
+        press(&mut state, UiKeyInput::KillToLineEnd);
+        assert_eq!(state.value(), "one\nt\nthree");
+        // At the end of the line, the line break goes.
+        press(&mut state, UiKeyInput::KillToLineEnd);
+        assert_eq!(state.value(), "one\ntthree");
+
+        state.set_cursor(state.value().chars().count());
+        assert!(!state.handle_input(UiInputEvent::Key(UiKeyInput::MoveLineDown)));
+    }
+
+    #[test]
+    fn joining_lines_leaves_one_space_between_them() {
+        let mut state = TextInputState::new("fn main() {  \n    body");
+        state.set_cursor(0);
+        press(&mut state, UiKeyInput::JoinLines);
+        assert_eq!(state.value(), "fn main() { body");
+        assert_eq!(state.cursor(), 11);
+    }
+
+    #[test]
+    fn the_next_occurrence_gets_a_cursor_and_typing_edits_at_each() {
+        let mut state = TextInputState::new("let a = a + a;");
+        state.set_cursor(5);
+        assert!(state.add_cursor_at_next_occurrence());
+        assert!(state.add_cursor_at_next_occurrence());
+        assert_eq!(state.extra_cursors(), [9, 13]);
+        assert!(!state.add_cursor_at_next_occurrence());
+
+        press(&mut state, UiKeyInput::Char('b'));
+        assert_eq!(state.value(), "let ab = ab + ab;");
+        assert_eq!(state.cursor(), 6);
+        assert_eq!(state.extra_cursors(), [11, 16]);
+    }
+
+    #[test]
+    fn toggled_cursors_each_take_the_edit() {
+        let mut state = TextInputState::new("x\nx");
+        state.set_cursor(1);
+        state.toggle_cursor(3);
+        press(&mut state, UiKeyInput::Char('!'));
+        assert_eq!(state.value(), "x!\nx!");
+
+        state.toggle_cursor(5);
+        assert!(state.extra_cursors().is_empty());
+        press(&mut state, UiKeyInput::Backspace);
+        assert_eq!(state.value(), "x\nx!");
+    }
+
+    #[test]
+    fn a_block_selection_copies_deletes_and_pastes_by_column() {
+        let mut state = TextInputState::new("abcd\nefgh\nijkl");
+        state.select_block_at_visual_positions((0, 1), (2, 3), 80);
+        assert_eq!(state.block_selection(), Some((0..3, 1..3)));
+        assert_eq!(state.block_text().as_deref(), Some("bc\nfg\njk"));
+        assert_eq!(state.cursor(), 13);
+        assert_eq!(state.extra_cursors(), [3, 8]);
+
+        press(&mut state, UiKeyInput::Backspace);
+        assert_eq!(state.value(), "ad\neh\nil");
+        assert_eq!(state.block_selection(), None);
+        assert_eq!(state.extra_cursors(), [1, 4]);
+
+        state.paste_str("1\n2\n3\n");
+        assert_eq!(state.value(), "a1d\ne2h\ni3l");
+    }
+
+    #[test]
+    fn edit_assist_closes_pairs_and_steps_over_them() {
+        let mut state = TextInputState::new("");
+        state.set_edit_assist(EditAssist::ON);
+        press(&mut state, UiKeyInput::Char('('));
+        assert_eq!((state.value(), state.cursor()), ("()", 1));
+        press(&mut state, UiKeyInput::Char(')'));
+        assert_eq!((state.value(), state.cursor()), ("()", 2));
+
+        state.set_cursor(1);
+        press(&mut state, UiKeyInput::Backspace);
+        assert_eq!(state.value(), "");
+
+        // An apostrophe in a word opens no quote.
+        type_str(&mut state, "don'");
+        assert_eq!(state.value(), "don'");
+    }
+
+    #[test]
+    fn edit_assist_keeps_the_indentation_of_the_broken_line() {
+        let mut state = TextInputState::new("    let a = 1;");
+        state.set_edit_assist(EditAssist::ON);
+        press(&mut state, UiKeyInput::Enter);
+        assert_eq!(
+            (state.value(), state.cursor()),
+            ("    let a = 1;\n    ", 19)
+        );
+
+        state.set_value("    if x {}");
+        state.set_cursor(10);
+        press(&mut state, UiKeyInput::Enter);
+        assert_eq!(state.value(), "    if x {\n        \n    }");
+        assert_eq!(state.cursor(), 19);
+    }
 }
@@ -0,0 +1,40 @@
+use std::time::{Duration, Instant};
+
+use super::{FocusEntry, FocusPath, FocusState};
+
+/// How long focus has to rest on an entry before its tooltip shows.
+const TOOLTIP_DELAY: Duration = Duration::from_millis(600);
+
+/// Where focus came to rest, and when.
+#[derive(Clone, Debug)]
+pub(crate) struct Dwell {
+    path: FocusPath,
+    since: Instant,
+}
+
+impl FocusState {
+    /// The focused entry's tooltip once focus has rested on it for a moment.
+    /// Call once per frame; backends draw it with
+    /// [`Node::with_tooltip`](crate::Node::with_tooltip).
+    pub fn tooltip(&mut self, entries: &[FocusEntry]) -> Option<(FocusPath, String)> {
+        let Some(entry) = self.focused_entry(entries) else {
+            self.dwell = None;
+            return None;
+        };
+        let now = Instant::now();
+        let dwell = match self.dwell.take() {
+            Some(dwell) if dwell.path == entry.path => dwell,
+            _ => Dwell {
+                path: entry.path.clone(),
+                since: now,
+            },
+        };
+        let shown = now.duration_since(dwell.since) >= TOOLTIP_DELAY;
+        self.dwell = Some(dwell);
+        if !shown || self.quick_jump.is_some() {
+            return None;
+        }
+        let tooltip = entry.tooltip.clone()?;
+        Some((entry.path.clone(), tooltip))
+    }
+}
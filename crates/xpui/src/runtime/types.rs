@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use crate::FocusId;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -21,39 +23,145 @@ pub enum UiKeyInput {
     Submit,
     Esc,
     Interrupt,
+    QuickJump,
+    /// Ctrl+V, for pastes the terminal or window does not handle itself.
+    Paste,
+    /// Ctrl+D, for another cursor at the next occurrence of the word under
+    /// the cursor.
+    AddCursorAtNext,
+    /// Ctrl+Shift+C, for copies the terminal or window does not make itself,
+    /// such as a block selection.
+    Copy,
+    /// Ctrl+X, for cutting the block selection, or the line without one.
+    Cut,
+    /// Ctrl+Z, for taking back the last edit.
+    Undo,
+    /// Ctrl+Shift+Z or Ctrl+Y, for making an undone edit again.
+    Redo,
+    /// Alt+1 to Alt+9, for the panel at that place in an activity bar.
+    Panel(u8),
+    /// Ctrl+K, for deleting from the cursor to the end of its line.
+    KillToLineEnd,
+    /// Ctrl+U, for deleting from the start of the line to the cursor.
+    KillToLineStart,
+    /// Ctrl+Shift+K, for deleting the cursor's line.
+    DeleteLine,
+    /// Alt+Shift+Down, for a copy of the cursor's line below it.
+    DuplicateLine,
+    /// Alt+Up, for swapping the cursor's line with the one above.
+    MoveLineUp,
+    /// Alt+Down, for swapping the cursor's line with the one below.
+    MoveLineDown,
+    /// Alt+J, for joining the next line onto the cursor's.
+    JoinLines,
     Char(char),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum UiInputEvent {
     Key(UiKeyInput),
     ScrollLines(i16),
-    MouseDown { x: u16, y: u16 },
+    MouseDown {
+        x: u16,
+        y: u16,
+        button: UiMouseButton,
+        /// Alt (Option) was held, which places another text cursor.
+        alt: bool,
+    },
+    /// The pointer moved with the left button held.
+    MouseDrag {
+        x: u16,
+        y: u16,
+        /// Alt (Option) was held, which selects a block of text.
+        alt: bool,
+    },
+    /// The left button was let go, ending a drag.
+    MouseUp {
+        x: u16,
+        y: u16,
+    },
+    /// Text pasted into the terminal in one piece, rather than typed.
+    Paste(String),
+    /// Files dragged onto the window from the OS. Terminals paste the paths
+    /// as text instead.
+    DropFiles(Vec<PathBuf>),
+    /// An item of the window's menu bar was picked; the command is the one
+    /// the app gave it.
+    MenuCommand(String),
+    /// The button with this focus id was pressed: Enter or Space while it
+    /// had focus, or a click on it.
+    Activate(FocusId),
     Tick,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiMouseButton {
+    Left,
+    Right,
+    /// Pastes the primary selection on Linux.
+    Middle,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FocusNavOutcome {
     Ignored,
     Handled,
     RequestQuit,
+    /// The focused button was pressed; the app gets
+    /// [`UiInputEvent::Activate`] for it.
+    Activate(FocusId),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum FocusKind {
     Generic,
     TextInput,
     ScrollRegion,
+    Button,
+    /// One row of a list; arrows move between rows.
+    ListItem,
+    Tab,
+    /// The root of a dialog or popup.
+    Dialog,
 }
 
+/// One step from a node to its child: the child's position, or its key when
+/// it has one, so keyed children keep their path when siblings come and go.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct FocusPath(pub Vec<usize>);
+pub enum PathSegment {
+    Index(usize),
+    Key(String),
+}
+
+/// Where a focusable node sits in the tree. Paths only identify nodes; the
+/// order of entries is the order they were collected in.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FocusPath(pub Vec<PathSegment>);
+
+impl FocusPath {
+    pub fn depth(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The first `depth` segments.
+    pub fn prefix(&self, depth: usize) -> &[PathSegment] {
+        &self.0[..depth.min(self.0.len())]
+    }
+
+    /// Whether `self` lies strictly below `ancestor`.
+    pub fn is_descendant_of(&self, ancestor: &FocusPath) -> bool {
+        self.0.len() > ancestor.0.len() && self.0.starts_with(&ancestor.0)
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct FocusEntry {
     pub id: FocusId,
     pub path: FocusPath,
     pub kind: FocusKind,
+    /// What to call the entry in breadcrumbs and announcements.
+    pub label: Option<String>,
+    pub tooltip: Option<String>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -70,3 +178,29 @@ impl Default for WindowSize {
         }
     }
 }
+
+impl WindowSize {
+    /// The smallest window apps are laid out in; below it the terminal
+    /// backend shows a notice instead of a squeezed layout.
+    pub const MIN: Self = Self {
+        width: 20.0,
+        height: 4.0,
+    };
+
+    pub fn is_too_small(&self) -> bool {
+        self.width < Self::MIN.width || self.height < Self::MIN.height
+    }
+}
+
+/// Where a graphics window was and how far it was zoomed, to open the next
+/// one the same way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowState {
+    /// Position and size, in logical pixels.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Text scale; 1.0 is the default size.
+    pub zoom: f32,
+}
@@ -0,0 +1,120 @@
+/// Where word motions stop in a [`TextInputState`](super::TextInputState):
+/// Ctrl+Left and Ctrl+Right, Ctrl+Backspace, and the word Ctrl+D looks for.
+/// Set with [`set_word_rules`](super::TextInputState::set_word_rules).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WordRules {
+    /// Only whitespace separates words, so `foo.bar(baz)` is one.
+    Whitespace,
+    /// Letters, digits and `_` make words, and runs of other punctuation are
+    /// words of their own: `foo.bar(baz)` is `foo`, `.`, `bar`, `(`, `baz`
+    /// and `)`.
+    #[default]
+    Punctuation,
+    /// As `Punctuation`, and camelCase and snake_case names split into their
+    /// parts: `parseHttpRequest` stops before `Http` and `Request`, and
+    /// `max_line_len` after each `_`.
+    SubWord,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Space,
+    Word,
+    Punctuation,
+}
+
+impl WordRules {
+    pub const ALL: [Self; 3] = [Self::Whitespace, Self::Punctuation, Self::SubWord];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Whitespace => "whitespace",
+            Self::Punctuation => "punctuation",
+            Self::SubWord => "subword",
+        }
+    }
+
+    fn class(self, ch: char) -> Class {
+        if ch.is_whitespace() {
+            Class::Space
+        } else if self == Self::Whitespace || ch.is_alphanumeric() || ch == '_' {
+            Class::Word
+        } else {
+            Class::Punctuation
+        }
+    }
+
+    /// Whether a word ends or starts between `chars[at - 1]` and
+    /// `chars[at]`. The ends of the text are boundaries.
+    pub(super) fn is_boundary(self, chars: &[char], at: usize) -> bool {
+        if at == 0 || at >= chars.len() {
+            return true;
+        }
+        let (before, after) = (chars[at - 1], chars[at]);
+        let class = self.class(after);
+        if self.class(before) != class {
+            return true;
+        }
+        if self != Self::SubWord || class != Class::Word {
+            return false;
+        }
+        // `max_|line`, `parse|Http`, `v2|Beta`, and `HTTP|Server`.
+        (before == '_' && after != '_')
+            || (after.is_uppercase() && (before.is_lowercase() || before.is_numeric()))
+            || (before.is_uppercase()
+                && after.is_uppercase()
+                && chars.get(at + 1).is_some_and(|next| next.is_lowercase()))
+    }
+
+    /// The start of the word before `cursor`, past any whitespace.
+    pub(super) fn prev_boundary(self, chars: &[char], cursor: usize) -> usize {
+        let mut at = cursor.min(chars.len());
+        while at > 0 && self.class(chars[at - 1]) == Class::Space {
+            at -= 1;
+        }
+        while at > 0 {
+            at -= 1;
+            if self.is_boundary(chars, at) {
+                break;
+            }
+        }
+        at
+    }
+
+    /// The end of the word after `cursor`, past any whitespace.
+    pub(super) fn next_boundary(self, chars: &[char], cursor: usize) -> usize {
+        let mut at = cursor.min(chars.len());
+        while at < chars.len() && self.class(chars[at]) == Class::Space {
+            at += 1;
+        }
+        while at < chars.len() {
+            at += 1;
+            if self.is_boundary(chars, at) {
+                break;
+            }
+        }
+        at
+    }
+
+    /// The word `cursor` is in or at the end of, as a char range; empty when
+    /// there is whitespace on both sides.
+    pub(super) fn word_around(self, chars: &[char], cursor: usize) -> (usize, usize) {
+        let cursor = cursor.min(chars.len());
+        let is_word = |at: usize| chars.get(at).is_some_and(|ch| !ch.is_whitespace());
+        // The cursor rests after a word just typed, so the one before wins.
+        let at = match cursor.checked_sub(1) {
+            Some(before) if is_word(before) => before,
+            _ if is_word(cursor) => cursor,
+            _ => return (cursor, cursor),
+        };
+        let mut start = at;
+        while !self.is_boundary(chars, start) {
+            start -= 1;
+        }
+        let mut end = at + 1;
+        while !self.is_boundary(chars, end) {
+            end += 1;
+        }
+        (start, end)
+    }
+}
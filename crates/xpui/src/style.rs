@@ -1,7 +1,7 @@
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Rgb(pub u32);
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct TextStyle {
     pub bold: bool,
     pub italic: bool,
@@ -11,6 +11,8 @@ pub struct TextStyle {
     pub cursor_anchor: bool,
     pub cursor_after: bool,
     pub bg: Option<Rgb>,
+    /// Hyperlink target; terminals without OSC 8 show plain text.
+    pub link: Option<String>,
 }
 
 impl TextStyle {
@@ -53,12 +55,19 @@ impl TextStyle {
         self.bg = Some(color);
         self
     }
+
+    pub fn link(mut self, url: impl Into<String>) -> Self {
+        self.link = Some(url.into());
+        self
+    }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Hash)]
 pub struct BoxStyle {
     pub bg: Option<Rgb>,
     pub text_color: Option<Rgb>,
+    /// A fixed width in columns; text inside wraps to it.
+    pub width: Option<u16>,
 }
 
 impl BoxStyle {
@@ -71,6 +80,11 @@ impl BoxStyle {
         self.text_color = Some(color);
         self
     }
+
+    pub fn width(mut self, columns: u16) -> Self {
+        self.width = Some(columns);
+        self
+    }
 }
 
 pub fn rgb(hex: u32) -> Rgb {
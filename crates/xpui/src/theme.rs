@@ -0,0 +1,169 @@
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::style::Rgb;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+/// The colors widgets draw with. Apps pick one with [`set_theme`] and read it
+/// back with [`theme`] for their own chrome.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Theme {
+    /// The background this theme is meant for.
+    pub appearance: Appearance,
+    pub text: Rgb,
+    /// Secondary text: titles, hints, line numbers.
+    pub muted: Rgb,
+    /// Text that recedes further: placeholders, separators.
+    pub faint: Rgb,
+    /// Raised boxes such as menus and status bars.
+    pub surface: Rgb,
+    /// Borders, tooltips and loading placeholders.
+    pub border: Rgb,
+    pub accent: Rgb,
+    /// The highlighted row of a menu, and its text.
+    pub selection: Rgb,
+    pub selection_text: Rgb,
+    /// Quick-jump badges, and their text.
+    pub badge: Rgb,
+    pub badge_text: Rgb,
+    /// Added and removed diff lines, and anything else marked good or bad.
+    pub added: Rgb,
+    pub removed: Rgb,
+    pub syntax: SyntaxColors,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SyntaxColors {
+    pub keyword: Rgb,
+    pub string: Rgb,
+    pub comment: Rgb,
+    /// Numbers and other constants, such as `true`.
+    pub number: Rgb,
+    /// Names of functions where they are defined or called.
+    pub function: Rgb,
+    pub type_name: Rgb,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            appearance: Appearance::Dark,
+            text: Rgb(0xe6edf3),
+            muted: Rgb(0x8b949e),
+            faint: Rgb(0x6e7681),
+            surface: Rgb(0x161b22),
+            border: Rgb(0x30363d),
+            accent: Rgb(0x2f81f7),
+            selection: Rgb(0x1f6feb),
+            selection_text: Rgb(0xf0f6fc),
+            badge: Rgb(0xf2cc60),
+            badge_text: Rgb(0x0d1117),
+            added: Rgb(0x3fb950),
+            removed: Rgb(0xf85149),
+            syntax: SyntaxColors {
+                keyword: Rgb(0xff7b72),
+                string: Rgb(0xa5d6ff),
+                comment: Rgb(0x8b949e),
+                number: Rgb(0x79c0ff),
+                function: Rgb(0xd2a8ff),
+                type_name: Rgb(0xffa657),
+            },
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            appearance: Appearance::Light,
+            text: Rgb(0x1f2328),
+            muted: Rgb(0x656d76),
+            faint: Rgb(0x8c959f),
+            surface: Rgb(0xf6f8fa),
+            border: Rgb(0xd0d7de),
+            accent: Rgb(0x0969da),
+            selection: Rgb(0x0969da),
+            selection_text: Rgb(0xffffff),
+            badge: Rgb(0xbf8700),
+            badge_text: Rgb(0xffffff),
+            added: Rgb(0x1a7f37),
+            removed: Rgb(0xcf222e),
+            syntax: SyntaxColors {
+                keyword: Rgb(0xcf222e),
+                string: Rgb(0x0a3069),
+                comment: Rgb(0x6e7781),
+                number: Rgb(0x0550ae),
+                function: Rgb(0x8250df),
+                type_name: Rgb(0x953800),
+            },
+        }
+    }
+
+    /// This theme with blue and orange in place of green and red, which
+    /// stay apart for red-green color blindness.
+    pub fn color_blind(self) -> Self {
+        let (added, removed) = match self.appearance {
+            Appearance::Light => (Rgb(0x0969da), Rgb(0xbc4c00)),
+            Appearance::Dark => (Rgb(0x58a6ff), Rgb(0xf0883e)),
+        };
+        Self {
+            added,
+            removed,
+            ..self
+        }
+    }
+
+    pub fn for_appearance(appearance: Appearance) -> Self {
+        match appearance {
+            Appearance::Light => Self::light(),
+            Appearance::Dark => Self::dark(),
+        }
+    }
+}
+
+static THEME: RwLock<Option<Theme>> = RwLock::new(None);
+
+/// The current theme; dark until [`set_theme`] says otherwise.
+pub fn theme() -> Theme {
+    THEME
+        .read()
+        .ok()
+        .and_then(|theme| theme.clone())
+        .unwrap_or_else(Theme::dark)
+}
+
+/// Switches every widget to `theme` from the next frame on.
+pub fn set_theme(theme: Theme) {
+    if let Ok(mut current) = THEME.write() {
+        *current = Some(theme);
+    }
+}
+
+const UNKNOWN: u8 = 0;
+const LIGHT: u8 = 1;
+const DARK: u8 = 2;
+
+static SYSTEM_APPEARANCE: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+/// Whether the window or terminal is light or dark, as the backend last saw
+/// it: the OS appearance under gpui, the terminal background under cpui.
+/// `None` until the backend found out, or when it cannot tell.
+pub fn system_appearance() -> Option<Appearance> {
+    match SYSTEM_APPEARANCE.load(Ordering::Relaxed) {
+        LIGHT => Some(Appearance::Light),
+        DARK => Some(Appearance::Dark),
+        _ => None,
+    }
+}
+
+pub(crate) fn set_system_appearance(appearance: Option<Appearance>) {
+    let value = match appearance {
+        Some(Appearance::Light) => LIGHT,
+        Some(Appearance::Dark) => DARK,
+        None => UNKNOWN,
+    };
+    SYSTEM_APPEARANCE.store(value, Ordering::Relaxed);
+}
@@ -0,0 +1,78 @@
+use crate::{
+    node::{FocusId, IntoNode, Node},
+    runtime::FocusKind,
+    style::{BoxStyle, TextStyle},
+    theme::theme,
+    widgets::{container, text},
+};
+
+/// A labelled box that does something when pressed: Enter or Space while it
+/// has focus, or a click on it, sends
+/// [`UiInputEvent::Activate`](crate::UiInputEvent::Activate) with its focus
+/// id to the app.
+pub struct ButtonWidget {
+    label: String,
+    focus_id: FocusId,
+    key: Option<String>,
+    focused: bool,
+    pressed: bool,
+}
+
+impl ButtonWidget {
+    pub fn new(label: impl Into<String>, focus_id: FocusId) -> Self {
+        Self {
+            label: label.into(),
+            focus_id,
+            key: None,
+            focused: false,
+            pressed: false,
+        }
+    }
+
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Drawn highlighted, as the focused row of a menu is.
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
+
+    /// Drawn held down, for a button that stays on until pressed again.
+    pub fn pressed(mut self, pressed: bool) -> Self {
+        self.pressed = pressed;
+        self
+    }
+}
+
+impl IntoNode for ButtonWidget {
+    fn into_node(self) -> Node {
+        let theme = theme();
+        let (style, bg) = if self.focused {
+            (
+                TextStyle::new().color(theme.selection_text).bold(),
+                theme.selection,
+            )
+        } else if self.pressed {
+            (TextStyle::new().color(theme.surface).bold(), theme.accent)
+        } else {
+            (TextStyle::new().color(theme.text), theme.surface)
+        };
+        let mut out = container(text("").run(format!(" {} ", self.label), style))
+            .style(BoxStyle::default().bg(bg))
+            .hover_style(BoxStyle::default().bg(theme.border))
+            .focus(self.focus_id)
+            .focus_kind(FocusKind::Button)
+            .label(self.label);
+        if let Some(key) = self.key {
+            out = out.key(key);
+        }
+        out.into_node()
+    }
+}
+
+pub fn button(label: impl Into<String>, focus_id: FocusId) -> ButtonWidget {
+    ButtonWidget::new(label, focus_id)
+}
@@ -0,0 +1,39 @@
+use crate::{
+    node::{FocusId, IntoNode},
+    runtime::{ContextMenuState, FocusKind},
+    style::{BoxStyle, TextStyle},
+    theme::theme,
+    widgets::{ContainerWidget, container, text},
+};
+
+/// The menu as a box of rows, the highlighted one marked, each followed by
+/// its key. The box is a dialog focus entry with `focus_id`, so it can be
+/// trapped while open.
+pub fn context_menu(state: &ContextMenuState, focus_id: FocusId) -> ContainerWidget {
+    let theme = theme();
+    let title_style = TextStyle::new().color(theme.muted).bold();
+    let key_style = TextStyle::new().color(theme.faint);
+    let selected_style = TextStyle::new()
+        .color(theme.selection_text)
+        .bg(theme.selection)
+        .bold();
+
+    let mut body = text("").run(format!(" {}", state.title()), title_style);
+    for (index, item) in state.items().iter().enumerate() {
+        body = body.run("\n", TextStyle::default());
+        let row = format!(" {} ", item.label);
+        body = if index == state.selected() {
+            body.run(row, selected_style.clone())
+        } else {
+            body.run(row, TextStyle::default())
+        };
+        if let Some(key) = item.key {
+            body = body.run(format!(" {key}"), key_style.clone());
+        }
+    }
+    container(body.into_node())
+        .style(BoxStyle::default().bg(theme.surface))
+        .focus(focus_id)
+        .focus_kind(FocusKind::Dialog)
+        .label(state.title())
+}
@@ -1,5 +1,6 @@
 use crate::{
-    node::{Axis, Container, FocusId, IntoNode, Node, ScrollView, Stack},
+    node::{Axis, Container, FocusId, IntoNode, Node, Overlay, ScrollView, Stack},
+    runtime::FocusKind,
     style::BoxStyle,
 };
 
@@ -35,6 +36,11 @@ impl StackWidget {
         self
     }
 
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.inner.key = Some(key.into());
+        self
+    }
+
     pub fn child(mut self, child: impl IntoNode) -> Self {
         self.inner.children.push(child.into_node());
         self
@@ -48,20 +54,35 @@ impl IntoNode for StackWidget {
 }
 
 pub struct ContainerWidget {
+    key: Option<String>,
     style: BoxStyle,
     focus_id: Option<FocusId>,
+    focus_kind: FocusKind,
+    label: Option<String>,
+    tooltip: Option<String>,
+    hover_style: Option<BoxStyle>,
     child: Node,
 }
 
 impl ContainerWidget {
     pub fn new(child: impl IntoNode) -> Self {
         Self {
+            key: None,
             style: BoxStyle::default(),
             focus_id: None,
+            focus_kind: FocusKind::Generic,
+            label: None,
+            tooltip: None,
+            hover_style: None,
             child: child.into_node(),
         }
     }
 
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
     pub fn style(mut self, style: BoxStyle) -> Self {
         self.style = style;
         self
@@ -71,13 +92,43 @@ impl ContainerWidget {
         self.focus_id = Some(focus_id);
         self
     }
+
+    /// What the focus entry is, when not a generic box.
+    pub fn focus_kind(mut self, kind: FocusKind) -> Self {
+        self.focus_kind = kind;
+        self
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Extra text, like the full form of something shortened, shown after
+    /// focus rests on the box. Needs a focus id.
+    pub fn tooltip(mut self, tooltip: impl Into<String>) -> Self {
+        self.tooltip = Some(tooltip.into());
+        self
+    }
+
+    /// Laid over the style while the pointer is on the box, in the window
+    /// and in terminals that report motion. Needs a focus id.
+    pub fn hover_style(mut self, style: BoxStyle) -> Self {
+        self.hover_style = Some(style);
+        self
+    }
 }
 
 impl IntoNode for ContainerWidget {
     fn into_node(self) -> Node {
         Node::Container(Container {
+            key: self.key,
             style: self.style,
             focus_id: self.focus_id,
+            focus_kind: self.focus_kind,
+            label: self.label,
+            tooltip: self.tooltip,
+            hover_style: self.hover_style,
             child: Box::new(self.child),
         })
     }
@@ -91,7 +142,9 @@ impl ScrollViewWidget {
     pub fn new(child: impl IntoNode) -> Self {
         Self {
             inner: ScrollView {
+                key: None,
                 focus_id: None,
+                label: None,
                 viewport_lines: None,
                 offset_lines: 0,
                 child: Box::new(child.into_node()),
@@ -99,6 +152,11 @@ impl ScrollViewWidget {
         }
     }
 
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.inner.key = Some(key.into());
+        self
+    }
+
     pub fn viewport_lines(mut self, lines: u16) -> Self {
         self.inner.viewport_lines = Some(lines.max(1));
         self
@@ -113,6 +171,11 @@ impl ScrollViewWidget {
         self.inner.focus_id = Some(focus_id);
         self
     }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.inner.label = Some(label.into());
+        self
+    }
 }
 
 impl IntoNode for ScrollViewWidget {
@@ -121,6 +184,32 @@ impl IntoNode for ScrollViewWidget {
     }
 }
 
+pub struct OverlayWidget {
+    inner: Overlay,
+}
+
+impl OverlayWidget {
+    pub fn new(child: impl IntoNode) -> Self {
+        Self {
+            inner: Overlay {
+                key: None,
+                child: Box::new(child.into_node()),
+            },
+        }
+    }
+
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.inner.key = Some(key.into());
+        self
+    }
+}
+
+impl IntoNode for OverlayWidget {
+    fn into_node(self) -> Node {
+        Node::Overlay(self.inner)
+    }
+}
+
 pub fn row() -> StackWidget {
     StackWidget::row()
 }
@@ -136,3 +225,10 @@ pub fn container(child: impl IntoNode) -> ContainerWidget {
 pub fn scroll_view(child: impl IntoNode) -> ScrollViewWidget {
     ScrollViewWidget::new(child)
 }
+
+/// `child` centred over the whole window with the rest dimmed, wherever it
+/// sits in the tree. Focus is trapped inside it until a frame leaves it out,
+/// then goes back where it was.
+pub fn overlay(child: impl IntoNode) -> OverlayWidget {
+    OverlayWidget::new(child)
+}
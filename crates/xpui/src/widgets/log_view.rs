@@ -0,0 +1,76 @@
+use crate::{
+    runtime::{LogLevel, LogViewState},
+    style::TextStyle,
+    theme::theme,
+    widgets::{ScrollViewWidget, scroll_view, text},
+};
+
+/// A status line, then the lines of `state` on screen, each tagged and
+/// colored by level, with the search marked in them.
+pub fn log_view(state: &LogViewState) -> ScrollViewWidget {
+    let theme = theme();
+    let status_style = TextStyle::new().color(theme.muted);
+    let match_style = TextStyle::new().color(theme.badge_text).bg(theme.badge);
+
+    let (lines, count) = state.visible();
+    let mut status = vec![
+        if state.following() {
+            "following".to_string()
+        } else {
+            "held · f follows".to_string()
+        },
+        format!("{} and up · l changes", state.min_level().label()),
+    ];
+    if state.searching() {
+        status.push(format!("/{}▍", state.search()));
+    } else if !state.search().is_empty() {
+        status.push(format!("/{}", state.search()));
+    }
+    status.push(format!("{count} lines"));
+    let mut body = text("").run(format!("Log · {}", status.join(" · ")), status_style);
+
+    for line in lines {
+        let (tag, style) = match line.level {
+            LogLevel::Debug => ("D", TextStyle::new().color(theme.faint)),
+            LogLevel::Info => ("I", TextStyle::new().color(theme.text)),
+            LogLevel::Warn => ("W", TextStyle::new().color(theme.badge)),
+            LogLevel::Error => ("E", TextStyle::new().color(theme.removed).bold()),
+        };
+        body = body.run(format!("\n{tag} "), style.clone());
+        let mut rest = line.text.as_str();
+        if !state.search().is_empty() {
+            let search = state.search().to_lowercase();
+            while let Some(at) = find_ignoring_case(rest, &search) {
+                let end = at + matched_len(&rest[at..], &search);
+                body = body
+                    .run(&rest[..at], style.clone())
+                    .run(&rest[at..end], match_style.clone());
+                rest = &rest[end..];
+            }
+        }
+        body = body.run(rest, style);
+    }
+    scroll_view(body)
+        .viewport_lines(state.viewport_lines().saturating_add(1))
+        .label("Log")
+}
+
+/// The byte offset in `text` where the lowercase `search` starts, ignoring
+/// case.
+fn find_ignoring_case(text: &str, search: &str) -> Option<usize> {
+    text.char_indices()
+        .map(|(at, _)| at)
+        .find(|at| text[*at..].to_lowercase().starts_with(search))
+}
+
+/// The bytes of `text` that the lowercase `search` covers from its start.
+fn matched_len(text: &str, search: &str) -> usize {
+    let mut lowered = String::new();
+    for (at, ch) in text.char_indices() {
+        if lowered.len() >= search.len() {
+            return at;
+        }
+        lowered.extend(ch.to_lowercase());
+    }
+    text.len()
+}
@@ -0,0 +1,268 @@
+use crate::{node::TextRun, style::TextStyle, theme::theme, widgets::TextWidget};
+
+/// `source` as styled text: headings, emphasis, inline code, links, lists
+/// and quotes, with fenced code blocks kept as written. Each source line
+/// stays a line, so the text wraps where plain text would, only shorter by
+/// the markers it drops.
+pub fn markdown(source: &str) -> TextWidget {
+    let muted = TextStyle::new().color(theme().muted);
+    let mut out = TextWidget::new("");
+    let mut in_code = false;
+    for (index, line) in source.split('\n').enumerate() {
+        if index > 0 {
+            out = out.run("\n", TextStyle::default());
+        }
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            out = out.run(line, muted.clone());
+        } else if in_code {
+            out = out.run(line, TextStyle::default());
+        } else {
+            for run in markdown_runs(line) {
+                out = out.run(run.text, run.style);
+            }
+        }
+    }
+    out
+}
+
+/// One line of markdown outside a code block as styled runs, for apps that
+/// draw code blocks themselves. Runs with the default style are plain text.
+pub fn markdown_runs(line: &str) -> Vec<TextRun> {
+    let theme = theme();
+    let mut runs = Vec::new();
+    let body = line.trim_start();
+    let indent = &line[..line.len() - body.len()];
+    push(&mut runs, indent, &TextStyle::default());
+
+    let hashes = body.chars().take_while(|&ch| ch == '#').count();
+    if (1..=6).contains(&hashes) && body[hashes..].starts_with(' ') {
+        let title = body[hashes..].trim().trim_end_matches('#').trim_end();
+        let style = TextStyle::new().bold();
+        let style = if hashes <= 2 {
+            style.color(theme.accent)
+        } else {
+            style
+        };
+        inline(title, &style, &mut runs);
+        return runs;
+    }
+    let compact = body.replace(' ', "");
+    if compact.len() >= 3
+        && ['-', '*', '_']
+            .into_iter()
+            .any(|mark| compact.chars().all(|ch| ch == mark))
+    {
+        push(
+            &mut runs,
+            &"─".repeat(body.chars().count()),
+            &TextStyle::new().color(theme.faint),
+        );
+        return runs;
+    }
+    if let Some(quoted) = body.strip_prefix("> ").or(body.strip_prefix('>')) {
+        push(&mut runs, "│ ", &TextStyle::new().color(theme.muted));
+        inline(quoted, &TextStyle::new().italic(), &mut runs);
+        return runs;
+    }
+    let marker = TextStyle::new().color(theme.accent);
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| body.strip_prefix(bullet))
+    {
+        push(&mut runs, "• ", &marker);
+        inline(item, &TextStyle::default(), &mut runs);
+        return runs;
+    }
+    let digits = body.chars().take_while(char::is_ascii_digit).count();
+    if (1..=9).contains(&digits)
+        && (body[digits..].starts_with(". ") || body[digits..].starts_with(") "))
+    {
+        push(&mut runs, &body[..digits + 2], &marker);
+        inline(&body[digits + 2..], &TextStyle::default(), &mut runs);
+        return runs;
+    }
+    inline(body, &TextStyle::default(), &mut runs);
+    runs
+}
+
+/// Appends `text` in `style`, joining it to the last run when that has the
+/// same style.
+fn push(runs: &mut Vec<TextRun>, text: &str, style: &TextStyle) {
+    if text.is_empty() {
+        return;
+    }
+    match runs.last_mut() {
+        Some(last) if last.style == *style => last.text.push_str(text),
+        _ => runs.push(TextRun {
+            text: text.to_string(),
+            style: style.clone(),
+        }),
+    }
+}
+
+/// Parses the inline markup of `text` on top of `style`: code spans,
+/// `*`/`_` emphasis, links, autolinks and backslash escapes. Markup that
+/// does not close is kept as written.
+fn inline(text: &str, style: &TextStyle, runs: &mut Vec<TextRun>) {
+    let theme = theme();
+    let mut at = 0;
+    let mut plain = String::new();
+    while let Some(ch) = text[at..].chars().next() {
+        let rest = &text[at..];
+        let before = text[..at].chars().next_back();
+        let span = match ch {
+            '\\' => rest[1..]
+                .chars()
+                .next()
+                .filter(char::is_ascii_punctuation)
+                .map(|escaped| {
+                    plain.push(escaped);
+                    1 + escaped.len_utf8()
+                }),
+            '`' => Some(match code_span(rest) {
+                Some((code, len)) => {
+                    flush(runs, &mut plain, style);
+                    let code_style = style.clone().color(theme.accent).bg(theme.surface);
+                    push(runs, code, &code_style);
+                    len
+                }
+                None => literal_run(rest, &mut plain),
+            }),
+            '*' | '_' => Some(match emphasis(rest, before) {
+                Some((inner, marks, len)) => {
+                    flush(runs, &mut plain, style);
+                    let mut inner_style = style.clone();
+                    if marks != 2 {
+                        inner_style = inner_style.italic();
+                    }
+                    if marks >= 2 {
+                        inner_style = inner_style.bold();
+                    }
+                    inline(inner, &inner_style, runs);
+                    len
+                }
+                None => literal_run(rest, &mut plain),
+            }),
+            '[' | '!' => link(rest).map(|(label, url, len)| {
+                flush(runs, &mut plain, style);
+                inline(label, &style.clone().underline().link(url), runs);
+                len
+            }),
+            '<' => autolink(rest).map(|(url, len)| {
+                flush(runs, &mut plain, style);
+                push(runs, url, &style.clone().underline().link(url));
+                len
+            }),
+            _ => None,
+        };
+        match span {
+            Some(len) => at += len,
+            None => {
+                plain.push(ch);
+                at += ch.len_utf8();
+            }
+        }
+    }
+    flush(runs, &mut plain, style);
+}
+
+/// Keeps the run of marks `text` starts with as written, so an unclosed
+/// `**` is not read as an unclosed `*` and another one.
+fn literal_run(text: &str, plain: &mut String) -> usize {
+    let mark = text.chars().next().unwrap_or_default();
+    let len = text.chars().take_while(|&ch| ch == mark).count();
+    plain.push_str(&text[..len]);
+    len
+}
+
+fn flush(runs: &mut Vec<TextRun>, plain: &mut String, style: &TextStyle) {
+    push(runs, plain, style);
+    plain.clear();
+}
+
+/// A code span at the start of `text`: what is between a run of backticks
+/// and the next run of as many, and the bytes the whole span takes.
+fn code_span(text: &str) -> Option<(&str, usize)> {
+    let ticks = text.chars().take_while(|&ch| ch == '`').count();
+    let fence = &text[..ticks];
+    let mut from = ticks;
+    loop {
+        let close = from + text[from..].find(fence)?;
+        let run = text[close..].chars().take_while(|&ch| ch == '`').count();
+        if run == ticks {
+            let code = &text[ticks..close];
+            let code = match code
+                .strip_prefix(' ')
+                .and_then(|code| code.strip_suffix(' '))
+            {
+                Some(trimmed) if !trimmed.trim().is_empty() => trimmed,
+                _ => code,
+            };
+            return Some((code, close + ticks));
+        }
+        from = close + run;
+    }
+}
+
+/// Emphasis at the start of `text`, which `before` precedes: the text
+/// inside, how many marks open it, and the bytes the whole span takes.
+/// An `_` inside a word, as in `snake_case`, does not open or close it.
+fn emphasis(text: &str, before: Option<char>) -> Option<(&str, usize, usize)> {
+    let mark = text.chars().next()?;
+    let marks = text.chars().take_while(|&ch| ch == mark).count();
+    if marks > 3
+        || text[marks..].starts_with(char::is_whitespace)
+        || (mark == '_' && before.is_some_and(char::is_alphanumeric))
+    {
+        return None;
+    }
+    let delimiter = &text[..marks];
+    let mut from = marks;
+    loop {
+        let close = from + text[from..].find(delimiter)?;
+        let inner = &text[marks..close];
+        let after = text[close + marks..].chars().next();
+        let intraword = mark == '_' && after.is_some_and(char::is_alphanumeric);
+        let closes = !inner.is_empty()
+            && !inner.ends_with(char::is_whitespace)
+            && after != Some(mark)
+            && !intraword;
+        if closes {
+            return Some((inner, marks, close + marks));
+        }
+        from = close + text[close..].chars().take_while(|&ch| ch == mark).count();
+    }
+}
+
+/// A `[label](url)` link, or an `![alt](url)` image shown as its alt text,
+/// at the start of `text`, and the bytes it takes.
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let open = usize::from(text.starts_with('!'));
+    let rest = text[open..].strip_prefix('[')?;
+    let mut depth = 1;
+    let label_len = rest.find(|ch| {
+        match ch {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    })?;
+    let target = rest[label_len + 1..].strip_prefix('(')?;
+    let target_len = target.find(')')?;
+    let url = target[..target_len].split_whitespace().next()?;
+    let url = url.trim_start_matches('<').trim_end_matches('>');
+    let len = open + 1 + label_len + 2 + target_len + 1;
+    Some((&rest[..label_len], url, len))
+}
+
+/// An `<https://…>` autolink at the start of `text`: the URL and the bytes
+/// it takes.
+fn autolink(text: &str) -> Option<(&str, usize)> {
+    let end = text.find('>')?;
+    let url = &text[1..end];
+    let is_url = (url.starts_with("https://") || url.starts_with("http://"))
+        && !url.contains(char::is_whitespace);
+    is_url.then_some((url, end + 1))
+}
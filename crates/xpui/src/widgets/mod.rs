@@ -1,11 +1,28 @@
+mod button;
+mod context_menu;
 mod icon;
 mod layout;
+mod log_view;
+mod markdown;
+mod placeholder;
+mod scrollbar;
+mod select;
+mod select_list;
 mod text;
 mod text_input;
 
+pub use button::{ButtonWidget, button};
+pub use context_menu::context_menu;
 pub use icon::{IconWidget, icon};
 pub use layout::{
-    ContainerWidget, ScrollViewWidget, StackWidget, column, container, row, scroll_view,
+    ContainerWidget, OverlayWidget, ScrollViewWidget, StackWidget, column, container, overlay, row,
+    scroll_view,
 };
+pub use log_view::log_view;
+pub use markdown::{markdown, markdown_runs};
+pub use placeholder::{EmptyStateWidget, SkeletonWidget, empty_state, skeleton};
+pub use scrollbar::scrollbar;
+pub use select::{select, select_menu};
+pub use select_list::select_list;
 pub use text::{TextWidget, text};
 pub use text_input::{TextInputWidget, text_input, text_input_from_state};
@@ -0,0 +1,118 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    node::{IconName, IntoNode, Node},
+    style::TextStyle,
+    theme::theme,
+    widgets::{column, icon, row, text},
+};
+
+/// What a view shows when it has nothing to show: an optional icon, a
+/// message and a hint at how to fill it.
+pub struct EmptyStateWidget {
+    icon: Option<IconName>,
+    message: String,
+    hint: Option<String>,
+}
+
+impl EmptyStateWidget {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            icon: None,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn icon(mut self, name: IconName) -> Self {
+        self.icon = Some(name);
+        self
+    }
+
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl IntoNode for EmptyStateWidget {
+    fn into_node(self) -> Node {
+        let theme = theme();
+        let mut heading = row().gap(1);
+        if let Some(name) = self.icon {
+            heading = heading.child(icon(name).color(theme.muted));
+        }
+        heading = heading.child(text("").run(self.message, TextStyle::new().color(theme.text)));
+        let mut out = column().child(heading);
+        if let Some(hint) = self.hint {
+            out = out.child(text("").run(hint, TextStyle::new().color(theme.faint)));
+        }
+        out.into_node()
+    }
+}
+
+pub fn empty_state(message: impl Into<String>) -> EmptyStateWidget {
+    EmptyStateWidget::new(message)
+}
+
+/// Gray bars standing in for content that is still loading, with a lighter
+/// band sweeping across them while the app keeps rendering, unless
+/// [`Pacing::animations`](crate::Pacing::animations) is off.
+pub struct SkeletonWidget {
+    lines: u16,
+    width: u16,
+}
+
+impl SkeletonWidget {
+    pub fn new(lines: u16) -> Self {
+        Self { lines, width: 40 }
+    }
+
+    /// Columns of the longest bar.
+    pub fn width(mut self, width: u16) -> Self {
+        self.width = width.max(1);
+        self
+    }
+}
+
+impl IntoNode for SkeletonWidget {
+    fn into_node(self) -> Node {
+        // Ragged right edges read as text rather than a block.
+        const LENGTHS: [u16; 4] = [100, 82, 94, 61];
+        const BAND: i64 = 6;
+        let theme = theme();
+        let base = TextStyle::new().color(theme.border);
+        let lit = TextStyle::new().color(theme.faint);
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis());
+        let sweep = i64::from(self.width) + 2 * BAND;
+        let band_start = if crate::runtime::pacing().animations {
+            (millis / 60) as i64 % sweep - BAND
+        } else {
+            // Parked left of the bars, out of sight.
+            -BAND
+        };
+
+        let mut out = text("");
+        for line in 0..self.lines {
+            if line > 0 {
+                out = out.run("\n", TextStyle::default());
+            }
+            let percent = u32::from(LENGTHS[line as usize % LENGTHS.len()]);
+            let len = i64::from(u32::from(self.width) * percent / 100).max(1);
+            let lit_from = band_start.clamp(0, len);
+            let lit_to = (band_start + BAND).clamp(0, len);
+            let bar = |cols: i64| "▆".repeat(cols as usize);
+            out = out
+                .run(bar(lit_from), base.clone())
+                .run(bar(lit_to - lit_from), lit.clone())
+                .run(bar(len - lit_to), base.clone());
+        }
+        out.into_node()
+    }
+}
+
+pub fn skeleton(lines: u16) -> SkeletonWidget {
+    SkeletonWidget::new(lines)
+}
@@ -0,0 +1,24 @@
+use crate::{runtime::FocusListState, style::TextStyle, theme::theme, widgets::TextWidget};
+
+/// A column as tall as the viewport of `list`, with a thumb showing how
+/// much of it is in view and where. Blank while everything fits.
+pub fn scrollbar(list: &FocusListState) -> TextWidget {
+    let theme = theme();
+    let track = TextStyle::new().color(theme.faint);
+    let thumb_style = TextStyle::new().color(theme.muted);
+    let thumb = list.scrollbar_thumb();
+    let mut out = TextWidget::new("");
+    for row in 0..list.viewport_lines() {
+        if row > 0 {
+            out = out.run("\n", TextStyle::default());
+        }
+        out = match thumb {
+            None => out.run(" ", TextStyle::default()),
+            Some((start, len)) if (start..start + len).contains(&row) => {
+                out.run("┃", thumb_style.clone())
+            }
+            Some(_) => out.run("│", track.clone()),
+        };
+    }
+    out
+}
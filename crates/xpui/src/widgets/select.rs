@@ -0,0 +1,51 @@
+use crate::{
+    node::{FocusId, IntoNode},
+    runtime::{FocusKind, SelectState},
+    style::{BoxStyle, TextStyle},
+    theme::theme,
+    widgets::{ButtonWidget, ContainerWidget, button, container, text},
+};
+
+/// The select while closed: a button showing the selected item. Pressing it
+/// sends [`UiInputEvent::Activate`](crate::UiInputEvent::Activate) with
+/// `focus_id`, for the app to [`open`](SelectState::open) the list.
+pub fn select(state: &SelectState, focus_id: FocusId) -> ButtonWidget {
+    let label = state.selected_item().unwrap_or("—");
+    button(format!("{label} ▾"), focus_id).pressed(state.is_open())
+}
+
+/// The open list as a box: the filter typed so far, then the matching
+/// items with the highlighted one marked. The box is a dialog focus entry
+/// with `focus_id`, so it can be trapped while open.
+pub fn select_menu(state: &SelectState, focus_id: FocusId) -> ContainerWidget {
+    let theme = theme();
+    let filter_style = TextStyle::new().color(theme.muted);
+    let highlighted_style = TextStyle::new()
+        .color(theme.selection_text)
+        .bg(theme.selection)
+        .bold();
+
+    let filter = match state.filter() {
+        "" => " type to filter".to_string(),
+        filter => format!(" filter: {filter}"),
+    };
+    let mut body = text("").run(filter, filter_style.clone());
+    let matches = state.matches();
+    if matches.is_empty() {
+        body = body.run("\n no matches", filter_style);
+    }
+    for index in matches {
+        body = body.run("\n", TextStyle::default());
+        let row = format!(" {} ", state.items()[index]);
+        body = if state.highlighted() == Some(index) {
+            body.run(row, highlighted_style.clone())
+        } else {
+            body.run(row, TextStyle::default())
+        };
+    }
+    container(body.into_node())
+        .style(BoxStyle::default().bg(theme.surface))
+        .focus(focus_id)
+        .focus_kind(FocusKind::Dialog)
+        .label(state.selected_item().unwrap_or_default())
+}
@@ -0,0 +1,43 @@
+use crate::{
+    node::IntoNode,
+    runtime::{FocusKind, FocusState, SelectListState},
+    style::BoxStyle,
+    theme::theme,
+    widgets::{ContainerWidget, ScrollViewWidget, column, scroll_view},
+};
+
+/// A scrolling list with one focusable row per item. `row` draws item `index`
+/// and is told whether it has focus; the list adds the row's key, focus id,
+/// kind and hover style. Shows the state's empty state when there are no items.
+pub fn select_list<T>(
+    state: &SelectListState,
+    focus: &FocusState,
+    items: impl IntoIterator<Item = T>,
+    mut row: impl FnMut(u16, T, bool) -> ContainerWidget,
+) -> ScrollViewWidget {
+    let binding = state.binding();
+    let focused = state.focused_index(focus);
+    let mut list = column().gap(state.list().gap_lines() as u8);
+    let hover = BoxStyle::default().bg(theme().surface);
+    let mut empty = true;
+    for (index, item) in items.into_iter().enumerate() {
+        empty = false;
+        let index = index as u16;
+        list = list.child(
+            row(index, item, focused == Some(index))
+                .key(binding.item_key(index))
+                .focus(binding.focus_id(index))
+                .focus_kind(FocusKind::ListItem)
+                .hover_style(hover.clone()),
+        );
+    }
+    let body = if empty {
+        state.empty_state().into_node()
+    } else {
+        list.into_node()
+    };
+    scroll_view(body)
+        .focus(state.scroll_id())
+        .viewport_lines(state.list().viewport_lines())
+        .offset_lines(state.list().scroll_offset())
+}
@@ -21,6 +21,13 @@ impl TextWidget {
         });
         self
     }
+
+    /// Breaks lines between words, and inside a word only when it is longer
+    /// than a line.
+    pub fn word_wrap(mut self) -> Self {
+        self.inner.word_wrap = true;
+        self
+    }
 }
 
 impl IntoNode for TextWidget {
@@ -13,13 +13,21 @@ impl TextInputWidget {
         let cursor = value.chars().count();
         Self {
             inner: TextInput {
+                key: None,
                 focus_id: None,
+                label: None,
                 value,
                 placeholder: None,
                 cursor,
+                extra_cursors: Vec::new(),
+                block_selection: None,
+                ghost: None,
                 focused: false,
                 gutter_highlighted: false,
                 visible_offset_lines: 0,
+                visible_lines: None,
+                soft_wrap: true,
+                scroll_columns: 0,
             },
         }
     }
@@ -27,22 +35,41 @@ impl TextInputWidget {
     pub fn from_state(state: &TextInputState) -> Self {
         Self {
             inner: TextInput {
+                key: None,
                 focus_id: None,
+                label: None,
                 value: state.value().to_string(),
                 placeholder: None,
                 cursor: state.cursor(),
+                extra_cursors: state.extra_cursors().to_vec(),
+                block_selection: state.block_selection(),
+                ghost: None,
                 focused: false,
                 gutter_highlighted: false,
                 visible_offset_lines: 0,
+                visible_lines: None,
+                soft_wrap: state.wraps(),
+                scroll_columns: state.scroll_columns(),
             },
         }
     }
 
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.inner.key = Some(key.into());
+        self
+    }
+
     pub fn placeholder(mut self, placeholder: impl Into<String>) -> Self {
         self.inner.placeholder = Some(placeholder.into());
         self
     }
 
+    /// Dim text after the value while the cursor is at its end.
+    pub fn ghost(mut self, ghost: Option<impl Into<String>>) -> Self {
+        self.inner.ghost = ghost.map(Into::into);
+        self
+    }
+
     pub fn cursor(mut self, cursor: usize) -> Self {
         self.inner.cursor = cursor;
         self
@@ -63,10 +90,33 @@ impl TextInputWidget {
         self
     }
 
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.inner.label = Some(label.into());
+        self
+    }
+
     pub fn visible_offset_lines(mut self, lines: u16) -> Self {
         self.inner.visible_offset_lines = lines;
         self
     }
+
+    /// The rows the input is shown in, as the scroll view around it has;
+    /// rows cut off past them are counted in the gutter.
+    pub fn visible_lines(mut self, lines: u16) -> Self {
+        self.inner.visible_lines = Some(lines);
+        self
+    }
+
+    /// Wrap long lines (the default), or clip them after `scroll_columns`.
+    pub fn soft_wrap(mut self, wrap: bool) -> Self {
+        self.inner.soft_wrap = wrap;
+        self
+    }
+
+    pub fn scroll_columns(mut self, columns: usize) -> Self {
+        self.inner.scroll_columns = columns;
+        self
+    }
 }
 
 impl IntoNode for TextInputWidget {
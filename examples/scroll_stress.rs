@@ -0,0 +1,174 @@
+//! Scrolls, resizes and moves focus through a long synthetic session and
+//! reports how long each frame took, so slow scrolling can be measured and
+//! reproduced.
+//!
+//! ```sh
+//! cargo run --release --example scroll_stress -- --messages 10000 --budget-ms 16
+//! ```
+//!
+//! Frames are timed from the input event to the finished UI tree, without a
+//! backend drawing it; that part does not grow with the history. With
+//! `--budget-ms` the run fails when a phase's 95th percentile goes over it.
+//! The transcript is saved as a session that `--resume` opens in the app.
+
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use loopcode_ui::{ChatOptions, ChatPanel, provider, session};
+use xpui::{UiApp, UiInputEvent, UiKeyInput, WindowSize};
+
+#[derive(Parser, Debug)]
+struct Args {
+    #[arg(long, default_value_t = 10_000, help = "Messages in the transcript")]
+    messages: usize,
+    #[arg(long, default_value_t = 300, help = "Frames per phase")]
+    frames: usize,
+    #[arg(
+        long,
+        value_name = "MS",
+        help = "Fail when a phase's p95 frame time exceeds this"
+    )]
+    budget_ms: Option<f64>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let started = Instant::now();
+    let session = match session::start_with_transcript(transcript(args.messages / 2)) {
+        Ok(session) => session,
+        Err(err) => {
+            eprintln!("cannot write the transcript: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let options = ChatOptions {
+        resident_messages: args.messages,
+        ..ChatOptions::default()
+    };
+    let mut panel = ChatPanel::new(
+        Box::new(provider::EchoProvider::new()),
+        None,
+        Ok(session),
+        options,
+    );
+    println!("{} messages in {:?}", args.messages, started.elapsed());
+
+    panel.set_window_size(size(120, 40));
+    frame(&mut panel, None);
+    // Closes the what's-new screen if it came up, then leaves the input for
+    // the history list.
+    frame(&mut panel, Some(UiInputEvent::Key(UiKeyInput::Esc)));
+    frame(&mut panel, Some(UiInputEvent::Key(UiKeyInput::Esc)));
+
+    let n = args.frames;
+    let phases = [
+        Scenario("page up", |_| Phase::Key(UiKeyInput::PageUp)),
+        Scenario("line down", |_| Phase::Key(UiKeyInput::Down)),
+        Scenario("wheel", |i| Phase::Wheel(if i % 40 < 20 { -3 } else { 3 })),
+        Scenario("resize", |i| {
+            Phase::Resize(80 + (i % 7) as u16 * 12, 24 + (i % 5) as u16 * 6)
+        }),
+        Scenario("focus", |_| Phase::Key(UiKeyInput::Tab)),
+    ];
+
+    let mut over_budget = false;
+    println!(
+        "{:<10} {:>8} {:>8} {:>8}",
+        "phase", "p50 ms", "p95 ms", "max ms"
+    );
+    for Scenario(name, step) in phases {
+        let mut times = (0..n)
+            .map(|i| match step(i) {
+                Phase::Key(key) => frame(&mut panel, Some(UiInputEvent::Key(key))),
+                Phase::Wheel(lines) => frame(&mut panel, Some(UiInputEvent::ScrollLines(lines))),
+                Phase::Resize(width, height) => {
+                    panel.set_window_size(size(width, height));
+                    frame(&mut panel, None)
+                }
+            })
+            .collect::<Vec<_>>();
+        times.sort();
+        let ms = |time: Duration| time.as_secs_f64() * 1000.0;
+        let p95 = ms(times[(times.len() * 95 / 100).min(times.len() - 1)]);
+        println!(
+            "{name:<10} {:>8.2} {p95:>8.2} {:>8.2}",
+            ms(times[times.len() / 2]),
+            ms(times[times.len() - 1]),
+        );
+        over_budget |= args.budget_ms.is_some_and(|budget| p95 > budget);
+    }
+
+    if let Some(id) = panel.session_id().borrow().as_ref() {
+        println!(
+            "reproduce with: cargo run --release -- --resume {}",
+            id.as_str()
+        );
+    }
+    if over_budget {
+        eprintln!(
+            "p95 frame time over the {}ms budget",
+            args.budget_ms.unwrap_or_default()
+        );
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// A named phase and the input for each of its frames.
+struct Scenario(&'static str, fn(usize) -> Phase);
+
+enum Phase {
+    Key(UiKeyInput),
+    Wheel(i16),
+    Resize(u16, u16),
+}
+
+fn size(width: u16, height: u16) -> WindowSize {
+    WindowSize {
+        width: f32::from(width),
+        height: f32::from(height),
+    }
+}
+
+/// Handles `event`, if any, and builds the next frame the way a backend
+/// would, returning how long that took.
+fn frame(panel: &mut ChatPanel, event: Option<UiInputEvent>) -> Duration {
+    let started = Instant::now();
+    if let Some(event) = event {
+        panel.on_input(event);
+    }
+    let node = panel.render();
+    let mut entries = Vec::new();
+    node.collect_focus_entries(&mut entries);
+    if let Some(focus) = panel.focus_state() {
+        focus.ensure_valid(&entries);
+    }
+    panel.on_focus_entries(&entries);
+    started.elapsed()
+}
+
+/// `exchanges` prompt and reply pairs of varied length, some with code, the
+/// same on every run.
+fn transcript(exchanges: usize) -> impl Iterator<Item = (String, String)> {
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    (0..exchanges).map(move |index| {
+        // xorshift: deterministic without a dependency.
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let lines = 1 + (seed % 24) as usize;
+        let prompt = format!("Question {index}: why does the list jump when it scrolls?");
+        let mut reply = String::new();
+        for line in 0..lines {
+            reply.push_str(&format!(
+                "Line {line} of answer {index}, long enough to wrap on a narrow terminal \
+                 and short enough not to on a wide one.\n"
+            ));
+        }
+        if seed.is_multiple_of(3) {
+            reply.push_str("```rust\nfn main() {\n    let answer = 42;\n}\n```\n");
+        }
+        (prompt, reply.trim_end().to_string())
+    })
+}
@@ -0,0 +1,320 @@
+use std::env;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::provider::{ChatProvider, CompletionRequest, RequestMessage, RequestRole, StreamEvent};
+use crate::session;
+use crate::terminal;
+
+/// How long the provider gets to answer the test prompt.
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    /// Works, but something will look or behave worse than it could.
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Warn => "warn",
+            Self::Fail => "FAIL",
+        }
+    }
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    fix: Option<&'static str>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn fix(mut self, fix: &'static str) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+/// Runs `loopcode doctor`: checks the environment, prints one line per check
+/// with a fix for anything that is off, and returns the exit code.
+pub(crate) fn run(provider: Result<Box<dyn ChatProvider>, String>) -> i32 {
+    let probe = xpui::probe_terminal();
+    let capabilities = xpui::capabilities();
+    let checks = [
+        terminal(&probe),
+        color(capabilities),
+        keyboard(&probe),
+        sync(&probe),
+        mouse(),
+        images(capabilities),
+        power(),
+        config(),
+        storage(),
+        provider_check(provider),
+        git(),
+        keychain(),
+    ];
+
+    println!("loopcode doctor");
+    for check in &checks {
+        println!(
+            "  {:<4}  {:<10} {}",
+            check.status.label(),
+            check.name,
+            check.detail
+        );
+        if let Some(fix) = check.fix {
+            println!("{:18}fix: {fix}", "");
+        }
+    }
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == Status::Fail)
+        .count();
+    if failed > 0 {
+        println!("{failed} check(s) failed");
+        1
+    } else {
+        0
+    }
+}
+
+fn var(name: &str) -> String {
+    env::var(name).unwrap_or_default()
+}
+
+fn terminal(probe: &xpui::TerminalProbe) -> Check {
+    let term = var("TERM");
+    if !probe.interactive {
+        return Check::new(
+            "terminal",
+            Status::Fail,
+            "stdin or stdout is not a terminal",
+        )
+        .fix("run loopcode directly in a terminal, not through a pipe");
+    }
+    if term.is_empty() || term == "dumb" {
+        return Check::new("terminal", Status::Fail, format!("TERM is {term:?}"))
+            .fix("export TERM=xterm-256color");
+    }
+    let size = probe
+        .size
+        .map_or("size unknown".to_string(), |(cols, rows)| {
+            format!("{cols}x{rows}")
+        });
+    Check::new("terminal", Status::Ok, format!("{term}, {size}"))
+}
+
+/// Checks the depth the renderer will use, which `COLORTERM` usually agrees
+/// with when it is right.
+fn color(capabilities: &xpui::Capabilities) -> Check {
+    let colorterm = var("COLORTERM");
+    let announced = matches!(colorterm.as_str(), "truecolor" | "24bit");
+    match capabilities.color {
+        xpui::ColorDepth::TrueColor if announced => Check::new(
+            "color",
+            Status::Ok,
+            format!("24-bit, COLORTERM={colorterm}"),
+        ),
+        xpui::ColorDepth::TrueColor => Check::new(
+            "color",
+            Status::Warn,
+            "24-bit assumed but COLORTERM does not announce it; colors may render wrong",
+        )
+        .fix("set \"color\": \"ansi256\" for this TERM in terminals.json if they do"),
+        depth => Check::new(
+            "color",
+            Status::Warn,
+            format!("{depth:?}; the theme is approximated"),
+        )
+        .fix("set \"color\": \"truecolor\" for this TERM in terminals.json if it can do better"),
+    }
+}
+
+fn keyboard(probe: &xpui::TerminalProbe) -> Check {
+    match probe.keyboard_enhancement {
+        Some(true) => Check::new("keyboard", Status::Ok, "kitty keyboard protocol"),
+        Some(false) => Check::new(
+            "keyboard",
+            Status::Warn,
+            "no kitty keyboard protocol; Shift+Enter and some Ctrl keys are indistinguishable",
+        )
+        .fix("use kitty, WezTerm, foot, Ghostty or iTerm2 3.5+, or enable CSI u in tmux"),
+        None => Check::new(
+            "keyboard",
+            Status::Warn,
+            "the terminal did not answer the query",
+        ),
+    }
+}
+
+fn sync(probe: &xpui::TerminalProbe) -> Check {
+    match probe.synchronized_updates {
+        Some(true) => Check::new("sync", Status::Ok, "synchronized updates (mode 2026)"),
+        Some(false) => Check::new(
+            "sync",
+            Status::Warn,
+            "no synchronized updates; large redraws may flicker",
+        ),
+        None => Check::new(
+            "sync",
+            Status::Warn,
+            "the terminal did not answer the query",
+        ),
+    }
+}
+
+fn mouse() -> Check {
+    if var("TERM") == "linux" {
+        return Check::new("mouse", Status::Warn, "the Linux console reports no mouse")
+            .fix("run loopcode in a terminal emulator");
+    }
+    if !var("TMUX").is_empty() {
+        return Check::new(
+            "mouse",
+            Status::Ok,
+            "inside tmux; clicks and scrolling need its mouse mode",
+        )
+        .fix("add `set -g mouse on` to ~/.tmux.conf if scrolling moves tmux instead");
+    }
+    Check::new("mouse", Status::Ok, "SGR mouse reporting")
+}
+
+fn power() -> Check {
+    if crate::power::on_battery() {
+        return Check::new(
+            "power",
+            Status::Ok,
+            "on battery, so low-power mode is on: replies update once a second",
+        )
+        .fix("pass --poll-ms 250 to keep the normal pace");
+    }
+    Check::new("power", Status::Ok, "not on battery")
+}
+
+fn images(capabilities: &xpui::Capabilities) -> Check {
+    match capabilities.images {
+        Some(xpui::ImageProtocol::Kitty) => Check::new("images", Status::Ok, "kitty graphics"),
+        Some(xpui::ImageProtocol::Iterm2) => {
+            Check::new("images", Status::Ok, "iTerm2 inline images")
+        }
+        None => Check::new(
+            "images",
+            Status::Warn,
+            "no image protocol detected; images show as placeholders",
+        ),
+    }
+}
+
+fn config() -> Check {
+    let path = terminal::overrides_path().map_or("no config directory".to_string(), |path| {
+        path.display().to_string()
+    });
+    match terminal::with_overrides(xpui::Capabilities::detect()) {
+        Ok(_) => Check::new("config", Status::Ok, path),
+        Err(err) => {
+            let cause =
+                std::error::Error::source(&err).map_or(String::new(), |cause| format!(": {cause}"));
+            Check::new("config", Status::Fail, format!("{err}{cause}"))
+                .fix("fix or remove the file; detected capabilities are used meanwhile")
+        }
+    }
+}
+
+fn storage() -> Check {
+    match session::check_storage() {
+        Ok(dir) => Check::new("storage", Status::Ok, dir.display().to_string()),
+        Err(err) => Check::new("storage", Status::Fail, err.to_string())
+            .fix("make the data directory writable, or sessions will not be saved"),
+    }
+}
+
+/// Sends one short prompt through the configured provider and waits for the
+/// reply to finish.
+fn provider_check(provider: Result<Box<dyn ChatProvider>, String>) -> Check {
+    let provider = match provider {
+        Ok(provider) => provider,
+        Err(err) => {
+            return Check::new("provider", Status::Fail, err)
+                .fix("check the --provider and --replay flags");
+        }
+    };
+    // A model every real provider serves, as the chat starts with.
+    let request = CompletionRequest {
+        model: "OpenRouter GPT-4.1".to_string(),
+        messages: vec![RequestMessage {
+            role: RequestRole::User,
+            content: "ping".to_string(),
+            images: Vec::new(),
+            tool_call: None,
+        }],
+        tools: Vec::new(),
+    };
+    let started = Instant::now();
+    let stream = match provider.stream_completion(request) {
+        Ok(stream) => stream,
+        Err(err) => return Check::new("provider", Status::Fail, err.to_string()),
+    };
+    while started.elapsed() < PROVIDER_TIMEOUT {
+        match stream.try_next() {
+            Some(StreamEvent::Done) => {
+                let ms = started.elapsed().as_millis();
+                return Check::new("provider", Status::Ok, format!("answered in {ms}ms"));
+            }
+            Some(StreamEvent::Failed(err)) => {
+                return Check::new("provider", Status::Fail, err.to_string());
+            }
+            Some(_) => {}
+            None => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+    Check::new(
+        "provider",
+        Status::Fail,
+        format!("no answer within {}s", PROVIDER_TIMEOUT.as_secs()),
+    )
+}
+
+fn git() -> Check {
+    match Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => Check::new(
+            "git",
+            Status::Ok,
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ),
+        _ => Check::new("git", Status::Warn, "git is not on PATH")
+            .fix("install git to use the git-aware commands"),
+    }
+}
+
+/// Nothing stores credentials yet; this only checks that the platform
+/// keychain tool is installed for when a provider does.
+fn keychain() -> Check {
+    let (tool, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("security", &["list-keychains"])
+    } else if cfg!(target_os = "windows") {
+        ("cmdkey", &["/list"])
+    } else {
+        ("secret-tool", &["--help"])
+    };
+    match Command::new(tool).args(args).output() {
+        Ok(_) => Check::new("keychain", Status::Ok, format!("{tool} is available")),
+        Err(_) => Check::new("keychain", Status::Warn, format!("{tool} is not on PATH"))
+            .fix("install your platform's secret service; API keys will need the environment"),
+    }
+}
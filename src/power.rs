@@ -0,0 +1,25 @@
+use std::fs;
+use std::process::Command;
+
+/// Whether the machine runs on battery right now: a Linux power supply
+/// reporting `Discharging`, or `pmset` naming battery power on macOS. `false`
+/// wherever that cannot be told, so desktops and unknown systems keep the
+/// normal pace.
+pub(crate) fn on_battery() -> bool {
+    if cfg!(target_os = "macos") {
+        return Command::new("pmset")
+            .args(["-g", "batt"])
+            .output()
+            .is_ok_and(|output| {
+                String::from_utf8_lossy(&output.stdout).contains("'Battery Power'")
+            });
+    }
+    let Ok(supplies) = fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    supplies.flatten().any(|supply| {
+        let path = supply.path();
+        let read = |name: &str| fs::read_to_string(path.join(name)).unwrap_or_default();
+        read("type").trim() == "Battery" && read("status").trim() == "Discharging"
+    })
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use thiserror::Error;
+use xpui::{Capabilities, ColorDepth, ImageProtocol};
+
+/// Per-`TERM` corrections to the detected capabilities, read from
+/// `terminals.json` in the config directory:
+///
+/// ```json
+/// { "xterm-256color": { "color": "ansi256", "hyperlinks": false } }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Override {
+    color: Option<Color>,
+    synchronized_updates: Option<bool>,
+    kitty_keyboard: Option<bool>,
+    ctrl_enter: Option<bool>,
+    images: Option<Images>,
+    hyperlinks: Option<bool>,
+    vscode: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Color {
+    Truecolor,
+    Ansi256,
+    Ansi16,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Images {
+    None,
+    Kitty,
+    Iterm2,
+}
+
+impl Override {
+    fn apply(self, capabilities: &mut Capabilities) {
+        if let Some(color) = self.color {
+            capabilities.color = match color {
+                Color::Truecolor => ColorDepth::TrueColor,
+                Color::Ansi256 => ColorDepth::Ansi256,
+                Color::Ansi16 => ColorDepth::Ansi16,
+            };
+        }
+        if let Some(images) = self.images {
+            capabilities.images = match images {
+                Images::None => None,
+                Images::Kitty => Some(ImageProtocol::Kitty),
+                Images::Iterm2 => Some(ImageProtocol::Iterm2),
+            };
+        }
+        let flags = [
+            (
+                self.synchronized_updates,
+                &mut capabilities.synchronized_updates,
+            ),
+            (self.kitty_keyboard, &mut capabilities.kitty_keyboard),
+            (self.ctrl_enter, &mut capabilities.ctrl_enter),
+            (self.hyperlinks, &mut capabilities.hyperlinks),
+            (self.vscode, &mut capabilities.vscode),
+        ];
+        for (value, flag) in flags {
+            if let Some(value) = value {
+                *flag = value;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum OverridesError {
+    #[error("cannot read {}", .0.display())]
+    Read(PathBuf, #[source] io::Error),
+    #[error("invalid terminal overrides in {}", .0.display())]
+    Parse(PathBuf, #[source] serde_json::Error),
+}
+
+/// What detection would have set inside VS Code, for when `TERM_PROGRAM`
+/// does not make it through (SSH, tmux, containers).
+pub(crate) fn force_vscode(capabilities: &mut Capabilities) {
+    capabilities.vscode = true;
+    capabilities.ctrl_enter = false;
+    capabilities.kitty_keyboard = false;
+}
+
+pub(crate) fn overrides_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("loopcode").join("terminals.json"))
+}
+
+/// `detected` with the user's override for its `TERM` applied, if any. A
+/// missing file is not an error.
+pub(crate) fn with_overrides(mut detected: Capabilities) -> Result<Capabilities, OverridesError> {
+    let Some(path) = overrides_path() else {
+        return Ok(detected);
+    };
+    let json = match fs::read(&path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(detected),
+        Err(err) => return Err(OverridesError::Read(path, err)),
+    };
+    let mut overrides: HashMap<String, Override> =
+        serde_json::from_slice(&json).map_err(|err| OverridesError::Parse(path, err))?;
+    if let Some(entry) = overrides.remove(&detected.term) {
+        entry.apply(&mut detected);
+    }
+    Ok(detected)
+}
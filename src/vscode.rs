@@ -0,0 +1,60 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json::json;
+
+const TASKS_PATH: &str = ".vscode/tasks.json";
+
+/// Runs `loopcode vscode-task`: writes a task that opens loopcode in a
+/// dedicated terminal of the current workspace. An existing `tasks.json` is
+/// never rewritten, since it may hold comments serde cannot keep; the task is
+/// printed for merging instead. Returns the exit code.
+pub(crate) fn install_task() -> i32 {
+    let task = task();
+    let path = Path::new(TASKS_PATH);
+    if path.exists() {
+        println!("{TASKS_PATH} already exists; add this entry to its \"tasks\" array:");
+        println!("{}", pretty(&task));
+        return 0;
+    }
+    let tasks = json!({ "version": "2.0.0", "tasks": [task] });
+    match write(path, &pretty(&tasks)) {
+        Ok(()) => {
+            println!("wrote {TASKS_PATH}; run it with \"Tasks: Run Task\" → loopcode");
+            0
+        }
+        Err(err) => {
+            eprintln!("loopcode: cannot write {TASKS_PATH}: {err}");
+            1
+        }
+    }
+}
+
+fn task() -> serde_json::Value {
+    // The binary that ran this command, so the task works without it on PATH.
+    let command = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_string))
+        .unwrap_or_else(|| "loopcode".to_string());
+    json!({
+        "label": "loopcode",
+        "type": "process",
+        "command": command,
+        "args": ["--vscode"],
+        "options": { "cwd": "${workspaceFolder}" },
+        "problemMatcher": [],
+        "presentation": { "panel": "dedicated", "focus": true, "reveal": "always" }
+    })
+}
+
+fn pretty(value: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+fn write(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, format!("{contents}\n"))
+}